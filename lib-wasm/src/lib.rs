@@ -0,0 +1,58 @@
+//! WebAssembly bindings for decoding public commitments in the browser
+//!
+//! Takes public values as hex, the way explorers show them, with or without a
+//! `0x` prefix. Decoded commitments come back in the JSON layout of
+//! [`solana_stub_prover_lib::json`]: hex byte arrays, and lamport amounts and
+//! rent epochs as decimal strings.
+//! Build the npm package with `wasm-pack build lib-wasm`.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use solana_stub_prover_lib::json::commitments_json;
+use solana_stub_prover_lib::PublicCommitments;
+use wasm_bindgen::prelude::*;
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    hex::decode(hex).map_err(|e| format!("Public values are not hex: {}", e))
+}
+
+fn decode(hex: &str) -> Result<PublicCommitments, String> {
+    let bytes = decode_hex(hex)?;
+    bincode::deserialize(&bytes).map_err(|e| format!("Public values are not PublicCommitments: {}", e))
+}
+
+/// Whether `next` continues the chain where `prev` ends
+fn continues(prev: &PublicCommitments, next: &PublicCommitments) -> bool {
+    next.start_slot == prev.end_slot && next.original_bank_hash == prev.last_bank_hash
+}
+
+/// Decode bincode public values into a plain object
+#[wasm_bindgen]
+pub fn decode_commitments(hex: &str) -> Result<JsValue, JsError> {
+    let commitments = decode(hex).map_err(|e| JsError::new(&e))?;
+    commitments_json(&commitments)
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// SHA-256 of the public values, as hex
+///
+/// The digest the consumer's duplicate detection compares.
+#[wasm_bindgen]
+pub fn commitment_digest(hex: &str) -> Result<String, JsError> {
+    let bytes = decode_hex(hex).map_err(|e| JsError::new(&e))?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+/// Whether the proof with public values `next_hex` starts where `prev_hex` ends
+///
+/// The start slot must be the previous end slot and the original bank hash the
+/// previous last bank hash.
+#[wasm_bindgen]
+pub fn verify_chain_link(prev_hex: &str, next_hex: &str) -> Result<bool, JsError> {
+    let prev = decode(prev_hex).map_err(|e| JsError::new(&e))?;
+    let next = decode(next_hex).map_err(|e| JsError::new(&e))?;
+    Ok(continues(&prev, &next))
+}
@@ -0,0 +1,18 @@
+fn main() {
+    // Keep the C header in step with src/ffi.rs; generated into OUT_DIR, never into the source tree
+    #[cfg(feature = "ffi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+        let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let config_path = format!("{}/cbindgen.toml", crate_dir);
+        let config = cbindgen::Config::from_file(config_path).expect("Invalid cbindgen.toml");
+        cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("Failed to generate the C header")
+            .write_to_file(format!("{}/solana_stub_prover.h", out_dir));
+    }
+}
@@ -0,0 +1,316 @@
+//! Pinned encodings of the committed statement
+//!
+//! Verifiers deployed for a statement version read the bincode layout of
+//! [`PublicCommitments`] and [`ProverInput`] as it was when they were
+//! deployed, which depends on the field order, the serde derives and
+//! bincode's fixed-width little-endian defaults. `tests/fixtures` holds the
+//! encoding of a sample statement for every [`STATEMENT_VERSION`], one field
+//! per line. The current types must encode the samples to the current
+//! version's bytes exactly and decode them back; older layouts are kept as
+//! types of their own here, so their fixtures stay checked as well. Adding,
+//! removing or reordering a field fails these tests until the version is
+//! bumped and its fixtures are added.
+
+use crate::{AccountStateCommitment, ProverInput, PublicCommitments, PublicCommitmentsRef, STATEMENT_VERSION};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+/// Fixture text of one version
+struct Fixture {
+    version: u32,
+    commitments: &'static str,
+    input: &'static str,
+}
+
+/// Fixtures of every statement version, oldest first
+const FIXTURES: &[Fixture] = &[
+    Fixture {
+        version: 1,
+        commitments: include_str!("../tests/fixtures/statement-v1-commitments.hex"),
+        input: include_str!("../tests/fixtures/statement-v1-input.hex"),
+    },
+    Fixture {
+        version: 2,
+        commitments: include_str!("../tests/fixtures/statement-v2-commitments.hex"),
+        input: include_str!("../tests/fixtures/statement-v2-input.hex"),
+    },
+    Fixture {
+        version: 3,
+        commitments: include_str!("../tests/fixtures/statement-v3-commitments.hex"),
+        input: include_str!("../tests/fixtures/statement-v3-input.hex"),
+    },
+    Fixture {
+        version: 4,
+        commitments: include_str!("../tests/fixtures/statement-v4-commitments.hex"),
+        input: include_str!("../tests/fixtures/statement-v4-input.hex"),
+    },
+];
+
+/// Bytes of a fixture, its hex with the `#` comments removed
+fn bytes(text: &str) -> Vec<u8> {
+    let hex: String = text.lines().map(|line| line.split('#').next().unwrap_or_default().trim()).collect();
+    hex::decode(hex).expect("fixtures are hex")
+}
+
+fn fixture(version: u32) -> &'static Fixture {
+    FIXTURES.iter().find(|fixture| fixture.version == version).expect("every statement version has a fixture")
+}
+
+/// `value` encodes to `expected` byte for byte, and `expected` decodes back to `value`
+fn assert_pinned<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T, expected: &[u8]) {
+    assert_eq!(hex::encode(bincode::serialize(value).unwrap()), hex::encode(expected));
+    assert_eq!(&bincode::deserialize::<T>(expected).unwrap(), value);
+}
+
+/// The account in the samples; the values are arbitrary but tell every field apart
+fn sample_account() -> AccountStateCommitment {
+    AccountStateCommitment {
+        account_pubkey: [0x44; 32],
+        last_change_slot: 1_000_050,
+        account_data_hash: [0x55; 32],
+        lamports: 1_141_440,
+        owner: [0x66; 32],
+        executable: true,
+        rent_epoch: u64::MAX,
+        data_len: 4,
+        data: b"stub".to_vec(),
+    }
+}
+
+fn sample_commitments() -> PublicCommitments {
+    PublicCommitments {
+        start_slot: 1_000_000,
+        end_slot: 1_000_100,
+        epoch: 2,
+        original_bank_hash: [0x11; 32],
+        last_bank_hash: [0x22; 32],
+        account_data_hash: [0x33; 32],
+        hash_root_valset: [0; 32],
+        total_active_stake: 1_000_000_000,
+        validator_count: 100,
+        monitored_accounts_state: vec![sample_account()],
+        validations_passed: true,
+        aggregated_vkey_digest: [0x88; 32],
+    }
+}
+
+fn sample_input() -> ProverInput {
+    ProverInput {
+        start_slot: 1_000_000,
+        end_slot: 1_000_100,
+        epoch: 2,
+        original_bank_hash: [0x11; 32],
+        last_bank_hash: [0x22; 32],
+        monitored_accounts_state: vec![sample_account()],
+    }
+}
+
+/// Version 1's layout, before accounts committed their `data_len`
+mod v1 {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct AccountStateCommitment {
+        pub account_pubkey: [u8; 32],
+        pub last_change_slot: u64,
+        pub account_data_hash: [u8; 32],
+        pub lamports: u64,
+        pub owner: [u8; 32],
+        pub executable: bool,
+        pub rent_epoch: u64,
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct PublicCommitments {
+        pub start_slot: u64,
+        pub end_slot: u64,
+        pub epoch: u64,
+        pub original_bank_hash: [u8; 32],
+        pub last_bank_hash: [u8; 32],
+        pub account_data_hash: [u8; 32],
+        pub hash_root_valset: [u8; 32],
+        pub total_active_stake: u64,
+        pub validator_count: u32,
+        pub monitored_accounts_state: Vec<AccountStateCommitment>,
+        pub validations_passed: bool,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct ProverInput {
+        pub start_slot: u64,
+        pub end_slot: u64,
+        pub epoch: u64,
+        pub original_bank_hash: [u8; 32],
+        pub last_bank_hash: [u8; 32],
+        pub monitored_accounts_state: Vec<AccountStateCommitment>,
+    }
+
+    pub fn account(account: super::AccountStateCommitment) -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: account.account_pubkey,
+            last_change_slot: account.last_change_slot,
+            account_data_hash: account.account_data_hash,
+            lamports: account.lamports,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: account.data,
+        }
+    }
+}
+
+/// Versions 2 and 3's layout, before commitments named the key of the proofs they aggregate
+mod v2 {
+    use crate::AccountStateCommitment;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    pub struct PublicCommitments {
+        pub start_slot: u64,
+        pub end_slot: u64,
+        pub epoch: u64,
+        pub original_bank_hash: [u8; 32],
+        pub last_bank_hash: [u8; 32],
+        pub account_data_hash: [u8; 32],
+        pub hash_root_valset: [u8; 32],
+        pub total_active_stake: u64,
+        pub validator_count: u32,
+        pub monitored_accounts_state: Vec<AccountStateCommitment>,
+        pub validations_passed: bool,
+    }
+
+    pub fn commitments(commitments: super::PublicCommitments) -> PublicCommitments {
+        PublicCommitments {
+            start_slot: commitments.start_slot,
+            end_slot: commitments.end_slot,
+            epoch: commitments.epoch,
+            original_bank_hash: commitments.original_bank_hash,
+            last_bank_hash: commitments.last_bank_hash,
+            account_data_hash: commitments.account_data_hash,
+            hash_root_valset: commitments.hash_root_valset,
+            total_active_stake: commitments.total_active_stake,
+            validator_count: commitments.validator_count,
+            monitored_accounts_state: commitments.monitored_accounts_state,
+            validations_passed: commitments.validations_passed,
+        }
+    }
+}
+
+#[test]
+fn every_version_has_fixtures() {
+    let versions: Vec<u32> = FIXTURES.iter().map(|fixture| fixture.version).collect();
+    assert_eq!(versions, (1..=STATEMENT_VERSION).collect::<Vec<_>>());
+}
+
+#[test]
+fn current_version_is_pinned() {
+    let current = fixture(STATEMENT_VERSION);
+    assert_pinned(&sample_commitments(), &bytes(current.commitments));
+    assert_pinned(&sample_input(), &bytes(current.input));
+}
+
+#[test]
+fn view_has_the_same_layout() {
+    let encoded = bytes(fixture(STATEMENT_VERSION).commitments);
+    let view: PublicCommitmentsRef = bincode::deserialize(&encoded).unwrap();
+    assert_eq!(bincode::serialize(&view).unwrap(), encoded);
+    assert_eq!(view.into_owned(), sample_commitments());
+}
+
+#[test]
+fn version_1_is_pinned() {
+    let v1 = fixture(1);
+    let commitments = sample_commitments();
+    let accounts = || commitments.monitored_accounts_state.iter().cloned().map(v1::account).collect();
+    let expected = v1::PublicCommitments {
+        start_slot: commitments.start_slot,
+        end_slot: commitments.end_slot,
+        epoch: commitments.epoch,
+        original_bank_hash: commitments.original_bank_hash,
+        last_bank_hash: commitments.last_bank_hash,
+        account_data_hash: commitments.account_data_hash,
+        hash_root_valset: commitments.hash_root_valset,
+        total_active_stake: commitments.total_active_stake,
+        validator_count: commitments.validator_count,
+        monitored_accounts_state: accounts(),
+        validations_passed: commitments.validations_passed,
+    };
+    assert_pinned(&expected, &bytes(v1.commitments));
+    let input = sample_input();
+    let expected = v1::ProverInput {
+        start_slot: input.start_slot,
+        end_slot: input.end_slot,
+        epoch: input.epoch,
+        original_bank_hash: input.original_bank_hash,
+        last_bank_hash: input.last_bank_hash,
+        monitored_accounts_state: accounts(),
+    };
+    assert_pinned(&expected, &bytes(v1.input));
+}
+
+#[test]
+fn versions_2_and_3_are_pinned() {
+    // Version 3 only changed the hashing, so both have version 2's layout; the input's is today's
+    for version in [2, 3] {
+        let fixture = fixture(version);
+        assert_pinned(&v2::commitments(sample_commitments()), &bytes(fixture.commitments));
+        assert_pinned(&sample_input(), &bytes(fixture.input));
+    }
+}
+
+#[test]
+fn version_3_commitments_are_not_current() {
+    assert!(bincode::deserialize::<PublicCommitments>(&bytes(fixture(3).commitments)).is_err());
+}
+
+#[cfg(feature = "hash")]
+mod hashes {
+    use super::*;
+    use crate::{compute_accounts_hash, hash_with_domain, stub_bank_hash, ACCOUNTS_DOMAIN, BANK_HASH_DOMAIN};
+
+    #[test]
+    fn accounts_hash_of_the_samples() {
+        assert_eq!(
+            hex::encode(compute_accounts_hash(&sample_commitments().monitored_accounts_state)),
+            "97dbc7564673082224ad270ae287a35ad3223090cf3e5cdc552dfeb7d357ce92"
+        );
+    }
+
+    #[test]
+    fn bank_hashes_of_the_samples() {
+        let commitments = sample_commitments();
+        assert_eq!(
+            hex::encode(stub_bank_hash(commitments.start_slot)),
+            "1d94a2cf9f4fd42c2023e378dfa3fafb87e0db9d4ca80788f83524b9296d1aff"
+        );
+        assert_eq!(
+            hex::encode(stub_bank_hash(commitments.end_slot)),
+            "e9dd75397018cd1459d8120318cb2b7fb66c8571aff41eedfc907cc988852970"
+        );
+    }
+
+    #[test]
+    fn accounts_hash_leaves_out_the_data() {
+        let committed = sample_account();
+        let left_out = AccountStateCommitment { data: Vec::new(), ..sample_account() };
+        assert_eq!(compute_accounts_hash(std::slice::from_ref(&committed)), compute_accounts_hash(&[left_out]));
+        let resized = AccountStateCommitment { data_len: 5, ..sample_account() };
+        assert_ne!(compute_accounts_hash(&[committed]), compute_accounts_hash(&[resized]));
+    }
+
+    #[test]
+    fn accounts_hash_depends_on_the_order() {
+        let first = sample_account();
+        let second = AccountStateCommitment { account_pubkey: [0x77; 32], ..sample_account() };
+        assert_ne!(compute_accounts_hash(&[first.clone(), second.clone()]), compute_accounts_hash(&[second, first]));
+    }
+
+    #[test]
+    fn domains_and_parts_are_separated() {
+        let slot = 1_000_000u64.to_le_bytes();
+        assert_ne!(hash_with_domain(BANK_HASH_DOMAIN, &[&slot]), hash_with_domain(ACCOUNTS_DOMAIN, &[&slot]));
+        assert_ne!(hash_with_domain("stub", &[b"ab", b"c"]), hash_with_domain("stub", &[b"a", b"bc"]));
+        assert_ne!(hash_with_domain("stub", &[b"ab"]), hash_with_domain("stu", &[b"bab"]));
+    }
+}
@@ -0,0 +1,147 @@
+//! Solana's epoch schedule
+//!
+//! Clusters created with warmup start with short epochs of
+//! [`MINIMUM_SLOTS_PER_EPOCH`] slots, doubling until they reach the normal
+//! length at `first_normal_epoch`; dividing the slot by the epoch length is
+//! wrong for every slot before `first_normal_slot`. The math is Solana's
+//! `EpochSchedule`, without the leader schedule offset.
+
+/// Length of the first warmup epoch
+pub const MINIMUM_SLOTS_PER_EPOCH: u64 = 32;
+
+/// Slots per epoch on mainnet, devnet and testnet
+pub const MAINNET_SLOTS_PER_EPOCH: u64 = 432_000;
+
+/// How a cluster's slots map to epochs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochSchedule {
+    /// Slots in every epoch from `first_normal_epoch` on
+    pub slots_per_epoch: u64,
+    /// Whether the cluster started with doubling warmup epochs
+    pub warmup: bool,
+    /// First epoch of `slots_per_epoch` slots
+    pub first_normal_epoch: u64,
+    /// First slot of `first_normal_epoch`
+    pub first_normal_slot: u64,
+}
+
+impl Default for EpochSchedule {
+    /// Mainnet's schedule
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl EpochSchedule {
+    /// Schedule of `slots_per_epoch`, warming up to it if `warmup`
+    ///
+    /// With warmup the normal length is rounded up to a power of two for
+    /// finding the first normal epoch, as Solana does.
+    pub fn new(slots_per_epoch: u64, warmup: bool) -> Self {
+        assert!(slots_per_epoch >= MINIMUM_SLOTS_PER_EPOCH, "an epoch has at least MINIMUM_SLOTS_PER_EPOCH slots");
+        let (first_normal_epoch, first_normal_slot) = if warmup {
+            let next_power_of_two = slots_per_epoch.next_power_of_two();
+            let epoch = next_power_of_two.trailing_zeros() - MINIMUM_SLOTS_PER_EPOCH.trailing_zeros();
+            (u64::from(epoch), next_power_of_two - MINIMUM_SLOTS_PER_EPOCH)
+        } else {
+            (0, 0)
+        };
+        Self { slots_per_epoch, warmup, first_normal_epoch, first_normal_slot }
+    }
+
+    /// Mainnet's schedule, which has no warmup; devnet and testnet use the same
+    pub fn mainnet() -> Self {
+        Self::new(MAINNET_SLOTS_PER_EPOCH, false)
+    }
+
+    /// Epoch of `slot`
+    pub fn get_epoch(&self, slot: u64) -> u64 {
+        self.get_epoch_and_slot_index(slot).0
+    }
+
+    /// Epoch of `slot` and its index within the epoch
+    pub fn get_epoch_and_slot_index(&self, slot: u64) -> (u64, u64) {
+        if slot < self.first_normal_slot {
+            // Warmup epoch n holds slots 32 * (2^n - 1) up to 32 * (2^(n + 1) - 1), exclusive
+            let epoch = (slot + MINIMUM_SLOTS_PER_EPOCH + 1).next_power_of_two().trailing_zeros()
+                - MINIMUM_SLOTS_PER_EPOCH.trailing_zeros()
+                - 1;
+            let epoch = u64::from(epoch);
+            (epoch, slot - self.get_first_slot_in_epoch(epoch))
+        } else {
+            let normal_slot_index = slot - self.first_normal_slot;
+            let epoch = self.first_normal_epoch + normal_slot_index / self.slots_per_epoch;
+            (epoch, normal_slot_index % self.slots_per_epoch)
+        }
+    }
+
+    /// Number of slots in `epoch`
+    pub fn get_slots_in_epoch(&self, epoch: u64) -> u64 {
+        if epoch < self.first_normal_epoch {
+            MINIMUM_SLOTS_PER_EPOCH << epoch
+        } else {
+            self.slots_per_epoch
+        }
+    }
+
+    /// First slot of `epoch`
+    pub fn get_first_slot_in_epoch(&self, epoch: u64) -> u64 {
+        if epoch <= self.first_normal_epoch {
+            ((1 << epoch) - 1) * MINIMUM_SLOTS_PER_EPOCH
+        } else {
+            (epoch - self.first_normal_epoch) * self.slots_per_epoch + self.first_normal_slot
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mainnet_has_no_warmup() {
+        let schedule = EpochSchedule::mainnet();
+        assert_eq!((schedule.first_normal_epoch, schedule.first_normal_slot), (0, 0));
+        assert_eq!(schedule.get_epoch_and_slot_index(0), (0, 0));
+        assert_eq!(schedule.get_epoch_and_slot_index(431_999), (0, 431_999));
+        assert_eq!(schedule.get_epoch_and_slot_index(432_000), (1, 0));
+        assert_eq!(schedule.get_epoch(250_000_100), 578);
+    }
+
+    #[test]
+    fn warmup_reaches_the_normal_length() {
+        // Solana's default schedule with warmup
+        let schedule = EpochSchedule::new(MAINNET_SLOTS_PER_EPOCH, true);
+        assert_eq!(schedule.first_normal_epoch, 14);
+        assert_eq!(schedule.first_normal_slot, 524_256);
+        assert_eq!(schedule.get_slots_in_epoch(13), 262_144);
+        assert_eq!(schedule.get_slots_in_epoch(14), MAINNET_SLOTS_PER_EPOCH);
+    }
+
+    #[test]
+    fn first_32_warmup_epochs() {
+        // Long enough a normal epoch that 32 epochs warm up to it
+        let schedule = EpochSchedule::new(MINIMUM_SLOTS_PER_EPOCH << 32, true);
+        assert_eq!(schedule.first_normal_epoch, 32);
+        for epoch in 0..32 {
+            let first_slot = MINIMUM_SLOTS_PER_EPOCH * ((1 << epoch) - 1);
+            let slots = MINIMUM_SLOTS_PER_EPOCH << epoch;
+            assert_eq!(schedule.get_first_slot_in_epoch(epoch), first_slot);
+            assert_eq!(schedule.get_slots_in_epoch(epoch), slots);
+            assert_eq!(schedule.get_epoch_and_slot_index(first_slot), (epoch, 0));
+            assert_eq!(schedule.get_epoch_and_slot_index(first_slot + slots - 1), (epoch, slots - 1));
+        }
+    }
+
+    #[test]
+    fn first_normal_epoch() {
+        let schedule = EpochSchedule::new(MAINNET_SLOTS_PER_EPOCH, true);
+        let first_normal_slot = schedule.first_normal_slot;
+        assert_eq!(schedule.get_first_slot_in_epoch(14), first_normal_slot);
+        assert_eq!(schedule.get_epoch_and_slot_index(first_normal_slot - 1), (13, 262_143));
+        assert_eq!(schedule.get_epoch_and_slot_index(first_normal_slot), (14, 0));
+        assert_eq!(schedule.get_epoch_and_slot_index(first_normal_slot + 431_999), (14, 431_999));
+        assert_eq!(schedule.get_epoch_and_slot_index(first_normal_slot + 432_000), (15, 0));
+        assert_eq!(schedule.get_first_slot_in_epoch(15), first_normal_slot + 432_000);
+    }
+}
@@ -0,0 +1,108 @@
+//! C API for decoding and digesting commitments
+//!
+//! Every function returns one of the `SSP_*` status codes. Inputs are borrowed
+//! for the duration of the call; a null input pointer is accepted only with a
+//! zero length. Buffers handed out by [`decode_commitments_json`] belong to
+//! the caller and must be released with [`free_buffer`], passing back the
+//! same pointer and length, exactly once. Out parameters are left untouched
+//! when a call fails. Building with the `ffi` feature generates the matching
+//! header in the build's `OUT_DIR`; `cbindgen` writes it where it is wanted.
+
+use crate::json::commitments_json;
+use crate::PublicCommitments;
+use sha2::{Digest, Sha256};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+/// The call succeeded
+pub const SSP_OK: i32 = 0;
+/// A required pointer was null
+pub const SSP_NULL_POINTER: i32 = -1;
+/// The input is not bincode `PublicCommitments`
+pub const SSP_DECODE_FAILED: i32 = -2;
+/// The call panicked; nothing was written
+pub const SSP_INTERNAL_ERROR: i32 = -3;
+
+/// Borrow `len` bytes at `ptr`, allowing null for an empty input
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Run `f`, turning a panic into [`SSP_INTERNAL_ERROR`] instead of unwinding into C
+fn guarded(f: impl FnOnce() -> i32) -> i32 {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(SSP_INTERNAL_ERROR)
+}
+
+/// Decode bincode public values into UTF-8 JSON
+///
+/// On success `*out_ptr` and `*out_len` describe a new buffer holding the JSON,
+/// not NUL-terminated, to be freed with [`free_buffer`].
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes, and `out_ptr` and `out_len`
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn decode_commitments_json(
+    ptr: *const u8,
+    len: usize,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    guarded(|| {
+        if out_ptr.is_null() || out_len.is_null() {
+            return SSP_NULL_POINTER;
+        }
+        let Some(bytes) = input(ptr, len) else {
+            return SSP_NULL_POINTER;
+        };
+        let commitments: PublicCommitments = match bincode::deserialize(bytes) {
+            Ok(commitments) => commitments,
+            Err(_) => return SSP_DECODE_FAILED,
+        };
+        let json = commitments_json(&commitments).to_string().into_bytes().into_boxed_slice();
+        *out_len = json.len();
+        *out_ptr = Box::into_raw(json) as *mut u8;
+        SSP_OK
+    })
+}
+
+/// Write the SHA-256 of the public values to `out32`
+///
+/// The digest the consumer's duplicate detection compares. The bytes are not
+/// decoded first.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes and `out32` for writes of 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn commitments_digest(ptr: *const u8, len: usize, out32: *mut u8) -> i32 {
+    guarded(|| {
+        if out32.is_null() {
+            return SSP_NULL_POINTER;
+        }
+        let Some(bytes) = input(ptr, len) else {
+            return SSP_NULL_POINTER;
+        };
+        let digest: [u8; 32] = Sha256::digest(bytes).into();
+        slice::from_raw_parts_mut(out32, 32).copy_from_slice(&digest);
+        SSP_OK
+    })
+}
+
+/// Free a buffer returned by this library; null is ignored
+///
+/// # Safety
+///
+/// `ptr` and `len` must be exactly what a successful call returned, and the
+/// buffer must not be used or freed again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn free_buffer(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+}
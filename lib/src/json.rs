@@ -0,0 +1,63 @@
+//! Commitments as JSON for other languages
+//!
+//! Byte arrays are rendered as hex strings. Lamport amounts and rent epochs are
+//! rendered as decimal strings because they can exceed the range JavaScript
+//! and JSON decoders without 64-bit integers read exactly. The rent epoch of a
+//! rent-exempt account is `"exempt"` instead of `u64::MAX`; the committed value
+//! stays numeric.
+
+use crate::{AccountStateCommitment, PublicCommitments, RENT_EXEMPT_EPOCH};
+use serde_json::{json, Value};
+
+/// JSON text of the rent epoch marking rent-exempt accounts
+pub const RENT_EXEMPT_JSON: &str = "exempt";
+
+/// `rent_epoch` as rendered in the JSON
+pub fn format_rent_epoch(rent_epoch: u64) -> String {
+    if rent_epoch == RENT_EXEMPT_EPOCH {
+        RENT_EXEMPT_JSON.to_string()
+    } else {
+        rent_epoch.to_string()
+    }
+}
+
+/// Rent epoch of its JSON text, `None` if it is neither `"exempt"` nor a decimal number
+pub fn parse_rent_epoch(text: &str) -> Option<u64> {
+    if text == RENT_EXEMPT_JSON {
+        Some(RENT_EXEMPT_EPOCH)
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn account_json(account: &AccountStateCommitment) -> Value {
+    json!({
+        "account_pubkey": hex::encode(account.account_pubkey),
+        "last_change_slot": account.last_change_slot,
+        "account_data_hash": hex::encode(account.account_data_hash),
+        "lamports": account.lamports.to_string(),
+        "owner": hex::encode(account.owner),
+        "executable": account.executable,
+        "rent_epoch": format_rent_epoch(account.rent_epoch),
+        "data_len": account.data_len,
+        "data": hex::encode(&account.data),
+    })
+}
+
+/// `commitments` as a JSON object
+pub fn commitments_json(commitments: &PublicCommitments) -> Value {
+    json!({
+        "start_slot": commitments.start_slot,
+        "end_slot": commitments.end_slot,
+        "epoch": commitments.epoch,
+        "original_bank_hash": hex::encode(commitments.original_bank_hash),
+        "last_bank_hash": hex::encode(commitments.last_bank_hash),
+        "account_data_hash": hex::encode(commitments.account_data_hash),
+        "hash_root_valset": hex::encode(commitments.hash_root_valset),
+        "total_active_stake": commitments.total_active_stake.to_string(),
+        "validator_count": commitments.validator_count,
+        "monitored_accounts_state": commitments.monitored_accounts_state.iter().map(account_json).collect::<Vec<_>>(),
+        "validations_passed": commitments.validations_passed,
+        "aggregated_vkey_digest": hex::encode(commitments.aggregated_vkey_digest),
+    })
+}
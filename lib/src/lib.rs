@@ -1,7 +1,36 @@
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "hash")]
+use sha2::{Digest, Sha256};
+
+#[cfg(test)]
+mod compat_tests;
+pub mod epoch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod phases;
+
+/// Version of the committed statement, the bincode layout of [`PublicCommitments`] and [`ProverInput`]
+/// and how their hashes are computed
+///
+/// Bump it with any change to either and add the new version's encodings to `lib/tests/fixtures`.
+pub const STATEMENT_VERSION: u32 = 4;
+
+/// Owner of accounts that do not exist, the system program
+pub const SYSTEM_PROGRAM_ID: [u8; 32] = [0; 32];
+
+/// Rent epoch Solana reports for rent-exempt accounts, which are never collected from again
+pub const RENT_EXEMPT_EPOCH: u64 = u64::MAX;
+
+/// Largest account data Solana allows (`MAX_PERMITTED_DATA_LENGTH`); the program rejects larger accounts
+pub const MAX_ACCOUNT_DATA_LEN: usize = 10 * 1024 * 1024;
 
 /// Public commitment per monitored account
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// An account without data has `data_len` 0 and the SHA-256 of the empty
+/// string as its `account_data_hash`, whichever shape the RPC returned it in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccountStateCommitment {
     pub account_pubkey: [u8; 32],
     pub last_change_slot: u64,
@@ -10,11 +39,13 @@ pub struct AccountStateCommitment {
     pub owner: [u8; 32],
     pub executable: bool,
     pub rent_epoch: u64,
+    /// Length of the account data, committed even when `data` is left out
+    pub data_len: u64,
     pub data: Vec<u8>,
 }
 
 /// The public values committed by the ZKVM program
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PublicCommitments {
     /// Start slot number of the proven chain
     pub start_slot: u64,
@@ -38,10 +69,171 @@ pub struct PublicCommitments {
     pub monitored_accounts_state: Vec<AccountStateCommitment>,
     /// Aggregated validation result (true if all validations passed)
     pub validations_passed: bool,
+    /// Verifying key digest the aggregated proofs were verified under, [`vkey_digest_bytes`];
+    /// [`NOT_AGGREGATED`] for a proof of a single range
+    pub aggregated_vkey_digest: [u8; 32],
 }
 
-/// Input data for the stub prover
+/// [`AccountStateCommitment`] borrowing its data from the decoded bytes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AccountStateCommitmentRef<'a> {
+    pub account_pubkey: [u8; 32],
+    pub last_change_slot: u64,
+    pub account_data_hash: [u8; 32],
+    pub lamports: u64,
+    pub owner: [u8; 32],
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub data_len: u64,
+    #[serde(borrow)]
+    pub data: &'a [u8],
+}
+
+impl AccountStateCommitmentRef<'_> {
+    /// Copy the data into an [`AccountStateCommitment`]
+    pub fn into_owned(self) -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: self.account_pubkey,
+            last_change_slot: self.last_change_slot,
+            account_data_hash: self.account_data_hash,
+            lamports: self.lamports,
+            owner: self.owner,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+            data_len: self.data_len,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+impl AccountStateCommitment {
+    /// Whether this commits to an account that does not exist
+    ///
+    /// Solana deletes accounts left without lamports, so a closed account and
+    /// one never created look the same: no lamports and no data, owned by the
+    /// system program. Goes by `data_len`, since `data` is empty whenever the
+    /// data was left out.
+    pub fn is_closed(&self) -> bool {
+        self.lamports == 0 && self.data_len == 0 && self.owner == SYSTEM_PROGRAM_ID
+    }
+
+    /// Whether the account is rent-exempt, its rent epoch the [`RENT_EXEMPT_EPOCH`] sentinel
+    pub fn is_rent_exempt(&self) -> bool {
+        self.rent_epoch == RENT_EXEMPT_EPOCH
+    }
+
+    /// Borrowed view of the commitment
+    pub fn view(&self) -> AccountStateCommitmentRef<'_> {
+        AccountStateCommitmentRef {
+            account_pubkey: self.account_pubkey,
+            last_change_slot: self.last_change_slot,
+            account_data_hash: self.account_data_hash,
+            lamports: self.lamports,
+            owner: self.owner,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+            data_len: self.data_len,
+            data: &self.data,
+        }
+    }
+}
+
+/// [`PublicCommitments`] borrowing the accounts' data from the decoded bytes
+///
+/// Same serialized layout as [`PublicCommitments`], so bincode-encoded public
+/// values decode into either. Decoding this one copies the scalar fields and
+/// the per-account headers but none of the accounts' data bytes, which is
+/// what readers that only look at slots, hashes or pubkeys want.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicCommitmentsRef<'a> {
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub epoch: u64,
+    pub original_bank_hash: [u8; 32],
+    pub last_bank_hash: [u8; 32],
+    pub account_data_hash: [u8; 32],
+    pub hash_root_valset: [u8; 32],
+    pub total_active_stake: u64,
+    pub validator_count: u32,
+    #[serde(borrow)]
+    pub monitored_accounts_state: Vec<AccountStateCommitmentRef<'a>>,
+    pub validations_passed: bool,
+    pub aggregated_vkey_digest: [u8; 32],
+}
+
+impl PublicCommitmentsRef<'_> {
+    /// Copy the accounts' data into a [`PublicCommitments`]
+    pub fn into_owned(self) -> PublicCommitments {
+        PublicCommitments {
+            start_slot: self.start_slot,
+            end_slot: self.end_slot,
+            epoch: self.epoch,
+            original_bank_hash: self.original_bank_hash,
+            last_bank_hash: self.last_bank_hash,
+            account_data_hash: self.account_data_hash,
+            hash_root_valset: self.hash_root_valset,
+            total_active_stake: self.total_active_stake,
+            validator_count: self.validator_count,
+            monitored_accounts_state: self
+                .monitored_accounts_state
+                .into_iter()
+                .map(AccountStateCommitmentRef::into_owned)
+                .collect(),
+            validations_passed: self.validations_passed,
+            aggregated_vkey_digest: self.aggregated_vkey_digest,
+        }
+    }
+}
+
+impl PublicCommitments {
+    /// Borrowed view of the commitments
+    pub fn view(&self) -> PublicCommitmentsRef<'_> {
+        PublicCommitmentsRef {
+            start_slot: self.start_slot,
+            end_slot: self.end_slot,
+            epoch: self.epoch,
+            original_bank_hash: self.original_bank_hash,
+            last_bank_hash: self.last_bank_hash,
+            account_data_hash: self.account_data_hash,
+            hash_root_valset: self.hash_root_valset,
+            total_active_stake: self.total_active_stake,
+            validator_count: self.validator_count,
+            monitored_accounts_state: self.monitored_accounts_state.iter().map(AccountStateCommitment::view).collect(),
+            validations_passed: self.validations_passed,
+            aggregated_vkey_digest: self.aggregated_vkey_digest,
+        }
+    }
+
+    /// Whether this aggregates proofs, which were verified under `aggregated_vkey_digest`
+    pub fn is_aggregate(&self) -> bool {
+        self.aggregated_vkey_digest != NOT_AGGREGATED
+    }
+}
+
+impl PublicCommitmentsRef<'_> {
+    /// Whether this aggregates proofs, which were verified under `aggregated_vkey_digest`
+    pub fn is_aggregate(&self) -> bool {
+        self.aggregated_vkey_digest != NOT_AGGREGATED
+    }
+}
+
+/// `aggregated_vkey_digest` of a proof of a single range, which aggregates nothing
+pub const NOT_AGGREGATED: [u8; 32] = [0; 32];
+
+/// Verifying key digest (`vk.hash_u32()`) as committed in `aggregated_vkey_digest`, each word big-endian
+///
+/// Verifiers compare the committed digest with this of the program key they
+/// pin; an aggregate under any other key proves nothing about this program.
+pub fn vkey_digest_bytes(digest: &[u32; 8]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (i, word) in digest.iter().enumerate() {
+        bytes[4 * i..4 * i + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+/// Input data for the stub prover
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProverInput {
     pub start_slot: u64,
     pub end_slot: u64,
@@ -49,4 +241,118 @@ pub struct ProverInput {
     pub original_bank_hash: [u8; 32],
     pub last_bank_hash: [u8; 32],
     pub monitored_accounts_state: Vec<AccountStateCommitment>,
+}
+
+/// Proofs of contiguous ranges to aggregate into one proof of their union
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationInput {
+    /// Verifying key digest (`vk.hash_u32()`) of the program the proofs were generated with
+    pub vkey_digest: [u32; 8],
+    /// Public values of each proof, in slot order
+    pub public_values: Vec<Vec<u8>>,
+}
+
+/// What the program is asked to prove
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProgramInput {
+    /// A slot range from its input
+    Range(ProverInput),
+    /// The union range of proofs that are verified recursively
+    Aggregate(AggregationInput),
+}
+
+/// Whether `accounts` are in strictly ascending pubkey order, as the program requires
+///
+/// Rules out a pubkey listed twice as well as any other order, so the same set
+/// of accounts has a single accounts hash.
+pub fn accounts_sorted(accounts: &[AccountStateCommitment]) -> bool {
+    accounts.windows(2).all(|pair| pair[0].account_pubkey < pair[1].account_pubkey)
+}
+
+/// Domain of the synthetic bank hashes of the stub's inputs
+pub const BANK_HASH_DOMAIN: &str = "stub:bank-hash";
+/// Domain of the hash over the monitored accounts, [`compute_accounts_hash`]
+pub const ACCOUNTS_DOMAIN: &str = "stub:accounts";
+
+/// SHA-256 of `parts` under `domain`
+///
+/// The domain and every part are prefixed with their length as a
+/// little-endian u64, so hashes of different domains, or of parts split
+/// differently, never share a preimage. Every hash the stub makes up goes
+/// through this; hashes of real data, like an account's `account_data_hash`,
+/// stay plain SHA-256.
+#[cfg(feature = "hash")]
+pub fn hash_with_domain(domain: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update((domain.len() as u64).to_le_bytes());
+    hasher.update(domain.as_bytes());
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Synthetic bank hash of `slot`
+#[cfg(feature = "hash")]
+pub fn stub_bank_hash(slot: u64) -> [u8; 32] {
+    hash_with_domain(BANK_HASH_DOMAIN, &[&slot.to_le_bytes()])
+}
+
+/// Dummy `account_data_hash` of the monitored accounts, as committed by the program
+///
+/// Covers each account's pubkey, last change slot, data length and data hash,
+/// but not the data itself, so it is the same whether the data is committed or
+/// left out.
+#[cfg(feature = "hash")]
+pub fn compute_accounts_hash(accounts: &[AccountStateCommitment]) -> [u8; 32] {
+    let mut records = Vec::with_capacity(accounts.len() * 80);
+    for account in accounts {
+        records.extend_from_slice(&account.account_pubkey);
+        records.extend_from_slice(&account.last_change_slot.to_le_bytes());
+        records.extend_from_slice(&account.data_len.to_le_bytes());
+        records.extend_from_slice(&account.account_data_hash);
+    }
+    hash_with_domain(ACCOUNTS_DOMAIN, &[&records])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed_account() -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: [1; 32],
+            last_change_slot: 0,
+            account_data_hash: [0; 32],
+            lamports: 0,
+            owner: SYSTEM_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+            data_len: 0,
+            data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn closed_accounts() {
+        assert!(closed_account().is_closed());
+        assert!(!AccountStateCommitment { lamports: 1, ..closed_account() }.is_closed());
+        assert!(!AccountStateCommitment { owner: [2; 32], ..closed_account() }.is_closed());
+        assert!(!AccountStateCommitment { data_len: 3, data: vec![1, 2, 3], ..closed_account() }.is_closed());
+    }
+
+    #[test]
+    fn left_out_data_is_not_closed() {
+        // Data left out of the commitment, only its length committed
+        assert!(!AccountStateCommitment { data_len: 3, ..closed_account() }.is_closed());
+    }
+    #[test]
+    fn vkey_digest_words_are_big_endian() {
+        let digest = [0x0102_0304, 0, 0, 0, 0, 0, 0, 0x0a0b_0c0d];
+        let bytes = vkey_digest_bytes(&digest);
+        assert_eq!(bytes[..4], [1, 2, 3, 4]);
+        assert_eq!(bytes[28..], [0x0a, 0x0b, 0x0c, 0x0d]);
+        assert_eq!(vkey_digest_bytes(&[0; 8]), NOT_AGGREGATED);
+    }
 }
\ No newline at end of file
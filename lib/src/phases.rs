@@ -0,0 +1,29 @@
+//! Names of the program's cycle-tracker phases
+//!
+//! The program brackets each phase with SP1's `cycle-tracker-report-start:`
+//! and `cycle-tracker-report-end:` lines, and the execution report adds up the
+//! cycles between them under the phase name, over every time it ran. The host
+//! reads them back by the same names. The aggregation mode only reports
+//! [`READ_INPUT`] and [`COMMIT_OUTPUT`].
+
+/// Deserializing the program input from stdin
+pub const READ_INPUT: &str = "read-input";
+/// Checking the slot range and each account, with the accounts' phases nested inside
+pub const CHECK_ACCOUNTS: &str = "check-accounts";
+/// Hashing the monitored accounts into `account_data_hash`
+pub const ACCOUNTS_HASH: &str = "accounts-hash";
+/// Building the validator set root, stake and count; constants in the stub
+pub const VALSET_ROOT: &str = "valset-root";
+/// Serializing the commitments and committing them
+pub const COMMIT_OUTPUT: &str = "commit-output";
+
+/// The top-level phases in program order; they do not overlap
+pub const ALL: [&str; 5] = [READ_INPUT, CHECK_ACCOUNTS, ACCOUNTS_HASH, VALSET_ROOT, COMMIT_OUTPUT];
+
+/// Prefix of the phase of one account's checks, followed by its index in the input
+pub const ACCOUNT_PREFIX: &str = "account-";
+
+/// Phase of the account at `index`
+pub fn account(index: usize) -> String {
+    format!("{}{}", ACCOUNT_PREFIX, index)
+}
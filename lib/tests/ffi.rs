@@ -0,0 +1,133 @@
+//! The C API called through raw pointers, as C callers do
+//!
+//! A counting allocator tracks the bytes each test thread holds, so a buffer
+//! the API hands out and `free_buffer` releases must leave the count where it
+//! was, and one that is not freed must show up in it.
+
+#![cfg(feature = "ffi")]
+
+use sha2::{Digest, Sha256};
+use solana_stub_prover_lib::ffi::{
+    commitments_digest, decode_commitments_json, free_buffer, SSP_DECODE_FAILED, SSP_NULL_POINTER, SSP_OK,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr;
+
+/// Counts the bytes allocated and not yet freed by the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(bytes: isize) {
+    // Not available while the thread shuts down; nothing is measured then
+    let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + bytes));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(Cell::get)
+}
+
+/// The lib's pinned sample statement of the current version
+fn public_values() -> Vec<u8> {
+    let text = include_str!("fixtures/statement-v4-commitments.hex");
+    let hex: String = text.lines().map(|line| line.split('#').next().unwrap_or_default().trim()).collect();
+    hex::decode(hex).unwrap()
+}
+
+#[test]
+fn decoded_json_is_freed_exactly() {
+    let bytes = public_values();
+    let before = live_bytes();
+    let (mut out_ptr, mut out_len) = (ptr::null_mut(), 0);
+    let status = unsafe { decode_commitments_json(bytes.as_ptr(), bytes.len(), &mut out_ptr, &mut out_len) };
+    assert_eq!(status, SSP_OK);
+    assert!(!out_ptr.is_null());
+    // The buffer is the only allocation left behind by the call
+    assert_eq!(live_bytes() - before, out_len as isize);
+
+    {
+        let json: serde_json::Value =
+            serde_json::from_slice(unsafe { std::slice::from_raw_parts(out_ptr, out_len) }).unwrap();
+        assert_eq!(json["start_slot"], 1_000_000);
+        assert_eq!(json["monitored_accounts_state"][0]["data"], hex::encode(b"stub"));
+    }
+
+    unsafe { free_buffer(out_ptr, out_len) };
+    assert_eq!(live_bytes(), before);
+}
+
+#[test]
+fn failed_decode_allocates_nothing_and_leaves_the_outputs() {
+    let bytes = public_values();
+    let truncated = &bytes[..bytes.len() - 1];
+    let before = live_bytes();
+    let (mut out_ptr, mut out_len) = (ptr::null_mut(), 7);
+    let status = unsafe { decode_commitments_json(truncated.as_ptr(), truncated.len(), &mut out_ptr, &mut out_len) };
+    assert_eq!(status, SSP_DECODE_FAILED);
+    assert_eq!((out_ptr, out_len), (ptr::null_mut(), 7));
+    assert_eq!(live_bytes(), before);
+}
+
+#[test]
+fn null_pointers_are_refused() {
+    let bytes = public_values();
+    let (mut out_ptr, mut out_len) = (ptr::null_mut(), 0);
+    unsafe {
+        assert_eq!(decode_commitments_json(ptr::null(), 4, &mut out_ptr, &mut out_len), SSP_NULL_POINTER);
+        assert_eq!(
+            decode_commitments_json(bytes.as_ptr(), bytes.len(), ptr::null_mut(), &mut out_len),
+            SSP_NULL_POINTER
+        );
+        assert_eq!(
+            decode_commitments_json(bytes.as_ptr(), bytes.len(), &mut out_ptr, ptr::null_mut()),
+            SSP_NULL_POINTER
+        );
+        assert_eq!(commitments_digest(bytes.as_ptr(), bytes.len(), ptr::null_mut()), SSP_NULL_POINTER);
+        assert_eq!(commitments_digest(ptr::null(), 1, [0u8; 32].as_mut_ptr()), SSP_NULL_POINTER);
+        // Freeing null is a no-op
+        free_buffer(ptr::null_mut(), 0);
+    }
+    assert!(out_ptr.is_null());
+}
+
+#[test]
+fn empty_input_may_be_null() {
+    let mut digest = [0u8; 32];
+    assert_eq!(unsafe { commitments_digest(ptr::null(), 0, digest.as_mut_ptr()) }, SSP_OK);
+    assert_eq!(digest, <[u8; 32]>::from(Sha256::digest(b"")));
+    let (mut out_ptr, mut out_len) = (ptr::null_mut(), 0);
+    assert_eq!(unsafe { decode_commitments_json(ptr::null(), 0, &mut out_ptr, &mut out_len) }, SSP_DECODE_FAILED);
+}
+
+#[test]
+fn digest_is_the_sha256_of_the_bytes() {
+    let bytes = public_values();
+    let before = live_bytes();
+    let mut digest = [0u8; 32];
+    assert_eq!(unsafe { commitments_digest(bytes.as_ptr(), bytes.len(), digest.as_mut_ptr()) }, SSP_OK);
+    assert_eq!(digest, <[u8; 32]>::from(Sha256::digest(&bytes)));
+    assert_eq!(live_bytes(), before);
+}
@@ -1,35 +1,77 @@
-//! Solana stub prover program that validates end_slot > start_slot
-//! and commits to the public values matching the twine-solana-prover structure
+//! Solana stub prover program that validates end_slot > start_slot and that
+//! the monitored accounts are sorted by pubkey without duplicates, and commits
+//! to the public values matching the twine-solana-prover structure
+//!
+//! In aggregation mode it instead verifies proofs of contiguous ranges made by
+//! this same program and commits to their union range.
+//!
+//! Each phase is a cycle-tracker phase named in `solana_stub_prover_lib::phases`,
+//! so execution reports its cycles separately.
 
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use solana_stub_prover_lib::{ProverInput, PublicCommitments};
+use solana_stub_prover_lib::phases::{self, ACCOUNTS_HASH, CHECK_ACCOUNTS, COMMIT_OUTPUT, READ_INPUT, VALSET_ROOT};
+use solana_stub_prover_lib::{
+    accounts_sorted, compute_accounts_hash, vkey_digest_bytes, AccountStateCommitment, AggregationInput, ProgramInput,
+    ProverInput, PublicCommitments, MAX_ACCOUNT_DATA_LEN, NOT_AGGREGATED,
+};
 use sha2::{Sha256, Digest};
 
 pub fn main() {
     // Read input from the prover
-    let input = sp1_zkvm::io::read::<ProverInput>();
+    let commitments = match phase(READ_INPUT, sp1_zkvm::io::read::<ProgramInput>) {
+        ProgramInput::Range(input) => prove_range(input),
+        ProgramInput::Aggregate(input) => aggregate(input),
+    };
     
-    // Simple validation: check that end_slot > start_slot
-    assert!(input.end_slot > input.start_slot, "end_slot must be greater than start_slot");
+    // Serialize and commit the public values
+    phase(COMMIT_OUTPUT, || {
+        let bytes = bincode::serialize(&commitments).expect("Failed to serialize commitments");
+        sp1_zkvm::io::commit_slice(&bytes);
+    });
+}
+
+/// Run `f` as the cycle-tracker phase `name`
+fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    println!("cycle-tracker-report-start: {}", name);
+    let result = f();
+    println!("cycle-tracker-report-end: {}", name);
+    result
+}
+
+fn prove_range(input: ProverInput) -> PublicCommitments {
+    phase(CHECK_ACCOUNTS, || {
+        // Simple validation: check that end_slot > start_slot
+        assert!(input.end_slot > input.start_slot, "end_slot must be greater than start_slot");
+        assert!(
+            accounts_sorted(&input.monitored_accounts_state),
+            "monitored accounts must be sorted by pubkey without duplicates"
+        );
+        for (i, account) in input.monitored_accounts_state.iter().enumerate() {
+            phase(&phases::account(i), || {
+                assert!(account.data_len <= MAX_ACCOUNT_DATA_LEN as u64, "account data exceeds MAX_ACCOUNT_DATA_LEN");
+                // The data is either left out or committed in full
+                assert!(
+                    account.data.is_empty() || account.data.len() as u64 == account.data_len,
+                    "account data does not match its data_len"
+                );
+            });
+        }
+    });
     
-    // Calculate a dummy account_data_hash from the monitored accounts
-    let mut hasher = Sha256::new();
-    for account in &input.monitored_accounts_state {
-        hasher.update(&account.account_pubkey);
-        hasher.update(&account.last_change_slot.to_le_bytes());
-        hasher.update(&account.account_data_hash);
-    }
-    let account_data_hash: [u8; 32] = hasher.finalize().into();
+    let account_data_hash = phase(ACCOUNTS_HASH, || compute_accounts_hash(&input.monitored_accounts_state));
     
     // Create dummy values for ESR and validator data
-    let hash_root_valset = [0u8; 32]; // Dummy merkle root
-    let total_active_stake = 1000000000u64; // 1 billion lamports
-    let validator_count = 100u32; // 100 validators
+    let (hash_root_valset, total_active_stake, validator_count) = phase(VALSET_ROOT, || {
+        let hash_root_valset = [0u8; 32]; // Dummy merkle root
+        let total_active_stake = 1000000000u64; // 1 billion lamports
+        let validator_count = 100u32; // 100 validators
+        (hash_root_valset, total_active_stake, validator_count)
+    });
     
     // Build public commitments
-    let commitments = PublicCommitments {
+    PublicCommitments {
         start_slot: input.start_slot,
         end_slot: input.end_slot,
         epoch: input.epoch,
@@ -41,9 +83,61 @@ pub fn main() {
         validator_count,
         monitored_accounts_state: input.monitored_accounts_state,
         validations_passed: true, // Always true for stub
-    };
+        aggregated_vkey_digest: NOT_AGGREGATED,
+    }
+}
+
+fn aggregate(input: AggregationInput) -> PublicCommitments {
+    assert!(!input.public_values.is_empty(), "no proofs to aggregate");
+    // Committed, so verifiers can tell the proofs were of the program they pin
+    let vkey_digest = vkey_digest_bytes(&input.vkey_digest);
     
-    // Serialize and commit the public values
-    let bytes = bincode::serialize(&commitments).expect("Failed to serialize commitments");
-    sp1_zkvm::io::commit_slice(&bytes);
+    // The proofs are deferred to the recursion circuit, which checks each against the digest of its public values
+    let mut parts = Vec::with_capacity(input.public_values.len());
+    for public_values in &input.public_values {
+        let digest: [u8; 32] = Sha256::digest(public_values).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(&input.vkey_digest, &digest);
+        let part: PublicCommitments =
+            bincode::deserialize(public_values).expect("Public values are not PublicCommitments");
+        // An aggregate among them must have verified its own proofs under the same key
+        assert!(
+            !part.is_aggregate() || part.aggregated_vkey_digest == vkey_digest,
+            "an aggregated proof verified proofs under another key"
+        );
+        parts.push(part);
+    }
+    
+    // A range starts at the slot and bank hash the previous one ended with
+    for pair in parts.windows(2) {
+        assert_eq!(pair[1].start_slot, pair[0].end_slot, "ranges are not contiguous");
+        assert_eq!(pair[1].original_bank_hash, pair[0].last_bank_hash, "bank hashes do not link up");
+    }
+    
+    // Latest state of every account; later ranges replace earlier ones
+    let mut accounts: Vec<AccountStateCommitment> = Vec::new();
+    for account in parts.iter().flat_map(|part| &part.monitored_accounts_state) {
+        match accounts.iter_mut().find(|known| known.account_pubkey == account.account_pubkey) {
+            Some(known) => *known = account.clone(),
+            None => accounts.push(account.clone()),
+        }
+    }
+    // Ranges may monitor different accounts; keep the committed list in the order ranges require
+    accounts.sort_by(|a, b| a.account_pubkey.cmp(&b.account_pubkey));
+    
+    let first = &parts[0];
+    let last = &parts[parts.len() - 1];
+    PublicCommitments {
+        start_slot: first.start_slot,
+        end_slot: last.end_slot,
+        epoch: last.epoch,
+        original_bank_hash: first.original_bank_hash,
+        last_bank_hash: last.last_bank_hash,
+        account_data_hash: compute_accounts_hash(&accounts),
+        hash_root_valset: last.hash_root_valset,
+        total_active_stake: last.total_active_stake,
+        validator_count: last.validator_count,
+        monitored_accounts_state: accounts,
+        validations_passed: parts.iter().all(|part| part.validations_passed),
+        aggregated_vkey_digest: vkey_digest,
+    }
 }
\ No newline at end of file
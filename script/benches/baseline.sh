@@ -0,0 +1,65 @@
+#!/bin/bash
+
+# Record or check benchmark baselines
+#
+#   benches/baseline.sh save    run the benchmarks and write benches/baseline.json
+#   benches/baseline.sh check   run the benchmarks and fail if one is slower than the baseline
+#
+# Compares criterion's mean estimates. BENCH_THRESHOLD is the allowed slowdown
+# in percent (default 10).
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "$0")/.." && pwd)"
+BASELINE="$SCRIPT_DIR/benches/baseline.json"
+TARGET_DIR="${CARGO_TARGET_DIR:-$SCRIPT_DIR/../target}"
+THRESHOLD="${BENCH_THRESHOLD:-10}"
+
+if ! command -v jq &> /dev/null; then
+    echo "❌ jq not found"
+    exit 1
+fi
+
+# Mean time in nanoseconds of every benchmark of the last run, by id
+collect() {
+    find "$TARGET_DIR/criterion" -path '*/new/benchmark.json' | sort | while read -r benchmark; do
+        estimates="$(dirname "$benchmark")/estimates.json"
+        jq -n --slurpfile b "$benchmark" --slurpfile e "$estimates" \
+            '{($b[0].full_id): ($e[0].mean.point_estimate | round)}'
+    done | jq -S -s 'add // {}'
+}
+
+run_benches() {
+    (cd "$SCRIPT_DIR" && cargo bench --bench hot_paths)
+}
+
+case "${1:-}" in
+    save)
+        run_benches
+        collect > "$BASELINE"
+        echo "✅ Wrote $BASELINE"
+        ;;
+    check)
+        if [ ! -f "$BASELINE" ]; then
+            echo "❌ No baseline at $BASELINE, run '$0 save' first"
+            exit 1
+        fi
+        run_benches
+        report=$(collect | jq -r --slurpfile base "$BASELINE" --argjson threshold "$THRESHOLD" '
+            to_entries[] | .key as $id | .value as $now | $base[0][$id] as $before
+            | if $before == null then "new     \($id): \($now) ns"
+              else (($now - $before) * 100 / $before) as $change
+                | "\(if $change > $threshold then "SLOWER " else "ok     " end)\($id): \($before) -> \($now) ns (\($change | round)%)"
+              end')
+        echo "$report"
+        if grep -q '^SLOWER' <<< "$report"; then
+            echo "❌ Slower than the baseline by more than ${THRESHOLD}%"
+            exit 1
+        fi
+        echo "✅ No benchmark slower than the baseline by more than ${THRESHOLD}%"
+        ;;
+    *)
+        echo "Usage: $0 save|check"
+        exit 1
+        ;;
+esac
@@ -0,0 +1,164 @@
+//! Benchmarks of the host-side hot paths
+//!
+//! Inputs come from the fixture generator, so every run measures the same
+//! bytes. Before measuring, each group checks that the benched code agrees
+//! with the implementation the prover and consumer use, so `cargo test
+//! --benches` doubles as a smoke test. `benches/baseline.sh` records and
+//! compares baselines (see the README).
+
+use base64::{engine::general_purpose, Engine as _};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use solana_stub_prover_lib::{
+    compute_accounts_hash, AccountStateCommitment, PublicCommitments, PublicCommitmentsRef, NOT_AGGREGATED,
+};
+use solana_stub_prover_script::envelope::decode_json;
+use solana_stub_prover_script::fixtures::{self, Preset, DEFAULT_SEED};
+use solana_stub_prover_script::pipeline::{decode_account_data, envelope};
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::public_values::{decode_abi, decode_bincode, encode_abi};
+use solana_stub_prover_script::types::AccountData;
+use solana_stub_prover_script::utils::sha256_hash;
+use std::hint::black_box;
+use twine_types::proofs::ProofData;
+
+const ACCOUNT_COUNTS: [usize; 4] = [1, 4, 16, 64];
+/// Empty, an SPL token account, 10 KiB
+const DATA_LENS: [usize; 3] = [0, 165, 10 * 1024];
+/// Size of a Groth16 proof bundle with its public values
+const PROOF_LEN: usize = 2 * 1024 * 1024;
+
+/// The public values the stub program commits for `accounts`
+fn commitments(accounts: Vec<AccountStateCommitment>) -> PublicCommitments {
+    let input = Preset::SmallAccount.generate(DEFAULT_SEED);
+    PublicCommitments {
+        start_slot: input.start_slot,
+        end_slot: input.end_slot,
+        epoch: input.epoch,
+        original_bank_hash: input.original_bank_hash,
+        last_bank_hash: input.last_bank_hash,
+        account_data_hash: compute_accounts_hash(&accounts),
+        hash_root_valset: [0u8; 32],
+        total_active_stake: 1_000_000_000,
+        validator_count: 100,
+        monitored_accounts_state: accounts,
+        validations_passed: true,
+        aggregated_vkey_digest: NOT_AGGREGATED,
+    }
+}
+
+fn accounts_hash(c: &mut Criterion) {
+    // The hash only covers pubkeys, slots, data lengths and data hashes, so it must not depend on the data itself
+    let accounts = fixtures::accounts(DEFAULT_SEED, 4, 165);
+    let mut stripped = accounts.clone();
+    stripped.iter_mut().for_each(|account| account.data.clear());
+    assert_eq!(compute_accounts_hash(&accounts), compute_accounts_hash(&stripped));
+
+    let mut group = c.benchmark_group("accounts_hash");
+    for count in ACCOUNT_COUNTS {
+        for data_len in DATA_LENS {
+            let accounts = fixtures::accounts(DEFAULT_SEED, count, data_len);
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_with_input(BenchmarkId::new(format!("{}b", data_len), count), &accounts, |b, accounts| {
+                b.iter(|| compute_accounts_hash(black_box(accounts)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn public_values(c: &mut Criterion) {
+    let mut group = c.benchmark_group("public_values");
+    for count in ACCOUNT_COUNTS {
+        let commitments = commitments(fixtures::accounts(DEFAULT_SEED, count, 165));
+        let bincode_bytes = bincode::serialize(&commitments).expect("commitments serialize");
+        let abi_bytes = encode_abi(&commitments);
+
+        // Both layouts must decode to the same commitments
+        let from_bincode = decode_bincode(&bincode_bytes).expect("bincode public values decode");
+        let from_abi = decode_abi(&abi_bytes).expect("ABI public values decode");
+        assert_eq!(bincode::serialize(&from_bincode).expect("commitments serialize"), bincode_bytes);
+        assert_eq!(bincode::serialize(&from_abi).expect("commitments serialize"), bincode_bytes);
+        assert_eq!(encode_abi(&from_bincode), abi_bytes);
+        // The borrowed view must see the same fields as the owned decode
+        let view: PublicCommitmentsRef = bincode::deserialize(&bincode_bytes).expect("borrowed public values decode");
+        assert_eq!(bincode::serialize(&view).expect("view serializes"), bincode_bytes);
+        assert_eq!(bincode::serialize(&view.into_owned()).expect("commitments serialize"), bincode_bytes);
+        assert_eq!(bincode::serialize(&from_bincode.view()).expect("view serializes"), bincode_bytes);
+
+        group.bench_with_input(BenchmarkId::new("bincode_encode", count), &commitments, |b, commitments| {
+            b.iter(|| bincode::serialize(black_box(commitments)).expect("commitments serialize"))
+        });
+        group.bench_with_input(BenchmarkId::new("bincode_decode", count), &bincode_bytes, |b, bytes| {
+            b.iter(|| decode_bincode(black_box(bytes)).expect("bincode public values decode"))
+        });
+        group.bench_with_input(BenchmarkId::new("bincode_decode_ref", count), &bincode_bytes, |b, bytes| {
+            b.iter(|| {
+                bincode::deserialize::<PublicCommitmentsRef>(black_box(bytes)).expect("borrowed public values decode")
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("abi_encode", count), &commitments, |b, commitments| {
+            b.iter(|| encode_abi(black_box(commitments)))
+        });
+        group.bench_with_input(BenchmarkId::new("abi_decode", count), &abi_bytes, |b, bytes| {
+            b.iter(|| decode_abi(black_box(bytes)).expect("ABI public values decode"))
+        });
+    }
+    group.finish();
+}
+
+fn account_data(c: &mut Criterion) {
+    let encoded = |data: &[u8], encoding: &str| {
+        AccountData::Encoded(general_purpose::STANDARD.encode(data), encoding.to_string())
+    };
+    // Only base64 and base64+zstd may be hashed; anything else must fail rather than decode
+    assert!(decode_account_data(&encoded(&[], "base64")).expect("empty account data decodes").is_empty());
+    assert!(decode_account_data(&AccountData::Other(serde_json::json!([]))).expect("`[]` decodes").is_empty());
+    assert!(decode_account_data(&encoded(b"data", "base58")).is_err());
+    assert!(decode_account_data(&AccountData::Other(serde_json::json!({ "parsed": {}, "space": 0 }))).is_err());
+
+    let mut group = c.benchmark_group("account_data");
+    for data_len in [165, 10 * 1024, 1024 * 1024] {
+        let data = fixtures::random_bytes(DEFAULT_SEED, data_len);
+        let base64 = encoded(&data, "base64");
+        let compressed = zstd::encode_all(data.as_slice(), 0).expect("account data compresses");
+        let zstd = encoded(&compressed, "base64+zstd");
+        assert_eq!(decode_account_data(&base64).expect("account data decodes"), data);
+        assert_eq!(decode_account_data(&zstd).expect("compressed account data decodes"), data);
+
+        group.throughput(Throughput::Bytes(data_len as u64));
+        group.bench_with_input(BenchmarkId::new("base64_decode", data_len), &base64, |b, encoded| {
+            b.iter(|| decode_account_data(black_box(encoded)).expect("account data decodes"))
+        });
+        group.bench_with_input(BenchmarkId::new("base64_zstd_decode", data_len), &zstd, |b, encoded| {
+            b.iter(|| decode_account_data(black_box(encoded)).expect("account data decodes"))
+        });
+    }
+    group.finish();
+}
+
+fn kafka_envelope(c: &mut Criterion) {
+    let proof = fixtures::random_bytes(DEFAULT_SEED, PROOF_LEN);
+    let commitments = commitments(fixtures::accounts(DEFAULT_SEED, 16, 165));
+    let public_value = bincode::serialize(&commitments).expect("commitments serialize");
+    let vk_hash = sha256_hash(b"verifying key");
+    let build = |proof: &[u8]| {
+        let message = envelope("bench".to_string(), ProofMode::Groth16, proof.to_vec(), public_value.clone(), vk_hash);
+        serde_json::to_vec(&message).expect("envelope serializes")
+    };
+
+    // The consumer must read back the proof that was published
+    let decoded = decode_json(&build(&proof)).expect("envelope decodes");
+    let ProofData::SP1(sp1_proof) = &decoded.proof.proof_data;
+    assert_eq!(decoded.proof.identifier, "bench");
+    assert_eq!(sp1_proof.proof, proof);
+    assert_eq!(sp1_proof.public_value, public_value);
+
+    let mut group = c.benchmark_group("envelope");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(PROOF_LEN as u64));
+    group.bench_function("json_2mib", |b| b.iter(|| build(black_box(&proof))));
+    group.finish();
+}
+
+criterion_group!(benches, accounts_hash, public_values, account_data, kafka_envelope);
+criterion_main!(benches);
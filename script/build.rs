@@ -1,5 +1,44 @@
 use sp1_build::build_program_with_args;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// File the expected program vkey hash is pinned in; the same as `vkey_registry::EXPECTED_VKEY_FILE`
+const EXPECTED_VKEY_FILE: &str = "../program/vkey-hash";
 
 fn main() {
-    build_program_with_args("../program", Default::default())
+    build_program_with_args("../program", Default::default());
+    write_expected_vkey();
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/prover.proto").expect("Failed to compile proto/prover.proto");
+}
+
+/// Generate `EXPECTED_VKEY_HASH` from the pinned file, or from the
+/// `EXPECTED_VKEY_HASH` environment variable, which overrides it
+fn write_expected_vkey() {
+    // A missing file would rerun the script on every build; watch the directory it will be written to instead
+    if Path::new(EXPECTED_VKEY_FILE).exists() {
+        println!("cargo:rerun-if-changed={}", EXPECTED_VKEY_FILE);
+    } else {
+        println!("cargo:rerun-if-changed=../program");
+    }
+    println!("cargo:rerun-if-env-changed=EXPECTED_VKEY_HASH");
+    let pinned = env::var("EXPECTED_VKEY_HASH").ok().or_else(|| fs::read_to_string(EXPECTED_VKEY_FILE).ok());
+    let hash = pinned.map(|hash| hash.trim().to_string()).filter(|hash| !hash.is_empty());
+    if let Some(hash) = &hash {
+        let digits = hash.strip_prefix("0x").unwrap_or(hash);
+        assert!(
+            digits.len() == 64 && digits.chars().all(|c| c.is_ascii_hexdigit()),
+            "Invalid expected vkey hash '{}' (expected 32 bytes of hex)",
+            hash
+        );
+    }
+    let code = format!(
+        "/// Program vkey hash the prover was built to expect, `None` if none was pinned\n\
+         pub const EXPECTED_VKEY_HASH: Option<&str> = {:?};\n",
+        hash
+    );
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    fs::write(Path::new(&out_dir).join("expected_vkey.rs"), code).expect("Failed to write expected_vkey.rs");
 }
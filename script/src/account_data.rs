@@ -0,0 +1,137 @@
+//! Raw account data sent next to a proof instead of inside it
+//!
+//! Committing the monitored accounts' data makes the program read and commit
+//! every byte of it, so a large account inflates the cycle count, the public
+//! values and every message built from them. By default the prover empties
+//! `AccountStateCommitment.data` before running the program and publishes the
+//! data in the envelope's metadata section instead, under
+//! [`ACCOUNT_DATA_FIELD`]; `--commit-raw-data` commits it as before. Each
+//! account's `account_data_hash` and the accounts hash over them are committed
+//! either way and do not cover the data bytes, so the omission leaves them
+//! unchanged and consumers check the metadata against them with [`verify`].
+
+use crate::utils::sha256_hash;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_stub_prover_lib::{compute_accounts_hash, ProverInput, PublicCommitments};
+use std::error::Error;
+use std::fmt;
+
+/// Field of the envelope metadata holding the raw data of the monitored accounts
+pub const ACCOUNT_DATA_FIELD: &str = "account_data";
+
+/// Whether the program commits the monitored accounts' raw data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawData {
+    /// Commit only the data hashes; the data travels out of band
+    #[default]
+    Omit,
+    /// Commit the data itself (`--commit-raw-data`)
+    Commit,
+}
+
+/// Raw data of one monitored account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawAccountData {
+    /// Base58 pubkey
+    pub account_pubkey: String,
+    /// Base64 data
+    pub data: String,
+}
+
+/// Why out-of-band account data does not match the commitments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountDataError {
+    /// The metadata's account data is not a list of accounts
+    Invalid(String),
+    /// The committed accounts do not hash to the committed `account_data_hash`
+    AccountsHash { committed: String, computed: String },
+    /// The data is for an account the proof does not commit to
+    NotCommitted(String),
+    /// The data does not hash to the account's committed `account_data_hash`
+    HashMismatch { account: String, committed: String, computed: String },
+}
+
+impl fmt::Display for AccountDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountDataError::Invalid(reason) => write!(f, "Invalid account data: {}", reason),
+            AccountDataError::AccountsHash { committed, computed } => write!(
+                f,
+                "The committed accounts hash to 0x{}, but the committed account_data_hash is 0x{}",
+                computed, committed
+            ),
+            AccountDataError::NotCommitted(account) => write!(f, "Account {} is not committed", account),
+            AccountDataError::HashMismatch { account, committed, computed } => {
+                write!(f, "Data of account {} hashes to 0x{}, but 0x{} is committed", account, computed, committed)
+            }
+        }
+    }
+}
+
+impl Error for AccountDataError {}
+
+/// Take the data out of every monitored account of `input`, leaving the hashes
+///
+/// Accounts assembled without their data (`InputConfig::keep_data`) have none
+/// to take and are left out.
+pub fn detach(input: &mut ProverInput) -> Vec<RawAccountData> {
+    input
+        .monitored_accounts_state
+        .iter_mut()
+        .filter(|account| account.data.len() as u64 == account.data_len)
+        .map(|account| RawAccountData {
+            account_pubkey: bs58::encode(account.account_pubkey).into_string(),
+            data: general_purpose::STANDARD.encode(std::mem::take(&mut account.data)),
+        })
+        .collect()
+}
+
+/// Envelope metadata carrying `accounts`
+pub fn metadata(accounts: &[RawAccountData]) -> Value {
+    json!({ ACCOUNT_DATA_FIELD: accounts })
+}
+
+/// The accounts in envelope metadata, `None` if it carries none
+pub fn from_metadata(metadata: &Value) -> Option<Result<Vec<RawAccountData>, AccountDataError>> {
+    let accounts = metadata.get(ACCOUNT_DATA_FIELD)?;
+    Some(Vec::<RawAccountData>::deserialize(accounts).map_err(|e| AccountDataError::Invalid(e.to_string())))
+}
+
+/// Check out-of-band data against the commitments, returning the number of bytes checked
+///
+/// The committed accounts must hash to the committed `account_data_hash`, and
+/// the data of every account in `accounts` must hash to that account's
+/// committed `account_data_hash`.
+pub fn verify(commitments: &PublicCommitments, accounts: &[RawAccountData]) -> Result<usize, AccountDataError> {
+    let computed = compute_accounts_hash(&commitments.monitored_accounts_state);
+    if computed != commitments.account_data_hash {
+        return Err(AccountDataError::AccountsHash {
+            committed: hex::encode(commitments.account_data_hash),
+            computed: hex::encode(computed),
+        });
+    }
+
+    let mut checked = 0;
+    for raw in accounts {
+        let committed = commitments
+            .monitored_accounts_state
+            .iter()
+            .find(|account| bs58::encode(account.account_pubkey).into_string() == raw.account_pubkey)
+            .ok_or_else(|| AccountDataError::NotCommitted(raw.account_pubkey.clone()))?;
+        let data = general_purpose::STANDARD
+            .decode(&raw.data)
+            .map_err(|e| AccountDataError::Invalid(format!("data of {} is not base64: {}", raw.account_pubkey, e)))?;
+        let computed = sha256_hash(&data);
+        if computed != committed.account_data_hash {
+            return Err(AccountDataError::HashMismatch {
+                account: raw.account_pubkey.clone(),
+                committed: hex::encode(committed.account_data_hash),
+                computed: hex::encode(computed),
+            });
+        }
+        checked += data.len();
+    }
+    Ok(checked)
+}
@@ -0,0 +1,138 @@
+//! Change notifications for watched accounts
+//!
+//! [`watch_accounts`] subscribes to the accounts over the node's websocket
+//! (`accountSubscribe`) and reports every notification as a change at its
+//! context slot. While the websocket cannot be reached it polls
+//! `getAccountInfo` instead, reporting a change whenever an account's lamports,
+//! owner or data differ from the previous poll, and tries the websocket again
+//! after every poll round.
+
+use crate::solana::SolanaRpcClient;
+use crate::types::AccountData;
+use crate::utils::sha256_hash;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Something that happened to a watched account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Changed { account: String, slot: u64 },
+}
+
+/// Why the websocket subscription ended
+enum SubscriptionEnd {
+    /// Nobody listens to the events any more
+    Closed,
+    /// The websocket could not be reached
+    Unavailable(String),
+    /// The websocket failed after subscribing
+    Dropped(String),
+}
+
+/// Report changes of `accounts` to `events` until the receiver is dropped
+pub async fn watch_accounts(
+    ws_url: String,
+    rpc: SolanaRpcClient,
+    accounts: Vec<String>,
+    poll_interval: Duration,
+    events: mpsc::Sender<WatchEvent>,
+) {
+    let mut fingerprints = HashMap::new();
+    let mut polling = false;
+    loop {
+        match subscribe(&ws_url, &accounts, &events).await {
+            SubscriptionEnd::Closed => return,
+            SubscriptionEnd::Unavailable(e) if !polling => {
+                eprintln!("Warning: account websocket unavailable ({}), polling every {:?}", e, poll_interval);
+            }
+            SubscriptionEnd::Unavailable(_) => {}
+            SubscriptionEnd::Dropped(e) => {
+                eprintln!("Warning: account websocket dropped ({}), polling every {:?}", e, poll_interval);
+            }
+        }
+        polling = true;
+
+        tokio::time::sleep(poll_interval).await;
+        for account in &accounts {
+            let (info, slot) = match rpc.fetch_account_info(account, None).await {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("Warning: failed to poll {}: {}", account, e);
+                    continue;
+                }
+            };
+            let mut fingerprint = match info.data {
+                AccountData::Encoded(data, encoding) => data + &encoding,
+                AccountData::Other(value) => value.to_string(),
+            }
+            .into_bytes();
+            fingerprint.extend_from_slice(info.owner.as_bytes());
+            fingerprint.extend_from_slice(&info.lamports.to_le_bytes());
+            let fingerprint = sha256_hash(&fingerprint);
+            let previous = fingerprints.insert(account.clone(), fingerprint);
+            let changed = previous.is_some_and(|previous| previous != fingerprint);
+            if changed && events.send(WatchEvent::Changed { account: account.clone(), slot }).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Forward account notifications until the websocket fails or the receiver is dropped
+async fn subscribe(ws_url: &str, accounts: &[String], events: &mpsc::Sender<WatchEvent>) -> SubscriptionEnd {
+    let (mut socket, _) = match connect_async(ws_url).await {
+        Ok(connection) => connection,
+        Err(e) => return SubscriptionEnd::Unavailable(e.to_string()),
+    };
+    for (id, account) in accounts.iter().enumerate() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "accountSubscribe",
+            "params": [account, { "encoding": "base64", "commitment": "confirmed" }],
+        });
+        if let Err(e) = socket.send(Message::Text(request.to_string())).await {
+            return SubscriptionEnd::Unavailable(e.to_string());
+        }
+    }
+    eprintln!("Subscribed to {} account(s) at {}", accounts.len(), ws_url);
+
+    // Subscription id -> account
+    let mut subscriptions = HashMap::new();
+    while let Some(message) = socket.next().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => return SubscriptionEnd::Dropped(e.to_string()),
+        };
+        let Ok(value) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if let Some(error) = value.get("error") {
+            return SubscriptionEnd::Unavailable(format!("accountSubscribe failed: {}", error));
+        }
+        let request_id = value["id"].as_u64().and_then(|id| accounts.get(id as usize));
+        if let (Some(account), Some(subscription)) = (request_id, value["result"].as_u64()) {
+            subscriptions.insert(subscription, account.clone());
+            continue;
+        }
+        if value["method"] != "accountNotification" {
+            continue;
+        }
+        let params = &value["params"];
+        let account = params["subscription"].as_u64().and_then(|subscription| subscriptions.get(&subscription));
+        if let (Some(account), Some(slot)) = (account, params["result"]["context"]["slot"].as_u64()) {
+            let event = WatchEvent::Changed { account: account.clone(), slot };
+            if events.send(event).await.is_err() {
+                return SubscriptionEnd::Closed;
+            }
+        }
+    }
+    SubscriptionEnd::Dropped("websocket closed".to_string())
+}
@@ -0,0 +1,332 @@
+//! Listing, creating and deleting ACLs
+//!
+//! rdkafka's safe admin API has no ACL operations, so these call librdkafka's
+//! CreateAcls, DescribeAcls and DeleteAcls directly on the admin client's
+//! handle through [`crate::native_admin`]. librdkafka copies the bindings it
+//! is given, so they are freed as soon as the request is queued.
+
+use crate::native_admin::{check_error, from_c, run_request};
+use rdkafka::admin::AdminClient;
+use rdkafka::client::ClientContext;
+use rdkafka_sys::*;
+use serde::Serialize;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::c_char;
+use std::ptr;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Kind of resource an ACL applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclResourceType {
+    Topic,
+    Group,
+}
+
+impl AclResourceType {
+    fn native(self) -> rd_kafka_ResourceType_t {
+        match self {
+            AclResourceType::Topic => rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_TOPIC,
+            AclResourceType::Group => rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_GROUP,
+        }
+    }
+}
+
+impl FromStr for AclResourceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "topic" => Ok(AclResourceType::Topic),
+            "group" => Ok(AclResourceType::Group),
+            other => Err(format!("Unknown resource type '{}': expected topic or group", other)),
+        }
+    }
+}
+
+impl fmt::Display for AclResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AclResourceType::Topic => write!(f, "topic"),
+            AclResourceType::Group => write!(f, "group"),
+        }
+    }
+}
+
+/// Operation an ACL allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclOperation {
+    Read,
+    Write,
+    Describe,
+}
+
+impl AclOperation {
+    fn native(self) -> rd_kafka_AclOperation_t {
+        match self {
+            AclOperation::Read => rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_READ,
+            AclOperation::Write => rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_WRITE,
+            AclOperation::Describe => rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_DESCRIBE,
+        }
+    }
+}
+
+impl FromStr for AclOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Ok(AclOperation::Read),
+            "write" => Ok(AclOperation::Write),
+            "describe" => Ok(AclOperation::Describe),
+            other => Err(format!("Unknown operation '{}': expected read, write or describe", other)),
+        }
+    }
+}
+
+impl fmt::Display for AclOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AclOperation::Read => write!(f, "read"),
+            AclOperation::Write => write!(f, "write"),
+            AclOperation::Describe => write!(f, "describe"),
+        }
+    }
+}
+
+/// Resource name of an ACL: one resource, or every resource starting with a prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceName {
+    Literal(String),
+    Prefixed(String),
+}
+
+impl ResourceName {
+    fn parts(&self) -> (&str, rd_kafka_ResourcePatternType_t) {
+        match self {
+            ResourceName::Literal(name) => (name, rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_LITERAL),
+            ResourceName::Prefixed(prefix) => {
+                (prefix, rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_PREFIXED)
+            }
+        }
+    }
+}
+
+/// An ACL allowing a principal an operation on a resource
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclRule {
+    pub resource_type: AclResourceType,
+    pub resource: ResourceName,
+    /// e.g. `User:alice`
+    pub principal: String,
+    /// `*` for any host
+    pub host: String,
+    pub operation: AclOperation,
+}
+
+impl AclRule {
+    /// The ACL as the cluster reports it once created
+    pub fn entry(&self) -> AclEntry {
+        let (name, pattern_type) = match &self.resource {
+            ResourceName::Literal(name) => (name, "LITERAL"),
+            ResourceName::Prefixed(prefix) => (prefix, "PREFIXED"),
+        };
+        AclEntry {
+            resource_type: self.resource_type.to_string().to_ascii_uppercase(),
+            resource_name: name.clone(),
+            pattern_type: pattern_type.to_string(),
+            principal: self.principal.clone(),
+            host: self.host.clone(),
+            operation: self.operation.to_string().to_ascii_uppercase(),
+            permission: "ALLOW".to_string(),
+        }
+    }
+}
+
+/// ACLs to list or delete; unset fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AclFilter {
+    pub resource_type: Option<AclResourceType>,
+    pub resource: Option<ResourceName>,
+    pub principal: Option<String>,
+    pub host: Option<String>,
+    pub operation: Option<AclOperation>,
+}
+
+/// An ACL as the cluster reports it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AclEntry {
+    /// `TOPIC`, `GROUP`, `BROKER`, ...
+    pub resource_type: String,
+    pub resource_name: String,
+    /// `LITERAL` or `PREFIXED`
+    pub pattern_type: String,
+    pub principal: String,
+    pub host: String,
+    /// `READ`, `WRITE`, `DESCRIBE`, `ALL`, ...
+    pub operation: String,
+    /// `ALLOW` or `DENY`
+    pub permission: String,
+}
+
+/// List the ACLs matching `filter`
+pub fn list_acls<C: ClientContext>(
+    admin: &AdminClient<C>,
+    filter: &AclFilter,
+    timeout: Duration,
+) -> Result<Vec<AclEntry>, String> {
+    let native = new_filter(filter)?;
+    let op = rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_DESCRIBEACLS;
+    let event = run_request(admin, op, timeout, |rk, options, queue| unsafe {
+        rd_kafka_DescribeAcls(rk, native.0, options, queue)
+    })?;
+    unsafe {
+        let result = rd_kafka_event_DescribeAcls_result(event.0);
+        if result.is_null() {
+            return Err("unexpected result for DescribeAcls".to_string());
+        }
+        let mut count = 0;
+        let acls = rd_kafka_DescribeAcls_result_acls(result, &mut count);
+        Ok((0..count).map(|i| read_entry(*acls.add(i))).collect())
+    }
+}
+
+/// Create the ACLs, allowing each rule's operation
+pub fn create_acls<C: ClientContext>(admin: &AdminClient<C>, rules: &[AclRule], timeout: Duration) -> Result<(), String> {
+    let bindings = rules.iter().map(new_binding).collect::<Result<Vec<_>, _>>()?;
+    let mut pointers: Vec<*mut rd_kafka_AclBinding_t> = bindings.iter().map(|binding| binding.0).collect();
+    let op = rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_CREATEACLS;
+    let event = run_request(admin, op, timeout, |rk, options, queue| unsafe {
+        rd_kafka_CreateAcls(rk, pointers.as_mut_ptr(), pointers.len(), options, queue)
+    })?;
+    unsafe {
+        let result = rd_kafka_event_CreateAcls_result(event.0);
+        if result.is_null() {
+            return Err("unexpected result for CreateAcls".to_string());
+        }
+        let mut count = 0;
+        let results = rd_kafka_CreateAcls_result_acls(result, &mut count);
+        for i in 0..count {
+            check_error(rd_kafka_acl_result_error(*results.add(i)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete the ACLs matching `filter`, returning the deleted ACLs
+pub fn delete_acls<C: ClientContext>(
+    admin: &AdminClient<C>,
+    filter: &AclFilter,
+    timeout: Duration,
+) -> Result<Vec<AclEntry>, String> {
+    let native = new_filter(filter)?;
+    let mut pointers = [native.0];
+    let op = rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_DELETEACLS;
+    let event = run_request(admin, op, timeout, |rk, options, queue| unsafe {
+        rd_kafka_DeleteAcls(rk, pointers.as_mut_ptr(), pointers.len(), options, queue)
+    })?;
+    unsafe {
+        let result = rd_kafka_event_DeleteAcls_result(event.0);
+        if result.is_null() {
+            return Err("unexpected result for DeleteAcls".to_string());
+        }
+        let mut deleted = Vec::new();
+        let mut count = 0;
+        let responses = rd_kafka_DeleteAcls_result_responses(result, &mut count);
+        for i in 0..count {
+            let response = *responses.add(i);
+            check_error(rd_kafka_DeleteAcls_result_response_error(response))?;
+            let mut matching = 0;
+            let acls = rd_kafka_DeleteAcls_result_response_matching_acls(response, &mut matching);
+            deleted.extend((0..matching).map(|j| read_entry(*acls.add(j))));
+        }
+        Ok(deleted)
+    }
+}
+
+/// # Safety
+/// `acl` must point to a binding owned by a live result event.
+unsafe fn read_entry(acl: *const rd_kafka_AclBinding_t) -> AclEntry {
+    AclEntry {
+        resource_type: from_c(rd_kafka_ResourceType_name(rd_kafka_AclBinding_restype(acl))),
+        resource_name: from_c(rd_kafka_AclBinding_name(acl)),
+        pattern_type: from_c(rd_kafka_ResourcePatternType_name(rd_kafka_AclBinding_resource_pattern_type(acl))),
+        principal: from_c(rd_kafka_AclBinding_principal(acl)),
+        host: from_c(rd_kafka_AclBinding_host(acl)),
+        operation: from_c(rd_kafka_AclOperation_name(rd_kafka_AclBinding_operation(acl))),
+        permission: from_c(rd_kafka_AclPermissionType_name(rd_kafka_AclBinding_permission_type(acl))),
+    }
+}
+
+fn c_string(field: &str, value: &str) -> Result<CString, String> {
+    CString::new(value).map_err(|_| format!("The {} must not contain NUL bytes", field))
+}
+
+fn c_ptr(value: &Option<CString>) -> *const c_char {
+    value.as_ref().map_or(ptr::null(), |value| value.as_ptr())
+}
+
+/// A binding or binding filter, destroyed on drop
+struct Binding(*mut rd_kafka_AclBinding_t);
+
+impl Drop for Binding {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_AclBinding_destroy(self.0) }
+    }
+}
+
+fn new_binding(rule: &AclRule) -> Result<Binding, String> {
+    let (name, pattern_type) = rule.resource.parts();
+    let name = c_string("resource name", name)?;
+    let principal = c_string("principal", &rule.principal)?;
+    let host = c_string("host", &rule.host)?;
+    let mut errstr = [0 as c_char; 512];
+    let binding = unsafe {
+        rd_kafka_AclBinding_new(
+            rule.resource_type.native(),
+            name.as_ptr(),
+            pattern_type,
+            principal.as_ptr(),
+            host.as_ptr(),
+            rule.operation.native(),
+            rd_kafka_AclPermissionType_t::RD_KAFKA_ACL_PERMISSION_TYPE_ALLOW,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        )
+    };
+    if binding.is_null() {
+        return Err(unsafe { from_c(errstr.as_ptr()) });
+    }
+    Ok(Binding(binding))
+}
+
+fn new_filter(filter: &AclFilter) -> Result<Binding, String> {
+    let (name, pattern_type) = match &filter.resource {
+        Some(resource) => {
+            let (name, pattern_type) = resource.parts();
+            (Some(c_string("resource name", name)?), pattern_type)
+        }
+        None => (None, rd_kafka_ResourcePatternType_t::RD_KAFKA_RESOURCE_PATTERN_ANY),
+    };
+    let principal = filter.principal.as_deref().map(|p| c_string("principal", p)).transpose()?;
+    let host = filter.host.as_deref().map(|h| c_string("host", h)).transpose()?;
+    let mut errstr = [0 as c_char; 512];
+    let binding = unsafe {
+        rd_kafka_AclBindingFilter_new(
+            filter.resource_type.map_or(rd_kafka_ResourceType_t::RD_KAFKA_RESOURCE_ANY, AclResourceType::native),
+            c_ptr(&name),
+            pattern_type,
+            c_ptr(&principal),
+            c_ptr(&host),
+            filter.operation.map_or(rd_kafka_AclOperation_t::RD_KAFKA_ACL_OPERATION_ANY, AclOperation::native),
+            rd_kafka_AclPermissionType_t::RD_KAFKA_ACL_PERMISSION_TYPE_ANY,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        )
+    };
+    if binding.is_null() {
+        return Err(unsafe { from_c(errstr.as_ptr()) });
+    }
+    Ok(Binding(binding))
+}
@@ -0,0 +1,242 @@
+//! JSON documents printed by `kafka_admin --json`
+//!
+//! Every subcommand prints exactly one document on stdout, except `tail
+//! --follow` and `monitor`, which print one message or sample per line.
+//! Failures print an [`ErrorOutput`] on stderr and exit with a non-zero code.
+
+use crate::acls::AclEntry;
+use crate::offset_reset::OffsetTarget;
+use crate::purge::{PurgeTarget, PurgedPartition};
+use crate::tail::MessageSummary;
+use crate::topic_profile::ConfigMismatch;
+use rdkafka::metadata::{Metadata, MetadataTopic};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A failure, printed on stderr
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorOutput {
+    /// What failed, e.g. `Failed to fetch metadata`
+    pub error: String,
+    /// The underlying error
+    pub detail: String,
+}
+
+/// Topics in the cluster (`list`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopicList {
+    pub topics: Vec<String>,
+}
+
+impl TopicList {
+    pub fn new(metadata: &Metadata) -> Self {
+        Self { topics: metadata.topics().iter().map(|topic| topic.name().to_string()).collect() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokerInfo {
+    pub id: i32,
+    pub host: String,
+    pub port: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartitionInfo {
+    pub id: i32,
+    pub leader: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopicInfo {
+    pub name: String,
+    pub partitions: Vec<PartitionInfo>,
+}
+
+impl TopicInfo {
+    pub fn new(topic: &MetadataTopic) -> Self {
+        Self {
+            name: topic.name().to_string(),
+            partitions: topic
+                .partitions()
+                .iter()
+                .map(|partition| PartitionInfo {
+                    id: partition.id(),
+                    leader: partition.leader(),
+                    replicas: partition.replicas().to_vec(),
+                    isr: partition.isr().to_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What became ready and how long it took (`wait`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WaitOutput {
+    pub broker_ready: bool,
+    pub topic: Option<String>,
+    pub topic_ready: bool,
+    /// Whether `--create-if-missing` created the topic
+    pub created: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Brokers and topics (`metadata`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClusterMetadata {
+    pub brokers: Vec<BrokerInfo>,
+    pub topics: Vec<TopicInfo>,
+}
+
+impl ClusterMetadata {
+    /// Brokers and the topics named `topic_filter`, or all topics
+    pub fn new(metadata: &Metadata, topic_filter: Option<&str>) -> Self {
+        Self {
+            brokers: metadata
+                .brokers()
+                .iter()
+                .map(|broker| BrokerInfo { id: broker.id(), host: broker.host().to_string(), port: broker.port() })
+                .collect(),
+            topics: metadata
+                .topics()
+                .iter()
+                .filter(|topic| topic_filter.is_none_or(|filter| topic.name() == filter))
+                .map(TopicInfo::new)
+                .collect(),
+        }
+    }
+}
+
+/// Whether a topic exists, with its partitions if it does (`check`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopicCheck {
+    pub topic: String,
+    pub exists: bool,
+    pub details: Option<TopicInfo>,
+}
+
+/// Outcome of creating or deleting one topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicStatus {
+    Created,
+    AlreadyExists,
+    Deleted,
+    NotFound,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopicResult {
+    pub topic: String,
+    pub status: TopicStatus,
+    pub error: Option<String>,
+}
+
+/// The requested topic and the outcome of creating it (`create`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CreateOutput {
+    pub topic: String,
+    pub partitions: i32,
+    pub replication_factor: i32,
+    pub profile: String,
+    pub configs: BTreeMap<String, String>,
+    pub result: TopicResult,
+    /// Requested configs the broker did not apply
+    pub config_mismatches: Vec<ConfigMismatch>,
+    /// Why the configs could not be verified after creation
+    pub verify_error: Option<String>,
+}
+
+/// Topics selected for deletion and the outcome per topic (`delete`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeleteOutput {
+    pub topics: Vec<String>,
+    pub dry_run: bool,
+    /// Empty on a dry run
+    pub results: Vec<TopicResult>,
+}
+
+/// The last messages on a topic (`tail` without `--follow`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TailOutput {
+    pub topic: String,
+    pub messages: Vec<MessageSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupSummary {
+    pub name: String,
+    pub state: String,
+    pub members: usize,
+}
+
+/// Consumer groups in the cluster (`groups list`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupListing {
+    pub groups: Vec<GroupSummary>,
+}
+
+/// Outcome of deleting one consumer group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupStatus {
+    Deleted,
+    NotFound,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupDeleteResult {
+    pub group: String,
+    pub status: GroupStatus,
+    pub error: Option<String>,
+}
+
+/// Groups selected for deletion and the outcome per group (`groups delete`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupDeleteOutput {
+    pub groups: Vec<String>,
+    pub dry_run: bool,
+    /// Empty on a dry run
+    pub results: Vec<GroupDeleteResult>,
+}
+
+/// Target offsets of a reset and whether they were committed (`reset-offsets`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResetOutput {
+    pub group: String,
+    pub topic: String,
+    /// `earliest`, `latest`, `offset N` or `timestamp N`, if a strategy was given
+    pub strategy: Option<String>,
+    pub dry_run: bool,
+    pub committed: bool,
+    pub targets: Vec<OffsetTarget>,
+}
+
+/// Cut-off per partition and the log start offsets after the purge (`purge`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeOutput {
+    pub topic: String,
+    pub dry_run: bool,
+    pub targets: Vec<PurgeTarget>,
+    /// Empty on a dry run
+    pub results: Vec<PurgedPartition>,
+}
+
+/// ACLs listed or created (`acls list`, `acls create`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AclList {
+    pub acls: Vec<AclEntry>,
+}
+
+/// ACLs matching a delete filter and whether they were deleted (`acls delete`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AclDeleteOutput {
+    pub dry_run: bool,
+    pub deleted: bool,
+    pub acls: Vec<AclEntry>,
+}
@@ -0,0 +1,103 @@
+//! Checking a batch of proofs before aggregating it
+//!
+//! Backfills produce many proofs of small ranges, and downstream wants one
+//! proof per epoch. Before the expensive aggregation runs, every proof of the
+//! batch must be a compressed proof (SP1 only verifies compressed proofs
+//! recursively) of a range that passed its validations, and must verify
+//! against the program's key; a proof that is itself an aggregate must commit
+//! that key's digest too, which [`ProofVerifier`] checks. Sorted by start slot,
+//! the proofs must then link up as in [`crate::continuity`]. The first proof
+//! that fails fails the whole batch, reported with its identifier.
+
+use crate::proof_filter::decode_commitments;
+use crate::verify::{ProofVerifier, VerifyStatus};
+use crate::wrapped_proof::WrappedProof;
+use solana_stub_prover_lib::PublicCommitments;
+use sp1_sdk::SP1ProofWithPublicValues;
+use std::error::Error;
+use std::fmt;
+use twine_types::proofs::{ProofData, ZkProof};
+
+/// A checked proof of a batch
+pub struct BatchMember {
+    pub identifier: String,
+    pub commitments: PublicCommitments,
+    pub proof: SP1ProofWithPublicValues,
+}
+
+/// Why a batch cannot be aggregated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchError {
+    /// The batch holds no proofs
+    Empty,
+    /// A proof failed a check of its own
+    Invalid { identifier: String, reason: String },
+    /// A proof does not start at the slot the previous one ended at
+    Gap { identifier: String, previous: String, expected_start: u64, start_slot: u64 },
+    /// A proof starts at the right slot but not from the previous proof's last bank hash
+    Fork { identifier: String, previous: String },
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Empty => write!(f, "No proofs to aggregate"),
+            BatchError::Invalid { identifier, reason } => write!(f, "{}: {}", identifier, reason),
+            BatchError::Gap { identifier, previous, expected_start, start_slot } => write!(
+                f,
+                "{}: starts at slot {}, but {} ends at slot {}",
+                identifier, start_slot, previous, expected_start
+            ),
+            BatchError::Fork { identifier, previous } => {
+                write!(f, "{}: original bank hash is not the last bank hash of {}", identifier, previous)
+            }
+        }
+    }
+}
+
+impl Error for BatchError {}
+
+/// Check one proof of a batch
+pub fn check_member(proof: &ZkProof, verifier: &ProofVerifier) -> Result<BatchMember, BatchError> {
+    let invalid = |reason: String| BatchError::Invalid { identifier: proof.identifier.clone(), reason };
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    let wrapped = WrappedProof::decode(&sp1_proof.proof).map_err(invalid)?;
+    if wrapped.kind() != "compressed" {
+        return Err(invalid(format!("{} proof, only compressed proofs can be aggregated", wrapped.kind())));
+    }
+    let commitments =
+        decode_commitments(proof).ok_or_else(|| invalid("public values are not PublicCommitments".to_string()))?;
+    if !commitments.validations_passed {
+        return Err(invalid("the program reported failed validations".to_string()));
+    }
+    if let VerifyStatus::Failed(reason) = verifier.verify(proof).status {
+        return Err(invalid(format!("verification failed: {}", reason)));
+    }
+    Ok(BatchMember { identifier: proof.identifier.clone(), commitments, proof: wrapped.bundle })
+}
+
+/// Sort a batch by start slot and check that each proof continues the previous one
+pub fn order_batch(mut members: Vec<BatchMember>) -> Result<Vec<BatchMember>, BatchError> {
+    if members.is_empty() {
+        return Err(BatchError::Empty);
+    }
+    members.sort_by_key(|member| (member.commitments.start_slot, member.commitments.end_slot));
+    for pair in members.windows(2) {
+        let (previous, next) = (&pair[0], &pair[1]);
+        if next.commitments.start_slot != previous.commitments.end_slot {
+            return Err(BatchError::Gap {
+                identifier: next.identifier.clone(),
+                previous: previous.identifier.clone(),
+                expected_start: previous.commitments.end_slot,
+                start_slot: next.commitments.start_slot,
+            });
+        }
+        if next.commitments.original_bank_hash != previous.commitments.last_bank_hash {
+            return Err(BatchError::Fork {
+                identifier: next.identifier.clone(),
+                previous: previous.identifier.clone(),
+            });
+        }
+    }
+    Ok(members)
+}
@@ -0,0 +1,187 @@
+//! Client of the HTTP API served by the `server` binary
+//!
+//! Every request sends the bearer token. Error responses are `{"error": "..."}`
+//! objects and are mapped to [`ApiError`] by status, so callers can tell a
+//! rejected token from a missing proof or job. The server URL and token can
+//! also come from a TOML config file (see [`ClientConfig`]).
+
+use crate::prove_jobs::{Job, ProveRequest};
+use reqwest::{RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// URL the server listens on by default
+pub const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:8080";
+
+/// Why a request failed
+#[derive(Debug)]
+pub enum ApiError {
+    /// 401: the token is missing or wrong
+    Unauthorized(String),
+    /// 404: no such proof, job or endpoint
+    NotFound(String),
+    /// Any other error status
+    Status { status: StatusCode, message: String },
+    /// The request did not complete
+    Transport(reqwest::Error),
+    /// The response is not what the endpoint returns
+    Decode(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unauthorized(message) => write!(f, "Unauthorized: {}", message),
+            ApiError::NotFound(message) => write!(f, "Not found: {}", message),
+            ApiError::Status { status, message } => write!(f, "Server error ({}): {}", status, message),
+            ApiError::Transport(e) => write!(f, "Request failed: {}", e),
+            ApiError::Decode(reason) => write!(f, "Unexpected response: {}", reason),
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiError::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// A stored proof as the server returns it, without its verifying key
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProofRecord {
+    pub identifier: String,
+    pub topic: String,
+    pub partition: i64,
+    pub offset: i64,
+    pub proof_kind: String,
+    pub start_slot: Option<i64>,
+    pub end_slot: Option<i64>,
+    pub epoch: Option<i64>,
+    pub last_bank_hash: Option<String>,
+    pub validations_passed: Option<i64>,
+    pub received_at: String,
+    pub accounts: Vec<AccountRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AccountRecord {
+    pub account_pubkey: String,
+    pub last_change_slot: i64,
+    pub lamports: i64,
+    pub owner: String,
+    pub data_len: i64,
+}
+
+/// Server URL and token read from a config file
+///
+/// ```toml
+/// url = "https://prover.example.org"
+/// token = "..."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    pub url: Option<String>,
+    pub token: Option<String>,
+}
+
+impl ClientConfig {
+    /// Read a config file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// `$XDG_CONFIG_HOME/stubctl/config.toml`, or `~/.config/stubctl/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("stubctl").join("config.toml"))
+    }
+}
+
+/// Authenticated client of one server
+pub struct ApiClient {
+    base_url: Url,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str, token: &str) -> Result<Self, String> {
+        let base_url = Url::parse(base_url).map_err(|e| format!("Invalid server URL '{}': {}", base_url, e))?;
+        if base_url.cannot_be_a_base() {
+            return Err(format!("Invalid server URL '{}'", base_url));
+        }
+        Ok(Self { base_url, token: token.trim().to_string(), http: reqwest::Client::new() })
+    }
+
+    /// URL of `segments` under the base URL, each segment percent-encoded
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().expect("the base URL can be a base").pop_if_empty().extend(segments);
+        url
+    }
+
+    /// Send a request and decode the response body, or the error body of a failed request
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ApiError> {
+        let response = request.bearer_auth(&self.token).send().await.map_err(ApiError::Transport)?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(ApiError::Transport)?;
+        if !status.is_success() {
+            let message = match serde_json::from_slice::<ErrorBody>(&body) {
+                Ok(error) => error.error,
+                Err(_) => String::from_utf8_lossy(&body).trim().to_string(),
+            };
+            return Err(match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Unauthorized(message),
+                StatusCode::NOT_FOUND => ApiError::NotFound(message),
+                status => ApiError::Status { status, message },
+            });
+        }
+        serde_json::from_slice(&body).map_err(|e| ApiError::Decode(e.to_string()))
+    }
+
+    /// `GET /proofs/latest`, optionally among proofs committing to `account`
+    pub async fn latest<T: DeserializeOwned>(&self, account: Option<&str>) -> Result<T, ApiError> {
+        let mut request = self.http.get(self.url(&["proofs", "latest"]));
+        if let Some(account) = account {
+            request = request.query(&[("account", account)]);
+        }
+        self.send(request).await
+    }
+
+    /// `GET /proofs/{identifier}`
+    pub async fn proof<T: DeserializeOwned>(&self, identifier: &str) -> Result<T, ApiError> {
+        self.send(self.http.get(self.url(&["proofs", identifier]))).await
+    }
+
+    /// `POST /prove`
+    pub async fn submit(&self, request: &ProveRequest) -> Result<Job, ApiError> {
+        self.send(self.http.post(self.url(&["prove"])).json(request)).await
+    }
+
+    /// `GET /jobs/{id}`
+    pub async fn job(&self, id: u64) -> Result<Job, ApiError> {
+        self.send(self.http.get(self.url(&["jobs", &id.to_string()]))).await
+    }
+
+    /// `GET /jobs`, oldest first
+    pub async fn jobs(&self) -> Result<Vec<Job>, ApiError> {
+        self.send(self.http.get(self.url(&["jobs"]))).await
+    }
+}
@@ -0,0 +1,175 @@
+//! Archive of consumed proofs on local disk
+//!
+//! Every saved message produces two files named by identifier, partition and
+//! offset: the raw payload (`.bin`) and, when the public values decode, a
+//! `.commitments.json` side file. Files are written to a temporary name and
+//! renamed into place, so a crash never leaves a partial file behind.
+
+use solana_stub_prover_lib::PublicCommitments;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files written for one archived message
+#[derive(Debug, Clone)]
+pub struct ArchivedMessage {
+    pub payload_path: PathBuf,
+    pub commitments_path: Option<PathBuf>,
+    pub bytes: u64,
+}
+
+/// Result of trying to archive a message
+#[derive(Debug, Clone)]
+pub enum ArchiveOutcome {
+    Saved(ArchivedMessage),
+    /// The size cap was reached; the message was not written
+    CapReached { size: u64, limit: u64 },
+}
+
+/// Error writing to the archive directory
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(e) => write!(f, "Archive I/O error: {}", e),
+            ArchiveError::Json(e) => write!(f, "Archive JSON error: {}", e),
+        }
+    }
+}
+
+impl Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArchiveError::Io(e) => Some(e),
+            ArchiveError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(e: std::io::Error) -> Self {
+        ArchiveError::Io(e)
+    }
+}
+
+/// Directory proofs are archived to, with an optional size cap
+#[derive(Debug)]
+pub struct ProofArchive {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    size: u64,
+}
+
+impl ProofArchive {
+    /// Open (creating if needed) an archive directory
+    ///
+    /// Files already in the directory count towards the size cap.
+    pub fn open(dir: PathBuf, max_bytes: Option<u64>) -> Result<Self, ArchiveError> {
+        fs::create_dir_all(&dir)?;
+
+        let mut size = 0;
+        for entry in fs::read_dir(&dir)? {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                size += metadata.len();
+            }
+        }
+        Ok(Self { dir, max_bytes, size })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Total size of the archive in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Whether the size cap has been reached
+    pub fn is_full(&self) -> bool {
+        self.max_bytes.is_some_and(|limit| self.size >= limit)
+    }
+
+    /// Save a message's raw payload and, if given, its decoded commitments
+    pub fn save(
+        &mut self,
+        identifier: &str,
+        partition: i32,
+        offset: i64,
+        payload: &[u8],
+        commitments: Option<&PublicCommitments>,
+    ) -> Result<ArchiveOutcome, ArchiveError> {
+        let commitments_json = match commitments {
+            Some(commitments) => Some(serde_json::to_vec_pretty(commitments).map_err(ArchiveError::Json)?),
+            None => None,
+        };
+
+        let bytes = payload.len() as u64 + commitments_json.as_ref().map_or(0, |json| json.len() as u64);
+        if let Some(limit) = self.max_bytes {
+            if self.size + bytes > limit {
+                return Ok(ArchiveOutcome::CapReached { size: self.size + bytes, limit });
+            }
+        }
+
+        let stem = self.unique_stem(identifier, partition, offset);
+        let payload_path = self.dir.join(format!("{}.bin", stem));
+        write_atomic(&payload_path, payload)?;
+
+        let commitments_path = match commitments_json {
+            Some(json) => {
+                let path = self.dir.join(format!("{}.commitments.json", stem));
+                write_atomic(&path, &json)?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        self.size += bytes;
+        Ok(ArchiveOutcome::Saved(ArchivedMessage { payload_path, commitments_path, bytes }))
+    }
+
+    /// File name stem that no existing file uses, suffixed with a counter on collision
+    fn unique_stem(&self, identifier: &str, partition: i32, offset: i64) -> String {
+        let base = format!("{}-p{}-o{}", sanitize(identifier), partition, offset);
+        let taken = |stem: &str| self.dir.join(format!("{}.bin", stem)).exists();
+
+        if !taken(&base) {
+            return base;
+        }
+        (1..)
+            .map(|n| format!("{}-{}", base, n))
+            .find(|stem| !taken(stem))
+            .expect("unbounded counter always finds a free name")
+    }
+}
+
+/// Replace characters that are not safe in file names
+fn sanitize(identifier: &str) -> String {
+    let safe: String = identifier
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if safe.is_empty() {
+        "unknown".to_string()
+    } else {
+        safe
+    }
+}
+
+/// Write a file via a temporary name and rename it into place
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), ArchiveError> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
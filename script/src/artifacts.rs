@@ -0,0 +1,93 @@
+//! Streaming JSON output of large proof artifacts
+//!
+//! A compressed proof serializes to 50–200 MB of JSON. Building that as a
+//! string before writing it holds the whole document in memory next to the
+//! proof, so [`write_json`] serializes straight into a buffered file instead,
+//! optionally through a gzip encoder, and [`json_len`] measures a document
+//! without keeping it.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writer passing bytes through to `inner` and counting them
+pub struct CountingWriter<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+
+    /// Bytes written so far
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Size of `value` as compact JSON, without building the document
+pub fn json_len<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<u64> {
+    let mut counter = CountingWriter::new(io::sink());
+    serde_json::to_writer(&mut counter, value)?;
+    Ok(counter.bytes())
+}
+
+/// Write `value` to `path` as pretty JSON, gzip-compressed if `gzip`
+///
+/// The file is written to a temporary path next to `path` and renamed into
+/// place, so readers never see half a document. Returns the size of the JSON
+/// before compression.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T, gzip: bool) -> io::Result<u64> {
+    let tmp_path = path.with_extension("tmp");
+    let file = BufWriter::new(File::create(&tmp_path)?);
+    let bytes = if gzip {
+        let mut writer = CountingWriter::new(GzEncoder::new(file, Compression::default()));
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        let bytes = writer.bytes();
+        finish(writer.into_inner().finish()?)?;
+        bytes
+    } else {
+        let mut writer = CountingWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        let bytes = writer.bytes();
+        finish(writer.into_inner())?;
+        bytes
+    };
+    fs::rename(&tmp_path, path)?;
+    Ok(bytes)
+}
+
+/// Flush a buffered file and sync it to disk
+fn finish(file: BufWriter<File>) -> io::Result<()> {
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// `name`, with `.gz` appended if `gzip`
+pub fn artifact_file_name(name: &str, gzip: bool) -> String {
+    if gzip {
+        format!("{}.gz", name)
+    } else {
+        name.to_string()
+    }
+}
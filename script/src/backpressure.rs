@@ -0,0 +1,152 @@
+//! Pausing consumption while the sinks fall behind
+//!
+//! Messages handed to the partition workers wait in their queues until the
+//! slow sinks (SQLite, webhooks, verification) get to them. Once the number of
+//! queued messages reaches the high-water mark the consumer pauses its
+//! partitions, so it keeps serving the group without fetching more, and
+//! resumes once the queues drained to the low-water mark. Pausing keeps each
+//! partition's position, so no message is skipped or reordered.
+
+use std::time::{Duration, Instant};
+
+/// Default number of queued messages at which consumption pauses
+pub const DEFAULT_PAUSE_HIGH_WATER: usize = 32;
+/// Default number of queued messages at which paused consumption resumes
+pub const DEFAULT_PAUSE_LOW_WATER: usize = 8;
+
+/// Change of the pause state after a queue depth update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Pause,
+    /// Resume after being paused for this long
+    Resume(Duration),
+}
+
+/// Hysteresis between the high- and low-water marks
+#[derive(Debug)]
+pub struct Backpressure {
+    high_water: usize,
+    low_water: usize,
+    paused_since: Option<Instant>,
+    pauses: u64,
+    paused_total: Duration,
+}
+
+impl Backpressure {
+    /// Pause at `high_water` queued messages and resume at `low_water`
+    pub fn new(high_water: usize, low_water: usize) -> Result<Self, String> {
+        if high_water == 0 {
+            return Err("The pause high-water mark must be at least 1".to_string());
+        }
+        if low_water >= high_water {
+            return Err(format!(
+                "The pause low-water mark ({}) must be below the high-water mark ({})",
+                low_water, high_water
+            ));
+        }
+        Ok(Self { high_water, low_water, paused_since: None, pauses: 0, paused_total: Duration::ZERO })
+    }
+
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    pub fn low_water(&self) -> usize {
+        self.low_water
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    /// Number of times consumption was paused
+    pub fn pauses(&self) -> u64 {
+        self.pauses
+    }
+
+    /// Time spent paused, including the current pause
+    pub fn paused_total(&self) -> Duration {
+        self.paused_total + self.paused_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// Feed the current queue depth, returning whether to pause or resume
+    pub fn update(&mut self, depth: usize) -> Option<Transition> {
+        match self.paused_since {
+            None if depth >= self.high_water => {
+                self.paused_since = Some(Instant::now());
+                self.pauses += 1;
+                Some(Transition::Pause)
+            }
+            Some(since) if depth <= self.low_water => {
+                let paused = since.elapsed();
+                self.paused_since = None;
+                self.paused_total += paused;
+                Some(Transition::Resume(paused))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_at_the_high_water_mark_and_resumes_at_the_low() {
+        let mut backpressure = Backpressure::new(4, 1).unwrap();
+        assert_eq!(backpressure.update(3), None);
+        assert_eq!(backpressure.update(4), Some(Transition::Pause));
+        assert!(backpressure.is_paused());
+
+        // Draining below the high-water mark is not enough
+        assert_eq!(backpressure.update(3), None);
+        assert_eq!(backpressure.update(2), None);
+        assert!(backpressure.is_paused());
+        assert!(matches!(backpressure.update(1), Some(Transition::Resume(_))));
+        assert!(!backpressure.is_paused());
+
+        // Climbing back under the high-water mark stays resumed
+        assert_eq!(backpressure.update(3), None);
+        assert_eq!(backpressure.update(9), Some(Transition::Pause));
+        assert_eq!(backpressure.pauses(), 2);
+    }
+
+    #[test]
+    fn repeated_depths_do_not_repeat_transitions() {
+        let mut backpressure = Backpressure::new(2, 0).unwrap();
+        assert_eq!(backpressure.update(0), None);
+        assert_eq!(backpressure.update(5), Some(Transition::Pause));
+        assert_eq!(backpressure.update(5), None);
+        assert!(backpressure.update(0).is_some());
+        assert_eq!(backpressure.update(0), None);
+        assert_eq!(backpressure.pauses(), 1);
+    }
+
+    #[test]
+    fn accumulates_the_time_spent_paused() {
+        let mut backpressure = Backpressure::new(2, 1).unwrap();
+        assert_eq!(backpressure.paused_total(), Duration::ZERO);
+        backpressure.update(2);
+        std::thread::sleep(Duration::from_millis(20));
+        // The current pause counts before it ends
+        assert!(backpressure.paused_total() >= Duration::from_millis(20));
+
+        let Some(Transition::Resume(paused)) = backpressure.update(0) else {
+            panic!("expected a resume");
+        };
+        assert!(paused >= Duration::from_millis(20));
+        assert_eq!(backpressure.paused_total(), paused);
+    }
+
+    #[test]
+    fn rejects_marks_without_hysteresis() {
+        assert!(Backpressure::new(0, 0).unwrap_err().contains("at least 1"));
+        for (high, low) in [(8, 8), (8, 9)] {
+            let error = Backpressure::new(high, low).unwrap_err();
+            assert!(error.contains("must be below the high-water mark"), "{}", error);
+        }
+        let backpressure = Backpressure::new(DEFAULT_PAUSE_HIGH_WATER, DEFAULT_PAUSE_LOW_WATER).unwrap();
+        assert_eq!((backpressure.high_water(), backpressure.low_water()), (32, 8));
+    }
+}
@@ -0,0 +1,218 @@
+//! Aggregate proofs of contiguous ranges into one proof of their union
+//!
+//! Reads compressed proofs from message files (or directories of them, as
+//! written by `--sink file`) or from offset windows of a topic. Every proof is
+//! verified and the batch must link up without gaps; the first proof that fails
+//! aborts the batch. The program's aggregation mode then verifies the proofs
+//! recursively and proves the union range. The aggregate is published to Kafka
+//! with an `aggregated-count` header, or written to `--sink-dir`.
+
+use clap::Parser;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::Message;
+use solana_stub_prover_script::aggregation::{check_member, order_batch};
+use solana_stub_prover_script::envelope::decode_json;
+use solana_stub_prover_script::kafka::{KafkaConfig, PayloadFormat, ProofPublisher, AGGREGATED_COUNT_HEADER};
+use solana_stub_prover_script::msk_iam::KafkaClientContext;
+use solana_stub_prover_script::pipeline::{prove_aggregate, PROVER_ELF};
+use solana_stub_prover_script::proof_sink::{DirSink, ProofSink};
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::replay::{resolve_windows, window_assignment, PartitionWindow, WindowTracker};
+use solana_stub_prover_script::verify::{ProofVerifier, VerifyingKeySource};
+use sp1_sdk::{Prover, ProverClient};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use twine_types::proofs::ZkProof;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Aggregate proofs of contiguous ranges into one proof", long_about = None)]
+struct Args {
+    /// Message files (e.g. last_kafka_message.json), or directories of them
+    #[arg(required_unless_present = "windows", conflicts_with = "windows")]
+    proofs: Vec<PathBuf>,
+    
+    /// Offset window <partition>:<start>..<end> (end exclusive) of the topic to read proofs from (repeatable)
+    #[arg(long = "window", value_name = "WINDOW")]
+    windows: Vec<PartitionWindow>,
+    
+    /// Topic the windows are read from (default: KAFKA_TOPIC)
+    #[arg(long, requires = "windows")]
+    topic: Option<String>,
+    
+    /// Kind of aggregate proof: groth16 or compressed
+    #[arg(long, default_value = "groth16")]
+    mode: ProofMode,
+    
+    /// Write the aggregate to this directory instead of publishing it to Kafka
+    #[arg(long)]
+    sink_dir: Option<PathBuf>,
+    
+    /// Only check the batch, without proving
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Messages of the given files, and of the `.json` files in the given directories
+fn read_files(paths: &[PathBuf]) -> Result<Vec<ZkProof>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let entries = fs::read_dir(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.extension().is_some_and(|extension| extension == "json"))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files.iter().map(|file| read_file(file)).collect()
+}
+
+fn read_file(path: &Path) -> Result<ZkProof, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    decode_json(&bytes).map(|decoded| decoded.proof).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Messages in the offset windows of a topic
+fn read_windows(config: &KafkaConfig, topic: &str, windows: &[PartitionWindow]) -> Result<Vec<ZkProof>, String> {
+    let timeout = Duration::from_secs(10);
+    // Partitions are assigned directly, so no group is joined and no offsets are committed
+    let mut client_config = config.client_config();
+    client_config.set("enable.auto.commit", "false");
+    let consumer: BaseConsumer<KafkaClientContext> =
+        client_config.create_with_context(config.client_context()).map_err(|e| e.to_string())?;
+    
+    let topics = [topic.to_string()];
+    let windows = resolve_windows(&consumer, &topics, windows, timeout).map_err(|e| e.to_string())?;
+    // A batch missing proofs would only fail later as a gap
+    for window in &windows {
+        if window.starts_before_log() || window.ends_after_log() {
+            return Err(format!(
+                "window {}:{}..{} of {} is not in the log (offsets {}..{})",
+                window.partition, window.requested.start, window.requested.end, topic, window.low, window.high
+            ));
+        }
+    }
+    let assignment = window_assignment(&windows).map_err(|e| e.to_string())?;
+    if assignment.count() > 0 {
+        consumer.assign(&assignment).map_err(|e| e.to_string())?;
+    }
+    
+    let mut tracker = WindowTracker::new(&windows);
+    let mut proofs = Vec::new();
+    while !tracker.is_done() {
+        if let Some(message) = consumer.poll(Duration::from_millis(500)) {
+            let msg = message.map_err(|e| format!("Failed to read {}: {}", topic, e))?;
+            if !tracker.observe(msg.topic(), msg.partition(), msg.offset()) {
+                continue;
+            }
+            let at = format!("partition {} offset {}", msg.partition(), msg.offset());
+            let Some(payload) = msg.payload() else {
+                return Err(format!("{}: empty message", at));
+            };
+            let proof = match PayloadFormat::of_message(&msg, payload) {
+                PayloadFormat::Json => decode_json(payload).map(|decoded| decoded.proof).map_err(|e| e.to_string()),
+                PayloadFormat::Bincode => bincode::deserialize::<ZkProof>(payload).map_err(|e| e.to_string()),
+                PayloadFormat::Avro => Err("Avro messages are not supported".to_string()),
+            };
+            proofs.push(proof.map_err(|e| format!("{}: {}", at, e))?);
+        }
+        // Windows can end in offsets that hold no message, e.g. transaction markers
+        tracker.close_reached(&consumer.position().map_err(|e| e.to_string())?);
+    }
+    Ok(proofs)
+}
+
+async fn run(args: &Args) -> Result<(), String> {
+    let kafka_config = match (&args.sink_dir, args.windows.is_empty(), args.dry_run) {
+        (Some(_), true, _) | (_, true, true) => None,
+        _ => {
+            let config = KafkaConfig::from_env().map_err(|e| e.to_string())?;
+            config.validate().map_err(|e| e.to_string())?;
+            Some(config)
+        }
+    };
+    let messages = match &kafka_config {
+        Some(config) if !args.windows.is_empty() => {
+            let topic = args.topic.as_deref().unwrap_or(&config.topic);
+            println!("📥 Reading {} window(s) of {}", args.windows.len(), topic);
+            read_windows(config, topic, &args.windows)?
+        }
+        _ => read_files(&args.proofs)?,
+    };
+    
+    println!("🔑 Setting up the program's verifying key...");
+    let (_, vk) = ProverClient::builder().cpu().build().setup(PROVER_ELF);
+    let verifier = ProofVerifier::new(VerifyingKeySource::Key(Box::new(vk)));
+    println!("🔍 Checking {} proof(s)", messages.len());
+    let mut members = Vec::with_capacity(messages.len());
+    for message in &messages {
+        let member = check_member(message, &verifier).map_err(|e| format!("{}; aborting the batch", e))?;
+        let range = (member.commitments.start_slot, member.commitments.end_slot);
+        println!("   ✅ {} (slots {}..{})", member.identifier, range.0, range.1);
+        members.push(member);
+    }
+    let members = order_batch(members).map_err(|e| format!("{}; aborting the batch", e))?;
+    
+    let count = members.len();
+    let (first, last) = (&members[0].commitments, &members[count - 1].commitments);
+    let (start_slot, end_slot) = (first.start_slot, last.end_slot);
+    println!("🔗 {} contiguous proof(s) of slots {}..{}", count, start_slot, end_slot);
+    if first.epoch != last.epoch {
+        println!("⚠️  The batch spans epochs {} to {}", first.epoch, last.epoch);
+    }
+    if args.dry_run {
+        println!("✅ Batch is ready to aggregate (dry run, nothing proven)");
+        return Ok(());
+    }
+    
+    let mode = args.mode;
+    let proofs: Vec<_> = members.into_iter().map(|member| member.proof).collect();
+    let artifacts = match tokio::task::spawn_blocking(move || prove_aggregate(&proofs, mode)).await {
+        Ok(result) => result.map_err(|e| e.to_string())?,
+        Err(e) => return Err(format!("Aggregation task failed: {}", e)),
+    };
+    let commitments = &artifacts.commitments;
+    if (commitments.start_slot, commitments.end_slot) != (start_slot, end_slot) {
+        return Err(format!(
+            "the aggregate commits to slots {}..{} instead of {}..{}",
+            commitments.start_slot, commitments.end_slot, start_slot, end_slot
+        ));
+    }
+    
+    let identifier = &artifacts.zk_proof.identifier;
+    match (&args.sink_dir, &kafka_config) {
+        (Some(dir), _) => {
+            let sink = DirSink::new(dir).map_err(|e| format!("Failed to open {}: {}", dir.display(), e))?;
+            let receipt = sink.publish(&artifacts.zk_proof).await.map_err(|e| e.to_string())?;
+            println!("📝 Wrote {} to {}", identifier, receipt);
+        }
+        (None, Some(config)) => {
+            let publisher = ProofPublisher::new(config).map_err(|e| e.to_string())?;
+            let count = count.to_string();
+            let (partition, offset) = publisher
+                .publish_proof_with_headers(&artifacts.zk_proof, None, &[(AGGREGATED_COUNT_HEADER, &count)])
+                .await
+                .map_err(|e| e.to_string())?;
+            println!("📤 Published {} to partition {} at offset {}", identifier, partition, offset);
+        }
+        (None, None) => unreachable!("Kafka is configured unless writing to a directory"),
+    }
+    println!("✅ Aggregated {} proof(s) of slots {}..{}", count, start_slot, end_slot);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    sp1_sdk::utils::setup_logger();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    if let Err(e) = run(&args).await {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
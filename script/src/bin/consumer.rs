@@ -1,35 +1,123 @@
-//! Kafka consumer that listens to the twine.solana.proofs topic and prints messages
+//! Kafka consumer that listens to the twine.solana.proofs topic (or several topics) and prints messages
 
 use clap::Parser;
-use rdkafka::consumer::{StreamConsumer, Consumer};
-use rdkafka::{ClientConfig, Message};
-use rdkafka::message::Headers;
+use rdkafka::consumer::{CommitMode, StreamConsumer, Consumer};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::{Message, Offset};
+use rdkafka::message::{BorrowedMessage, Header, Headers, OwnedHeaders, Timestamp};
+use serde::Serialize;
 use serde_json::Value;
 use twine_types::proofs::{ZkProof, ProofData};
+use solana_stub_prover_script::account_data;
+use solana_stub_prover_script::archive::{ArchiveOutcome, ProofArchive};
+use solana_stub_prover_script::backpressure::{
+    Backpressure, Transition, DEFAULT_PAUSE_HIGH_WATER, DEFAULT_PAUSE_LOW_WATER,
+};
+use solana_stub_prover_script::commit::{
+    CommitBatcher, CommitStrategy, DEFAULT_COMMIT_BATCH, DEFAULT_COMMIT_INTERVAL_MS,
+};
+use solana_stub_prover_script::commitment_facts::{check_republish_topic, CommitmentFacts};
+use solana_stub_prover_script::continuity::{ChainKeying, ContinuityEvent, ContinuityMonitor};
+use solana_stub_prover_script::csv_export::{CsvExport, CSV_FLUSH_INTERVAL};
+use solana_stub_prover_script::dedup::{commitment_digest, DedupVerdict, DedupWindow};
+use solana_stub_prover_script::envelope::{decode_payload, DecodedProof, EnvelopeShape};
+use solana_stub_prover_script::health::{ConsumerHealth, DEFAULT_STALL_THRESHOLD_SECS};
+use solana_stub_prover_script::dlq::{
+    DLQ_ATTEMPTS_HEADER, DLQ_ERROR_HEADER, DLQ_FAILED_AT_HEADER, DLQ_ORIGINAL_TOPIC_HEADER, DLQ_REASON_HEADER,
+};
+use solana_stub_prover_script::kafka::{
+    create_admin_client, ConnectionOptions, ConsumerTuning, KafkaAuth, KafkaConfig, KafkaConfigError, PayloadFormat,
+    ProofPublisher, SaslMechanism, SecurityProtocol, CONTENT_TYPE_HEADER, DEFAULT_FETCH_MAX_BYTES,
+    DEFAULT_MAX_POLL_INTERVAL_MS, DEFAULT_SESSION_TIMEOUT_MS,
+};
+use solana_stub_prover_script::lag::{fetch_lag, group_positions, PartitionLag};
+use solana_stub_prover_script::latest::{is_compacted, tail_windows, Lookback, Newest, ProofRank, DEFAULT_LOOKBACK};
+use solana_stub_prover_script::metrics::{self, Routes};
+use solana_stub_prover_script::presenter::{self, commitment_rows, ColorChoice, OutputStyle, Presenter, Section, Tone};
+use solana_stub_prover_script::{errln, outln};
+use solana_stub_prover_script::msk_iam::KafkaClientContext;
+use solana_stub_prover_script::partition_workers::{PartitionWorkers, ProcessedWatermarks, DEFAULT_PARTITION_QUEUE};
+use solana_stub_prover_script::proof_filter::{
+    decode_commitments, decode_commitments_ref, execution_index, kind_label, FilterOutcome, KindFilter, ProofFilter,
+};
+use solana_stub_prover_script::replay::{
+    parse_timestamp, resolve_assignment, resolve_windows, window_assignment, PartitionOffset, PartitionWindow,
+    ReplayStart, WindowTracker,
+};
+use solana_stub_prover_script::utils::{bytes32_to_base58, parse_pubkey_or_hex};
+use solana_stub_prover_script::verify::{ProofVerifier, Verification, VerifyStatus, VerifyingKeySource};
+use solana_stub_prover_script::vkey_registry::{format_vkey_hash, VkeyRegistry};
+use solana_stub_prover_script::shutdown::spawn_signal_handler;
+use solana_stub_prover_script::sqlite_sink::{MessagePosition, SqliteSink};
+use solana_stub_prover_script::stats::{ConsumerStats, StatsInterval};
+use solana_stub_prover_script::wrapped_proof::{public_values_digest, WrappedProof};
+use solana_stub_prover_script::webhook::{
+    DeliveryOutcome, ProofSummary, WebhookConfig, WebhookForwarder, DEFAULT_CIRCUIT_COOLDOWN_SECS,
+    DEFAULT_CIRCUIT_FAILURES, DEFAULT_WEBHOOK_RETRIES, DEFAULT_WEBHOOK_TIMEOUT_MS,
+};
+use solana_stub_prover_script::schema_registry::{SchemaRegistryClient, SchemaRegistryConfig};
+use solana_stub_prover_lib::{PublicCommitments, PublicCommitmentsRef};
 use futures::StreamExt;
-use chrono::Utc;
-use std::time::Duration;
-
-const DEFAULT_KAFKA_BROKER_TLS: &str = "kafka-bootstrap.twine.limited:443";
-const DEFAULT_KAFKA_BROKER_PLAIN: &str = "b-1.test.7alql0.c5.kafka.us-east-1.amazonaws.com:9092";
-const KAFKA_TOPIC: &str = "twine.solana.proofs";
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 /// Command line arguments for the consumer
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Kafka consumer for Solana proofs", long_about = None)]
 struct Args {
-    /// Kafka broker address (can be comma-separated list)
+    /// Kafka broker address (can be comma-separated list; default: KAFKA_BROKER)
     #[arg(long)]
     broker: Option<String>,
     
-    /// Consumer group ID
-    #[arg(long, default_value = "solana-proof-consumer")]
-    group_id: String,
+    /// Consumer group ID (default: solana-proof-consumer)
+    #[arg(long)]
+    group_id: Option<String>,
     
     /// Start from beginning of topic
     #[arg(long)]
     from_beginning: bool,
     
+    /// Replay a partition from an offset, as <partition>:<offset> (repeatable; no consumer group)
+    #[arg(long, conflicts_with_all = ["from_timestamp", "from_beginning"])]
+    from_offset: Vec<PartitionOffset>,
+    
+    /// Replay every partition from a time, as RFC 3339 or Unix milliseconds (no consumer group)
+    #[arg(long, conflicts_with = "from_beginning")]
+    from_timestamp: Option<String>,
+    
+    /// Replay exactly an offset window, as <partition>:<start>..<end> with end exclusive (repeatable; no consumer group)
+    #[arg(long, conflicts_with_all = ["from_offset", "from_timestamp", "from_beginning"])]
+    replay: Vec<PartitionWindow>,
+    
+    /// Offset commit strategy: auto (on receipt) or manual (after processing)
+    #[arg(long, default_value = "auto")]
+    commit: CommitStrategy,
+    
+    /// Maximum time between manual commits in milliseconds
+    #[arg(long, default_value_t = DEFAULT_COMMIT_INTERVAL_MS)]
+    commit_interval_ms: u64,
+    
+    /// Number of processed messages that triggers a manual commit
+    #[arg(long, default_value_t = DEFAULT_COMMIT_BATCH)]
+    commit_batch: usize,
+    
+    /// Messages buffered per partition worker before reading pauses
+    #[arg(long, default_value_t = DEFAULT_PARTITION_QUEUE)]
+    partition_queue: usize,
+    
+    /// Pause consumption once this many messages are queued to the partition workers
+    #[arg(long, default_value_t = DEFAULT_PAUSE_HIGH_WATER)]
+    pause_high_water: usize,
+    
+    /// Resume paused consumption once no more than this many messages are queued
+    #[arg(long, default_value_t = DEFAULT_PAUSE_LOW_WATER)]
+    pause_low_water: usize,
+    
     /// Show raw JSON output
     #[arg(long)]
     raw: bool,
@@ -38,7 +126,15 @@ struct Args {
     #[arg(long)]
     minimal: bool,
     
-    /// Enable SASL authentication
+    /// Output style: pretty (emoji and boxes) or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto")]
+    color: ColorChoice,
+    
+    /// Enable SASL authentication (PLAIN unless --sasl-mechanism is given)
     #[arg(long)]
     sasl: bool,
     
@@ -50,13 +146,14 @@ struct Args {
     #[arg(long, env = "KAFKA_PASSWORD")]
     password: Option<String>,
     
-    /// SASL mechanism (PLAIN, SCRAM-SHA-256, SCRAM-SHA-512)
-    #[arg(long, default_value = "PLAIN")]
-    sasl_mechanism: String,
+    /// SASL mechanism: PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512 (default: KAFKA_SASL_MECHANISM)
+    #[arg(long)]
+    sasl_mechanism: Option<SaslMechanism>,
     
-    /// Security protocol (plaintext, ssl, sasl_plaintext, sasl_ssl)
-    #[arg(long, default_value = "plaintext")]
-    security_protocol: String,
+    /// Security protocol: plaintext, ssl, sasl_plaintext or sasl_ssl (default: KAFKA_SECURITY_PROTOCOL,
+    /// otherwise derived from the TLS and SASL options)
+    #[arg(long)]
+    security_protocol: Option<SecurityProtocol>,
     
     /// Enable debug output
     #[arg(long)]
@@ -66,40 +163,1071 @@ struct Args {
     #[arg(long, default_value = "30")]
     connection_timeout: u64,
     
+    /// Client ID reported to the brokers (default: KAFKA_CLIENT_ID)
+    #[arg(long)]
+    client_id: Option<String>,
+    
+    /// Time without heartbeats before the group evicts the consumer, in milliseconds
+    #[arg(long, default_value_t = DEFAULT_SESSION_TIMEOUT_MS)]
+    session_timeout_ms: u64,
+    
+    /// Maximum time between polls before the consumer leaves the group, in milliseconds (at least --session-timeout-ms)
+    #[arg(long, default_value_t = DEFAULT_MAX_POLL_INTERVAL_MS)]
+    max_poll_interval_ms: u64,
+    
+    /// Maximum bytes fetched per request across partitions
+    #[arg(long, default_value_t = DEFAULT_FETCH_MAX_BYTES)]
+    fetch_max_bytes: u64,
+    
     /// Use TLS connection (default: true)
     #[arg(long, default_value = "true")]
     tls: bool,
     
-    /// CA certificate file path
-    #[arg(long, default_value = "./ca.crt")]
-    ca_cert: String,
+    /// CA certificate file path (default: KAFKA_CA_CERT or ./ca.crt)
+    #[arg(long)]
+    ca_cert: Option<String>,
+    
+    /// Client certificate file path (default: KAFKA_CLIENT_CERT or ./user.crt)
+    #[arg(long)]
+    client_cert: Option<String>,
+    
+    /// Client key file path (default: KAFKA_CLIENT_KEY or ./user.key)
+    #[arg(long)]
+    client_key: Option<String>,
+    
+    /// Disable TLS (use plain connection)
+    #[arg(long)]
+    no_tls: bool,
+    
+    /// SASL authentication for the TLS connection: none or msk-iam (default: KAFKA_AUTH or none)
+    #[arg(long)]
+    kafka_auth: Option<KafkaAuth>,
+    
+    /// AWS region of the MSK cluster for msk-iam authentication
+    #[arg(long, env = "AWS_REGION")]
+    aws_region: Option<String>,
+    
+    /// IAM role to assume when signing msk-iam tokens
+    #[arg(long, env = "AWS_ROLE_ARN")]
+    aws_role_arn: Option<String>,
+    
+    /// Continue without certificate files that do not exist (plaintext-broker development)
+    #[arg(long)]
+    allow_missing_certs: bool,
+    
+    /// Consume the given dead-letter topic instead of the proofs topic
+    #[arg(long, conflicts_with_all = ["topic", "topic_pattern"])]
+    dlq_topic: Option<String>,
+    
+    /// Topic to consume (repeatable; default: KAFKA_TOPIC or twine.solana.proofs)
+    #[arg(long, conflicts_with = "topic_pattern")]
+    topic: Vec<String>,
+    
+    /// Subscribe to every topic matching this regex (e.g. 'twine\.solana\.proofs\..*')
+    #[arg(long, conflicts_with_all = ["from_offset", "from_timestamp"])]
+    topic_pattern: Option<String>,
+    
+    /// Apply the --filter-* options only to messages from this topic (repeatable; default: all topics)
+    #[arg(long)]
+    filter_topic: Vec<String>,
+    
+    /// Only show proofs whose range starts at or after this slot
+    #[arg(long)]
+    filter_start_slot: Option<u64>,
+    
+    /// Only show proofs whose range ends at or before this slot
+    #[arg(long)]
+    filter_end_slot: Option<u64>,
+    
+    /// Only show proofs monitoring this account (base58 or hex)
+    #[arg(long)]
+    filter_account: Option<String>,
+    
+    /// Only show proofs whose identifier starts with this prefix
+    #[arg(long)]
+    filter_identifier_prefix: Option<String>,
+    
+    /// Only show proofs of this kind: execution, consensus or all
+    #[arg(long, default_value_t = KindFilter::All)]
+    kind: KindFilter,
+    
+    /// Show proofs whose public values cannot be decoded while slot or account filters are set
+    #[arg(long)]
+    show_undecodable: bool,
+    
+    /// Detect repeated proofs among this many recently seen identifiers
+    #[arg(long)]
+    dedup_window: Option<usize>,
+    
+    /// Do not display duplicate proofs (conflicts are always shown)
+    #[arg(long, requires = "dedup_window")]
+    suppress_duplicates: bool,
+    
+    /// Check that each proof continues its chain, with chains keyed by monitored accounts or identifier prefix
+    #[arg(long)]
+    continuity: Option<ChainKeying>,
+    
+    /// Persist chain tips to this file so continuity checks survive restarts
+    #[arg(long, requires = "continuity")]
+    continuity_state: Option<PathBuf>,
+    
+    /// Save each displayed message's payload and decoded commitments to this directory
+    #[arg(long)]
+    save_dir: Option<PathBuf>,
+    
+    /// Stop saving (but keep consuming) once the save directory reaches this many bytes
+    #[arg(long, requires = "save_dir")]
+    save_max_bytes: Option<u64>,
+    
+    /// Store decoded commitments in this SQLite database (requires the sqlite feature)
+    #[arg(long)]
+    sqlite: Option<PathBuf>,
+    
+    /// Do not store raw payloads in the SQLite database
+    #[arg(long, requires = "sqlite")]
+    sqlite_no_raw: bool,
+    
+    /// Append one row per monitored account of each displayed proof to this CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    
+    /// Verify each displayed proof (requires --vkey or --vkey-hash)
+    #[arg(long)]
+    verify: bool,
+    
+    /// Verifying key JSON written by the prover, used with --verify
+    #[arg(long, conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (0x-prefixed bytes32) used with --verify; only Groth16 proofs can be checked
+    #[arg(long)]
+    vkey_hash: Option<String>,
+    
+    /// Do not print the public commitments of proofs that fail verification
+    #[arg(long, requires = "verify")]
+    reject_unverified: bool,
+    
+    /// Flag proofs whose envelope carries another program vkey hash (0x-prefixed bytes32; default: the one
+    /// pinned when the consumer was built, if any)
+    #[arg(long, conflicts_with = "vkey_registry")]
+    expected_vkey_hash: Option<String>,
+    
+    /// TOML file of statement version = program vkey hash; flag proofs under keys it does not list
+    #[arg(long)]
+    vkey_registry: Option<PathBuf>,
+    
+    /// POST a JSON summary of each displayed proof to this URL (repeatable)
+    #[arg(long)]
+    webhook_url: Vec<String>,
+    
+    /// Secret used to sign webhook and alert webhook bodies (HMAC-SHA256 in the x-signature-sha256 header)
+    #[arg(long, env = "WEBHOOK_SECRET")]
+    webhook_secret: Option<String>,
+    
+    /// Webhook request timeout in milliseconds
+    #[arg(long, default_value_t = DEFAULT_WEBHOOK_TIMEOUT_MS)]
+    webhook_timeout_ms: u64,
+    
+    /// Number of retries after a failed webhook delivery
+    #[arg(long, default_value_t = DEFAULT_WEBHOOK_RETRIES)]
+    webhook_retries: u32,
+    
+    /// Only forward proofs that pass verification
+    #[arg(long, requires_all = ["verify", "webhook_url"])]
+    webhook_verified_only: bool,
+    
+    /// POST proofs whose commitments report failed validations to this URL as soon as they are read (repeatable)
+    #[arg(long)]
+    alert_webhook: Vec<String>,
+    
+    /// Exit with a non-zero code once a proof with failed validations is seen
+    #[arg(long)]
+    fail_on_invalid: bool,
+    
+    /// Publish a compact JSON summary of each displayed consensus proof's commitments to this topic
+    #[arg(long)]
+    republish_topic: Option<String>,
+    
+    /// Only republish proofs that pass verification
+    #[arg(long, requires_all = ["verify", "republish_topic"])]
+    republish_only_verified: bool,
+    
+    /// Exit after displaying this many (post-filter) messages
+    #[arg(long)]
+    max_messages: Option<u64>,
+    
+    /// Exit after displaying a proof whose end slot is at or beyond this slot
+    #[arg(long)]
+    until_end_slot: Option<u64>,
+    
+    /// Exit with code 2 if no message arrives for this many seconds
+    #[arg(long)]
+    idle_timeout: Option<u64>,
+    
+    /// Print consumer lag every this many seconds (also the metrics refresh interval; default: 15)
+    #[arg(long)]
+    lag_interval: Option<u64>,
+    
+    /// Print session statistics at this interval (e.g. 60s, 5m), in addition to the summary at shutdown
+    #[arg(long)]
+    stats_interval: Option<StatsInterval>,
+    
+    /// Only report the consumer group's lag every --lag-interval seconds, without consuming
+    #[arg(long, conflicts_with_all = ["from_offset", "from_timestamp"])]
+    watch_lag: bool,
+    
+    /// Print the newest proof monitoring this account (base58 or hex) in the topic's tail, then exit without a group
+    #[arg(long, conflicts_with_all = [
+        "from_offset", "from_timestamp", "replay", "from_beginning", "watch_lag", "topic_pattern", "group_id",
+        "filter_account",
+    ])]
+    latest: Option<String>,
+    
+    /// Messages read before the end of each partition by --latest (default: 1000)
+    #[arg(long, requires = "latest")]
+    lookback: Option<u64>,
+    
+    /// Read records from this time on for --latest, as RFC 3339 or Unix milliseconds, instead of --lookback
+    #[arg(long, requires = "latest", conflicts_with = "lookback")]
+    lookback_since: Option<String>,
+    
+    /// Records are keyed by account (producer --partition-key account); on a compacted topic --latest matches keys
+    #[arg(long, requires = "latest")]
+    account_keys: bool,
+    
+    /// Serve Prometheus metrics on this address at /metrics (e.g. 0.0.0.0:9100)
+    #[arg(long)]
+    metrics_addr: Option<std::net::SocketAddr>,
+    
+    /// Serve /healthz, /readyz and /status on this address (shares the server if equal to --metrics-addr)
+    #[arg(long, conflicts_with = "watch_lag")]
+    http_addr: Option<std::net::SocketAddr>,
+    
+    /// Seconds without poll-loop progress before /healthz reports the consumer as stalled
+    #[arg(long, default_value_t = DEFAULT_STALL_THRESHOLD_SECS)]
+    stall_threshold: u64,
+    
+    /// Schema Registry URL used to decode Avro (Confluent wire format) messages
+    #[arg(long, env = "SCHEMA_REGISTRY_URL")]
+    schema_registry_url: Option<String>,
+    
+    /// Schema Registry basic auth username
+    #[arg(long, env = "SCHEMA_REGISTRY_USERNAME")]
+    schema_registry_username: Option<String>,
+    
+    /// Schema Registry basic auth password
+    #[arg(long, env = "SCHEMA_REGISTRY_PASSWORD")]
+    schema_registry_password: Option<String>,
+}
+
+/// Whether the filters apply to messages from `topic` (`--filter-topic`, or every topic)
+fn filter_topics_match(filter_topics: &[String], topic: &str) -> bool {
+    filter_topics.is_empty() || filter_topics.iter().any(|t| t == topic)
+}
+
+/// Build the proof filter from the `--filter-*` options
+fn build_filter(args: &Args) -> Result<ProofFilter, Box<dyn std::error::Error>> {
+    let account = match &args.filter_account {
+        Some(account) => Some(parse_pubkey_or_hex(account).map_err(|e| format!("Invalid --filter-account: {}", e))?),
+        None => None,
+    };
+    Ok(ProofFilter {
+        start_slot: args.filter_start_slot,
+        end_slot: args.filter_end_slot,
+        account,
+        identifier_prefix: args.filter_identifier_prefix.clone(),
+        kind: args.kind,
+    })
+}
+
+/// Build the Schema Registry client from the `--schema-registry-*` options
+fn build_registry(args: &Args) -> Option<SchemaRegistryClient> {
+    args.schema_registry_url.clone().map(|url| {
+        outln!("📚 Schema Registry: {}", url);
+        SchemaRegistryClient::new(SchemaRegistryConfig {
+            url,
+            username: args.schema_registry_username.clone(),
+            password: args.schema_registry_password.clone(),
+        })
+    })
+}
+
+/// Build the proof verifier from the `--verify` options
+fn build_verifier(args: &Args) -> Result<Option<Arc<ProofVerifier>>, Box<dyn std::error::Error>> {
+    if !args.verify {
+        return Ok(None);
+    }
+    let source = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => VerifyingKeySource::from_file(path)?,
+        (None, Some(hash)) => VerifyingKeySource::from_hash(hash)?,
+        (None, None) => return Err("--verify requires --vkey or --vkey-hash".into()),
+    };
+    Ok(Some(Arc::new(ProofVerifier::new(source))))
+}
+
+/// Verify a proof on a blocking task so the consume loop keeps polling
+async fn verify_proof(verifier: &Arc<ProofVerifier>, proof: &ZkProof) -> Verification {
+    let verifier = Arc::clone(verifier);
+    let proof = proof.clone();
+    let started = Instant::now();
+    match tokio::task::spawn_blocking(move || verifier.verify(&proof)).await {
+        Ok(verification) => verification,
+        Err(e) => Verification {
+            status: VerifyStatus::Failed(format!("verifier task failed: {}", e)),
+            elapsed: started.elapsed(),
+        },
+    }
+}
+
+/// Exit code when `--idle-timeout` elapses before a stop condition is met
+const EXIT_IDLE_TIMEOUT: i32 = 2;
+/// Exit code when `--fail-on-invalid` sees a proof with failed validations
+const EXIT_INVALID_PROOF: i32 = 3;
+/// Exit code when `--latest` finds no matching proof
+const EXIT_NO_MATCH: i32 = 4;
+
+/// Why the consume loop stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopReason {
+    Interrupted,
+    StreamEnded,
+    MaxMessages(u64),
+    ReachedEndSlot(u64),
+    IdleTimeout(u64),
+    ReplayComplete,
+    InvalidProof,
+}
+
+/// Build the webhook summary of a displayed proof
+fn proof_summary(
+    decoded: &DecodedProof,
+    commitments: Option<&PublicCommitmentsRef<'_>>,
+    position: MessagePosition<'_>,
+    verified: Option<bool>,
+) -> ProofSummary {
+    ProofSummary {
+        identifier: decoded.proof.identifier.clone(),
+        kind: decoded.kind.clone(),
+        topic: position.topic.to_string(),
+        partition: position.partition,
+        offset: position.offset,
+        start_slot: commitments.map(|c| c.start_slot),
+        end_slot: commitments.map(|c| c.end_slot),
+        epoch: commitments.map(|c| c.epoch),
+        validations_passed: commitments.map(|c| c.validations_passed),
+        alert: commitments.is_some_and(|c| !c.validations_passed),
+        accounts: commitments
+            .map(|c| {
+                c.monitored_accounts_state
+                    .iter()
+                    .map(|account| bytes32_to_base58(&account.account_pubkey))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        verified,
+        received_at: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Forward a summary (or a continuity event) to the webhooks, recording each delivery in the metrics
+async fn forward_summary(
+    forwarder: &mut WebhookForwarder,
+    summary: &impl Serialize,
+    counts: &mut BTreeMap<&'static str, u64>,
+    quiet: bool,
+) {
+    for (url, outcome) in forwarder.forward(summary).await {
+        metrics::WEBHOOK_DELIVERIES.with_label_values(&[outcome.label()]).inc();
+        *counts.entry(outcome.label()).or_default() += 1;
+        match outcome {
+            DeliveryOutcome::Delivered { attempts } if !quiet => {
+                outln!("📤 Forwarded to {} ({} attempt(s))", url, attempts);
+            }
+            DeliveryOutcome::Failed { attempts, error } => {
+                errln!("❌ Webhook {} failed after {} attempt(s): {}", url, attempts, error);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A displayed message handed to its partition's worker
+struct Job {
+    topic: String,
+    /// Label output with the topic when several topics are consumed
+    label_topic: bool,
+    partition: i32,
+    offset: i64,
+    key: String,
+    /// Position of the message among displayed messages
+    number: u64,
+    timestamp: Timestamp,
+    lag: Option<f64>,
+    headers: Vec<(String, Option<Vec<u8>>)>,
+    /// Payload, its format, the decode result and how long decoding took
+    decoded: Option<(Vec<u8>, PayloadFormat, Result<DecodedProof, String>, Duration)>,
+    commitments: Option<PublicCommitments>,
+    outcome: FilterOutcome,
+    verdict: DedupVerdict,
+}
+
+/// Sinks written by the partition workers
+///
+/// Each worker holds the lock only while writing one message, so every
+/// partition's messages reach the sinks in offset order.
+struct Sinks {
+    archive: Option<ProofArchive>,
+    saving: bool,
+    saved: u64,
+    sqlite: Option<SqliteSink>,
+    stored: u64,
+    csv: Option<CsvExport>,
+    csv_rows: usize,
+}
+
+/// Webhook forwarder and its delivery counts, shared across partition workers
+struct WebhookState {
+    forwarder: WebhookForwarder,
+    counts: BTreeMap<&'static str, u64>,
+}
+
+/// Publishes commitment facts to the `--republish-topic`
+struct Republisher {
+    publisher: ProofPublisher,
+    topic: String,
+    only_verified: bool,
+    published: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Settings and shared state of the partition workers
+struct Processor {
+    minimal: bool,
+    raw: bool,
+    debug: bool,
+    reject_unverified: bool,
+    webhook_verified_only: bool,
+    verifier: Option<Arc<ProofVerifier>>,
+    /// Known program vkey hashes; proofs under other keys are flagged
+    vkey_registry: Option<VkeyRegistry>,
+    sinks: Mutex<Sinks>,
+    webhooks: Option<tokio::sync::Mutex<WebhookState>>,
+    republisher: Option<Republisher>,
+    verified: AtomicU64,
+    verify_failed: AtomicU64,
+    unknown_vkeys: AtomicU64,
+    /// Session statistics, also served at /status
+    stats: Arc<Mutex<ConsumerStats>>,
+    /// Messages dispatched to the workers and not yet processed
+    queued: AtomicUsize,
+    /// Reports processed messages back to the consume loop for manual commits
+    processed: Option<mpsc::UnboundedSender<(String, i32, i64)>>,
+}
+
+impl Processor {
+    fn quiet(&self) -> bool {
+        self.minimal || self.raw
+    }
+
+    /// Lock the sinks; a worker that panicked while writing leaves them usable
+    fn lock_sinks(&self) -> std::sync::MutexGuard<'_, Sinks> {
+        self.sinks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_stats(&self) -> std::sync::MutexGuard<'_, ConsumerStats> {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Print, verify and sink one message; runs on its partition's worker
+async fn process_job(processor: &Processor, job: Job) {
+    let quiet = processor.quiet();
+    let topic_label = job.label_topic.then_some(job.topic.as_str());
+    if !quiet {
+        match topic_label {
+            Some(topic) => outln!("📬 Message #{} | Topic: {} | Partition: {} | Offset: {} | Key: {}", 
+                job.number, topic, job.partition, job.offset, job.key),
+            None => outln!("📬 Message #{} | Partition: {} | Offset: {} | Key: {}", 
+                job.number, job.partition, job.offset, job.key),
+        }
+        print_record_timestamp(job.timestamp, job.lag);
+        print_dead_letter_headers(&job.headers);
+        if job.outcome == FilterOutcome::Undecodable {
+            outln!("⚠️  Public values could not be decoded, filters not applied");
+        }
+    }
+    if let DedupVerdict::Duplicate { .. } = &job.verdict {
+        let verdict = presenter::current().paint(&job.verdict, Tone::Warning);
+        if quiet {
+            errln!("🔁 {}", verdict);
+        } else {
+            outln!("🔁 {}", verdict);
+        }
+    }
+    
+    // Archive the message before printing so a crash while printing still leaves it on disk
+    {
+        let mut sinks = processor.lock_sinks();
+        let Sinks { archive, saving, saved, sqlite, stored, csv, csv_rows } = &mut *sinks;
+        if *saving {
+            if let (Some(archive), Some((payload, _, decoded, _))) = (archive.as_mut(), &job.decoded) {
+                let proof = decoded.as_ref().ok().map(|d| &d.proof);
+                match save_message(archive, proof, job.partition, job.offset, payload, quiet) {
+                    Some(ArchiveOutcome::Saved(_)) => *saved += 1,
+                    Some(ArchiveOutcome::CapReached { .. }) => *saving = false,
+                    None => {}
+                }
+            }
+        }
+        
+        // Store the decoded commitments; failures are reported and consuming continues
+        if let (Some(sink), Some((payload, _, Ok(decoded), _))) = (sqlite.as_mut(), &job.decoded) {
+            let position = MessagePosition { topic: &job.topic, partition: job.partition, offset: job.offset };
+            match sink.upsert(&decoded.proof, &decoded.kind, job.commitments.as_ref(), position, payload) {
+                Ok(()) => *stored += 1,
+                Err(e) => errln!("❌ Failed to store {} in SQLite: {}", decoded.proof.identifier, e),
+            }
+        }
+        
+        // Duplicates would double count in the spreadsheet; conflicts are new content
+        let is_duplicate = matches!(job.verdict, DedupVerdict::Duplicate { .. });
+        if let (Some(export), Some((_, _, Ok(decoded), _)), Some(commitments), false) =
+            (csv.as_mut(), &job.decoded, &job.commitments, is_duplicate)
+        {
+            match export.append(&decoded.proof.identifier, commitments) {
+                Ok(rows) => *csv_rows += rows,
+                Err(e) => errln!("❌ Failed to write {} to CSV: {}", decoded.proof.identifier, e),
+            }
+        }
+    }
+    
+    // Print the decoded proof
+    if let Some((payload, format, decoded, decode_time)) = &job.decoded {
+        match decoded {
+            Ok(proof) => {
+                if !quiet {
+                    outln!("⏱️  Decoded in {:?}", decode_time);
+                }
+                
+                if let Some(registry) = &processor.vkey_registry {
+                    let ProofData::SP1(sp1_proof) = &proof.proof.proof_data;
+                    if registry.version_of(&sp1_proof.verification_key).is_none() {
+                        processor.unknown_vkeys.fetch_add(1, Ordering::Relaxed);
+                        let key = format_vkey_hash(&sp1_proof.verification_key);
+                        let warning = format!("Unknown verification key {}", key);
+                        let warning = presenter::current().paint(warning, Tone::Warning);
+                        if quiet {
+                            errln!("🔑 {}: {}", proof.proof.identifier, warning);
+                        } else {
+                            outln!("🔑 {}", warning);
+                        }
+                    }
+                }
+                
+                let mut show_commitments = true;
+                let mut verified = None;
+                if let Some(verifier) = &processor.verifier {
+                    let verification = verify_proof(verifier, &proof.proof).await;
+                    verified = Some(verification.is_verified());
+                    if verification.is_verified() {
+                        processor.verified.fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        processor.verify_failed.fetch_add(1, Ordering::Relaxed);
+                        processor.lock_stats().record_verify_failure();
+                        metrics::PROOFS_VERIFICATION_FAILURES.with_label_values(&[&job.topic]).inc();
+                        show_commitments = !processor.reject_unverified;
+                    }
+                    let (icon, tone) = if verification.is_verified() { ("✅", Tone::Good) } else { ("❌", Tone::Bad) };
+                    let status = presenter::current().paint(&verification.status, tone);
+                    if quiet {
+                        errln!("{} {}: {} in {:?}", icon, proof.proof.identifier, status, verification.elapsed);
+                    } else {
+                        outln!("🔏 {} {} in {:?}", icon, status, verification.elapsed);
+                    }
+                }
+                print_proof_details(proof, topic_label, processor.raw, processor.minimal, show_commitments);
+                if let Some(commitments) = &job.commitments {
+                    check_account_data(proof, commitments, quiet);
+                }
+                
+                if let Some(webhooks) = &processor.webhooks {
+                    if !processor.webhook_verified_only || verified == Some(true) {
+                        let position = MessagePosition { topic: &job.topic, partition: job.partition, offset: job.offset };
+                        let view = job.commitments.as_ref().map(PublicCommitments::view);
+                        let summary = proof_summary(proof, view.as_ref(), position, verified);
+                        let mut webhooks = webhooks.lock().await;
+                        let WebhookState { forwarder, counts } = &mut *webhooks;
+                        forward_summary(forwarder, &summary, counts, quiet).await;
+                    }
+                }
+                
+                if let (Some(republisher), Some(commitments)) = (&processor.republisher, &job.commitments) {
+                    // A topic pattern may match the republish topic itself
+                    if job.topic != republisher.topic && (!republisher.only_verified || verified == Some(true)) {
+                        let facts = CommitmentFacts::new(
+                            &proof.proof.identifier,
+                            commitments,
+                            verified,
+                            &job.topic,
+                            job.partition,
+                            job.offset,
+                        );
+                        republish(republisher, &facts, quiet).await;
+                    }
+                }
+            }
+            Err(e) => {
+                errln!("❌ Error decoding {} message: {}", format, e);
+                if processor.debug {
+                    // Try to parse as generic JSON for debugging
+                    match serde_json::from_slice::<Value>(payload).map(|json| serde_json::to_string_pretty(&json)) {
+                        Ok(Ok(json)) => {
+                            outln!("Raw JSON structure:");
+                            outln!("{}", json);
+                        }
+                        _ => errln!("Raw payload: {}", String::from_utf8_lossy(payload)),
+                    }
+                }
+            }
+        }
+    } else {
+        errln!("⚠️  Empty message payload");
+    }
+    
+    // Print headers if present and not in minimal mode
+    if !quiet {
+        for (key, value) in &job.headers {
+            let value = value.as_deref().unwrap_or(b"");
+            outln!("   Header: {} = {}", key, String::from_utf8_lossy(value));
+        }
+    }
     
-    /// Client certificate file path  
-    #[arg(long, default_value = "./user.crt")]
-    client_cert: String,
+    // The message is fully processed; only now may its offset be committed
+    if let Some(processed) = &processor.processed {
+        let _ = processed.send((job.topic, job.partition, job.offset));
+    }
+    let queued = processor.queued.fetch_sub(1, Ordering::Relaxed) - 1;
+    metrics::CONSUMER_QUEUE_DEPTH.set(queued as i64);
+}
+
+/// Publish the commitment facts of a proof to the republish topic
+async fn republish(republisher: &Republisher, facts: &CommitmentFacts, quiet: bool) {
+    let payload = match serde_json::to_vec(facts) {
+        Ok(payload) => payload,
+        Err(e) => {
+            errln!("❌ Failed to serialize commitment facts of {}: {}", facts.identifier, e);
+            return;
+        }
+    };
+    let headers = OwnedHeaders::new().insert(Header {
+        key: CONTENT_TYPE_HEADER,
+        value: Some(PayloadFormat::Json.content_type()),
+    });
+    match republisher.publisher.publish_with_headers(facts.record_key(), &payload, headers).await {
+        Ok((partition, offset)) => {
+            republisher.published.fetch_add(1, Ordering::Relaxed);
+            if !quiet {
+                outln!("📣 Republished commitments to {} (partition {}, offset {})", republisher.topic, partition, offset);
+            }
+        }
+        Err(e) => {
+            republisher.failed.fetch_add(1, Ordering::Relaxed);
+            errln!("❌ Failed to republish {} to {}: {}", facts.identifier, republisher.topic, e);
+        }
+    }
+}
+
+/// Record a message as processed and store the partition's contiguous processed watermark
+fn store_processed(
+    consumer: &StreamConsumer<KafkaClientContext>,
+    watermarks: &mut ProcessedWatermarks,
+    batcher: &mut CommitBatcher,
+    topic: &str,
+    partition: i32,
+    offset: i64,
+) {
+    batcher.processed();
+    if let Some(watermark) = watermarks.processed(topic, partition, offset) {
+        // Stores watermark + 1, the next offset to consume
+        if let Err(e) = consumer.store_offset(topic, partition, watermark) {
+            errln!("⚠️  Failed to store offset {} on partition {}: {}", watermark, partition, e);
+        }
+    }
+    if batcher.is_due() {
+        commit_offsets(consumer, batcher, CommitMode::Async);
+    }
+}
+
+/// Pause or resume fetching from every assigned partition
+fn pause_partitions(consumer: &StreamConsumer<KafkaClientContext>, pause: bool) {
+    let result = consumer.assignment().and_then(|assignment| {
+        if pause {
+            consumer.pause(&assignment)
+        } else {
+            consumer.resume(&assignment)
+        }
+    });
+    if let Err(e) = result {
+        errln!("⚠️  Failed to {} partitions: {}", if pause { "pause" } else { "resume" }, e);
+    }
+}
+
+/// Default interval between lag reports and lag metric refreshes
+const DEFAULT_LAG_INTERVAL_SECS: u64 = 15;
+
+/// Update the per-partition lag gauges
+fn record_lag_metrics(lags: &[PartitionLag]) {
+    for lag in lags {
+        metrics::PROOFS_CONSUMER_LAG
+            .with_label_values(&[&lag.topic, &lag.partition.to_string()])
+            .set(lag.lag());
+    }
+}
+
+/// Print the lag of each partition and the total
+fn print_lag(lags: &[PartitionLag], to_stderr: bool) {
+    let mut lines = vec![format!(
+        "📊 Consumer lag: {} message(s) across {} partition(s)",
+        lags.iter().map(PartitionLag::lag).sum::<i64>(),
+        lags.len()
+    )];
+    for lag in lags {
+        let position = match lag.position {
+            Some(position) => format!("offset {}", position),
+            None => "no committed offset".to_string(),
+        };
+        lines.push(format!(
+            "   {} partition {}: {} | high watermark {} | lag {}",
+            lag.topic, lag.partition, position, lag.high, lag.lag()
+        ));
+    }
+    for line in lines {
+        if to_stderr {
+            errln!("{}", line);
+        } else {
+            outln!("{}", line);
+        }
+    }
+}
+
+/// Print the session statistics, as one `"type": "stats"` JSON line in raw mode
+fn print_stats(processor: &Processor, title: &str) {
+    let stats = processor.lock_stats();
+    if processor.raw {
+        match stats.to_json_line() {
+            Ok(json) => println!("{}", json),
+            Err(e) => errln!("Error serializing statistics: {}", e),
+        }
+    } else {
+        outln!("{}", stats.section(title).render(presenter::current()));
+    }
+}
+
+/// Serve Prometheus metrics in the background
+async fn start_metrics_server(addr: std::net::SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    start_http_server(addr, true, None).await
+}
+
+/// Serve /metrics (if `metrics`) and the health endpoints of `routes` in the background
+async fn start_http_server(
+    addr: std::net::SocketAddr,
+    metrics: bool,
+    routes: Option<Routes>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind HTTP address {}: {}", addr, e))?;
+    let health = routes.is_some();
+    tokio::spawn(metrics::serve_routes(listener, metrics, routes));
+    if metrics {
+        outln!("📈 Metrics: http://{}/metrics", addr);
+    }
+    if health {
+        outln!("🩺 Health: http://{}/healthz, /readyz and /status", addr);
+    }
+    Ok(())
+}
+
+/// Report the group's lag from its committed offsets until interrupted, without consuming
+async fn watch_lag(
+    consumer: Arc<StreamConsumer<KafkaClientContext>>,
+    topics: Vec<String>,
+    interval: Duration,
+    timeout: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut shutdown = spawn_signal_handler();
+    let mut tick = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {}
+            _ = shutdown.changed() => break,
+        }
+        let consumer = Arc::clone(&consumer);
+        let topics = topics.clone();
+        let lags = tokio::task::spawn_blocking(move || {
+            group_positions(consumer.as_ref(), &topics, timeout)
+                .and_then(|positions| fetch_lag(consumer.as_ref(), &positions, timeout))
+        })
+        .await?;
+        match lags {
+            Ok(lags) => {
+                record_lag_metrics(&lags);
+                outln!("🕒 {}", Utc::now().to_rfc3339());
+                print_lag(&lags, false);
+            }
+            Err(e) => errln!("⚠️  Failed to fetch consumer lag: {}", e),
+        }
+    }
+    outln!("\n👋 Stopped watching lag");
+    Ok(())
+}
+
+/// The newest matching proof read by `--latest` and where it was read
+struct LatestProof {
+    decoded: DecodedProof,
+    topic: String,
+    partition: i32,
+    offset: i64,
+    timestamp: Timestamp,
+}
+
+/// Print the newest proof monitoring `account` in the tail of the topics, exiting with
+/// [`EXIT_NO_MATCH`] if there is none
+///
+/// Partitions are assigned directly, so no group offsets move. The other
+/// `--filter-*` options narrow the match.
+async fn latest_proof(
+    args: &Args,
+    kafka: &KafkaConfig,
+    consumer: &StreamConsumer<KafkaClientContext>,
+    topics: &[String],
+    account: &str,
+    label_topics: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(args.connection_timeout);
+    let pubkey = parse_pubkey_or_hex(account).map_err(|e| format!("Invalid --latest account: {}", e))?;
+    // Record keys are base58, whichever form the account was given in
+    let account = bytes32_to_base58(&pubkey);
+    let filter = ProofFilter { account: Some(pubkey), ..build_filter(args)? };
+    let registry = build_registry(args);
+    
+    // A compacted topic keyed by account keeps each account's last record, however old
+    let mut key_index = false;
+    if args.account_keys {
+        let admin = create_admin_client(kafka)?;
+        key_index = true;
+        for topic in topics {
+            let compacted = is_compacted(&admin, topic, timeout)
+                .await
+                .map_err(|e| format!("Failed to describe the configuration of {}: {}", topic, e))?;
+            if !compacted {
+                errln!("⚠️  {} is not compacted; reading the lookback window and decoding every record", topic);
+                key_index = false;
+            }
+        }
+    }
+    let lookback = match &args.lookback_since {
+        _ if key_index => Lookback::Log,
+        Some(since) => Lookback::Since(parse_timestamp(since)?),
+        None => Lookback::Messages(args.lookback.unwrap_or(DEFAULT_LOOKBACK)),
+    };
+    
+    let windows = match tail_windows(consumer, topics, lookback, timeout) {
+        Ok(windows) => windows,
+        Err(e) => {
+            errln!("❌ Failed to resolve the lookback window: {}", e);
+            return Err(Box::new(e));
+        }
+    };
+    let mut tracker = WindowTracker::new(&windows);
+    let assignment = window_assignment(&windows)?;
+    let total: i64 = windows.iter().map(|window| window.range().end - window.range().start).sum();
+    match lookback {
+        Lookback::Log => outln!("🗂️  Compacted topic keyed by account: reading the whole log, matching record keys"),
+        Lookback::Since(millis) => outln!(
+            "⏪ Reading records since {}",
+            DateTime::<Utc>::from_timestamp_millis(millis)
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_else(|| millis.to_string())
+        ),
+        Lookback::Messages(count) => outln!("⏪ Reading the last {} message(s) of each partition", count),
+    }
+    outln!("🎯 Looking for the latest proof of {} in {} message(s) across {} partition(s)", account, total, assignment.count());
+    if assignment.count() > 0 {
+        if let Err(e) = consumer.assign(&assignment) {
+            errln!("❌ Failed to assign partitions: {}", e);
+            return Err(Box::new(e));
+        }
+    }
+    
+    let mut shutdown = spawn_signal_handler();
+    let mut window_tick = tokio::time::interval(Duration::from_secs(1));
+    let mut message_stream = consumer.stream();
+    let mut newest = Newest::new();
+    let mut read = 0;
+    while !tracker.is_done() {
+        let message = tokio::select! {
+            message = message_stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            _ = window_tick.tick() => {
+                // Windows can end in offsets that hold no message, e.g. transaction markers
+                if let Ok(positions) = consumer.position() {
+                    tracker.close_reached(&positions);
+                }
+                continue;
+            }
+            _ = shutdown.changed() => return Err("Interrupted before the lookback window was read".into()),
+        };
+        let msg = message.map_err(|e| format!("Failed to read the lookback window: {}", e))?;
+        if !tracker.observe(msg.topic(), msg.partition(), msg.offset()) {
+            continue;
+        }
+        read += 1;
+        // Records keyed by other accounts are skipped without decoding them
+        if key_index && msg.key() != Some(account.as_bytes()) {
+            continue;
+        }
+        let Some(payload) = msg.payload() else { continue };
+        let decoded = match decode_payload(payload, PayloadFormat::of_message(&msg, payload), registry.as_ref()).await {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                if args.debug {
+                    errln!("⚠️  Skipping partition {} offset {}: {}", msg.partition(), msg.offset(), e);
+                }
+                continue;
+            }
+        };
+        if filter.apply(&decoded.proof) != FilterOutcome::Matched {
+            continue;
+        }
+        let Some(commitments) = decode_commitments(&decoded.proof) else { continue };
+        let rank = ProofRank { end_slot: commitments.end_slot, timestamp: msg.timestamp().to_millis() };
+        newest.offer(rank, LatestProof {
+            decoded,
+            topic: msg.topic().to_string(),
+            partition: msg.partition(),
+            offset: msg.offset(),
+            timestamp: msg.timestamp(),
+        });
+    }
+    
+    let candidates = newest.candidates();
+    let Some((rank, found)) = newest.into_best() else {
+        errln!("❌ No proof of {} among {} message(s) read", account, read);
+        std::process::exit(EXIT_NO_MATCH);
+    };
+    outln!(
+        "✅ Newest of {} matching proof(s) among {} message(s) read: end slot {}",
+        candidates, read, rank.end_slot
+    );
+    if !args.minimal && !args.raw {
+        outln!("📬 Topic: {} | Partition: {} | Offset: {}", found.topic, found.partition, found.offset);
+        let lag = found
+            .timestamp
+            .to_millis()
+            .map(|timestamp| (Utc::now().timestamp_millis() - timestamp).max(0) as f64 / 1000.0);
+        print_record_timestamp(found.timestamp, lag);
+    }
+    print_proof_details(&found.decoded, label_topics.then_some(found.topic.as_str()), args.raw, args.minimal, true);
+    Ok(())
+}
+
+/// Default consumer group when not replaying
+const DEFAULT_GROUP_ID: &str = "solana-proof-consumer";
+
+/// Resolve `--from-offset` / `--from-timestamp` / `--replay` into a replay start
+///
+/// Replays assign partitions directly, so they cannot be combined with a
+/// consumer group or group offset commits.
+fn replay_start(args: &Args) -> Result<Option<ReplayStart>, Box<dyn std::error::Error>> {
+    let start = if !args.from_offset.is_empty() {
+        ReplayStart::Offsets(args.from_offset.clone())
+    } else if let Some(timestamp) = &args.from_timestamp {
+        ReplayStart::Timestamp(parse_timestamp(timestamp)?)
+    } else if !args.replay.is_empty() {
+        ReplayStart::Windows(args.replay.clone())
+    } else {
+        return Ok(None);
+    };
     
-    /// Client key file path
-    #[arg(long, default_value = "./user.key")]
-    client_key: String,
+    if let Some(group_id) = &args.group_id {
+        return Err(format!(
+            "--from-offset/--from-timestamp/--replay replay without a consumer group and cannot be combined with --group-id {}",
+            group_id
+        )
+        .into());
+    }
+    if args.commit == CommitStrategy::Manual {
+        return Err("--from-offset/--from-timestamp/--replay do not commit offsets and cannot be combined with --commit manual".into());
+    }
+    Ok(Some(start))
+}
+
+/// Copy the headers of a message so it can be processed after the next poll
+fn owned_headers(msg: &BorrowedMessage<'_>) -> Vec<(String, Option<Vec<u8>>)> {
+    msg.headers()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|header| (header.key.to_string(), header.value.map(<[u8]>::to_vec)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Print the failure details of a dead-letter message, if it carries DLQ headers
+fn print_dead_letter_headers(headers: &[(String, Option<Vec<u8>>)]) {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .and_then(|(_, value)| value.as_deref())
+            .map(|v| String::from_utf8_lossy(v).to_string())
+    };
     
-    /// Disable TLS (use plain connection)
-    #[arg(long)]
-    no_tls: bool,
+    let Some(reason) = header(DLQ_REASON_HEADER) else { return };
+    outln!("☠️  Dead letter ({})", reason);
+    outln!("   Original topic: {}", header(DLQ_ORIGINAL_TOPIC_HEADER).unwrap_or_else(|| "unknown".to_string()));
+    outln!("   Failed at: {}", header(DLQ_FAILED_AT_HEADER).unwrap_or_else(|| "unknown".to_string()));
+    outln!("   Attempts: {}", header(DLQ_ATTEMPTS_HEADER).unwrap_or_else(|| "unknown".to_string()));
+    outln!("   Error: {}", header(DLQ_ERROR_HEADER).unwrap_or_else(|| "unknown".to_string()));
 }
 
-fn format_bytes(bytes: &[u8], max_len: usize) -> String {
-    if bytes.len() <= max_len {
-        hex::encode(bytes)
-    } else {
-        format!("{}... ({} bytes)", hex::encode(&bytes[..max_len]), bytes.len())
+/// Check the raw account data sent in the envelope's metadata against the committed hashes
+fn check_account_data(decoded: &DecodedProof, commitments: &PublicCommitments, quiet: bool) {
+    let Some(accounts) = decoded.metadata.as_ref().and_then(account_data::from_metadata) else {
+        return;
+    };
+    match accounts.and_then(|accounts| account_data::verify(commitments, &accounts)) {
+        Ok(bytes) if !quiet => outln!("🧮 Account data ({} bytes) matches the committed hashes", bytes),
+        Ok(_) => {}
+        Err(e) => errln!("❌ {}: {}", decoded.proof.identifier, presenter::current().paint(e, Tone::Bad)),
     }
 }
 
-fn print_proof_details(proof: &ZkProof, raw: bool, minimal: bool) {
+/// Print a decoded proof; `topic` labels the output when several topics are consumed
+fn print_proof_details(
+    decoded: &DecodedProof,
+    topic: Option<&str>,
+    raw: bool,
+    minimal: bool,
+    show_commitments: bool,
+) {
+    let proof = &decoded.proof;
     let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+    let label = topic.map(|topic| format!("[{}] ", topic)).unwrap_or_default();
+    let separator = topic.map(|topic| format!("--- {}", topic)).unwrap_or_else(|| "---".to_string());
     
     if minimal {
-        println!("[{}] Proof ID: {}", timestamp, proof.identifier);
+        outln!("[{}] {}Proof ID: {}", timestamp, label, proof.identifier);
+        return;
+    }
+    
+    if raw && !show_commitments {
+        outln!("[{}] {}Proof ID: {} (withheld: failed verification)", timestamp, label, proof.identifier);
+        outln!("{}", separator);
         return;
     }
     
@@ -107,90 +1235,215 @@ fn print_proof_details(proof: &ZkProof, raw: bool, minimal: bool) {
         // Print raw JSON
         match serde_json::to_string_pretty(proof) {
             Ok(json) => println!("{}", json),
-            Err(e) => eprintln!("Error serializing proof: {}", e),
+            Err(e) => errln!("Error serializing proof: {}", e),
         }
-        println!("---");
+        outln!("{}", separator);
         return;
     }
     
-    // Pretty print proof details
-    println!("\n╔══════════════════════════════════════════════════════════════════════");
-    println!("║ 📦 New Proof Received at {}", timestamp);
-    println!("╟──────────────────────────────────────────────────────────────────────");
-    println!("║ Identifier: {}", proof.identifier);
-    println!("║ Proof Kind: {:?}", proof.proof_kind);
+    // Print proof details as a box, or aligned lines in the plain style
+    let index = execution_index(proof);
+    let mut section = match index {
+        Some(_) => Section::new(format!("⚙️  New Execution Proof Received at {}", timestamp)),
+        None => Section::new(format!("📦 New Proof Received at {}", timestamp)),
+    };
+    if let Some(topic) = topic {
+        section.field(0, "Topic", topic);
+    }
+    section.field(0, "Identifier", &proof.identifier).field(0, "Proof Kind", &decoded.kind);
+    if let Some(index) = index {
+        section.field(0, "Execution Index", index);
+    }
+    if decoded.shape == EnvelopeShape::Legacy {
+        section.field(0, "Envelope", "legacy (hex-encoded fields)");
+    }
     
     match &proof.proof_data {
         ProofData::SP1(sp1_proof) => {
-            println!("║ Proof Type: SP1");
-            println!("║ Version: {}", sp1_proof.version);
-            println!("║ Verification Key: {}", hex::encode(&sp1_proof.verification_key));
-            println!("║ Proof Size: {} bytes", sp1_proof.proof.len());
-            println!("║ Public Values Size: {} bytes", sp1_proof.public_value.len());
+            section
+                .field(0, "Proof Type", "SP1")
+                .field(0, "Version", sp1_proof.version)
+                .field(0, "Verification Key", hex::encode(sp1_proof.verification_key))
+                .field(0, "Proof Size", format!("{} bytes", sp1_proof.proof.len()))
+                .field(0, "Public Values Size", format!("{} bytes", sp1_proof.public_value.len()));
             
-            // Try to decode public values as PublicCommitments
-            if let Ok(commitments) = bincode::deserialize::<solana_stub_prover_lib::PublicCommitments>(&sp1_proof.public_value) {
-                println!("║");
-                println!("║ 📊 Public Commitments:");
-                println!("║   Start Slot: {}", commitments.start_slot);
-                println!("║   End Slot: {}", commitments.end_slot);
-                println!("║   Epoch: {}", commitments.epoch);
-                println!("║   Original Bank Hash: {}", format_bytes(&commitments.original_bank_hash, 8));
-                println!("║   Last Bank Hash: {}", format_bytes(&commitments.last_bank_hash, 8));
-                println!("║   Account Data Hash: {}", format_bytes(&commitments.account_data_hash, 8));
-                println!("║   Validator Set Hash: {}", format_bytes(&commitments.hash_root_valset, 8));
-                println!("║   Total Active Stake: {}", commitments.total_active_stake);
-                println!("║   Validator Count: {}", commitments.validator_count);
-                println!("║   Monitored Accounts: {}", commitments.monitored_accounts_state.len());
-                println!("║   Validations Passed: {}", commitments.validations_passed);
-                
-                for (i, account) in commitments.monitored_accounts_state.iter().enumerate() {
-                    println!("║");
-                    println!("║   Account #{}: {}", i + 1, format_bytes(&account.account_pubkey, 8));
-                    println!("║     Last Change Slot: {}", account.last_change_slot);
-                    println!("║     Lamports: {}", account.lamports);
-                    println!("║     Executable: {}", account.executable);
-                    println!("║     Data Size: {} bytes", account.data.len());
+            let wrapped = WrappedProof::decode(&sp1_proof.proof);
+            match &wrapped {
+                Ok(wrapped) => {
+                    section.field(0, "Wrapped Proof", format!("{} ({})", wrapped.kind(), wrapped.encoding));
+                    if wrapped.public_values() != sp1_proof.public_value.as_slice() {
+                        section.field(1, "Embedded Public Values", "DIFFER from the message's public values");
+                    }
+                    if let Some(snark) = wrapped.snark() {
+                        let expected_digest = public_values_digest(wrapped.public_values());
+                        let digest = match &snark.public_values_digest {
+                            Some(digest) if *digest == expected_digest => format!("{} (matches public values)", digest),
+                            Some(digest) => format!("{} (MISMATCH: public values hash to {})", digest, expected_digest),
+                            None => "unavailable".to_string(),
+                        };
+                        section
+                            .blank()
+                            .heading(0, format!("🔐 {} Proof:", snark.system))
+                            .field(1, "Program VKey Hash", snark.vkey_hash.as_deref().unwrap_or("unavailable"))
+                            .field(1, "Public Values Digest", digest)
+                            .field(1, "Raw Proof Size", format!("{} bytes", snark.raw_proof_size))
+                            .field(1, "Encoded Proof Size", format!("{} bytes", snark.encoded_proof_size))
+                            .field(1, "Circuit VKey Hash", hex::encode(snark.circuit_vkey_hash));
+                    }
+                }
+                Err(e) => {
+                    section.field(0, "Wrapped Proof", e);
+                }
+            }
+            
+            // Try to decode public values as PublicCommitments, unless withheld after failed verification;
+            // execution proofs carry no Solana commitments. Public values that do not decode fall back
+            // to the ones embedded in the wrapped proof.
+            if index.is_none() {
+                let embedded = || {
+                    let wrapped = wrapped.as_ref().ok()?;
+                    bincode::deserialize::<PublicCommitments>(wrapped.public_values()).ok()
+                };
+                if !show_commitments {
+                    section.blank().note("🚫 Public commitments withheld: proof failed verification");
+                } else if let Some(commitments) = decode_commitments(proof) {
+                    section.blank().heading(0, "📊 Public Commitments:");
+                    commitment_rows(&mut section, &commitments, 1);
+                } else if let Some(commitments) = embedded() {
+                    section.blank().heading(0, "📊 Public Commitments (from the wrapped proof):");
+                    commitment_rows(&mut section, &commitments, 1);
+                } else {
+                    section.heading(1, "(Unable to decode public commitments)");
                 }
-            } else {
-                println!("║   (Unable to decode public commitments)");
             }
         }
     }
     
-    println!("╚══════════════════════════════════════════════════════════════════════");
+    println!("{}", section.render(presenter::current()));
 }
 
-async fn test_connection(broker: &str, timeout_secs: u64, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔄 Testing connection to broker: {}", broker);
+/// Build the Kafka configuration from `KAFKA_*` environment variables overridden by CLI flags
+fn build_kafka_config(args: &Args) -> Result<KafkaConfig, KafkaConfigError> {
+    let kafka = KafkaConfig::from_env_with(&ConnectionOptions {
+        broker: args.broker.clone(),
+        client_id: args.client_id.clone(),
+        disable_tls: args.no_tls || !args.tls,
+        protocol: args.security_protocol,
+        auth: args.kafka_auth,
+        sasl_mechanism: args.sasl_mechanism.or(args.sasl.then_some(SaslMechanism::Plain)),
+        sasl_username: args.username.clone(),
+        sasl_password: args.password.clone(),
+        aws_region: args.aws_region.clone(),
+        aws_role_arn: args.aws_role_arn.clone(),
+        ca_cert_path: args.ca_cert.clone(),
+        client_cert_path: args.client_cert.clone(),
+        client_key_path: args.client_key.clone(),
+        allow_missing_certs: args.allow_missing_certs,
+    })?;
     
-    let mut test_config = ClientConfig::new();
+    kafka.validate()?;
+    Ok(kafka)
+}
+
+/// Build the group session and fetch settings, rejecting invalid combinations before connecting
+fn build_consumer_tuning(args: &Args) -> Result<ConsumerTuning, KafkaConfigError> {
+    let tuning = ConsumerTuning {
+        session_timeout_ms: args.session_timeout_ms,
+        max_poll_interval_ms: args.max_poll_interval_ms,
+        fetch_max_bytes: args.fetch_max_bytes,
+    };
+    tuning.validate()?;
+    Ok(tuning)
+}
+
+/// Archive a message, reporting failures without interrupting the consume loop
+///
+/// Returns `None` if writing failed; the failure has already been reported.
+fn save_message(
+    archive: &mut ProofArchive,
+    proof: Option<&ZkProof>,
+    partition: i32,
+    offset: i64,
+    payload: &[u8],
+    quiet: bool,
+) -> Option<ArchiveOutcome> {
+    let identifier = proof.map_or("undecoded", |proof| proof.identifier.as_str());
+    let commitments = proof.and_then(decode_commitments);
+    
+    match archive.save(identifier, partition, offset, payload, commitments.as_ref()) {
+        Ok(outcome) => {
+            match &outcome {
+                ArchiveOutcome::Saved(saved) if !quiet => {
+                    outln!("💾 Saved to {}", saved.payload_path.display());
+                }
+                ArchiveOutcome::Saved(_) => {}
+                ArchiveOutcome::CapReached { size, limit } => {
+                    errln!(
+                        "⚠️  Save directory limit reached ({} bytes would exceed {} bytes), no longer saving messages",
+                        size, limit
+                    );
+                }
+            }
+            Some(outcome)
+        }
+        Err(e) => {
+            errln!("❌ Failed to save message at partition {} offset {}: {}", partition, offset, e);
+            None
+        }
+    }
+}
+
+/// Commit stored offsets, treating "nothing to commit" as success
+fn commit_offsets(
+    consumer: &StreamConsumer<KafkaClientContext>,
+    batcher: &mut CommitBatcher,
+    mode: CommitMode,
+) {
+    match consumer.commit_consumer_state(mode) {
+        Ok(()) | Err(KafkaError::ConsumerCommit(RDKafkaErrorCode::NoOffset)) => batcher.committed(),
+        Err(e) => errln!("⚠️  Failed to commit offsets ({} messages pending): {}", batcher.pending(), e),
+    }
+}
+
+/// Print the record timestamp and how far it lags behind the wall clock
+fn print_record_timestamp(timestamp: Timestamp, lag: Option<f64>) {
+    let (kind, millis) = match timestamp {
+        Timestamp::NotAvailable => {
+            outln!("🕒 Timestamp: not available");
+            return;
+        }
+        Timestamp::CreateTime(millis) => ("create time", millis),
+        Timestamp::LogAppendTime(millis) => ("log append time", millis),
+    };
+    let time = DateTime::<Utc>::from_timestamp_millis(millis)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| millis.to_string());
+    outln!("🕒 Timestamp: {} ({}) | Lag: {:.3}s", time, kind, lag.unwrap_or_default());
+}
+
+async fn test_connection(kafka: &KafkaConfig, timeout_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let broker = kafka.broker();
+    outln!("🔄 Testing connection to broker: {}", broker);
+    
+    // Brokers, client ID, TLS and SASL, as for the consumer itself
+    let mut test_config = kafka.client_config();
     test_config
-        .set("bootstrap.servers", broker)
         .set("group.id", "connection-test")
         .set("socket.timeout.ms", &format!("{}", timeout_secs * 1000))
         .set("session.timeout.ms", "6000");
     
-    // Configure TLS if enabled
-    let use_tls = !args.no_tls && args.tls;
-    if use_tls {
-        test_config.set("security.protocol", "ssl");
-        test_config.set("ssl.ca.location", &args.ca_cert);
-        test_config.set("ssl.certificate.location", &args.client_cert);
-        test_config.set("ssl.key.location", &args.client_key);
-    }
-    
-    let test_consumer: Result<StreamConsumer, _> = test_config.create();
+    let test_consumer: Result<StreamConsumer<KafkaClientContext>, _> =
+        test_config.create_with_context(kafka.client_context());
     
     match test_consumer {
         Ok(consumer) => {
             // Try to get metadata (this is a synchronous call)
             match consumer.fetch_metadata(None, Duration::from_secs(timeout_secs)) {
                 Ok(metadata) => {
-                    println!("✅ Successfully connected to broker");
-                    println!("   Broker count: {}", metadata.brokers().len());
+                    outln!("✅ Successfully connected to broker");
+                    outln!("   Broker count: {}", metadata.brokers().len());
                     for broker in metadata.brokers() {
-                        println!("   - Broker {}: {}:{}", broker.id(), broker.host(), broker.port());
+                        outln!("   - Broker {}: {}:{}", broker.id(), broker.host(), broker.port());
                     }
                     Ok(())
                 }
@@ -207,119 +1460,152 @@ async fn test_connection(broker: &str, timeout_secs: u64, args: &Args) -> Result
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+    
     // Parse arguments
     let args = Args::parse();
+    Presenter::new(args.style, args.color).install();
     
-    // Determine if TLS should be used
-    let use_tls = !args.no_tls && args.tls;
-    
-    // Determine broker address
-    let broker = args.broker.clone().unwrap_or_else(|| {
-        if use_tls {
-            DEFAULT_KAFKA_BROKER_TLS.to_string()
-        } else {
-            DEFAULT_KAFKA_BROKER_PLAIN.to_string()
+    // Resolve and validate the Kafka configuration
+    let kafka = match build_kafka_config(&args) {
+        Ok(kafka) => kafka,
+        Err(e) => {
+            errln!("❌ Invalid Kafka configuration: {}", e);
+            return Err(Box::new(e));
         }
-    });
+    };
+    let tuning = match build_consumer_tuning(&args) {
+        Ok(tuning) => tuning,
+        Err(e) => {
+            errln!("❌ Invalid Kafka configuration: {}", e);
+            return Err(Box::new(e));
+        }
+    };
+    let use_tls = kafka.use_tls;
+    let broker = kafka.broker().to_string();
+    // Topics to read; a pattern subscribes with librdkafka's `^` regex syntax
+    let topics: Vec<String> = match (&args.dlq_topic, &args.topic_pattern) {
+        (Some(dlq_topic), _) => vec![dlq_topic.clone()],
+        (None, Some(pattern)) if pattern.starts_with('^') => vec![pattern.clone()],
+        (None, Some(pattern)) => vec![format!("^{}", pattern)],
+        (None, None) if !args.topic.is_empty() => args.topic.clone(),
+        (None, None) => vec![kafka.topic.clone()],
+    };
+    // Output and metrics are labeled with the source topic when more than one topic can appear
+    let label_topics = topics.len() > 1 || args.topic_pattern.is_some();
+    let replay = match replay_start(&args) {
+        Ok(replay) => replay,
+        Err(e) => {
+            errln!("❌ {}", e);
+            return Err(e);
+        }
+    };
+    let group_id = args.group_id.clone().unwrap_or_else(|| DEFAULT_GROUP_ID.to_string());
     
-    println!("🚀 Starting Kafka Consumer");
-    println!("📍 Broker(s): {}", broker);
-    println!("📨 Topic: {}", KAFKA_TOPIC);
-    println!("👥 Group ID: {}", args.group_id);
-    println!("🔐 Security Protocol: {}", if use_tls { "SSL/TLS" } else { args.security_protocol.as_str() });
+    outln!("🚀 Starting Kafka Consumer");
+    outln!("📍 Broker(s): {}", broker);
+    if args.topic_pattern.is_some() {
+        outln!("📨 Topic pattern: {}", topics[0]);
+    } else {
+        outln!("📨 Topic{}: {}", if topics.len() > 1 { "s" } else { "" }, topics.join(", "));
+    }
+    match &replay {
+        Some(ReplayStart::Offsets(offsets)) => outln!(
+            "⏪ Replaying from offsets: {} (no consumer group)",
+            offsets.iter().map(|start| start.to_string()).collect::<Vec<_>>().join(", ")
+        ),
+        Some(ReplayStart::Timestamp(millis)) => outln!(
+            "⏪ Replaying from {} (no consumer group)",
+            DateTime::<Utc>::from_timestamp_millis(*millis)
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_else(|| millis.to_string())
+        ),
+        Some(ReplayStart::Windows(windows)) => outln!(
+            "⏪ Replaying offset windows: {} (no consumer group)",
+            windows.iter().map(|window| window.to_string()).collect::<Vec<_>>().join(", ")
+        ),
+        None if args.latest.is_some() => outln!("🎯 Looking up the latest proof (no consumer group)"),
+        None => outln!("👥 Group ID: {}", group_id),
+    }
+    outln!("🔐 Security Protocol: {}", kafka.security_summary());
+    if let Some(client_id) = &kafka.client_id {
+        outln!("🏷️  Client ID: {}", client_id);
+    }
     
-    if args.sasl {
-        println!("🔑 SASL Authentication: Enabled");
-        println!("   Mechanism: {}", args.sasl_mechanism);
-        if args.username.is_some() {
-            println!("   Username: ***");
+    if let Some(mechanism) = kafka.sasl_mechanism {
+        outln!("🔑 SASL Authentication: Enabled");
+        outln!("   Mechanism: {}", mechanism);
+        if kafka.sasl_username.is_some() {
+            outln!("   Username: ***");
         }
     }
     
     if args.from_beginning {
-        println!("⏮️  Reading from beginning of topic");
+        outln!("⏮️  Reading from beginning of topic");
     }
     if args.minimal {
-        println!("📝 Minimal output mode");
+        outln!("📝 Minimal output mode");
     } else if args.raw {
-        println!("📝 Raw JSON output mode");
+        outln!("📝 Raw JSON output mode");
     }
     
-    println!("────────────────────────────────────────────────────────────────────");
+    outln!("────────────────────────────────────────────────────────────────────");
     
     // Show TLS configuration if enabled
     if use_tls {
-        println!("🔒 TLS Configuration:");
-        println!("   CA Certificate: {}", args.ca_cert);
-        println!("   Client Certificate: {}", args.client_cert);
-        println!("   Client Key: {}", args.client_key);
-        
-        // Check if certificate files exist
-        use std::path::Path;
-        if !Path::new(&args.ca_cert).exists() {
-            eprintln!("⚠️  Warning: CA certificate not found at {}", args.ca_cert);
-        }
-        if !Path::new(&args.client_cert).exists() {
-            eprintln!("⚠️  Warning: Client certificate not found at {}", args.client_cert);
-        }
-        if !Path::new(&args.client_key).exists() {
-            eprintln!("⚠️  Warning: Client key not found at {}", args.client_key);
-        }
+        outln!("🔒 TLS Configuration:");
+        outln!("   CA Certificate: {}", kafka.ca_cert_path.as_deref().unwrap_or("(none)"));
+        outln!("   Client Certificate: {}", kafka.client_cert_path.as_deref().unwrap_or("(none)"));
+        outln!("   Client Key: {}", kafka.client_key_path.as_deref().unwrap_or("(none)"));
     }
     
     // Test connection first
-    if let Err(e) = test_connection(&broker, args.connection_timeout, &args).await {
-        eprintln!("\n❌ Connection test failed: {}", e);
-        eprintln!("\n🔍 Troubleshooting tips:");
-        eprintln!("   1. Check if the broker address is correct: {}", broker);
-        eprintln!("   2. Verify network connectivity to the broker");
-        eprintln!("   3. Check if authentication is required (use --sasl flag)");
-        eprintln!("   4. Ensure the broker port is not blocked by firewall");
-        eprintln!("   5. Try using a different security protocol (--security-protocol)");
-        eprintln!("\nYou can also try:");
-        eprintln!("   - Using localhost:9092 for local Kafka");
-        eprintln!("   - Setting KAFKA_USERNAME and KAFKA_PASSWORD environment variables");
-        eprintln!("   - Using --debug flag for more detailed error messages");
+    if let Err(e) = test_connection(&kafka, args.connection_timeout).await {
+        errln!("\n❌ Connection test failed: {}", e);
+        errln!("\n🔍 Troubleshooting tips:");
+        errln!("   1. Check if the broker address is correct: {}", broker);
+        errln!("   2. Verify network connectivity to the broker");
+        errln!("   3. Check if authentication is required (use --sasl flag)");
+        errln!("   4. Ensure the broker port is not blocked by firewall");
+        errln!("   5. Try using a different security protocol (--security-protocol)");
+        errln!("\nYou can also try:");
+        errln!("   - Using localhost:9092 for local Kafka");
+        errln!("   - Setting KAFKA_USERNAME and KAFKA_PASSWORD environment variables");
+        errln!("   - Using --debug flag for more detailed error messages");
         
         if !args.debug {
             return Err(e);
         }
         
-        eprintln!("\n⚠️  Debug mode enabled, continuing despite connection test failure...");
+        errln!("\n⚠️  Debug mode enabled, continuing despite connection test failure...");
     }
     
-    // Create consumer configuration
-    let mut config = ClientConfig::new();
+    // Create consumer configuration; brokers, client ID, TLS and SASL (sasl_ssl applies both) come from the shared builder
+    let mut config = kafka.client_config();
     config
-        .set("bootstrap.servers", &broker)
-        .set("group.id", &args.group_id)
-        .set("enable.auto.commit", "true")
-        .set("auto.commit.interval.ms", "1000")
-        .set("session.timeout.ms", "6000")
         .set("socket.timeout.ms", &format!("{}", args.connection_timeout * 1000))
-        .set("api.version.request.timeout.ms", "10000")
-        .set("enable.auto.offset.store", "true");
+        .set("api.version.request.timeout.ms", "10000");
+    tuning.apply(&mut config);
     
-    // Configure TLS if enabled
-    if use_tls {
-        config.set("security.protocol", "ssl");
-        config.set("ssl.ca.location", &args.ca_cert);
-        config.set("ssl.certificate.location", &args.client_cert);
-        config.set("ssl.key.location", &args.client_key);
-    } else {
-        config.set("security.protocol", &args.security_protocol);
-        
-        // Add SASL configuration if enabled and not using TLS
-        if args.sasl {
-            config.set("sasl.mechanism", &args.sasl_mechanism);
-            
-            if let Some(username) = &args.username {
-                config.set("sasl.username", username);
-            }
-            
-            if let Some(password) = &args.password {
-                config.set("sasl.password", password);
-            }
+    // Replays and --latest assign partitions directly
+    match (replay.is_some() || args.latest.is_some(), args.commit) {
+        (true, _) => {
+            // Assigned partitions outside a group have nowhere to commit to
+            config.set("enable.auto.commit", "false");
+        }
+        (false, CommitStrategy::Auto) => {
+            config
+                .set("group.id", &group_id)
+                .set("enable.auto.commit", "true")
+                .set("auto.commit.interval.ms", "1000")
+                .set("enable.auto.offset.store", "true");
+        }
+        (false, CommitStrategy::Manual) => {
+            // Offsets are stored after processing and committed by the consume loop
+            config
+                .set("group.id", &group_id)
+                .set("enable.auto.commit", "false")
+                .set("enable.auto.offset.store", "false");
         }
     }
     
@@ -335,41 +1621,492 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     // Create consumer
-    let consumer: StreamConsumer = match config.create() {
-        Ok(c) => c,
+    let consumer: Arc<StreamConsumer<KafkaClientContext>> = match config.create_with_context(kafka.client_context()) {
+        Ok(c) => Arc::new(c),
         Err(e) => {
-            eprintln!("❌ Failed to create consumer: {}", e);
-            eprintln!("\n💡 Possible solutions:");
-            eprintln!("   - Check broker connectivity: telnet {} <port>", 
+            errln!("❌ Failed to create consumer: {}", e);
+            errln!("\n💡 Possible solutions:");
+            errln!("   - Check broker connectivity: telnet {} <port>", 
                      args.broker.as_ref().map(|b| b.split(':').next().unwrap_or(b)).unwrap_or(&broker));
-            eprintln!("   - Verify Kafka is running on the specified broker");
-            eprintln!("   - Check authentication credentials if SASL is enabled");
+            errln!("   - Verify Kafka is running on the specified broker");
+            errln!("   - Check authentication credentials if SASL is enabled");
             return Err(Box::new(e));
         }
     };
     
-    // Subscribe to topic
-    match consumer.subscribe(&[KAFKA_TOPIC]) {
-        Ok(_) => println!("✅ Subscribed to topic: {}", KAFKA_TOPIC),
-        Err(e) => {
-            eprintln!("❌ Failed to subscribe to topic: {}", e);
+    let lag_interval = Duration::from_secs(args.lag_interval.unwrap_or(DEFAULT_LAG_INTERVAL_SECS));
+    if args.watch_lag {
+        if topics.iter().any(|topic| topic.starts_with('^')) {
+            return Err("--watch-lag needs explicit topics, not a --topic-pattern".into());
+        }
+        if let Some(addr) = args.metrics_addr {
+            start_metrics_server(addr).await?;
+        }
+        outln!("📊 Watching lag of group {} every {:?} (Press Ctrl+C to stop)\n", group_id, lag_interval);
+        return watch_lag(consumer, topics, lag_interval, Duration::from_secs(args.connection_timeout)).await;
+    }
+    if let Some(account) = &args.latest {
+        return latest_proof(&args, &kafka, &consumer, &topics, account, label_topics).await;
+    }
+    
+    if args.commit == CommitStrategy::Manual {
+        consumer.context().commit_before_revoke(&consumer);
+        outln!(
+            "📌 Manual offset commits every {} messages or {}ms",
+            args.commit_batch, args.commit_interval_ms
+        );
+    }
+    
+    let mut windows = None;
+    if let Some(start) = &replay {
+        // Assign the partitions at the resolved offsets instead of joining a group
+        let timeout = Duration::from_secs(args.connection_timeout);
+        let resolved = match start {
+            ReplayStart::Windows(requested) => {
+                let resolved = match resolve_windows(consumer.as_ref(), &topics, requested, timeout) {
+                    Ok(resolved) => resolved,
+                    Err(e) => {
+                        errln!("❌ Failed to fetch partition watermarks: {}", e);
+                        return Err(Box::new(e));
+                    }
+                };
+                for window in &resolved {
+                    let requested = format!("{} partition {} window {}..{}", window.topic, window.partition, window.requested.start, window.requested.end);
+                    if window.starts_before_log() {
+                        errln!("⚠️  {} starts below the log start offset {}; earlier messages were deleted", requested, window.low);
+                    }
+                    if window.range().is_empty() {
+                        errln!("⚠️  {} has no messages (partition holds offsets {}..{})", requested, window.low, window.high);
+                    } else if window.ends_after_log() {
+                        errln!("⚠️  {} ends beyond the high watermark {}; replaying up to it", requested, window.high);
+                    }
+                }
+                windows = Some(WindowTracker::new(&resolved));
+                window_assignment(&resolved)
+            }
+            start => resolve_assignment(consumer.as_ref(), &topics, start, timeout),
+        };
+        let assignment = match resolved {
+            Ok(assignment) if assignment.count() > 0 => assignment,
+            Ok(_) => {
+                errln!("❌ No partitions to replay in {}", topics.join(", "));
+                return Err(format!("No partitions to replay in {}", topics.join(", ")).into());
+            }
+            Err(e) => {
+                errln!("❌ Failed to resolve starting offsets: {}", e);
+                return Err(Box::new(e));
+            }
+        };
+        
+        outln!("✅ Assigned {} partition(s) of: {}", assignment.count(), topics.join(", "));
+        for element in assignment.elements() {
+            let offset = match element.offset() {
+                Offset::Offset(offset) => offset.to_string(),
+                Offset::End => "end (no records at or after the timestamp)".to_string(),
+                other => format!("{:?}", other),
+            };
+            outln!("   {} partition {}: starting at offset {}", element.topic(), element.partition(), offset);
+        }
+        if let Err(e) = consumer.assign(&assignment) {
+            errln!("❌ Failed to assign partitions: {}", e);
             return Err(Box::new(e));
         }
+    } else {
+        // Subscribe to the topics
+        let subscription: Vec<&str> = topics.iter().map(String::as_str).collect();
+        match consumer.subscribe(&subscription) {
+            Ok(_) => outln!("✅ Subscribed to: {}", topics.join(", ")),
+            Err(e) => {
+                errln!("❌ Failed to subscribe to topic: {}", e);
+                return Err(Box::new(e));
+            }
+        }
+    }
+    
+    let registry = build_registry(&args);
+    
+    let filter = build_filter(&args)?;
+    if !filter.is_empty() {
+        outln!("🔎 Filters:");
+        if let Some(slot) = args.filter_start_slot {
+            outln!("   Start slot >= {}", slot);
+        }
+        if let Some(slot) = args.filter_end_slot {
+            outln!("   End slot <= {}", slot);
+        }
+        if let Some(account) = &args.filter_account {
+            outln!("   Account: {}", account);
+        }
+        if let Some(prefix) = &args.filter_identifier_prefix {
+            outln!("   Identifier prefix: {}", prefix);
+        }
+        if args.kind != KindFilter::All {
+            outln!("   Kind: {}", args.kind);
+        }
+        if !args.filter_topic.is_empty() {
+            outln!("   Only on topics: {}", args.filter_topic.join(", "));
+        }
+    }
+    
+    let archive = match &args.save_dir {
+        Some(dir) => {
+            let archive = ProofArchive::open(dir.clone(), args.save_max_bytes)
+                .map_err(|e| format!("Failed to open save directory {}: {}", dir.display(), e))?;
+            outln!("💾 Saving messages to: {}", dir.display());
+            if let Some(limit) = args.save_max_bytes {
+                outln!("   Size limit: {} bytes ({} bytes used)", limit, archive.size());
+            }
+            Some(archive)
+        }
+        None => None,
+    };
+    let sqlite = match &args.sqlite {
+        Some(path) => {
+            let sink = SqliteSink::open(path, !args.sqlite_no_raw)
+                .map_err(|e| format!("Failed to open SQLite database {}: {}", path.display(), e))?;
+            outln!(
+                "🗄️  Storing commitments in SQLite: {}{}",
+                path.display(),
+                if args.sqlite_no_raw { " (without raw payloads)" } else { "" }
+            );
+            Some(sink)
+        }
+        None => None,
+    };
+    
+    let csv = match &args.csv {
+        Some(path) => {
+            let export = CsvExport::open(path)
+                .map_err(|e| format!("Failed to open CSV file {}: {}", path.display(), e))?;
+            outln!("📄 Appending commitment rows to: {}", path.display());
+            Some(export)
+        }
+        None => None,
+    };
+    
+    let verifier = build_verifier(&args)?;
+    if verifier.is_some() {
+        match (&args.vkey, &args.vkey_hash) {
+            (Some(path), _) => outln!("🔏 Verifying proofs with key: {}", path.display()),
+            (None, Some(hash)) => outln!("🔏 Verifying Groth16 proofs with vkey hash: {}", hash),
+            (None, None) => {}
+        }
+        if args.reject_unverified {
+            outln!("   Commitments of unverified proofs are withheld");
+        }
+    }
+    let vkey_registry = match (&args.expected_vkey_hash, &args.vkey_registry) {
+        (Some(hash), _) => Some(VkeyRegistry::current(hash)?),
+        (None, Some(path)) => Some(VkeyRegistry::load(path)?),
+        // The key the consumer was built next to
+        (None, None) => Some(VkeyRegistry::pinned()).filter(|registry| !registry.is_empty()),
+    };
+    if let Some(registry) = &vkey_registry {
+        outln!("🔑 Flagging proofs under program vkey hashes other than {} known one(s)", registry.len());
+    }
+    
+    let mut dedup = args.dedup_window.map(|capacity| {
+        outln!(
+            "🔁 Detecting duplicates among the last {} proofs{}",
+            capacity,
+            if args.suppress_duplicates { " (duplicates hidden)" } else { "" }
+        );
+        DedupWindow::new(capacity)
+    });
+    let mut duplicate_count = 0;
+    let mut conflict_count = 0;
+    
+    let mut continuity = match args.continuity {
+        Some(keying) => {
+            let monitor = match &args.continuity_state {
+                Some(path) => ContinuityMonitor::open(keying, path)
+                    .map_err(|e| format!("Failed to load continuity state {}: {}", path.display(), e))?,
+                None => ContinuityMonitor::new(keying),
+            };
+            outln!("⛓️  Checking chain continuity, chains keyed by {}", keying);
+            if let Some(path) = &args.continuity_state {
+                outln!("   State file: {} ({} chain(s) known)", path.display(), monitor.chains());
+            }
+            Some(monitor)
+        }
+        None => None,
+    };
+    let mut gap_count = 0;
+    let mut mismatch_count = 0;
+    
+    let mut alerts = if args.alert_webhook.is_empty() {
+        None
+    } else {
+        let forwarder = WebhookForwarder::new(WebhookConfig {
+            urls: args.alert_webhook.clone(),
+            secret: args.webhook_secret.clone(),
+            timeout: Duration::from_millis(args.webhook_timeout_ms),
+            retries: args.webhook_retries,
+            circuit_failures: DEFAULT_CIRCUIT_FAILURES,
+            circuit_cooldown: Duration::from_secs(DEFAULT_CIRCUIT_COOLDOWN_SECS),
+        })
+        .map_err(|e| format!("Failed to create alert webhook client: {}", e))?;
+        outln!("🚨 Sending proofs with failed validations to: {}", args.alert_webhook.join(", "));
+        Some(forwarder)
+    };
+    let mut alert_count = 0;
+    let mut alert_deliveries: BTreeMap<&'static str, u64> = BTreeMap::new();
+    let mut invalid_proof: Option<String> = None;
+    if args.fail_on_invalid {
+        outln!("🛑 Exiting with code {} once a proof with failed validations is seen", EXIT_INVALID_PROOF);
+    }
+    
+    let webhooks = if args.webhook_url.is_empty() {
+        None
+    } else {
+        let forwarder = WebhookForwarder::new(WebhookConfig {
+            urls: args.webhook_url.clone(),
+            secret: args.webhook_secret.clone(),
+            timeout: Duration::from_millis(args.webhook_timeout_ms),
+            retries: args.webhook_retries,
+            circuit_failures: DEFAULT_CIRCUIT_FAILURES,
+            circuit_cooldown: Duration::from_secs(DEFAULT_CIRCUIT_COOLDOWN_SECS),
+        })
+        .map_err(|e| format!("Failed to create webhook client: {}", e))?;
+        outln!("📤 Forwarding proofs to: {}", args.webhook_url.join(", "));
+        if args.webhook_secret.is_some() {
+            outln!("   Signing bodies with HMAC-SHA256");
+        }
+        if args.webhook_verified_only {
+            outln!("   Only verified proofs are forwarded");
+        }
+        Some(forwarder)
+    };
+    
+    let republisher = match &args.republish_topic {
+        Some(topic) => {
+            check_republish_topic(topic, &topics)?;
+            // Facts go to a single topic as JSON; routing, transactions and the spool are producer-only
+            let config = KafkaConfig {
+                topic: topic.clone(),
+                routes: Vec::new(),
+                payload_format: PayloadFormat::Json,
+                transactional_id: None,
+                dlq_topic: None,
+                spool_dir: None,
+                ..kafka.clone()
+            };
+            let publisher = ProofPublisher::new(&config).map_err(|e| format!("Failed to create republish producer: {}", e))?;
+            outln!("📣 Republishing commitment facts to: {}", topic);
+            if args.republish_only_verified {
+                outln!("   Only verified proofs are republished");
+            }
+            Some(Republisher {
+                publisher,
+                topic: topic.clone(),
+                only_verified: args.republish_only_verified,
+                published: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            })
+        }
+        None => None,
+    };
+    
+    // Displayed messages are processed by one task per partition; manual commits wait for them
+    let manual_commit = args.commit == CommitStrategy::Manual;
+    let (processed_sender, mut processed_receiver) = mpsc::unbounded_channel();
+    let processor = Arc::new(Processor {
+        minimal: args.minimal,
+        raw: args.raw,
+        debug: args.debug,
+        reject_unverified: args.reject_unverified,
+        webhook_verified_only: args.webhook_verified_only,
+        verifier,
+        vkey_registry,
+        sinks: Mutex::new(Sinks {
+            saving: archive.as_ref().is_some_and(|archive| !archive.is_full()),
+            archive,
+            saved: 0,
+            sqlite,
+            stored: 0,
+            csv,
+            csv_rows: 0,
+        }),
+        webhooks: webhooks.map(|forwarder| {
+            tokio::sync::Mutex::new(WebhookState { forwarder, counts: BTreeMap::new() })
+        }),
+        republisher,
+        verified: AtomicU64::new(0),
+        verify_failed: AtomicU64::new(0),
+        unknown_vkeys: AtomicU64::new(0),
+        stats: Arc::new(Mutex::new(ConsumerStats::new())),
+        queued: AtomicUsize::new(0),
+        processed: manual_commit.then_some(processed_sender),
+    });
+    let mut workers = PartitionWorkers::new(args.partition_queue, {
+        let processor = Arc::clone(&processor);
+        move |job| {
+            let processor = Arc::clone(&processor);
+            async move { process_job(&processor, job).await }
+        }
+    });
+    let mut watermarks = ProcessedWatermarks::new();
+    let mut backpressure = Backpressure::new(args.pause_high_water, args.pause_low_water)?;
+    
+    let health = Arc::new(ConsumerHealth::new(
+        Duration::from_secs(args.stall_threshold),
+        Arc::clone(&processor.stats),
+    ));
+    health.set_subscribed(true);
+    match (args.metrics_addr, args.http_addr) {
+        (Some(metrics_addr), Some(http_addr)) if metrics_addr == http_addr => {
+            start_http_server(http_addr, true, Some(health.routes())).await?;
+        }
+        (metrics_addr, http_addr) => {
+            if let Some(addr) = metrics_addr {
+                start_metrics_server(addr).await?;
+            }
+            if let Some(addr) = http_addr {
+                start_http_server(addr, false, Some(health.routes())).await?;
+            }
+        }
     }
     
-    println!("⏳ Waiting for messages... (Press Ctrl+C to stop)\n");
+    // The first SIGINT/SIGTERM stops polling; messages already queued to partition workers are still processed
+    let mut shutdown = spawn_signal_handler();
+    
+    outln!("⏳ Waiting for messages... (Press Ctrl+C to stop)\n");
     
     // Process messages
     let mut message_stream = consumer.stream();
     let mut message_count = 0;
+    let mut decoded_count = 0;
+    let mut skipped_count = 0;
+    let mut undecodable_count = 0;
+    let mut total_errors = 0;
+    let mut last_offsets: BTreeMap<(String, i32), i64> = BTreeMap::new();
     let mut error_count = 0;
     const MAX_CONSECUTIVE_ERRORS: u32 = 10;
     
-    while let Some(message) = message_stream.next().await {
+    let commit_interval = Duration::from_millis(args.commit_interval_ms);
+    let mut batcher = CommitBatcher::new(commit_interval, args.commit_batch);
+    let mut commit_tick = tokio::time::interval(commit_interval);
+    let mut lag_tick = tokio::time::interval(lag_interval);
+    let mut csv_tick = tokio::time::interval(CSV_FLUSH_INTERVAL);
+    let mut window_tick = tokio::time::interval(Duration::from_secs(1));
+    let mut health_tick = tokio::time::interval(Duration::from_secs(1));
+    let mut backpressure_tick = tokio::time::interval(Duration::from_millis(100));
+    // Without --stats-interval the tick is never polled; its period only has to be valid
+    let stats_interval = args.stats_interval.map_or(Duration::from_secs(60), |interval| interval.0);
+    let mut stats_tick = tokio::time::interval_at(tokio::time::Instant::now() + stats_interval, stats_interval);
+    
+    if let Some(max) = args.max_messages {
+        outln!("🔢 Exiting after {} message(s)", max);
+    }
+    if let Some(slot) = args.until_end_slot {
+        outln!("🏁 Exiting once a proof reaches end slot {}", slot);
+    }
+    let idle_timeout = args.idle_timeout.map(Duration::from_secs);
+    let mut last_received = tokio::time::Instant::now();
+    
+    let stop_reason = loop {
+        health.progress();
+        let queued = processor.queued.load(Ordering::Relaxed);
+        match backpressure.update(queued) {
+            Some(Transition::Pause) => {
+                errln!(
+                    "⏸️  {} message(s) waiting for the sinks, pausing consumption until {} remain",
+                    queued,
+                    backpressure.low_water()
+                );
+                pause_partitions(&consumer, true);
+                metrics::CONSUMER_PAUSED.set(1);
+            }
+            Some(Transition::Resume(paused)) => {
+                errln!("▶️  Sinks caught up after {:.1}s, resuming consumption", paused.as_secs_f64());
+                pause_partitions(&consumer, false);
+                metrics::CONSUMER_PAUSED.set(0);
+                metrics::CONSUMER_PAUSE_SECONDS.observe(paused.as_secs_f64());
+            }
+            None => {}
+        }
+        let idle_deadline = last_received + idle_timeout.unwrap_or_default();
+        let message = tokio::select! {
+            message = message_stream.next() => match message {
+                Some(message) => message,
+                None => break StopReason::StreamEnded,
+            },
+            _ = tokio::time::sleep_until(idle_deadline), if idle_timeout.is_some() => {
+                break StopReason::IdleTimeout(args.idle_timeout.unwrap_or_default());
+            }
+            Some((topic, partition, offset)) = processed_receiver.recv(), if manual_commit => {
+                store_processed(&consumer, &mut watermarks, &mut batcher, &topic, partition, offset);
+                continue;
+            }
+            _ = commit_tick.tick(), if manual_commit => {
+                if batcher.is_due() {
+                    commit_offsets(&consumer, &mut batcher, CommitMode::Async);
+                }
+                continue;
+            }
+            _ = lag_tick.tick(), if args.metrics_addr.is_some() || args.http_addr.is_some() || args.lag_interval.is_some() => {
+                // Watermarks are fetched with blocking broker round trips
+                let consumer = Arc::clone(&consumer);
+                let health = Arc::clone(&health);
+                let timeout = Duration::from_secs(args.connection_timeout);
+                let print = args.lag_interval.is_some();
+                let to_stderr = args.minimal || args.raw;
+                tokio::task::spawn_blocking(move || {
+                    match consumer.position().and_then(|positions| fetch_lag(consumer.as_ref(), &positions, timeout)) {
+                        // Nothing is assigned until the group rebalance completes
+                        Ok(lags) if lags.is_empty() => {}
+                        Ok(lags) => {
+                            record_lag_metrics(&lags);
+                            health.set_consumer_lag(lags.iter().map(PartitionLag::lag).sum());
+                            if print {
+                                print_lag(&lags, to_stderr);
+                            }
+                        }
+                        Err(e) => errln!("⚠️  Failed to fetch consumer lag: {}", e),
+                    }
+                });
+                continue;
+            }
+            _ = csv_tick.tick(), if args.csv.is_some() => {
+                let mut sinks = processor.lock_sinks();
+                if let Some(export) = sinks.csv.as_mut().filter(|export| export.pending() > 0) {
+                    if let Err(e) = export.flush() {
+                        errln!("⚠️  Failed to flush CSV file: {}", e);
+                    }
+                }
+                continue;
+            }
+            _ = window_tick.tick(), if windows.is_some() => {
+                // Windows can end in offsets that hold no message, e.g. transaction markers
+                if let (Some(tracker), Ok(positions)) = (windows.as_mut(), consumer.position()) {
+                    tracker.close_reached(&positions);
+                    if tracker.is_done() {
+                        break StopReason::ReplayComplete;
+                    }
+                }
+                continue;
+            }
+            _ = stats_tick.tick(), if args.stats_interval.is_some() => {
+                print_stats(&processor, "📈 Statistics");
+                continue;
+            }
+            _ = backpressure_tick.tick(), if backpressure.is_paused() => {
+                // Checks whether the workers drained; partitions assigned by a rebalance start unpaused
+                pause_partitions(&consumer, true);
+                continue;
+            }
+            _ = health_tick.tick(), if args.http_addr.is_some() => {
+                // Keeps an idle loop live and follows rebalances
+                health.set_assigned_partitions(consumer.assignment().map_or(0, |assignment| assignment.count()));
+                continue;
+            }
+            _ = shutdown.changed() => break StopReason::Interrupted,
+        };
+        last_received = tokio::time::Instant::now();
+        
         match message {
             Ok(msg) => {
                 error_count = 0; // Reset error counter on success
-                message_count += 1;
                 
                 // Get message details
                 let key = msg.key().map(|k| String::from_utf8_lossy(k).to_string())
@@ -377,59 +2114,236 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 
                 let partition = msg.partition();
                 let offset = msg.offset();
+                let msg_topic = msg.topic().to_string();
+                if let Some(tracker) = windows.as_mut() {
+                    // Messages past a window are fetched ahead and ignored
+                    if !tracker.observe(&msg_topic, partition, offset) {
+                        if tracker.is_done() {
+                            break StopReason::ReplayComplete;
+                        }
+                        continue;
+                    }
+                }
+                if manual_commit {
+                    watermarks.read(&msg_topic, partition, offset);
+                }
+                last_offsets.insert((msg_topic.clone(), partition), offset);
+                metrics::PROOFS_CONSUMED.with_label_values(&[&msg_topic]).inc();
+                
+                // End-to-end lag from the record timestamp (producer create time or broker log append time)
+                let lag = msg.timestamp().to_millis().map(|timestamp| {
+                    let lag_secs = (Utc::now().timestamp_millis() - timestamp).max(0) as f64 / 1000.0;
+                    metrics::KAFKA_END_TO_END_LAG_SECONDS.observe(lag_secs);
+                    lag_secs
+                });
+                health.message(msg.timestamp().to_millis(), lag);
                 
-                if !args.minimal && !args.raw {
-                    println!("📬 Message #{} | Partition: {} | Offset: {} | Key: {}", 
-                        message_count, partition, offset, key);
+                // Decode the payload first so filters can be applied before anything is printed
+                let decoded = match msg.payload() {
+                    Some(payload) => {
+                        let format = PayloadFormat::of_message(&msg, payload);
+                        let decode_started = Instant::now();
+                        let decoded = decode_payload(payload, format, registry.as_ref()).await;
+                        let decode_time = decode_started.elapsed();
+                        metrics::KAFKA_DECODE_SECONDS.observe(decode_time.as_secs_f64());
+                        Some((payload, format, decoded, decode_time))
+                    }
+                    None => None,
+                };
+                match &decoded {
+                    Some((_, _, Ok(_), _)) => decoded_count += 1,
+                    Some((payload, _, Err(_), _)) => {
+                        total_errors += 1;
+                        processor.lock_stats().record_decode_failure(payload.len());
+                        metrics::PROOFS_DECODE_FAILURES.with_label_values(&[&msg_topic]).inc();
+                    }
+                    None => processor.lock_stats().record_empty(),
+                }
+                // Scalar fields and pubkeys only; the accounts' data is copied for displayed messages
+                let view = match &decoded {
+                    Some((_, _, Ok(decoded), _)) => decode_commitments_ref(&decoded.proof),
+                    _ => None,
+                };
+                if let Some((payload, _, Ok(decoded), _)) = &decoded {
+                    processor.lock_stats().record_decoded(&decoded.kind, payload.len(), view.as_ref());
+                    metrics::PROOFS_CONSUMED_BY_KIND.with_label_values(&[&msg_topic, kind_label(&decoded.proof)]).inc();
+                }
+                if let Some(commitments) = &view {
+                    let last_end_slot = metrics::PROOFS_LAST_END_SLOT.with_label_values(&[&msg_topic]);
+                    if commitments.end_slot as i64 > last_end_slot.get() {
+                        last_end_slot.set(commitments.end_slot as i64);
+                    }
+                    if !commitments.validations_passed {
+                        metrics::PROOFS_VALIDATION_FAILED.with_label_values(&[&msg_topic]).inc();
+                    }
                 }
                 
-                // Parse message payload
-                if let Some(payload) = msg.payload() {
-                    match serde_json::from_slice::<ZkProof>(payload) {
-                        Ok(proof) => {
-                            print_proof_details(&proof, args.raw, args.minimal);
+                // Failed validations are reported whether or not the proof is displayed
+                if let (Some((_, _, Ok(decoded), _)), Some(commitments)) = (&decoded, &view) {
+                    if !commitments.validations_passed {
+                        alert_count += 1;
+                        errln!(
+                            "🚨 {}: proof {} (partition {}, offset {}) reports validations_passed = false",
+                            presenter::current().paint("INVALID", Tone::Bad),
+                            decoded.proof.identifier,
+                            partition,
+                            offset
+                        );
+                        if let Some(forwarder) = alerts.as_mut() {
+                            let position = MessagePosition { topic: &msg_topic, partition, offset };
+                            let summary = proof_summary(decoded, Some(commitments), position, None);
+                            forward_summary(forwarder, &summary, &mut alert_deliveries, args.minimal || args.raw).await;
                         }
-                        Err(e) => {
-                            eprintln!("❌ Error parsing message as ZkProof: {}", e);
-                            if args.debug {
-                                // Try to parse as generic JSON for debugging
-                                if let Ok(json) = serde_json::from_slice::<Value>(payload) {
-                                    println!("Raw JSON structure:");
-                                    println!("{}", serde_json::to_string_pretty(&json)?);
-                                } else {
-                                    eprintln!("Raw payload: {}", String::from_utf8_lossy(payload));
-                                }
-                            }
+                        if args.fail_on_invalid && invalid_proof.is_none() {
+                            invalid_proof = Some(decoded.proof.identifier.clone());
                         }
                     }
-                } else {
-                    eprintln!("⚠️  Empty message payload");
                 }
                 
-                // Print headers if present and not in minimal mode
-                if !args.minimal && !args.raw {
-                    if let Some(headers) = msg.headers() {
-                        for header in headers.iter() {
-                            println!("   Header: {} = {}", 
-                                header.key, 
-                                String::from_utf8_lossy(header.value.unwrap_or(b"")));
+                let outcome = match &decoded {
+                    Some((_, _, Ok(decoded), _)) if filter_topics_match(&args.filter_topic, &msg_topic) => {
+                        filter.apply(&decoded.proof)
+                    }
+                    _ => FilterOutcome::Matched,
+                };
+                let display = match outcome {
+                    FilterOutcome::Matched => true,
+                    FilterOutcome::Skipped => {
+                        skipped_count += 1;
+                        false
+                    }
+                    FilterOutcome::Undecodable => {
+                        undecodable_count += 1;
+                        args.show_undecodable
+                    }
+                };
+                let verdict = match (dedup.as_mut(), &decoded) {
+                    (Some(window), Some((_, _, Ok(decoded), _))) if display => {
+                        let verdict = window.check(&decoded.proof.identifier, commitment_digest(&decoded.proof));
+                        if let Some(label) = verdict.label() {
+                            metrics::PROOFS_DUPLICATES.with_label_values(&[&msg_topic, label]).inc();
+                        }
+                        verdict
+                    }
+                    _ => DedupVerdict::New,
+                };
+                let display = match &verdict {
+                    DedupVerdict::Duplicate { .. } => {
+                        duplicate_count += 1;
+                        processor.lock_stats().record_duplicate();
+                        display && !args.suppress_duplicates
+                    }
+                    DedupVerdict::Conflict { .. } => {
+                        conflict_count += 1;
+                        display
+                    }
+                    DedupVerdict::New => display,
+                };
+                // Full owned decode, with the accounts' data, only for messages that are displayed and sunk
+                let commitments = if display { view.map(PublicCommitmentsRef::into_owned) } else { None };
+                if let (DedupVerdict::Conflict { previous_digest }, Some((_, _, Ok(decoded), _))) = (&verdict, &decoded) {
+                    // A reproof changed the content of a proof; never suppressed and always on stderr
+                    errln!(
+                        "🚨 {}: proof {} (partition {}, offset {}) has commitments digest 0x{}, previously 0x{}",
+                        presenter::current().paint("CONFLICT", Tone::Bad),
+                        decoded.proof.identifier,
+                        partition,
+                        offset,
+                        hex::encode(commitment_digest(&decoded.proof)),
+                        hex::encode(previous_digest)
+                    );
+                }
+                // Displayed proofs are checked against their chain; duplicates repeat a link already checked
+                let is_duplicate = matches!(verdict, DedupVerdict::Duplicate { .. });
+                if let (Some(monitor), Some((_, _, Ok(decoded), _)), Some(commitments), true, false) =
+                    (continuity.as_mut(), &decoded, &commitments, display, is_duplicate)
+                {
+                    if let Some(event) = monitor.observe(&decoded.proof.identifier, commitments) {
+                        metrics::PROOFS_CONTINUITY_BREAKS.with_label_values(&[&msg_topic, event.label()]).inc();
+                        match &event {
+                            ContinuityEvent::Gap { .. } => gap_count += 1,
+                            ContinuityEvent::Mismatch { .. } => mismatch_count += 1,
                         }
+                        errln!("🚨 {}", presenter::current().paint(&event, Tone::Bad));
+                        if let Some(webhooks) = &processor.webhooks {
+                            let mut webhooks = webhooks.lock().await;
+                            let WebhookState { forwarder, counts } = &mut *webhooks;
+                            forward_summary(forwarder, &event, counts, args.minimal || args.raw).await;
+                        }
+                    }
+                    if let Err(e) = monitor.save() {
+                        errln!("⚠️  Failed to save continuity state: {}", e);
+                    }
+                }
+                if !display {
+                    // Filtered messages are processed, but their offset is stored only
+                    // once the earlier messages of the partition are
+                    if manual_commit {
+                        store_processed(&consumer, &mut watermarks, &mut batcher, &msg_topic, partition, offset);
+                    }
+                    if invalid_proof.is_some() {
+                        break StopReason::InvalidProof;
+                    }
+                    if windows.as_ref().is_some_and(WindowTracker::is_done) {
+                        break StopReason::ReplayComplete;
+                    }
+                    continue;
+                }
+                message_count += 1;
+                let end_slot = commitments.as_ref().map(|commitments| commitments.end_slot);
+                
+                let job = Job {
+                    topic: msg_topic.clone(),
+                    label_topic: label_topics,
+                    partition,
+                    offset,
+                    key,
+                    number: message_count,
+                    timestamp: msg.timestamp(),
+                    lag,
+                    headers: owned_headers(&msg),
+                    decoded: decoded.map(|(payload, format, decoded, decode_time)| {
+                        (payload.to_vec(), format, decoded.map_err(|e| e.to_string()), decode_time)
+                    }),
+                    commitments,
+                    outcome,
+                    verdict,
+                };
+                // Waits while the partition's queue is full, which pauses reading
+                let queued = processor.queued.fetch_add(1, Ordering::Relaxed) + 1;
+                metrics::CONSUMER_QUEUE_DEPTH.set(queued as i64);
+                workers.dispatch(&msg_topic, partition, job).await;
+                
+                // Stop conditions are checked once the message is handed over; workers finish it before exit
+                if invalid_proof.is_some() {
+                    break StopReason::InvalidProof;
+                }
+                if args.max_messages.is_some_and(|max| message_count >= max) {
+                    break StopReason::MaxMessages(message_count);
+                }
+                if let (Some(until), Some(end_slot)) = (args.until_end_slot, end_slot) {
+                    if end_slot >= until {
+                        break StopReason::ReachedEndSlot(end_slot);
                     }
                 }
+                if windows.as_ref().is_some_and(WindowTracker::is_done) {
+                    break StopReason::ReplayComplete;
+                }
             }
             Err(e) => {
                 error_count += 1;
-                eprintln!("❌ Error receiving message (attempt {}/{}): {}", 
+                total_errors += 1;
+                errln!("❌ Error receiving message (attempt {}/{}): {}", 
                     error_count, MAX_CONSECUTIVE_ERRORS, e);
                 
                 if error_count >= MAX_CONSECUTIVE_ERRORS {
-                    eprintln!("\n⚠️  Too many consecutive errors. The broker connection may be lost.");
-                    eprintln!("   Last error: {}", e);
-                    eprintln!("\n🔍 Troubleshooting:");
-                    eprintln!("   - Check network connectivity");
-                    eprintln!("   - Verify the Kafka broker is still running");
-                    eprintln!("   - Check if authentication has expired");
-                    eprintln!("   - Review broker logs for issues");
+                    errln!("\n⚠️  Too many consecutive errors. The broker connection may be lost.");
+                    errln!("   Last error: {}", e);
+                    errln!("\n🔍 Troubleshooting:");
+                    errln!("   - Check network connectivity");
+                    errln!("   - Verify the Kafka broker is still running");
+                    errln!("   - Check if authentication has expired");
+                    errln!("   - Review broker logs for issues");
                     
                     return Err(format!("Consumer stopped after {} consecutive errors", MAX_CONSECUTIVE_ERRORS).into());
                 }
@@ -438,8 +2352,138 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }
+    };
+    
+    // Probes see the consumer as not ready while it drains
+    health.set_subscribed(false);
+    
+    match stop_reason {
+        StopReason::MaxMessages(count) => outln!("\n✅ Displayed {} message(s), stopping", count),
+        StopReason::ReachedEndSlot(slot) => outln!("\n✅ Reached end slot {}, stopping", slot),
+        StopReason::IdleTimeout(secs) => errln!("\n⏰ No messages for {}s, giving up", secs),
+        StopReason::ReplayComplete => outln!("\n✅ Every replay window has been consumed, stopping"),
+        StopReason::InvalidProof => errln!(
+            "\n🛑 Proof {} failed validations, stopping (--fail-on-invalid)",
+            invalid_proof.as_deref().unwrap_or("unknown")
+        ),
+        StopReason::Interrupted | StopReason::StreamEnded => {}
+    }
+    
+    // Queued messages are processed before their offsets are committed
+    if stop_reason == StopReason::Interrupted && workers.partitions() > 0 {
+        outln!("⏳ Finishing queued messages on {} partition(s)...", workers.partitions());
+    }
+    workers.finish().await;
+    while let Ok((topic, partition, offset)) = processed_receiver.try_recv() {
+        store_processed(&consumer, &mut watermarks, &mut batcher, &topic, partition, offset);
+    }
+    
+    if manual_commit && batcher.pending() > 0 {
+        let pending = batcher.pending();
+        commit_offsets(&consumer, &mut batcher, CommitMode::Sync);
+        if batcher.pending() == 0 {
+            outln!("📌 Committed offsets for {} processed messages", pending);
+        }
+    }
+    
+    outln!("\n👋 Consumer shutting down. Processed {} messages.", message_count);
+    outln!("   Decoded: {} | Errors: {}", decoded_count, total_errors);
+    if !filter.is_empty() {
+        outln!("   Skipped by filters: {}", skipped_count);
+        outln!(
+            "   Undecodable public values: {}{}",
+            undecodable_count,
+            if args.show_undecodable { "" } else { " (hidden, use --show-undecodable)" }
+        );
+    }
+    if processor.verifier.is_some() {
+        outln!(
+            "   Verified: {} | Failed verification: {}",
+            processor.verified.load(Ordering::Relaxed),
+            processor.verify_failed.load(Ordering::Relaxed)
+        );
+    }
+    if processor.vkey_registry.is_some() {
+        outln!("   Unknown verification keys: {}", processor.unknown_vkeys.load(Ordering::Relaxed));
+    }
+    if dedup.is_some() {
+        outln!(
+            "   Duplicates: {}{} | Conflicts: {}",
+            duplicate_count,
+            if args.suppress_duplicates { " (hidden)" } else { "" },
+            conflict_count
+        );
+    }
+    if alert_count > 0 || alerts.is_some() {
+        outln!("   Failed validations: {}", alert_count);
+    }
+    if alerts.is_some() {
+        outln!(
+            "   Alert deliveries: {} delivered | {} failed | {} skipped (circuit open)",
+            alert_deliveries.get("delivered").unwrap_or(&0),
+            alert_deliveries.get("failed").unwrap_or(&0),
+            alert_deliveries.get("skipped").unwrap_or(&0)
+        );
+    }
+    if backpressure.pauses() > 0 {
+        outln!(
+            "   Paused for slow sinks: {} time(s), {:.1}s in total",
+            backpressure.pauses(),
+            backpressure.paused_total().as_secs_f64()
+        );
+    }
+    if let Some(monitor) = &continuity {
+        outln!(
+            "   Chain gaps: {} | Mismatches: {} ({} chain(s) tracked)",
+            gap_count,
+            mismatch_count,
+            monitor.chains()
+        );
+    }
+    {
+        let mut sinks = processor.lock_sinks();
+        let Sinks { archive, saved, stored, csv, csv_rows, .. } = &mut *sinks;
+        if let Some(archive) = archive {
+            outln!("   Saved to {}: {} ({} bytes)", archive.dir().display(), saved, archive.size());
+        }
+        if let Some(path) = &args.sqlite {
+            outln!("   Stored in {}: {}", path.display(), stored);
+        }
+        if let (Some(export), Some(path)) = (csv.as_mut(), &args.csv) {
+            if let Err(e) = export.flush() {
+                errln!("⚠️  Failed to flush CSV file: {}", e);
+            }
+            outln!("   CSV rows in {}: {}", path.display(), csv_rows);
+        }
+    }
+    if let Some(webhooks) = &processor.webhooks {
+        let webhooks = webhooks.lock().await;
+        let counts = &webhooks.counts;
+        outln!(
+            "   Webhook deliveries: {} delivered | {} failed | {} skipped (circuit open)",
+            counts.get("delivered").unwrap_or(&0),
+            counts.get("failed").unwrap_or(&0),
+            counts.get("skipped").unwrap_or(&0)
+        );
     }
+    if let Some(republisher) = &processor.republisher {
+        outln!(
+            "   Republished to {}: {} | Failed: {}",
+            republisher.topic,
+            republisher.published.load(Ordering::Relaxed),
+            republisher.failed.load(Ordering::Relaxed)
+        );
+    }
+    for ((topic, partition), offset) in &last_offsets {
+        outln!("   {} partition {}: last offset {}", topic, partition, offset);
+    }
+    print_stats(&processor, "📈 Session Statistics");
     
-    println!("\n👋 Consumer shutting down. Processed {} messages.", message_count);
+    if let StopReason::IdleTimeout(_) = stop_reason {
+        std::process::exit(EXIT_IDLE_TIMEOUT);
+    }
+    if stop_reason == StopReason::InvalidProof {
+        std::process::exit(EXIT_INVALID_PROOF);
+    }
     Ok(())
 }
\ No newline at end of file
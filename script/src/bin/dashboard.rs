@@ -0,0 +1,417 @@
+//! Terminal dashboard of the proofs arriving on a topic
+//!
+//! Consumes the topic with its own consumer group, decodes every message like
+//! the consumer does, optionally verifies it, and shows recent proofs, the
+//! latest state of every monitored account, chain continuity, consumer lag and
+//! alerts. Up/Down (or j/k), Home and End select a proof, Enter opens the full
+//! commitments of the selected proof, Esc closes them and q quits. When stdout
+//! is not a terminal, each proof and alert is printed as a line instead.
+
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::Message;
+use solana_stub_prover_script::continuity::{ChainKeying, ContinuityMonitor};
+use solana_stub_prover_script::dashboard::{Dashboard, DashboardEvent, ProofRow};
+use solana_stub_prover_script::envelope::decode_payload;
+use solana_stub_prover_script::kafka::{KafkaConfig, PayloadFormat};
+use solana_stub_prover_script::lag::fetch_lag;
+use solana_stub_prover_script::msk_iam::KafkaClientContext;
+use solana_stub_prover_script::proof_filter::{decode_commitments, kind_label};
+use solana_stub_prover_script::schema_registry::{SchemaRegistryClient, SchemaRegistryConfig};
+use solana_stub_prover_script::verify::{ProofVerifier, VerifyStatus, VerifyingKeySource};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+
+/// How often the consumer lag is fetched
+const LAG_INTERVAL: Duration = Duration::from_secs(5);
+/// How long the UI waits for a key before redrawing
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Live terminal dashboard of the proofs on a topic", long_about = None)]
+struct Args {
+    /// Topic to watch (default: KAFKA_TOPIC)
+    #[arg(long)]
+    topic: Option<String>,
+    
+    /// Consumer group ID
+    #[arg(long, default_value = "solana-stub-dashboard")]
+    group_id: String,
+    
+    /// Start from the beginning of the topic instead of new messages only
+    #[arg(long)]
+    from_beginning: bool,
+    
+    /// Verifying key JSON written by the prover; every proof is verified with it
+    #[arg(long, conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (0x-prefixed bytes32); only Groth16 proofs can be checked
+    #[arg(long)]
+    vkey_hash: Option<String>,
+    
+    /// How proofs are grouped into chains: accounts or prefix
+    #[arg(long, default_value = "accounts")]
+    keying: ChainKeying,
+    
+    /// Schema Registry URL used to decode Avro (Confluent wire format) messages
+    #[arg(long, env = "SCHEMA_REGISTRY_URL")]
+    schema_registry_url: Option<String>,
+    
+    /// Schema Registry basic auth username
+    #[arg(long, env = "SCHEMA_REGISTRY_USERNAME")]
+    schema_registry_username: Option<String>,
+    
+    /// Schema Registry basic auth password
+    #[arg(long, env = "SCHEMA_REGISTRY_PASSWORD")]
+    schema_registry_password: Option<String>,
+}
+
+/// Decode, verify and describe one message
+async fn read_message<M: Message>(
+    msg: &M,
+    registry: Option<&SchemaRegistryClient>,
+    verifier: Option<&Arc<ProofVerifier>>,
+) -> DashboardEvent {
+    let undecodable = |reason: String| DashboardEvent::Undecodable {
+        topic: msg.topic().to_string(),
+        partition: msg.partition(),
+        offset: msg.offset(),
+        reason,
+    };
+    let Some(payload) = msg.payload() else {
+        return undecodable("empty message".to_string());
+    };
+    let decoded = match decode_payload(payload, PayloadFormat::of_message(msg, payload), registry).await {
+        Ok(decoded) => decoded,
+        Err(e) => return undecodable(e.to_string()),
+    };
+    let proof = decoded.proof;
+    let verification = match verifier {
+        Some(verifier) => {
+            // Verification is CPU-bound and can take seconds
+            let verifier = Arc::clone(verifier);
+            let proof = proof.clone();
+            Some(match tokio::task::spawn_blocking(move || verifier.verify(&proof)).await {
+                Ok(verification) => verification.status,
+                Err(e) => VerifyStatus::Failed(format!("verifier task failed: {}", e)),
+            })
+        }
+        None => None,
+    };
+    DashboardEvent::Proof(ProofRow {
+        identifier: proof.identifier.clone(),
+        topic: msg.topic().to_string(),
+        partition: msg.partition(),
+        offset: msg.offset(),
+        size: payload.len(),
+        kind: kind_label(&proof),
+        received_at: chrono::Utc::now(),
+        verification,
+        commitments: decode_commitments(&proof),
+    })
+}
+
+/// Read the topic until the dashboard goes away
+async fn consume(
+    consumer: Arc<StreamConsumer<KafkaClientContext>>,
+    registry: Option<SchemaRegistryClient>,
+    verifier: Option<Arc<ProofVerifier>>,
+    events: UnboundedSender<DashboardEvent>,
+) {
+    loop {
+        let event = match consumer.recv().await {
+            Ok(msg) => read_message(&msg, registry.as_ref(), verifier.as_ref()).await,
+            Err(e) => DashboardEvent::ConsumerError(e.to_string()),
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Fetch the consumer lag every [`LAG_INTERVAL`] until the dashboard goes away
+async fn watch_lag(consumer: Arc<StreamConsumer<KafkaClientContext>>, events: UnboundedSender<DashboardEvent>) {
+    let mut interval = tokio::time::interval(LAG_INTERVAL);
+    loop {
+        interval.tick().await;
+        // Watermarks are fetched with blocking broker round trips
+        let consumer = Arc::clone(&consumer);
+        let lag = tokio::task::spawn_blocking(move || {
+            consumer.position().and_then(|positions| fetch_lag(consumer.as_ref(), &positions, LAG_INTERVAL))
+        })
+        .await;
+        let event = match lag {
+            // Nothing is assigned until the group rebalance completes
+            Ok(Ok(lags)) if lags.is_empty() => continue,
+            Ok(Ok(lags)) => DashboardEvent::Lag(lags),
+            Ok(Err(e)) => DashboardEvent::ConsumerError(format!("Failed to fetch consumer lag: {}", e)),
+            Err(e) => DashboardEvent::ConsumerError(format!("Lag task failed: {}", e)),
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Print a line per proof and alert, for when stdout is not a terminal
+async fn run_plain(mut dashboard: Dashboard, mut events: UnboundedReceiver<DashboardEvent>) {
+    while let Some(event) = events.recv().await {
+        let row = match &event {
+            DashboardEvent::Proof(row) => Some(row.clone()),
+            _ => None,
+        };
+        let alerts = dashboard.apply(event);
+        if let Some(row) = row {
+            println!(
+                "{} {}[{}]@{} {} slots {} verified {} {} bytes",
+                row.received_at.format("%H:%M:%S"),
+                row.topic,
+                row.partition,
+                row.offset,
+                row.identifier,
+                row.slots(),
+                row.verified(),
+                row.size
+            );
+        }
+        for alert in alerts {
+            println!("ALERT {}", alert);
+        }
+    }
+}
+
+/// Draw the dashboard and handle keys until q is pressed
+fn run_tui(
+    terminal: &mut DefaultTerminal,
+    mut dashboard: Dashboard,
+    mut events: UnboundedReceiver<DashboardEvent>,
+) -> std::io::Result<()> {
+    loop {
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    dashboard.apply(event);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        terminal.draw(|frame| draw(frame, &dashboard))?;
+        
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') => return Ok(()),
+            KeyCode::Esc => dashboard.close_detail(),
+            KeyCode::Enter => dashboard.toggle_detail(),
+            KeyCode::Down | KeyCode::Char('j') => dashboard.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => dashboard.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => dashboard.select_first(),
+            KeyCode::End | KeyCode::Char('G') => dashboard.select_last(),
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let [header, main, bottom] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(8), Constraint::Length(10)]).areas(frame.area());
+    let summary = format!(
+        " {} message(s), lag {}  |  ↑↓ select  Enter details  Esc back  q quit",
+        dashboard.received(),
+        dashboard.total_lag()
+    );
+    frame.render_widget(Paragraph::new(summary).style(Style::new().add_modifier(Modifier::BOLD)), header);
+    
+    if dashboard.showing_detail() {
+        draw_detail(frame, dashboard, main);
+    } else {
+        let [proofs, side] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(main);
+        let [accounts, chains] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+        draw_proofs(frame, dashboard, proofs);
+        draw_accounts(frame, dashboard, accounts);
+        draw_chains(frame, dashboard, chains);
+    }
+    
+    let [lag, alerts] = Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(bottom);
+    draw_lag(frame, dashboard, lag);
+    draw_alerts(frame, dashboard, alerts);
+}
+
+fn draw_proofs(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let rows = dashboard.proofs().map(|row| {
+        let style = match &row.verification {
+            Some(VerifyStatus::Failed(_)) => Style::new().fg(Color::Red),
+            _ if row.commitments.as_ref().is_some_and(|c| !c.validations_passed) => Style::new().fg(Color::Red),
+            Some(VerifyStatus::Verified) => Style::new().fg(Color::Green),
+            None => Style::new(),
+        };
+        Row::new([
+            row.identifier.clone(),
+            row.slots(),
+            row.verified().to_string(),
+            format!("{} B", row.size),
+            row.received_at.format("%H:%M:%S").to_string(),
+        ])
+        .style(style)
+    });
+    let widths = [
+        Constraint::Fill(1),
+        Constraint::Length(23),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(8),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Identifier", "Slots", "Verified", "Size", "Received"]).style(bold()))
+        .block(Block::bordered().title(" Recent proofs "))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = TableState::new().with_selected(dashboard.selected_index());
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_accounts(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let rows = dashboard.accounts().map(|account| {
+        Row::new([
+            account.pubkey.clone(),
+            account.lamports.to_string(),
+            account.last_change_slot.to_string(),
+            format!("{} B", account.data_len),
+        ])
+    });
+    let widths = [Constraint::Fill(1), Constraint::Length(14), Constraint::Length(12), Constraint::Length(10)];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Account", "Lamports", "Changed", "Data"]).style(bold()))
+        .block(Block::bordered().title(" Accounts "));
+    frame.render_widget(table, area);
+}
+
+fn draw_chains(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let rows = dashboard.chains().map(|chain| {
+        let (status, style) = match &chain.last_break {
+            Some(last_break) => {
+                (format!("{} break(s), last {}", chain.breaks, last_break), Style::new().fg(Color::Red))
+            }
+            None => ("continuous".to_string(), Style::new().fg(Color::Green)),
+        };
+        Row::new([chain.chain.clone(), chain.end_slot.to_string(), chain.proofs.to_string(), status]).style(style)
+    });
+    let widths = [Constraint::Fill(1), Constraint::Length(12), Constraint::Length(7), Constraint::Fill(1)];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Chain", "Tip slot", "Proofs", "Continuity"]).style(bold()))
+        .block(Block::bordered().title(" Chains "));
+    frame.render_widget(table, area);
+}
+
+fn draw_lag(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let rows = dashboard.lag().iter().map(|lag| {
+        let position = lag.position.map_or_else(|| "-".to_string(), |position| position.to_string());
+        Row::new([format!("{}[{}]", lag.topic, lag.partition), position, lag.high.to_string(), lag.lag().to_string()])
+    });
+    let widths = [Constraint::Fill(1), Constraint::Length(10), Constraint::Length(10), Constraint::Length(8)];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["Partition", "Position", "High", "Lag"]).style(bold()))
+        .block(Block::bordered().title(" Consumer lag "));
+    frame.render_widget(table, area);
+}
+
+fn draw_alerts(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let items: Vec<ListItem> =
+        dashboard.alerts().map(|alert| ListItem::new(alert.to_string()).style(Style::new().fg(Color::Red))).collect();
+    let title = format!(" Alerts ({}) ", items.len());
+    frame.render_widget(List::new(items).block(Block::bordered().title(title)), area);
+}
+
+fn draw_detail(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let lines: Vec<Line> = dashboard
+        .detail()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| Line::from(format!("{:<42} {}", name, value)))
+        .collect();
+    let title = dashboard.selected().map_or_else(String::new, |row| format!(" {} ", row.identifier));
+    let detail = Paragraph::new(lines).block(Block::bordered().title(title)).wrap(Wrap { trim: false });
+    frame.render_widget(detail, area);
+}
+
+fn bold() -> Style {
+    Style::new().add_modifier(Modifier::BOLD)
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let kafka = KafkaConfig::from_env().map_err(|e| e.to_string())?;
+    kafka.validate().map_err(|e| e.to_string())?;
+    let topic = args.topic.clone().unwrap_or_else(|| kafka.topic.clone());
+    let verifier = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => Some(VerifyingKeySource::from_file(path).map_err(|e| e.to_string())?),
+        (None, Some(hash)) => Some(VerifyingKeySource::from_hash(hash).map_err(|e| e.to_string())?),
+        (None, None) => None,
+    }
+    .map(|source| Arc::new(ProofVerifier::new(source)));
+    let registry = args.schema_registry_url.clone().map(|url| {
+        SchemaRegistryClient::new(SchemaRegistryConfig {
+            url,
+            username: args.schema_registry_username.clone(),
+            password: args.schema_registry_password.clone(),
+        })
+    });
+    
+    let mut config = kafka.client_config();
+    config
+        .set("group.id", &args.group_id)
+        .set("enable.auto.commit", "true")
+        .set("auto.offset.reset", if args.from_beginning { "earliest" } else { "latest" });
+    let consumer: StreamConsumer<KafkaClientContext> =
+        config.create_with_context(kafka.client_context()).map_err(|e| format!("Failed to create consumer: {}", e))?;
+    consumer.subscribe(&[&topic]).map_err(|e| format!("Failed to subscribe to {}: {}", topic, e))?;
+    let consumer = Arc::new(consumer);
+    
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(consume(Arc::clone(&consumer), registry, verifier, sender.clone()));
+    tokio::spawn(watch_lag(consumer, sender));
+    let dashboard = Dashboard::new(ContinuityMonitor::new(args.keying));
+    
+    if !std::io::stdout().is_terminal() {
+        println!("Watching {} with group {}", topic, args.group_id);
+        run_plain(dashboard, receiver).await;
+        return Ok(());
+    }
+    let result = tokio::task::spawn_blocking(move || {
+        let mut terminal = ratatui::init();
+        let result = run_tui(&mut terminal, dashboard, receiver);
+        ratatui::restore();
+        result
+    })
+    .await;
+    match result {
+        Ok(result) => result.map_err(|e| format!("Terminal error: {}", e)),
+        Err(e) => Err(format!("Dashboard task failed: {}", e)),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    if let Err(e) = run(args).await {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,49 @@
+//! Generate deterministic ProverInput fixtures for tests and benchmarks
+
+use clap::Parser;
+use solana_stub_prover_script::fixtures::{write_fixtures, Preset, DEFAULT_SEED, MANIFEST_FILE};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Generate deterministic ProverInput fixtures", long_about = None)]
+struct Args {
+    /// Directory to write the fixtures and manifest.json to
+    #[arg(long, default_value = "fixtures")]
+    out_dir: PathBuf,
+    
+    /// Seed of the generated data; the same seed always gives the same fixtures
+    #[arg(long, default_value_t = DEFAULT_SEED)]
+    seed: u64,
+    
+    /// Preset to generate (repeatable, default: all): small-account, token-accounts, large-account,
+    /// broken-chain, duplicate-pubkeys or closed-account
+    #[arg(long = "preset")]
+    presets: Vec<Preset>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let presets = if args.presets.is_empty() { Preset::ALL.to_vec() } else { args.presets.clone() };
+    
+    let manifest = match write_fixtures(&args.out_dir, args.seed, &presets) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    for fixture in &manifest.fixtures {
+        let outcome = if fixture.expected.accepted { "accepted" } else { "rejected" };
+        println!(
+            "📝 {:<24} {:>2} account(s) {:>9} data bytes, slots {}..{} ({})",
+            fixture.file, fixture.accounts, fixture.data_bytes, fixture.start_slot, fixture.end_slot, outcome
+        );
+    }
+    println!(
+        "✅ Wrote {} fixture(s) for seed {} to {} (see {})",
+        manifest.fixtures.len(),
+        manifest.seed,
+        args.out_dir.display(),
+        MANIFEST_FILE
+    );
+}
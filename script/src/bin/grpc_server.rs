@@ -0,0 +1,148 @@
+//! gRPC API for proving, stored commitments and verification
+//!
+//! Serves the `Prover` service of `proto/prover.proto` over TLS. Every call
+//! needs `authorization: Bearer <token>` metadata. Proofs are published to
+//! Kafka (configured with the `KAFKA_*` environment variables) or written to
+//! `--sink-dir`.
+
+use clap::Parser;
+use solana_stub_prover_script::grpc::proto::prover_server::ProverServer;
+use solana_stub_prover_script::grpc::{ProverService, TokenInterceptor};
+use solana_stub_prover_script::kafka::{KafkaConfig, ProofPublisher};
+use solana_stub_prover_script::proof_sink::{DirSink, ProofSink};
+use solana_stub_prover_script::proof_store::ProofStore;
+use solana_stub_prover_script::prove_jobs::DEFAULT_MAX_QUEUED_JOBS;
+use solana_stub_prover_script::shutdown::spawn_signal_handler;
+use solana_stub_prover_script::solana::DEVNET_RPC_URL;
+use solana_stub_prover_script::verify::{ProofVerifier, VerifyingKeySource};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tonic::transport::{Identity, Server, ServerTlsConfig};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "gRPC API for proving, stored proofs and verification", long_about = None)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, env = "GRPC_LISTEN", default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+    
+    /// SQLite database written by `consumer --sqlite`
+    #[arg(long, env = "GRPC_SQLITE")]
+    sqlite: PathBuf,
+    
+    /// Bearer token clients must send in the authorization metadata
+    #[arg(long, env = "GRPC_API_TOKEN", hide_env_values = true)]
+    api_token: String,
+    
+    /// PEM certificate chain of the server
+    #[arg(long, env = "GRPC_TLS_CERT", required_unless_present = "no_tls", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    
+    /// PEM private key of the server
+    #[arg(long, env = "GRPC_TLS_KEY", required_unless_present = "no_tls", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    
+    /// Serve plain HTTP/2 without TLS (local development only)
+    #[arg(long, conflicts_with_all = ["tls_cert", "tls_key"])]
+    no_tls: bool,
+    
+    /// Solana JSON-RPC endpoint accounts and slots are fetched from
+    #[arg(long, env = "SOLANA_RPC_URL", default_value = DEVNET_RPC_URL)]
+    rpc_url: String,
+    
+    /// Write proofs to this directory instead of publishing them to Kafka
+    #[arg(long)]
+    sink_dir: Option<PathBuf>,
+    
+    /// Verifying key (vkey.json written by the prover) VerifyProof checks proofs against
+    #[arg(long, conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (0x-prefixed bytes32) VerifyProof checks proofs against; only Groth16 proofs can be checked
+    #[arg(long)]
+    vkey_hash: Option<String>,
+    
+    /// Proofs waiting to run before Prove is refused
+    #[arg(long, default_value_t = DEFAULT_MAX_QUEUED_JOBS)]
+    max_queued_jobs: usize,
+}
+
+/// Kafka publisher from `KAFKA_*` environment variables, or a directory sink
+fn build_sink(args: &Args) -> Result<Arc<dyn ProofSink>, Box<dyn std::error::Error>> {
+    match &args.sink_dir {
+        Some(dir) => Ok(Arc::new(DirSink::new(dir)?)),
+        None => {
+            let kafka_config = KafkaConfig::from_env()?;
+            kafka_config.validate()?;
+            Ok(Arc::new(ProofPublisher::new(&kafka_config)?))
+        }
+    }
+}
+
+/// Verifier for VerifyProof, if a key was given
+fn build_verifier(args: &Args) -> Result<Option<ProofVerifier>, Box<dyn std::error::Error>> {
+    let source = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => VerifyingKeySource::from_file(path)?,
+        (None, Some(hash)) => VerifyingKeySource::from_hash(hash)?,
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(ProofVerifier::new(source)))
+}
+
+/// TLS identity from the certificate and key files
+fn tls_config(cert: &Path, key: &Path) -> Result<ServerTlsConfig, Box<dyn std::error::Error>> {
+    let cert = fs::read(cert).map_err(|e| format!("Failed to read {}: {}", cert.display(), e))?;
+    let key = fs::read(key).map_err(|e| format!("Failed to read {}: {}", key.display(), e))?;
+    Ok(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    sp1_sdk::utils::setup_logger();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    
+    let token = args.api_token.trim();
+    if token.is_empty() {
+        eprintln!("❌ The API token must not be empty");
+        std::process::exit(1);
+    }
+    let store = match ProofStore::open(&args.sqlite) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ Failed to open {}: {}", args.sqlite.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let sink = build_sink(&args)?;
+    let verifier = build_verifier(&args)?;
+    if verifier.is_none() {
+        println!("⚠️  No --vkey or --vkey-hash, VerifyProof is disabled");
+    }
+    let service = ProverService::new(&args.rpc_url, sink, store, verifier, args.max_queued_jobs);
+    
+    let mut server = Server::builder();
+    let scheme = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => {
+            server = server.tls_config(tls_config(cert, key)?)?;
+            "https"
+        }
+        _ => "http",
+    };
+    println!("🚀 Serving {} on {}://{}", args.sqlite.display(), scheme, args.listen);
+    
+    let mut shutdown = spawn_signal_handler();
+    server
+        .add_service(ProverServer::with_interceptor(service.clone(), TokenInterceptor::new(token)))
+        .serve_with_shutdown(args.listen, async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        })
+        .await?;
+    
+    // Queued proofs are failed; a running proof is allowed to finish
+    service.shutdown().await;
+    println!("👋 Server stopped");
+    Ok(())
+}
@@ -0,0 +1,333 @@
+//! Inspector for committed public values
+//!
+//! Takes hex public values, a `last_proof.json` (SP1 proof) or a
+//! `last_kafka_message.json` (Kafka message), detects which it is, decodes the
+//! `PublicCommitments` and prints every field. With `--compare` it prints the
+//! fields that differ between two inputs instead. `export-evm` writes the
+//! commitments as ABI-encoded public values, a Foundry test and JSON.
+
+use clap::{Parser, Subcommand};
+use solana_stub_prover_lib::PublicCommitments;
+use solana_stub_prover_script::envelope::{decode_json, EnvelopeError};
+use solana_stub_prover_script::evm_export::{EvmExport, DEFAULT_CONTRACT_NAME};
+use solana_stub_prover_script::presenter::{self, ColorChoice, OutputStyle, Presenter, Section, Tone};
+use solana_stub_prover_script::public_values::{self, diff, fields, FieldChange, PublicValuesEncoding};
+use solana_stub_prover_script::utils::sha256_hash;
+use solana_stub_prover_script::wrapped_proof::{public_values_digest, WrappedProof, WrapperEncoding};
+use solana_stub_prover_script::{errln, outln};
+use sp1_sdk::SP1ProofWithPublicValues;
+use std::fs;
+use std::path::{Path, PathBuf};
+use twine_types::proofs::ProofData;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Decode and compare committed public values", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    
+    /// Hex public values, or a last_proof.json or last_kafka_message.json file
+    #[arg(required = true)]
+    input: Option<String>,
+    
+    /// Another input, in any of the same forms, to compare against
+    #[arg(long, value_name = "OTHER")]
+    compare: Option<String>,
+    
+    /// Output style: pretty (emoji and boxes) or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long, global = true)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto", global = true)]
+    color: ColorChoice,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Write the commitments as ABI-encoded public values, a Foundry test and JSON of hex fields
+    ExportEvm {
+        /// Hex public values, or a proof, Kafka message or public values file
+        input: String,
+        
+        /// Encoding of the input's public values: bincode (as committed) or abi (default: detect)
+        #[arg(long)]
+        encoding: Option<PublicValuesEncoding>,
+        
+        /// Directory to write public_values.hex, public_values.json and the Solidity test to
+        #[arg(long, default_value = "evm_export")]
+        out_dir: PathBuf,
+        
+        /// Name of the Solidity test contract, also the file name
+        #[arg(long, default_value = DEFAULT_CONTRACT_NAME)]
+        contract_name: String,
+    },
+}
+
+/// Public values read from an input, with what was learned about it on the way
+struct Input {
+    label: String,
+    /// What the input turned out to be
+    source: String,
+    /// Proof details shown before the commitments
+    details: Vec<(&'static str, String)>,
+    public_values: Vec<u8>,
+    /// Public values digest committed by a Groth16 or Plonk proof
+    snark_digest: Option<String>,
+}
+
+impl Input {
+    fn new(label: impl Into<String>, source: impl Into<String>, public_values: Vec<u8>) -> Self {
+        Self {
+            label: label.into(),
+            source: source.into(),
+            details: Vec::new(),
+            public_values,
+            snark_digest: None,
+        }
+    }
+}
+
+/// Read an input, detecting whether it is a file (and which kind) or a hex string
+fn load(input: &str) -> Result<Input, String> {
+    let path = Path::new(input);
+    if !path.is_file() {
+        let public_values = parse_hex(input).map_err(|e| format!("'{}' is neither a file nor hex: {}", input, e))?;
+        return Ok(Input::new("hex public values", "hex string", public_values));
+    }
+    
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let label = path.display().to_string();
+    let text = std::str::from_utf8(&bytes).map(str::trim).unwrap_or_default();
+    if text.starts_with('{') {
+        return load_json(label, &bytes).map_err(|e| format!("{}: {}", path.display(), e));
+    }
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit() || c.is_ascii_whitespace()) {
+        let public_values = parse_hex(text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Ok(Input::new(label, "hex file", public_values));
+    }
+    Ok(Input::new(label, "binary file", bytes))
+}
+
+/// A Kafka message (`last_kafka_message.json`) or an SP1 proof (`last_proof.json`)
+fn load_json(label: String, bytes: &[u8]) -> Result<Input, String> {
+    let wrapped = match decode_json(bytes) {
+        Ok(decoded) => {
+            let ProofData::SP1(sp1_proof) = decoded.proof.proof_data;
+            let mut input =
+                Input::new(label, format!("Kafka message ({} envelope)", decoded.shape), sp1_proof.public_value);
+            input.details = vec![
+                ("Identifier", decoded.proof.identifier),
+                ("Proof Kind", decoded.kind),
+                ("SP1 Version", sp1_proof.version.to_string()),
+                ("Verification Key", format!("0x{}", hex::encode(sp1_proof.verification_key))),
+            ];
+            match WrappedProof::decode(&sp1_proof.proof) {
+                Ok(wrapped) => {
+                    input.details.push(("Wrapped Proof", format!("{} ({})", wrapped.kind(), wrapped.encoding)));
+                    if wrapped.public_values() != input.public_values.as_slice() {
+                        input.details.push(("Embedded Public Values", "DIFFER from the message's".to_string()));
+                    }
+                    input.snark_digest = wrapped.snark().and_then(|snark| snark.public_values_digest);
+                }
+                Err(e) => input.details.push(("Wrapped Proof", e)),
+            }
+            return Ok(input);
+        }
+        Err(EnvelopeError::InvalidJson(e)) => return Err(format!("not valid JSON: {}", e)),
+        // SP1ProofWithPublicValues has `proof` and `public_values` fields
+        Err(EnvelopeError::UnknownShape { keys, .. }) if keys.iter().any(|key| key == "public_values") => {
+            let bundle: SP1ProofWithPublicValues =
+                serde_json::from_slice(bytes).map_err(|e| format!("invalid SP1 proof: {}", e))?;
+            WrappedProof { bundle, encoding: WrapperEncoding::Json }
+        }
+        Err(e) => return Err(format!("neither a Kafka message nor an SP1 proof: {}", e)),
+    };
+    let mut input = Input::new(label, format!("SP1 proof ({})", wrapped.kind()), wrapped.public_values().to_vec());
+    input.snark_digest = wrapped.snark().and_then(|snark| snark.public_values_digest);
+    Ok(input)
+}
+
+/// Hex with an optional `0x` prefix; whitespace is ignored
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text.split_whitespace().collect();
+    let digits = digits.strip_prefix("0x").unwrap_or(&digits);
+    if let Some((position, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex character '{}' at digit {}", c, position));
+    }
+    if digits.is_empty() {
+        return Err("no hex digits".to_string());
+    }
+    if digits.len() % 2 != 0 {
+        return Err(format!("odd number of hex digits ({})", digits.len()));
+    }
+    hex::decode(digits).map_err(|e| e.to_string())
+}
+
+/// Decode an input's public values, exiting with the decode error if they are not commitments
+fn decode(input: &Input) -> (PublicCommitments, PublicValuesEncoding) {
+    match public_values::decode(&input.public_values) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            errln!("❌ {}: public values ({} bytes) are {}", input.label, input.public_values.len(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn print_input(input: &Input) {
+    let (commitments, encoding) = decode(input);
+    let presenter = presenter::current();
+    let digest = public_values_digest(&input.public_values);
+    
+    let mut section = Section::new(format!("🔎 {}", input.label));
+    section.field(0, "Source", &input.source);
+    for (key, value) in &input.details {
+        section.field(0, *key, value);
+    }
+    section
+        .field(0, "Public Values", format!("{} bytes, {}", input.public_values.len(), encoding))
+        .field(0, "Commitment Digest", format!("0x{}", hex::encode(sha256_hash(&input.public_values))));
+    match &input.snark_digest {
+        Some(committed) if *committed == digest => {
+            section.field(0, "SNARK Digest", format!("{} ({})", digest, presenter.paint("matches", Tone::Good)))
+        }
+        Some(committed) => section.field(
+            0,
+            "SNARK Digest",
+            format!("{} ({}: the proof commits to {})", digest, presenter.paint("MISMATCH", Tone::Bad), committed),
+        ),
+        None => section.field(0, "SNARK Digest", digest),
+    };
+    
+    section.blank().heading(0, "📊 Public Commitments:");
+    let mut account = None;
+    for (path, value) in fields(&commitments) {
+        match path.split_once("].") {
+            Some((prefix, name)) => {
+                if account.as_deref() != Some(prefix) {
+                    section.blank().heading(1, format!("{}]", prefix));
+                    account = Some(prefix.to_string());
+                }
+                section.field(2, name, value);
+            }
+            None => {
+                section.field(1, path, value);
+            }
+        }
+    }
+    outln!("{}", section.render(presenter));
+}
+
+fn print_comparison(left: &Input, right: &Input) {
+    let (left_commitments, left_encoding) = decode(left);
+    let (right_commitments, right_encoding) = decode(right);
+    let presenter = presenter::current();
+    let changes = diff(&left_commitments, &right_commitments);
+    
+    let mut section = Section::new("🔀 Public Values Comparison");
+    section
+        .field(0, "Left", format!("{} ({}, {})", left.label, left.source, left_encoding))
+        .field(0, "Right", format!("{} ({}, {})", right.label, right.source, right_encoding))
+        .blank();
+    if changes.is_empty() {
+        section.note(format!("✅ {}", presenter.paint("No differences", Tone::Good)));
+    } else {
+        section.heading(0, format!("{} field(s) differ:", presenter.paint(changes.len(), Tone::Warning)));
+        let missing = "(missing)";
+        for change in changes {
+            let (path, from, to) = match change {
+                FieldChange::Changed { path, left, right } => (path, left, right),
+                FieldChange::Removed { path, value } => (path, value, missing.to_string()),
+                FieldChange::Added { path, value } => (path, missing.to_string(), value),
+            };
+            let values = format!("{} -> {}", presenter.paint(from, Tone::Bad), presenter.paint(to, Tone::Good));
+            section.field(1, path, values);
+        }
+    }
+    outln!("{}", section.render(presenter));
+}
+
+/// Write the EVM export of an input's commitments to `out_dir`
+fn export_evm(
+    input: &Input,
+    encoding: Option<PublicValuesEncoding>,
+    out_dir: &Path,
+    contract_name: &str,
+) -> Result<(), String> {
+    let mut chars = contract_name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("'{}' is not a valid Solidity contract name", contract_name));
+    }
+    let (commitments, encoding) = match encoding {
+        Some(encoding) => match public_values::decode_as(&input.public_values, encoding) {
+            Ok(commitments) => (commitments, encoding),
+            Err(e) => {
+                return Err(format!("{}: public values ({} bytes) are {}", input.label, input.public_values.len(), e))
+            }
+        },
+        None => decode(input),
+    };
+    let export = EvmExport::new(commitments);
+    let presenter = presenter::current();
+    
+    fs::create_dir_all(out_dir).map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+    let json = serde_json::to_string_pretty(&export.json()).map_err(|e| e.to_string())?;
+    let files = [
+        ("public_values.hex".to_string(), format!("0x{}\n", hex::encode(&export.public_values))),
+        ("public_values.json".to_string(), format!("{}\n", json)),
+        (format!("{}.t.sol", contract_name), export.solidity(contract_name)),
+    ];
+    for (name, contents) in &files {
+        let path = out_dir.join(name);
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    
+    let mut section = Section::new(format!("🧾 {}", input.label));
+    section
+        .field(0, "Source", &input.source)
+        .field(0, "Public Values", format!("{} bytes, {}", input.public_values.len(), encoding))
+        .field(0, "ABI Public Values", format!("{} bytes", export.public_values.len()))
+        .field(0, "Keccak256", format!("0x{}", hex::encode(export.digest)));
+    // An ABI input decodes from non-canonical encodings too, e.g. with other offsets
+    if encoding == PublicValuesEncoding::Abi && export.public_values != input.public_values {
+        section.field(0, "Re-encoding", presenter.paint("DIFFERS from the input's bytes", Tone::Warning));
+    }
+    section.blank().heading(0, format!("📝 Wrote to {}:", out_dir.display()));
+    for (name, _) in &files {
+        section.field(1, "File", name);
+    }
+    outln!("{}", section.render(presenter));
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    Presenter::new(args.style, args.color).install();
+    
+    let load_or_exit = |input: &str| match load(input) {
+        Ok(input) => input,
+        Err(e) => {
+            errln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    if let Some(Commands::ExportEvm { input, encoding, out_dir, contract_name }) = &args.command {
+        if let Err(e) = export_evm(&load_or_exit(input), *encoding, out_dir, contract_name) {
+            errln!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // Required unless a subcommand is given
+    let input = load_or_exit(args.input.as_deref().unwrap_or_default());
+    match &args.compare {
+        Some(other) => print_comparison(&input, &load_or_exit(other)),
+        None => print_input(&input),
+    }
+}
@@ -1,18 +1,42 @@
-//! Kafka admin tool to check and create topics
+//! Kafka admin tool for managing topics, consumer groups and ACLs
 
+use chrono::Utc;
 use clap::{Parser, Subcommand};
-use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
-use rdkafka::client::DefaultClientContext;
-use rdkafka::config::ClientConfig;
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, ResourceSpecifier, TopicReplication};
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::{KafkaResult, RDKafkaErrorCode};
+use rdkafka::message::BorrowedMessage;
 use rdkafka::metadata::Metadata;
-use std::time::Duration;
-
-const DEFAULT_KAFKA_BROKER_TLS: &str = "kafka-bootstrap.twine.limited:443";
-const DEFAULT_KAFKA_BROKER_PLAIN: &str = "b-1.test.7alql0.c5.kafka.us-east-1.amazonaws.com:9092";
-const KAFKA_TOPIC: &str = "twine.solana.proofs";
+use rdkafka::{Message, Offset, TopicPartitionList};
+use serde::Serialize;
+use solana_stub_prover_script::acls::{self, AclEntry, AclFilter, AclOperation, AclResourceType, AclRule, ResourceName};
+use solana_stub_prover_script::admin_output::{
+    AclDeleteOutput, AclList, ClusterMetadata, CreateOutput, DeleteOutput, ErrorOutput, GroupDeleteOutput,
+    GroupDeleteResult, GroupListing, GroupStatus, GroupSummary, ResetOutput, TailOutput, TopicCheck, TopicInfo,
+    PurgeOutput, TopicList, TopicResult, TopicStatus, WaitOutput,
+};
+use solana_stub_prover_script::consumer_groups::{self, GroupDescription};
+use solana_stub_prover_script::kafka::{
+    self, ConnectionOptions, KafkaAuth, KafkaConfig, PayloadFormat, SaslMechanism, SecurityProtocol, KAFKA_TOPIC,
+};
+use solana_stub_prover_script::latest::{tail_windows, Lookback};
+use solana_stub_prover_script::msk_iam::KafkaClientContext;
+use solana_stub_prover_script::purge::{self, resolve_purge, PurgePoint, PurgeTarget};
+use solana_stub_prover_script::offset_reset::{commit_list, resolve_targets, OffsetTarget, ResetStrategy};
+use solana_stub_prover_script::replay::{parse_timestamp, PartitionOffset, WindowTracker};
+use solana_stub_prover_script::stats::StatsInterval;
+use solana_stub_prover_script::tail::{newest, tail_assignment, MessageSummary};
+use solana_stub_prover_script::throughput::ThroughputSample;
+use solana_stub_prover_script::topic_profile::{
+    config_mismatches, topic_configs, ConfigMismatch, ConfigOverride, TopicProfile,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Kafka admin tool for managing topics", long_about = None)]
+#[command(author, version, about = "Kafka admin tool for managing topics, consumer groups and ACLs", long_about = None)]
 struct Args {
     #[command(subcommand)]
     command: Commands,
@@ -29,17 +53,58 @@ struct Args {
     #[arg(long)]
     no_tls: bool,
     
-    /// CA certificate file path
-    #[arg(long, default_value = "./ca.crt")]
-    ca_cert: String,
+    /// CA certificate file path (default: KAFKA_CA_CERT or ./ca.crt)
+    #[arg(long)]
+    ca_cert: Option<String>,
+    
+    /// Client certificate file path (default: KAFKA_CLIENT_CERT or ./user.crt)
+    #[arg(long)]
+    client_cert: Option<String>,
     
-    /// Client certificate file path  
-    #[arg(long, default_value = "./user.crt")]
-    client_cert: String,
+    /// Client key file path (default: KAFKA_CLIENT_KEY or ./user.key)
+    #[arg(long)]
+    client_key: Option<String>,
+    
+    /// SASL authentication: none or msk-iam (default: KAFKA_AUTH or none)
+    #[arg(long)]
+    kafka_auth: Option<KafkaAuth>,
+    
+    /// Security protocol: plaintext, ssl, sasl_plaintext or sasl_ssl (default: KAFKA_SECURITY_PROTOCOL,
+    /// otherwise derived from the TLS and SASL options)
+    #[arg(long)]
+    security_protocol: Option<SecurityProtocol>,
+    
+    /// Enable SASL authentication (PLAIN unless --sasl-mechanism is given)
+    #[arg(long)]
+    sasl: bool,
+    
+    /// SASL mechanism: PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512 (default: KAFKA_SASL_MECHANISM)
+    #[arg(long)]
+    sasl_mechanism: Option<SaslMechanism>,
     
-    /// Client key file path
-    #[arg(long, default_value = "./user.key")]
-    client_key: String,
+    /// SASL username
+    #[arg(long, env = "KAFKA_USERNAME")]
+    username: Option<String>,
+    
+    /// SASL password
+    #[arg(long, env = "KAFKA_PASSWORD")]
+    password: Option<String>,
+    
+    /// AWS region of the MSK cluster for msk-iam authentication
+    #[arg(long, env = "AWS_REGION")]
+    aws_region: Option<String>,
+    
+    /// IAM role to assume when signing msk-iam tokens
+    #[arg(long, env = "AWS_ROLE_ARN")]
+    aws_role_arn: Option<String>,
+    
+    /// Client ID reported to the brokers (default: KAFKA_CLIENT_ID)
+    #[arg(long)]
+    client_id: Option<String>,
+    
+    /// Print one JSON document on stdout instead of text, and errors as JSON on stderr
+    #[arg(long, global = true)]
+    json: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -67,6 +132,117 @@ enum Commands {
         /// Replication factor
         #[arg(long, default_value = "1")]
         replication_factor: i32,
+        
+        /// Topic configs to apply: proofs (8 MiB messages, zstd, 30 days retention), summary (compacted, zstd) or default
+        #[arg(long, default_value = "default")]
+        profile: TopicProfile,
+        
+        /// Set a topic config on top of the profile, as <key>=<value> (repeatable)
+        #[arg(long = "set")]
+        config: Vec<ConfigOverride>,
+    },
+    
+    /// Delete topics
+    Delete {
+        /// Topic name to delete (repeatable)
+        #[arg(long, required_unless_present = "pattern")]
+        topic: Vec<String>,
+        
+        /// Also delete every topic matching this glob, e.g. 'test.*' (repeatable; never matches internal topics)
+        #[arg(long)]
+        pattern: Vec<String>,
+        
+        /// Confirm the deletion
+        #[arg(long)]
+        yes: bool,
+        
+        /// Only list the topics that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Delete the records before an offset or a point in time, moving the log start offset forward
+    Purge {
+        /// Topic to purge
+        #[arg(long)]
+        topic: String,
+        
+        /// Only purge this partition (default: every partition)
+        #[arg(long)]
+        partition: Option<i32>,
+        
+        /// Delete the records before this offset (clamped to the retained log)
+        #[arg(long, required_unless_present = "before_timestamp", conflicts_with = "before_timestamp")]
+        before_offset: Option<i64>,
+        
+        /// Delete the records before the first record at or after this time, as RFC 3339 or Unix milliseconds
+        #[arg(long)]
+        before_timestamp: Option<String>,
+        
+        /// Confirm the deletion
+        #[arg(long)]
+        yes: bool,
+        
+        /// Only print the records that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Print a summary line for each of the last messages on a topic, without a consumer group
+    Tail {
+        /// Topic to read
+        #[arg(long, default_value = KAFKA_TOPIC)]
+        topic: String,
+        
+        /// Number of messages to print
+        #[arg(long, default_value = "10")]
+        count: u64,
+        
+        /// Keep printing new messages until interrupted
+        #[arg(long)]
+        follow: bool,
+    },
+    
+    /// Wait until the broker responds and, with --topic, until the topic exists
+    Wait {
+        /// Also wait until this topic exists
+        #[arg(long)]
+        topic: Option<String>,
+        
+        /// Give up after this many seconds
+        #[arg(long, default_value = "60")]
+        timeout: u64,
+        
+        /// Create the topic with the proofs profile once the broker is up, if it does not exist
+        #[arg(long, requires = "topic")]
+        create_if_missing: bool,
+        
+        /// Number of partitions of a created topic
+        #[arg(long, default_value = "3")]
+        partitions: i32,
+        
+        /// Replication factor of a created topic
+        #[arg(long, default_value = "1")]
+        replication_factor: i32,
+    },
+    
+    /// Print the produce rate of a topic at an interval, from its high watermarks
+    Monitor {
+        /// Topic to monitor
+        #[arg(long, default_value = KAFKA_TOPIC)]
+        topic: String,
+        
+        /// Time between samples (e.g. 10s, 1m)
+        #[arg(long, default_value = "10s")]
+        interval: StatsInterval,
+        
+        /// Stop after this long (e.g. 5m; default: until interrupted)
+        #[arg(long)]
+        duration: Option<StatsInterval>,
+        
+        /// Records per interval whose sizes estimate the bytes per second (0 disables the estimate)
+        #[arg(long, default_value = "50")]
+        sample: usize,
     },
     
     /// Get metadata about topics
@@ -75,37 +251,709 @@ enum Commands {
         #[arg(long)]
         topic: Option<String>,
     },
+    
+    /// List, describe or delete consumer groups
+    Groups {
+        #[command(subcommand)]
+        action: GroupsAction,
+    },
+    
+    /// Move a consumer group's committed offsets on a topic (the group must have no active members)
+    ResetOffsets {
+        /// Consumer group ID
+        #[arg(long)]
+        group: String,
+        
+        /// Topic whose offsets are reset
+        #[arg(long, default_value = KAFKA_TOPIC)]
+        topic: String,
+        
+        /// Reset to the log start offset
+        #[arg(long, conflicts_with_all = ["to_latest", "to_offset", "to_timestamp"])]
+        to_earliest: bool,
+        
+        /// Reset to the high watermark
+        #[arg(long, conflicts_with_all = ["to_offset", "to_timestamp"])]
+        to_latest: bool,
+        
+        /// Reset every partition to this offset (clamped to the retained log)
+        #[arg(long, conflicts_with = "to_timestamp")]
+        to_offset: Option<i64>,
+        
+        /// Reset to the first record at or after this time, as RFC 3339 or Unix milliseconds
+        #[arg(long)]
+        to_timestamp: Option<String>,
+        
+        /// Reset one partition to an offset instead, as <partition>:<offset> (repeatable)
+        #[arg(long)]
+        partition_offset: Vec<PartitionOffset>,
+        
+        /// Print the target offsets without committing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// List, create or delete ACLs (requires an authorizer on the brokers)
+    Acls {
+        #[command(subcommand)]
+        action: AclsAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GroupsAction {
+    /// List all consumer groups
+    List,
+    
+    /// Show a group's members, committed offsets, log-end offsets and lag
+    Describe {
+        /// Consumer group ID
+        #[arg(long)]
+        group: String,
+        
+        /// Topic to describe (repeatable; default: the topics assigned to the group's members)
+        #[arg(long)]
+        topic: Vec<String>,
+    },
+    
+    /// Delete consumer groups and their committed offsets
+    Delete {
+        /// Consumer group ID to delete (repeatable)
+        #[arg(long, required_unless_present = "pattern")]
+        group: Vec<String>,
+        
+        /// Also delete every group matching this glob, e.g. 'test-*' (repeatable)
+        #[arg(long)]
+        pattern: Vec<String>,
+        
+        /// Confirm the deletion
+        #[arg(long)]
+        yes: bool,
+        
+        /// Send the deletion even for groups with active members (the broker still rejects non-empty groups)
+        #[arg(long)]
+        force: bool,
+        
+        /// Only list the groups that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
-fn create_admin_client(args: &Args) -> Result<AdminClient<DefaultClientContext>, rdkafka::error::KafkaError> {
-    let mut config = ClientConfig::new();
+#[derive(Subcommand, Debug)]
+enum AclsAction {
+    /// List ACLs, optionally filtered
+    List {
+        /// Only ACLs on this resource type: topic or group
+        #[arg(long)]
+        resource_type: Option<AclResourceType>,
+        
+        /// Only ACLs on exactly this resource name
+        #[arg(long, conflicts_with = "prefix")]
+        name: Option<String>,
+        
+        /// Only prefixed ACLs with exactly this prefix
+        #[arg(long)]
+        prefix: Option<String>,
+        
+        /// Only ACLs for this principal, e.g. User:alice
+        #[arg(long)]
+        principal: Option<String>,
+        
+        /// Only ACLs for this host
+        #[arg(long)]
+        host: Option<String>,
+        
+        /// Only ACLs for this operation: read, write or describe
+        #[arg(long)]
+        operation: Option<AclOperation>,
+    },
     
-    // Determine if TLS should be used
-    let use_tls = !args.no_tls && args.tls;
+    /// Allow a principal operations on a topic or group
+    Create {
+        /// Resource type: topic or group
+        #[arg(long)]
+        resource_type: AclResourceType,
+        
+        /// Resource name
+        #[arg(long, required_unless_present = "prefix", conflicts_with = "prefix")]
+        name: Option<String>,
+        
+        /// Apply to every resource whose name starts with this prefix instead
+        #[arg(long)]
+        prefix: Option<String>,
+        
+        /// Principal, e.g. User:alice
+        #[arg(long)]
+        principal: String,
+        
+        /// Host the principal connects from
+        #[arg(long, default_value = "*")]
+        host: String,
+        
+        /// Operation to allow: read, write or describe (repeatable)
+        #[arg(long, required = true)]
+        operation: Vec<AclOperation>,
+    },
     
-    // Determine broker address
-    let broker = args.broker.as_ref().map(|s| s.as_str()).unwrap_or_else(|| {
-        if use_tls {
-            DEFAULT_KAFKA_BROKER_TLS
+    /// Delete the ACLs matching a filter (allow and deny)
+    Delete {
+        /// Resource type: topic or group
+        #[arg(long)]
+        resource_type: Option<AclResourceType>,
+        
+        /// Resource name
+        #[arg(long, conflicts_with = "prefix")]
+        name: Option<String>,
+        
+        /// Prefix of prefixed ACLs
+        #[arg(long)]
+        prefix: Option<String>,
+        
+        /// Principal, e.g. User:alice
+        #[arg(long)]
+        principal: Option<String>,
+        
+        /// Host
+        #[arg(long)]
+        host: Option<String>,
+        
+        /// Operation: read, write or describe
+        #[arg(long)]
+        operation: Option<AclOperation>,
+        
+        /// Confirm the deletion
+        #[arg(long)]
+        yes: bool,
+        
+        /// Only list the ACLs that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// `println!` unless the output is JSON
+macro_rules! say {
+    ($json:expr, $($arg:tt)*) => {
+        if !$json {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Report a failure and exit: a JSON error object on stderr with `--json`, otherwise a text line
+fn fail(json: bool, error: &str, detail: impl fmt::Display) -> ! {
+    if json {
+        let output = ErrorOutput { error: error.to_string(), detail: detail.to_string() };
+        eprintln!("{}", serde_json::to_string(&output).unwrap_or_default());
+    } else {
+        eprintln!("❌ {}: {}", error, detail);
+    }
+    std::process::exit(1);
+}
+
+/// Print a JSON document on stdout
+fn emit<T: Serialize>(document: &T) {
+    match serde_json::to_string_pretty(document) {
+        Ok(output) => println!("{}", output),
+        Err(e) => fail(true, "Failed to serialize the output", e),
+    }
+}
+
+/// ACL filter from the list and delete options; no name or prefix matches every pattern
+fn acl_filter(
+    resource_type: Option<AclResourceType>,
+    name: Option<String>,
+    prefix: Option<String>,
+    principal: Option<String>,
+    host: Option<String>,
+    operation: Option<AclOperation>,
+) -> AclFilter {
+    AclFilter {
+        resource_type,
+        resource: name.map(ResourceName::Literal).or(prefix.map(ResourceName::Prefixed)),
+        principal,
+        host,
+        operation,
+    }
+}
+
+fn build_kafka_config(args: &Args) -> Result<KafkaConfig, Box<dyn std::error::Error>> {
+    // Same connection settings and validation as the producer and consumer
+    let config = KafkaConfig::from_env_with(&ConnectionOptions {
+        broker: args.broker.clone(),
+        client_id: args.client_id.clone(),
+        disable_tls: args.no_tls || !args.tls,
+        protocol: args.security_protocol,
+        auth: args.kafka_auth,
+        sasl_mechanism: args.sasl_mechanism.or(args.sasl.then_some(SaslMechanism::Plain)),
+        sasl_username: args.username.clone(),
+        sasl_password: args.password.clone(),
+        aws_region: args.aws_region.clone(),
+        aws_role_arn: args.aws_role_arn.clone(),
+        ca_cert_path: args.ca_cert.clone(),
+        client_cert_path: args.client_cert.clone(),
+        client_key_path: args.client_key.clone(),
+        allow_missing_certs: false,
+    })?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Describe a consumer group with its members and lag
+fn describe_group(
+    config: &KafkaConfig,
+    group: &str,
+    topics: &[String],
+) -> Result<GroupDescription, Box<dyn std::error::Error>> {
+    // A consumer with the group's ID reads its committed offsets without joining it
+    let mut client_config = config.client_config();
+    client_config.set("group.id", group).set("enable.auto.commit", "false");
+    let consumer: BaseConsumer<KafkaClientContext> = client_config.create_with_context(config.client_context())?;
+    Ok(consumer_groups::describe_group(&consumer, group, topics, Duration::from_secs(10))?)
+}
+
+/// Commit new offsets for a group without members, printing the targets first unless the output is JSON
+///
+/// Returns the targets; nothing is committed on a dry run or without targets.
+fn reset_offsets(
+    config: &KafkaConfig,
+    group: &str,
+    topic: &str,
+    strategy: Option<ResetStrategy>,
+    overrides: &[PartitionOffset],
+    dry_run: bool,
+    json: bool,
+) -> Result<Vec<OffsetTarget>, Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(10);
+    // Offsets are committed as the group by a consumer that never joins it
+    let mut client_config = config.client_config();
+    client_config.set("group.id", group).set("enable.auto.commit", "false");
+    let consumer: BaseConsumer<KafkaClientContext> = client_config.create_with_context(config.client_context())?;
+    
+    // Active members would overwrite the reset offsets with their own commits
+    let groups = consumer.fetch_group_list(Some(group), timeout)?;
+    if let Some(info) = groups.groups().iter().find(|info| info.name() == group && !info.members().is_empty()) {
+        return Err(format!(
+            "group '{}' has {} active member(s) ({}); stop its consumers first",
+            group,
+            info.members().len(),
+            info.state()
+        )
+        .into());
+    }
+    
+    let targets = resolve_targets(&consumer, topic, strategy, overrides, timeout)?;
+    if targets.is_empty() {
+        say!(json, "⚠️  No partitions to reset");
+        return Ok(targets);
+    }
+    say!(json, "\n   {:>9}  {:>12}  {:>12}  {:>10}", "PARTITION", "CURRENT", "TARGET", "DELTA");
+    for target in &targets {
+        say!(
+            json,
+            "   {:>9}  {:>12}  {:>12}  {:>10}{}",
+            target.partition,
+            target.current.map_or_else(|| "-".to_string(), |offset| offset.to_string()),
+            target.target,
+            target.delta().map_or_else(|| "-".to_string(), |delta| format!("{:+}", delta)),
+            if target.clamped {
+                format!("  (clamped to the log, offsets {}..{})", target.low, target.high)
+            } else {
+                String::new()
+            }
+        );
+    }
+    
+    if dry_run {
+        say!(json, "\n🔍 Dry run, offsets not committed");
+        return Ok(targets);
+    }
+    consumer.commit(&commit_list(&targets)?, CommitMode::Sync)?;
+    say!(json, "\n✅ Committed {} offset(s) for group '{}' on {}", targets.len(), group, topic);
+    Ok(targets)
+}
+
+/// Resolve the cut-off of the partition, or of every partition of the topic
+fn purge_targets(
+    config: &KafkaConfig,
+    topic: &str,
+    partition: Option<i32>,
+    point: PurgePoint,
+) -> Result<Vec<PurgeTarget>, Box<dyn std::error::Error>> {
+    // Offsets by time need a consumer; it is never subscribed, so no group is joined
+    let consumer: BaseConsumer<KafkaClientContext> =
+        config.client_config().create_with_context(config.client_context())?;
+    Ok(resolve_purge(&consumer, topic, partition, point, Duration::from_secs(10))?)
+}
+
+/// Print the last `count` messages on a topic, then stream new ones when following
+///
+/// With `--json` the tail is one document, or one message per line when following.
+fn tail_topic(
+    config: &KafkaConfig,
+    topic: &str,
+    count: u64,
+    follow: bool,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(10);
+    // Partitions are assigned directly, so no group is joined and no offsets are committed
+    let mut client_config = config.client_config();
+    client_config.set("enable.auto.commit", "false");
+    let consumer: BaseConsumer<KafkaClientContext> = client_config.create_with_context(config.client_context())?;
+    
+    let windows = tail_windows(&consumer, &[topic.to_string()], Lookback::Messages(count), timeout)?;
+    if windows.is_empty() {
+        return Err(format!("topic '{}' does not exist", topic).into());
+    }
+    let assignment = tail_assignment(&windows, follow)?;
+    if assignment.count() > 0 {
+        consumer.assign(&assignment)?;
+    }
+    
+    let mut tracker = WindowTracker::new(&windows);
+    let mut summaries = Vec::new();
+    // Messages past the windows that arrive while following, printed after the tail
+    let mut newer = Vec::new();
+    while !tracker.is_done() {
+        if let Some(message) = consumer.poll(Duration::from_millis(500)) {
+            let msg = message?;
+            if tracker.observe(msg.topic(), msg.partition(), msg.offset()) {
+                summaries.push(summarize(&msg));
+            } else if follow {
+                newer.push(summarize(&msg));
+            }
+        }
+        // Windows can end in offsets that hold no message, e.g. transaction markers
+        tracker.close_reached(&consumer.position()?);
+    }
+    
+    let tail = newest(summaries, usize::try_from(count).unwrap_or(usize::MAX));
+    let print = |summary: &MessageSummary| {
+        if json {
+            println!("{}", serde_json::to_string(summary).unwrap_or_default());
         } else {
-            DEFAULT_KAFKA_BROKER_PLAIN
+            println!("   {}", summary);
+        }
+    };
+    if json && !follow {
+        emit(&TailOutput { topic: topic.to_string(), messages: tail });
+        return Ok(());
+    }
+    if tail.is_empty() && !follow {
+        println!("   (No messages)");
+    }
+    tail.iter().chain(&newer).for_each(print);
+    if !follow {
+        return Ok(());
+    }
+    
+    say!(json, "\n👀 Following new messages (Ctrl-C to stop)...");
+    loop {
+        if let Some(message) = consumer.poll(Duration::from_millis(500)) {
+            print(&summarize(&message?));
+        }
+    }
+}
+
+/// Poll metadata until the broker responds and the topic, if any, exists, printing a dot per attempt that is not ready
+///
+/// With `create` the topic is created once with the proofs profile as soon as the broker is up.
+async fn wait_for_broker(
+    admin: &AdminClient<KafkaClientContext>,
+    topic: Option<&str>,
+    timeout: Duration,
+    mut create: Option<(i32, i32)>,
+    json: bool,
+) -> Result<WaitOutput, String> {
+    let start = Instant::now();
+    let deadline = start + timeout;
+    let mut output = WaitOutput {
+        broker_ready: false,
+        topic: topic.map(str::to_string),
+        topic_ready: false,
+        created: false,
+        elapsed_ms: 0,
+    };
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(match topic {
+                Some(topic) if output.broker_ready => format!("topic '{}' did not appear within {:?}", topic, timeout),
+                _ => format!("the broker did not respond within {:?}", timeout),
+            });
         }
-    });
+        
+        // Short attempts, so a broker that comes up is noticed quickly
+        if let Ok(metadata) = admin.inner().fetch_metadata(None, remaining.min(Duration::from_secs(2))) {
+            if !output.broker_ready {
+                output.broker_ready = true;
+                say!(json, "\n✅ Broker is up after {:.1}s", start.elapsed().as_secs_f64());
+            }
+            let Some(topic) = topic else {
+                break;
+            };
+            if metadata.topics().iter().any(|t| t.name() == topic && t.error().is_none()) {
+                output.topic_ready = true;
+                say!(json, "✅ Topic '{}' exists", topic);
+                break;
+            }
+            if let Some((partitions, replication_factor)) = create.take() {
+                output.created = create_proofs_topic(admin, topic, partitions, replication_factor).await?;
+                if output.created {
+                    say!(json, "📝 Created topic '{}' with the proofs profile", topic);
+                }
+                // The new topic shows up in the metadata on a later attempt
+                continue;
+            }
+        }
+        if !json {
+            print!(".");
+            let _ = std::io::stdout().flush();
+        }
+        tokio::time::sleep(deadline.saturating_duration_since(Instant::now()).min(Duration::from_millis(500))).await;
+    }
+    output.elapsed_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    Ok(output)
+}
+
+/// Create a topic with the proofs profile; `false` if another client created it first
+async fn create_proofs_topic(
+    admin: &AdminClient<KafkaClientContext>,
+    topic: &str,
+    partitions: i32,
+    replication_factor: i32,
+) -> Result<bool, String> {
+    let configs = TopicProfile::Proofs.configs();
+    let new_topic = configs.iter().fold(
+        NewTopic::new(topic, partitions, TopicReplication::Fixed(replication_factor)),
+        |new_topic, (key, value)| new_topic.set(key, value),
+    );
+    let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(30)));
+    let results = admin.create_topics(&[new_topic], &options).await.map_err(|e| e.to_string())?;
+    match results.into_iter().next() {
+        Some(Ok(_)) => Ok(true),
+        Some(Err((_, RDKafkaErrorCode::TopicAlreadyExists))) => Ok(false),
+        Some(Err((name, err))) => Err(format!("failed to create topic '{}': {}", name, err)),
+        None => Err(format!("the broker returned no result for topic '{}'", topic)),
+    }
+}
+
+/// High watermark of every partition of a topic; empty if the topic does not exist
+fn high_watermarks(
+    consumer: &BaseConsumer<KafkaClientContext>,
+    topic: &str,
+    timeout: Duration,
+) -> KafkaResult<BTreeMap<i32, i64>> {
+    let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+    let mut highs = BTreeMap::new();
+    for partition in metadata.topics().iter().filter(|t| t.name() == topic).flat_map(|t| t.partitions()) {
+        let (_, high) = consumer.fetch_watermarks(topic, partition.id(), timeout)?;
+        highs.insert(partition.id(), high);
+    }
+    Ok(highs)
+}
+
+/// Sample the high watermarks of a topic every `interval` and print its produce rate
+///
+/// Between samples up to `sample` new records are consumed to estimate their
+/// size. With `--json` every sample is printed as one line.
+fn monitor_topic(
+    config: &KafkaConfig,
+    topic: &str,
+    interval: Duration,
+    duration: Option<Duration>,
+    sample: usize,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(10);
+    // Partitions are assigned directly, so no group is joined and no offsets are committed
+    let mut client_config = config.client_config();
+    client_config.set("enable.auto.commit", "false");
+    let consumer: BaseConsumer<KafkaClientContext> = client_config.create_with_context(config.client_context())?;
     
-    config.set("bootstrap.servers", broker);
+    // Sizes are sampled from the newest records, so every interval starts at the end of the log
+    let assign_at_end = |highs: &BTreeMap<i32, i64>| -> KafkaResult<()> {
+        let mut assignment = TopicPartitionList::new();
+        for &partition in highs.keys() {
+            assignment.add_partition_offset(topic, partition, Offset::End)?;
+        }
+        consumer.assign(&assignment)
+    };
     
-    // Configure TLS if enabled
-    if use_tls {
-        config.set("security.protocol", "ssl");
-        config.set("ssl.ca.location", &args.ca_cert);
-        config.set("ssl.certificate.location", &args.client_cert);
-        config.set("ssl.key.location", &args.client_key);
-        println!("🔐 Using TLS connection to {}", broker);
-    } else {
-        println!("📡 Using plain connection to {}", broker);
+    let mut previous = high_watermarks(&consumer, topic, timeout)?;
+    if previous.is_empty() {
+        return Err(format!("topic '{}' does not exist", topic).into());
+    }
+    if sample > 0 {
+        assign_at_end(&previous)?;
+    }
+    let start = Instant::now();
+    let mut last = start;
+    let mut sizes = Vec::new();
+    loop {
+        let deadline = last + interval;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            if sizes.len() >= sample {
+                std::thread::sleep(remaining);
+                continue;
+            }
+            if let Some(message) = consumer.poll(remaining.min(Duration::from_millis(200))) {
+                let msg = message?;
+                sizes.push(msg.key().map_or(0, <[u8]>::len) + msg.payload().map_or(0, <[u8]>::len));
+            }
+        }
+        
+        let current = high_watermarks(&consumer, topic, timeout)?;
+        let now = Instant::now();
+        let summary = ThroughputSample::new(Utc::now().timestamp_millis(), &previous, &current, now - last, &sizes);
+        if json {
+            println!("{}", serde_json::to_string(&summary)?);
+        } else {
+            println!("   {}  {}", Utc::now().format("%H:%M:%S"), summary);
+        }
+        if duration.is_some_and(|duration| now - start >= duration) {
+            return Ok(());
+        }
+        
+        if sample > 0 {
+            assign_at_end(&current)?;
+        }
+        previous = current;
+        last = now;
+        sizes.clear();
+    }
+}
+
+fn summarize(msg: &BorrowedMessage<'_>) -> MessageSummary {
+    let format = msg.payload().map_or(PayloadFormat::Json, |payload| PayloadFormat::of_message(msg, payload));
+    MessageSummary::new(msg.partition(), msg.offset(), msg.timestamp().to_millis(), msg.key(), msg.payload(), format)
+}
+
+/// Describe a created topic's configs and return the requested ones it does not have
+async fn verify_topic_configs(
+    admin: &AdminClient<KafkaClientContext>,
+    topic: &str,
+    requested: &BTreeMap<String, String>,
+) -> Result<Vec<ConfigMismatch>, String> {
+    let options = AdminOptions::new().request_timeout(Some(Duration::from_secs(10)));
+    let mut results = admin
+        .describe_configs(&[ResourceSpecifier::Topic(topic)], &options)
+        .await
+        .map_err(|e| e.to_string())?;
+    match results.pop() {
+        Some(Ok(described)) => Ok(config_mismatches(requested, &described)),
+        Some(Err(e)) => Err(e.to_string()),
+        None => Err("the broker returned no configs".to_string()),
+    }
+}
+
+fn print_acls(entries: &[AclEntry]) {
+    if entries.is_empty() {
+        println!("   (No ACLs found)");
+        return;
+    }
+    let headers = ["RESOURCE", "NAME", "PATTERN", "PRINCIPAL", "HOST", "OPERATION", "PERMISSION"];
+    let rows: Vec<[&str; 7]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                entry.resource_type.as_str(),
+                entry.resource_name.as_str(),
+                entry.pattern_type.as_str(),
+                entry.principal.as_str(),
+                entry.host.as_str(),
+                entry.operation.as_str(),
+                entry.permission.as_str(),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0).max(headers[i].len()))
+        .collect();
+    let format_row = |cells: &[&str]| {
+        let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, &width)| format!("{:<width$}", cell)).collect();
+        format!("   {}", padded.join("  ").trim_end())
+    };
+    println!("{}", format_row(&headers));
+    for row in &rows {
+        println!("{}", format_row(row));
+    }
+}
+
+fn print_group(description: &GroupDescription) {
+    println!("\n👥 Group: {}", description.group);
+    println!("   State: {}", description.state);
+    if !description.protocol.is_empty() {
+        println!("   Assignor: {}", description.protocol);
+    }
+    println!("   Members: {}", description.members.len());
+    for member in &description.members {
+        println!(
+            "      - {} (client {}, host {}): {} partition(s)",
+            member.id,
+            member.client_id,
+            member.host,
+            member.assignment.len()
+        );
     }
     
-    config.create()
+    if description.partitions.is_empty() {
+        println!("\n   (No assigned partitions or committed offsets)");
+        return;
+    }
+    let width = description.partitions.iter().map(|p| p.topic.len()).max().unwrap_or(0).max("TOPIC".len());
+    println!(
+        "\n   {:<width$}  {:>9}  {:>12}  {:>12}  {:>10}  MEMBER",
+        "TOPIC", "PARTITION", "COMMITTED", "LOG-END", "LAG"
+    );
+    for partition in &description.partitions {
+        println!(
+            "   {:<width$}  {:>9}  {:>12}  {:>12}  {:>10}  {}",
+            partition.topic,
+            partition.partition,
+            partition.committed.map_or_else(|| "-".to_string(), |offset| offset.to_string()),
+            partition.log_end,
+            partition.lag,
+            partition.member.as_deref().unwrap_or("-")
+        );
+    }
+    println!("\n📊 Total lag: {} message(s)", description.total_lag);
+}
+
+/// Match a topic or group name against a glob where `*` matches any run of characters and `?` one character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it currently absorbs up to
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, absorbed)) => {
+                    p = star + 1;
+                    n = absorbed + 1;
+                    backtrack = Some((star, absorbed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 fn print_metadata(metadata: &Metadata, topic_filter: Option<&str>) {
@@ -141,23 +989,27 @@ fn print_metadata(metadata: &Metadata, topic_filter: Option<&str>) {
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    let json = args.json;
     
-    println!("🚀 Kafka Admin Tool");
-    println!("────────────────────────────────────────────");
+    say!(json, "🚀 Kafka Admin Tool");
+    say!(json, "────────────────────────────────────────────");
     
-    let admin = match create_admin_client(&args) {
+    let config = match build_kafka_config(&args) {
+        Ok(config) => config,
+        Err(e) => fail(json, "Failed to create admin client", e),
+    };
+    say!(json, "🔐 Using {} connection to {}", config.security_summary(), config.broker());
+    let admin = match kafka::create_admin_client(&config) {
         Ok(client) => client,
-        Err(e) => {
-            eprintln!("❌ Failed to create admin client: {}", e);
-            std::process::exit(1);
-        }
+        Err(e) => fail(json, "Failed to create admin client", e),
     };
     
     match args.command {
         Commands::List => {
-            println!("\n📋 Fetching topic list...");
+            say!(json, "\n📋 Fetching topic list...");
             
             match admin.inner().fetch_metadata(None, Duration::from_secs(10)) {
+                Ok(metadata) if json => emit(&TopicList::new(&metadata)),
                 Ok(metadata) => {
                     println!("\n✅ Topics in cluster:");
                     for topic in metadata.topics() {
@@ -168,91 +1020,599 @@ async fn main() {
                         println!("   (No topics found)");
                     }
                 }
-                Err(e) => {
-                    eprintln!("❌ Failed to fetch metadata: {}", e);
-                    std::process::exit(1);
-                }
+                Err(e) => fail(json, "Failed to fetch metadata", e),
             }
         }
         
         Commands::Check { topic } => {
-            println!("\n🔍 Checking if topic '{}' exists...", topic);
+            say!(json, "\n🔍 Checking if topic '{}' exists...", topic);
             
             match admin.inner().fetch_metadata(None, Duration::from_secs(10)) {
                 Ok(metadata) => {
                     let exists = metadata.topics().iter().any(|t| t.name() == topic);
                     
                     if exists {
-                        println!("✅ Topic '{}' exists", topic);
+                        say!(json, "✅ Topic '{}' exists", topic);
                         
                         // Get detailed info about the topic
                         match admin.inner().fetch_metadata(Some(&topic), Duration::from_secs(10)) {
+                            Ok(topic_metadata) if json => {
+                                let details =
+                                    topic_metadata.topics().iter().find(|t| t.name() == topic).map(TopicInfo::new);
+                                emit(&TopicCheck { topic, exists, details });
+                            }
                             Ok(topic_metadata) => {
                                 print_metadata(&topic_metadata, Some(&topic));
                             }
+                            Err(e) if json => fail(json, "Failed to fetch topic metadata", e),
                             Err(e) => {
                                 eprintln!("⚠️  Failed to fetch topic metadata: {}", e);
                             }
                         }
+                    } else if json {
+                        emit(&TopicCheck { topic, exists, details: None });
                     } else {
                         println!("❌ Topic '{}' does not exist", topic);
                         println!("\n💡 To create it, run:");
                         println!("   cargo run --release --bin kafka_admin -- create --topic {}", topic);
                     }
                 }
-                Err(e) => {
-                    eprintln!("❌ Failed to fetch metadata: {}", e);
-                    std::process::exit(1);
-                }
+                Err(e) => fail(json, "Failed to fetch metadata", e),
             }
         }
         
-        Commands::Create { topic, partitions, replication_factor } => {
-            println!("\n📝 Creating topic '{}'...", topic);
-            println!("   Partitions: {}", partitions);
-            println!("   Replication factor: {}", replication_factor);
+        Commands::Create { topic, partitions, replication_factor, profile, config } => {
+            say!(json, "\n📝 Creating topic '{}'...", topic);
+            say!(json, "   Partitions: {}", partitions);
+            say!(json, "   Replication factor: {}", replication_factor);
+            say!(json, "   Profile: {}", profile);
             
-            let new_topic = NewTopic::new(
-                &topic,
-                partitions,
-                TopicReplication::Fixed(replication_factor)
+            let configs = topic_configs(profile, &config);
+            for (key, value) in &configs {
+                say!(json, "   {} = {}", key, value);
+            }
+            
+            let new_topic = configs.iter().fold(
+                NewTopic::new(&topic, partitions, TopicReplication::Fixed(replication_factor)),
+                |new_topic, (key, value)| new_topic.set(key, value),
             );
             
             let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(30)));
             
-            match admin.create_topics(&[new_topic], &options).await {
-                Ok(results) => {
-                    for result in results {
-                        match result {
-                            Ok(name) => println!("✅ Topic '{}' created successfully", name),
-                            Err((name, err)) => {
-                                if err.to_string().contains("already exists") {
-                                    println!("⚠️  Topic '{}' already exists", name);
-                                } else {
-                                    eprintln!("❌ Failed to create topic '{}': {}", name, err);
-                                }
+            let results = match admin.create_topics(&[new_topic], &options).await {
+                Ok(results) => results,
+                Err(e) => fail(json, "Failed to create topics", e),
+            };
+            for result in results {
+                let mut output = CreateOutput {
+                    topic: topic.clone(),
+                    partitions,
+                    replication_factor,
+                    profile: profile.to_string(),
+                    configs: configs.clone(),
+                    result: TopicResult { topic: topic.clone(), status: TopicStatus::Created, error: None },
+                    config_mismatches: Vec::new(),
+                    verify_error: None,
+                };
+                match result {
+                    Ok(name) => {
+                        say!(json, "✅ Topic '{}' created successfully", name);
+                        if !configs.is_empty() {
+                            match verify_topic_configs(&admin, &name, &configs).await {
+                                Ok(mismatches) => output.config_mismatches = mismatches,
+                                Err(e) => output.verify_error = Some(e),
+                            }
+                        }
+                    }
+                    Err((_, RDKafkaErrorCode::TopicAlreadyExists)) => {
+                        output.result.status = TopicStatus::AlreadyExists;
+                    }
+                    Err((_, RDKafkaErrorCode::InvalidConfig)) => {
+                        output.result.status = TopicStatus::Failed;
+                        output.result.error = Some("the broker rejected a topic config".to_string());
+                    }
+                    Err((_, err)) => {
+                        output.result.status = TopicStatus::Failed;
+                        output.result.error = Some(err.to_string());
+                    }
+                }
+                
+                if json {
+                    emit(&output);
+                    if output.result.status == TopicStatus::Failed {
+                        std::process::exit(1);
+                    }
+                    continue;
+                }
+                if let Some(e) = &output.verify_error {
+                    eprintln!("⚠️  Failed to verify the configs of '{}': {}", topic, e);
+                } else if !configs.is_empty() && output.result.status == TopicStatus::Created {
+                    if output.config_mismatches.is_empty() {
+                        println!("✅ Verified {} topic config(s)", configs.len());
+                    } else {
+                        eprintln!("⚠️  The broker did not apply {} topic config(s):", output.config_mismatches.len());
+                        for mismatch in &output.config_mismatches {
+                            eprintln!("   - {}", mismatch);
+                        }
+                    }
+                }
+                match (output.result.status, &output.result.error) {
+                    (TopicStatus::AlreadyExists, _) => {
+                        println!("⚠️  Topic '{}' already exists (its configs were not changed)", topic);
+                    }
+                    (TopicStatus::Failed, Some(e)) => fail(json, &format!("Failed to create topic '{}'", topic), e),
+                    _ => {}
+                }
+            }
+        }
+        
+        Commands::Delete { topic, pattern, yes, dry_run } => {
+            let mut topics = topic;
+            if !pattern.is_empty() {
+                say!(json, "\n🔍 Resolving topic patterns: {}", pattern.join(", "));
+                match admin.inner().fetch_metadata(None, Duration::from_secs(10)) {
+                    Ok(metadata) => {
+                        for name in metadata.topics().iter().map(|t| t.name()) {
+                            // Internal topics such as __consumer_offsets are never deleted by pattern
+                            let matched = !name.starts_with("__") && pattern.iter().any(|p| glob_match(p, name));
+                            if matched && !topics.iter().any(|t| t == name) {
+                                topics.push(name.to_string());
                             }
                         }
                     }
+                    Err(e) => fail(json, "Failed to fetch metadata", e),
+                }
+            }
+            
+            let mut output = DeleteOutput { topics, dry_run, results: Vec::new() };
+            if output.topics.is_empty() {
+                if json {
+                    emit(&output);
+                } else {
+                    println!("⚠️  No topics match, nothing to delete");
+                }
+                return;
+            }
+            say!(json, "\n🗑️  Topics to delete:");
+            for name in &output.topics {
+                say!(json, "   - {}", name);
+            }
+            if dry_run {
+                if json {
+                    emit(&output);
+                } else {
+                    println!("\n🔍 Dry run, no topics deleted");
+                }
+                return;
+            }
+            if !yes {
+                fail(json, &format!("Refusing to delete {} topic(s)", output.topics.len()), "pass --yes to confirm");
+            }
+            
+            let names: Vec<&str> = output.topics.iter().map(String::as_str).collect();
+            let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(30)));
+            
+            let results = match admin.delete_topics(&names, &options).await {
+                Ok(results) => results,
+                Err(e) => fail(json, "Failed to delete topics", e),
+            };
+            for result in results {
+                let (topic, status, error) = match result {
+                    Ok(name) => {
+                        say!(json, "✅ Topic '{}' deleted", name);
+                        (name, TopicStatus::Deleted, None)
+                    }
+                    Err((name, RDKafkaErrorCode::UnknownTopicOrPartition | RDKafkaErrorCode::UnknownTopic)) => {
+                        say!(json, "⚠️  Topic '{}' does not exist", name);
+                        (name, TopicStatus::NotFound, None)
+                    }
+                    Err((name, err)) => {
+                        if !json {
+                            eprintln!("❌ Failed to delete topic '{}': {}", name, err);
+                        }
+                        (name, TopicStatus::Failed, Some(err.to_string()))
+                    }
+                };
+                output.results.push(TopicResult { topic, status, error });
+            }
+            if json {
+                emit(&output);
+            }
+            if output.results.iter().any(|result| result.status == TopicStatus::Failed) {
+                std::process::exit(1);
+            }
+        }
+        
+        Commands::Purge { topic, partition, before_offset, before_timestamp, yes, dry_run } => {
+            let point = match (before_offset, &before_timestamp) {
+                (Some(offset), _) => PurgePoint::Offset(offset),
+                (None, Some(timestamp)) => match parse_timestamp(timestamp) {
+                    Ok(millis) => PurgePoint::Timestamp(millis),
+                    Err(e) => fail(json, "Invalid --before-timestamp", e),
+                },
+                (None, None) => unreachable!("clap requires --before-offset or --before-timestamp"),
+            };
+            say!(json, "\n✂️  Purging the records of '{}' before {}", topic, point);
+            
+            let targets = match purge_targets(&config, &topic, partition, point) {
+                Ok(targets) => targets,
+                Err(e) => fail(json, &format!("Failed to resolve the purge of '{}'", topic), e),
+            };
+            say!(
+                json,
+                "\n   {:>9}  {:>12}  {:>12}  {:>12}  {:>10}",
+                "PARTITION", "LOG-START", "LOG-END", "BEFORE", "RECORDS"
+            );
+            for target in &targets {
+                say!(
+                    json,
+                    "   {:>9}  {:>12}  {:>12}  {:>12}  {:>10}",
+                    target.partition,
+                    target.low,
+                    target.high,
+                    target.before,
+                    target.records()
+                );
+            }
+            
+            let records: i64 = targets.iter().map(PurgeTarget::records).sum();
+            let mut output = PurgeOutput { topic, dry_run, targets, results: Vec::new() };
+            if dry_run || records == 0 {
+                if json {
+                    emit(&output);
+                } else if dry_run {
+                    println!("\n🔍 Dry run, no records deleted");
+                } else {
+                    println!("\n⚠️  Nothing to delete");
                 }
+                return;
+            }
+            if !yes {
+                fail(json, &format!("Refusing to delete {} record(s)", records), "pass --yes to confirm");
+            }
+            
+            output.results = match purge::delete_records(&admin, &output.targets, Duration::from_secs(30)) {
+                Ok(results) => results,
+                Err(e) => fail(json, &format!("Failed to purge '{}'", output.topic), e),
+            };
+            if json {
+                emit(&output);
+            } else {
+                println!("\n   {:>9}  {:>12}  {:>12}", "PARTITION", "LOG-START", "LOG-END");
+                for result in &output.results {
+                    // Deleting records leaves the high watermark where it was
+                    let high = output.targets.iter().find(|t| t.partition == result.partition).map(|t| t.high);
+                    match (result.low, &result.error) {
+                        (Some(low), _) => {
+                            println!("   {:>9}  {:>12}  {:>12}", result.partition, low, high.unwrap_or(low));
+                        }
+                        (None, error) => {
+                            eprintln!("   {:>9}  ❌ {}", result.partition, error.as_deref().unwrap_or("failed"));
+                        }
+                    }
+                }
+            }
+            if output.results.iter().any(|result| result.error.is_some()) {
+                std::process::exit(1);
+            }
+            say!(json, "\n✅ Deleted {} record(s) from '{}'", records, output.topic);
+        }
+        
+        Commands::Tail { topic, count, follow } => {
+            say!(json, "\n📜 Last {} message(s) on '{}':", count, topic);
+            
+            if let Err(e) = tail_topic(&config, &topic, count, follow, json) {
+                fail(json, &format!("Failed to tail topic '{}'", topic), e);
+            }
+        }
+        
+        Commands::Wait { topic, timeout, create_if_missing, partitions, replication_factor } => {
+            match &topic {
+                Some(topic) => say!(json, "\n⏳ Waiting up to {}s for the broker and topic '{}'", timeout, topic),
+                None => say!(json, "\n⏳ Waiting up to {}s for the broker", timeout),
+            }
+            
+            let create = create_if_missing.then_some((partitions, replication_factor));
+            match wait_for_broker(&admin, topic.as_deref(), Duration::from_secs(timeout), create, json).await {
+                Ok(output) if json => emit(&output),
+                Ok(output) => println!("🚀 Ready after {:.1}s", output.elapsed_ms as f64 / 1000.0),
                 Err(e) => {
-                    eprintln!("❌ Failed to create topics: {}", e);
-                    std::process::exit(1);
+                    say!(json, "");
+                    fail(json, "Gave up waiting", e);
                 }
             }
         }
         
+        Commands::Monitor { topic, interval, duration, sample } => {
+            say!(json, "\n📈 Sampling the produce rate of '{}' every {} (Ctrl-C to stop)...", topic, interval);
+            
+            if let Err(e) = monitor_topic(&config, &topic, interval.0, duration.map(|d| d.0), sample, json) {
+                fail(json, &format!("Failed to monitor topic '{}'", topic), e);
+            }
+        }
+        
         Commands::Metadata { topic } => {
-            println!("\n📊 Fetching cluster metadata...");
+            say!(json, "\n📊 Fetching cluster metadata...");
             
             match admin.inner().fetch_metadata(topic.as_deref(), Duration::from_secs(10)) {
+                Ok(metadata) if json => emit(&ClusterMetadata::new(&metadata, topic.as_deref())),
                 Ok(metadata) => {
                     print_metadata(&metadata, topic.as_deref());
                 }
-                Err(e) => {
-                    eprintln!("❌ Failed to fetch metadata: {}", e);
-                    std::process::exit(1);
+                Err(e) => fail(json, "Failed to fetch metadata", e),
+            }
+        }
+        
+        Commands::Groups { action: GroupsAction::List } => {
+            say!(json, "\n📋 Fetching consumer groups...");
+            
+            match admin.inner().fetch_group_list(None, Duration::from_secs(10)) {
+                Ok(groups) if json => emit(&GroupListing {
+                    groups: groups
+                        .groups()
+                        .iter()
+                        .map(|group| GroupSummary {
+                            name: group.name().to_string(),
+                            state: group.state().to_string(),
+                            members: group.members().len(),
+                        })
+                        .collect(),
+                }),
+                Ok(groups) => {
+                    println!("\n✅ Consumer groups:");
+                    for group in groups.groups() {
+                        println!("   - {} ({}, {} member(s))", group.name(), group.state(), group.members().len());
+                    }
+                    
+                    if groups.groups().is_empty() {
+                        println!("   (No consumer groups found)");
+                    }
+                }
+                Err(e) => fail(json, "Failed to list consumer groups", e),
+            }
+        }
+        
+        Commands::Groups { action: GroupsAction::Delete { group, pattern, yes, force, dry_run } } => {
+            let listing = match admin.inner().fetch_group_list(None, Duration::from_secs(10)) {
+                Ok(listing) => listing,
+                Err(e) => fail(json, "Failed to list consumer groups", e),
+            };
+            let mut groups = group;
+            if !pattern.is_empty() {
+                say!(json, "\n🔍 Resolving group patterns: {}", pattern.join(", "));
+                for name in listing.groups().iter().map(|g| g.name()) {
+                    if pattern.iter().any(|p| glob_match(p, name)) && !groups.iter().any(|g| g == name) {
+                        groups.push(name.to_string());
+                    }
+                }
+            }
+            
+            let summaries: Vec<GroupSummary> = groups
+                .iter()
+                .filter_map(|name| listing.groups().iter().find(|g| g.name() == name))
+                .map(|g| GroupSummary {
+                    name: g.name().to_string(),
+                    state: g.state().to_string(),
+                    members: g.members().len(),
+                })
+                .collect();
+            let mut output = GroupDeleteOutput { groups, dry_run, results: Vec::new() };
+            if output.groups.is_empty() {
+                if json {
+                    emit(&output);
+                } else {
+                    println!("⚠️  No groups match, nothing to delete");
                 }
+                return;
+            }
+            say!(json, "\n🗑️  Groups to delete:");
+            for name in &output.groups {
+                match summaries.iter().find(|summary| &summary.name == name) {
+                    Some(summary) => say!(json, "   - {} ({}, {} member(s))", name, summary.state, summary.members),
+                    None => say!(json, "   - {} (not listed by the cluster)", name),
+                }
+            }
+            if dry_run {
+                if json {
+                    emit(&output);
+                } else {
+                    println!("\n🔍 Dry run, no groups deleted");
+                }
+                return;
+            }
+            let active: Vec<&str> =
+                summaries.iter().filter(|summary| summary.members > 0).map(|summary| summary.name.as_str()).collect();
+            if !active.is_empty() && !force {
+                fail(
+                    json,
+                    &format!("Refusing to delete {} group(s) with active members", active.len()),
+                    format!("{}; stop their consumers or pass --force", active.join(", ")),
+                );
+            }
+            if !yes {
+                fail(json, &format!("Refusing to delete {} group(s)", output.groups.len()), "pass --yes to confirm");
+            }
+            
+            let names: Vec<&str> = output.groups.iter().map(String::as_str).collect();
+            let options = AdminOptions::new().operation_timeout(Some(Duration::from_secs(30)));
+            
+            let results = match admin.delete_groups(&names, &options).await {
+                Ok(results) => results,
+                Err(e) => fail(json, "Failed to delete consumer groups", e),
+            };
+            for result in results {
+                let (group, status, error) = match result {
+                    Ok(name) => {
+                        say!(json, "✅ Group '{}' deleted", name);
+                        (name, GroupStatus::Deleted, None)
+                    }
+                    Err((name, RDKafkaErrorCode::GroupIdNotFound)) => {
+                        say!(json, "⚠️  Group '{}' does not exist", name);
+                        (name, GroupStatus::NotFound, None)
+                    }
+                    Err((name, err)) => {
+                        let error = match err {
+                            RDKafkaErrorCode::NonEmptyGroup => "the group has active members".to_string(),
+                            err => err.to_string(),
+                        };
+                        if !json {
+                            eprintln!("❌ Failed to delete group '{}': {}", name, error);
+                        }
+                        (name, GroupStatus::Failed, Some(error))
+                    }
+                };
+                output.results.push(GroupDeleteResult { group, status, error });
+            }
+            if json {
+                emit(&output);
+            }
+            if output.results.iter().any(|result| result.status == GroupStatus::Failed) {
+                std::process::exit(1);
+            }
+        }
+        
+        Commands::ResetOffsets {
+            group,
+            topic,
+            to_earliest,
+            to_latest,
+            to_offset,
+            to_timestamp,
+            partition_offset,
+            dry_run,
+        } => {
+            let strategy = match (to_earliest, to_latest, to_offset, &to_timestamp) {
+                (true, _, _, _) => Some(ResetStrategy::Earliest),
+                (_, true, _, _) => Some(ResetStrategy::Latest),
+                (_, _, Some(offset), _) => Some(ResetStrategy::Offset(offset)),
+                (_, _, _, Some(timestamp)) => match parse_timestamp(timestamp) {
+                    Ok(millis) => Some(ResetStrategy::Timestamp(millis)),
+                    Err(e) => fail(json, "Invalid --to-timestamp", e),
+                },
+                _ => None,
+            };
+            if strategy.is_none() && partition_offset.is_empty() {
+                fail(
+                    json,
+                    "Nothing to reset",
+                    "give --to-earliest, --to-latest, --to-offset, --to-timestamp or --partition-offset",
+                );
+            }
+            
+            say!(json, "\n⏪ Resetting offsets of group '{}' on {}", group, topic);
+            if let Some(strategy) = strategy {
+                say!(json, "   Strategy: {}", strategy);
+            }
+            for partition in &partition_offset {
+                say!(json, "   Partition {}: offset {}", partition.partition, partition.offset);
+            }
+            
+            match reset_offsets(&config, &group, &topic, strategy, &partition_offset, dry_run, json) {
+                Ok(targets) if json => emit(&ResetOutput {
+                    committed: !dry_run && !targets.is_empty(),
+                    group,
+                    topic,
+                    strategy: strategy.map(|strategy| strategy.to_string()),
+                    dry_run,
+                    targets,
+                }),
+                Ok(_) => {}
+                Err(e) => fail(json, &format!("Failed to reset offsets of group '{}'", group), e),
+            }
+        }
+        
+        Commands::Acls { action: AclsAction::List { resource_type, name, prefix, principal, host, operation } } => {
+            say!(json, "\n🔐 Fetching ACLs...");
+            let filter = acl_filter(resource_type, name, prefix, principal, host, operation);
+            
+            match acls::list_acls(&admin, &filter, Duration::from_secs(10)) {
+                Ok(entries) if json => emit(&AclList { acls: entries }),
+                Ok(entries) => {
+                    println!("\n✅ ACLs:");
+                    print_acls(&entries);
+                }
+                Err(e) => fail(json, "Failed to list ACLs", e),
+            }
+        }
+        
+        Commands::Acls { action: AclsAction::Create { resource_type, name, prefix, principal, host, operation } } => {
+            let resource = match (name, prefix) {
+                (Some(name), _) => ResourceName::Literal(name),
+                (None, Some(prefix)) => ResourceName::Prefixed(prefix),
+                (None, None) => unreachable!("clap requires --name or --prefix"),
+            };
+            let rules: Vec<AclRule> = operation
+                .into_iter()
+                .map(|operation| AclRule {
+                    resource_type,
+                    resource: resource.clone(),
+                    principal: principal.clone(),
+                    host: host.clone(),
+                    operation,
+                })
+                .collect();
+            
+            say!(json, "\n📝 Creating {} ACL(s)...", rules.len());
+            for rule in &rules {
+                let (pattern, name) = match &rule.resource {
+                    ResourceName::Literal(name) => ("", name),
+                    ResourceName::Prefixed(prefix) => (" prefix", prefix),
+                };
+                say!(
+                    json,
+                    "   Allow {} {} on {}{} '{}' from {}",
+                    rule.principal, rule.operation, rule.resource_type, pattern, name, rule.host
+                );
+            }
+            
+            match acls::create_acls(&admin, &rules, Duration::from_secs(30)) {
+                Ok(()) if json => emit(&AclList { acls: rules.iter().map(AclRule::entry).collect() }),
+                Ok(()) => println!("✅ Created {} ACL(s)", rules.len()),
+                Err(e) => fail(json, "Failed to create ACLs", e),
+            }
+        }
+        
+        Commands::Acls {
+            action: AclsAction::Delete { resource_type, name, prefix, principal, host, operation, yes, dry_run },
+        } => {
+            let filter = acl_filter(resource_type, name, prefix, principal, host, operation);
+            let timeout = Duration::from_secs(30);
+            
+            // Preview what the filter matches before deleting anything
+            let matching = match acls::list_acls(&admin, &filter, timeout) {
+                Ok(entries) => entries,
+                Err(e) => fail(json, "Failed to list ACLs", e),
+            };
+            if !json {
+                println!("\n🗑️  ACLs to delete:");
+                print_acls(&matching);
+            }
+            if dry_run || matching.is_empty() {
+                if json {
+                    emit(&AclDeleteOutput { dry_run, deleted: false, acls: matching });
+                } else if dry_run {
+                    println!("\n🔍 Dry run, no ACLs deleted");
+                }
+                return;
+            }
+            if !yes {
+                fail(json, &format!("Refusing to delete {} ACL(s)", matching.len()), "pass --yes to confirm");
+            }
+            
+            match acls::delete_acls(&admin, &filter, timeout) {
+                Ok(deleted) if json => emit(&AclDeleteOutput { dry_run, deleted: true, acls: deleted }),
+                Ok(deleted) => println!("\n✅ Deleted {} ACL(s)", deleted.len()),
+                Err(e) => fail(json, "Failed to delete ACLs", e),
+            }
+        }
+        
+        Commands::Groups { action: GroupsAction::Describe { group, topic } } => {
+            say!(json, "\n🔍 Describing consumer group '{}'...", group);
+            
+            match describe_group(&config, &group, &topic) {
+                Ok(description) if json => emit(&description),
+                Ok(description) => print_group(&description),
+                Err(e) => fail(json, &format!("Failed to describe consumer group '{}'", group), e),
             }
         }
     }
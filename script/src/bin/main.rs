@@ -1,37 +1,101 @@
 //! Solana stub prover script that fetches account data from Solana devnet
 //! and generates SP1 proofs to post to Kafka
 
-use base64::{Engine as _, engine::general_purpose};
 use clap::Parser;
-use serde_json;
 use std::fs;
-use solana_stub_prover_lib::{ProverInput, PublicCommitments, AccountStateCommitment};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use solana_stub_prover_script::{
-    kafka::{publish_json_to_kafka_with_config, KafkaConfig},
-    solana::{fetch_account_info, get_current_slot},
-    utils::{base58_to_bytes32, get_epoch_for_slot, sha256_from_u64, sha256_hash},
+    account_data::{self, RawAccountData, RawData},
+    artifacts::{artifact_file_name, write_json},
+    checkpoint::Checkpoint,
+    dlq::{self, DEFAULT_DLQ_DIR},
+    envelope::EnvelopeWithMetadata,
+    fixtures::load_input,
+    spool::DEFAULT_MAX_SPOOL_BYTES,
+    presenter::{self, commitment_rows, ColorChoice, OutputStyle, Presenter, Section},
+    pipeline::{
+        assemble_input, check_input_size, execute, missing_accounts, prove_with, publish, Consistency, InputConfig,
+        InputLimits, PipelineError, ProgramKeys, ProofArtifacts, DEFAULT_MAX_TOTAL_INPUT_BYTES,
+        DEFAULT_RPC_CONCURRENCY,
+    },
+    proof_sink::{DirSink, SinkKind},
+    prove_jobs::ProofMode,
+    kafka::{
+        ConnectionOptions, KafkaAuth, KafkaConfig, KafkaConfigError, KafkaPublishError, PartitionKey,
+        PayloadFormat, ProofPublisher, RetryPolicy, SaslMechanism, SecurityProtocol, TopicCreation,
+        DEFAULT_MAX_MESSAGE_BYTES,
+    },
+    proof_filter::decode_commitments,
+    routing::{Route, DEFAULT_NETWORK},
+    s3_archive::{ArchiveOptions, ArchiveSink, Encryption, ProofMetrics, S3Location},
+    schema_registry::SchemaRegistryConfig,
+    solana::{SolanaRpcClient, DEVNET_RPC_URL},
+    utils::{validate_pubkeys, PubkeyProblem},
+    vkey_registry::{check_startup_vkey, format_vkey_hash, VkeyCheck, VkeyRegistry},
 };
-use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
-use twine_types::proofs::{ZkProof, ProofKind, ProofData, SP1Proof};
+use solana_stub_prover_lib::{MAX_ACCOUNT_DATA_LEN, STATEMENT_VERSION};
+use sp1_sdk::HashableKey;
+use twine_types::proofs::ZkProof;
 
-/// The ELF file for the Solana stub prover program
-pub const PROVER_ELF: &[u8] = include_elf!("solana-stub-prover-program");
+/// Exit code used when the transactional producer has been fenced by a newer instance
+const EXIT_FENCED: i32 = 3;
 
 /// Command line arguments
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Start slot number
-    #[arg(long)]
-    start_slot: u64,
+    #[arg(long, required_unless_present_any = ["from_dlq", "from_archive", "input"])]
+    start_slot: Option<u64>,
     
     /// End slot number
+    #[arg(long, required_unless_present_any = ["from_dlq", "from_archive", "input"])]
+    end_slot: Option<u64>,
+    
+    /// Account pubkey to monitor (base58 encoded); repeat to monitor several
+    #[arg(long, required_unless_present_any = ["from_dlq", "from_archive", "input"])]
+    account: Vec<String>,
+    
+    /// Accounts fetched from the RPC node at the same time
+    #[arg(long, default_value_t = DEFAULT_RPC_CONCURRENCY)]
+    rpc_concurrency: usize,
+    
+    /// Reject an account with more data than this; the program accepts at most 10 MiB
+    #[arg(long, default_value_t = MAX_ACCOUNT_DATA_LEN)]
+    max_account_bytes: usize,
+    
+    /// Reject inputs whose accounts have more data than this together
+    #[arg(long, default_value_t = DEFAULT_MAX_TOTAL_INPUT_BYTES)]
+    max_total_input_bytes: usize,
+    
+    /// Commit accounts that do not exist at the end slot as closed instead of failing
+    #[arg(long, conflicts_with = "input")]
+    allow_missing_accounts: bool,
+    
+    /// Accept the all-zeros pubkey (the system program) as --account
     #[arg(long)]
-    end_slot: u64,
+    allow_zero_pubkey: bool,
+    
+    /// Refuse to publish a proof under another program vkey hash (0x-prefixed bytes32)
+    #[arg(long, conflicts_with = "vkey_registry")]
+    expected_vkey_hash: Option<String>,
+    
+    /// TOML file of statement version = program vkey hash; refuse to publish under another key
+    #[arg(long)]
+    vkey_registry: Option<PathBuf>,
     
-    /// Account pubkey to monitor (base58 encoded)
+    /// Refuse to prove when the program vkey hash is not the one pinned at build time, instead of warning
     #[arg(long)]
-    account: String,
+    strict_vkey: bool,
+    
+    /// Publish even if the committed public values differ from the input; only for debugging the program
+    #[arg(long)]
+    unsafe_skip_consistency_check: bool,
+    
+    /// ProverInput JSON to run instead of fetching the account, e.g. a gen-fixtures fixture
+    #[arg(long, conflicts_with_all = ["start_slot", "end_slot", "account", "use_current_slot"])]
+    input: Option<PathBuf>,
     
     /// Execute only (no proof generation)
     #[arg(long)]
@@ -53,7 +117,35 @@ struct Args {
     #[arg(long)]
     compressed_only: bool,
     
-    /// Kafka broker address (overrides default)
+    /// Commit the monitored accounts' raw data instead of sending it in the envelope metadata
+    #[arg(long)]
+    commit_raw_data: bool,
+    
+    /// Hash the monitored accounts' raw data as it is fetched, and neither commit nor send it
+    #[arg(long, conflicts_with = "commit_raw_data")]
+    drop_raw_data: bool,
+    
+    /// Print the program's cycles per phase and per account
+    #[arg(long)]
+    profile_guest: bool,
+    
+    /// Gzip last_proof.json and last_kafka_message.json (written as .json.gz)
+    #[arg(long)]
+    gzip_artifacts: bool,
+    
+    /// Solana JSON-RPC endpoint accounts and block times are fetched from
+    #[arg(long, env = "SOLANA_RPC_URL", default_value = DEVNET_RPC_URL)]
+    rpc_url: String,
+    
+    /// Where proofs go: kafka, or file to write them to --sink-dir without a broker
+    #[arg(long, default_value = "kafka")]
+    sink: SinkKind,
+    
+    /// Directory proofs are written to with --sink file
+    #[arg(long, default_value = "proofs")]
+    sink_dir: PathBuf,
+    
+    /// Kafka broker address (overrides KAFKA_BROKER and the default)
     #[arg(long)]
     kafka_broker: Option<String>,
     
@@ -65,17 +157,484 @@ struct Args {
     #[arg(long)]
     no_kafka_tls: bool,
     
-    /// CA certificate file path for Kafka TLS
-    #[arg(long, default_value = "./ca.crt")]
-    kafka_ca_cert: String,
+    /// CA certificate file path for Kafka TLS (default: KAFKA_CA_CERT or ./ca.crt)
+    #[arg(long)]
+    kafka_ca_cert: Option<String>,
+    
+    /// Client certificate file path for Kafka TLS (default: KAFKA_CLIENT_CERT or ./user.crt)
+    #[arg(long)]
+    kafka_client_cert: Option<String>,
+    
+    /// Client key file path for Kafka TLS (default: KAFKA_CLIENT_KEY or ./user.key)
+    #[arg(long)]
+    kafka_client_key: Option<String>,
+    
+    /// Kafka SASL authentication: none or msk-iam (default: KAFKA_AUTH or none)
+    #[arg(long)]
+    kafka_auth: Option<KafkaAuth>,
+    
+    /// Kafka security protocol: plaintext, ssl, sasl_plaintext or sasl_ssl (default: KAFKA_SECURITY_PROTOCOL,
+    /// otherwise derived from the TLS and SASL options)
+    #[arg(long)]
+    kafka_security_protocol: Option<SecurityProtocol>,
+    
+    /// Kafka SASL mechanism: PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512 (default: KAFKA_SASL_MECHANISM)
+    #[arg(long)]
+    kafka_sasl_mechanism: Option<SaslMechanism>,
+    
+    /// Kafka SASL username (default: KAFKA_USERNAME)
+    #[arg(long)]
+    kafka_username: Option<String>,
+    
+    /// Kafka SASL password (default: KAFKA_PASSWORD)
+    #[arg(long)]
+    kafka_password: Option<String>,
+    
+    /// AWS region of the MSK cluster for msk-iam authentication
+    #[arg(long, env = "AWS_REGION")]
+    aws_region: Option<String>,
+    
+    /// IAM role to assume when signing msk-iam tokens
+    #[arg(long, env = "AWS_ROLE_ARN")]
+    aws_role_arn: Option<String>,
+    
+    /// Continue without Kafka certificate files that do not exist (plaintext-broker development)
+    #[arg(long)]
+    allow_missing_certs: bool,
+    
+    /// Kafka topic (default: KAFKA_TOPIC or twine.solana.proofs)
+    #[arg(long)]
+    kafka_topic: Option<String>,
+    
+    /// Route proofs to a topic by kind or network: kind=<groth16|compressed>:<topic> or
+    /// network=<network>:<topic> (repeatable, first match wins)
+    #[arg(long = "route")]
+    routes: Vec<Route>,
+    
+    /// Solana network the proofs are generated against, matched by network routes
+    #[arg(long, default_value = DEFAULT_NETWORK)]
+    network: String,
+    
+    /// Maximum number of Kafka publish attempts for retryable errors
+    #[arg(long, default_value = "5")]
+    kafka_retry_attempts: u32,
+    
+    /// Initial delay between Kafka publish retries in milliseconds
+    #[arg(long, default_value = "500")]
+    kafka_retry_base_delay_ms: u64,
+    
+    /// Maximum delay between Kafka publish retries in milliseconds
+    #[arg(long, default_value = "10000")]
+    kafka_retry_max_delay_ms: u64,
+    
+    /// Largest Kafka message the producer will send, in bytes (message.max.bytes)
+    #[arg(long, default_value_t = DEFAULT_MAX_MESSAGE_BYTES)]
+    kafka_max_message_bytes: usize,
+    
+    /// Kafka payload encoding (json, bincode or avro)
+    #[arg(long, default_value = "json")]
+    payload_format: PayloadFormat,
+    
+    /// Kafka record key: identifier, account (first monitored account) or slot-range
+    #[arg(long, default_value = "identifier")]
+    partition_key: PartitionKey,
+    
+    /// Kafka record key template overriding --partition-key, with {account}, {start} and {end} placeholders
+    #[arg(long)]
+    kafka_key_template: Option<String>,
+    
+    /// Schema Registry URL (required for --payload-format avro)
+    #[arg(long, env = "SCHEMA_REGISTRY_URL")]
+    schema_registry_url: Option<String>,
+    
+    /// Schema Registry basic auth username
+    #[arg(long, env = "SCHEMA_REGISTRY_USERNAME")]
+    schema_registry_username: Option<String>,
+    
+    /// Schema Registry basic auth password
+    #[arg(long, env = "SCHEMA_REGISTRY_PASSWORD")]
+    schema_registry_password: Option<String>,
+    
+    /// Publish inside a Kafka transaction with this transactional.id (exactly-once)
+    #[arg(long, env = "KAFKA_TRANSACTIONAL_ID")]
+    kafka_transactional_id: Option<String>,
+    
+    /// File recording the last published proof, written before the transaction commits (Kafka sink only)
+    #[arg(long)]
+    checkpoint_file: Option<PathBuf>,
+    
+    /// Dead-letter topic for proofs that fail to publish after all retries
+    #[arg(long)]
+    kafka_dlq_topic: Option<String>,
+    
+    /// Directory dead letters are spooled to when the DLQ topic cannot be written
+    #[arg(long, default_value = DEFAULT_DLQ_DIR)]
+    dlq_dir: PathBuf,
+    
+    /// Spool proofs to this directory when the broker is unreachable and publish them on a later run
+    #[arg(long)]
+    spool_dir: Option<PathBuf>,
+    
+    /// Size limit of --spool-dir in bytes; new proofs are refused once it is reached
+    #[arg(long, default_value_t = DEFAULT_MAX_SPOOL_BYTES)]
+    max_spool_bytes: u64,
+    
+    /// Skip the Kafka connectivity check that runs before proving
+    #[arg(long)]
+    skip_preflight: bool,
+    
+    /// Timeout of the Kafka connectivity check in seconds
+    #[arg(long, default_value = "10")]
+    preflight_timeout_secs: u64,
+    
+    /// Create missing target topics during the connectivity check
+    #[arg(long)]
+    create_topic_if_missing: bool,
+    
+    /// Number of partitions for topics created by --create-topic-if-missing
+    #[arg(long, default_value = "3")]
+    topic_partitions: i32,
+    
+    /// Replication factor for topics created by --create-topic-if-missing
+    #[arg(long, default_value = "1")]
+    topic_replication_factor: i32,
+    
+    /// Replay dead letters spooled in --dlq-dir instead of proving
+    #[arg(long)]
+    from_dlq: bool,
+    
+    /// Also archive proofs to S3 at <bucket>/<prefix>
+    #[arg(long, env = "ARCHIVE_S3")]
+    archive_s3: Option<S3Location>,
+    
+    /// Endpoint of S3-compatible storage such as MinIO (default: AWS)
+    #[arg(long, env = "ARCHIVE_S3_ENDPOINT", requires = "archive_s3")]
+    archive_endpoint: Option<String>,
+    
+    /// Server-side encryption of archived objects: none, aes256 or kms
+    #[arg(long, default_value = "none")]
+    archive_sse: Encryption,
+    
+    /// KMS key for --archive-sse kms (default: the AWS managed key)
+    #[arg(long, requires = "archive_s3")]
+    archive_kms_key_id: Option<String>,
+    
+    /// Publish the proof archived under this S3 key to Kafka instead of proving
+    #[arg(long, requires = "archive_s3", conflicts_with = "from_dlq")]
+    from_archive: Option<String>,
+    
+    /// Style of the summaries: pretty or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// Build the Kafka configuration from `KAFKA_*` environment variables overridden by CLI flags
+fn build_kafka_config(args: &Args) -> Result<KafkaConfig, KafkaConfigError> {
+    let mut config = KafkaConfig::from_env_with(&ConnectionOptions {
+        broker: args.kafka_broker.clone(),
+        client_id: None,
+        disable_tls: args.no_kafka_tls || !args.kafka_tls,
+        protocol: args.kafka_security_protocol,
+        auth: args.kafka_auth,
+        sasl_mechanism: args.kafka_sasl_mechanism,
+        sasl_username: args.kafka_username.clone(),
+        sasl_password: args.kafka_password.clone(),
+        aws_region: args.aws_region.clone(),
+        aws_role_arn: args.aws_role_arn.clone(),
+        ca_cert_path: args.kafka_ca_cert.clone(),
+        client_cert_path: args.kafka_client_cert.clone(),
+        client_key_path: args.kafka_client_key.clone(),
+        allow_missing_certs: args.allow_missing_certs,
+    })?;
+    
+    if let Some(topic) = &args.kafka_topic {
+        config.topic = topic.clone();
+    }
+    config.routes = args.routes.clone();
+    config.network = args.network.clone();
+    
+    config.retry_policy = RetryPolicy {
+        max_attempts: args.kafka_retry_attempts,
+        base_delay: Duration::from_millis(args.kafka_retry_base_delay_ms),
+        max_delay: Duration::from_millis(args.kafka_retry_max_delay_ms),
+    };
+    config.max_message_bytes = args.kafka_max_message_bytes;
+    config.payload_format = args.payload_format;
+    config.partition_key = args.partition_key;
+    config.key_template = args.kafka_key_template.clone();
+    config.transactional_id = args.kafka_transactional_id.clone();
+    config.dlq_topic = args.kafka_dlq_topic.clone();
+    config.dlq_dir = args.dlq_dir.clone();
+    config.spool_dir = args.spool_dir.clone();
+    config.max_spool_bytes = args.max_spool_bytes;
+    config.schema_registry = args.schema_registry_url.clone().map(|url| SchemaRegistryConfig {
+        url,
+        username: args.schema_registry_username.clone(),
+        password: args.schema_registry_password.clone(),
+    });
+    
+    Ok(config)
+}
+
+/// Record timestamp for a proof: the block time of its end slot, in milliseconds
+///
+/// Returns `None` (the publisher then uses the current time) when the RPC node
+/// has no block time for the slot.
+async fn record_timestamp(rpc: &SolanaRpcClient, end_slot: u64) -> Option<i64> {
+    match rpc.get_block_time(end_slot).await {
+        Ok(Some(block_time)) => {
+            println!("Using block time of slot {} as record timestamp: {}", end_slot, block_time);
+            Some(block_time * 1000)
+        }
+        Ok(None) => {
+            eprintln!("Warning: no block time for slot {}, using the current time as record timestamp", end_slot);
+            None
+        }
+        Err(e) => {
+            eprintln!("Warning: failed to fetch block time for slot {} ({}), using the current time", end_slot, e);
+            None
+        }
+    }
+}
+
+/// Check that the broker is reachable and the target topics exist before spending time on proving
+///
+/// Uses a non-transactional publisher so the check does not register with
+/// the transaction coordinator.
+async fn preflight(args: &Args, kafka_config: &KafkaConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut preflight_config = kafka_config.clone();
+    preflight_config.transactional_id = None;
+    let publisher = ProofPublisher::new(&preflight_config)?;
+    
+    let create_missing = args.create_topic_if_missing.then_some(TopicCreation {
+        partitions: args.topic_partitions,
+        replication_factor: args.topic_replication_factor,
+    });
+    publisher
+        .check_connectivity(Duration::from_secs(args.preflight_timeout_secs), create_missing)
+        .await?;
+    println!("Kafka pre-flight check passed for {}", publisher.target_topics().join(", "));
+    Ok(())
+}
+
+/// Exit cleanly if the producer was fenced, so the orchestrator restarts a single instance
+fn exit_if_fenced(e: KafkaPublishError) -> KafkaPublishError {
+    if e.is_fenced() {
+        eprintln!("Error: {}", e);
+        eprintln!("Another prover instance is using the same transactional id; exiting.");
+        std::process::exit(EXIT_FENCED);
+    }
+    e
+}
+
+/// Publish a proof and record the checkpoint
+///
+/// In transactional mode the record only becomes visible to read-committed
+/// consumers once the checkpoint has been durably written; if writing the
+/// checkpoint fails the transaction is aborted. Returns `false` when the
+/// broker was unreachable and the proof was spooled instead.
+async fn publish_proof(
+    zk_proof: &ZkProof,
+    account_data: &[RawAccountData],
+    kafka_config: &KafkaConfig,
+    checkpoint_path: Option<&Path>,
+    start_slot: u64,
+    end_slot: u64,
+    timestamp_ms: Option<i64>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let publisher = match ProofPublisher::new(kafka_config) {
+        Ok(publisher) => publisher,
+        Err(e) => match e.downcast::<KafkaPublishError>() {
+            Ok(e) => return Err(Box::new(exit_if_fenced(*e))),
+            Err(e) => return Err(e),
+        },
+    };
+    let transactional = kafka_config.transactional_id.is_some();
+    
+    if transactional {
+        publisher.begin_transaction().map_err(exit_if_fenced)?;
+    }
+    
+    // Raw account data left out of the public values travels in the envelope's metadata
+    let published = if account_data.is_empty() {
+        publisher.publish_proof_at(zk_proof, timestamp_ms).await
+    } else {
+        publisher.publish_proof_with_metadata(zk_proof, timestamp_ms, &account_data::metadata(account_data)).await
+    };
+    let (partition, offset) = match published {
+        Ok(delivery) => delivery,
+        Err(KafkaPublishError::Spooled(path)) => {
+            // Not published yet, so there is nothing to checkpoint
+            println!("Proof spooled to {}; it will be published on the next run", path.display());
+            return Ok(false);
+        }
+        Err(e) => {
+            let e = exit_if_fenced(e);
+            if transactional {
+                publisher.abort_transaction().map_err(exit_if_fenced)?;
+            }
+            return Err(Box::new(e));
+        }
+    };
+    
+    if let Some(path) = checkpoint_path {
+        let checkpoint = Checkpoint {
+            identifier: zk_proof.identifier.clone(),
+            start_slot,
+            end_slot,
+            partition,
+            offset,
+        };
+        if let Err(e) = checkpoint.write_durable(path) {
+            if transactional {
+                eprintln!("Error: failed to write checkpoint, aborting Kafka transaction");
+                publisher.abort_transaction().map_err(exit_if_fenced)?;
+            }
+            return Err(e);
+        }
+        println!("Checkpoint written to {}", path.display());
+    }
+    
+    if transactional {
+        publisher.commit_transaction().map_err(exit_if_fenced)?;
+        println!("Kafka transaction committed");
+    }
+    
+    let mut summary = Section::new("Routing summary");
+    for (topic, count) in publisher.routing_summary() {
+        summary.field(0, topic, format!("{} proof(s)", count));
+    }
+    println!("{}", summary.render(presenter::current()));
     
-    /// Client certificate file path for Kafka TLS
-    #[arg(long, default_value = "./user.crt")]
-    kafka_client_cert: String,
+    Ok(true)
+}
+
+/// Hand a proof to the selected sink
+///
+/// Returns `false` when the proof was spooled instead of published.
+async fn deliver_proof(
+    args: &Args,
+    kafka_config: Option<&KafkaConfig>,
+    artifacts: &ProofArtifacts,
+    start_slot: u64,
+    end_slot: u64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    match (args.sink, kafka_config) {
+        (SinkKind::Kafka, Some(kafka_config)) => {
+            println!("Publishing proof to Kafka...");
+            let timestamp_ms = record_timestamp(&SolanaRpcClient::new(&args.rpc_url), end_slot).await;
+            let checkpoint_path = args.checkpoint_file.as_deref();
+            publish_proof(
+                &artifacts.zk_proof,
+                &artifacts.account_data,
+                kafka_config,
+                checkpoint_path,
+                start_slot,
+                end_slot,
+                timestamp_ms,
+            )
+            .await
+        }
+        (SinkKind::Kafka, None) => Err("Kafka config is built for the Kafka sink".into()),
+        (SinkKind::File, _) => {
+            let sink = DirSink::new(&args.sink_dir)?;
+            let receipt = publish(artifacts, &sink).await?;
+            println!("Proof written to {}", receipt);
+            Ok(true)
+        }
+    }
+}
+
+/// Publish every spooled dead letter to its original topic, oldest first
+///
+/// Stops at the first failure so the remaining letters keep their order.
+async fn replay_dead_letters(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let kafka_config = build_kafka_config(args)?;
+    kafka_config.validate()?;
+    let publisher = ProofPublisher::new(&kafka_config)?;
+    
+    let paths = dlq::spooled(&args.dlq_dir)?;
+    println!("Replaying {} dead letters from {}", paths.len(), args.dlq_dir.display());
+    
+    for path in &paths {
+        let letter = dlq::read(path)?;
+        println!(
+            "Replaying {} (failed at {}: {})",
+            letter.key, letter.failed_at, letter.error
+        );
+        publisher.replay_dead_letter(&letter).await?;
+        fs::remove_file(path)?;
+    }
     
-    /// Client key file path for Kafka TLS
-    #[arg(long, default_value = "./user.key")]
-    kafka_client_key: String,
+    println!("All {} dead letters replayed", paths.len());
+    Ok(())
+}
+
+/// Connect to the bucket given by --archive-s3
+async fn connect_archive(args: &Args, location: &S3Location) -> Result<ArchiveSink, Box<dyn std::error::Error>> {
+    ArchiveSink::connect(ArchiveOptions {
+        location: location.clone(),
+        network: args.network.clone(),
+        endpoint: args.archive_endpoint.clone(),
+        encryption: args.archive_sse,
+        kms_key_id: args.archive_kms_key_id.clone(),
+    })
+    .await
+}
+
+/// Publish a proof archived in S3 to Kafka again
+async fn republish_archived(args: &Args, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(location) = &args.archive_s3 else {
+        unreachable!("clap requires --archive-s3 with --from-archive");
+    };
+    let kafka_config = build_kafka_config(args)?;
+    kafka_config.validate()?;
+    let archive = connect_archive(args, location).await?;
+    
+    let zk_proof = archive.fetch(key).await?;
+    let commitments = decode_commitments(&zk_proof)
+        .ok_or_else(|| format!("Public values of {} are not PublicCommitments", zk_proof.identifier))?;
+    println!("Republishing {} from s3://{}/{}", zk_proof.identifier, location.bucket, key);
+    
+    let (start_slot, end_slot) = (commitments.start_slot, commitments.end_slot);
+    let timestamp_ms = record_timestamp(&SolanaRpcClient::new(&args.rpc_url), end_slot).await;
+    let checkpoint_path = args.checkpoint_file.as_deref();
+    if publish_proof(&zk_proof, &[], &kafka_config, checkpoint_path, start_slot, end_slot, timestamp_ms).await? {
+        println!("Archived proof successfully published to Kafka!");
+    }
+    Ok(())
+}
+
+/// Input config of the range and accounts given on the command line
+fn input_config(args: &Args) -> InputConfig {
+    // clap requires these unless --from-dlq, --from-archive or --input was given
+    let (Some(start_slot), Some(end_slot)) = (args.start_slot, args.end_slot) else {
+        unreachable!(
+            "--start-slot, --end-slot and --account are required without --from-dlq, --from-archive or --input"
+        );
+    };
+    InputConfig {
+        rpc_url: args.rpc_url.clone(),
+        accounts: args.account.clone(),
+        start_slot,
+        // Optionally use current slot
+        end_slot: if args.use_current_slot && end_slot == 0 { None } else { Some(end_slot) },
+        concurrency: args.rpc_concurrency,
+        limits: input_limits(args),
+        allow_missing: args.allow_missing_accounts,
+        allow_zero_pubkey: args.allow_zero_pubkey,
+        // Ask the node, so clusters started with warmup epochs commit the right epoch
+        epoch_schedule: None,
+        keep_data: !args.drop_raw_data,
+    }
+}
+
+fn input_limits(args: &Args) -> InputLimits {
+    InputLimits { max_account_bytes: args.max_account_bytes, max_total_bytes: args.max_total_input_bytes }
 }
 
 #[tokio::main]
@@ -85,232 +644,284 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv().ok();
     
     // Parse arguments
-    let mut args = Args::parse();
+    let args = Args::parse();
+    let presenter = Presenter::new(args.style, args.color).install();
+    
+    if args.from_dlq {
+        return replay_dead_letters(&args).await;
+    }
+    
+    if let Some(key) = &args.from_archive {
+        return republish_archived(&args, key).await;
+    }
     
     if args.execute == args.prove {
         eprintln!("Error: You must specify either --execute or --prove");
         std::process::exit(1);
     }
     
-    // Optionally use current slot
-    if args.use_current_slot && args.end_slot == 0 {
-        args.end_slot = get_current_slot().await?;
-        println!("Using current slot as end_slot: {}", args.end_slot);
+    // Reject malformed pubkeys before connecting to Kafka, S3 or the RPC node
+    if let Err(invalid) = validate_pubkeys(&args.account, args.allow_zero_pubkey) {
+        let zero = invalid.iter().any(|pubkey| pubkey.problem == PubkeyProblem::Zero);
+        eprintln!("Error: {}", PipelineError::InvalidPubkeys(invalid));
+        if zero {
+            eprintln!("Pass --allow-zero-pubkey to monitor the all-zeros pubkey");
+        }
+        std::process::exit(1);
     }
     
-    println!("Fetching account info for: {}", args.account);
-    println!("Start slot: {}, End slot: {}", args.start_slot, args.end_slot);
-    
-    // Validate slots
-    if args.end_slot <= args.start_slot {
-        eprintln!("Error: end_slot must be greater than start_slot");
+    let vkey_registry = match (&args.expected_vkey_hash, &args.vkey_registry) {
+        (Some(hash), _) => Some(VkeyRegistry::current(hash)),
+        (None, Some(path)) => Some(VkeyRegistry::load(path)),
+        (None, None) => None,
+    }
+    .transpose()
+    .unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
+    });
+    
+    // The ELF is embedded, so a rebuilt program shows up as a key other than the pinned one before any proving
+    let keys = args.prove.then(ProgramKeys::setup);
+    if let Some(keys) = &keys {
+        let vk_hash = keys.vkey_hash();
+        match check_startup_vkey(&VkeyRegistry::pinned(), &vk_hash, args.strict_vkey) {
+            Ok(None) => println!("Program vkey hash {} matches the pinned one", format_vkey_hash(&vk_hash)),
+            Ok(Some(warning)) => eprintln!("Warning: {}", warning),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
     
-    // Fetch account info
-    let (account_info, actual_slot) = fetch_account_info(&args.account, Some(args.end_slot)).await?;
-    println!("Fetched account info at slot: {}", actual_slot);
-    
-    // Use the actual slot if it's different from requested
-    let effective_end_slot = if actual_slot > args.end_slot {
-        println!("Note: Using actual slot {} as end_slot (was {})", actual_slot, args.end_slot);
-        actual_slot
-    } else {
-        args.end_slot
-    };
-    
-    // Decode account data
-    let account_data = if !account_info.data.is_empty() {
-        general_purpose::STANDARD.decode(&account_info.data[0])?
+    // Validate the Kafka configuration up front so a bad setup fails before proving
+    let kafka_config = if args.prove && args.sink == SinkKind::Kafka {
+        match build_kafka_config(&args).and_then(|config| config.validate().map(|_| config)) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
     } else {
-        Vec::new()
+        None
     };
     
-    // Convert account pubkey
-    let account_pubkey = base58_to_bytes32(&args.account)?;
-    
-    // Convert owner pubkey
-    let owner_bytes = base58_to_bytes32(&account_info.owner)?;
-    
-    // Calculate account data hash
-    let account_data_hash = sha256_hash(&account_data);
-    
-    // Get epoch for the actual slot
-    let epoch = get_epoch_for_slot(effective_end_slot);
+    if let (Some(kafka_config), false) = (&kafka_config, args.skip_preflight) {
+        if let Err(e) = preflight(&args, kafka_config).await {
+            eprintln!("Error: Kafka pre-flight check failed: {}", e);
+            std::process::exit(1);
+        }
+    }
     
-    // Create account state commitment with actual slot data
-    let account_state = AccountStateCommitment {
-        account_pubkey,
-        last_change_slot: effective_end_slot,
-        account_data_hash,
-        lamports: account_info.lamports,
-        owner: owner_bytes,
-        executable: account_info.executable,
-        rent_epoch: account_info.rent_epoch,
-        data: account_data,
+    let archive = match (&args.archive_s3, args.prove) {
+        (Some(location), true) => match connect_archive(&args, location).await {
+            Ok(archive) => Some(archive),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => None,
     };
     
-    // Create dummy bank hashes
-    let original_bank_hash = sha256_from_u64(args.start_slot);
-    let last_bank_hash = sha256_from_u64(effective_end_slot);
-    
-    // Create prover input with effective end slot
-    let input = ProverInput {
-        start_slot: args.start_slot,
-        end_slot: effective_end_slot,
-        epoch,
-        original_bank_hash,
-        last_bank_hash,
-        monitored_accounts_state: vec![account_state],
+    let assembly_started = Instant::now();
+    let input = match &args.input {
+        Some(path) => {
+            let input = load_input(path)?;
+            if let Err(e) = check_input_size(&input, &input_limits(&args)) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            println!(
+                "Loaded prover input from {}: slots {}..{}, {} account(s)",
+                path.display(),
+                input.start_slot,
+                input.end_slot,
+                input.monitored_accounts_state.len()
+            );
+            input
+        }
+        None => match assemble_input(&input_config(&args)).await {
+            Ok(input) => input,
+            Err(PipelineError::InvalidRange { .. }) => {
+                eprintln!("Error: end_slot must be greater than start_slot");
+                std::process::exit(1);
+            }
+            Err(e @ (PipelineError::Accounts { .. } | PipelineError::InputTooLarge { .. })) => {
+                eprintln!("Error: {}", e);
+                if let PipelineError::Accounts { failures, .. } = &e {
+                    if failures.iter().any(|(_, e)| matches!(e, PipelineError::AccountNotFound(_))) {
+                        eprintln!("Pass --allow-missing-accounts to commit accounts that do not exist as closed");
+                    }
+                }
+                std::process::exit(1);
+            }
+            Err(e) => return Err(e.into()),
+        },
     };
-    
-    // Setup prover client
-    let client = ProverClient::from_env();
-    
-    // Prepare input
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&input);
+    // Time spent fetching the accounts, None when the input was loaded from a file
+    let assemble_seconds = args.input.is_none().then(|| assembly_started.elapsed().as_secs_f64());
+    if let Some(seconds) = assemble_seconds {
+        println!("Assembled input for {} account(s) in {:.2}s", input.monitored_accounts_state.len(), seconds);
+    }
+    let missing = missing_accounts(&input);
+    if !missing.is_empty() {
+        println!("Missing account(s), committed as closed: {}", missing.join(", "));
+    }
+    let (start_slot, effective_end_slot) = (input.start_slot, input.end_slot);
+    let raw_data = if args.commit_raw_data { RawData::Commit } else { RawData::Omit };
+    let consistency = if args.unsafe_skip_consistency_check {
+        eprintln!("Warning: not checking the committed public values against the input");
+        Consistency::UnsafeSkip
+    } else {
+        Consistency::Check
+    };
+    let raw_data_len: usize = input.monitored_accounts_state.iter().map(|account| account.data.len()).sum();
     
     if args.execute {
         // Execute only
-        let (output, report) = client.execute(PROVER_ELF, &stdin).run().unwrap();
+        // Inputs loaded with --input can be meant to fail, so report the program's error instead of panicking
+        let execution = match execute(&input, raw_data, consistency) {
+            Ok(execution) => execution,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
         println!("Program executed successfully.");
         
-        // Deserialize output
-        let commitments: PublicCommitments = bincode::deserialize(&output.to_vec()).unwrap();
-        println!("Commitments: {:?}", commitments);
-        println!("Number of cycles: {}", report.total_instruction_count());
+        let mut summary = Section::new("Execution summary");
+        let public_values = format!("{} bytes", execution.public_values_len);
+        summary.field(0, "Cycles", execution.cycles).field(0, "Public Values", public_values);
+        if !missing.is_empty() {
+            summary.field(0, "Missing Accounts", missing.join(", "));
+        }
+        
+        // Measure what leaving the raw account data out saves
+        if raw_data == RawData::Omit && raw_data_len > 0 {
+            match execute(&input, RawData::Commit, consistency) {
+                Ok(committed) => {
+                    let saved_cycles = committed.cycles.saturating_sub(execution.cycles);
+                    let saved_bytes = committed.public_values_len.saturating_sub(execution.public_values_len);
+                    let raw = format!("{} bytes, sent in the envelope metadata", raw_data_len);
+                    let with_raw = format!("{} cycles, {} bytes", committed.cycles, committed.public_values_len);
+                    let saved = format!(
+                        "{} cycles ({:.1}%), {} bytes ({:.1}%)",
+                        saved_cycles,
+                        saved_cycles as f64 * 100.0 / committed.cycles.max(1) as f64,
+                        saved_bytes,
+                        saved_bytes as f64 * 100.0 / committed.public_values_len.max(1) as f64
+                    );
+                    summary
+                        .field(0, "Raw Account Data", raw)
+                        .field(0, "Committing It", with_raw)
+                        .field(0, "Saved", saved);
+                }
+                Err(e) => eprintln!("Warning: could not execute with the raw data committed for comparison: {}", e),
+            }
+        }
+        if args.profile_guest {
+            summary.blank().heading(0, "Cycles by Phase:");
+            execution.profile.rows(&mut summary, 1);
+        }
+        summary.blank().heading(0, "Public Commitments:");
+        commitment_rows(&mut summary, &execution.commitments, 1);
+        println!("{}", summary.render(presenter));
     } else {
-        // Generate proof
-        println!("Setting up proving keys...");
-        let (pk, vk) = client.setup(PROVER_ELF);
+        // Groth16 for on-chain verification (default), or compressed only (faster but not verifiable on-chain)
+        let mode = if args.compressed_only { ProofMode::Compressed } else { ProofMode::Groth16 };
+        let started = Instant::now();
+        let keys = keys.as_ref().expect("the keys are set up when proving");
+        let artifacts = prove_with(keys, &input, mode, raw_data, consistency)?;
+        let prove_seconds = started.elapsed().as_secs_f64();
+        if args.profile_guest {
+            match &artifacts.profile {
+                Some(profile) => {
+                    let mut section = Section::new("Cycles by phase");
+                    profile.rows(&mut section, 0);
+                    println!("{}", section.render(presenter));
+                }
+                None => eprintln!("Warning: compressed proofs skip the separate execution; use --execute to profile"),
+            }
+        }
+        
+        // A rebuilt program has a new key; its proofs would not verify where the old one is configured
+        if let Some(registry) = &vkey_registry {
+            let vk_hash = artifacts.vk.bytes32_raw();
+            let refusal = match registry.check(STATEMENT_VERSION, &vk_hash) {
+                VkeyCheck::Match => None,
+                VkeyCheck::Mismatch { expected } => Some(format!("expected {}", format_vkey_hash(&expected))),
+                VkeyCheck::UnknownVersion => Some("the registry has no key for this statement version".to_string()),
+            };
+            if let Some(reason) = refusal {
+                eprintln!(
+                    "Error: the proof verifies under vkey hash {} for statement version {}, but {}; not publishing it",
+                    format_vkey_hash(&vk_hash),
+                    STATEMENT_VERSION,
+                    reason
+                );
+                std::process::exit(1);
+            }
+            println!("Program vkey hash matches the expected one for statement version {}", STATEMENT_VERSION);
+        }
         
         // Save verification key to file
-        let vkey_json = serde_json::to_string_pretty(&vk).expect("Failed to serialize verification key");
-        fs::write("vkey.json", &vkey_json).expect("Failed to write vkey.json");
-        println!("Verification key saved to vkey.json ({} bytes)", vkey_json.len());
+        let vkey_bytes = write_json(Path::new("vkey.json"), &artifacts.vk, false).expect("Failed to write vkey.json");
+        println!("Verification key saved to vkey.json ({} bytes)", vkey_bytes);
+        println!("Program vkey hash: {}", artifacts.vk.bytes32());
         
-        if args.compressed_only {
-            // Generate compressed proof only (faster but not verifiable on-chain)
-            println!("Generating compressed proof...");
-            let proof = client
-                .prove(&pk, &stdin)
-                .compressed()
-                .run()
-                .expect("failed to generate compressed proof");
-            
-            println!("Successfully generated compressed proof!");
-            
-            // Verify the compressed proof
-            client.verify(&proof, &vk).expect("failed to verify proof");
-            println!("Successfully verified compressed proof!");
-            
-            // Serialize the proof to JSON
-            let proof_json = serde_json::to_string_pretty(&proof).expect("Failed to serialize proof");
-            println!("Proof size (JSON): {} bytes", proof_json.len());
-            
-            // Save proof to file
-            fs::write("last_proof.json", &proof_json).expect("Failed to write last_proof.json");
-            println!("Proof saved to last_proof.json");
-            
-            // Create ZkProof structure for Kafka using weaver types
-            let proof_bytes = bincode::serialize(&proof).expect("Failed to serialize compressed proof");
-            let vk_hash = sha256_hash(&bincode::serialize(&vk).unwrap_or_default());
-            let vk_bytes: [u8; 32] = vk_hash.try_into().unwrap_or([0u8; 32]);
-            
-            let sp1_proof = SP1Proof {
-                version: 1,
-                proof: proof_bytes,
-                public_value: proof.public_values.to_vec(),
-                verification_key: vk_bytes,
-            };
-            
-            let zk_proof = ZkProof {
-                identifier: format!("solana-stub-{}-{}", args.start_slot, effective_end_slot),
-                proof_kind: ProofKind::SolanaConsensusProof,
-                proof_data: ProofData::SP1(sp1_proof),
-            };
-            
-            // Save full ZkProof structure to file as well
-            let zk_proof_json = serde_json::to_string_pretty(&zk_proof).expect("Failed to serialize ZkProof");
-            fs::write("last_kafka_message.json", &zk_proof_json).expect("Failed to write last_kafka_message.json");
-            println!("Full Kafka message saved to last_kafka_message.json");
-            
-            // Configure Kafka
-            let kafka_config = KafkaConfig {
-                use_tls: !args.no_kafka_tls && args.kafka_tls,
-                ca_cert_path: Some(args.kafka_ca_cert.clone()),
-                client_cert_path: Some(args.kafka_client_cert.clone()),
-                client_key_path: Some(args.kafka_client_key.clone()),
-                broker: args.kafka_broker.clone(),
-            };
-            
-            // Publish to Kafka as JSON
-            println!("Publishing compressed proof to Kafka...");
-            let json_value = serde_json::to_value(&zk_proof).expect("Failed to convert to JSON value");
-            publish_json_to_kafka_with_config(json_value, &kafka_config).await?;
-            println!("Compressed proof successfully published to Kafka!");
-        } else {
-            // Generate Groth16 proof for on-chain verification (default)
-            println!("Generating Groth16 proof...");
-            let groth16_proof = client
-                .prove(&pk, &stdin)
-                .groth16()
-                .run()
-                .expect("failed to generate Groth16 proof");
-            
-            println!("Successfully generated Groth16 proof!");
-            
-            // Serialize the Groth16 proof to JSON using native serde
-            let proof_json = serde_json::to_string_pretty(&groth16_proof).expect("Failed to serialize Groth16 proof");
-            println!("Groth16 proof size (JSON): {} bytes", proof_json.len());
-            
-            // Save proof to file
-            fs::write("last_proof.json", &proof_json).expect("Failed to write last_proof.json");
-            println!("Groth16 proof saved to last_proof.json");
-            
-            // Create ZkProof structure for Kafka using weaver types
-            let proof_bytes = bincode::serialize(&groth16_proof).expect("Failed to serialize Groth16 proof");
-            let vk_hash = sha256_hash(&bincode::serialize(&vk).unwrap_or_default());
-            let vk_bytes: [u8; 32] = vk_hash.try_into().unwrap_or([0u8; 32]);
-            
-            // Extract public values - for Groth16, we need to get them from the original output
-            let (output, _) = client.execute(PROVER_ELF, &stdin).run().unwrap();
-            let commitments: PublicCommitments = bincode::deserialize(&output.to_vec()).unwrap();
-            let public_values = bincode::serialize(&commitments).unwrap_or_default();
-            
-            let sp1_proof = SP1Proof {
-                version: 2,  // Version 2 for Groth16
-                proof: proof_bytes,
-                public_value: public_values,
-                verification_key: vk_bytes,
-            };
-            
-            let zk_proof = ZkProof {
-                identifier: format!("solana-stub-{}-{}", args.start_slot, effective_end_slot),
-                proof_kind: ProofKind::SolanaConsensusProof,
-                proof_data: ProofData::SP1(sp1_proof),
-            };
-            
-            // Save full ZkProof structure to file as well
-            let zk_proof_json = serde_json::to_string_pretty(&zk_proof).expect("Failed to serialize ZkProof");
-            fs::write("last_kafka_message.json", &zk_proof_json).expect("Failed to write last_kafka_message.json");
-            println!("Full Kafka message saved to last_kafka_message.json");
-            
-            // Configure Kafka
-            let kafka_config = KafkaConfig {
-                use_tls: !args.no_kafka_tls && args.kafka_tls,
-                ca_cert_path: Some(args.kafka_ca_cert.clone()),
-                client_cert_path: Some(args.kafka_client_cert.clone()),
-                client_key_path: Some(args.kafka_client_key.clone()),
-                broker: args.kafka_broker.clone(),
+        // Stream the proof to file; compressed proofs are too large to build as a string first
+        let proof_path = artifact_file_name("last_proof.json", args.gzip_artifacts);
+        let proof_bytes = write_json(Path::new(&proof_path), &artifacts.proof, args.gzip_artifacts)
+            .unwrap_or_else(|e| panic!("Failed to write {}: {}", proof_path, e));
+        println!("Proof size (JSON): {} bytes", proof_bytes);
+        println!("Proof saved to {}", proof_path);
+        
+        // Save full ZkProof structure to file as well
+        let message_path = artifact_file_name("last_kafka_message.json", args.gzip_artifacts);
+        let metadata = (!artifacts.account_data.is_empty()).then(|| account_data::metadata(&artifacts.account_data));
+        let written = match &metadata {
+            Some(metadata) => {
+                let envelope = EnvelopeWithMetadata { proof: &artifacts.zk_proof, metadata };
+                write_json(Path::new(&message_path), &envelope, args.gzip_artifacts)
+            }
+            None => write_json(Path::new(&message_path), &artifacts.zk_proof, args.gzip_artifacts),
+        };
+        written.unwrap_or_else(|e| panic!("Failed to write {}: {}", message_path, e));
+        println!("Full Kafka message saved to {}", message_path);
+        if !artifacts.account_data.is_empty() {
+            println!(
+                "Raw account data ({} bytes) left out of the public values, sent in the envelope metadata",
+                raw_data_len
+            );
+        }
+        
+        // Publish to the selected sink
+        let published =
+            deliver_proof(&args, kafka_config.as_ref(), &artifacts, start_slot, effective_end_slot).await?;
+        if published {
+            let label = if args.compressed_only { "Compressed" } else { "Groth16" };
+            println!("{} proof successfully published to the {} sink!", label, args.sink);
+        }
+        
+        // Archive the proof, its envelope and the run's metrics
+        if let Some(archive) = &archive {
+            let metrics = ProofMetrics {
+                identifier: artifacts.zk_proof.identifier.clone(),
+                start_slot,
+                end_slot: effective_end_slot,
+                proof_mode: mode,
+                assemble_seconds,
+                missing_accounts: missing,
+                prove_seconds,
+                proof_bytes: proof_bytes as usize,
+                guest_profile: artifacts.profile.clone(),
+                generated_at: chrono::Utc::now().to_rfc3339(),
             };
-            
-            // Publish to Kafka as JSON
-            println!("Publishing Groth16 proof to Kafka...");
-            let json_value = serde_json::to_value(&zk_proof).expect("Failed to convert to JSON value");
-            publish_json_to_kafka_with_config(json_value, &kafka_config).await?;
-            println!("Groth16 proof successfully published to Kafka!");
+            let receipt = archive.archive(&artifacts.zk_proof, Some(&metrics)).await?;
+            println!("Proof archived to {}", receipt);
         }
     }
     
@@ -0,0 +1,219 @@
+//! Long-lived prover driven by account changes
+//!
+//! Watches the accounts of a TOML configuration, decides when each needs a new
+//! proof, proves and publishes it through the pipeline library, and records the
+//! result in a crash-safe state file. Status is served over HTTP when
+//! `status_addr` is configured.
+
+use clap::Parser;
+use solana_stub_prover_script::account_data::RawData;
+use solana_stub_prover_script::account_watch::{watch_accounts, WatchEvent};
+use solana_stub_prover_script::kafka::{KafkaConfig, ProofPublisher};
+use solana_stub_prover_script::metrics::{self, HttpResponse, Routes};
+use solana_stub_prover_script::pipeline::{assemble_input, prove, publish, Consistency, InputConfig};
+use solana_stub_prover_script::presenter::{ColorChoice, OutputStyle, Presenter};
+use solana_stub_prover_script::proof_sink::{DirSink, ProofSink};
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::publish_queue::{PublishQueue, QueueFullPolicy};
+use solana_stub_prover_script::scheduler::{ScheduledProof, Scheduler, SchedulerConfig, SchedulerState};
+use solana_stub_prover_script::shutdown::spawn_signal_handler;
+use solana_stub_prover_script::solana::SolanaRpcClient;
+use solana_stub_prover_script::{errln, outln};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Prove watched accounts whenever their policies call for it", long_about = None)]
+struct Args {
+    /// Scheduler configuration (TOML)
+    config: PathBuf,
+    
+    /// Output style: pretty (emoji and boxes) or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// A finished proof with its actual end slot and identifier, or why it failed
+type ProofOutcome = (ScheduledProof, Result<(u64, String), String>);
+
+fn lock(scheduler: &Mutex<Scheduler>) -> MutexGuard<'_, Scheduler> {
+    scheduler.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// How long the publish queue may take to drain on shutdown
+const QUEUE_CLOSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Directory sink, or a publish queue in front of a Kafka publisher from `KAFKA_*` environment variables
+///
+/// Each running proof publishes once, so the queue holds as many proofs as can run at the same time.
+fn build_sink(
+    config: &SchedulerConfig,
+) -> Result<(Arc<dyn ProofSink>, Option<Arc<PublishQueue>>), Box<dyn std::error::Error>> {
+    match &config.sink_dir {
+        Some(dir) => Ok((Arc::new(DirSink::new(dir)?), None)),
+        None => {
+            let kafka_config = KafkaConfig::from_env()?;
+            kafka_config.validate()?;
+            let publisher = ProofPublisher::new(&kafka_config)?;
+            let queue = Arc::new(publisher.into_queue(config.max_concurrent_proofs, QueueFullPolicy::Block));
+            let sink: Arc<dyn ProofSink> = queue.clone();
+            Ok((sink, Some(queue)))
+        }
+    }
+}
+
+/// `/healthz` and `/status` of the scheduler
+fn routes(scheduler: Arc<Mutex<Scheduler>>) -> Routes {
+    Arc::new(move |path| match path {
+        "/healthz" => Some(HttpResponse { status: "200 OK", content_type: "text/plain", body: "ok\n".to_string() }),
+        "/status" => Some(match lock(&scheduler).status_json() {
+            Ok(body) => HttpResponse { status: "200 OK", content_type: "application/json", body },
+            Err(e) => HttpResponse {
+                status: "500 Internal Server Error",
+                content_type: "text/plain",
+                body: format!("{}\n", e),
+            },
+        }),
+        _ => None,
+    })
+}
+
+/// Fetch, prove and publish one scheduled proof, returning its end slot and identifier
+async fn run_proof(
+    proof: &ScheduledProof,
+    rpc_url: String,
+    mode: ProofMode,
+    sink: Arc<dyn ProofSink>,
+) -> Result<(u64, String), String> {
+    let input_config = InputConfig::new(&rpc_url, &proof.account, proof.start_slot, Some(proof.end_slot));
+    let input = assemble_input(&input_config).await.map_err(|e| e.to_string())?;
+    let end_slot = input.end_slot;
+    // Proving is CPU-bound and takes minutes
+    let artifacts = tokio::task::spawn_blocking(move || prove(&input, mode, RawData::Omit, Consistency::Check))
+        .await
+        .map_err(|e| format!("Proving task failed: {}", e))?
+        .map_err(|e| e.to_string())?;
+    let receipt = publish(&artifacts, sink.as_ref()).await.map_err(|e| e.to_string())?;
+    outln!("📤 {} published to {}", artifacts.zk_proof.identifier, receipt);
+    Ok((end_slot, artifacts.zk_proof.identifier))
+}
+
+/// Start every proof that is due
+fn start_due(
+    scheduler: &Mutex<Scheduler>,
+    config: &SchedulerConfig,
+    sink: &Arc<dyn ProofSink>,
+    done: &mpsc::UnboundedSender<ProofOutcome>,
+) {
+    let due = lock(scheduler).due();
+    for proof in due {
+        outln!(
+            "⏳ Proving {} slots {}..{} ({})",
+            proof.account,
+            proof.start_slot,
+            proof.end_slot,
+            proof.trigger
+        );
+        let (rpc_url, mode, sink, done) = (config.rpc_url.clone(), config.proof_mode, Arc::clone(sink), done.clone());
+        tokio::spawn(async move {
+            let result = run_proof(&proof, rpc_url, mode, sink).await;
+            let _ = done.send((proof, result));
+        });
+    }
+}
+
+/// Record a finished proof and persist the state
+fn finish(scheduler: &Mutex<Scheduler>, config: &SchedulerConfig, (proof, result): ProofOutcome) {
+    let mut scheduler = lock(scheduler);
+    match result {
+        Ok((end_slot, identifier)) => {
+            outln!("✅ {} proven up to slot {}", proof.account, end_slot);
+            scheduler.complete(&proof.account, end_slot, &identifier);
+        }
+        Err(e) => {
+            errln!("❌ Proof of {} slots {}..{} failed: {}", proof.account, proof.start_slot, proof.end_slot, e);
+            scheduler.fail(&proof.account);
+        }
+    }
+    if let Err(e) = scheduler.state().save(&config.state_file) {
+        errln!("⚠️  Failed to save {}: {}", config.state_file.display(), e);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    sp1_sdk::utils::setup_logger();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    Presenter::new(args.style, args.color).install();
+    
+    let config = match SchedulerConfig::load(&args.config) {
+        Ok(config) => config,
+        Err(e) => {
+            errln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let (sink, queue) = build_sink(&config)?;
+    let rpc = SolanaRpcClient::new(&config.rpc_url);
+    let slot = rpc.get_current_slot().await?;
+    let state = SchedulerState::load(&config.state_file)?;
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(&config, state, slot)));
+    lock(&scheduler).state().save(&config.state_file)?;
+    outln!("👀 Watching {} account(s) from slot {}", config.accounts.len(), slot);
+    
+    if let Some(addr) = config.status_addr {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Failed to bind status address {}: {}", addr, e))?;
+        tokio::spawn(metrics::serve_routes(listener, true, Some(routes(Arc::clone(&scheduler)))));
+        outln!("🩺 Status: http://{}/status", addr);
+    }
+    
+    let poll_interval = Duration::from_secs(config.poll_interval_secs);
+    let (event_sender, mut events) = mpsc::channel(1024);
+    let accounts = config.accounts.iter().map(|policy| policy.pubkey.clone()).collect();
+    tokio::spawn(watch_accounts(config.ws_url(), rpc.clone(), accounts, poll_interval, event_sender));
+    
+    let (done_sender, mut done) = mpsc::unbounded_channel();
+    let mut shutdown = spawn_signal_handler();
+    let mut tick = tokio::time::interval(poll_interval);
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            Some(WatchEvent::Changed { account, slot }) = events.recv() => {
+                if lock(&scheduler).observe_change(&account, slot) {
+                    outln!("🔔 {} changed at slot {}", account, slot);
+                }
+            }
+            _ = tick.tick() => {
+                match rpc.get_current_slot().await {
+                    Ok(slot) => lock(&scheduler).advance(slot),
+                    Err(e) => errln!("⚠️  Failed to get the current slot: {}", e),
+                }
+            }
+            Some(outcome) = done.recv() => finish(&scheduler, &config, outcome),
+        }
+        start_due(&scheduler, &config, &sink, &done_sender);
+    }
+    
+    // Let running proofs finish so their results are recorded
+    while lock(&scheduler).running().next().is_some() {
+        match done.recv().await {
+            Some(outcome) => finish(&scheduler, &config, outcome),
+            None => break,
+        }
+    }
+    lock(&scheduler).state().save(&config.state_file)?;
+    if let Some(queue) = queue {
+        let report = queue.close(QUEUE_CLOSE_TIMEOUT).await?;
+        outln!("📤 {} proof(s) published, {} spooled, {} failed", report.published, report.spooled, report.failed);
+    }
+    Ok(())
+}
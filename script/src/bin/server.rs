@@ -0,0 +1,302 @@
+//! HTTP API serving stored proofs and running prove jobs
+//!
+//! Proofs are read from the SQLite database written by `consumer --sqlite`.
+//! Prove jobs run one at a time with the prover binary. Every request needs
+//! `Authorization: Bearer <token>`, and every error is a JSON object with an
+//! `error` field.
+
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_stub_prover_script::proof_store::{ProofStore, StoredProof};
+use solana_stub_prover_script::prove_jobs::{
+    Job, JobQueue, ProveRequest, ProverCommand, SubmitError, DEFAULT_MAX_QUEUED_JOBS,
+};
+use solana_stub_prover_script::shutdown::spawn_signal_handler;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "HTTP API for stored proofs and prove-on-demand", long_about = None)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, env = "SERVER_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    
+    /// SQLite database written by `consumer --sqlite`
+    #[arg(long, env = "SERVER_SQLITE")]
+    sqlite: PathBuf,
+    
+    /// Bearer token clients must send in the Authorization header
+    #[arg(long, env = "SERVER_API_TOKEN", hide_env_values = true)]
+    api_token: String,
+    
+    /// Prover binary that runs prove jobs (default: solana-stub-prover next to this binary)
+    #[arg(long)]
+    prover_bin: Option<PathBuf>,
+    
+    /// Argument passed to the prover before each job's own, e.g. --prover-arg=--no-kafka-tls (repeatable)
+    #[arg(long = "prover-arg", allow_hyphen_values = true)]
+    prover_args: Vec<String>,
+    
+    /// Jobs waiting to run before POST /prove is refused
+    #[arg(long, default_value_t = DEFAULT_MAX_QUEUED_JOBS)]
+    max_queued_jobs: usize,
+}
+
+struct AppState {
+    store: ProofStore,
+    jobs: JobQueue,
+    /// SHA-256 of the API token, so comparing tokens takes the same time whatever they share
+    token_digest: [u8; 32],
+}
+
+/// An error response, `{"error": "..."}`
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+    
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+    
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+    
+    fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorBody { error: self.message })).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<SubmitError> for ApiError {
+    fn from(e: SubmitError) -> Self {
+        let status = match e {
+            SubmitError::Invalid(_) => StatusCode::BAD_REQUEST,
+            SubmitError::QueueFull | SubmitError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+/// Run a store query off the async runtime
+async fn query<T, F>(state: &Arc<AppState>, run: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&ProofStore) -> rusqlite::Result<T> + Send + 'static,
+{
+    let state = Arc::clone(state);
+    tokio::task::spawn_blocking(move || run(&state.store))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::internal(format!("database error: {}", e)))
+}
+
+#[derive(Deserialize)]
+struct LatestQuery {
+    account: Option<String>,
+}
+
+async fn latest_proof(
+    State(state): State<Arc<AppState>>,
+    query_params: Result<Query<LatestQuery>, QueryRejection>,
+) -> Result<Json<StoredProof>, ApiError> {
+    let Query(LatestQuery { account }) = query_params?;
+    let description = account.as_ref().map_or_else(String::new, |account| format!(" for account {}", account));
+    query(&state, move |store| store.latest(account.as_deref()))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("no proof found{}", description)))
+}
+
+async fn proof(
+    State(state): State<Arc<AppState>>,
+    identifier: Result<Path<String>, PathRejection>,
+) -> Result<Json<StoredProof>, ApiError> {
+    let Path(identifier) = identifier?;
+    let missing = format!("no proof with identifier {}", identifier);
+    query(&state, move |store| store.proof(&identifier))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(missing))
+}
+
+#[derive(Deserialize)]
+struct RangeQuery {
+    start_slot: u64,
+    end_slot: u64,
+}
+
+#[derive(Serialize)]
+struct Commitments {
+    start_slot: u64,
+    end_slot: u64,
+    proofs: Vec<StoredProof>,
+}
+
+async fn commitments(
+    State(state): State<Arc<AppState>>,
+    query_params: Result<Query<RangeQuery>, QueryRejection>,
+) -> Result<Json<Commitments>, ApiError> {
+    let Query(RangeQuery { start_slot, end_slot }) = query_params?;
+    if end_slot < start_slot {
+        return Err(ApiError::bad_request("end_slot must not be less than start_slot"));
+    }
+    let proofs = query(&state, move |store| store.in_range(start_slot, end_slot)).await?;
+    Ok(Json(Commitments { start_slot, end_slot, proofs }))
+}
+
+async fn prove(
+    State(state): State<Arc<AppState>>,
+    request: Result<Json<ProveRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(request) = request?;
+    let job = state.jobs.submit(request)?;
+    println!(
+        "📥 Queued job {}: {} slots {}..{} ({})",
+        job.id, job.request.account, job.request.start_slot, job.request.end_slot, job.request.mode
+    );
+    let location = format!("/jobs/{}", job.id);
+    Ok((StatusCode::ACCEPTED, [(header::LOCATION, location)], Json(job)).into_response())
+}
+
+async fn job(State(state): State<Arc<AppState>>, id: Result<Path<u64>, PathRejection>) -> Result<Response, ApiError> {
+    let Path(id) = id?;
+    match state.jobs.get(id) {
+        Some(job) => Ok(Json(job).into_response()),
+        None => Err(ApiError::not_found(format!("no job with id {}", id))),
+    }
+}
+
+async fn jobs(State(state): State<Arc<AppState>>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
+
+async fn not_found() -> ApiError {
+    ApiError::not_found("no such endpoint")
+}
+
+/// Reject requests without the API token
+async fn require_token(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token.map(|token| <[u8; 32]>::from(Sha256::digest(token.trim()))) {
+        Some(digest) if digest == state.token_digest => next.run(request).await,
+        Some(_) => ApiError::new(StatusCode::UNAUTHORIZED, "invalid bearer token").into_response(),
+        None => ApiError::new(StatusCode::UNAUTHORIZED, "missing bearer token").into_response(),
+    }
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/proofs/latest", get(latest_proof))
+        .route("/proofs/:identifier", get(proof))
+        .route("/commitments", get(commitments))
+        .route("/prove", post(prove))
+        .route("/jobs", get(jobs))
+        .route("/jobs/:id", get(job))
+        .fallback(not_found)
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), require_token))
+        .with_state(state)
+}
+
+/// The prover binary built alongside this one
+fn default_prover_bin() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let dir = exe.parent().ok_or("the server binary has no parent directory")?;
+    Ok(dir.join(format!("solana-stub-prover{}", std::env::consts::EXE_SUFFIX)))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    
+    let token = args.api_token.trim();
+    if token.is_empty() {
+        eprintln!("❌ The API token must not be empty");
+        std::process::exit(1);
+    }
+    let store = match ProofStore::open(&args.sqlite) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ Failed to open {}: {}", args.sqlite.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let prover = ProverCommand {
+        binary: match args.prover_bin.clone() {
+            Some(path) => path,
+            None => default_prover_bin()?,
+        },
+        args: args.prover_args.clone(),
+    };
+    println!("🔧 Prove jobs run {}", prover.binary.display());
+    
+    let jobs = JobQueue::new(args.max_queued_jobs, move |request| {
+        let prover = prover.clone();
+        async move { prover.run(request).await }
+    });
+    let state = Arc::new(AppState { store, jobs, token_digest: Sha256::digest(token).into() });
+    
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!("🚀 Serving {} on http://{}", args.sqlite.display(), listener.local_addr()?);
+    
+    let mut shutdown = spawn_signal_handler();
+    axum::serve(listener, router(Arc::clone(&state)))
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        })
+        .await?;
+    
+    // Queued jobs are failed; a running job is allowed to finish
+    state.jobs.shutdown().await;
+    println!("👋 Server stopped");
+    Ok(())
+}
@@ -0,0 +1,478 @@
+//! End-to-end smoke test that needs no network, certificates or GPU
+//!
+//! Serves an account from the mock Solana RPC, assembles the input, assembles
+//! inputs with an account that does not exist, executes the program, reads its
+//! cycles per phase back from the execution report, checks that commitments
+//! which differ from the input are refused, executes it on sorted, reversed and
+//! duplicated account lists, checks the program vkey hash against the one
+//! pinned at build time, builds the envelope with SP1's mock prover, publishes
+//! it to an in-memory sink, decodes the JSON payload with the consumer's
+//! decoder, also in the legacy envelope shape, and compares every field with
+//! what was published. Prints each stage with its timing and exits with 1 as
+//! soon as one fails.
+
+use clap::Parser;
+use solana_stub_prover_lib::phases::{self, ACCOUNTS_HASH};
+use solana_stub_prover_lib::{compute_accounts_hash, ProverInput, PublicCommitments};
+use solana_stub_prover_script::account_data::{self, RawData};
+use solana_stub_prover_script::envelope::{decode_json, EnvelopeShape, EnvelopeWithMetadata};
+use solana_stub_prover_script::fixtures::{Preset, DEFAULT_SEED};
+use solana_stub_prover_script::guest_profile::GuestProfile;
+use solana_stub_prover_script::mock_rpc::{MockAccount, MockSolanaRpc};
+use solana_stub_prover_script::pipeline::{
+    assemble_input, check_consistency, execute, missing_accounts, program_vkey_hash, prove, publish, Consistency,
+    Execution, InputConfig, PipelineError,
+};
+use solana_stub_prover_script::presenter::{ColorChoice, OutputStyle, Presenter, Tone};
+use solana_stub_prover_script::proof_filter::decode_commitments;
+use solana_stub_prover_script::proof_sink::MemorySink;
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::public_values::FieldChange;
+use solana_stub_prover_script::vkey_registry::{
+    check_startup_vkey, expected_vkey_hash, format_vkey_hash, VkeyRegistry,
+};
+use solana_stub_prover_script::{errln, outln};
+use sp1_sdk::HashableKey;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use twine_types::proofs::{ProofData, ZkProof};
+
+/// Token Program, the account served by the mock RPC
+const ACCOUNT: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+/// BPF loader, the account's owner
+const OWNER: &str = "BPFLoader2111111111111111111111111111111111";
+/// Vote Program, an account the mock RPC does not serve
+const MISSING: &str = "Vote111111111111111111111111111111111111111";
+/// Largest share of the cycles allowed outside every phase: the entrypoint's
+/// setup and the public values digest, a fixed cost next to a small input
+const MAX_UNTRACKED_SHARE: f64 = 0.5;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Run the whole pipeline against the mock RPC and mock prover", long_about = None)]
+struct Args {
+    /// First slot of the proven range
+    #[arg(long, default_value_t = 1_000_000)]
+    start_slot: u64,
+    
+    /// Last slot of the proven range
+    #[arg(long, default_value_t = 1_000_100)]
+    end_slot: u64,
+    
+    /// Kind of mock proof to build: groth16 or compressed
+    #[arg(long, default_value = "groth16")]
+    mode: ProofMode,
+    
+    /// Output style: pretty (emoji and boxes) or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// Runs stages in order and prints each outcome
+struct Stages<'a> {
+    presenter: &'a Presenter,
+    passed: usize,
+    total: Duration,
+}
+
+impl Stages<'_> {
+    /// Print a finished stage, returning its value or exiting on failure
+    fn finish<T>(&mut self, name: &str, started: Instant, result: Result<(T, String), String>) -> T {
+        let elapsed = started.elapsed();
+        self.total += elapsed;
+        match result {
+            Ok((value, detail)) => {
+                self.passed += 1;
+                outln!("✅ {:<10} {:>8} ms  {}", name, elapsed.as_millis(), detail);
+                value
+            }
+            Err(reason) => {
+                errln!("❌ {:<10} {:>8} ms  {}", name, elapsed.as_millis(), reason);
+                let summary = format!("Smoke test failed at {} after {} passed stage(s)", name, self.passed);
+                outln!("{}", self.presenter.paint(summary, Tone::Bad));
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Names of the fields that differ between two envelopes
+fn envelope_mismatches(published: &ZkProof, decoded: &ZkProof) -> Vec<&'static str> {
+    let ProofData::SP1(published_proof) = &published.proof_data;
+    let ProofData::SP1(decoded_proof) = &decoded.proof_data;
+    let fields = [
+        ("identifier", published.identifier == decoded.identifier),
+        ("proof_kind", format!("{:?}", published.proof_kind) == format!("{:?}", decoded.proof_kind)),
+        ("version", published_proof.version == decoded_proof.version),
+        ("proof", published_proof.proof == decoded_proof.proof),
+        ("public_value", published_proof.public_value == decoded_proof.public_value),
+        ("verification_key", published_proof.verification_key == decoded_proof.verification_key),
+    ];
+    fields.into_iter().filter(|(_, equal)| !equal).map(|(field, _)| field).collect()
+}
+
+/// `proof` in the legacy JSON envelope shape, with hex-encoded proof fields
+fn legacy_payload(proof: &ZkProof) -> serde_json::Value {
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    serde_json::json!({
+        "identifier": proof.identifier,
+        "kind": format!("{:?}", proof.proof_kind),
+        "proof_data": {
+            "type": "SP1",
+            "version": sp1_proof.version,
+            "proof": hex::encode(&sp1_proof.proof),
+            "public_values": hex::encode(&sp1_proof.public_value),
+            "verification_key": hex::encode(sp1_proof.verification_key),
+        },
+    })
+}
+
+/// Names of the fields that differ between two sets of commitments
+fn commitment_mismatches(expected: &PublicCommitments, actual: &PublicCommitments) -> Vec<String> {
+    let fields = [
+        ("start_slot", expected.start_slot == actual.start_slot),
+        ("end_slot", expected.end_slot == actual.end_slot),
+        ("epoch", expected.epoch == actual.epoch),
+        ("original_bank_hash", expected.original_bank_hash == actual.original_bank_hash),
+        ("last_bank_hash", expected.last_bank_hash == actual.last_bank_hash),
+        ("account_data_hash", expected.account_data_hash == actual.account_data_hash),
+        ("hash_root_valset", expected.hash_root_valset == actual.hash_root_valset),
+        ("total_active_stake", expected.total_active_stake == actual.total_active_stake),
+        ("validator_count", expected.validator_count == actual.validator_count),
+        ("validations_passed", expected.validations_passed == actual.validations_passed),
+        ("aggregated_vkey_digest", expected.aggregated_vkey_digest == actual.aggregated_vkey_digest),
+        ("monitored_accounts_state", expected.monitored_accounts_state.len() == actual.monitored_accounts_state.len()),
+    ];
+    let mut mismatches: Vec<String> =
+        fields.into_iter().filter(|(_, equal)| !equal).map(|(field, _)| field.to_string()).collect();
+    for (index, (expected, actual)) in
+        expected.monitored_accounts_state.iter().zip(&actual.monitored_accounts_state).enumerate()
+    {
+        let fields = [
+            ("account_pubkey", expected.account_pubkey == actual.account_pubkey),
+            ("last_change_slot", expected.last_change_slot == actual.last_change_slot),
+            ("account_data_hash", expected.account_data_hash == actual.account_data_hash),
+            ("lamports", expected.lamports == actual.lamports),
+            ("owner", expected.owner == actual.owner),
+            ("executable", expected.executable == actual.executable),
+            ("rent_epoch", expected.rent_epoch == actual.rent_epoch),
+            ("data_len", expected.data_len == actual.data_len),
+            ("data", expected.data == actual.data),
+        ];
+        mismatches.extend(
+            fields
+                .into_iter()
+                .filter(|(_, equal)| !equal)
+                .map(|(field, _)| format!("monitored_accounts_state[{}].{}", index, field)),
+        );
+    }
+    mismatches
+}
+
+/// Check that the execution reported every phase and account and that the
+/// phases add up to most of the total, then parse trackers missing phases
+fn check_profile(input: &ProverInput, execution: &Execution) -> Result<((), String), String> {
+    let profile = &execution.profile;
+    if !profile.missing_phases().is_empty() {
+        return Err(format!("the program did not report the phase(s) {:?}", profile.missing_phases()));
+    }
+    if profile.accounts.len() != input.monitored_accounts_state.len() {
+        return Err(format!(
+            "{} account(s) reported for {} monitored",
+            profile.accounts.len(),
+            input.monitored_accounts_state.len()
+        ));
+    }
+    if profile.total_cycles != execution.cycles || profile.tracked_cycles() > profile.total_cycles {
+        return Err(format!("{} cycles in phases of {} in total", profile.tracked_cycles(), profile.total_cycles));
+    }
+    let untracked = profile.untracked_cycles() as f64 / profile.total_cycles.max(1) as f64;
+    if untracked > MAX_UNTRACKED_SHARE {
+        return Err(format!("{:.1}% of the cycles are outside every phase", untracked * 100.0));
+    }
+    
+    // A tracker without some phases, as from an aggregation, leaves them out
+    let pubkeys: Vec<[u8; 32]> = input.monitored_accounts_state.iter().map(|account| account.account_pubkey).collect();
+    let mut tracker: HashMap<String, u64> =
+        profile.phases.iter().map(|phase| (phase.phase.clone(), phase.cycles)).collect();
+    tracker.remove(ACCOUNTS_HASH);
+    let partial = GuestProfile::from_tracker(&tracker, profile.total_cycles, &pubkeys);
+    if partial.missing_phases() != [ACCOUNTS_HASH] || partial.phase(ACCOUNTS_HASH).is_some() {
+        return Err(format!("a tracker without {} parsed as missing {:?}", ACCOUNTS_HASH, partial.missing_phases()));
+    }
+    let empty = GuestProfile::from_tracker(&HashMap::new(), profile.total_cycles, &pubkeys);
+    if empty.missing_phases() != phases::ALL || !empty.accounts.is_empty() || empty.tracked_cycles() != 0 {
+        return Err("an empty tracker parsed as reported phases".to_string());
+    }
+    let detail = format!(
+        "{} phases, {} account(s), {:.1}% untracked",
+        profile.phases.len(),
+        profile.accounts.len(),
+        untracked * 100.0
+    );
+    Ok(((), detail))
+}
+
+/// Check the executed commitments against the input the program was given,
+/// then the same commitments with the end slot moved past the input's
+fn check_inconsistent(input: &ProverInput, committed: &PublicCommitments) -> Result<((), String), String> {
+    let mut input = input.clone();
+    account_data::detach(&mut input);
+    check_consistency(&input, committed).map_err(|e| e.to_string())?;
+    let shifted = PublicCommitments { end_slot: committed.end_slot + 1, ..committed.clone() };
+    let changes = match check_consistency(&input, &shifted) {
+        Err(PipelineError::Inconsistent(changes)) => changes,
+        Err(e) => return Err(format!("a shifted end slot failed with: {}", e)),
+        Ok(()) => return Err("a shifted end slot was accepted".to_string()),
+    };
+    let expected = FieldChange::Changed {
+        path: "end_slot".to_string(),
+        left: input.end_slot.to_string(),
+        right: shifted.end_slot.to_string(),
+    };
+    if changes != [expected] {
+        return Err(format!("a shifted end slot was reported as {:?}", changes));
+    }
+    Ok(((), format!("matches the input, shifted end slot refused as {}", changes[0])))
+}
+
+/// Execute the program on the token accounts fixture as generated, sorted by
+/// pubkey, then reversed and with an account listed twice, which it must refuse
+fn check_ordering() -> Result<((), String), String> {
+    let sorted = Preset::TokenAccounts.generate(DEFAULT_SEED);
+    let accounts = &sorted.monitored_accounts_state;
+    let execution = execute(&sorted, RawData::Omit, Consistency::Check).map_err(|e| e.to_string())?;
+    if execution.commitments.account_data_hash != compute_accounts_hash(accounts) {
+        return Err("the program and the host hash the sorted accounts differently".to_string());
+    }
+    let mut reversed = sorted.clone();
+    reversed.monitored_accounts_state.reverse();
+    // The order is part of the hash, which is why the program allows only one
+    if compute_accounts_hash(&reversed.monitored_accounts_state) == execution.commitments.account_data_hash {
+        return Err("the accounts hash does not depend on the order".to_string());
+    }
+    let mut duplicated = sorted.clone();
+    duplicated.monitored_accounts_state.insert(1, accounts[0].clone());
+    for (name, input) in [("reversed", reversed), ("duplicated", duplicated)] {
+        match execute(&input, RawData::Omit, Consistency::Check) {
+            Err(PipelineError::Execution(_)) => {}
+            Err(e) => return Err(format!("{} accounts failed with: {}", name, e)),
+            Ok(_) => return Err(format!("the program accepted {} accounts", name)),
+        }
+    }
+    Ok(((), format!("{} sorted accounts hashed as the host does, reversed and duplicated refused", accounts.len())))
+}
+
+/// Run the prover's startup check on the program vkey hash: against the one
+/// the build pinned, if any, and as built pinning another program's hash
+fn check_pinned_vkey() -> Result<((), String), String> {
+    let vk_hash = program_vkey_hash();
+    let pinned = match check_startup_vkey(&VkeyRegistry::pinned(), &vk_hash, false)? {
+        None => "matches the pinned hash",
+        Some(_) if expected_vkey_hash().is_none() => "none pinned",
+        Some(warning) => return Err(warning),
+    };
+    // As built with EXPECTED_VKEY_HASH set to a key of another program
+    let mut other = vk_hash;
+    other[31] ^= 1;
+    let rebuilt = VkeyRegistry::current(&format_vkey_hash(&other))?;
+    if check_startup_vkey(&rebuilt, &vk_hash, false)?.is_none() {
+        return Err("another pinned hash passes the startup check".to_string());
+    }
+    if check_startup_vkey(&rebuilt, &vk_hash, true).is_ok() {
+        return Err("another pinned hash passes the strict startup check".to_string());
+    }
+    Ok(((), format!("{}, {}, another one refused", format_vkey_hash(&vk_hash), pinned)))
+}
+
+/// Assemble inputs with an account the mock RPC does not serve: rejected by
+/// default, committed as closed when allowed, alone or next to a served one
+async fn check_missing(config: &InputConfig) -> Result<((), String), String> {
+    let rejected = InputConfig { accounts: vec![ACCOUNT.to_string(), MISSING.to_string()], ..config.clone() };
+    let failures = match assemble_input(&rejected).await {
+        Err(PipelineError::Accounts { failures, .. }) => failures,
+        Err(e) => return Err(format!("a missing account failed with: {}", e)),
+        Ok(_) => return Err("a missing account was accepted without allow_missing".to_string()),
+    };
+    if !matches!(failures.as_slice(), [(account, PipelineError::AccountNotFound(_))] if account == MISSING) {
+        return Err(format!("only {} should have failed, got {:?}", MISSING, failures));
+    }
+    for accounts in [vec![ACCOUNT, MISSING], vec![MISSING]] {
+        let allowed = InputConfig {
+            accounts: accounts.iter().map(|account| account.to_string()).collect(),
+            allow_missing: true,
+            ..config.clone()
+        };
+        let input = assemble_input(&allowed).await.map_err(|e| e.to_string())?;
+        let closed = input.monitored_accounts_state.iter().filter(|account| account.is_closed()).count();
+        if input.monitored_accounts_state.len() != accounts.len() || closed != 1 {
+            return Err(format!("{} account(s) assembled as {} closed", accounts.len(), closed));
+        }
+        if missing_accounts(&input) != [MISSING] {
+            return Err(format!("missing accounts reported as {:?}", missing_accounts(&input)));
+        }
+    }
+    Ok(((), "rejected by default, committed as closed alone and mixed".to_string()))
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let presenter = Presenter::new(args.style, args.color).install();
+    // The mock prover skips proof generation but runs everything around it
+    std::env::set_var("SP1_PROVER", "mock");
+    let mut stages = Stages { presenter, passed: 0, total: Duration::ZERO };
+    
+    let started = Instant::now();
+    let data = b"solana-stub-prover smoke test".to_vec();
+    let account = MockAccount {
+        data: data.clone(),
+        lamports: 1_141_440,
+        owner: OWNER.to_string(),
+        executable: true,
+        rent_epoch: u64::MAX,
+    };
+    let result = MockSolanaRpc::start(args.end_slot).await.map_err(|e| format!("Failed to start: {}", e));
+    let rpc = stages.finish(
+        "Mock RPC",
+        started,
+        result.map(|rpc| {
+            rpc.set_account(ACCOUNT, account);
+            let detail = format!("{} serving {} at slot {}", rpc.url(), ACCOUNT, args.end_slot);
+            (rpc, detail)
+        }),
+    );
+    
+    let started = Instant::now();
+    let config = InputConfig::new(rpc.url(), ACCOUNT, args.start_slot, Some(args.end_slot));
+    let result = assemble_input(&config).await.map_err(|e| e.to_string()).and_then(|input| {
+        match input.monitored_accounts_state.first() {
+            Some(state) if state.data == data => {
+                let detail = format!("slots {}..{}, epoch {}", input.start_slot, input.end_slot, input.epoch);
+                Ok((input, detail))
+            }
+            Some(_) => Err("the account data differs from what the mock RPC served".to_string()),
+            None => Err("the input has no monitored account".to_string()),
+        }
+    });
+    let input = stages.finish("Assemble", started, result);
+    
+    let started = Instant::now();
+    stages.finish("Missing", started, check_missing(&config).await);
+    
+    let started = Instant::now();
+    let result = execute(&input, RawData::Omit, Consistency::Check).map_err(|e| e.to_string()).and_then(|execution| {
+        if !execution.commitments.validations_passed {
+            return Err("the program reported failed validations".to_string());
+        }
+        // The program runs without the account data, which must not change the hash
+        if compute_accounts_hash(&input.monitored_accounts_state) != execution.commitments.account_data_hash {
+            return Err("the program and the host hash the accounts differently".to_string());
+        }
+        let detail = format!("{} cycles, validations passed", execution.cycles);
+        Ok((execution, detail))
+    });
+    let execution = stages.finish("Execute", started, result);
+    
+    let started = Instant::now();
+    stages.finish("Profile", started, check_profile(&input, &execution));
+    
+    let started = Instant::now();
+    stages.finish("Consistency", started, check_inconsistent(&input, &execution.commitments));
+    
+    let started = Instant::now();
+    stages.finish("Ordering", started, check_ordering());
+    
+    let started = Instant::now();
+    stages.finish("Vkey", started, check_pinned_vkey());
+    
+    let started = Instant::now();
+    let mode = args.mode;
+    let prove_input = input.clone();
+    let result =
+        match tokio::task::spawn_blocking(move || prove(&prove_input, mode, RawData::Omit, Consistency::Check)).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Proving task failed: {}", e)),
+        };
+    let result = result.and_then(|artifacts| {
+        let ProofData::SP1(sp1_proof) = &artifacts.zk_proof.proof_data;
+        if sp1_proof.verification_key != artifacts.vk.bytes32_raw() {
+            return Err("the envelope does not carry the program vkey hash".to_string());
+        }
+        let detail = format!("{} envelope {}, vkey {}", mode, artifacts.zk_proof.identifier, artifacts.vk.bytes32());
+        Ok((artifacts, detail))
+    });
+    let artifacts = stages.finish("Prove", started, result);
+    
+    let started = Instant::now();
+    let sink = MemorySink::new();
+    let result = publish(&artifacts, &sink).await.map_err(|e| e.to_string()).and_then(|receipt| match sink.len() {
+        1 => Ok(((), format!("published to {}", receipt))),
+        count => Err(format!("the sink holds {} proofs instead of 1", count)),
+    });
+    stages.finish("Publish", started, result);
+    
+    let started = Instant::now();
+    let published = sink.proofs().remove(0);
+    // The JSON payload the Kafka sink publishes, with the account data in the envelope's metadata
+    let payload = match &sink.metadata()[0] {
+        Some(metadata) => serde_json::to_vec(&EnvelopeWithMetadata { proof: &published, metadata }),
+        None => serde_json::to_vec(&published),
+    };
+    let result = payload.map_err(|e| format!("Failed to encode the payload: {}", e)).and_then(|payload| {
+        let decoded = decode_json(&payload).map_err(|e| e.to_string())?;
+        let commitments = decode_commitments(&decoded.proof)
+            .ok_or_else(|| "the public values are not PublicCommitments".to_string())?;
+        let accounts = match decoded.metadata.as_ref().and_then(account_data::from_metadata) {
+            Some(accounts) => accounts.map_err(|e| e.to_string())?,
+            None => Vec::new(),
+        };
+        let detail = format!(
+            "{} byte JSON payload, {} envelope, {} account(s) in the metadata",
+            payload.len(),
+            decoded.kind,
+            accounts.len()
+        );
+        Ok(((decoded.proof, commitments, accounts), detail))
+    });
+    let (decoded, commitments, accounts) = stages.finish("Decode", started, result);
+    
+    // Legacy envelopes must normalize into the same twine_types envelope the producer publishes
+    let started = Instant::now();
+    let payload = legacy_payload(&published).to_string();
+    let result = decode_json(payload.as_bytes()).map_err(|e| e.to_string()).and_then(|legacy| {
+        let mismatches = envelope_mismatches(&published, &legacy.proof);
+        if legacy.shape != EnvelopeShape::Legacy {
+            Err(format!("the legacy payload decoded as a {} envelope", legacy.shape))
+        } else if !mismatches.is_empty() {
+            Err(format!("legacy envelope fields differ: {}", mismatches.join(", ")))
+        } else {
+            Ok(((), format!("{} byte legacy payload decodes to the published envelope", payload.len())))
+        }
+    });
+    stages.finish("Legacy", started, result);
+    
+    let started = Instant::now();
+    let mut mismatches: Vec<String> =
+        envelope_mismatches(&published, &decoded).into_iter().map(|field| format!("envelope {}", field)).collect();
+    mismatches.extend(commitment_mismatches(&execution.commitments, &commitments));
+    // The account data left out of the public values must arrive whole and match the committed hashes
+    if accounts != artifacts.account_data {
+        mismatches.push("account data in the metadata".to_string());
+    }
+    if let Err(e) = account_data::verify(&commitments, &accounts) {
+        mismatches.push(format!("account data ({})", e));
+    }
+    let result = if mismatches.is_empty() {
+        Ok(((), "decoded envelope and commitments equal what was published".to_string()))
+    } else {
+        Err(format!("fields differ: {}", mismatches.join(", ")))
+    };
+    stages.finish("Compare", started, result);
+    
+    let summary = format!("Smoke test passed: {} stages in {} ms", stages.passed, stages.total.as_millis());
+    outln!("{}", presenter.paint(summary, Tone::Good));
+}
@@ -0,0 +1,341 @@
+//! Command-line client of the REST server
+//!
+//! Submits prove jobs and waits for them, and looks up stored proofs and jobs.
+//! The server URL and token come from flags, the environment or a config file
+//! (see `ClientConfig`). Output is a human-readable table unless `--json` is
+//! given. Exit codes: 0 on success, 3 when a job failed, 4 when the proof or
+//! job does not exist, 5 when the server rejected the token, 1 otherwise.
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use solana_stub_prover_script::admin_output::ErrorOutput;
+use solana_stub_prover_script::api_client::{ApiClient, ApiError, ClientConfig, ProofRecord, DEFAULT_SERVER_URL};
+use solana_stub_prover_script::prove_jobs::{Job, JobState, ProofMode, ProveRequest};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_JOB_FAILED: i32 = 3;
+const EXIT_NOT_FOUND: i32 = 4;
+const EXIT_UNAUTHORIZED: i32 = 5;
+
+#[derive(Parser, Debug)]
+#[command(name = "stubctl", about = "Client of the solana-stub-prover REST server")]
+struct Args {
+    /// Server URL (default: the config file's url, or http://127.0.0.1:8080)
+    #[arg(long, env = "STUBCTL_URL", global = true)]
+    url: Option<String>,
+    
+    /// Bearer token (default: the config file's token)
+    #[arg(long, env = "SERVER_API_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+    
+    /// Config file with url and token (default: ~/.config/stubctl/config.toml)
+    #[arg(long, env = "STUBCTL_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    
+    /// Print JSON instead of tables
+    #[arg(long, global = true)]
+    json: bool,
+    
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Submit a prove job and wait for it to finish
+    Prove {
+        /// Base58 pubkey of the account to monitor
+        #[arg(long)]
+        account: String,
+        
+        #[arg(long)]
+        start_slot: u64,
+        
+        #[arg(long)]
+        end_slot: u64,
+        
+        /// groth16 or compressed
+        #[arg(long, default_value = "groth16")]
+        mode: ProofMode,
+        
+        /// Print the queued job and return without waiting
+        #[arg(long)]
+        no_wait: bool,
+        
+        /// Seconds between polls of the job
+        #[arg(long, default_value_t = 2)]
+        poll_interval: u64,
+        
+        /// Give up waiting after this many seconds (the job keeps running)
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Show the latest stored proof
+    Latest {
+        /// Only proofs committing to this account (base58)
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Show a stored proof
+    Get {
+        #[arg(long)]
+        identifier: String,
+    },
+    /// Inspect prove jobs
+    Jobs {
+        #[command(subcommand)]
+        command: JobsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum JobsCommand {
+    /// List every job, oldest first
+    List,
+}
+
+/// Why the command failed, and the exit code it maps to
+struct Failure {
+    code: i32,
+    error: String,
+    detail: String,
+}
+
+impl Failure {
+    fn new(code: i32, error: &str, detail: impl ToString) -> Self {
+        Self { code, error: error.to_string(), detail: detail.to_string() }
+    }
+}
+
+impl From<ApiError> for Failure {
+    fn from(e: ApiError) -> Self {
+        match &e {
+            ApiError::Unauthorized(message) => Failure::new(EXIT_UNAUTHORIZED, "Unauthorized", message),
+            ApiError::NotFound(message) => Failure::new(EXIT_NOT_FOUND, "Not found", message),
+            _ => Failure::new(EXIT_FAILURE, "Request failed", &e),
+        }
+    }
+}
+
+/// Client from the flags, falling back to the config file and then the defaults
+fn client(args: &Args) -> Result<ApiClient, Failure> {
+    let config = match &args.config {
+        Some(path) => ClientConfig::load(path),
+        // A missing default config file is an empty one
+        None => match ClientConfig::default_path() {
+            Some(path) if path.exists() => ClientConfig::load(&path),
+            _ => Ok(ClientConfig::default()),
+        },
+    }
+    .map_err(|e| Failure::new(EXIT_FAILURE, "Invalid configuration", e))?;
+    
+    let url = args.url.clone().or(config.url).unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+    let token = args.token.clone().or(config.token).ok_or_else(|| {
+        Failure::new(EXIT_FAILURE, "No API token", "pass --token, set SERVER_API_TOKEN or add token to the config file")
+    })?;
+    ApiClient::new(&url, &token).map_err(|e| Failure::new(EXIT_FAILURE, "Invalid configuration", e))
+}
+
+async fn run(args: &Args) -> Result<(), Failure> {
+    let client = client(args)?;
+    match &args.command {
+        Command::Prove { account, start_slot, end_slot, mode, no_wait, poll_interval, timeout } => {
+            let request =
+                ProveRequest { account: account.clone(), start_slot: *start_slot, end_slot: *end_slot, mode: *mode };
+            let job = client.submit(&request).await?;
+            if *no_wait {
+                return print_job(args.json, &job);
+            }
+            if !args.json {
+                println!("📥 Submitted job {} ({} {}..{}, {})", job.id, account, start_slot, end_slot, mode);
+            }
+            let interval = Duration::from_secs(*poll_interval);
+            let job = wait(&client, job, args.json, interval, timeout.map(Duration::from_secs)).await?;
+            print_job(args.json, &job)?;
+            if job.state == JobState::Failed {
+                let reason = job.error.as_deref().unwrap_or("no reason given");
+                return Err(Failure::new(EXIT_JOB_FAILED, &format!("Job {} failed", job.id), reason));
+            }
+        }
+        Command::Latest { account } => {
+            if args.json {
+                emit(&client.latest::<serde_json::Value>(account.as_deref()).await?)?;
+            } else {
+                print_proof(&client.latest::<ProofRecord>(account.as_deref()).await?);
+            }
+        }
+        Command::Get { identifier } => {
+            if args.json {
+                emit(&client.proof::<serde_json::Value>(identifier).await?)?;
+            } else {
+                print_proof(&client.proof::<ProofRecord>(identifier).await?);
+            }
+        }
+        Command::Jobs { command: JobsCommand::List } => {
+            let jobs = client.jobs().await?;
+            if args.json {
+                emit(&jobs)?;
+            } else if jobs.is_empty() {
+                println!("No jobs");
+            } else {
+                print_jobs(&jobs);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Poll a job until it succeeds or fails, reporting every state change
+async fn wait(
+    client: &ApiClient,
+    mut job: Job,
+    json: bool,
+    interval: Duration,
+    timeout: Option<Duration>,
+) -> Result<Job, Failure> {
+    let started = Instant::now();
+    let mut state = job.state;
+    while !matches!(job.state, JobState::Succeeded | JobState::Failed) {
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            let detail = format!("job {} is still {} after {}s", job.id, job.state, started.elapsed().as_secs());
+            return Err(Failure::new(EXIT_FAILURE, "Timed out", detail));
+        }
+        tokio::time::sleep(interval).await;
+        job = client.job(job.id).await?;
+        if job.state != state && !json {
+            println!("⏳ Job {} is {}", job.id, job.state);
+        }
+        state = job.state;
+    }
+    Ok(job)
+}
+
+/// Print a JSON document on stdout
+fn emit<T: Serialize>(document: &T) -> Result<(), Failure> {
+    let output = serde_json::to_string_pretty(document)
+        .map_err(|e| Failure::new(EXIT_FAILURE, "Failed to serialize the output", e))?;
+    println!("{}", output);
+    Ok(())
+}
+
+fn print_job(json: bool, job: &Job) -> Result<(), Failure> {
+    if json {
+        return emit(job);
+    }
+    let icon = match job.state {
+        JobState::Succeeded => "✅",
+        JobState::Failed => "❌",
+        JobState::Queued | JobState::Running => "📋",
+    };
+    println!("{} Job {}: {}", icon, job.id, job.state);
+    println!("   Account:   {}", job.request.account);
+    println!("   Slots:     {}..{}", job.request.start_slot, job.request.end_slot);
+    println!("   Mode:      {}", job.request.mode);
+    println!("   Submitted: {}", job.submitted_at);
+    if let Some(started_at) = &job.started_at {
+        println!("   Started:   {}", started_at);
+    }
+    if let Some(finished_at) = &job.finished_at {
+        println!("   Finished:  {}", finished_at);
+    }
+    if let Some(error) = &job.error {
+        println!("   Error:     {}", error);
+    }
+    Ok(())
+}
+
+fn print_jobs(jobs: &[Job]) {
+    let headers = ["ID", "STATE", "ACCOUNT", "SLOTS", "MODE", "SUBMITTED", "ERROR"];
+    let rows: Vec<Vec<String>> = jobs
+        .iter()
+        .map(|job| {
+            vec![
+                job.id.to_string(),
+                job.state.to_string(),
+                job.request.account.clone(),
+                format!("{}..{}", job.request.start_slot, job.request.end_slot),
+                job.request.mode.to_string(),
+                job.submitted_at.clone(),
+                job.error.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&headers, &rows);
+}
+
+fn print_proof(proof: &ProofRecord) {
+    let optional = |value: Option<i64>| value.map_or("-".to_string(), |value| value.to_string());
+    let slots = match (proof.start_slot, proof.end_slot) {
+        (Some(start), Some(end)) => format!("{}..{}", start, end),
+        _ => "-".to_string(),
+    };
+    let validations = match proof.validations_passed {
+        Some(0) => "failed",
+        Some(_) => "passed",
+        None => "-",
+    };
+    println!("🧾 {}", proof.identifier);
+    println!("   Message:        {}[{}]@{}", proof.topic, proof.partition, proof.offset);
+    println!("   Kind:           {}", proof.proof_kind);
+    println!("   Slots:          {}", slots);
+    println!("   Epoch:          {}", optional(proof.epoch));
+    println!("   Last bank hash: {}", proof.last_bank_hash.as_deref().unwrap_or("-"));
+    println!("   Validations:    {}", validations);
+    println!("   Received:       {}", proof.received_at);
+    if proof.accounts.is_empty() {
+        return;
+    }
+    println!();
+    let headers = ["ACCOUNT", "LAST CHANGE", "LAMPORTS", "OWNER", "DATA LEN"];
+    let rows: Vec<Vec<String>> = proof
+        .accounts
+        .iter()
+        .map(|account| {
+            vec![
+                account.account_pubkey.clone(),
+                account.last_change_slot.to_string(),
+                account.lamports.to_string(),
+                account.owner.clone(),
+                account.data_len.to_string(),
+            ]
+        })
+        .collect();
+    print_table(&headers, &rows);
+}
+
+/// Print rows under a header, every column as wide as its widest cell
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> =
+            cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("   {}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    
+    let args = Args::parse();
+    if let Err(failure) = run(&args).await {
+        if args.json {
+            let output = ErrorOutput { error: failure.error, detail: failure.detail };
+            eprintln!("{}", serde_json::to_string(&output).unwrap_or_default());
+        } else {
+            eprintln!("❌ {}: {}", failure.error, failure.detail);
+        }
+        std::process::exit(failure.code);
+    }
+}
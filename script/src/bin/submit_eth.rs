@@ -0,0 +1,177 @@
+//! Submit a Groth16 proof to the SP1 verifier gateway on Ethereum
+
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Address, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::{LocalSigner, PrivateKeySigner};
+use clap::Parser;
+use solana_stub_prover_script::eth_submit::{describe_error, Groth16Artifact, SP1_GROTH16_GATEWAY};
+use solana_stub_prover_script::keys::{Key, KeyError, KeyKind, KeySource};
+use solana_stub_prover_script::verify::VerifyingKeySource;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Margin added to the gas estimate, in percent
+const GAS_MARGIN_PERCENT: u64 = 20;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Submit a Groth16 proof to the SP1 verifier gateway", long_about = None)]
+struct Args {
+    /// Groth16 proof: last_proof.json or a Kafka message such as last_kafka_message.json
+    #[arg(default_value = "last_proof.json")]
+    proof: PathBuf,
+    
+    /// Print the verifyProof calldata instead of sending a transaction
+    #[arg(long)]
+    calldata_only: bool,
+    
+    /// Ethereum JSON-RPC endpoint
+    #[arg(long, env = "ETH_RPC_URL", required_unless_present = "calldata_only")]
+    eth_rpc: Option<String>,
+    
+    /// SP1 verifier gateway address
+    #[arg(long, env = "SP1_VERIFIER_GATEWAY", default_value_t = SP1_GROTH16_GATEWAY)]
+    verifier: Address,
+    
+    /// Key file or encrypted keystore of the sending account
+    #[arg(long, conflicts_with = "keystore")]
+    key_file: Option<PathBuf>,
+    
+    /// Environment variable holding the sending account's secret as hex, used without --key-file or --keystore
+    #[arg(long, default_value = "ETH_PRIVATE_KEY")]
+    key_env: String,
+    
+    /// Web3 Secret Storage (geth, Foundry) keystore of the sending account
+    #[arg(long)]
+    keystore: Option<PathBuf>,
+    
+    /// Password of --keystore
+    #[arg(long, env = "ETH_KEYSTORE_PASSWORD", hide_env_values = true)]
+    keystore_password: Option<String>,
+    
+    /// Verifying key JSON written by the prover; the proof must be for this program
+    #[arg(long, conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (0x-prefixed bytes32) the proof must be for
+    #[arg(long)]
+    vkey_hash: Option<String>,
+    
+    /// Confirmations to wait for
+    #[arg(long, default_value = "1")]
+    confirmations: u64,
+    
+    /// Seconds to wait for the receipt
+    #[arg(long, default_value = "300")]
+    receipt_timeout: u64,
+}
+
+/// The account that signs the transaction
+fn signer(args: &Args) -> Result<PrivateKeySigner, String> {
+    if let Some(path) = &args.keystore {
+        let password = args
+            .keystore_password
+            .as_deref()
+            .ok_or("--keystore needs --keystore-password or ETH_KEYSTORE_PASSWORD")?;
+        return LocalSigner::decrypt_keystore(path, password)
+            .map_err(|e| format!("Failed to decrypt {}: {}", path.display(), e));
+    }
+    let source = match &args.key_file {
+        Some(path) => KeySource::File(path.clone()),
+        None => KeySource::Env(args.key_env.clone()),
+    };
+    match Key::load(KeyKind::Secp256k1, &source) {
+        Ok(Key::Secp256k1(key)) => Ok(PrivateKeySigner::from_signing_key(key)),
+        Ok(key) => Err(format!("{} holds an {} key", source, key.kind())),
+        Err(KeyError::MissingEnv(var)) => {
+            Err(format!("A signing key is needed: --key-file, --keystore or the {} environment variable", var))
+        }
+        Err(e) => Err(format!("Failed to load the signing key from {}: {}", source, e)),
+    }
+}
+
+async fn run(args: &Args) -> Result<(), String> {
+    let artifact = Groth16Artifact::load(&args.proof)?;
+    let source = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => Some(VerifyingKeySource::from_file(path).map_err(|e| e.to_string())?),
+        (None, Some(hash)) => Some(VerifyingKeySource::from_hash(hash).map_err(|e| e.to_string())?),
+        (None, None) => None,
+    };
+    if let Some(source) = &source {
+        artifact.expect_program(source)?;
+    }
+    
+    let calldata = artifact.calldata();
+    if args.calldata_only {
+        println!("{}", calldata);
+        return Ok(());
+    }
+    
+    let signer = signer(args)?;
+    let sender = signer.address();
+    let rpc = args.eth_rpc.as_deref().ok_or("--eth-rpc is required")?;
+    let url = rpc.parse().map_err(|e| format!("Invalid RPC URL {}: {}", rpc, e))?;
+    let provider = ProviderBuilder::new().wallet(EthereumWallet::from(signer)).connect_http(url);
+    
+    println!("📄 Proof: {}", args.proof.display());
+    println!("   Program VKey Hash: {}", artifact.vkey_hash);
+    println!("   Public Values: {} bytes, Proof: {} bytes", artifact.public_values.len(), artifact.proof_bytes.len());
+    println!("🔑 Sender: {}", sender);
+    println!("🎯 Verifier gateway: {}", args.verifier);
+    
+    let tx = TransactionRequest::default().with_from(sender).with_to(args.verifier).with_input(calldata);
+    // The gateway reverts on invalid proofs, so a failed estimate means the proof would be rejected
+    let gas = provider
+        .estimate_gas(tx.clone())
+        .await
+        .map_err(|e| format!("Gas estimation failed: {}", describe_error(&e)))?;
+    let gas_limit = gas + gas * GAS_MARGIN_PERCENT / 100;
+    match provider.get_gas_price().await {
+        Ok(price) => println!(
+            "⛽ Estimated gas: {} (limit {}), about {} ETH",
+            gas,
+            gas_limit,
+            format_ether(U256::from(gas) * U256::from(price))
+        ),
+        Err(_) => println!("⛽ Estimated gas: {} (limit {})", gas, gas_limit),
+    }
+    
+    let pending = provider
+        .send_transaction(tx.with_gas_limit(gas_limit))
+        .await
+        .map_err(|e| format!("Failed to send the transaction: {}", describe_error(&e)))?;
+    println!("📤 Transaction: {}", pending.tx_hash());
+    println!("⏳ Waiting for {} confirmation(s)...", args.confirmations);
+    let receipt = pending
+        .with_required_confirmations(args.confirmations)
+        .with_timeout(Some(Duration::from_secs(args.receipt_timeout)))
+        .get_receipt()
+        .await
+        .map_err(|e| format!("Failed to get the receipt: {}", e))?;
+    
+    let block = receipt.block_number.map_or_else(|| "pending".to_string(), |block| block.to_string());
+    if !receipt.status() {
+        return Err(format!("Transaction {} reverted in block {}", receipt.transaction_hash, block));
+    }
+    println!("✅ Proof verified on-chain in block {} (gas used {})", block, receipt.gas_used);
+    for log in receipt.inner.logs() {
+        println!(
+            "   📣 Event from {}: {}",
+            log.address(),
+            log.topics().first().map_or_else(|| "anonymous".to_string(), |topic| topic.to_string())
+        );
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    if let Err(e) = run(&args).await {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,198 @@
+//! Submit a Groth16 proof to a verifier program on Solana
+
+use clap::Parser;
+use ed25519_dalek::SigningKey;
+use solana_stub_prover_script::keys::{Key, KeyKind, KeySource};
+use solana_stub_prover_script::solana::{SolanaRpcClient, DEVNET_RPC_URL};
+use solana_stub_prover_script::solana_submit::{
+    create_account_instruction, encode_pubkey, init_buffer_instruction, parse_pubkey, Groth16Proof, Instruction,
+    Message, Pubkey, SubmissionPlan,
+};
+use solana_stub_prover_script::verify::VerifyingKeySource;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the signature status is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Submit a Groth16 proof to a Solana verifier program", long_about = None)]
+struct Args {
+    /// Groth16 proof: last_proof.json or a Kafka message such as last_kafka_message.json
+    #[arg(default_value = "last_proof.json")]
+    proof: PathBuf,
+    
+    /// Base58 id of the verifier program
+    #[arg(long, env = "SOLANA_VERIFIER_PROGRAM_ID")]
+    program_id: String,
+    
+    /// Key file or keystore of the fee payer (default: ~/.config/solana/id.json)
+    #[arg(long, alias = "keypair", env = "SOLANA_KEYPAIR")]
+    key_file: Option<PathBuf>,
+    
+    /// Environment variable holding the fee payer's secret as hex, used instead of --key-file
+    #[arg(long, conflicts_with = "key_file")]
+    key_env: Option<String>,
+    
+    /// Solana JSON-RPC endpoint
+    #[arg(long, env = "SOLANA_RPC_URL", default_value = DEVNET_RPC_URL)]
+    rpc_url: String,
+    
+    /// Only simulate the first transaction and print its logs
+    #[arg(long)]
+    simulate: bool,
+    
+    /// Verifying key JSON written by the prover; the proof must be for this program
+    #[arg(long, conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (0x-prefixed bytes32) the proof must be for
+    #[arg(long)]
+    vkey_hash: Option<String>,
+    
+    /// Seconds to wait for each transaction to be confirmed
+    #[arg(long, default_value = "60")]
+    confirm_timeout: u64,
+}
+
+/// The fee payer's key, from the Solana CLI's default keypair unless given
+fn payer_key(args: &Args) -> Result<Key, String> {
+    let source = match KeySource::from_args(args.key_file.as_deref(), args.key_env.as_deref()) {
+        Some(source) => source,
+        None => {
+            let home = std::env::var_os("HOME").ok_or("--key-file is required when HOME is not set")?;
+            KeySource::File(PathBuf::from(home).join(".config/solana/id.json"))
+        }
+    };
+    Key::load(KeyKind::Ed25519, &source).map_err(|e| format!("Failed to load the fee payer key from {}: {}", source, e))
+}
+
+/// Transaction of `instructions` signed by `signers`, the first paying the fee
+async fn sign(rpc: &SolanaRpcClient, instructions: &[Instruction], signers: &[&SigningKey]) -> Result<Vec<u8>, String> {
+    let blockhash = rpc.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+    let payer = signers[0].verifying_key().to_bytes();
+    Message::new(instructions, &payer, blockhash).sign(signers)
+}
+
+/// Send a transaction and poll until it is confirmed
+async fn send(
+    rpc: &SolanaRpcClient,
+    label: &str,
+    instructions: &[Instruction],
+    signers: &[&SigningKey],
+    timeout: Duration,
+) -> Result<(), String> {
+    let transaction = sign(rpc, instructions, signers).await?;
+    let signature = rpc.send_transaction(&transaction).await.map_err(|e| format!("{}: {}", label, e))?;
+    println!("📤 {}: {} ({} bytes)", label, signature, transaction.len());
+    
+    let started = Instant::now();
+    loop {
+        match rpc.get_signature_status(&signature).await {
+            Ok(Some(status)) => {
+                if let Some(err) = status.err {
+                    return Err(format!("{} failed in slot {}: {}", label, status.slot, err));
+                }
+                if matches!(status.confirmation_status.as_deref(), Some("confirmed" | "finalized")) {
+                    println!("   ✅ Confirmed in slot {}", status.slot);
+                    return Ok(());
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("   ⚠️  Failed to get the status of {}: {}", signature, e),
+        }
+        if started.elapsed() >= timeout {
+            return Err(format!("{} was not confirmed within {:?}", signature, timeout));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Simulate the first transaction of the plan and print its logs
+async fn simulate(rpc: &SolanaRpcClient, instructions: &[Instruction], signers: &[&SigningKey]) -> Result<(), String> {
+    let transaction = sign(rpc, instructions, signers).await?;
+    let result = rpc.simulate_transaction(&transaction).await.map_err(|e| e.to_string())?;
+    for log in result.logs.unwrap_or_default() {
+        println!("   {}", log);
+    }
+    if let Some(units) = result.units_consumed {
+        println!("⚙️  Compute units: {}", units);
+    }
+    match result.err {
+        Some(err) => Err(format!("Simulation failed: {}", err)),
+        None => {
+            println!("✅ Simulation succeeded");
+            Ok(())
+        }
+    }
+}
+
+async fn run(args: &Args) -> Result<(), String> {
+    let proof = Groth16Proof::load(&args.proof)?;
+    let source = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => Some(VerifyingKeySource::from_file(path).map_err(|e| e.to_string())?),
+        (None, Some(hash)) => Some(VerifyingKeySource::from_hash(hash).map_err(|e| e.to_string())?),
+        (None, None) => None,
+    };
+    if let Some(source) = &source {
+        proof.expect_program(source)?;
+    }
+    
+    let program_id = parse_pubkey(&args.program_id)?;
+    let Key::Ed25519(payer) = payer_key(args)? else {
+        return Err("The fee payer key is not an ed25519 key".to_string());
+    };
+    let payer_pubkey: Pubkey = payer.verifying_key().to_bytes();
+    // Only used when the proof needs a buffer account
+    let buffer = SigningKey::generate(&mut rand::rngs::OsRng);
+    let plan = SubmissionPlan::new(&program_id, &payer_pubkey, &buffer.verifying_key().to_bytes(), &proof);
+    let rpc = SolanaRpcClient::new(&args.rpc_url);
+    
+    println!("📄 Proof: {}", args.proof.display());
+    println!("   Program VKey Hash: 0x{}", hex::encode(proof.vkey_hash));
+    println!("   Public Values: {} bytes, Proof: {} bytes", proof.public_values.len(), proof.proof_bytes.len());
+    println!("🔑 Fee payer: {}", encode_pubkey(&payer_pubkey));
+    println!("🎯 Verifier program: {}", args.program_id);
+    println!("📦 {} transaction(s)", plan.transactions());
+    
+    let timeout = Duration::from_secs(args.confirm_timeout);
+    match plan {
+        SubmissionPlan::Direct(verify) => {
+            if args.simulate {
+                return simulate(&rpc, &[verify], &[&payer]).await;
+            }
+            send(&rpc, "verify", &[verify], &[&payer], timeout).await?;
+        }
+        SubmissionPlan::Buffered { buffer: buffer_pubkey, space, writes, verify } => {
+            println!("🗄️  Buffer account: {} ({} bytes)", encode_pubkey(&buffer_pubkey), space);
+            let lamports = rpc.get_minimum_balance_for_rent_exemption(space).await.map_err(|e| e.to_string())?;
+            let setup = [
+                create_account_instruction(&payer_pubkey, &buffer_pubkey, lamports, space, &program_id),
+                init_buffer_instruction(&program_id, &buffer_pubkey, &payer_pubkey, (space - 32) as u32),
+            ];
+            if args.simulate {
+                // The writes and the verification need the buffer to exist
+                println!("ℹ️  Simulating the buffer setup only; the later transactions depend on it");
+                return simulate(&rpc, &setup, &[&payer, &buffer]).await;
+            }
+            send(&rpc, "init_buffer", &setup, &[&payer, &buffer], timeout).await?;
+            for (index, write) in writes.iter().enumerate() {
+                let label = format!("write_buffer {}/{}", index + 1, writes.len());
+                send(&rpc, &label, std::slice::from_ref(write), &[&payer], timeout).await?;
+            }
+            send(&rpc, "verify_buffer", &[verify], &[&payer], timeout).await?;
+        }
+    }
+    println!("✅ Proof verified on-chain");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    if let Err(e) = run(&args).await {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
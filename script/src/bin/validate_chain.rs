@@ -0,0 +1,165 @@
+//! Offline audit of accumulated proofs
+//!
+//! Loads the proofs of an archive directory or SQLite database, checks that
+//! each chain is contiguous and consistent, and prints a report. Exits with 1
+//! when anything is wrong, so it can gate scripts.
+
+use clap::Parser;
+use solana_stub_prover_script::chain_audit::{audit, load_dir, load_store, AuditReport, ChainIssue, ChainLink};
+use solana_stub_prover_script::continuity::ChainKeying;
+use solana_stub_prover_script::presenter::{ColorChoice, OutputStyle, Presenter, Section, Tone};
+use solana_stub_prover_script::verify::{ProofVerifier, VerifyStatus, VerifyingKeySource};
+use solana_stub_prover_script::{errln, outln};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Check that accumulated proofs form contiguous chains", long_about = None)]
+struct Args {
+    /// Directory of proofs: a consumer --save-dir or proof JSON files
+    #[arg(required_unless_present = "sqlite")]
+    dir: Option<PathBuf>,
+    
+    /// Read the proofs of a database written by consumer --sqlite instead
+    #[arg(long, conflicts_with = "dir")]
+    sqlite: Option<PathBuf>,
+    
+    /// How proofs are grouped into chains: accounts or prefix
+    #[arg(long, default_value = "accounts")]
+    keying: ChainKeying,
+    
+    /// Also verify every proof cryptographically (needs --vkey or --vkey-hash)
+    #[arg(long, conflicts_with = "sqlite")]
+    verify: bool,
+    
+    /// Verifying key JSON written by the prover
+    #[arg(long, requires = "verify", conflicts_with = "vkey_hash")]
+    vkey: Option<PathBuf>,
+    
+    /// Program vkey hash (Groth16 and Plonk proofs only)
+    #[arg(long, requires = "verify")]
+    vkey_hash: Option<String>,
+    
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+    
+    /// Output style: pretty (emoji and boxes) or plain (default: pretty on a terminal, plain otherwise)
+    #[arg(long)]
+    style: Option<OutputStyle>,
+    
+    /// Color output: auto, always or never
+    #[arg(long, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// Verify every proof, returning the failures with their chains
+fn verify_links(links: &[ChainLink], source: VerifyingKeySource) -> Vec<(String, ChainIssue)> {
+    let verifier = ProofVerifier::new(source);
+    let mut failures = Vec::new();
+    for link in links {
+        let reason = match &link.proof {
+            Some(proof) => match verifier.verify(proof).status {
+                VerifyStatus::Verified => continue,
+                VerifyStatus::Failed(reason) => reason,
+            },
+            None => "only the commitments could be read".to_string(),
+        };
+        let issue = ChainIssue::VerificationFailed { identifier: link.identifier.clone(), reason };
+        failures.push((link.chain.clone(), issue));
+    }
+    failures
+}
+
+fn print_report(report: &AuditReport, presenter: &Presenter) {
+    for chain in &report.chains {
+        let mut section = Section::new(format!("Chain {}", chain.chain));
+        section
+            .field(0, "Proofs", chain.proofs)
+            .field(0, "Slots", format!("{}..{}", chain.first_slot, chain.last_slot));
+        if chain.duplicates > 0 {
+            section.field(0, "Duplicates", chain.duplicates);
+        }
+        if chain.issues.is_empty() {
+            section.note(format!("✅ {}", presenter.paint("Contiguous", Tone::Good)));
+        } else {
+            section.heading(0, format!("{} issue(s):", presenter.paint(chain.issues.len(), Tone::Bad)));
+            for issue in &chain.issues {
+                section.note(format!("❌ {}", issue));
+            }
+        }
+        outln!("{}", section.render(presenter));
+    }
+    for unreadable in &report.unreadable {
+        errln!("⚠️  {}: {}", unreadable.source, unreadable.reason);
+    }
+    
+    let proofs: usize = report.chains.iter().map(|chain| chain.proofs).sum();
+    let summary = format!(
+        "{} proof(s) in {} chain(s), {} issue(s), {} unreadable",
+        proofs,
+        report.chains.len(),
+        report.issues(),
+        report.unreadable.len()
+    );
+    if report.is_clean() {
+        outln!("✅ {}", presenter.paint(summary, Tone::Good));
+    } else {
+        outln!("❌ {}", presenter.paint(summary, Tone::Bad));
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let presenter = Presenter::new(args.style, args.color).install();
+    
+    let loaded = match (&args.dir, &args.sqlite) {
+        (_, Some(path)) => load_store(path, args.keying),
+        (Some(dir), None) => load_dir(dir, args.keying),
+        (None, None) => unreachable!("clap requires a directory or --sqlite"),
+    };
+    let (links, unreadable) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            errln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    let source = match (&args.vkey, &args.vkey_hash) {
+        (Some(path), _) => Some(VerifyingKeySource::from_file(path)),
+        (None, Some(hash)) => Some(VerifyingKeySource::from_hash(hash)),
+        (None, None) if args.verify => {
+            errln!("❌ --verify needs --vkey or --vkey-hash");
+            std::process::exit(1);
+        }
+        (None, None) => None,
+    };
+    let source = match source.transpose() {
+        Ok(source) => source,
+        Err(e) => {
+            errln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    let failures = source.map(|source| verify_links(&links, source)).unwrap_or_default();
+    let mut report = audit(links, unreadable);
+    for (chain, issue) in failures {
+        report.add_issue(&chain, issue);
+    }
+    
+    if args.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => outln!("{}", json),
+            Err(e) => {
+                errln!("❌ Failed to serialize the report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report, presenter);
+    }
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
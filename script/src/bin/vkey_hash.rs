@@ -0,0 +1,35 @@
+//! Print the program vkey hash and pin it as the one the prover expects
+
+use clap::Parser;
+use solana_stub_prover_script::pipeline::program_vkey_hash;
+use solana_stub_prover_script::vkey_registry::{expected_vkey_hash, format_vkey_hash, EXPECTED_VKEY_FILE};
+use std::fs;
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Print the program vkey hash and pin it for the prover", long_about = None)]
+struct Args {
+    /// Write the hash to the pinned file; rebuild the prover and consumer to expect it
+    #[arg(long)]
+    write: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let vk_hash = program_vkey_hash();
+    println!("🔑 Program vkey hash: {}", format_vkey_hash(&vk_hash));
+    match expected_vkey_hash() {
+        Some(expected) if expected == vk_hash => println!("✅ Matches the hash this build expects"),
+        Some(expected) => println!("⚠️  This build expects {}", format_vkey_hash(&expected)),
+        None => println!("⚠️  This build expects no hash"),
+    }
+    
+    if args.write {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(EXPECTED_VKEY_FILE);
+        if let Err(e) = fs::write(&path, format!("{}\n", format_vkey_hash(&vk_hash))) {
+            eprintln!("❌ Failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        println!("📝 Pinned in {}", path.display());
+    }
+}
@@ -0,0 +1,372 @@
+//! Offline audit of accumulated proofs
+//!
+//! Proofs are loaded from a directory (the consumer's `--save-dir`, or proof
+//! JSON files such as those written by `--sink file`) or from the SQLite store,
+//! grouped into chains like the continuity monitor does, and each chain is
+//! walked in start-slot order. Every proof must start at the slot the previous
+//! one ended at, from its last bank hash; gaps, overlaps, forks (two different
+//! proofs from the same start slot) and bank hash mismatches are reported, as
+//! are proofs committing `validations_passed = false`. Identical copies of a
+//! proof, such as redelivered messages, count once.
+
+use crate::continuity::ChainKeying;
+#[cfg(feature = "sqlite")]
+use crate::continuity::{account_set_key, identifier_prefix};
+use crate::envelope::decode_json;
+use crate::kafka::PayloadFormat;
+use crate::proof_filter::decode_commitments;
+#[cfg(feature = "sqlite")]
+use crate::proof_store::ProofStore;
+use serde::Serialize;
+use solana_stub_prover_lib::PublicCommitments;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use twine_types::proofs::ZkProof;
+
+const COMMITMENTS_SUFFIX: &str = ".commitments.json";
+
+/// One proof's place in a chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainLink {
+    pub identifier: String,
+    /// File or database the proof was loaded from
+    pub source: String,
+    pub chain: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Hex of the bank hashes
+    pub original_bank_hash: String,
+    pub last_bank_hash: String,
+    pub validations_passed: bool,
+    /// The proof itself, when the source has it
+    #[serde(skip)]
+    pub proof: Option<ZkProof>,
+}
+
+impl ChainLink {
+    pub fn from_commitments(
+        keying: ChainKeying,
+        identifier: &str,
+        source: &str,
+        commitments: &PublicCommitments,
+        proof: Option<ZkProof>,
+    ) -> Self {
+        Self {
+            identifier: identifier.to_string(),
+            source: source.to_string(),
+            chain: keying.chain_key(identifier, commitments),
+            start_slot: commitments.start_slot,
+            end_slot: commitments.end_slot,
+            original_bank_hash: hex::encode(commitments.original_bank_hash),
+            last_bank_hash: hex::encode(commitments.last_bank_hash),
+            validations_passed: commitments.validations_passed,
+            proof,
+        }
+    }
+
+    /// Whether two links commit to the same slots and bank hashes
+    fn same_commitment(&self, other: &ChainLink) -> bool {
+        self.start_slot == other.start_slot
+            && self.end_slot == other.end_slot
+            && self.original_bank_hash == other.original_bank_hash
+            && self.last_bank_hash == other.last_bank_hash
+    }
+}
+
+/// A problem found by the audit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum ChainIssue {
+    /// Slots are missing between two proofs
+    Gap { identifier: String, previous_identifier: String, expected_start_slot: u64, start_slot: u64 },
+    /// A proof starts before the previous proof ended
+    Overlap { identifier: String, previous_identifier: String, previous_end_slot: u64, start_slot: u64 },
+    /// Two different proofs start at the same slot
+    Fork { identifier: String, other_identifier: String, start_slot: u64 },
+    /// A proof starts at the right slot but from a different bank hash
+    Mismatch {
+        identifier: String,
+        previous_identifier: String,
+        slot: u64,
+        expected_bank_hash: String,
+        original_bank_hash: String,
+    },
+    /// The program committed `validations_passed = false`
+    ValidationsFailed { identifier: String },
+    /// `--verify` rejected the proof
+    VerificationFailed { identifier: String, reason: String },
+}
+
+impl fmt::Display for ChainIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainIssue::Gap { identifier, previous_identifier, expected_start_slot, start_slot } => write!(
+                f,
+                "GAP: {} starts at slot {}, but {} ended at slot {} ({} slot(s) missing)",
+                identifier,
+                start_slot,
+                previous_identifier,
+                expected_start_slot,
+                start_slot - expected_start_slot
+            ),
+            ChainIssue::Overlap { identifier, previous_identifier, previous_end_slot, start_slot } => write!(
+                f,
+                "OVERLAP: {} starts at slot {}, before {} ended at slot {}",
+                identifier, start_slot, previous_identifier, previous_end_slot
+            ),
+            ChainIssue::Fork { identifier, other_identifier, start_slot } => write!(
+                f,
+                "FORK: {} and {} both start at slot {} with different commitments",
+                other_identifier, identifier, start_slot
+            ),
+            ChainIssue::Mismatch { identifier, previous_identifier, slot, expected_bank_hash, original_bank_hash } => {
+                write!(
+                    f,
+                    "MISMATCH: {} starts at slot {} from bank hash 0x{}, but {} ended with 0x{}",
+                    identifier, slot, original_bank_hash, previous_identifier, expected_bank_hash
+                )
+            }
+            ChainIssue::ValidationsFailed { identifier } => {
+                write!(f, "VALIDATIONS FAILED: {} committed validations_passed = false", identifier)
+            }
+            ChainIssue::VerificationFailed { identifier, reason } => {
+                write!(f, "UNVERIFIED: {} does not verify ({})", identifier, reason)
+            }
+        }
+    }
+}
+
+/// Audit of one chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainReport {
+    pub chain: String,
+    /// Distinct proofs, not counting duplicates
+    pub proofs: usize,
+    pub duplicates: usize,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    pub issues: Vec<ChainIssue>,
+}
+
+/// A file that could not be read as a proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Unreadable {
+    pub source: String,
+    pub reason: String,
+}
+
+/// Audit of all loaded proofs
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub chains: Vec<ChainReport>,
+    pub unreadable: Vec<Unreadable>,
+}
+
+impl AuditReport {
+    /// Record an issue found outside the chain walk, such as a failed verification
+    pub fn add_issue(&mut self, chain: &str, issue: ChainIssue) {
+        if let Some(report) = self.chains.iter_mut().find(|report| report.chain == chain) {
+            report.issues.push(issue);
+        }
+    }
+
+    pub fn issues(&self) -> usize {
+        self.chains.iter().map(|chain| chain.issues.len()).sum()
+    }
+
+    /// No issues and nothing unreadable
+    pub fn is_clean(&self) -> bool {
+        self.issues() == 0 && self.unreadable.is_empty()
+    }
+}
+
+/// Load the proofs of a directory
+///
+/// Reads archived payloads (`.bin`, JSON or bincode), falling back to their
+/// `.commitments.json` side file when the payload cannot be decoded (Avro),
+/// and proof JSON files. Other files are ignored.
+pub fn load_dir(dir: &Path, keying: ChainKeying) -> Result<(Vec<ChainLink>, Vec<Unreadable>), String> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut links = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in &paths {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let source = path.display().to_string();
+        let loaded = if let Some(stem) = name.strip_suffix(".bin") {
+            let side_file = dir.join(format!("{}{}", stem, COMMITMENTS_SUFFIX));
+            match load_payload(path, keying) {
+                Err(_) if side_file.is_file() => load_commitments(&side_file, stem, keying),
+                loaded => loaded,
+            }
+        } else if let Some(stem) = name.strip_suffix(COMMITMENTS_SUFFIX) {
+            // Loaded with its payload when there is one
+            if dir.join(format!("{}.bin", stem)).is_file() {
+                continue;
+            }
+            load_commitments(path, stem, keying)
+        } else if name.ends_with(".json") {
+            load_payload(path, keying)
+        } else {
+            continue;
+        };
+        match loaded {
+            Ok(link) => links.push(link),
+            Err(reason) => unreadable.push(Unreadable { source, reason }),
+        }
+    }
+    Ok((links, unreadable))
+}
+
+/// Load a proof payload and its committed public values
+fn load_payload(path: &Path, keying: ChainKeying) -> Result<ChainLink, String> {
+    let payload = fs::read(path).map_err(|e| e.to_string())?;
+    let proof = match PayloadFormat::sniff(&payload) {
+        PayloadFormat::Json => decode_json(&payload).map_err(|e| e.to_string())?.proof,
+        PayloadFormat::Bincode => {
+            bincode::deserialize::<ZkProof>(&payload).map_err(|e| format!("undecodable bincode: {}", e))?
+        }
+        PayloadFormat::Avro => return Err("Avro payload without a commitments file".to_string()),
+    };
+    let commitments = decode_commitments(&proof).ok_or("public values are not PublicCommitments")?;
+    let identifier = proof.identifier.clone();
+    Ok(ChainLink::from_commitments(keying, &identifier, &path.display().to_string(), &commitments, Some(proof)))
+}
+
+/// Load the commitments side file of an archived message
+///
+/// The file does not name the proof, so the identifier is the archive file stem.
+fn load_commitments(path: &Path, stem: &str, keying: ChainKeying) -> Result<ChainLink, String> {
+    let json = fs::read(path).map_err(|e| e.to_string())?;
+    let commitments: PublicCommitments =
+        serde_json::from_slice(&json).map_err(|e| format!("invalid commitments: {}", e))?;
+    Ok(ChainLink::from_commitments(keying, stem, &path.display().to_string(), &commitments, None))
+}
+
+/// Load the proofs of a database written by `consumer --sqlite`
+///
+/// The database holds commitments only, so its proofs cannot be verified.
+#[cfg(feature = "sqlite")]
+pub fn load_store(path: &Path, keying: ChainKeying) -> Result<(Vec<ChainLink>, Vec<Unreadable>), String> {
+    let store = ProofStore::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let proofs = store.all().map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut links = Vec::new();
+    let mut unreadable = Vec::new();
+    for proof in proofs {
+        let source = format!("{}#{}", path.display(), proof.identifier);
+        let (Some(start_slot), Some(end_slot), Some(original_bank_hash), Some(last_bank_hash)) =
+            (proof.start_slot, proof.end_slot, proof.original_bank_hash, proof.last_bank_hash)
+        else {
+            unreadable.push(Unreadable { source, reason: "no decoded commitments stored".to_string() });
+            continue;
+        };
+        let chain = match keying {
+            ChainKeying::Accounts => {
+                account_set_key(proof.accounts.into_iter().map(|account| account.account_pubkey).collect())
+            }
+            ChainKeying::IdentifierPrefix => identifier_prefix(&proof.identifier).to_string(),
+        };
+        links.push(ChainLink {
+            identifier: proof.identifier,
+            source,
+            chain,
+            start_slot: start_slot as u64,
+            end_slot: end_slot as u64,
+            original_bank_hash,
+            last_bank_hash,
+            validations_passed: proof.validations_passed.unwrap_or(0) != 0,
+            proof: None,
+        });
+    }
+    Ok((links, unreadable))
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_store(_path: &Path, _keying: ChainKeying) -> Result<(Vec<ChainLink>, Vec<Unreadable>), String> {
+    Err("--sqlite requires building with the sqlite feature".to_string())
+}
+
+/// Check every chain of `links`
+pub fn audit(mut links: Vec<ChainLink>, unreadable: Vec<Unreadable>) -> AuditReport {
+    links.sort_by(|a, b| {
+        (&a.chain, a.start_slot, a.end_slot, &a.identifier).cmp(&(&b.chain, b.start_slot, b.end_slot, &b.identifier))
+    });
+    let mut chains: BTreeMap<String, Vec<ChainLink>> = BTreeMap::new();
+    for link in links {
+        chains.entry(link.chain.clone()).or_default().push(link);
+    }
+    AuditReport { chains: chains.into_iter().map(|(chain, links)| audit_chain(chain, &links)).collect(), unreadable }
+}
+
+/// Walk one chain's links, sorted by start slot
+fn audit_chain(chain: String, links: &[ChainLink]) -> ChainReport {
+    let mut issues = Vec::new();
+    let mut duplicates = 0;
+    let mut distinct: Vec<&ChainLink> = Vec::new();
+    // Proof the next one must continue from: the one reaching furthest so far
+    let mut tip: Option<&ChainLink> = None;
+
+    for link in links {
+        if distinct.iter().any(|seen| seen.same_commitment(link)) {
+            duplicates += 1;
+            continue;
+        }
+        let fork = distinct.iter().find(|other| other.start_slot == link.start_slot).copied();
+        distinct.push(link);
+        if !link.validations_passed {
+            issues.push(ChainIssue::ValidationsFailed { identifier: link.identifier.clone() });
+        }
+
+        if let Some(other) = fork {
+            issues.push(ChainIssue::Fork {
+                identifier: link.identifier.clone(),
+                other_identifier: other.identifier.clone(),
+                start_slot: link.start_slot,
+            });
+        } else if let Some(previous) = tip {
+            if link.start_slot < previous.end_slot {
+                issues.push(ChainIssue::Overlap {
+                    identifier: link.identifier.clone(),
+                    previous_identifier: previous.identifier.clone(),
+                    previous_end_slot: previous.end_slot,
+                    start_slot: link.start_slot,
+                });
+            } else if link.start_slot > previous.end_slot {
+                issues.push(ChainIssue::Gap {
+                    identifier: link.identifier.clone(),
+                    previous_identifier: previous.identifier.clone(),
+                    expected_start_slot: previous.end_slot,
+                    start_slot: link.start_slot,
+                });
+            } else if link.original_bank_hash != previous.last_bank_hash {
+                issues.push(ChainIssue::Mismatch {
+                    identifier: link.identifier.clone(),
+                    previous_identifier: previous.identifier.clone(),
+                    slot: link.start_slot,
+                    expected_bank_hash: previous.last_bank_hash.clone(),
+                    original_bank_hash: link.original_bank_hash.clone(),
+                });
+            }
+        }
+        if tip.is_none_or(|previous| link.end_slot > previous.end_slot) {
+            tip = Some(link);
+        }
+    }
+
+    ChainReport {
+        chain,
+        proofs: distinct.len(),
+        duplicates,
+        first_slot: links.first().map_or(0, |link| link.start_slot),
+        last_slot: links.iter().map(|link| link.end_slot).max().unwrap_or(0),
+        issues,
+    }
+}
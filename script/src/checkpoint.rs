@@ -0,0 +1,52 @@
+//! Durable record of the last successfully published proof
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Last proof published to Kafka
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub identifier: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+impl Checkpoint {
+    /// Load a checkpoint, returning `None` if the file does not exist
+    pub fn load(path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read(path)?;
+        Ok(Some(serde_json::from_slice(&contents)?))
+    }
+
+    /// Write the checkpoint so that it survives a crash at any point
+    ///
+    /// The data is written to a temporary file, fsynced, renamed over the
+    /// target and the directory is fsynced, so readers see either the old or
+    /// the new checkpoint, never a partial one.
+    pub fn write_durable(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("tmp");
+        let contents = serde_json::to_vec_pretty(self)?;
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&tmp_path, path)?;
+
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+}
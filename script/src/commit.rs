@@ -0,0 +1,136 @@
+//! Consumer offset commit strategies
+//!
+//! With manual commits the consumer stores a message's offset only once it has
+//! been fully processed (saved, verified and printed) and commits the stored
+//! offsets in batches, on shutdown and before partitions are revoked, so a
+//! crash mid-message leads to redelivery instead of a lost message.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Default time between manual commits
+pub const DEFAULT_COMMIT_INTERVAL_MS: u64 = 5000;
+/// Default number of processed messages that triggers a manual commit
+pub const DEFAULT_COMMIT_BATCH: usize = 100;
+
+/// How consumed offsets are committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitStrategy {
+    /// librdkafka stores and commits offsets on receipt, every second
+    #[default]
+    Auto,
+    /// Offsets are stored after processing and committed in batches
+    Manual,
+}
+
+impl FromStr for CommitStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(CommitStrategy::Auto),
+            "manual" => Ok(CommitStrategy::Manual),
+            other => Err(format!("Unknown commit strategy '{}' (expected auto or manual)", other)),
+        }
+    }
+}
+
+impl fmt::Display for CommitStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitStrategy::Auto => write!(f, "auto"),
+            CommitStrategy::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+/// Decides when stored offsets should be committed
+#[derive(Debug)]
+pub struct CommitBatcher {
+    interval: Duration,
+    batch: usize,
+    pending: usize,
+    last_commit: Instant,
+}
+
+impl CommitBatcher {
+    pub fn new(interval: Duration, batch: usize) -> Self {
+        Self { interval, batch: batch.max(1), pending: 0, last_commit: Instant::now() }
+    }
+
+    /// Record a processed message whose offset has been stored
+    pub fn processed(&mut self) {
+        self.pending += 1;
+    }
+
+    /// Number of processed messages not yet committed
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Whether enough messages or time have accumulated to commit
+    pub fn is_due(&self) -> bool {
+        self.pending >= self.batch || (self.pending > 0 && self.last_commit.elapsed() >= self.interval)
+    }
+
+    /// Record a successful commit
+    pub fn committed(&mut self) {
+        self.pending = 0;
+        self.last_commit = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!("auto".parse::<CommitStrategy>().unwrap(), CommitStrategy::Auto);
+        assert_eq!("MANUAL".parse::<CommitStrategy>().unwrap(), CommitStrategy::Manual);
+        assert_eq!(CommitStrategy::Manual.to_string(), "manual");
+        assert_eq!(CommitStrategy::default(), CommitStrategy::Auto);
+        assert!("sync".parse::<CommitStrategy>().unwrap_err().contains("'sync'"));
+    }
+
+    #[test]
+    fn commits_once_the_batch_fills() {
+        let mut batcher = CommitBatcher::new(Duration::from_secs(3600), 3);
+        batcher.processed();
+        batcher.processed();
+        assert!(!batcher.is_due());
+        batcher.processed();
+        assert!(batcher.is_due());
+        assert_eq!(batcher.pending(), 3);
+
+        batcher.committed();
+        assert_eq!(batcher.pending(), 0);
+        assert!(!batcher.is_due());
+    }
+
+    #[test]
+    fn commits_pending_messages_once_the_interval_passes() {
+        let mut batcher = CommitBatcher::new(Duration::from_millis(20), 100);
+        std::thread::sleep(Duration::from_millis(30));
+        // Nothing to commit, however long it has been
+        assert!(!batcher.is_due());
+        batcher.processed();
+        assert!(batcher.is_due());
+
+        // Committing restarts the interval
+        batcher.committed();
+        batcher.processed();
+        assert!(!batcher.is_due());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(batcher.is_due());
+    }
+
+    #[test]
+    fn a_zero_batch_commits_every_message() {
+        let mut batcher = CommitBatcher::new(Duration::from_secs(3600), 0);
+        assert!(!batcher.is_due());
+        batcher.processed();
+        assert!(batcher.is_due());
+    }
+}
@@ -0,0 +1,93 @@
+//! Compact commitment facts republished for downstream services
+//!
+//! Downstream services rarely need the proof itself, only what it proves. The
+//! consumer can republish each decoded consensus proof as a small JSON record
+//! of its slot range, epoch and monitored accounts, keyed by the first
+//! monitored account so every account's facts stay in order on one partition.
+
+use crate::utils::bytes32_to_base58;
+use serde::{Deserialize, Serialize};
+use solana_stub_prover_lib::PublicCommitments;
+
+/// State of one monitored account at the end of the proven range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountFact {
+    /// Base58 pubkey
+    pub account: String,
+    pub lamports: u64,
+    /// Hex of the account data hash
+    pub data_hash: String,
+    pub last_change_slot: u64,
+}
+
+/// What a proof commits to, without the proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentFacts {
+    pub identifier: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub epoch: u64,
+    pub validations_passed: bool,
+    /// Verification result, when the consumer verifies proofs
+    pub verified: Option<bool>,
+    pub accounts: Vec<AccountFact>,
+    /// Where the proof was consumed from
+    pub source_topic: String,
+    pub source_partition: i32,
+    pub source_offset: i64,
+}
+
+impl CommitmentFacts {
+    pub fn new(
+        identifier: &str,
+        commitments: &PublicCommitments,
+        verified: Option<bool>,
+        source_topic: &str,
+        source_partition: i32,
+        source_offset: i64,
+    ) -> Self {
+        Self {
+            identifier: identifier.to_string(),
+            start_slot: commitments.start_slot,
+            end_slot: commitments.end_slot,
+            epoch: commitments.epoch,
+            validations_passed: commitments.validations_passed,
+            verified,
+            accounts: commitments
+                .monitored_accounts_state
+                .iter()
+                .map(|account| AccountFact {
+                    account: bytes32_to_base58(&account.account_pubkey),
+                    lamports: account.lamports,
+                    data_hash: hex::encode(account.account_data_hash),
+                    last_change_slot: account.last_change_slot,
+                })
+                .collect(),
+            source_topic: source_topic.to_string(),
+            source_partition,
+            source_offset,
+        }
+    }
+
+    /// Record key: the first monitored account, or the identifier if there is none
+    pub fn record_key(&self) -> &str {
+        self.accounts.first().map_or(self.identifier.as_str(), |account| account.account.as_str())
+    }
+}
+
+/// Refuse to republish onto a consumed topic, which would feed the consumer its own output
+///
+/// Topic patterns (`^...`) cannot be checked up front; messages whose topic is
+/// the republish topic must be skipped as they arrive.
+pub fn check_republish_topic(republish_topic: &str, source_topics: &[String]) -> Result<(), String> {
+    if republish_topic.trim().is_empty() {
+        return Err("The republish topic must not be empty".to_string());
+    }
+    if source_topics.iter().any(|topic| topic == republish_topic) {
+        return Err(format!(
+            "Refusing to republish onto {}, which is being consumed (this would loop)",
+            republish_topic
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! Consumer group members, committed offsets and lag
+//!
+//! Groups are described with the broker's group APIs. Member assignments are
+//! sent in the consumer protocol's binary encoding and decoded here. Committed
+//! offsets are read by a consumer configured with the group's ID that never
+//! joins the group, so describing a group does not trigger a rebalance.
+
+use crate::lag::{fetch_lag, group_positions};
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::error::KafkaResult;
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// A member of a consumer group and the partitions assigned to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupMember {
+    pub id: String,
+    pub client_id: String,
+    pub host: String,
+    /// `(topic, partition)` pairs
+    pub assignment: Vec<(String, i32)>,
+}
+
+/// Committed offset and lag of the group on one partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupPartition {
+    pub topic: String,
+    pub partition: i32,
+    /// Next offset the group will consume, or `None` if it never committed
+    pub committed: Option<i64>,
+    /// High watermark
+    pub log_end: i64,
+    pub lag: i64,
+    /// Member the partition is assigned to
+    pub member: Option<String>,
+}
+
+/// State, members and per-partition lag of a consumer group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupDescription {
+    pub group: String,
+    /// `Stable`, `Empty`, `PreparingRebalance`, ... or `Dead` for an unknown group
+    pub state: String,
+    pub protocol: String,
+    pub members: Vec<GroupMember>,
+    pub partitions: Vec<GroupPartition>,
+    pub total_lag: i64,
+}
+
+/// Decode a consumer protocol member assignment into `(topic, partition)` pairs
+///
+/// The layout is a version (i16), an array of topics each holding a name
+/// (i16-prefixed string) and an array of partitions (i32), then user data.
+pub fn decode_assignment(bytes: &[u8]) -> Option<Vec<(String, i32)>> {
+    let mut reader = Reader { bytes };
+    let _version = reader.i16()?;
+    let mut assignment = Vec::new();
+    for _ in 0..reader.array_len()? {
+        let length = usize::try_from(reader.i16()?).ok()?;
+        let topic = String::from_utf8(reader.take(length)?.to_vec()).ok()?;
+        for _ in 0..reader.array_len()? {
+            assignment.push((topic.clone(), reader.i32()?));
+        }
+    }
+    Some(assignment)
+}
+
+/// Big-endian reader over a byte slice
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    /// Array length; negative lengths (null arrays) count as empty
+    fn array_len(&mut self) -> Option<usize> {
+        Some(usize::try_from(self.i32()?).unwrap_or(0))
+    }
+}
+
+/// Describe `group`: its members, and its committed offsets and lag on `topics`
+///
+/// `consumer` must be configured with the group's ID. Without `topics`, the
+/// topics assigned to the members are described; if nothing is assigned,
+/// every non-internal topic is searched for partitions the group committed to.
+pub fn describe_group<C, T>(consumer: &T, group: &str, topics: &[String], timeout: Duration) -> KafkaResult<GroupDescription>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let groups = consumer.fetch_group_list(Some(group), timeout)?;
+    let info = groups.groups().iter().find(|info| info.name() == group);
+    let members: Vec<GroupMember> = info
+        .map(|info| {
+            info.members()
+                .iter()
+                .map(|member| GroupMember {
+                    id: member.id().to_string(),
+                    client_id: member.client_id().to_string(),
+                    host: member.client_host().to_string(),
+                    assignment: member.assignment().and_then(decode_assignment).unwrap_or_default(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let assigned: BTreeSet<String> = members
+        .iter()
+        .flat_map(|member| member.assignment.iter().map(|(topic, _)| topic.clone()))
+        .collect();
+    let (topics, committed_only) = if !topics.is_empty() {
+        (topics.to_vec(), false)
+    } else if !assigned.is_empty() {
+        (assigned.into_iter().collect(), false)
+    } else {
+        let metadata = consumer.fetch_metadata(None, timeout)?;
+        let all = metadata
+            .topics()
+            .iter()
+            .map(|topic| topic.name().to_string())
+            .filter(|name| !name.starts_with("__"))
+            .collect();
+        (all, true)
+    };
+
+    let mut positions = group_positions(consumer, &topics, timeout)?;
+    if committed_only {
+        // Watermarks are only fetched for partitions the group committed to
+        let mut committed = TopicPartitionList::new();
+        for element in positions.elements() {
+            if let Offset::Offset(offset) = element.offset() {
+                committed.add_partition_offset(element.topic(), element.partition(), Offset::Offset(offset))?;
+            }
+        }
+        positions = committed;
+    }
+    let partitions: Vec<GroupPartition> = fetch_lag(consumer, &positions, timeout)?
+        .into_iter()
+        .map(|lag| GroupPartition {
+            member: members
+                .iter()
+                .find(|member| member.assignment.iter().any(|(t, p)| *t == lag.topic && *p == lag.partition))
+                .map(|member| member.id.clone()),
+            committed: lag.position,
+            log_end: lag.high,
+            lag: lag.lag(),
+            topic: lag.topic,
+            partition: lag.partition,
+        })
+        .collect();
+
+    Ok(GroupDescription {
+        group: group.to_string(),
+        state: info.map_or("Dead", |info| info.state()).to_string(),
+        protocol: info.map_or("", |info| info.protocol()).to_string(),
+        total_lag: partitions.iter().map(|partition| partition.lag).sum(),
+        members,
+        partitions,
+    })
+}
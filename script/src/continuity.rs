@@ -0,0 +1,400 @@
+//! Proof-chain continuity monitoring
+//!
+//! Consecutive proofs of a chain must link up: a proof starts at the slot the
+//! previous one ended at, and its original bank hash is the previous proof's
+//! last bank hash. The monitor remembers the tip of every chain, keyed by the
+//! monitored account set or by identifier prefix, and reports a GAP when the
+//! slots do not line up and a MISMATCH (a fork) when they do but the bank
+//! hashes differ. Tips can be persisted so a restarted consumer keeps context.
+
+use serde::{Deserialize, Serialize};
+use solana_stub_prover_lib::PublicCommitments;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How proofs are grouped into chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainKeying {
+    /// Proofs monitoring the same set of accounts
+    Accounts,
+    /// Proofs whose identifiers share a prefix, ignoring trailing numeric segments
+    /// (`solana-stub-100-200` belongs to `solana-stub`)
+    IdentifierPrefix,
+}
+
+impl ChainKeying {
+    /// Chain a proof belongs to
+    pub fn chain_key(&self, identifier: &str, commitments: &PublicCommitments) -> String {
+        match self {
+            ChainKeying::Accounts => account_set_key(
+                commitments
+                    .monitored_accounts_state
+                    .iter()
+                    .map(|account| bs58::encode(account.account_pubkey).into_string())
+                    .collect(),
+            ),
+            ChainKeying::IdentifierPrefix => identifier_prefix(identifier).to_string(),
+        }
+    }
+}
+
+/// Chain key of a set of base58 account pubkeys, whatever their order
+pub fn account_set_key(mut accounts: Vec<String>) -> String {
+    accounts.sort();
+    accounts.dedup();
+    if accounts.is_empty() {
+        "(no accounts)".to_string()
+    } else {
+        accounts.join(",")
+    }
+}
+
+impl FromStr for ChainKeying {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "accounts" => Ok(ChainKeying::Accounts),
+            "prefix" => Ok(ChainKeying::IdentifierPrefix),
+            _ => Err(format!("Invalid chain keying: {} (expected accounts or prefix)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ChainKeying {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainKeying::Accounts => write!(f, "accounts"),
+            ChainKeying::IdentifierPrefix => write!(f, "prefix"),
+        }
+    }
+}
+
+/// `identifier` without its trailing `-<digits>` segments
+pub fn identifier_prefix(identifier: &str) -> &str {
+    let mut prefix = identifier;
+    while let Some((head, tail)) = prefix.rsplit_once('-') {
+        if tail.is_empty() || !tail.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        prefix = head;
+    }
+    prefix
+}
+
+/// Last proof seen on a chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainTip {
+    pub identifier: String,
+    pub end_slot: u64,
+    /// Hex of the last bank hash
+    pub last_bank_hash: String,
+}
+
+/// A break in a chain, reported for the proof that caused it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ContinuityEvent {
+    /// The proof does not start where the previous proof ended
+    Gap {
+        chain: String,
+        identifier: String,
+        previous_identifier: String,
+        expected_start_slot: u64,
+        start_slot: u64,
+    },
+    /// The proof starts at the right slot but from a different bank hash
+    Mismatch {
+        chain: String,
+        identifier: String,
+        previous_identifier: String,
+        slot: u64,
+        expected_bank_hash: String,
+        original_bank_hash: String,
+    },
+}
+
+impl ContinuityEvent {
+    /// Metric label of the event
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContinuityEvent::Gap { .. } => "gap",
+            ContinuityEvent::Mismatch { .. } => "mismatch",
+        }
+    }
+}
+
+impl fmt::Display for ContinuityEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContinuityEvent::Gap { chain, identifier, previous_identifier, expected_start_slot, start_slot } => write!(
+                f,
+                "GAP on chain {}: {} starts at slot {}, but {} ended at slot {}",
+                chain, identifier, start_slot, previous_identifier, expected_start_slot
+            ),
+            ContinuityEvent::Mismatch {
+                chain,
+                identifier,
+                previous_identifier,
+                slot,
+                expected_bank_hash,
+                original_bank_hash,
+            } => write!(
+                f,
+                "MISMATCH on chain {}: {} starts at slot {} from bank hash 0x{}, but {} ended with 0x{}",
+                chain, identifier, slot, original_bank_hash, previous_identifier, expected_bank_hash
+            ),
+        }
+    }
+}
+
+/// Tracks the tip of every chain and checks new proofs against it
+pub struct ContinuityMonitor {
+    keying: ChainKeying,
+    tips: BTreeMap<String, ChainTip>,
+    state_path: Option<PathBuf>,
+}
+
+impl ContinuityMonitor {
+    /// Monitor without persisted state
+    pub fn new(keying: ChainKeying) -> Self {
+        Self { keying, tips: BTreeMap::new(), state_path: None }
+    }
+
+    /// Monitor persisting its tips to `path`, starting from the tips saved there if any
+    pub fn open(keying: ChainKeying, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let tips = if path.exists() { serde_json::from_slice(&fs::read(path)?)? } else { BTreeMap::new() };
+        Ok(Self { keying, tips, state_path: Some(path.to_path_buf()) })
+    }
+
+    pub fn keying(&self) -> ChainKeying {
+        self.keying
+    }
+
+    /// Number of chains with a known tip
+    pub fn chains(&self) -> usize {
+        self.tips.len()
+    }
+
+    /// Check a proof against its chain's tip and advance the tip
+    ///
+    /// The tip only moves forward: a proof ending before the current tip is
+    /// checked but does not replace it.
+    pub fn observe(&mut self, identifier: &str, commitments: &PublicCommitments) -> Option<ContinuityEvent> {
+        let chain = self.keying.chain_key(identifier, commitments);
+        let original_bank_hash = hex::encode(commitments.original_bank_hash);
+
+        let event = self.tips.get(&chain).and_then(|tip| {
+            if commitments.start_slot != tip.end_slot {
+                Some(ContinuityEvent::Gap {
+                    chain: chain.clone(),
+                    identifier: identifier.to_string(),
+                    previous_identifier: tip.identifier.clone(),
+                    expected_start_slot: tip.end_slot,
+                    start_slot: commitments.start_slot,
+                })
+            } else if original_bank_hash != tip.last_bank_hash {
+                Some(ContinuityEvent::Mismatch {
+                    chain: chain.clone(),
+                    identifier: identifier.to_string(),
+                    previous_identifier: tip.identifier.clone(),
+                    slot: commitments.start_slot,
+                    expected_bank_hash: tip.last_bank_hash.clone(),
+                    original_bank_hash,
+                })
+            } else {
+                None
+            }
+        });
+
+        if self.tips.get(&chain).is_none_or(|tip| commitments.end_slot >= tip.end_slot) {
+            self.tips.insert(
+                chain,
+                ChainTip {
+                    identifier: identifier.to_string(),
+                    end_slot: commitments.end_slot,
+                    last_bank_hash: hex::encode(commitments.last_bank_hash),
+                },
+            );
+        }
+        event
+    }
+
+    /// Write the tips to the state file, if one was given
+    ///
+    /// The file is replaced atomically, so a crash leaves either the old or the new state.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec_pretty(&self.tips)?)?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_stub_prover_lib::{AccountStateCommitment, NOT_AGGREGATED};
+
+    /// Commitments of `start_slot..end_slot` whose bank hashes are the slot numbers' low bytes
+    fn commitments(start_slot: u64, end_slot: u64, accounts: &[[u8; 32]]) -> PublicCommitments {
+        PublicCommitments {
+            start_slot,
+            end_slot,
+            epoch: 0,
+            original_bank_hash: [start_slot as u8; 32],
+            last_bank_hash: [end_slot as u8; 32],
+            account_data_hash: [0; 32],
+            hash_root_valset: [0; 32],
+            total_active_stake: 0,
+            validator_count: 0,
+            monitored_accounts_state: accounts
+                .iter()
+                .map(|&account_pubkey| AccountStateCommitment {
+                    account_pubkey,
+                    last_change_slot: start_slot,
+                    account_data_hash: [0; 32],
+                    lamports: 0,
+                    owner: [0; 32],
+                    executable: false,
+                    rent_epoch: 0,
+                    data_len: 0,
+                    data: Vec::new(),
+                })
+                .collect(),
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        }
+    }
+
+    #[test]
+    fn proofs_that_link_up_raise_no_events() {
+        let mut monitor = ContinuityMonitor::new(ChainKeying::IdentifierPrefix);
+        for start in [100, 110, 120, 130] {
+            let identifier = format!("solana-stub-{}-{}", start, start + 10);
+            assert_eq!(monitor.observe(&identifier, &commitments(start, start + 10, &[])), None);
+        }
+        assert_eq!(monitor.chains(), 1);
+    }
+
+    #[test]
+    fn a_proof_not_starting_at_the_tip_is_a_gap() {
+        let mut monitor = ContinuityMonitor::new(ChainKeying::IdentifierPrefix);
+        monitor.observe("solana-stub-100-110", &commitments(100, 110, &[]));
+        let event = monitor.observe("solana-stub-115-120", &commitments(115, 120, &[])).unwrap();
+        assert_eq!(
+            event,
+            ContinuityEvent::Gap {
+                chain: "solana-stub".to_string(),
+                identifier: "solana-stub-115-120".to_string(),
+                previous_identifier: "solana-stub-100-110".to_string(),
+                expected_start_slot: 110,
+                start_slot: 115,
+            }
+        );
+        assert_eq!(event.label(), "gap");
+        assert_eq!(
+            event.to_string(),
+            "GAP on chain solana-stub: solana-stub-115-120 starts at slot 115, but solana-stub-100-110 ended at slot 110"
+        );
+        // The chain continues from the new tip
+        assert_eq!(monitor.observe("solana-stub-120-130", &commitments(120, 130, &[])), None);
+    }
+
+    #[test]
+    fn a_proof_starting_from_another_bank_hash_is_a_mismatch() {
+        let mut monitor = ContinuityMonitor::new(ChainKeying::IdentifierPrefix);
+        monitor.observe("solana-stub-100-110", &commitments(100, 110, &[]));
+        let forked = PublicCommitments { original_bank_hash: [0xee; 32], ..commitments(110, 120, &[]) };
+        let event = monitor.observe("solana-stub-110-120", &forked).unwrap();
+        match &event {
+            ContinuityEvent::Mismatch { slot, expected_bank_hash, original_bank_hash, .. } => {
+                assert_eq!(*slot, 110);
+                assert_eq!(*expected_bank_hash, hex::encode([110u8; 32]));
+                assert_eq!(*original_bank_hash, "ee".repeat(32));
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+        assert_eq!(event.label(), "mismatch");
+        assert!(event.to_string().starts_with("MISMATCH on chain solana-stub: solana-stub-110-120 starts at slot 110"));
+    }
+
+    #[test]
+    fn a_late_proof_is_checked_without_moving_the_tip_back() {
+        let mut monitor = ContinuityMonitor::new(ChainKeying::IdentifierPrefix);
+        monitor.observe("solana-stub-100-110", &commitments(100, 110, &[]));
+        monitor.observe("solana-stub-110-120", &commitments(110, 120, &[]));
+        assert!(matches!(
+            monitor.observe("solana-stub-100-110", &commitments(100, 110, &[])),
+            Some(ContinuityEvent::Gap { expected_start_slot: 120, .. })
+        ));
+        assert_eq!(monitor.observe("solana-stub-120-130", &commitments(120, 130, &[])), None);
+    }
+
+    #[test]
+    fn chains_are_tracked_separately() {
+        let mut by_prefix = ContinuityMonitor::new(ChainKeying::IdentifierPrefix);
+        by_prefix.observe("mainnet-100-110", &commitments(100, 110, &[]));
+        assert_eq!(by_prefix.observe("devnet-500-510", &commitments(500, 510, &[])), None);
+        assert_eq!(by_prefix.observe("mainnet-110-120", &commitments(110, 120, &[])), None);
+        assert_eq!(by_prefix.chains(), 2);
+
+        // Account sets match whatever their order
+        let mut by_accounts = ContinuityMonitor::new(ChainKeying::Accounts);
+        by_accounts.observe("a", &commitments(100, 110, &[[1; 32], [2; 32]]));
+        assert_eq!(by_accounts.observe("b", &commitments(500, 510, &[[3; 32]])), None);
+        assert_eq!(by_accounts.observe("c", &commitments(110, 120, &[[2; 32], [1; 32]])), None);
+        assert_eq!(by_accounts.chains(), 2);
+    }
+
+    #[test]
+    fn identifier_prefixes_drop_trailing_numbers_only() {
+        assert_eq!(identifier_prefix("solana-stub-100-200"), "solana-stub");
+        assert_eq!(identifier_prefix("solana-stub-v2-100"), "solana-stub-v2");
+        assert_eq!(identifier_prefix("proof-"), "proof-");
+        assert_eq!(identifier_prefix("12345"), "12345");
+        assert_eq!(account_set_key(Vec::new()), "(no accounts)");
+        assert_eq!(account_set_key(vec!["b".into(), "a".into(), "b".into()]), "a,b");
+        assert_eq!("PREFIX".parse::<ChainKeying>().unwrap(), ChainKeying::IdentifierPrefix);
+        assert!("slots".parse::<ChainKeying>().is_err());
+    }
+
+    #[test]
+    fn tips_survive_a_restart_through_the_state_file() {
+        let dir = std::env::temp_dir().join(format!("continuity-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tips.json");
+
+        let mut monitor = ContinuityMonitor::open(ChainKeying::IdentifierPrefix, &path).unwrap();
+        assert_eq!(monitor.chains(), 0);
+        monitor.observe("mainnet-100-110", &commitments(100, 110, &[]));
+        monitor.observe("devnet-500-510", &commitments(500, 510, &[]));
+        monitor.save().unwrap();
+        assert!(!path.with_extension("tmp").exists());
+
+        let mut restarted = ContinuityMonitor::open(ChainKeying::IdentifierPrefix, &path).unwrap();
+        assert_eq!(restarted.chains(), 2);
+        assert_eq!(restarted.observe("mainnet-110-120", &commitments(110, 120, &[])), None);
+        assert!(matches!(
+            restarted.observe("devnet-520-530", &commitments(520, 530, &[])),
+            Some(ContinuityEvent::Gap { expected_start_slot: 510, .. })
+        ));
+
+        // A monitor without a state file saves nothing
+        ContinuityMonitor::new(ChainKeying::Accounts).save().unwrap();
+        fs::write(&path, b"{not json").unwrap();
+        assert!(ContinuityMonitor::open(ChainKeying::IdentifierPrefix, &path).is_err());
+    }
+}
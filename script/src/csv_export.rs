@@ -0,0 +1,243 @@
+//! CSV export of commitment fields
+//!
+//! One row is appended per monitored account of each proof, so a spreadsheet
+//! can sum lamports per account over time. Rows are flushed every
+//! `FLUSH_EVERY_ROWS` rows or `CSV_FLUSH_INTERVAL`, whichever comes first, and
+//! callers flush idle exports on a timer, so a long-running consumer leaves a
+//! usable file behind at any time.
+
+use crate::utils::bytes32_to_base58;
+use solana_stub_prover_lib::PublicCommitments;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Column names written when the file is created
+pub const CSV_HEADER: [&str; 8] = [
+    "identifier",
+    "start_slot",
+    "end_slot",
+    "epoch",
+    "account",
+    "lamports",
+    "data_hash",
+    "validations_passed",
+];
+
+/// Rows written between flushes
+const FLUSH_EVERY_ROWS: usize = 100;
+/// Maximum time rows stay buffered while proofs keep arriving
+pub const CSV_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Appends commitment rows to a CSV file
+pub struct CsvExport {
+    writer: csv::Writer<std::fs::File>,
+    unflushed: usize,
+    last_flush: Instant,
+}
+
+impl CsvExport {
+    /// Open `path` for appending, writing the header if the file is new or empty
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+        if is_empty {
+            writer.write_record(CSV_HEADER)?;
+            writer.flush()?;
+        }
+        Ok(Self { writer, unflushed: 0, last_flush: Instant::now() })
+    }
+
+    /// Append the rows of one proof, returning how many were written
+    ///
+    /// A proof without monitored accounts gets a single row with empty account columns.
+    pub fn append(&mut self, identifier: &str, commitments: &PublicCommitments) -> Result<usize, Box<dyn Error>> {
+        let common = [
+            identifier.to_string(),
+            commitments.start_slot.to_string(),
+            commitments.end_slot.to_string(),
+            commitments.epoch.to_string(),
+        ];
+        let validations_passed = commitments.validations_passed.to_string();
+
+        let mut rows = 0;
+        for account in &commitments.monitored_accounts_state {
+            self.writer.write_record(common.iter().map(String::as_str).chain([
+                bytes32_to_base58(&account.account_pubkey).as_str(),
+                account.lamports.to_string().as_str(),
+                format!("0x{}", hex::encode(account.account_data_hash)).as_str(),
+                validations_passed.as_str(),
+            ]))?;
+            rows += 1;
+        }
+        if rows == 0 {
+            self.writer
+                .write_record(common.iter().map(String::as_str).chain(["", "", "", validations_passed.as_str()]))?;
+            rows = 1;
+        }
+
+        self.unflushed += rows;
+        if self.unflushed >= FLUSH_EVERY_ROWS || self.last_flush.elapsed() >= CSV_FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(rows)
+    }
+
+    /// Number of rows written since the last flush
+    pub fn pending(&self) -> usize {
+        self.unflushed
+    }
+
+    /// Write buffered rows to the file
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_stub_prover_lib::{AccountStateCommitment, NOT_AGGREGATED};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// A path in an empty directory for one test
+    fn test_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv-export-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("proofs.csv")
+    }
+
+    fn commitments(accounts: &[([u8; 32], u64)]) -> PublicCommitments {
+        PublicCommitments {
+            start_slot: 100,
+            end_slot: 200,
+            epoch: 3,
+            original_bank_hash: [0; 32],
+            last_bank_hash: [0; 32],
+            account_data_hash: [0; 32],
+            hash_root_valset: [0; 32],
+            total_active_stake: 0,
+            validator_count: 0,
+            monitored_accounts_state: accounts
+                .iter()
+                .map(|&(account_pubkey, lamports)| AccountStateCommitment {
+                    account_pubkey,
+                    last_change_slot: 150,
+                    account_data_hash: [0xab; 32],
+                    lamports,
+                    owner: [0; 32],
+                    executable: false,
+                    rent_epoch: 0,
+                    data_len: 0,
+                    data: Vec::new(),
+                })
+                .collect(),
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        }
+    }
+
+    /// Every record of the file, header included
+    fn records(path: &Path) -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(path)
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap().iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_row_per_monitored_account() {
+        let path = test_path("rows");
+        let mut export = CsvExport::open(&path).unwrap();
+        assert_eq!(export.append("proof-1", &commitments(&[([1; 32], 10), ([2; 32], 20)])).unwrap(), 2);
+        export.flush().unwrap();
+
+        let records = records(&path);
+        assert_eq!(records[0], CSV_HEADER);
+        assert_eq!(
+            records[1],
+            [
+                "proof-1",
+                "100",
+                "200",
+                "3",
+                &bytes32_to_base58(&[1; 32]),
+                "10",
+                &format!("0x{}", "ab".repeat(32)),
+                "true"
+            ]
+        );
+        assert_eq!(records[2][5], "20");
+        assert_eq!(records.len(), 3);
+    }
+
+    #[test]
+    fn a_proof_without_accounts_gets_one_row_with_empty_account_columns() {
+        let path = test_path("no-accounts");
+        let mut export = CsvExport::open(&path).unwrap();
+        assert_eq!(export.append("proof-1", &commitments(&[])).unwrap(), 1);
+        export.flush().unwrap();
+        assert_eq!(records(&path)[1], ["proof-1", "100", "200", "3", "", "", "", "true"]);
+    }
+
+    #[test]
+    fn identifiers_with_commas_quotes_and_newlines_round_trip() {
+        let path = test_path("escaping");
+        let identifiers = ["a,b", "say \"hi\"", "line\nbreak", "crlf\r\nend", " padded ", ""];
+        let mut export = CsvExport::open(&path).unwrap();
+        for identifier in identifiers {
+            export.append(identifier, &commitments(&[([1; 32], 10)])).unwrap();
+        }
+        export.flush().unwrap();
+
+        let read_back: Vec<String> = records(&path)[1..].iter().map(|record| record[0].clone()).collect();
+        assert_eq!(read_back, identifiers);
+        assert!(fs::read_to_string(&path).unwrap().contains("\"say \"\"hi\"\"\""));
+    }
+
+    #[test]
+    fn appending_to_an_existing_file_does_not_repeat_the_header() {
+        let path = test_path("append");
+        for identifier in ["proof-1", "proof-2"] {
+            let mut export = CsvExport::open(&path).unwrap();
+            export.append(identifier, &commitments(&[([1; 32], 10)])).unwrap();
+            export.flush().unwrap();
+        }
+
+        let records = records(&path);
+        assert_eq!(records.iter().filter(|record| **record == CSV_HEADER).count(), 1);
+        let identifiers: Vec<&str> = records[1..].iter().map(|record| record[0].as_str()).collect();
+        assert_eq!(identifiers, ["proof-1", "proof-2"]);
+    }
+
+    #[test]
+    fn an_empty_existing_file_gets_the_header() {
+        let path = test_path("empty");
+        fs::write(&path, b"").unwrap();
+        drop(CsvExport::open(&path).unwrap());
+        assert_eq!(records(&path), [CSV_HEADER]);
+    }
+
+    #[test]
+    fn rows_are_flushed_every_hundred_rows() {
+        let path = test_path("flush");
+        let mut export = CsvExport::open(&path).unwrap();
+        for _ in 0..FLUSH_EVERY_ROWS - 1 {
+            export.append("proof", &commitments(&[([1; 32], 10)])).unwrap();
+        }
+        assert_eq!(export.pending(), FLUSH_EVERY_ROWS - 1);
+        export.append("proof", &commitments(&[([1; 32], 10)])).unwrap();
+        assert_eq!(export.pending(), 0);
+        assert_eq!(records(&path).len(), FLUSH_EVERY_ROWS + 1);
+    }
+}
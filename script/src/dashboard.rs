@@ -0,0 +1,367 @@
+//! State of the live proof dashboard
+//!
+//! The `dashboard` binary turns every consumed message into a
+//! [`DashboardEvent`] and applies it to a [`Dashboard`], which keeps what the
+//! panels show: the most recent proofs, the latest state of every monitored
+//! account, the tip and breaks of every chain (see [`crate::continuity`]), the
+//! consumer lag, and alerts for failed validations, failed verifications,
+//! chain breaks and undecodable messages. Selection and the detail view live
+//! here too, so the terminal code only draws the state and forwards keys.
+
+use crate::continuity::{ContinuityEvent, ContinuityMonitor};
+use crate::lag::PartitionLag;
+use crate::public_values::fields;
+use crate::utils::bytes32_to_base58;
+use crate::verify::VerifyStatus;
+use chrono::{DateTime, Utc};
+use solana_stub_prover_lib::PublicCommitments;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+/// Proofs kept in the recent proofs table
+pub const MAX_PROOFS: usize = 200;
+/// Alerts kept in the alerts pane
+pub const MAX_ALERTS: usize = 100;
+
+/// A consumed proof
+#[derive(Debug, Clone)]
+pub struct ProofRow {
+    pub identifier: String,
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    /// Payload size in bytes
+    pub size: usize,
+    pub kind: &'static str,
+    pub received_at: DateTime<Utc>,
+    /// `None` when no verifying key was given
+    pub verification: Option<VerifyStatus>,
+    /// `None` when the public values are not `PublicCommitments`
+    pub commitments: Option<PublicCommitments>,
+}
+
+impl ProofRow {
+    /// Proven slot range, `?` if the public values could not be decoded
+    pub fn slots(&self) -> String {
+        match &self.commitments {
+            Some(c) => format!("{}..{}", c.start_slot, c.end_slot),
+            None => "?".to_string(),
+        }
+    }
+
+    /// Verification outcome as shown in the table
+    pub fn verified(&self) -> &'static str {
+        match &self.verification {
+            None => "-",
+            Some(VerifyStatus::Verified) => "yes",
+            Some(VerifyStatus::Failed(_)) => "FAILED",
+        }
+    }
+}
+
+/// Latest committed state of a monitored account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountState {
+    /// Base58 pubkey
+    pub pubkey: String,
+    pub lamports: u64,
+    pub last_change_slot: u64,
+    /// Base58 pubkey of the owner
+    pub owner: String,
+    pub data_len: usize,
+    /// Proof the state was committed by, and the slot its range ends at
+    pub identifier: String,
+    pub end_slot: u64,
+}
+
+/// Continuity of one chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainStatus {
+    pub chain: String,
+    /// Proof at the tip and the slot it ends at
+    pub tip: String,
+    pub end_slot: u64,
+    pub proofs: u64,
+    pub breaks: u64,
+    pub last_break: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// The program reported failed validations
+    Validation,
+    Verification,
+    /// A GAP or MISMATCH in a chain
+    Continuity,
+    Undecodable,
+    /// Reading from Kafka failed
+    Consumer,
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertKind::Validation => write!(f, "validation"),
+            AlertKind::Verification => write!(f, "verification"),
+            AlertKind::Continuity => write!(f, "continuity"),
+            AlertKind::Undecodable => write!(f, "undecodable"),
+            AlertKind::Consumer => write!(f, "consumer"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub at: DateTime<Utc>,
+    pub kind: AlertKind,
+    pub message: String,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}] {}", self.at.format("%H:%M:%S"), self.kind, self.message)
+    }
+}
+
+/// Something the dashboard learned from the topic
+#[derive(Debug, Clone)]
+pub enum DashboardEvent {
+    Proof(ProofRow),
+    /// A message that is not a proof envelope
+    Undecodable {
+        topic: String,
+        partition: i32,
+        offset: i64,
+        reason: String,
+    },
+    /// Lag of every assigned partition
+    Lag(Vec<PartitionLag>),
+    /// Reading from Kafka failed
+    ConsumerError(String),
+}
+
+/// What the panels show, and the selected proof
+pub struct Dashboard {
+    monitor: ContinuityMonitor,
+    /// Newest first
+    proofs: VecDeque<ProofRow>,
+    accounts: BTreeMap<String, AccountState>,
+    chains: BTreeMap<String, ChainStatus>,
+    lag: Vec<PartitionLag>,
+    /// Newest first
+    alerts: VecDeque<Alert>,
+    received: u64,
+    selected: usize,
+    detail: bool,
+}
+
+impl Dashboard {
+    pub fn new(monitor: ContinuityMonitor) -> Self {
+        Self {
+            monitor,
+            proofs: VecDeque::new(),
+            accounts: BTreeMap::new(),
+            chains: BTreeMap::new(),
+            lag: Vec::new(),
+            alerts: VecDeque::new(),
+            received: 0,
+            selected: 0,
+            detail: false,
+        }
+    }
+
+    /// Update the state with an event, returning the alerts it raised
+    pub fn apply(&mut self, event: DashboardEvent) -> Vec<Alert> {
+        let alerts = match event {
+            DashboardEvent::Proof(row) => self.apply_proof(row),
+            DashboardEvent::Undecodable { topic, partition, offset, reason } => {
+                self.received += 1;
+                let message = format!("{}[{}]@{}: {}", topic, partition, offset, reason);
+                vec![alert(AlertKind::Undecodable, message)]
+            }
+            DashboardEvent::Lag(lag) => {
+                self.lag = lag;
+                Vec::new()
+            }
+            DashboardEvent::ConsumerError(reason) => vec![alert(AlertKind::Consumer, reason)],
+        };
+        for alert in &alerts {
+            self.alerts.push_front(alert.clone());
+        }
+        self.alerts.truncate(MAX_ALERTS);
+        alerts
+    }
+
+    fn apply_proof(&mut self, row: ProofRow) -> Vec<Alert> {
+        self.received += 1;
+        let mut alerts = Vec::new();
+        if let Some(VerifyStatus::Failed(reason)) = &row.verification {
+            alerts.push(alert(AlertKind::Verification, format!("{}: {}", row.identifier, reason)));
+        }
+        if let Some(commitments) = &row.commitments {
+            if !commitments.validations_passed {
+                let message = format!("{}: the program reported failed validations", row.identifier);
+                alerts.push(alert(AlertKind::Validation, message));
+            }
+            if let Some(event) = self.observe_chain(&row.identifier, commitments) {
+                alerts.push(alert(AlertKind::Continuity, event.to_string()));
+            }
+            self.observe_accounts(&row.identifier, commitments);
+        }
+
+        // Keep the selected proof selected unless the newest one is followed
+        if self.detail || self.selected > 0 {
+            self.selected += 1;
+        }
+        self.proofs.push_front(row);
+        self.proofs.truncate(MAX_PROOFS);
+        self.selected = self.selected.min(self.proofs.len() - 1);
+        alerts
+    }
+
+    fn observe_chain(&mut self, identifier: &str, commitments: &PublicCommitments) -> Option<ContinuityEvent> {
+        let chain = self.monitor.keying().chain_key(identifier, commitments);
+        let event = self.monitor.observe(identifier, commitments);
+        let status = self.chains.entry(chain.clone()).or_insert_with(|| ChainStatus {
+            chain,
+            tip: identifier.to_string(),
+            end_slot: commitments.end_slot,
+            proofs: 0,
+            breaks: 0,
+            last_break: None,
+        });
+        status.proofs += 1;
+        // Like the monitor's tip, the shown tip only moves forward
+        if commitments.end_slot >= status.end_slot {
+            status.tip = identifier.to_string();
+            status.end_slot = commitments.end_slot;
+        }
+        if let Some(event) = &event {
+            status.breaks += 1;
+            status.last_break = Some(format!("{} at {}", event.label().to_uppercase(), identifier));
+        }
+        event
+    }
+
+    fn observe_accounts(&mut self, identifier: &str, commitments: &PublicCommitments) {
+        for account in &commitments.monitored_accounts_state {
+            let pubkey = bytes32_to_base58(&account.account_pubkey);
+            // A proof of an older range must not replace a newer state
+            if self.accounts.get(&pubkey).is_some_and(|state| state.end_slot > commitments.end_slot) {
+                continue;
+            }
+            let state = AccountState {
+                pubkey: pubkey.clone(),
+                lamports: account.lamports,
+                last_change_slot: account.last_change_slot,
+                owner: bytes32_to_base58(&account.owner),
+                data_len: account.data_len as usize,
+                identifier: identifier.to_string(),
+                end_slot: commitments.end_slot,
+            };
+            self.accounts.insert(pubkey, state);
+        }
+    }
+
+    /// Recent proofs, newest first
+    pub fn proofs(&self) -> impl Iterator<Item = &ProofRow> {
+        self.proofs.iter()
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountState> {
+        self.accounts.values()
+    }
+
+    pub fn chains(&self) -> impl Iterator<Item = &ChainStatus> {
+        self.chains.values()
+    }
+
+    pub fn lag(&self) -> &[PartitionLag] {
+        &self.lag
+    }
+
+    /// Lag summed over every assigned partition
+    pub fn total_lag(&self) -> i64 {
+        self.lag.iter().map(PartitionLag::lag).sum()
+    }
+
+    /// Alerts, newest first
+    pub fn alerts(&self) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter()
+    }
+
+    /// Messages received, including undecodable ones
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Index of the selected proof in [`Dashboard::proofs`]
+    pub fn selected_index(&self) -> Option<usize> {
+        (!self.proofs.is_empty()).then_some(self.selected)
+    }
+
+    pub fn selected(&self) -> Option<&ProofRow> {
+        self.proofs.get(self.selected)
+    }
+
+    /// Select the next older proof
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.proofs.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Select the next newer proof
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Select the newest proof
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    /// Select the oldest proof
+    pub fn select_last(&mut self) {
+        self.selected = self.proofs.len().saturating_sub(1);
+    }
+
+    /// Open or close the detail view of the selected proof
+    pub fn toggle_detail(&mut self) {
+        self.detail = !self.detail && !self.proofs.is_empty();
+    }
+
+    pub fn close_detail(&mut self) {
+        self.detail = false;
+    }
+
+    pub fn showing_detail(&self) -> bool {
+        self.detail
+    }
+
+    /// Every field of the selected proof, its commitments in full
+    pub fn detail(&self) -> Option<Vec<(String, String)>> {
+        let row = self.selected()?;
+        let verification = match &row.verification {
+            None => "not verified (no --vkey or --vkey-hash)".to_string(),
+            Some(status) => status.to_string(),
+        };
+        let mut lines = vec![
+            ("identifier".to_string(), row.identifier.clone()),
+            ("message".to_string(), format!("{}[{}]@{}", row.topic, row.partition, row.offset)),
+            ("received_at".to_string(), row.received_at.to_rfc3339()),
+            ("kind".to_string(), row.kind.to_string()),
+            ("size".to_string(), format!("{} bytes", row.size)),
+            ("verification".to_string(), verification),
+        ];
+        match &row.commitments {
+            Some(commitments) => lines.extend(fields(commitments)),
+            None => lines.push(("public_values".to_string(), "not PublicCommitments".to_string())),
+        }
+        Some(lines)
+    }
+}
+
+fn alert(kind: AlertKind, message: String) -> Alert {
+    Alert { at: Utc::now(), kind, message }
+}
@@ -0,0 +1,264 @@
+//! Detection of repeated proofs within a window of recent messages
+//!
+//! Producer retries and reproofs can deliver the same logical proof more than
+//! once. The window remembers the most recently seen identifiers together with
+//! a digest of their public commitments: a repeated identifier with the same
+//! digest, or a known digest under a new identifier, is a duplicate; a repeated
+//! identifier with a different digest is a conflict.
+
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use twine_types::proofs::{ProofData, ZkProof};
+
+/// SHA-256 of a proof's public values (its encoded commitments)
+pub fn commitment_digest(proof: &ZkProof) -> [u8; 32] {
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    Sha256::digest(&sp1_proof.public_value).into()
+}
+
+/// How a proof relates to the ones already in the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DedupVerdict {
+    /// Neither the identifier nor the commitments were seen recently
+    New,
+    /// Same commitments as a recent proof, first seen under `first_identifier`
+    Duplicate { first_identifier: String },
+    /// Same identifier as a recent proof but different commitments
+    Conflict { previous_digest: [u8; 32] },
+}
+
+impl DedupVerdict {
+    /// Metric label, or `None` for new proofs
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            DedupVerdict::New => None,
+            DedupVerdict::Duplicate { .. } => Some("duplicate"),
+            DedupVerdict::Conflict { .. } => Some("conflict"),
+        }
+    }
+}
+
+impl fmt::Display for DedupVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DedupVerdict::New => write!(f, "NEW"),
+            DedupVerdict::Duplicate { first_identifier } => write!(f, "DUPLICATE of {}", first_identifier),
+            DedupVerdict::Conflict { previous_digest } => {
+                write!(f, "CONFLICT (previous commitments digest 0x{})", hex::encode(previous_digest))
+            }
+        }
+    }
+}
+
+struct Entry {
+    digest: [u8; 32],
+    /// Clock value of the last time the entry was seen
+    touched: u64,
+}
+
+/// Least-recently-used window of proof identifiers and commitment digests
+pub struct DedupWindow {
+    capacity: usize,
+    clock: u64,
+    by_identifier: HashMap<String, Entry>,
+    by_digest: HashMap<[u8; 32], String>,
+    /// Identifiers in the order they were touched; entries older than the
+    /// identifier's current `touched` value are stale and skipped
+    order: VecDeque<(u64, String)>,
+}
+
+impl DedupWindow {
+    /// Window remembering up to `capacity` identifiers (at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            clock: 0,
+            by_identifier: HashMap::new(),
+            by_digest: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Number of identifiers in the window
+    pub fn len(&self) -> usize {
+        self.by_identifier.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_identifier.is_empty()
+    }
+
+    /// Classify a proof and record it in the window
+    pub fn check(&mut self, identifier: &str, digest: [u8; 32]) -> DedupVerdict {
+        if let Some(entry) = self.by_identifier.get(identifier) {
+            let previous_digest = entry.digest;
+            if previous_digest == digest {
+                self.touch(identifier);
+                return DedupVerdict::Duplicate { first_identifier: identifier.to_string() };
+            }
+            // Later proofs are compared against the latest content
+            if self.by_digest.get(&previous_digest).is_some_and(|owner| owner == identifier) {
+                self.by_digest.remove(&previous_digest);
+            }
+            self.by_digest.entry(digest).or_insert_with(|| identifier.to_string());
+            if let Some(entry) = self.by_identifier.get_mut(identifier) {
+                entry.digest = digest;
+            }
+            self.touch(identifier);
+            return DedupVerdict::Conflict { previous_digest };
+        }
+
+        if let Some(first_identifier) = self.by_digest.get(&digest).cloned() {
+            self.touch(&first_identifier);
+            return DedupVerdict::Duplicate { first_identifier };
+        }
+
+        self.by_identifier.insert(identifier.to_string(), Entry { digest, touched: 0 });
+        self.by_digest.insert(digest, identifier.to_string());
+        self.touch(identifier);
+        self.evict();
+        DedupVerdict::New
+    }
+
+    fn touch(&mut self, identifier: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.by_identifier.get_mut(identifier) {
+            entry.touched = self.clock;
+            self.order.push_back((self.clock, identifier.to_string()));
+        }
+        // Drop stale positions once they dominate the queue
+        if self.order.len() > self.capacity * 2 {
+            let by_identifier = &self.by_identifier;
+            self.order
+                .retain(|(touched, identifier)| by_identifier.get(identifier).is_some_and(|e| e.touched == *touched));
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.by_identifier.len() > self.capacity {
+            let Some((touched, identifier)) = self.order.pop_front() else {
+                break;
+            };
+            if self.by_identifier.get(&identifier).is_some_and(|entry| entry.touched == touched) {
+                if let Some(entry) = self.by_identifier.remove(&identifier) {
+                    if self.by_digest.get(&entry.digest).is_some_and(|owner| *owner == identifier) {
+                        self.by_digest.remove(&entry.digest);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    fn duplicate_of(identifier: &str) -> DedupVerdict {
+        DedupVerdict::Duplicate { first_identifier: identifier.to_string() }
+    }
+
+    #[test]
+    fn unseen_proofs_are_new() {
+        let mut window = DedupWindow::new(4);
+        assert!(window.is_empty());
+        assert_eq!(window.check("a", digest(1)), DedupVerdict::New);
+        assert_eq!(window.check("b", digest(2)), DedupVerdict::New);
+        assert_eq!(window.len(), 2);
+        assert_eq!(DedupVerdict::New.label(), None);
+        assert_eq!(DedupVerdict::New.to_string(), "NEW");
+    }
+
+    #[test]
+    fn the_same_identifier_and_digest_is_a_duplicate() {
+        let mut window = DedupWindow::new(4);
+        window.check("a", digest(1));
+        let verdict = window.check("a", digest(1));
+        assert_eq!(verdict, duplicate_of("a"));
+        assert_eq!(verdict.label(), Some("duplicate"));
+        assert_eq!(verdict.to_string(), "DUPLICATE of a");
+        assert_eq!(window.len(), 1);
+    }
+
+    #[test]
+    fn known_commitments_under_a_new_identifier_are_a_duplicate_of_the_first() {
+        let mut window = DedupWindow::new(4);
+        window.check("a", digest(1));
+        assert_eq!(window.check("a-retry", digest(1)), duplicate_of("a"));
+        assert_eq!(window.check("a-retry", digest(1)), duplicate_of("a"));
+        // The alias is not remembered as an identifier of its own
+        assert_eq!(window.len(), 1);
+    }
+
+    #[test]
+    fn the_same_identifier_with_a_different_digest_is_a_conflict() {
+        let mut window = DedupWindow::new(4);
+        window.check("a", digest(1));
+        let verdict = window.check("a", digest(2));
+        assert_eq!(verdict, DedupVerdict::Conflict { previous_digest: digest(1) });
+        assert_eq!(verdict.label(), Some("conflict"));
+        assert_eq!(verdict.to_string(), format!("CONFLICT (previous commitments digest 0x{})", "01".repeat(32)));
+
+        // Later proofs are compared against the latest content
+        assert_eq!(window.check("a", digest(2)), duplicate_of("a"));
+        assert_eq!(window.check("a", digest(1)), DedupVerdict::Conflict { previous_digest: digest(2) });
+        assert_eq!(window.check("b", digest(2)), DedupVerdict::New);
+    }
+
+    #[test]
+    fn the_least_recently_seen_identifier_is_evicted_at_capacity() {
+        let mut window = DedupWindow::new(2);
+        window.check("a", digest(1));
+        window.check("b", digest(2));
+        // Seeing `a` again makes `b` the oldest
+        window.check("a", digest(1));
+        assert_eq!(window.check("c", digest(3)), DedupVerdict::New);
+        assert_eq!(window.len(), 2);
+
+        // `a` is still known, `b` and its digest are forgotten
+        assert_eq!(window.check("a", digest(1)), duplicate_of("a"));
+        assert_eq!(window.check("b-retry", digest(2)), DedupVerdict::New);
+        assert_eq!(window.len(), 2);
+        // ...which in turn evicted `c`, now the least recently seen
+        assert_eq!(window.check("c", digest(3)), DedupVerdict::New);
+    }
+
+    #[test]
+    fn a_duplicate_under_an_alias_keeps_the_first_identifier_alive() {
+        let mut window = DedupWindow::new(2);
+        window.check("a", digest(1));
+        window.check("b", digest(2));
+        window.check("a-retry", digest(1));
+        window.check("c", digest(3));
+        assert_eq!(window.check("a", digest(1)), duplicate_of("a"));
+        assert_eq!(window.check("b", digest(2)), DedupVerdict::New);
+    }
+
+    #[test]
+    fn stays_within_capacity_over_many_repeats() {
+        let mut window = DedupWindow::new(3);
+        for round in 0..100u8 {
+            window.check("hot", digest(0));
+            assert_eq!(window.check(&format!("cold-{}", round), digest(round + 1)), DedupVerdict::New);
+            assert!(window.len() <= 3);
+        }
+        assert_eq!(window.check("hot", digest(0)), duplicate_of("hot"));
+        assert_eq!(window.check("cold-99", digest(100)), duplicate_of("cold-99"));
+        assert_eq!(window.check("cold-0", digest(1)), DedupVerdict::New);
+    }
+
+    #[test]
+    fn a_zero_capacity_still_remembers_the_last_proof() {
+        let mut window = DedupWindow::new(0);
+        window.check("a", digest(1));
+        assert_eq!(window.check("a", digest(1)), duplicate_of("a"));
+        window.check("b", digest(2));
+        assert_eq!(window.len(), 1);
+        assert_eq!(window.check("a", digest(1)), DedupVerdict::New);
+    }
+}
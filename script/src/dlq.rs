@@ -0,0 +1,228 @@
+//! Dead-letter handling for proofs that could not be published
+//!
+//! Failed messages go to a DLQ topic with headers describing the failure; if
+//! that fails too they are spooled to a local directory, one JSON file per
+//! message, and can be replayed later.
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default directory for spooled dead letters
+pub const DEFAULT_DLQ_DIR: &str = "dlq";
+
+/// Header with the short failure class (`retryable`, `fatal`, `oversized`, ...)
+pub const DLQ_REASON_HEADER: &str = "x-dlq-reason";
+/// Header with the full error message
+pub const DLQ_ERROR_HEADER: &str = "x-dlq-error";
+/// Header with the topic the message was meant for
+pub const DLQ_ORIGINAL_TOPIC_HEADER: &str = "x-dlq-original-topic";
+/// Header with the RFC 3339 time of the failure
+pub const DLQ_FAILED_AT_HEADER: &str = "x-dlq-failed-at";
+/// Header with the number of publish attempts made before giving up
+pub const DLQ_ATTEMPTS_HEADER: &str = "x-dlq-attempts";
+
+/// A message that could not be published, as stored in the spool directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub original_topic: String,
+    pub key: String,
+    pub content_type: String,
+    pub reason: String,
+    pub error: String,
+    pub failed_at: String,
+    /// Publish attempts made; 0 when the message was rejected before sending
+    #[serde(default)]
+    pub attempts: u32,
+    /// Base64-encoded message payload
+    pub payload: String,
+}
+
+impl DeadLetter {
+    pub fn new(
+        original_topic: &str,
+        key: &str,
+        content_type: &str,
+        payload: &[u8],
+        reason: &str,
+        error: &str,
+        attempts: u32,
+    ) -> Self {
+        Self {
+            original_topic: original_topic.to_string(),
+            key: key.to_string(),
+            content_type: content_type.to_string(),
+            reason: reason.to_string(),
+            error: error.to_string(),
+            failed_at: Utc::now().to_rfc3339(),
+            attempts,
+            payload: general_purpose::STANDARD.encode(payload),
+        }
+    }
+
+    /// Headers describing the failure, sent with the message to the DLQ topic
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (DLQ_REASON_HEADER, self.reason.clone()),
+            (DLQ_ERROR_HEADER, self.error.clone()),
+            (DLQ_ORIGINAL_TOPIC_HEADER, self.original_topic.clone()),
+            (DLQ_FAILED_AT_HEADER, self.failed_at.clone()),
+            (DLQ_ATTEMPTS_HEADER, self.attempts.to_string()),
+        ]
+    }
+
+    /// Decoded message payload
+    pub fn payload_bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        general_purpose::STANDARD.decode(&self.payload)
+    }
+}
+
+/// Write a dead letter to the spool directory and return the file path
+///
+/// File names start with a nanosecond timestamp so that a directory listing
+/// sorts in failure order.
+pub fn spool(dir: &Path, letter: &DeadLetter) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+
+    let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let safe_key: String = letter
+        .key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("{:020}-{}.json", timestamp, safe_key));
+    let tmp_path = path.with_extension("tmp");
+
+    fs::write(&tmp_path, serde_json::to_vec_pretty(letter)?)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(path)
+}
+
+/// List spooled dead letters in failure order
+pub fn spooled(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Read a spooled dead letter
+pub fn read(path: &Path) -> Result<DeadLetter, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for the dead letters of one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dlq-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn letter(key: &str, payload: &[u8]) -> DeadLetter {
+        DeadLetter::new(
+            "proofs.mainnet",
+            key,
+            "application/json",
+            payload,
+            "retryable",
+            "Kafka publish failed after retries: Message production error: MessageTimedOut",
+            5,
+        )
+    }
+
+    fn header<'a>(headers: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn headers_describe_the_failure() {
+        let letter = letter("proof-1", b"{}");
+        let headers = letter.headers();
+        assert_eq!(header(&headers, DLQ_REASON_HEADER), Some("retryable"));
+        assert_eq!(header(&headers, DLQ_ORIGINAL_TOPIC_HEADER), Some("proofs.mainnet"));
+        assert_eq!(header(&headers, DLQ_ATTEMPTS_HEADER), Some("5"));
+        assert_eq!(header(&headers, DLQ_ERROR_HEADER), Some(letter.error.as_str()));
+        let failed_at = header(&headers, DLQ_FAILED_AT_HEADER).unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(failed_at).is_ok(), "{}", failed_at);
+        assert_eq!(headers.len(), 5);
+    }
+
+    #[test]
+    fn a_proof_rejected_before_sending_reports_no_attempts() {
+        let letter = DeadLetter::new("proofs", "proof-1", "application/json", b"{}", "oversized", "too large", 0);
+        assert_eq!(header(&letter.headers(), DLQ_REASON_HEADER), Some("oversized"));
+        assert_eq!(header(&letter.headers(), DLQ_ATTEMPTS_HEADER), Some("0"));
+    }
+
+    #[test]
+    fn spooled_letters_decode_for_replay() {
+        let dir = test_dir("replay");
+        let payload = [0u8, 159, 146, 150, 255];
+        let path = spool(&dir, &letter("proof-1", &payload)).unwrap();
+
+        let read_back = read(&path).unwrap();
+        assert_eq!(read_back.original_topic, "proofs.mainnet");
+        assert_eq!(read_back.key, "proof-1");
+        assert_eq!(read_back.content_type, "application/json");
+        assert_eq!(read_back.reason, "retryable");
+        assert_eq!(read_back.attempts, 5);
+        assert_eq!(read_back.payload_bytes().unwrap(), payload);
+    }
+
+    #[test]
+    fn letters_spooled_before_attempts_were_recorded_still_decode() {
+        let dir = test_dir("legacy");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("00000000000000000001-proof-1.json");
+        let mut json = serde_json::to_value(letter("proof-1", b"{}")).unwrap();
+        json.as_object_mut().unwrap().remove("attempts");
+        fs::write(&path, json.to_string()).unwrap();
+
+        let read_back = read(&path).unwrap();
+        assert_eq!(read_back.attempts, 0);
+        assert_eq!(read_back.payload_bytes().unwrap(), b"{}");
+    }
+
+    #[test]
+    fn replay_order_is_failure_order() {
+        let dir = test_dir("order");
+        assert!(spooled(&dir).unwrap().is_empty());
+
+        let keys = ["proof-b", "proof-a", "proof/c"];
+        for key in keys {
+            spool(&dir, &letter(key, key.as_bytes())).unwrap();
+        }
+        // Unfinished writes and other files are not replayed
+        fs::write(dir.join("00000000000000000000-partial.tmp"), b"{").unwrap();
+
+        let replayed: Vec<String> = spooled(&dir).unwrap().iter().map(|path| read(path).unwrap().key).collect();
+        assert_eq!(replayed, keys);
+        // Keys are sanitized for the file name but kept intact in the letter
+        assert!(spooled(&dir).unwrap()[2].to_string_lossy().ends_with("-proof_c.json"));
+    }
+
+    #[test]
+    fn a_corrupt_letter_fails_to_decode() {
+        let dir = test_dir("corrupt");
+        let path = spool(&dir, &letter("proof-1", b"{}")).unwrap();
+        fs::write(&path, b"{\"original_topic\":").unwrap();
+        assert!(read(&path).is_err());
+
+        let mut bad_payload = letter("proof-1", b"{}");
+        bad_payload.payload = "***".to_string();
+        assert!(bad_payload.payload_bytes().is_err());
+    }
+}
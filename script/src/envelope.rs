@@ -0,0 +1,395 @@
+//! Tolerant decoding of JSON proof envelopes
+//!
+//! Besides the typed `ZkProof` envelope, older producers wrote an ad-hoc JSON
+//! shape with a `kind` string and hex-encoded proof fields:
+//!
+//! ```json
+//! {"identifier": "...", "kind": "SolanaConsensusProof",
+//!  "proof_data": {"type": "SP1", "version": 2, "proof": "<hex>", "public_values": "<hex>"}}
+//! ```
+//!
+//! Some of them wrote `proof` as the nested JSON of the SP1 proof instead of
+//! hex; its JSON text is kept as the proof bytes (see [`crate::wrapped_proof`]).
+//!
+//! Both shapes are normalized into a `DecodedProof`; anything else is reported
+//! as an unrecognized envelope with its top-level keys instead of a serde error.
+//! [`decode_payload`] decodes a Kafka payload in any of the payload formats.
+//!
+//! A JSON envelope of either shape may carry a top-level [`METADATA_FIELD`]
+//! object next to the proof, e.g. the raw account data the public values
+//! leave out ([`crate::account_data`]). Binary payloads have no room for it.
+
+use crate::kafka::PayloadFormat;
+use crate::schema_registry::{decode_proof, decode_wire_format, SchemaRegistryClient};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt;
+use twine_types::proofs::{ProofData, ProofKind, SP1Proof, ZkProof};
+
+/// Top-level field of a JSON envelope holding its metadata
+pub const METADATA_FIELD: &str = "metadata";
+
+/// Envelope shape a proof was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeShape {
+    /// Typed `ZkProof` (JSON, bincode or Avro)
+    Typed,
+    /// Ad-hoc JSON with a `kind` string and hex-encoded fields
+    Legacy,
+}
+
+impl fmt::Display for EnvelopeShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeShape::Typed => write!(f, "typed"),
+            EnvelopeShape::Legacy => write!(f, "legacy"),
+        }
+    }
+}
+
+/// A consumed proof, normalized from whichever envelope it arrived in
+#[derive(Debug, Clone)]
+pub struct DecodedProof {
+    /// Normalized proof used by filters, the verifier and the archive
+    pub proof: ZkProof,
+    /// Proof kind as sent by the producer
+    pub kind: String,
+    pub shape: EnvelopeShape,
+    /// The envelope's [`METADATA_FIELD`], if it had one
+    pub metadata: Option<Value>,
+}
+
+impl DecodedProof {
+    pub fn typed(proof: ZkProof) -> Self {
+        let kind = format!("{:?}", proof.proof_kind);
+        Self { proof, kind, shape: EnvelopeShape::Typed, metadata: None }
+    }
+}
+
+/// A typed JSON envelope with its [`METADATA_FIELD`], as the producer publishes it
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EnvelopeWithMetadata<'a> {
+    #[serde(flatten)]
+    pub proof: &'a ZkProof,
+    pub metadata: &'a Value,
+}
+
+/// Just the metadata of a JSON envelope; the proof is skipped without being allocated
+#[derive(Deserialize)]
+struct EnvelopeMetadata {
+    metadata: Option<Value>,
+}
+
+/// Error decoding a JSON envelope
+#[derive(Debug)]
+pub enum EnvelopeError {
+    /// The payload is not JSON at all
+    InvalidJson(serde_json::Error),
+    /// The payload is JSON but matches neither envelope shape
+    UnknownShape { keys: Vec<String>, size: usize },
+    /// The payload has the legacy shape but a field is missing or invalid
+    InvalidLegacy(String),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::InvalidJson(e) => write!(f, "Payload is not valid JSON: {}", e),
+            EnvelopeError::UnknownShape { keys, size } if keys.is_empty() => {
+                write!(f, "Unrecognized JSON envelope (not an object, {} bytes)", size)
+            }
+            EnvelopeError::UnknownShape { keys, size } => write!(
+                f,
+                "Unrecognized JSON envelope (top-level keys: {}; {} bytes)",
+                keys.join(", "),
+                size
+            ),
+            EnvelopeError::InvalidLegacy(reason) => write!(f, "Invalid legacy envelope: {}", reason),
+        }
+    }
+}
+
+impl Error for EnvelopeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EnvelopeError::InvalidJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a JSON payload in either envelope shape
+pub fn decode_json(payload: &[u8]) -> Result<DecodedProof, EnvelopeError> {
+    if let Ok(proof) = serde_json::from_slice::<ZkProof>(payload) {
+        let metadata = serde_json::from_slice::<EnvelopeMetadata>(payload).ok().and_then(|envelope| envelope.metadata);
+        return Ok(DecodedProof { metadata, ..DecodedProof::typed(proof) });
+    }
+
+    let mut value: Value = serde_json::from_slice(payload).map_err(EnvelopeError::InvalidJson)?;
+    let Some(object) = value.as_object_mut() else {
+        return Err(EnvelopeError::UnknownShape { keys: Vec::new(), size: payload.len() });
+    };
+    // A typed envelope with metadata, if the envelope type refuses fields it does not know
+    let metadata = object.remove(METADATA_FIELD);
+    if metadata.is_some() {
+        if let Ok(proof) = serde_json::from_value::<ZkProof>(Value::Object(object.clone())) {
+            return Ok(DecodedProof { metadata, ..DecodedProof::typed(proof) });
+        }
+    }
+    if !is_legacy(object) {
+        return Err(EnvelopeError::UnknownShape {
+            keys: object.keys().cloned().collect(),
+            size: payload.len(),
+        });
+    }
+    let decoded = decode_legacy(object)?;
+    Ok(DecodedProof { metadata, ..decoded })
+}
+
+/// Decode a message payload in the given format
+///
+/// JSON payloads may use either the typed or the legacy envelope shape. Avro
+/// payloads need a registry to look their schema up.
+pub async fn decode_payload(
+    payload: &[u8],
+    format: PayloadFormat,
+    registry: Option<&SchemaRegistryClient>,
+) -> Result<DecodedProof, Box<dyn Error>> {
+    match format {
+        PayloadFormat::Json => Ok(decode_json(payload)?),
+        PayloadFormat::Bincode => Ok(DecodedProof::typed(bincode::deserialize::<ZkProof>(payload)?)),
+        PayloadFormat::Avro => {
+            let registry = registry.ok_or("Avro message received but no --schema-registry-url was given")?;
+            let (schema_id, body) = decode_wire_format(payload)?;
+            let schema = registry.schema_by_id(schema_id).await?;
+            Ok(DecodedProof::typed(decode_proof(&schema, body)?))
+        }
+    }
+}
+
+/// Whether an object looks like the legacy envelope
+fn is_legacy(object: &Map<String, Value>) -> bool {
+    object.get("kind").is_some_and(Value::is_string)
+        && object
+            .get("proof_data")
+            .and_then(Value::as_object)
+            .is_some_and(|data| data.contains_key("proof") && data.contains_key("public_values"))
+}
+
+fn decode_legacy(object: &Map<String, Value>) -> Result<DecodedProof, EnvelopeError> {
+    let invalid = |reason: String| EnvelopeError::InvalidLegacy(reason);
+
+    let identifier = object
+        .get("identifier")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("missing 'identifier'".to_string()))?;
+    let kind = object.get("kind").and_then(Value::as_str).unwrap_or_default();
+    let data = object
+        .get("proof_data")
+        .and_then(Value::as_object)
+        .ok_or_else(|| invalid("missing 'proof_data'".to_string()))?;
+
+    let proof_type = data.get("type").and_then(Value::as_str).unwrap_or("SP1");
+    if !proof_type.eq_ignore_ascii_case("SP1") {
+        return Err(invalid(format!("unsupported proof type '{}'", proof_type)));
+    }
+    let version = data
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| invalid("missing or invalid 'proof_data.version'".to_string()))?;
+    let hex_field = |name: &str| -> Result<Vec<u8>, EnvelopeError> {
+        let text = data
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(format!("missing 'proof_data.{}'", name)))?;
+        hex::decode(text.trim_start_matches("0x")).map_err(|e| invalid(format!("'proof_data.{}' is not hex: {}", name, e)))
+    };
+    // Nested SP1 proofs are kept as their JSON text
+    let proof_bytes = match data.get("proof") {
+        Some(nested @ (Value::Object(_) | Value::Array(_))) => {
+            serde_json::to_vec(nested).map_err(|e| invalid(format!("'proof_data.proof' is not serializable: {}", e)))?
+        }
+        _ => hex_field("proof")?,
+    };
+    let verification_key = match data.get("verification_key") {
+        Some(_) => hex_field("verification_key")?
+            .try_into()
+            .map_err(|_| invalid("'proof_data.verification_key' must be 32 bytes".to_string()))?,
+        None => [0u8; 32],
+    };
+
+    // Kinds this crate does not know are kept as text; the normalized proof treats them as consensus proofs
+    let execution = kind.strip_prefix("ExecutionProof(").and_then(|rest| rest.strip_suffix(')'));
+    let proof_kind: ProofKind = match execution.map(str::parse::<u64>) {
+        Some(Ok(index)) => ProofKind::ExecutionProof(index),
+        _ => serde_json::from_value(Value::String(kind.to_string())).unwrap_or(ProofKind::SolanaConsensusProof),
+    };
+
+    let proof = ZkProof {
+        identifier: identifier.to_string(),
+        proof_kind,
+        proof_data: ProofData::SP1(SP1Proof {
+            version: version.try_into().map_err(|_| invalid(format!("version {} is out of range", version)))?,
+            proof: proof_bytes,
+            public_value: hex_field("public_values")?,
+            verification_key,
+        }),
+    };
+    Ok(DecodedProof { proof, kind: kind.to_string(), shape: EnvelopeShape::Legacy, metadata: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A consensus proof in the legacy shape, with every field hex-encoded
+    const LEGACY: &str = include_str!("../tests/fixtures/envelope-legacy.json");
+    /// An execution proof in the legacy shape with a nested SP1 proof and metadata
+    const LEGACY_NESTED: &str = include_str!("../tests/fixtures/envelope-legacy-nested.json");
+
+    /// The proof of the `LEGACY` fixture, published in the typed shape
+    fn typed_equivalent() -> ZkProof {
+        ZkProof {
+            identifier: "solana-devnet-1000000-1000100".to_string(),
+            proof_kind: ProofKind::SolanaConsensusProof,
+            proof_data: ProofData::SP1(SP1Proof {
+                version: 2,
+                proof: vec![0x0a, 0x0b, 0x0c],
+                public_value: vec![1, 2, 3],
+                verification_key: [0x11; 32],
+            }),
+        }
+    }
+
+    /// Proofs have no `PartialEq`; compare their JSON instead
+    fn json_of(proof: &ZkProof) -> Value {
+        serde_json::to_value(proof).unwrap()
+    }
+
+    fn legacy_with(edit: impl FnOnce(&mut Value)) -> Vec<u8> {
+        let mut value: Value = serde_json::from_str(LEGACY).unwrap();
+        edit(&mut value);
+        serde_json::to_vec(&value).unwrap()
+    }
+
+    /// Check that the `LEGACY` fixture changed by `edit` is rejected for `expected`
+    fn assert_invalid_legacy(edit: impl FnOnce(&mut Value), expected: &str) {
+        match decode_json(&legacy_with(edit)) {
+            Err(EnvelopeError::InvalidLegacy(reason)) => assert!(reason.contains(expected), "{}", reason),
+            other => panic!("expected an invalid legacy envelope, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_the_typed_shape() {
+        let payload = serde_json::to_vec(&typed_equivalent()).unwrap();
+        let decoded = decode_json(&payload).unwrap();
+        assert_eq!(decoded.shape, EnvelopeShape::Typed);
+        assert_eq!(decoded.kind, "SolanaConsensusProof");
+        assert_eq!(decoded.metadata, None);
+        assert_eq!(json_of(&decoded.proof), json_of(&typed_equivalent()));
+    }
+
+    #[test]
+    fn decodes_the_typed_shape_with_metadata() {
+        let metadata = serde_json::json!({ "accounts": [{ "account_pubkey": "11111111111111111111111111111111" }] });
+        let proof = typed_equivalent();
+        let payload = serde_json::to_vec(&EnvelopeWithMetadata { proof: &proof, metadata: &metadata }).unwrap();
+
+        let decoded = decode_json(&payload).unwrap();
+        assert_eq!(decoded.shape, EnvelopeShape::Typed);
+        assert_eq!(decoded.metadata, Some(metadata));
+        assert_eq!(json_of(&decoded.proof), json_of(&proof));
+    }
+
+    #[test]
+    fn decodes_the_legacy_shape_to_the_same_proof() {
+        let decoded = decode_json(LEGACY.as_bytes()).unwrap();
+        assert_eq!(decoded.shape, EnvelopeShape::Legacy);
+        assert_eq!(decoded.kind, "SolanaConsensusProof");
+        assert_eq!(decoded.metadata, None);
+        assert_eq!(json_of(&decoded.proof), json_of(&typed_equivalent()));
+    }
+
+    #[test]
+    fn decodes_a_legacy_execution_proof_with_a_nested_sp1_proof() {
+        let decoded = decode_json(LEGACY_NESTED.as_bytes()).unwrap();
+        assert_eq!(decoded.shape, EnvelopeShape::Legacy);
+        assert_eq!(decoded.kind, "ExecutionProof(3)");
+        assert!(matches!(decoded.proof.proof_kind, ProofKind::ExecutionProof(3)));
+        assert_eq!(decoded.metadata, Some(serde_json::json!({ "producer": "legacy-prover" })));
+
+        let fixture: Value = serde_json::from_str(LEGACY_NESTED).unwrap();
+        let ProofData::SP1(sp1_proof) = &decoded.proof.proof_data;
+        assert_eq!(sp1_proof.version, 1);
+        assert_eq!(sp1_proof.proof, serde_json::to_vec(&fixture["proof_data"]["proof"]).unwrap());
+        assert_eq!(sp1_proof.public_value, [1, 2, 3]);
+        assert_eq!(sp1_proof.verification_key, [0; 32]);
+    }
+
+    #[test]
+    fn unknown_legacy_kinds_are_kept_as_text() {
+        let payload = legacy_with(|value| value["kind"] = "FutureProof".into());
+        let decoded = decode_json(&payload).unwrap();
+        assert_eq!(decoded.kind, "FutureProof");
+        assert!(matches!(decoded.proof.proof_kind, ProofKind::SolanaConsensusProof));
+    }
+
+    #[test]
+    fn rejects_payloads_that_are_not_json() {
+        let err = decode_json(b"\x00\x01binary").unwrap_err();
+        assert!(matches!(err, EnvelopeError::InvalidJson(_)));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn reports_the_keys_of_unrecognized_json() {
+        match decode_json(br#"{"slot": 1, "hash": "abc"}"#) {
+            Err(EnvelopeError::UnknownShape { mut keys, size }) => {
+                keys.sort();
+                assert_eq!((keys, size), (vec!["hash".to_string(), "slot".to_string()], 26));
+            }
+            other => panic!("expected an unknown shape, got {:?}", other),
+        }
+        let err = decode_json(b"[1, 2]").unwrap_err();
+        assert_eq!(err.to_string(), "Unrecognized JSON envelope (not an object, 6 bytes)");
+        // A legacy envelope without its public values is not recognized as one
+        let payload = legacy_with(|value| {
+            value["proof_data"].as_object_mut().unwrap().remove("public_values");
+        });
+        assert!(matches!(decode_json(&payload), Err(EnvelopeError::UnknownShape { .. })));
+    }
+
+    #[test]
+    fn rejects_invalid_legacy_fields() {
+        assert_invalid_legacy(
+            |value| {
+                value.as_object_mut().unwrap().remove("identifier");
+            },
+            "missing 'identifier'",
+        );
+        assert_invalid_legacy(
+            |value| value["proof_data"]["type"] = "Plonky2".into(),
+            "unsupported proof type 'Plonky2'",
+        );
+        assert_invalid_legacy(|value| value["proof_data"]["version"] = "2".into(), "invalid 'proof_data.version'");
+        assert_invalid_legacy(|value| value["proof_data"]["version"] = (1u64 << 32).into(), "out of range");
+        assert_invalid_legacy(|value| value["proof_data"]["proof"] = "xyz".into(), "'proof_data.proof' is not hex");
+        assert_invalid_legacy(|value| value["proof_data"]["verification_key"] = "1111".into(), "must be 32 bytes");
+    }
+
+    #[tokio::test]
+    async fn decodes_payloads_by_format() {
+        let proof = typed_equivalent();
+        let json = decode_payload(&serde_json::to_vec(&proof).unwrap(), PayloadFormat::Json, None).await.unwrap();
+        assert_eq!(json_of(&json.proof), json_of(&proof));
+        let bincode = decode_payload(&bincode::serialize(&proof).unwrap(), PayloadFormat::Bincode, None).await.unwrap();
+        assert_eq!(bincode.shape, EnvelopeShape::Typed);
+        assert_eq!(json_of(&bincode.proof), json_of(&proof));
+
+        let err = decode_payload(&[0, 0, 0, 0, 1], PayloadFormat::Avro, None).await.unwrap_err();
+        assert!(err.to_string().contains("--schema-registry-url"), "{}", err);
+        assert!(decode_payload(b"\xff", PayloadFormat::Bincode, None).await.is_err());
+    }
+}
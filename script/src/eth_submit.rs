@@ -0,0 +1,123 @@
+//! Submitting Groth16 proofs to the SP1 verifier gateway
+//!
+//! The gateway routes `verifyProof(programVKey, publicValues, proofBytes)` to
+//! the verifier named by the first four bytes of the proof and reverts if the
+//! proof does not verify. It is a view function and emits no event: a mined
+//! transaction with a successful status is the verification. Requires the
+//! `eth` feature.
+
+use crate::envelope::decode_json;
+use crate::verify::VerifyingKeySource;
+use crate::wrapped_proof::WrappedProof;
+use alloy::primitives::{address, Address, Bytes, B256};
+use alloy::sol;
+use alloy::sol_types::{SolCall, SolInterface};
+use alloy::transports::TransportError;
+use sp1_sdk::HashableKey;
+use std::fs;
+use std::path::Path;
+use twine_types::proofs::ProofData;
+
+/// Canonical SP1 Groth16 verifier gateway, deployed at the same address on every supported chain
+pub const SP1_GROTH16_GATEWAY: Address = address!("397A5f7f3dBd538f23DE225B51f532c34448dA9B");
+
+sol! {
+    /// SP1 verifier gateway and the errors of the verifiers it routes to
+    interface ISP1VerifierGateway {
+        error RouteNotFound(bytes4 selector);
+        error RouteIsFrozen(bytes4 selector);
+        error WrongVerifierSelector(bytes4 received, bytes4 expected);
+        error InvalidProof();
+
+        function verifyProof(bytes32 programVKey, bytes calldata publicValues, bytes calldata proofBytes) external view;
+    }
+}
+
+/// What the gateway needs to verify a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Groth16Artifact {
+    /// Program vkey hash committed by the proof
+    pub vkey_hash: B256,
+    pub public_values: Bytes,
+    /// Verifier selector followed by the encoded proof
+    pub proof_bytes: Bytes,
+}
+
+impl Groth16Artifact {
+    /// Load a Groth16 proof from `last_proof.json` or a Kafka message such as `last_kafka_message.json`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let wrapped = match decode_json(&bytes) {
+            Ok(decoded) => {
+                let ProofData::SP1(sp1_proof) = decoded.proof.proof_data;
+                WrappedProof::decode(&sp1_proof.proof).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            Err(_) => WrappedProof::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        };
+        Self::from_wrapped(&wrapped).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_wrapped(wrapped: &WrappedProof) -> Result<Self, String> {
+        if wrapped.kind() != "groth16" {
+            return Err(format!("a {} proof cannot be verified on-chain, generate a Groth16 proof", wrapped.kind()));
+        }
+        let vkey_hash = wrapped
+            .snark()
+            .and_then(|snark| snark.vkey_hash)
+            .and_then(|hash| hash.parse::<B256>().ok())
+            .ok_or("the proof does not commit to a program vkey hash")?;
+        Ok(Self {
+            vkey_hash,
+            public_values: Bytes::copy_from_slice(wrapped.public_values()),
+            proof_bytes: Bytes::from(wrapped.bundle.bytes()),
+        })
+    }
+
+    /// Fail unless the proof was generated for the program with this key
+    pub fn expect_program(&self, source: &VerifyingKeySource) -> Result<(), String> {
+        let expected = match source {
+            VerifyingKeySource::Key(vk) => vk.bytes32(),
+            VerifyingKeySource::Hash(hash) => hash.clone(),
+        };
+        let expected: B256 = expected.parse().map_err(|e| format!("Invalid vkey hash {}: {}", expected, e))?;
+        if expected != self.vkey_hash {
+            return Err(format!("the proof is for program {}, not {}", self.vkey_hash, expected));
+        }
+        Ok(())
+    }
+
+    /// Calldata of `verifyProof` for this proof
+    pub fn calldata(&self) -> Bytes {
+        ISP1VerifierGateway::verifyProofCall {
+            programVKey: self.vkey_hash,
+            publicValues: self.public_values.clone(),
+            proofBytes: self.proof_bytes.clone(),
+        }
+        .abi_encode()
+        .into()
+    }
+}
+
+/// An RPC error, with the gateway's revert decoded when there is one
+pub fn describe_error(error: &TransportError) -> String {
+    use ISP1VerifierGateway::ISP1VerifierGatewayErrors as GatewayError;
+
+    let revert = error
+        .as_error_resp()
+        .and_then(|payload| payload.as_revert_data())
+        .and_then(|data| GatewayError::abi_decode(&data).ok());
+    match revert {
+        Some(GatewayError::InvalidProof(_)) => "the proof does not verify (InvalidProof)".to_string(),
+        Some(GatewayError::RouteNotFound(e)) => {
+            format!("the gateway has no verifier for selector {} (RouteNotFound)", e.selector)
+        }
+        Some(GatewayError::RouteIsFrozen(e)) => {
+            format!("the verifier for selector {} is frozen (RouteIsFrozen)", e.selector)
+        }
+        Some(GatewayError::WrongVerifierSelector(e)) => format!(
+            "the proof is for verifier {} but reached {} (WrongVerifierSelector)",
+            e.received, e.expected
+        ),
+        None => error.to_string(),
+    }
+}
@@ -0,0 +1,216 @@
+//! Commitments as EVM calldata and Solidity test fixtures
+//!
+//! Contracts receive the public values ABI-encoded (see [`crate::public_values`]
+//! for the layout). An export holds those bytes and their keccak256 digest, and
+//! renders them as a Foundry test contract with every value inlined as a
+//! constant, or as JSON of named hex fields for `vm.parseJson`.
+
+use crate::public_values::{encode_abi, uint_word};
+use alloy_primitives::keccak256;
+use serde_json::{json, Value};
+use solana_stub_prover_lib::{AccountStateCommitment, PublicCommitments};
+use std::fmt::Write;
+
+/// Contract name of the Solidity snippet unless one is given
+pub const DEFAULT_CONTRACT_NAME: &str = "PublicCommitmentsFixture";
+
+/// Struct definitions the test contract decodes the public values into
+const STRUCTS: &str = "\
+struct AccountStateCommitment {
+    bytes32 accountPubkey;
+    uint64 lastChangeSlot;
+    bytes32 accountDataHash;
+    uint64 lamports;
+    bytes32 owner;
+    bool executable;
+    uint64 rentEpoch;
+    uint64 dataLen;
+    bytes data;
+}
+
+struct PublicCommitments {
+    uint64 startSlot;
+    uint64 endSlot;
+    uint64 epoch;
+    bytes32 originalBankHash;
+    bytes32 lastBankHash;
+    bytes32 accountDataHash;
+    bytes32 hashRootValset;
+    uint64 totalActiveStake;
+    uint32 validatorCount;
+    AccountStateCommitment[] monitoredAccountsState;
+    bool validationsPassed;
+    bytes32 aggregatedVkeyDigest;
+}
+";
+
+/// A Solidity constant: name, type and literal
+struct Constant {
+    name: String,
+    ty: &'static str,
+    literal: String,
+    /// Member of the decoded struct the constant is compared with
+    member: String,
+}
+
+/// ABI-encoded public values with their keccak256 digest
+#[derive(Debug, Clone)]
+pub struct EvmExport {
+    pub commitments: PublicCommitments,
+    /// `abi.encode(commitments)`
+    pub public_values: Vec<u8>,
+    /// `keccak256(public_values)`
+    pub digest: [u8; 32],
+}
+
+impl EvmExport {
+    pub fn new(commitments: PublicCommitments) -> Self {
+        let public_values = encode_abi(&commitments);
+        let digest = keccak256(&public_values).0;
+        Self { commitments, public_values, digest }
+    }
+
+    /// Every value of the commitments as a Solidity constant
+    fn constants(&self) -> Vec<Constant> {
+        let c = &self.commitments;
+        let mut constants = vec![
+            uint("START_SLOT", "uint64", c.start_slot, "startSlot"),
+            uint("END_SLOT", "uint64", c.end_slot, "endSlot"),
+            uint("EPOCH", "uint64", c.epoch, "epoch"),
+            bytes32("ORIGINAL_BANK_HASH", &c.original_bank_hash, "originalBankHash"),
+            bytes32("LAST_BANK_HASH", &c.last_bank_hash, "lastBankHash"),
+            bytes32("ACCOUNT_DATA_HASH", &c.account_data_hash, "accountDataHash"),
+            bytes32("HASH_ROOT_VALSET", &c.hash_root_valset, "hashRootValset"),
+            uint("TOTAL_ACTIVE_STAKE", "uint64", c.total_active_stake, "totalActiveStake"),
+            uint("VALIDATOR_COUNT", "uint32", u64::from(c.validator_count), "validatorCount"),
+            boolean("VALIDATIONS_PASSED", c.validations_passed, "validationsPassed"),
+            bytes32("AGGREGATED_VKEY_DIGEST", &c.aggregated_vkey_digest, "aggregatedVkeyDigest"),
+        ];
+        for (i, account) in c.monitored_accounts_state.iter().enumerate() {
+            constants.extend(account_constants(i, account));
+        }
+        constants
+    }
+
+    /// Foundry test contract with the public values, digest and every field inlined
+    ///
+    /// The tests check the digest and that decoding the public values gives
+    /// back each constant.
+    pub fn solidity(&self, contract_name: &str) -> String {
+        let constants = self.constants();
+        let accounts = self.commitments.monitored_accounts_state.len();
+        let mut out = String::new();
+        out.push_str("// SPDX-License-Identifier: MIT\n");
+        out.push_str("// Generated by `inspect export-evm`; do not edit\n");
+        out.push_str("pragma solidity ^0.8.20;\n\n");
+        out.push_str("import {Test} from \"forge-std/Test.sol\";\n\n");
+        out.push_str(STRUCTS);
+        let _ = writeln!(
+            out,
+            "\n/// Public values committed for slots {}..{}",
+            self.commitments.start_slot, self.commitments.end_slot
+        );
+        let _ = writeln!(out, "contract {} is Test {{", contract_name);
+        let _ = writeln!(out, "    bytes constant PUBLIC_VALUES = hex\"{}\";", hex::encode(&self.public_values));
+        let _ = writeln!(out, "    bytes32 constant PUBLIC_VALUES_KECCAK = 0x{};", hex::encode(self.digest));
+        let _ = writeln!(out, "    uint256 constant ACCOUNT_COUNT = {};", accounts);
+        for constant in &constants {
+            let _ = writeln!(out, "    {} constant {} = {};", constant.ty, constant.name, constant.literal);
+        }
+
+        out.push_str("\n    function test_publicValuesKeccak() public {\n");
+        out.push_str("        assertEq(keccak256(PUBLIC_VALUES), PUBLIC_VALUES_KECCAK);\n");
+        out.push_str("    }\n\n");
+        out.push_str("    function test_decodePublicValues() public {\n");
+        out.push_str("        PublicCommitments memory c = abi.decode(PUBLIC_VALUES, (PublicCommitments));\n");
+        out.push_str("        assertEq(c.monitoredAccountsState.length, ACCOUNT_COUNT);\n");
+        for constant in &constants {
+            let _ = writeln!(out, "        assertEq(c.{}, {});", constant.member, constant.name);
+        }
+        out.push_str("    }\n}\n");
+        out
+    }
+
+    /// The public values, digest and every field as `0x`-prefixed hex
+    ///
+    /// Scalars are their 32-byte ABI words, account data its raw bytes.
+    pub fn json(&self) -> Value {
+        let c = &self.commitments;
+        let word = |value: u64| Value::String(format!("0x{}", hex::encode(uint_word(value))));
+        let hash = |bytes: &[u8]| Value::String(format!("0x{}", hex::encode(bytes)));
+        let accounts: Vec<Value> = c
+            .monitored_accounts_state
+            .iter()
+            .map(|account| {
+                json!({
+                    "accountPubkey": hash(&account.account_pubkey),
+                    "lastChangeSlot": word(account.last_change_slot),
+                    "accountDataHash": hash(&account.account_data_hash),
+                    "lamports": word(account.lamports),
+                    "owner": hash(&account.owner),
+                    "executable": word(u64::from(account.executable)),
+                    "rentEpoch": word(account.rent_epoch),
+                    "dataLen": word(account.data_len),
+                    "data": hash(&account.data),
+                })
+            })
+            .collect();
+        json!({
+            "publicValues": hash(&self.public_values),
+            "publicValuesKeccak": hash(&self.digest),
+            "fields": {
+                "startSlot": word(c.start_slot),
+                "endSlot": word(c.end_slot),
+                "epoch": word(c.epoch),
+                "originalBankHash": hash(&c.original_bank_hash),
+                "lastBankHash": hash(&c.last_bank_hash),
+                "accountDataHash": hash(&c.account_data_hash),
+                "hashRootValset": hash(&c.hash_root_valset),
+                "totalActiveStake": word(c.total_active_stake),
+                "validatorCount": word(u64::from(c.validator_count)),
+                "monitoredAccountsState": accounts,
+                "validationsPassed": word(u64::from(c.validations_passed)),
+                "aggregatedVkeyDigest": hash(&c.aggregated_vkey_digest),
+            },
+        })
+    }
+}
+
+fn uint(name: &str, ty: &'static str, value: u64, member: &str) -> Constant {
+    Constant { name: name.to_string(), ty, literal: value.to_string(), member: member.to_string() }
+}
+
+fn bytes32(name: &str, value: &[u8; 32], member: &str) -> Constant {
+    Constant {
+        name: name.to_string(),
+        ty: "bytes32",
+        literal: format!("0x{}", hex::encode(value)),
+        member: member.to_string(),
+    }
+}
+
+fn boolean(name: &str, value: bool, member: &str) -> Constant {
+    Constant { name: name.to_string(), ty: "bool", literal: value.to_string(), member: member.to_string() }
+}
+
+/// Constants of the `index`th account, named `ACCOUNT_<index>_<FIELD>`
+fn account_constants(index: usize, account: &AccountStateCommitment) -> Vec<Constant> {
+    let name = |field: &str| format!("ACCOUNT_{}_{}", index, field);
+    let member = |field: &str| format!("monitoredAccountsState[{}].{}", index, field);
+    vec![
+        bytes32(&name("PUBKEY"), &account.account_pubkey, &member("accountPubkey")),
+        uint(&name("LAST_CHANGE_SLOT"), "uint64", account.last_change_slot, &member("lastChangeSlot")),
+        bytes32(&name("DATA_HASH"), &account.account_data_hash, &member("accountDataHash")),
+        uint(&name("LAMPORTS"), "uint64", account.lamports, &member("lamports")),
+        bytes32(&name("OWNER"), &account.owner, &member("owner")),
+        boolean(&name("EXECUTABLE"), account.executable, &member("executable")),
+        uint(&name("RENT_EPOCH"), "uint64", account.rent_epoch, &member("rentEpoch")),
+        uint(&name("DATA_LEN"), "uint64", account.data_len, &member("dataLen")),
+        Constant {
+            name: name("DATA"),
+            ty: "bytes",
+            literal: format!("hex\"{}\"", hex::encode(&account.data)),
+            member: member("data"),
+        },
+    ]
+}
@@ -0,0 +1,319 @@
+//! Synthetic `ProverInput` fixtures
+//!
+//! Each preset builds an input from a seed with its own SplitMix64 stream, so
+//! a preset comes out byte-for-byte the same for the same seed whichever other
+//! presets are generated, on any platform. The manifest written next to the
+//! fixtures records what each case is and how the program is expected to
+//! handle it. The stub program only rejects inputs whose end slot is not after
+//! the start slot or whose accounts are not sorted by pubkey without
+//! duplicates; everything else is accepted with `validations_passed`. The
+//! accounts of every preset are sorted, so only duplicates break the order.
+
+use crate::utils::{base58_to_bytes32, get_epoch_for_slot, sha256_hash};
+use serde::{Deserialize, Serialize};
+use solana_stub_prover_lib::{stub_bank_hash, AccountStateCommitment, ProverInput};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Seed used when none is given
+pub const DEFAULT_SEED: u64 = 42;
+
+/// File the manifest is written to in the fixture directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SYSTEM_PROGRAM: [u8; 32] = [0u8; 32];
+/// Size of an SPL token account
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const LARGE_ACCOUNT_LEN: usize = 1024 * 1024;
+
+/// A kind of input to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// One account with a little data
+    SmallAccount,
+    /// 16 SPL token accounts of the same mint
+    TokenAccounts,
+    /// One account with 1 MiB of data
+    LargeAccount,
+    /// End slot before the start slot
+    BrokenChain,
+    /// The same pubkey committed twice
+    DuplicatePubkeys,
+    /// An account closed in the range: no lamports, no data
+    ClosedAccount,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 6] = [
+        Preset::SmallAccount,
+        Preset::TokenAccounts,
+        Preset::LargeAccount,
+        Preset::BrokenChain,
+        Preset::DuplicatePubkeys,
+        Preset::ClosedAccount,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Preset::SmallAccount => "one program-owned account with 128 bytes of data",
+            Preset::TokenAccounts => "16 SPL token accounts of the same mint",
+            Preset::LargeAccount => "one program-owned account with 1 MiB of data",
+            Preset::BrokenChain => "end slot before the start slot",
+            Preset::DuplicatePubkeys => "three accounts, two with the same pubkey",
+            Preset::ClosedAccount => "an account closed in the range: no lamports or data, owned by the system program",
+        }
+    }
+
+    /// How the program handles inputs of this preset
+    pub fn expected(self) -> ExpectedOutcome {
+        match self {
+            Preset::BrokenChain => ExpectedOutcome {
+                accepted: false,
+                validations_passed: None,
+                error: Some("end_slot must be greater than start_slot".to_string()),
+            },
+            Preset::DuplicatePubkeys => ExpectedOutcome {
+                accepted: false,
+                validations_passed: None,
+                error: Some("monitored accounts must be sorted by pubkey without duplicates".to_string()),
+            },
+            _ => ExpectedOutcome { accepted: true, validations_passed: Some(true), error: None },
+        }
+    }
+
+    /// The input of this preset for `seed`
+    pub fn generate(self, seed: u64) -> ProverInput {
+        let name_hash = sha256_hash(self.to_string().as_bytes());
+        let mut rng = SplitMix64(seed ^ u64::from_le_bytes(name_hash[..8].try_into().expect("8 bytes")));
+        let start_slot = 300_000_000 + rng.below(10_000_000);
+        let end_slot = start_slot + 1 + rng.below(1_000);
+
+        let program = rng.bytes32();
+        let mut accounts = match self {
+            Preset::SmallAccount | Preset::BrokenChain => vec![rng.account(start_slot, end_slot, program, 128)],
+            Preset::TokenAccounts => {
+                let token_program = base58_to_bytes32(TOKEN_PROGRAM).expect("valid token program id");
+                let mint = rng.bytes32();
+                (0..16).map(|_| rng.token_account(start_slot, end_slot, token_program, mint)).collect()
+            }
+            Preset::LargeAccount => vec![rng.account(start_slot, end_slot, program, LARGE_ACCOUNT_LEN)],
+            Preset::DuplicatePubkeys => {
+                let first = rng.account(start_slot, end_slot, program, 64);
+                let mut duplicate = rng.account(start_slot, end_slot, program, 64);
+                duplicate.account_pubkey = first.account_pubkey;
+                vec![first, duplicate, rng.account(start_slot, end_slot, program, 64)]
+            }
+            Preset::ClosedAccount => {
+                let mut account = rng.account(start_slot, end_slot, SYSTEM_PROGRAM, 0);
+                account.lamports = 0;
+                account.rent_epoch = 0;
+                vec![account]
+            }
+        };
+        // In the order input assembly puts them in
+        accounts.sort_by(|a, b| a.account_pubkey.cmp(&b.account_pubkey));
+
+        // A broken chain runs backwards
+        let end_slot = match self {
+            Preset::BrokenChain => start_slot - 1 - rng.below(100),
+            _ => end_slot,
+        };
+        ProverInput {
+            start_slot,
+            end_slot,
+            epoch: get_epoch_for_slot(end_slot),
+            original_bank_hash: stub_bank_hash(start_slot),
+            last_bank_hash: stub_bank_hash(end_slot),
+            monitored_accounts_state: accounts,
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.to_string() == s.to_ascii_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<String> = Preset::ALL.iter().map(Preset::to_string).collect();
+                format!("Unknown preset '{}' (expected one of {})", s, names.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Preset::SmallAccount => write!(f, "small-account"),
+            Preset::TokenAccounts => write!(f, "token-accounts"),
+            Preset::LargeAccount => write!(f, "large-account"),
+            Preset::BrokenChain => write!(f, "broken-chain"),
+            Preset::DuplicatePubkeys => write!(f, "duplicate-pubkeys"),
+            Preset::ClosedAccount => write!(f, "closed-account"),
+        }
+    }
+}
+
+/// How the program is expected to handle a fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedOutcome {
+    /// Whether the program commits public values (rather than panicking)
+    pub accepted: bool,
+    pub validations_passed: Option<bool>,
+    /// Panic message of the program when rejected
+    pub error: Option<String>,
+}
+
+/// One generated fixture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub preset: String,
+    /// File name in the fixture directory
+    pub file: String,
+    pub description: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub accounts: usize,
+    /// Account data of all accounts in bytes
+    pub data_bytes: usize,
+    pub expected: ExpectedOutcome,
+}
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureManifest {
+    pub seed: u64,
+    pub fixtures: Vec<ManifestEntry>,
+}
+
+/// Write the inputs of `presets` for `seed` and their manifest to `dir`
+pub fn write_fixtures(dir: &Path, seed: u64, presets: &[Preset]) -> Result<FixtureManifest, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut fixtures = Vec::new();
+    for &preset in presets {
+        let input = preset.generate(seed);
+        let file = format!("{}.json", preset);
+        let path = dir.join(&file);
+        let json = serde_json::to_vec(&input).map_err(|e| format!("Failed to serialize {}: {}", preset, e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        fixtures.push(ManifestEntry {
+            preset: preset.to_string(),
+            file,
+            description: preset.description().to_string(),
+            start_slot: input.start_slot,
+            end_slot: input.end_slot,
+            accounts: input.monitored_accounts_state.len(),
+            data_bytes: input.monitored_accounts_state.iter().map(|account| account.data.len()).sum(),
+            expected: preset.expected(),
+        });
+    }
+
+    let manifest = FixtureManifest { seed, fixtures };
+    let path = dir.join(MANIFEST_FILE);
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| format!("Failed to serialize the manifest: {}", e))?;
+    fs::write(&path, json + "\n").map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(manifest)
+}
+
+/// Read a `ProverInput` JSON file, such as a generated fixture
+pub fn load_input(path: &Path) -> Result<ProverInput, String> {
+    let json = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Invalid prover input in {}: {}", path.display(), e))
+}
+
+/// `count` accounts of one owner with `data_len` bytes of data each, as benchmark inputs
+pub fn accounts(seed: u64, count: usize, data_len: usize) -> Vec<AccountStateCommitment> {
+    let mut rng = SplitMix64(seed);
+    let owner = rng.bytes32();
+    (0..count).map(|_| rng.account(300_000_000, 300_001_000, owner, data_len)).collect()
+}
+
+/// `len` random bytes, e.g. a stand-in for a serialized proof
+pub fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    SplitMix64(seed).fill(&mut bytes);
+    bytes
+}
+
+/// SplitMix64, so fixtures do not change with a random number crate's version
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    fn fill(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+
+    fn bytes32(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.fill(&mut bytes);
+        bytes
+    }
+
+    /// A rent-exempt account with random data, last changed within the range
+    fn account(&mut self, start_slot: u64, end_slot: u64, owner: [u8; 32], data_len: usize) -> AccountStateCommitment {
+        let mut data = vec![0u8; data_len];
+        self.fill(&mut data);
+        self.account_with_data(start_slot, end_slot, owner, data)
+    }
+
+    /// An initialized SPL token account of `mint`
+    fn token_account(
+        &mut self,
+        start_slot: u64,
+        end_slot: u64,
+        token_program: [u8; 32],
+        mint: [u8; 32],
+    ) -> AccountStateCommitment {
+        let mut data = Vec::with_capacity(TOKEN_ACCOUNT_LEN);
+        data.extend_from_slice(&mint);
+        data.extend_from_slice(&self.bytes32()); // owner
+        data.extend_from_slice(&self.below(1_000_000_000_000).to_le_bytes()); // amount
+        data.extend_from_slice(&[0u8; 36]); // no delegate
+        data.push(1); // initialized
+        data.extend_from_slice(&[0u8; 12]); // not native
+        data.extend_from_slice(&[0u8; 8]); // delegated amount
+        data.extend_from_slice(&[0u8; 36]); // no close authority
+        self.account_with_data(start_slot, end_slot, token_program, data)
+    }
+
+    fn account_with_data(
+        &mut self,
+        start_slot: u64,
+        end_slot: u64,
+        owner: [u8; 32],
+        data: Vec<u8>,
+    ) -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: self.bytes32(),
+            last_change_slot: start_slot + self.below(end_slot - start_slot + 1),
+            account_data_hash: sha256_hash(&data),
+            // Rent-exempt minimum: two years of rent for the data and the account header
+            lamports: (128 + data.len() as u64) * 6960,
+            owner,
+            executable: false,
+            rent_epoch: u64::MAX,
+            data_len: data.len() as u64,
+            data,
+        }
+    }
+}
@@ -0,0 +1,450 @@
+//! gRPC API for proving, stored commitments and verification
+//!
+//! [`proto`] is generated from `proto/prover.proto` at build time, next to
+//! conversions between its messages and the library types. [`ProverService`]
+//! implements the `Prover` service: proofs run one at a time through the
+//! pipeline and stream their stage until they are published, stored proofs
+//! come from the database written by `consumer --sqlite`, and envelopes are
+//! verified against a fixed key. [`TokenInterceptor`] rejects calls without
+//! the bearer token. Requires the `grpc` feature.
+
+use crate::account_data::RawData;
+use crate::pipeline::{assemble_input, prove, publish, Consistency, InputConfig, PipelineError, ProofArtifacts};
+use crate::proof_filter::decode_commitments;
+use crate::proof_sink::{ProofSink, SinkReceipt};
+use crate::proof_store::{ProofStore, StoredAccount, StoredProof};
+use crate::prove_jobs::{ProofMode, ProveRequest};
+use crate::verify::{ProofVerifier, VerifyStatus};
+use sha2::{Digest, Sha256};
+use solana_stub_prover_lib::{AccountStateCommitment, PublicCommitments};
+use sp1_sdk::HashableKey;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::service::Interceptor;
+use tonic::{Request, Response, Status};
+use twine_types::proofs::ZkProof;
+
+/// Messages and service stubs generated from `proto/prover.proto`
+pub mod proto {
+    tonic::include_proto!("solana_stub_prover.v1");
+}
+
+use proto::prover_server::Prover;
+use proto::Stage;
+
+impl From<ProofMode> for proto::ProofMode {
+    fn from(mode: ProofMode) -> Self {
+        match mode {
+            ProofMode::Groth16 => proto::ProofMode::Groth16,
+            ProofMode::Compressed => proto::ProofMode::Compressed,
+        }
+    }
+}
+
+impl From<proto::ProofMode> for ProofMode {
+    fn from(mode: proto::ProofMode) -> Self {
+        match mode {
+            proto::ProofMode::Groth16 => ProofMode::Groth16,
+            proto::ProofMode::Compressed => ProofMode::Compressed,
+        }
+    }
+}
+
+impl From<ProveRequest> for proto::ProveRequest {
+    fn from(request: ProveRequest) -> Self {
+        Self {
+            account: request.account,
+            start_slot: request.start_slot,
+            end_slot: request.end_slot,
+            mode: proto::ProofMode::from(request.mode).into(),
+        }
+    }
+}
+
+impl TryFrom<proto::ProveRequest> for ProveRequest {
+    type Error = String;
+
+    fn try_from(request: proto::ProveRequest) -> Result<Self, Self::Error> {
+        let mode =
+            proto::ProofMode::try_from(request.mode).map_err(|_| format!("Unknown proof mode {}", request.mode))?;
+        Ok(Self {
+            account: request.account,
+            start_slot: request.start_slot,
+            end_slot: request.end_slot,
+            mode: mode.into(),
+        })
+    }
+}
+
+impl From<&AccountStateCommitment> for proto::AccountCommitment {
+    fn from(account: &AccountStateCommitment) -> Self {
+        Self {
+            account_pubkey: account.account_pubkey.to_vec(),
+            last_change_slot: account.last_change_slot,
+            account_data_hash: account.account_data_hash.to_vec(),
+            lamports: account.lamports,
+            owner: account.owner.to_vec(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data_len: account.data_len,
+            data: account.data.clone(),
+        }
+    }
+}
+
+impl TryFrom<proto::AccountCommitment> for AccountStateCommitment {
+    type Error = String;
+
+    fn try_from(account: proto::AccountCommitment) -> Result<Self, Self::Error> {
+        Ok(Self {
+            account_pubkey: bytes32("account_pubkey", account.account_pubkey)?,
+            last_change_slot: account.last_change_slot,
+            account_data_hash: bytes32("account_data_hash", account.account_data_hash)?,
+            lamports: account.lamports,
+            owner: bytes32("owner", account.owner)?,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data_len: account.data_len,
+            data: account.data,
+        })
+    }
+}
+
+impl From<&PublicCommitments> for proto::Commitments {
+    fn from(commitments: &PublicCommitments) -> Self {
+        Self {
+            start_slot: commitments.start_slot,
+            end_slot: commitments.end_slot,
+            epoch: commitments.epoch,
+            original_bank_hash: commitments.original_bank_hash.to_vec(),
+            last_bank_hash: commitments.last_bank_hash.to_vec(),
+            account_data_hash: commitments.account_data_hash.to_vec(),
+            hash_root_valset: commitments.hash_root_valset.to_vec(),
+            total_active_stake: commitments.total_active_stake,
+            validator_count: commitments.validator_count,
+            monitored_accounts_state: commitments.monitored_accounts_state.iter().map(Into::into).collect(),
+            validations_passed: commitments.validations_passed,
+            aggregated_vkey_digest: commitments.aggregated_vkey_digest.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<proto::Commitments> for PublicCommitments {
+    type Error = String;
+
+    fn try_from(commitments: proto::Commitments) -> Result<Self, Self::Error> {
+        Ok(Self {
+            start_slot: commitments.start_slot,
+            end_slot: commitments.end_slot,
+            epoch: commitments.epoch,
+            original_bank_hash: bytes32("original_bank_hash", commitments.original_bank_hash)?,
+            last_bank_hash: bytes32("last_bank_hash", commitments.last_bank_hash)?,
+            account_data_hash: bytes32("account_data_hash", commitments.account_data_hash)?,
+            hash_root_valset: bytes32("hash_root_valset", commitments.hash_root_valset)?,
+            total_active_stake: commitments.total_active_stake,
+            validator_count: commitments.validator_count,
+            monitored_accounts_state: commitments
+                .monitored_accounts_state
+                .into_iter()
+                .map(AccountStateCommitment::try_from)
+                .collect::<Result<_, _>>()?,
+            validations_passed: commitments.validations_passed,
+            aggregated_vkey_digest: bytes32("aggregated_vkey_digest", commitments.aggregated_vkey_digest)?,
+        })
+    }
+}
+
+impl From<StoredAccount> for proto::StoredAccount {
+    fn from(account: StoredAccount) -> Self {
+        Self {
+            account_pubkey: account.account_pubkey,
+            last_change_slot: account.last_change_slot,
+            account_data_hash: account.account_data_hash,
+            lamports: account.lamports,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data_len: account.data_len,
+        }
+    }
+}
+
+impl From<StoredProof> for proto::StoredProof {
+    fn from(proof: StoredProof) -> Self {
+        Self {
+            identifier: proof.identifier,
+            topic: proof.topic,
+            partition: proof.partition,
+            offset: proof.offset,
+            proof_kind: proof.proof_kind,
+            version: proof.version,
+            verification_key: proof.verification_key,
+            start_slot: proof.start_slot,
+            end_slot: proof.end_slot,
+            epoch: proof.epoch,
+            original_bank_hash: proof.original_bank_hash,
+            last_bank_hash: proof.last_bank_hash,
+            account_data_hash: proof.account_data_hash,
+            hash_root_valset: proof.hash_root_valset,
+            total_active_stake: proof.total_active_stake,
+            validator_count: proof.validator_count,
+            validations_passed: proof.validations_passed,
+            received_at: proof.received_at,
+            accounts: proof.accounts.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn bytes32(field: &str, bytes: Vec<u8>) -> Result<[u8; 32], String> {
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| format!("{} must be 32 bytes, got {}", field, len))
+}
+
+/// Rejects calls without `authorization: Bearer <token>` metadata
+#[derive(Clone)]
+pub struct TokenInterceptor {
+    /// SHA-256 of the token, so comparing tokens takes the same time whatever they share
+    digest: [u8; 32],
+}
+
+impl TokenInterceptor {
+    pub fn new(token: &str) -> Self {
+        Self { digest: Sha256::digest(token.trim()).into() }
+    }
+}
+
+impl Interceptor for TokenInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match token.map(|token| <[u8; 32]>::from(Sha256::digest(token.trim()))) {
+            Some(digest) if digest == self.digest => Ok(request),
+            Some(_) => Err(Status::unauthenticated("invalid bearer token")),
+            None => Err(Status::unauthenticated("missing bearer token")),
+        }
+    }
+}
+
+type StatusSender = mpsc::Sender<Result<proto::ProveStatus, Status>>;
+
+/// A place among the proofs waiting to run, given up when dropped
+struct QueuePlace(Arc<AtomicUsize>);
+
+impl QueuePlace {
+    fn take(queued: &Arc<AtomicUsize>, max_queued: usize) -> Option<Self> {
+        queued.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max_queued).then_some(n + 1)).ok()?;
+        Some(Self(Arc::clone(queued)))
+    }
+}
+
+impl Drop for QueuePlace {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The `Prover` service
+///
+/// Proofs run one at a time, in the order they were requested; proving uses
+/// every core, so running two at once would only slow both down. At most
+/// `max_queued` proofs wait to run, beyond that `Prove` fails with
+/// `RESOURCE_EXHAUSTED`.
+#[derive(Clone)]
+pub struct ProverService {
+    rpc_url: String,
+    sink: Arc<dyn ProofSink>,
+    store: ProofStore,
+    /// `None` makes `VerifyProof` fail with `FAILED_PRECONDITION`
+    verifier: Option<Arc<ProofVerifier>>,
+    max_queued: usize,
+    queued: Arc<AtomicUsize>,
+    /// Held by the running proof
+    proving: Arc<Semaphore>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl ProverService {
+    pub fn new(
+        rpc_url: &str,
+        sink: Arc<dyn ProofSink>,
+        store: ProofStore,
+        verifier: Option<ProofVerifier>,
+        max_queued: usize,
+    ) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+            sink,
+            store,
+            verifier: verifier.map(Arc::new),
+            max_queued,
+            queued: Arc::new(AtomicUsize::new(0)),
+            proving: Arc::new(Semaphore::new(1)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Fail the queued proofs and wait for the running one to finish
+    pub async fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let _ = self.proving.acquire().await;
+    }
+
+    /// Run one proof once it is its turn, reporting each stage to `updates`
+    async fn run(
+        &self,
+        request: ProveRequest,
+        place: QueuePlace,
+        updates: &StatusSender,
+    ) -> Result<proto::ProofArtifacts, Status> {
+        let _permit = self.proving.acquire().await.map_err(|e| Status::internal(e.to_string()))?;
+        drop(place);
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("the server is shutting down"));
+        }
+        if updates.is_closed() {
+            return Err(Status::cancelled("the client went away before the proof started"));
+        }
+
+        send_stage(updates, Stage::Fetching).await;
+        let input_config =
+            InputConfig::new(&self.rpc_url, &request.account, request.start_slot, Some(request.end_slot));
+        let input = assemble_input(&input_config).await.map_err(pipeline_status)?;
+
+        send_stage(updates, Stage::Proving).await;
+        let mode = request.mode;
+        // Proving is CPU-bound and takes minutes
+        let artifacts = tokio::task::spawn_blocking(move || prove(&input, mode, RawData::Omit, Consistency::Check))
+            .await
+            .map_err(|e| Status::internal(format!("Proving task failed: {}", e)))?
+            .map_err(pipeline_status)?;
+
+        send_stage(updates, Stage::Publishing).await;
+        let receipt = publish(&artifacts, self.sink.as_ref()).await.map_err(pipeline_status)?;
+        println!("📤 {} published to {}", artifacts.zk_proof.identifier, receipt);
+        artifacts_message(&artifacts, &receipt)
+    }
+}
+
+async fn send_stage(updates: &StatusSender, stage: Stage) {
+    // A client that went away does not stop the proof
+    let _ = updates.send(Ok(proto::ProveStatus { stage: stage.into(), artifacts: None })).await;
+}
+
+fn artifacts_message(artifacts: &ProofArtifacts, receipt: &SinkReceipt) -> Result<proto::ProofArtifacts, Status> {
+    let envelope = serde_json::to_vec(&artifacts.zk_proof)
+        .map_err(|e| Status::internal(format!("Failed to encode the envelope: {}", e)))?;
+    Ok(proto::ProofArtifacts {
+        identifier: artifacts.zk_proof.identifier.clone(),
+        mode: proto::ProofMode::from(artifacts.mode).into(),
+        vkey_hash: artifacts.vk.bytes32(),
+        envelope,
+        commitments: Some((&artifacts.commitments).into()),
+        published_to: receipt.to_string(),
+    })
+}
+
+fn pipeline_status(e: PipelineError) -> Status {
+    match e {
+        PipelineError::InvalidRange { .. }
+        | PipelineError::InvalidAccount(_)
+        | PipelineError::InvalidPubkeys(_)
+        | PipelineError::AccountTooLarge { .. }
+        | PipelineError::InputTooLarge { .. } => Status::invalid_argument(e.to_string()),
+        PipelineError::Rpc(_) => Status::unavailable(e.to_string()),
+        PipelineError::AccountNotFound(_) => Status::not_found(e.to_string()),
+        PipelineError::Accounts { ref failures, .. }
+            if failures.iter().all(|(_, e)| matches!(e, PipelineError::AccountNotFound(_))) =>
+        {
+            Status::not_found(e.to_string())
+        }
+        PipelineError::Accounts { ref failures, .. }
+            if failures.iter().all(|(_, e)| {
+                matches!(
+                    e,
+                    PipelineError::InvalidAccount(_)
+                        | PipelineError::AccountTooLarge { .. }
+                        | PipelineError::InputTooLarge { .. }
+                )
+            }) =>
+        {
+            Status::invalid_argument(e.to_string())
+        }
+        PipelineError::Accounts { .. } => Status::unavailable(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl Prover for ProverService {
+    type ProveStream = ReceiverStream<Result<proto::ProveStatus, Status>>;
+
+    async fn prove(&self, request: Request<proto::ProveRequest>) -> Result<Response<Self::ProveStream>, Status> {
+        let request = ProveRequest::try_from(request.into_inner()).map_err(Status::invalid_argument)?;
+        request.validate().map_err(Status::invalid_argument)?;
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("the server is shutting down"));
+        }
+        let place = QueuePlace::take(&self.queued, self.max_queued)
+            .ok_or_else(|| Status::resource_exhausted(format!("{} proofs are already queued", self.max_queued)))?;
+        println!("📥 Queued {} slots {}..{} ({})", request.account, request.start_slot, request.end_slot, request.mode);
+
+        let (updates, receiver) = mpsc::channel(8);
+        send_stage(&updates, Stage::Queued).await;
+        let service = self.clone();
+        tokio::spawn(async move {
+            let last = match service.run(request, place, &updates).await {
+                Ok(artifacts) => Ok(proto::ProveStatus { stage: Stage::Done.into(), artifacts: Some(artifacts) }),
+                Err(status) => {
+                    eprintln!("❌ Proof failed: {}", status.message());
+                    Err(status)
+                }
+            };
+            let _ = updates.send(last).await;
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn get_latest_commitments(
+        &self,
+        request: Request<proto::AccountFilter>,
+    ) -> Result<Response<proto::StoredProof>, Status> {
+        let account = Some(request.into_inner().account).filter(|account| !account.is_empty());
+        let description = account.as_ref().map_or_else(String::new, |account| format!(" for account {}", account));
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.latest(account.as_deref()))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| Status::internal(format!("database error: {}", e)))?
+            .map(|proof| Response::new(proof.into()))
+            .ok_or_else(|| Status::not_found(format!("no proof found{}", description)))
+    }
+
+    async fn verify_proof(&self, request: Request<proto::ProofBlob>) -> Result<Response<proto::VerifyResult>, Status> {
+        let verifier = self
+            .verifier
+            .clone()
+            .ok_or_else(|| Status::failed_precondition("the server was started without --vkey or --vkey-hash"))?;
+        let proof: ZkProof = serde_json::from_slice(&request.into_inner().envelope)
+            .map_err(|e| Status::invalid_argument(format!("not a proof envelope: {}", e)))?;
+        let commitments = decode_commitments(&proof).map(|commitments| (&commitments).into());
+        // Verification is CPU-bound and can take seconds
+        let verification = tokio::task::spawn_blocking(move || verifier.verify(&proof))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let (verified, reason) = match verification.status {
+            VerifyStatus::Verified => (true, String::new()),
+            VerifyStatus::Failed(reason) => (false, reason),
+        };
+        Ok(Response::new(proto::VerifyResult {
+            verified,
+            reason,
+            elapsed_ms: verification.elapsed.as_millis() as u64,
+            commitments,
+        }))
+    }
+}
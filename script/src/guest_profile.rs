@@ -0,0 +1,106 @@
+//! Cycles the program spends in each phase
+//!
+//! The program reports its phases ([`solana_stub_prover_lib::phases`]) to
+//! SP1's cycle tracker, and the execution report holds the cycles of each
+//! under its name. [`GuestProfile::from_tracker`] reads them back next to the
+//! total, so a slower proof shows which phase grew. Phases the program did not
+//! report, such as the range phases of an aggregation, are left out rather
+//! than counted as zero. Whatever runs outside every phase, the entrypoint's
+//! setup and the final public values digest among it, is the untracked rest.
+
+use crate::presenter::Section;
+use crate::utils::bytes32_to_base58;
+use serde::{Deserialize, Serialize};
+use solana_stub_prover_lib::phases;
+use std::collections::HashMap;
+
+/// Cycles of one phase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseCycles {
+    pub phase: String,
+    pub cycles: u64,
+}
+
+/// Cycles of one monitored account's checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountCycles {
+    /// Base58 pubkey of the account
+    pub account: String,
+    pub cycles: u64,
+}
+
+/// Where the cycles of one execution went
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestProfile {
+    pub total_cycles: u64,
+    /// The top-level phases the program reported, in program order
+    pub phases: Vec<PhaseCycles>,
+    /// The accounts whose checks the program reported, in input order; part of their phase's cycles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<AccountCycles>,
+}
+
+impl GuestProfile {
+    /// Profile of an execution from its cycle tracker, the report's `cycle_tracker`
+    ///
+    /// `accounts` are the pubkeys of the input's monitored accounts, which name
+    /// the per-account phases by their index.
+    pub fn from_tracker(tracker: &HashMap<String, u64>, total_cycles: u64, accounts: &[[u8; 32]]) -> Self {
+        let phases = phases::ALL
+            .iter()
+            .filter_map(|phase| {
+                tracker.get(*phase).map(|cycles| PhaseCycles { phase: phase.to_string(), cycles: *cycles })
+            })
+            .collect();
+        let accounts = accounts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, pubkey)| {
+                let cycles = *tracker.get(&phases::account(i))?;
+                Some(AccountCycles { account: bytes32_to_base58(pubkey), cycles })
+            })
+            .collect();
+        Self { total_cycles, phases, accounts }
+    }
+
+    /// Cycles of `phase`, `None` if the program did not report it
+    pub fn phase(&self, phase: &str) -> Option<u64> {
+        self.phases.iter().find(|reported| reported.phase == phase).map(|reported| reported.cycles)
+    }
+
+    /// The top-level phases the program did not report
+    pub fn missing_phases(&self) -> Vec<&'static str> {
+        phases::ALL.into_iter().filter(|phase| self.phase(phase).is_none()).collect()
+    }
+
+    /// Cycles of all reported top-level phases together
+    pub fn tracked_cycles(&self) -> u64 {
+        self.phases.iter().map(|phase| phase.cycles).sum()
+    }
+
+    /// Cycles outside every phase
+    pub fn untracked_cycles(&self) -> u64 {
+        self.total_cycles.saturating_sub(self.tracked_cycles())
+    }
+
+    /// Add the profile to `section` as a table of phases and accounts, with their share of the total
+    pub fn rows(&self, section: &mut Section, depth: usize) {
+        let share = |cycles: u64| {
+            format!("{:>12} cycles ({:.1}%)", cycles, cycles as f64 * 100.0 / self.total_cycles.max(1) as f64)
+        };
+        for phase in &self.phases {
+            section.field(depth, &phase.phase, share(phase.cycles));
+        }
+        section.field(depth, "untracked", share(self.untracked_cycles()));
+        for phase in self.missing_phases() {
+            section.field(depth, phase, "not reported");
+        }
+        section.field(depth, "total", format!("{:>12} cycles", self.total_cycles));
+        if !self.accounts.is_empty() {
+            section.blank().heading(depth, "Account checks:");
+            for account in &self.accounts {
+                section.field(depth + 1, &account.account, share(account.cycles));
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Liveness, readiness and status of the consumer for orchestrator probes
+//!
+//! The poll loop reports progress on every iteration, including the idle ticks
+//! it takes while no messages arrive, so the consumer is live as long as the
+//! loop keeps turning. It stops turning when it hangs, e.g. while a partition
+//! queue stays full. It is ready once subscribed with partitions assigned, and
+//! not ready any more once it starts shutting down.
+//!
+//! `/healthz`, `/readyz` and `/status` are served by the same lightweight
+//! server as `/metrics` (see [`metrics::serve_routes`](crate::metrics::serve_routes)).
+
+use crate::metrics::{HttpResponse, Routes};
+use crate::stats::ConsumerStats;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default time without poll-loop progress before `/healthz` fails
+pub const DEFAULT_STALL_THRESHOLD_SECS: u64 = 60;
+
+struct State {
+    last_progress: Instant,
+    subscribed: bool,
+    assigned_partitions: usize,
+    /// Record timestamp of the last message read
+    last_message_at: Option<DateTime<Utc>>,
+    end_to_end_lag_seconds: Option<f64>,
+    /// Messages behind the high watermarks of the assigned partitions, from the last lag refresh
+    consumer_lag: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    healthy: bool,
+    ready: bool,
+    subscribed: bool,
+    assigned_partitions: usize,
+    seconds_since_progress: f64,
+    last_message_at: Option<String>,
+    end_to_end_lag_seconds: Option<f64>,
+    consumer_lag: Option<i64>,
+    stats: &'a ConsumerStats,
+}
+
+/// Health of a running consumer, shared between the poll loop and the HTTP server
+pub struct ConsumerHealth {
+    stall_threshold: Duration,
+    state: Mutex<State>,
+    stats: Arc<Mutex<ConsumerStats>>,
+}
+
+impl ConsumerHealth {
+    /// Health failing after `stall_threshold` without progress, reporting `stats` in `/status`
+    pub fn new(stall_threshold: Duration, stats: Arc<Mutex<ConsumerStats>>) -> Self {
+        Self {
+            stall_threshold,
+            state: Mutex::new(State {
+                last_progress: Instant::now(),
+                subscribed: false,
+                assigned_partitions: 0,
+                last_message_at: None,
+                end_to_end_lag_seconds: None,
+                consumer_lag: None,
+            }),
+            stats,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record that the poll loop went through an iteration
+    pub fn progress(&self) {
+        self.lock().last_progress = Instant::now();
+    }
+
+    /// Record whether the consumer is subscribed (or assigned) and not shutting down
+    pub fn set_subscribed(&self, subscribed: bool) {
+        self.lock().subscribed = subscribed;
+    }
+
+    pub fn set_assigned_partitions(&self, partitions: usize) {
+        self.lock().assigned_partitions = partitions;
+    }
+
+    /// Record a message read, with its record timestamp in milliseconds and end-to-end lag
+    pub fn message(&self, timestamp_ms: Option<i64>, lag_seconds: Option<f64>) {
+        let mut state = self.lock();
+        state.last_message_at = timestamp_ms.and_then(DateTime::from_timestamp_millis);
+        state.end_to_end_lag_seconds = lag_seconds;
+    }
+
+    /// Record the total consumer lag over the assigned partitions
+    pub fn set_consumer_lag(&self, lag: i64) {
+        self.lock().consumer_lag = Some(lag);
+    }
+
+    /// Whether the poll loop made progress within the stall threshold
+    pub fn is_healthy(&self) -> bool {
+        self.lock().last_progress.elapsed() <= self.stall_threshold
+    }
+
+    /// Whether the consumer is subscribed and has partitions assigned
+    pub fn is_ready(&self) -> bool {
+        let state = self.lock();
+        state.subscribed && state.assigned_partitions > 0
+    }
+
+    /// Status of the consumer and its statistics as JSON
+    pub fn status_json(&self) -> Result<String, serde_json::Error> {
+        let healthy = self.is_healthy();
+        let ready = self.is_ready();
+        let state = self.lock();
+        let stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        serde_json::to_string(&Status {
+            healthy,
+            ready,
+            subscribed: state.subscribed,
+            assigned_partitions: state.assigned_partitions,
+            seconds_since_progress: state.last_progress.elapsed().as_secs_f64(),
+            last_message_at: state.last_message_at.map(|at| at.to_rfc3339()),
+            end_to_end_lag_seconds: state.end_to_end_lag_seconds,
+            consumer_lag: state.consumer_lag,
+            stats: &stats,
+        })
+    }
+
+    /// Response for `/healthz`, `/readyz` or `/status`, or `None` for other paths
+    pub fn respond(&self, path: &str) -> Option<HttpResponse> {
+        let probe = |ok: bool, failure: &str| {
+            if ok {
+                HttpResponse { status: "200 OK", content_type: "text/plain", body: "ok\n".to_string() }
+            } else {
+                HttpResponse {
+                    status: "503 Service Unavailable",
+                    content_type: "text/plain",
+                    body: format!("{}\n", failure),
+                }
+            }
+        };
+        match path {
+            "/healthz" => Some(probe(self.is_healthy(), "stalled")),
+            "/readyz" => Some(probe(self.is_ready(), "not ready")),
+            "/status" => Some(match self.status_json() {
+                Ok(body) => HttpResponse { status: "200 OK", content_type: "application/json", body },
+                Err(e) => HttpResponse {
+                    status: "500 Internal Server Error",
+                    content_type: "text/plain",
+                    body: format!("{}\n", e),
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    /// Routes serving this health on the metrics server
+    pub fn routes(self: &Arc<Self>) -> Routes {
+        let health = Arc::clone(self);
+        Arc::new(move |path| health.respond(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(stall_threshold: Duration) -> ConsumerHealth {
+        ConsumerHealth::new(stall_threshold, Arc::new(Mutex::new(ConsumerStats::new())))
+    }
+
+    #[test]
+    fn is_ready_only_while_subscribed_with_partitions() {
+        let health = health(Duration::from_secs(60));
+        assert!(!health.is_ready());
+        health.set_subscribed(true);
+        // Subscribed but still waiting for the group to assign partitions
+        assert!(!health.is_ready());
+        health.set_assigned_partitions(3);
+        assert!(health.is_ready());
+
+        health.set_assigned_partitions(0);
+        assert!(!health.is_ready());
+        health.set_assigned_partitions(2);
+        // Shutting down
+        health.set_subscribed(false);
+        assert!(!health.is_ready());
+    }
+
+    #[test]
+    fn fails_liveness_once_progress_stalls() {
+        let health = health(Duration::from_millis(50));
+        assert!(health.is_healthy());
+        std::thread::sleep(Duration::from_millis(80));
+        assert!(!health.is_healthy());
+        assert_eq!(health.respond("/healthz").unwrap().body, "stalled\n");
+
+        health.progress();
+        assert!(health.is_healthy());
+    }
+
+    #[test]
+    fn probes_answer_with_status_codes() {
+        let health = Arc::new(health(Duration::from_secs(60)));
+        assert_eq!(health.respond("/healthz").unwrap().status, "200 OK");
+        let not_ready = health.respond("/readyz").unwrap();
+        assert_eq!((not_ready.status, not_ready.body.as_str()), ("503 Service Unavailable", "not ready\n"));
+
+        health.set_subscribed(true);
+        health.set_assigned_partitions(1);
+        assert_eq!(health.respond("/readyz").unwrap().status, "200 OK");
+        assert!(health.respond("/metrics").is_none());
+        assert!(health.routes()("/healthz").is_some());
+    }
+
+    #[test]
+    fn status_reports_the_state_and_stats() {
+        let health = Arc::new(health(Duration::from_secs(60)));
+        health.set_subscribed(true);
+        health.set_assigned_partitions(2);
+        health.message(Some(1_700_000_000_000), Some(1.5));
+        health.set_consumer_lag(42);
+        health.stats.lock().unwrap().record_empty();
+
+        let response = health.respond("/status").unwrap();
+        assert_eq!((response.status, response.content_type), ("200 OK", "application/json"));
+        let status: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(status["healthy"], true);
+        assert_eq!(status["ready"], true);
+        assert_eq!(status["assigned_partitions"], 2);
+        assert_eq!(status["last_message_at"], "2023-11-14T22:13:20+00:00");
+        assert_eq!(status["end_to_end_lag_seconds"], 1.5);
+        assert_eq!(status["consumer_lag"], 42);
+        assert_eq!(status["stats"]["empty"], 1);
+    }
+
+    #[test]
+    fn status_leaves_unknown_values_null() {
+        let health = health(Duration::from_secs(60));
+        // Messages without a record timestamp clear the previous one
+        health.message(Some(1_700_000_000_000), None);
+        health.message(None, None);
+
+        let status: serde_json::Value = serde_json::from_str(&health.status_json().unwrap()).unwrap();
+        assert_eq!(status["ready"], false);
+        assert!(status["last_message_at"].is_null());
+        assert!(status["end_to_end_lag_seconds"].is_null());
+        assert!(status["consumer_lag"].is_null());
+    }
+}
@@ -1,112 +1,1948 @@
-use twine_types::proofs::ZkProof;
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use crate::artifacts::json_len;
+use crate::envelope::EnvelopeWithMetadata;
+use crate::dlq::{self, DeadLetter, DEFAULT_DLQ_DIR};
+use crate::metrics;
+use crate::msk_iam::{apply_msk_iam, KafkaClientContext, MskIamConfig, MSK_IAM_SUPPORTED};
+use crate::publish_queue::{PublishQueue, QueueFullPolicy};
+use crate::routing::{route_proof, Route, DEFAULT_NETWORK};
+use crate::spool::{Spool, SpoolError, SpooledMessage, DEFAULT_MAX_SPOOL_BYTES};
+use crate::schema_registry::{
+    encode_proof, encode_wire_format, proof_envelope_schema, SchemaRegistryClient,
+    SchemaRegistryConfig, PROOF_ENVELOPE_SUBJECT,
+};
+use solana_stub_prover_lib::PublicCommitments;
+use twine_types::proofs::{ProofData, ZkProof};
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Header, Headers, Message, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
 use rdkafka::ClientConfig;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
 use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
-use std::time::Duration;
-use std::path::Path;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
 
 const KAFKA_BROKER_TLS: &str = "kafka-bootstrap.twine.limited:443";
 const KAFKA_BROKER_PLAIN: &str = "b-1.test.7alql0.c5.kafka.us-east-1.amazonaws.com:9092";
-const KAFKA_TOPIC: &str = "twine.solana.proofs";
+pub const KAFKA_TOPIC: &str = "twine.solana.proofs";
+
+/// librdkafka's default `message.max.bytes`
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 1_000_000;
+
+/// Worst-case bytes a v2 record adds around its key, value and headers
+///
+/// Length, attributes, timestamp and offset deltas, key and value lengths and
+/// the header count, each at its largest varint size.
+pub const RECORD_OVERHEAD_BYTES: usize = 36;
+
+/// Default consumer group session timeout (`session.timeout.ms`)
+pub const DEFAULT_SESSION_TIMEOUT_MS: u64 = 6000;
+/// librdkafka's default `max.poll.interval.ms`
+pub const DEFAULT_MAX_POLL_INTERVAL_MS: u64 = 300_000;
+/// librdkafka's default `fetch.max.bytes`
+pub const DEFAULT_FETCH_MAX_BYTES: u64 = 52_428_800;
+
+/// Kafka header naming the payload encoding
+pub const CONTENT_TYPE_HEADER: &str = "content-type";
+/// Kafka header with the number of proofs an aggregate proof combines
+pub const AGGREGATED_COUNT_HEADER: &str = "aggregated-count";
+
+/// Encoding used for proof payloads on the topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadFormat {
+    /// serde_json encoding of `ZkProof`
+    #[default]
+    Json,
+    /// bincode encoding of `ZkProof`
+    Bincode,
+    /// Avro in Confluent wire format (requires a Schema Registry)
+    Avro,
+}
+
+impl PayloadFormat {
+    /// Value of the `content-type` header for this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "application/json",
+            PayloadFormat::Bincode => "application/x-bincode",
+            PayloadFormat::Avro => "application/vnd.confluent.avro",
+        }
+    }
+    
+    /// Parse a `content-type` header value
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "application/json" => Some(PayloadFormat::Json),
+            "application/x-bincode" => Some(PayloadFormat::Bincode),
+            "application/vnd.confluent.avro" => Some(PayloadFormat::Avro),
+            _ => None,
+        }
+    }
+    
+    /// Guess the format of a payload without a `content-type` header
+    ///
+    /// JSON envelopes start with `{`, Confluent wire-format messages with a
+    /// zero magic byte; anything else is assumed to be bincode.
+    pub fn sniff(payload: &[u8]) -> Self {
+        match payload.first() {
+            Some(b'{') => PayloadFormat::Json,
+            Some(0) if payload.len() >= 5 => PayloadFormat::Avro,
+            _ => PayloadFormat::Bincode,
+        }
+    }
+    
+    /// Format of a consumed message: its `content-type` header, sniffing the payload if absent
+    pub fn of_message<M: Message>(msg: &M, payload: &[u8]) -> Self {
+        msg.headers()
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|header| header.key == CONTENT_TYPE_HEADER)
+                    .and_then(|header| header.value)
+                    .and_then(|value| std::str::from_utf8(value).ok())
+                    .and_then(PayloadFormat::from_content_type)
+            })
+            .unwrap_or_else(|| PayloadFormat::sniff(payload))
+    }
+}
+
+impl FromStr for PayloadFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(PayloadFormat::Json),
+            "bincode" => Ok(PayloadFormat::Bincode),
+            "avro" => Ok(PayloadFormat::Avro),
+            other => Err(format!("Unknown payload format '{}' (expected json, bincode or avro)", other)),
+        }
+    }
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadFormat::Json => write!(f, "json"),
+            PayloadFormat::Bincode => write!(f, "bincode"),
+            PayloadFormat::Avro => write!(f, "avro"),
+        }
+    }
+}
+
+/// SASL authentication used on top of the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KafkaAuth {
+    /// No SASL; mutual TLS with client certificates when configured
+    #[default]
+    None,
+    /// AWS MSK IAM (SASL_SSL with OAUTHBEARER tokens signed by the AWS credentials chain)
+    MskIam,
+}
+
+impl FromStr for KafkaAuth {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(KafkaAuth::None),
+            "msk-iam" => Ok(KafkaAuth::MskIam),
+            other => Err(format!("Unknown Kafka auth mode '{}' (expected none or msk-iam)", other)),
+        }
+    }
+}
+
+impl fmt::Display for KafkaAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaAuth::None => write!(f, "none"),
+            KafkaAuth::MskIam => write!(f, "msk-iam"),
+        }
+    }
+}
+
+/// librdkafka `security.protocol`: whether the connection uses TLS and whether it uses SASL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityProtocol {
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    /// Protocol for a connection with or without TLS and SASL
+    pub fn new(tls: bool, sasl: bool) -> Self {
+        match (tls, sasl) {
+            (false, false) => SecurityProtocol::Plaintext,
+            (true, false) => SecurityProtocol::Ssl,
+            (false, true) => SecurityProtocol::SaslPlaintext,
+            (true, true) => SecurityProtocol::SaslSsl,
+        }
+    }
+    
+    pub fn uses_tls(&self) -> bool {
+        matches!(self, SecurityProtocol::Ssl | SecurityProtocol::SaslSsl)
+    }
+    
+    pub fn uses_sasl(&self) -> bool {
+        matches!(self, SecurityProtocol::SaslPlaintext | SecurityProtocol::SaslSsl)
+    }
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "plaintext" => Ok(SecurityProtocol::Plaintext),
+            "ssl" => Ok(SecurityProtocol::Ssl),
+            "sasl_plaintext" => Ok(SecurityProtocol::SaslPlaintext),
+            "sasl_ssl" => Ok(SecurityProtocol::SaslSsl),
+            _ => Err(format!(
+                "Unknown security protocol '{}' (expected plaintext, ssl, sasl_plaintext or sasl_ssl)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SecurityProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecurityProtocol::Plaintext => write!(f, "plaintext"),
+            SecurityProtocol::Ssl => write!(f, "ssl"),
+            SecurityProtocol::SaslPlaintext => write!(f, "sasl_plaintext"),
+            SecurityProtocol::SaslSsl => write!(f, "sasl_ssl"),
+        }
+    }
+}
+
+/// SASL mechanism authenticated with a username and password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+impl FromStr for SaslMechanism {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().replace('_', "-").as_str() {
+            "PLAIN" => Ok(SaslMechanism::Plain),
+            "SCRAM-SHA-256" => Ok(SaslMechanism::ScramSha256),
+            "SCRAM-SHA-512" => Ok(SaslMechanism::ScramSha512),
+            _ => Err(format!(
+                "Unknown SASL mechanism '{}' (expected PLAIN, SCRAM-SHA-256 or SCRAM-SHA-512)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SaslMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaslMechanism::Plain => write!(f, "PLAIN"),
+            SaslMechanism::ScramSha256 => write!(f, "SCRAM-SHA-256"),
+            SaslMechanism::ScramSha512 => write!(f, "SCRAM-SHA-512"),
+        }
+    }
+}
+
+/// Strategy for deriving the record key (and therefore the partition) of a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionKey {
+    /// The proof identifier
+    #[default]
+    Identifier,
+    /// Base58 pubkey of the first monitored account, keeping each account's proofs in order on one partition
+    Account,
+    /// `{start_slot}-{end_slot}` of the proven range
+    SlotRange,
+}
+
+impl PartitionKey {
+    /// Record key for a proof under this strategy
+    ///
+    /// Falls back to the identifier when the public values cannot be decoded.
+    pub fn record_key(&self, proof: &ZkProof) -> String {
+        if *self == PartitionKey::Identifier {
+            return proof.identifier.clone();
+        }
+        
+        let ProofData::SP1(sp1_proof) = &proof.proof_data;
+        let Ok(commitments) = bincode::deserialize::<PublicCommitments>(&sp1_proof.public_value) else {
+            return proof.identifier.clone();
+        };
+        
+        match self {
+            PartitionKey::Account => commitments
+                .monitored_accounts_state
+                .first()
+                .map(|account| bs58::encode(account.account_pubkey).into_string())
+                .unwrap_or_else(|| proof.identifier.clone()),
+            PartitionKey::SlotRange => format!("{}-{}", commitments.start_slot, commitments.end_slot),
+            PartitionKey::Identifier => proof.identifier.clone(),
+        }
+    }
+}
+
+impl FromStr for PartitionKey {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "identifier" => Ok(PartitionKey::Identifier),
+            "account" => Ok(PartitionKey::Account),
+            "slot-range" => Ok(PartitionKey::SlotRange),
+            other => Err(format!("Unknown partition key '{}' (expected identifier, account or slot-range)", other)),
+        }
+    }
+}
+
+impl fmt::Display for PartitionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionKey::Identifier => write!(f, "identifier"),
+            PartitionKey::Account => write!(f, "account"),
+            PartitionKey::SlotRange => write!(f, "slot-range"),
+        }
+    }
+}
+
+/// Placeholders accepted by `--kafka-key-template`
+pub const KEY_TEMPLATE_PLACEHOLDERS: [&str; 3] = ["account", "start", "end"];
+
+/// Check that a key template only uses known placeholders
+pub fn validate_key_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in key template '{}'", template))?;
+        let name = &rest[open + 1..open + close];
+        if !KEY_TEMPLATE_PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder '{{{}}}' in key template '{}' (expected {{account}}, {{start}} or {{end}})",
+                name, template
+            ));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    Ok(())
+}
+
+/// Render a record key template for a proof
+///
+/// `{account}` is the base58 pubkey of the first monitored account, `{start}`
+/// and `{end}` the proven slot range. Falls back to the identifier when the
+/// public values cannot be decoded.
+pub fn render_key_template(template: &str, proof: &ZkProof) -> String {
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    let Ok(commitments) = bincode::deserialize::<PublicCommitments>(&sp1_proof.public_value) else {
+        return proof.identifier.clone();
+    };
+    let account = commitments
+        .monitored_accounts_state
+        .first()
+        .map(|account| bs58::encode(account.account_pubkey).into_string())
+        .unwrap_or_default();
+    
+    template
+        .replace("{account}", &account)
+        .replace("{start}", &commitments.start_slot.to_string())
+        .replace("{end}", &commitments.end_slot.to_string())
+}
+
+/// Retry policy applied to retryable publish errors
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for every following retry
+    pub base_delay: Duration,
+    /// Upper bound for the delay between two attempts
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay to wait after the given (1-based) failed attempt
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Error returned by the proof publisher
+#[derive(Debug)]
+pub enum KafkaPublishError {
+    /// Transient broker or network error that persisted through all retries
+    Retryable(KafkaError),
+    /// Error that cannot be fixed by retrying (auth, unknown topic, serialization, ...)
+    Fatal(Box<dyn Error + Send + Sync>),
+    /// Another producer with the same `transactional.id` has taken over
+    Fenced(KafkaError),
+    /// Payload exceeds the maximum message size
+    ///
+    /// `limit` is the producer's `message.max.bytes`; it is 0 when the size
+    /// check passed locally but the broker rejected the message.
+    Oversized { size: usize, limit: usize },
+    /// The broker was unreachable and the message was written to the local spool
+    ///
+    /// Not a loss: the message is published before anything else once the broker is back.
+    Spooled(PathBuf),
+}
+
+impl KafkaPublishError {
+    /// Classify an rdkafka error for a payload of the given size
+    pub fn from_kafka_error(error: KafkaError, size: usize) -> Self {
+        match error.rdkafka_error_code() {
+            Some(
+                RDKafkaErrorCode::QueueFull
+                | RDKafkaErrorCode::MessageTimedOut
+                | RDKafkaErrorCode::RequestTimedOut
+                | RDKafkaErrorCode::OperationTimedOut
+                | RDKafkaErrorCode::BrokerTransportFailure
+                | RDKafkaErrorCode::AllBrokersDown
+                | RDKafkaErrorCode::NetworkException
+                | RDKafkaErrorCode::LeaderNotAvailable
+                | RDKafkaErrorCode::NotLeaderForPartition,
+            ) => KafkaPublishError::Retryable(error),
+            Some(
+                RDKafkaErrorCode::Fenced
+                | RDKafkaErrorCode::ProducerFenced
+                | RDKafkaErrorCode::TransactionCoordinatorFenced,
+            ) => KafkaPublishError::Fenced(error),
+            Some(RDKafkaErrorCode::MessageSizeTooLarge) => KafkaPublishError::Oversized {
+                size,
+                limit: 0,
+            },
+            _ => KafkaPublishError::Fatal(Box::new(error)),
+        }
+    }
+    
+    /// Whether the publish may succeed if attempted again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, KafkaPublishError::Retryable(_))
+    }
+    
+    /// Whether this producer instance has been fenced and must shut down
+    pub fn is_fenced(&self) -> bool {
+        matches!(self, KafkaPublishError::Fenced(_))
+    }
+    
+    /// Whether the message was kept in the local spool for later delivery
+    pub fn is_spooled(&self) -> bool {
+        matches!(self, KafkaPublishError::Spooled(_))
+    }
+    
+    /// Short label used for metrics
+    pub fn reason(&self) -> &'static str {
+        match self {
+            KafkaPublishError::Retryable(_) => "retryable",
+            KafkaPublishError::Fatal(_) => "fatal",
+            KafkaPublishError::Fenced(_) => "fenced",
+            KafkaPublishError::Oversized { .. } => "oversized",
+            KafkaPublishError::Spooled(_) => "spooled",
+        }
+    }
+}
+
+impl fmt::Display for KafkaPublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaPublishError::Retryable(e) => write!(f, "Kafka publish failed after retries: {}", e),
+            KafkaPublishError::Fatal(e) => write!(f, "Kafka publish failed: {}", e),
+            KafkaPublishError::Fenced(e) => write!(f, "Kafka producer was fenced by a newer instance: {}", e),
+            KafkaPublishError::Oversized { size, limit } if *limit == 0 => {
+                write!(f, "Message of {} bytes rejected by the broker as too large", size)
+            }
+            KafkaPublishError::Oversized { size, limit } => {
+                write!(f, "Message of {} bytes exceeds the limit of {} bytes", size, limit)
+            }
+            KafkaPublishError::Spooled(path) => {
+                write!(f, "Kafka unavailable, message spooled to {}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for KafkaPublishError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KafkaPublishError::Retryable(e) => Some(e),
+            KafkaPublishError::Fatal(e) => Some(e.as_ref()),
+            KafkaPublishError::Fenced(e) => Some(e),
+            KafkaPublishError::Oversized { .. } | KafkaPublishError::Spooled(_) => None,
+        }
+    }
+}
+
+/// Invalid Kafka configuration, detected before connecting
+#[derive(Debug)]
+pub enum KafkaConfigError {
+    /// An environment variable has a value that cannot be parsed
+    InvalidEnv { var: &'static str, value: String },
+    /// The broker list is not a comma-separated list of host:port pairs
+    InvalidBroker(String),
+    /// A configured certificate or key file does not exist
+    MissingFile { path: String, description: &'static str },
+    /// A certificate or key file exists but cannot be read
+    UnreadableFile { path: String, description: &'static str, source: std::io::Error },
+    /// A certificate or key file is not valid PEM
+    InvalidPem { path: String, description: &'static str, reason: String },
+    /// A PEM file holds the wrong kind of object, e.g. a key where a certificate is expected
+    WrongPemType { path: String, description: &'static str, expected: &'static str, found: String },
+    /// Options that cannot be used together
+    Conflict(String),
+}
+
+impl fmt::Display for KafkaConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaConfigError::InvalidEnv { var, value } => write!(f, "Invalid value for {}: '{}'", var, value),
+            KafkaConfigError::InvalidBroker(broker) => {
+                write!(f, "Invalid broker '{}': expected host:port (comma-separated for multiple brokers)", broker)
+            }
+            KafkaConfigError::MissingFile { path, description } => write!(f, "{} not found at {}", description, path),
+            KafkaConfigError::UnreadableFile { path, description, source } => {
+                write!(f, "{} at {} cannot be read: {}", description, path, source)
+            }
+            KafkaConfigError::InvalidPem { path, description, reason } => {
+                write!(f, "{} at {} is not valid PEM: {}", description, path, reason)
+            }
+            KafkaConfigError::WrongPemType { path, description, expected, found } => write!(
+                f,
+                "{} at {} contains {} instead of {} (are the certificate and key paths swapped?)",
+                description, path, found, expected
+            ),
+            KafkaConfigError::Conflict(message) => write!(f, "Conflicting Kafka options: {}", message),
+        }
+    }
+}
+
+impl Error for KafkaConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KafkaConfigError::UnreadableFile { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Read an environment variable, treating empty values as unset
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Parse the labels of all PEM blocks in a file (e.g. `CERTIFICATE`, `PRIVATE KEY`)
+pub fn parse_pem_labels(contents: &str) -> Result<Vec<String>, String> {
+    let mut labels = Vec::new();
+    let mut lines = contents.lines().map(str::trim);
+    
+    while let Some(line) = lines.next() {
+        let Some(label) = line.strip_prefix("-----BEGIN ").and_then(|l| l.strip_suffix("-----")) else {
+            continue;
+        };
+        
+        let end_marker = format!("-----END {}-----", label);
+        let mut body = String::new();
+        loop {
+            match lines.next() {
+                Some(l) if l == end_marker => break,
+                Some(l) if l.contains(':') => continue, // encapsulated headers of legacy encrypted keys
+                Some(l) => body.push_str(l),
+                None => return Err(format!("missing '{}'", end_marker)),
+            }
+        }
+        
+        general_purpose::STANDARD
+            .decode(&body)
+            .map_err(|e| format!("invalid base64 in {} block: {}", label, e))?;
+        labels.push(label.to_string());
+    }
+    
+    if labels.is_empty() {
+        return Err("no PEM blocks found".to_string());
+    }
+    Ok(labels)
+}
+
+/// Failure of the publisher's connectivity pre-flight check
+#[derive(Debug)]
+pub enum PreflightError {
+    /// Cluster metadata could not be fetched
+    Unreachable { broker: String, source: KafkaError },
+    /// Target topics do not exist and were not created
+    MissingTopics { broker: String, topics: Vec<String> },
+    /// A missing topic could not be created
+    CreateTopic { broker: String, topic: String, reason: String },
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreflightError::Unreachable { broker, source } => {
+                write!(f, "Cannot reach Kafka broker {}: {}", broker, source)
+            }
+            PreflightError::MissingTopics { broker, topics } => write!(
+                f,
+                "Topic(s) {} do not exist on {} (use --create-topic-if-missing to create them)",
+                topics.join(", "),
+                broker
+            ),
+            PreflightError::CreateTopic { broker, topic, reason } => {
+                write!(f, "Failed to create topic {} on {}: {}", topic, broker, reason)
+            }
+        }
+    }
+}
+
+impl Error for PreflightError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PreflightError::Unreachable { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for topics created by the pre-flight check
+#[derive(Debug, Clone, Copy)]
+pub struct TopicCreation {
+    pub partitions: i32,
+    pub replication_factor: i32,
+}
 
 /// Kafka configuration options
+#[derive(Clone)]
 pub struct KafkaConfig {
     pub use_tls: bool,
     pub ca_cert_path: Option<String>,
     pub client_cert_path: Option<String>,
     pub client_key_path: Option<String>,
     pub broker: Option<String>,
+    /// `client.id` reported to the brokers (librdkafka's default when unset)
+    pub client_id: Option<String>,
+    /// SASL authentication mode
+    pub auth: KafkaAuth,
+    /// AWS region of the MSK cluster (MSK IAM only)
+    pub aws_region: Option<String>,
+    /// Role assumed before signing MSK IAM tokens
+    pub aws_role_arn: Option<String>,
+    /// Security protocol requested explicitly; checked against the TLS and SASL settings
+    pub protocol: Option<SecurityProtocol>,
+    /// SASL mechanism authenticated with `sasl_username` and `sasl_password`
+    pub sasl_mechanism: Option<SaslMechanism>,
+    pub sasl_username: Option<String>,
+    pub sasl_password: Option<String>,
+    /// Topic proofs are published to when no route matches
+    pub topic: String,
+    /// Topic routes by proof kind or network, first match wins
+    pub routes: Vec<Route>,
+    /// Solana network proofs are generated against, matched by network routes
+    pub network: String,
+    /// Delivery timeout for a single attempt (`message.timeout.ms`)
+    pub message_timeout_ms: u64,
+    /// Largest payload the producer will attempt to send (`message.max.bytes`)
+    pub max_message_bytes: usize,
+    pub retry_policy: RetryPolicy,
+    /// Encoding used by `ProofPublisher::publish_proof`
+    pub payload_format: PayloadFormat,
+    /// Record key strategy used by `ProofPublisher::publish_proof`
+    pub partition_key: PartitionKey,
+    /// Record key template overriding `partition_key`, e.g. `{account}-{end}`
+    pub key_template: Option<String>,
+    /// Schema Registry used when `payload_format` is Avro
+    pub schema_registry: Option<SchemaRegistryConfig>,
+    /// Enables transactional (exactly-once) publishing with this `transactional.id`
+    pub transactional_id: Option<String>,
+    /// Topic receiving proofs that failed to publish
+    pub dlq_topic: Option<String>,
+    /// Directory dead letters are spooled to when the DLQ topic is unavailable
+    pub dlq_dir: PathBuf,
+    /// Skip missing certificate files with a warning instead of failing validation
+    pub allow_missing_certs: bool,
+    /// Directory proofs are spooled to while the broker is unreachable
+    pub spool_dir: Option<PathBuf>,
+    /// Size limit of the spool directory in bytes
+    pub max_spool_bytes: u64,
+}
+
+/// Stands in for a secret in `Debug` output, showing only whether it is set
+pub(crate) fn redacted(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| "<redacted>")
+}
+
+// Written out so the SASL password never appears in logs
+impl fmt::Debug for KafkaConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaConfig")
+            .field("use_tls", &self.use_tls)
+            .field("ca_cert_path", &self.ca_cert_path)
+            .field("client_cert_path", &self.client_cert_path)
+            .field("client_key_path", &self.client_key_path)
+            .field("broker", &self.broker)
+            .field("client_id", &self.client_id)
+            .field("auth", &self.auth)
+            .field("aws_region", &self.aws_region)
+            .field("aws_role_arn", &self.aws_role_arn)
+            .field("protocol", &self.protocol)
+            .field("sasl_mechanism", &self.sasl_mechanism)
+            .field("sasl_username", &self.sasl_username)
+            .field("sasl_password", &redacted(&self.sasl_password))
+            .field("topic", &self.topic)
+            .field("routes", &self.routes)
+            .field("network", &self.network)
+            .field("message_timeout_ms", &self.message_timeout_ms)
+            .field("max_message_bytes", &self.max_message_bytes)
+            .field("retry_policy", &self.retry_policy)
+            .field("payload_format", &self.payload_format)
+            .field("partition_key", &self.partition_key)
+            .field("key_template", &self.key_template)
+            .field("schema_registry", &self.schema_registry)
+            .field("transactional_id", &self.transactional_id)
+            .field("dlq_topic", &self.dlq_topic)
+            .field("dlq_dir", &self.dlq_dir)
+            .field("allow_missing_certs", &self.allow_missing_certs)
+            .field("spool_dir", &self.spool_dir)
+            .field("max_spool_bytes", &self.max_spool_bytes)
+            .finish()
+    }
+}
+
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            use_tls: true, // Default to TLS
+            ca_cert_path: Some("./ca.crt".to_string()),
+            client_cert_path: Some("./user.crt".to_string()),
+            client_key_path: Some("./user.key".to_string()),
+            broker: None,
+            client_id: None,
+            auth: KafkaAuth::None,
+            aws_region: None,
+            aws_role_arn: None,
+            protocol: None,
+            sasl_mechanism: None,
+            sasl_username: None,
+            sasl_password: None,
+            topic: KAFKA_TOPIC.to_string(),
+            routes: Vec::new(),
+            network: DEFAULT_NETWORK.to_string(),
+            message_timeout_ms: 5000,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            retry_policy: RetryPolicy::default(),
+            payload_format: PayloadFormat::Json,
+            partition_key: PartitionKey::Identifier,
+            key_template: None,
+            schema_registry: None,
+            transactional_id: None,
+            dlq_topic: None,
+            dlq_dir: PathBuf::from(DEFAULT_DLQ_DIR),
+            allow_missing_certs: false,
+            spool_dir: None,
+            max_spool_bytes: DEFAULT_MAX_SPOOL_BYTES,
+        }
+    }
+}
+
+/// Connection settings given on the command line, taking precedence over `KAFKA_*` variables
+///
+/// The producer, consumer and admin binaries name their flags differently but
+/// resolve them all through [`KafkaConfig::from_env_with`], so the same
+/// settings produce the same client configuration in every binary.
+#[derive(Clone, Default)]
+pub struct ConnectionOptions {
+    pub broker: Option<String>,
+    pub client_id: Option<String>,
+    /// Force a plain connection (`--no-tls`)
+    pub disable_tls: bool,
+    pub protocol: Option<SecurityProtocol>,
+    pub auth: Option<KafkaAuth>,
+    pub sasl_mechanism: Option<SaslMechanism>,
+    pub sasl_username: Option<String>,
+    pub sasl_password: Option<String>,
+    pub aws_region: Option<String>,
+    pub aws_role_arn: Option<String>,
+    pub ca_cert_path: Option<String>,
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+    pub allow_missing_certs: bool,
+}
+
+impl fmt::Debug for ConnectionOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionOptions")
+            .field("broker", &self.broker)
+            .field("client_id", &self.client_id)
+            .field("disable_tls", &self.disable_tls)
+            .field("protocol", &self.protocol)
+            .field("auth", &self.auth)
+            .field("sasl_mechanism", &self.sasl_mechanism)
+            .field("sasl_username", &self.sasl_username)
+            .field("sasl_password", &redacted(&self.sasl_password))
+            .field("aws_region", &self.aws_region)
+            .field("aws_role_arn", &self.aws_role_arn)
+            .field("ca_cert_path", &self.ca_cert_path)
+            .field("client_cert_path", &self.client_cert_path)
+            .field("client_key_path", &self.client_key_path)
+            .field("allow_missing_certs", &self.allow_missing_certs)
+            .finish()
+    }
+}
+
+impl KafkaConfig {
+    /// Build a configuration from `KAFKA_*` environment variables, falling back to the defaults
+    pub fn from_env() -> Result<Self, KafkaConfigError> {
+        Self::from_env_with(&ConnectionOptions::default())
+    }
+    
+    /// Build a configuration from `KAFKA_*` environment variables overridden by `options`
+    ///
+    /// Reads `KAFKA_BROKER`, `KAFKA_CLIENT_ID`, `KAFKA_USE_TLS`, `KAFKA_SECURITY_PROTOCOL`,
+    /// `KAFKA_AUTH`, `KAFKA_SASL_MECHANISM`, `KAFKA_USERNAME`, `KAFKA_PASSWORD`,
+    /// `KAFKA_CA_CERT`, `KAFKA_CLIENT_CERT`, `KAFKA_CLIENT_KEY`, `KAFKA_TOPIC`,
+    /// `AWS_REGION` and `AWS_ROLE_ARN`. An explicit security protocol decides
+    /// whether TLS is used; the default certificate paths only apply to TLS
+    /// without SASL. Combinations are checked by [`KafkaConfig::validate`].
+    pub fn from_env_with(options: &ConnectionOptions) -> Result<Self, KafkaConfigError> {
+        let mut config = Self::default();
+        
+        let env_tls = match env_var("KAFKA_USE_TLS") {
+            Some(value) => match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => return Err(KafkaConfigError::InvalidEnv { var: "KAFKA_USE_TLS", value }),
+            },
+            None => true,
+        };
+        config.protocol = match (options.protocol, env_var("KAFKA_SECURITY_PROTOCOL")) {
+            (Some(protocol), _) => Some(protocol),
+            (None, Some(value)) => Some(
+                value.parse().map_err(|_| KafkaConfigError::InvalidEnv { var: "KAFKA_SECURITY_PROTOCOL", value })?,
+            ),
+            (None, None) => None,
+        };
+        config.auth = match (options.auth, env_var("KAFKA_AUTH")) {
+            (Some(auth), _) => auth,
+            (None, Some(value)) => value.parse().map_err(|_| KafkaConfigError::InvalidEnv { var: "KAFKA_AUTH", value })?,
+            (None, None) => KafkaAuth::None,
+        };
+        config.sasl_mechanism = match (options.sasl_mechanism, env_var("KAFKA_SASL_MECHANISM")) {
+            (Some(mechanism), _) => Some(mechanism),
+            (None, Some(value)) => Some(
+                value.parse().map_err(|_| KafkaConfigError::InvalidEnv { var: "KAFKA_SASL_MECHANISM", value })?,
+            ),
+            (None, None) => None,
+        };
+        config.sasl_username = options.sasl_username.clone().or_else(|| env_var("KAFKA_USERNAME"));
+        config.sasl_password = options.sasl_password.clone().or_else(|| env_var("KAFKA_PASSWORD"));
+        config.aws_region = options.aws_region.clone().or_else(|| env_var("AWS_REGION"));
+        config.aws_role_arn = options.aws_role_arn.clone().or_else(|| env_var("AWS_ROLE_ARN"));
+        
+        // MSK IAM brokers always use TLS unless the protocol says otherwise, which validation rejects
+        config.use_tls = match config.protocol {
+            Some(protocol) => protocol.uses_tls(),
+            None => env_tls || config.auth == KafkaAuth::MskIam,
+        };
+        if options.disable_tls {
+            config.use_tls = false;
+        }
+        
+        // SASL authenticates the client and MSK brokers use the system trust store,
+        // so only certificate files configured explicitly are used with SASL
+        if !config.use_tls || config.uses_sasl() {
+            config.ca_cert_path = None;
+            config.client_cert_path = None;
+            config.client_key_path = None;
+        }
+        if let Some(path) = options.ca_cert_path.clone().or_else(|| env_var("KAFKA_CA_CERT")) {
+            config.ca_cert_path = Some(path);
+        }
+        if let Some(path) = options.client_cert_path.clone().or_else(|| env_var("KAFKA_CLIENT_CERT")) {
+            config.client_cert_path = Some(path);
+        }
+        if let Some(path) = options.client_key_path.clone().or_else(|| env_var("KAFKA_CLIENT_KEY")) {
+            config.client_key_path = Some(path);
+        }
+        config.allow_missing_certs = options.allow_missing_certs;
+        
+        if let Some(broker) = options.broker.clone().or_else(|| env_var("KAFKA_BROKER")) {
+            config.broker = Some(broker);
+        }
+        config.client_id = options.client_id.clone().or_else(|| env_var("KAFKA_CLIENT_ID"));
+        if let Some(topic) = env_var("KAFKA_TOPIC") {
+            config.topic = topic;
+        }
+        
+        Ok(config)
+    }
+    
+    /// MSK IAM signing settings, when MSK IAM authentication is enabled
+    pub fn msk_iam_config(&self) -> Option<MskIamConfig> {
+        match self.auth {
+            KafkaAuth::MskIam => Some(MskIamConfig {
+                region: self.aws_region.clone().unwrap_or_default(),
+                role_arn: self.aws_role_arn.clone(),
+            }),
+            KafkaAuth::None => None,
+        }
+    }
+    
+    /// Client context carrying the MSK IAM token provider, if any
+    pub fn client_context(&self) -> KafkaClientContext {
+        KafkaClientContext::new(self.msk_iam_config())
+    }
+    
+    /// Whether clients authenticate with SASL (MSK IAM or a username and password)
+    pub fn uses_sasl(&self) -> bool {
+        self.auth == KafkaAuth::MskIam || self.sasl_mechanism.is_some()
+    }
+    
+    /// Security protocol resulting from the TLS and SASL settings
+    pub fn security_protocol(&self) -> SecurityProtocol {
+        SecurityProtocol::new(self.use_tls, self.uses_sasl())
+    }
+    
+    /// Security protocol and authentication, e.g. `sasl_ssl (SCRAM-SHA-512)`
+    pub fn security_summary(&self) -> String {
+        let protocol = self.security_protocol();
+        match (self.auth, self.sasl_mechanism) {
+            (KafkaAuth::MskIam, _) => format!("{} (MSK IAM)", protocol),
+            (KafkaAuth::None, Some(mechanism)) => format!("{} ({})", protocol, mechanism),
+            (KafkaAuth::None, None) => protocol.to_string(),
+        }
+    }
+    
+    /// Apply the TLS and SASL settings to a librdkafka client configuration
+    ///
+    /// Both are applied together, so `sasl_ssl` gets the certificate files
+    /// as well as the SASL credentials.
+    pub fn apply_security(&self, client_config: &mut ClientConfig) {
+        let protocol = self.security_protocol();
+        client_config.set("security.protocol", protocol.to_string());
+        if protocol.uses_tls() {
+            for (property, path) in self.ssl_file_settings() {
+                client_config.set(property, path);
+            }
+        }
+        if self.auth == KafkaAuth::MskIam {
+            apply_msk_iam(client_config);
+        } else if let Some(mechanism) = self.sasl_mechanism {
+            client_config.set("sasl.mechanism", mechanism.to_string());
+            if let Some(username) = &self.sasl_username {
+                client_config.set("sasl.username", username);
+            }
+            if let Some(password) = &self.sasl_password {
+                client_config.set("sasl.password", password);
+            }
+        }
+    }
+    
+    /// Client configuration shared by the producer, consumer and admin clients
+    ///
+    /// Holds the brokers, the client ID and the TLS and SASL settings; each
+    /// client adds its own properties.
+    pub fn client_config(&self) -> ClientConfig {
+        let mut client_config = ClientConfig::new();
+        client_config.set("bootstrap.servers", self.broker());
+        if let Some(client_id) = &self.client_id {
+            client_config.set("client.id", client_id);
+        }
+        self.apply_security(&mut client_config);
+        client_config
+    }
+    
+    /// Broker list, falling back to the default endpoint for the TLS mode
+    pub fn broker(&self) -> &str {
+        self.broker.as_deref().unwrap_or(if self.use_tls {
+            KAFKA_BROKER_TLS
+        } else {
+            KAFKA_BROKER_PLAIN
+        })
+    }
+    
+    /// Check the configuration before any connection is attempted
+    pub fn validate(&self) -> Result<(), KafkaConfigError> {
+        for broker in self.broker().split(',').map(str::trim) {
+            let valid = broker
+                .rsplit_once(':')
+                .map(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+                .unwrap_or(false);
+            if !valid {
+                return Err(KafkaConfigError::InvalidBroker(broker.to_string()));
+            }
+        }
+        
+        if self.topic.is_empty() {
+            return Err(KafkaConfigError::Conflict("topic must not be empty".to_string()));
+        }
+        
+        if self.client_id.as_deref().is_some_and(|client_id| client_id.trim().is_empty()) {
+            return Err(KafkaConfigError::Conflict("client ID must not be empty".to_string()));
+        }
+        
+        // librdkafka's ranges, which it would otherwise only report when the producer is created
+        if !(1000..=1_000_000_000).contains(&self.max_message_bytes) {
+            return Err(KafkaConfigError::Conflict(format!(
+                "max message bytes must be between 1000 and 1000000000, got {}",
+                self.max_message_bytes
+            )));
+        }
+        if self.message_timeout_ms > i32::MAX as u64 {
+            return Err(KafkaConfigError::Conflict(format!(
+                "message timeout must be at most {} ms, got {}",
+                i32::MAX,
+                self.message_timeout_ms
+            )));
+        }
+        if self.retry_policy.max_attempts == 0 {
+            return Err(KafkaConfigError::Conflict("retry attempts must be at least 1".to_string()));
+        }
+        if self.retry_policy.base_delay > self.retry_policy.max_delay {
+            return Err(KafkaConfigError::Conflict(format!(
+                "retry base delay ({:?}) must not exceed the max delay ({:?})",
+                self.retry_policy.base_delay, self.retry_policy.max_delay
+            )));
+        }
+        
+        let cert_files = [
+            (&self.ca_cert_path, "CA certificate", PemKind::Certificate),
+            (&self.client_cert_path, "Client certificate", PemKind::Certificate),
+            (&self.client_key_path, "Client key", PemKind::PrivateKey),
+        ];
+        
+        if !self.use_tls {
+            if let Some((_, description, _)) = cert_files.iter().find(|(path, _, _)| path.is_some()) {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "{} configured but TLS is disabled",
+                    description
+                )));
+            }
+        }
+        
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            return Err(KafkaConfigError::Conflict(
+                "client certificate and client key must be configured together".to_string(),
+            ));
+        }
+        
+        if self.payload_format == PayloadFormat::Avro && self.schema_registry.is_none() {
+            return Err(KafkaConfigError::Conflict(
+                "avro payload format requires a Schema Registry".to_string(),
+            ));
+        }
+        
+        if let Some(template) = &self.key_template {
+            validate_key_template(template).map_err(KafkaConfigError::Conflict)?;
+        }
+        
+        if self.auth == KafkaAuth::MskIam {
+            if !MSK_IAM_SUPPORTED {
+                return Err(KafkaConfigError::Conflict(
+                    "msk-iam authentication requires building with the msk-iam feature".to_string(),
+                ));
+            }
+            if !self.use_tls {
+                return Err(KafkaConfigError::Conflict("msk-iam authentication requires TLS".to_string()));
+            }
+            if self.aws_region.is_none() {
+                return Err(KafkaConfigError::Conflict(
+                    "msk-iam authentication requires an AWS region (--aws-region or AWS_REGION)".to_string(),
+                ));
+            }
+        }
+        
+        if let Some(protocol) = self.protocol {
+            if protocol.uses_tls() && !self.use_tls {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "security protocol {} requires TLS, which is disabled",
+                    protocol
+                )));
+            }
+            if !protocol.uses_tls() && self.use_tls {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "security protocol {} does not use TLS, but TLS is enabled",
+                    protocol
+                )));
+            }
+            if protocol.uses_sasl() && !self.uses_sasl() {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "security protocol {} requires a SASL mechanism or msk-iam authentication",
+                    protocol
+                )));
+            }
+            if !protocol.uses_sasl() && self.uses_sasl() {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "security protocol {} does not use SASL, but {} authentication is configured",
+                    protocol,
+                    self.sasl_mechanism.map_or_else(|| self.auth.to_string(), |mechanism| mechanism.to_string())
+                )));
+            }
+        }
+        
+        if let Some(mechanism) = self.sasl_mechanism {
+            if self.auth == KafkaAuth::MskIam {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "msk-iam authentication cannot be combined with SASL mechanism {}",
+                    mechanism
+                )));
+            }
+            if self.sasl_username.is_none() || self.sasl_password.is_none() {
+                return Err(KafkaConfigError::Conflict(format!(
+                    "SASL {} requires a username and password (KAFKA_USERNAME and KAFKA_PASSWORD)",
+                    mechanism
+                )));
+            }
+        }
+        
+        if self.spool_dir.is_some() && self.transactional_id.is_some() {
+            return Err(KafkaConfigError::Conflict(
+                "the local spool cannot be combined with transactional publishing".to_string(),
+            ));
+        }
+        
+        for (path, description, kind) in cert_files {
+            let Some(path) = path else { continue };
+            if !Path::new(path).exists() {
+                if self.allow_missing_certs {
+                    eprintln!("Warning: {} not found at {}, continuing without it", description, path);
+                    continue;
+                }
+                return Err(KafkaConfigError::MissingFile { path: path.clone(), description });
+            }
+            let contents = std::fs::read_to_string(path).map_err(|source| KafkaConfigError::UnreadableFile {
+                path: path.clone(),
+                description,
+                source,
+            })?;
+            let labels = parse_pem_labels(&contents).map_err(|reason| KafkaConfigError::InvalidPem {
+                path: path.clone(),
+                description,
+                reason,
+            })?;
+            if !labels.iter().any(|label| kind.matches(label)) {
+                return Err(KafkaConfigError::WrongPemType {
+                    path: path.clone(),
+                    description,
+                    expected: kind.name(),
+                    found: labels.join(", "),
+                });
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// librdkafka SSL properties for the configured certificate files
+    ///
+    /// With `allow_missing_certs`, files that do not exist are left out.
+    pub fn ssl_file_settings(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("ssl.ca.location", &self.ca_cert_path),
+            ("ssl.certificate.location", &self.client_cert_path),
+            ("ssl.key.location", &self.client_key_path),
+        ]
+        .into_iter()
+        .filter_map(|(property, path)| path.as_deref().map(|path| (property, path)))
+        .filter(|(_, path)| !self.allow_missing_certs || Path::new(path).exists())
+        .collect()
+    }
+}
+
+/// Expected content of a PEM file
+#[derive(Debug, Clone, Copy)]
+enum PemKind {
+    Certificate,
+    PrivateKey,
 }
 
-impl Default for KafkaConfig {
+impl PemKind {
+    fn matches(&self, label: &str) -> bool {
+        match self {
+            PemKind::Certificate => label == "CERTIFICATE" || label == "TRUSTED CERTIFICATE",
+            PemKind::PrivateKey => label.ends_with("PRIVATE KEY"),
+        }
+    }
+    
+    fn name(&self) -> &'static str {
+        match self {
+            PemKind::Certificate => "a CERTIFICATE",
+            PemKind::PrivateKey => "a PRIVATE KEY",
+        }
+    }
+}
+
+/// Create a Kafka producer with the given configuration
+///
+/// Certificate files are not checked here; call `KafkaConfig::validate` first.
+pub fn create_producer(config: &KafkaConfig) -> Result<FutureProducer<KafkaClientContext>, Box<dyn Error>> {
+    // Brokers, client ID, TLS and SASL
+    let mut client_config = config.client_config();
+    
+    let broker = config.broker();
+    
+    client_config.set("message.timeout.ms", config.message_timeout_ms.to_string());
+    client_config.set("message.max.bytes", config.max_message_bytes.to_string());
+    
+    if let Some(transactional_id) = &config.transactional_id {
+        client_config.set("transactional.id", transactional_id);
+        client_config.set("enable.idempotence", "true");
+    }
+    
+    if config.auth == KafkaAuth::MskIam {
+        println!("Using MSK IAM authentication to {}", broker);
+    } else if let Some(mechanism) = config.sasl_mechanism {
+        println!("Using SASL {} authentication ({}) to {}", mechanism, config.security_protocol(), broker);
+    } else if config.use_tls {
+        println!("Using TLS connection to {}", broker);
+    } else {
+        println!("Using plain connection to {}", broker);
+    }
+    
+    client_config
+        .create_with_context(config.client_context())
+        .map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+/// Create an admin client with the connection settings of the configuration
+pub fn create_admin_client(config: &KafkaConfig) -> Result<AdminClient<KafkaClientContext>, KafkaError> {
+    config.client_config().create_with_context(config.client_context())
+}
+
+/// Group session and fetch settings of a consumer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumerTuning {
+    /// Time without heartbeats before the group evicts the consumer (`session.timeout.ms`)
+    pub session_timeout_ms: u64,
+    /// Time between polls before the consumer leaves the group (`max.poll.interval.ms`)
+    pub max_poll_interval_ms: u64,
+    /// Bytes fetched per request across partitions (`fetch.max.bytes`)
+    pub fetch_max_bytes: u64,
+}
+
+impl Default for ConsumerTuning {
     fn default() -> Self {
         Self {
-            use_tls: true, // Default to TLS
-            ca_cert_path: Some("./ca.crt".to_string()),
-            client_cert_path: Some("./user.crt".to_string()),
-            client_key_path: Some("./user.key".to_string()),
-            broker: None,
+            session_timeout_ms: DEFAULT_SESSION_TIMEOUT_MS,
+            max_poll_interval_ms: DEFAULT_MAX_POLL_INTERVAL_MS,
+            fetch_max_bytes: DEFAULT_FETCH_MAX_BYTES,
         }
     }
 }
 
-/// Create a Kafka producer with the given configuration
-pub fn create_producer(config: &KafkaConfig) -> Result<FutureProducer, Box<dyn Error>> {
-    let mut client_config = ClientConfig::new();
+impl ConsumerTuning {
+    /// Check the settings against each other and librdkafka's limits before creating the consumer
+    pub fn validate(&self) -> Result<(), KafkaConfigError> {
+        if !(1..=3_600_000).contains(&self.session_timeout_ms) {
+            return Err(KafkaConfigError::Conflict(format!(
+                "session timeout must be between 1 and 3600000 ms, got {}",
+                self.session_timeout_ms
+            )));
+        }
+        if self.max_poll_interval_ms > 86_400_000 {
+            return Err(KafkaConfigError::Conflict(format!(
+                "max poll interval must be at most 86400000 ms, got {}",
+                self.max_poll_interval_ms
+            )));
+        }
+        // librdkafka refuses to create a consumer that would leave the group before its session expires
+        if self.max_poll_interval_ms < self.session_timeout_ms {
+            return Err(KafkaConfigError::Conflict(format!(
+                "max poll interval ({} ms) must not be below the session timeout ({} ms)",
+                self.max_poll_interval_ms, self.session_timeout_ms
+            )));
+        }
+        // A fetch must hold at least one message of the largest size
+        if self.fetch_max_bytes < DEFAULT_MAX_MESSAGE_BYTES as u64 || self.fetch_max_bytes > 2_147_483_135 {
+            return Err(KafkaConfigError::Conflict(format!(
+                "fetch max bytes must be between {} (message.max.bytes) and 2147483135, got {}",
+                DEFAULT_MAX_MESSAGE_BYTES, self.fetch_max_bytes
+            )));
+        }
+        Ok(())
+    }
     
-    // Determine broker address
-    let broker = config.broker.as_ref().map(|s| s.as_str()).unwrap_or_else(|| {
-        if config.use_tls {
-            KAFKA_BROKER_TLS
-        } else {
-            KAFKA_BROKER_PLAIN
+    /// Apply the settings to a consumer's client configuration
+    pub fn apply(&self, client_config: &mut ClientConfig) {
+        client_config
+            .set("session.timeout.ms", self.session_timeout_ms.to_string())
+            .set("max.poll.interval.ms", self.max_poll_interval_ms.to_string())
+            .set("fetch.max.bytes", self.fetch_max_bytes.to_string());
+    }
+}
+
+/// Bytes a record takes on the wire, at most
+///
+/// librdkafka compares `message.max.bytes` with the whole record, so the key,
+/// the headers and the record framing count as well as the payload. Header
+/// lengths are counted at their largest varint size.
+pub fn record_size(key: &str, payload_len: usize, headers: &OwnedHeaders) -> usize {
+    let headers_len: usize =
+        headers.iter().map(|header| 10 + header.key.len() + header.value.map_or(0, <[u8]>::len)).sum();
+    RECORD_OVERHEAD_BYTES + key.len() + payload_len + headers_len
+}
+
+/// Pre-flight check of a record's size against the producer's `message.max.bytes`
+///
+/// Catching this before `send` avoids waiting for the delivery timeout on a
+/// message librdkafka or the broker would reject anyway. Pass the
+/// [`record_size`], not just the payload length, for Kafka records.
+pub fn check_message_size(size: usize, limit: usize) -> Result<(), KafkaPublishError> {
+    if size > limit {
+        return Err(KafkaPublishError::Oversized { size, limit });
+    }
+    Ok(())
+}
+
+/// Publishes proof payloads to Kafka, retrying transient failures
+pub struct ProofPublisher {
+    producer: FutureProducer<KafkaClientContext>,
+    topic: String,
+    routes: Vec<Route>,
+    network: String,
+    /// Proofs delivered per topic, for the run summary
+    routed: Mutex<BTreeMap<String, usize>>,
+    retry_policy: RetryPolicy,
+    send_timeout: Duration,
+    max_message_bytes: usize,
+    payload_format: PayloadFormat,
+    partition_key: PartitionKey,
+    key_template: Option<String>,
+    schema_registry: Option<SchemaRegistryClient>,
+    schema_id: OnceCell<u32>,
+    transactional: bool,
+    dlq_topic: Option<String>,
+    dlq_dir: PathBuf,
+    spool: Option<Spool>,
+    /// Connection settings, used by the pre-flight check
+    config: KafkaConfig,
+}
+
+impl ProofPublisher {
+    /// Create a publisher for the configured topic
+    ///
+    /// With a `transactional_id` the producer registers with the transaction
+    /// coordinator here, fencing any older instance using the same id.
+    pub fn new(config: &KafkaConfig) -> Result<Self, Box<dyn Error>> {
+        let producer = create_producer(config)?;
+        if config.transactional_id.is_some() {
+            producer
+                .init_transactions(Duration::from_millis(config.message_timeout_ms))
+                .map_err(|e| Box::new(KafkaPublishError::from_kafka_error(e, 0)) as Box<dyn Error>)?;
+        }
+        
+        Ok(Self {
+            producer,
+            topic: config.topic.clone(),
+            routes: config.routes.clone(),
+            network: config.network.clone(),
+            routed: Mutex::new(BTreeMap::new()),
+            retry_policy: config.retry_policy.clone(),
+            send_timeout: Duration::from_millis(config.message_timeout_ms),
+            max_message_bytes: config.max_message_bytes,
+            payload_format: config.payload_format,
+            partition_key: config.partition_key,
+            key_template: config.key_template.clone(),
+            schema_registry: config.schema_registry.clone().map(SchemaRegistryClient::new),
+            schema_id: OnceCell::new(),
+            transactional: config.transactional_id.is_some(),
+            dlq_topic: config.dlq_topic.clone(),
+            dlq_dir: config.dlq_dir.clone(),
+            spool: config
+                .spool_dir
+                .clone()
+                .map(|dir| Spool::new(dir, config.max_spool_bytes)),
+            config: config.clone(),
+        })
+    }
+    
+    /// Topics this publisher may write to: the default topic, route topics and the DLQ topic
+    pub fn target_topics(&self) -> Vec<String> {
+        let mut topics = BTreeSet::new();
+        topics.insert(self.topic.clone());
+        topics.extend(self.routes.iter().map(|route| route.topic.clone()));
+        topics.extend(self.dlq_topic.clone());
+        topics.into_iter().collect()
+    }
+    
+    /// Check that the cluster is reachable and all target topics exist
+    ///
+    /// Missing topics are created through the admin API when `create_missing`
+    /// is given; otherwise they fail the check.
+    pub async fn check_connectivity(
+        &self,
+        timeout: Duration,
+        create_missing: Option<TopicCreation>,
+    ) -> Result<(), PreflightError> {
+        let broker = self.config.broker().to_string();
+        
+        let metadata = self
+            .producer
+            .client()
+            .fetch_metadata(None, timeout)
+            .map_err(|source| PreflightError::Unreachable { broker: broker.clone(), source })?;
+        println!("Connected to {} ({} brokers)", broker, metadata.brokers().len());
+        
+        let existing: BTreeSet<&str> = metadata
+            .topics()
+            .iter()
+            .filter(|topic| topic.error().is_none())
+            .map(|topic| topic.name())
+            .collect();
+        let missing: Vec<String> = self
+            .target_topics()
+            .into_iter()
+            .filter(|topic| !existing.contains(topic.as_str()))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        
+        let Some(creation) = create_missing else {
+            return Err(PreflightError::MissingTopics { broker, topics: missing });
+        };
+        
+        let admin = create_admin_client(&self.config).map_err(|e| PreflightError::CreateTopic {
+            broker: broker.clone(),
+            topic: missing.join(", "),
+            reason: e.to_string(),
+        })?;
+        let new_topics: Vec<NewTopic> = missing
+            .iter()
+            .map(|topic| {
+                NewTopic::new(topic, creation.partitions, TopicReplication::Fixed(creation.replication_factor))
+            })
+            .collect();
+        let options = AdminOptions::new().operation_timeout(Some(timeout));
+        
+        let results = admin
+            .create_topics(&new_topics, &options)
+            .await
+            .map_err(|e| PreflightError::CreateTopic {
+                broker: broker.clone(),
+                topic: missing.join(", "),
+                reason: e.to_string(),
+            })?;
+        for result in results {
+            match result {
+                Ok(topic) => println!("Created topic {}", topic),
+                Err((topic, RDKafkaErrorCode::TopicAlreadyExists)) => println!("Topic {} already exists", topic),
+                Err((topic, code)) => {
+                    return Err(PreflightError::CreateTopic { broker, topic, reason: code.to_string() });
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Move the publisher into a bounded background queue
+    ///
+    /// Not meant for transactional publishers, whose commits must follow each send.
+    pub fn into_queue(self, capacity: usize, policy: QueueFullPolicy) -> PublishQueue {
+        PublishQueue::start(Arc::new(self), capacity, policy)
+    }
+    
+    /// Start a transaction; all following publishes belong to it until commit or abort
+    pub fn begin_transaction(&self) -> Result<(), KafkaPublishError> {
+        self.producer
+            .begin_transaction()
+            .map_err(|e| KafkaPublishError::from_kafka_error(e, 0))
+    }
+    
+    /// Commit the current transaction, making its records visible to read-committed consumers
+    pub fn commit_transaction(&self) -> Result<(), KafkaPublishError> {
+        self.producer
+            .commit_transaction(self.send_timeout)
+            .map_err(|e| KafkaPublishError::from_kafka_error(e, 0))
+    }
+    
+    /// Abort the current transaction, discarding its records
+    pub fn abort_transaction(&self) -> Result<(), KafkaPublishError> {
+        self.producer
+            .abort_transaction(self.send_timeout)
+            .map_err(|e| KafkaPublishError::from_kafka_error(e, 0))
+    }
+    
+    /// Publish a raw payload under the given key
+    ///
+    /// Returns the partition and offset of the delivered message.
+    pub async fn publish(&self, key: &str, payload: &[u8]) -> Result<(i32, i64), KafkaPublishError> {
+        self.publish_with_headers(key, payload, OwnedHeaders::new()).await
+    }
+    
+    /// Publish a raw payload under the given key with record headers
+    pub async fn publish_with_headers(
+        &self,
+        key: &str,
+        payload: &[u8],
+        headers: OwnedHeaders,
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        self.send_to(&self.topic, key, payload, headers, None).await
+    }
+    
+    /// Send to a specific topic, retrying retryable errors per the retry policy
+    ///
+    /// `timestamp_ms` sets the record timestamp; without it the producer uses the send time.
+    async fn send_to(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: &[u8],
+        headers: OwnedHeaders,
+        timestamp_ms: Option<i64>,
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        self.send_counting_attempts(topic, key, payload, headers, timestamp_ms).await.0
+    }
+    
+    /// Like `send_to`, also returning the number of attempts made (0 when rejected before sending)
+    async fn send_counting_attempts(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: &[u8],
+        headers: OwnedHeaders,
+        timestamp_ms: Option<i64>,
+    ) -> (Result<(i32, i64), KafkaPublishError>, u32) {
+        let span = tracing::info_span!(
+            "kafka_send",
+            topic,
+            key,
+            bytes = payload.len(),
+            attempts = Empty,
+            ack_ms = Empty,
+            partition = Empty,
+            offset = Empty,
+        );
+        self.send_with_retries(topic, key, payload, headers, timestamp_ms).instrument(span).await
+    }
+    
+    async fn send_with_retries(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: &[u8],
+        headers: OwnedHeaders,
+        timestamp_ms: Option<i64>,
+    ) -> (Result<(i32, i64), KafkaPublishError>, u32) {
+        let size = record_size(key, payload.len(), &headers);
+        if let Err(e) = check_message_size(size, self.max_message_bytes) {
+            metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&[e.reason()]).inc();
+            return (Err(e), 0);
+        }
+        
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 0;
+        
+        loop {
+            attempt += 1;
+            metrics::KAFKA_PUBLISH_ATTEMPTS.inc();
+            Span::current().record("attempts", attempt);
+            
+            let mut record = FutureRecord::to(topic)
+                .payload(payload)
+                .key(key)
+                .headers(headers.clone());
+            if let Some(timestamp_ms) = timestamp_ms {
+                record = record.timestamp(timestamp_ms);
+            }
+            
+            let sent_at = Instant::now();
+            let delivery_status = self
+                .producer
+                .send(record, Timeout::After(self.send_timeout))
+                .await;
+            
+            let error = match delivery_status {
+                Ok((partition, offset)) => {
+                    let ack_latency = sent_at.elapsed();
+                    metrics::KAFKA_PUBLISHED.inc();
+                    metrics::KAFKA_ACK_LATENCY_SECONDS.observe(ack_latency.as_secs_f64());
+                    Span::current()
+                        .record("ack_ms", ack_latency.as_millis() as u64)
+                        .record("partition", partition)
+                        .record("offset", offset);
+                    tracing::info!(
+                        topic,
+                        partition,
+                        offset,
+                        latency_ms = ack_latency.as_millis() as u64,
+                        "Message sent to partition {} at offset {} in {:?}",
+                        partition,
+                        offset,
+                        ack_latency
+                    );
+                    return (Ok((partition, offset)), attempt);
+                }
+                Err((e, _)) => KafkaPublishError::from_kafka_error(e, size),
+            };
+            
+            if !error.is_retryable() || attempt >= max_attempts {
+                metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&[error.reason()]).inc();
+                return (Err(error), attempt);
+            }
+            
+            let delay = self.retry_policy.delay_for_attempt(attempt);
+            eprintln!(
+                "Warning: publish attempt {}/{} failed ({}), retrying in {:?}",
+                attempt, max_attempts, error, delay
+            );
+            metrics::KAFKA_PUBLISH_RETRIES.inc();
+            tokio::time::sleep(delay).await;
         }
-    });
+    }
+    
+    /// Publish a JSON value, keyed by its `identifier` field
+    pub async fn publish_json(&self, json_value: &Value) -> Result<(i32, i64), KafkaPublishError> {
+        let payload = json_value.to_string();
+        
+        // Extract identifier from JSON for the key
+        let key = json_value
+            .get("identifier")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+        
+        self.publish(key, payload.as_bytes()).await
+    }
+    
+    /// Publish a typed proof using the configured payload format
+    ///
+    /// The topic is chosen by the routing table, falling back to the default topic.
+    /// With a spool configured, spooled messages are drained first; if the
+    /// broker is unreachable the proof is spooled and `Spooled` is returned.
+    /// The record timestamp is the current time.
+    pub async fn publish_proof(&self, proof: &ZkProof) -> Result<(i32, i64), KafkaPublishError> {
+        self.publish_proof_at(proof, None).await
+    }
+    
+    /// Publish a typed proof with an explicit record timestamp in milliseconds
+    ///
+    /// Falls back to the current time when `timestamp_ms` is `None`.
+    pub async fn publish_proof_at(
+        &self,
+        proof: &ZkProof,
+        timestamp_ms: Option<i64>,
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        self.publish_proof_with_headers(proof, timestamp_ms, &[]).await
+    }
     
-    client_config.set("bootstrap.servers", broker);
-    client_config.set("message.timeout.ms", "5000");
+    /// Publish a typed proof like [`publish_proof_at`](Self::publish_proof_at) with extra record headers
+    ///
+    /// The headers are sent after `content-type`. A proof that is spooled
+    /// keeps only its content type.
+    pub async fn publish_proof_with_headers(
+        &self,
+        proof: &ZkProof,
+        timestamp_ms: Option<i64>,
+        headers: &[(&str, &str)],
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        let timestamp_ms = timestamp_ms.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let span = tracing::info_span!(
+            "kafka_publish_proof",
+            identifier = %proof.identifier,
+            format = %self.payload_format,
+            timestamp_ms,
+            serialize_ms = Empty,
+        );
+        self.route_and_publish(proof, timestamp_ms, headers, None).instrument(span).await
+    }
+    
+    /// Publish a typed proof like [`publish_proof_at`](Self::publish_proof_at) with envelope metadata
+    ///
+    /// JSON payloads carry `metadata` as the envelope's top-level
+    /// [`METADATA_FIELD`](crate::envelope::METADATA_FIELD), so it counts against the message size like the
+    /// proof and is spooled with it. Bincode and Avro envelopes have no room
+    /// for it; they are published without it, with a warning.
+    pub async fn publish_proof_with_metadata(
+        &self,
+        proof: &ZkProof,
+        timestamp_ms: Option<i64>,
+        metadata: &Value,
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        let timestamp_ms = timestamp_ms.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let span = tracing::info_span!(
+            "kafka_publish_proof",
+            identifier = %proof.identifier,
+            format = %self.payload_format,
+            timestamp_ms,
+            serialize_ms = Empty,
+        );
+        self.route_and_publish(proof, timestamp_ms, &[], Some(metadata)).instrument(span).await
+    }
     
-    // Configure TLS if enabled
-    if config.use_tls {
-        client_config.set("security.protocol", "ssl");
+    async fn route_and_publish(
+        &self,
+        proof: &ZkProof,
+        timestamp_ms: i64,
+        extra_headers: &[(&str, &str)],
+        metadata: Option<&Value>,
+    ) -> Result<(i32, i64), KafkaPublishError> {
+        let serialize_started = Instant::now();
+        let payload = self.encode_proof(proof, metadata).await?;
+        let serialize_time = serialize_started.elapsed();
+        metrics::KAFKA_SERIALIZE_SECONDS.observe(serialize_time.as_secs_f64());
+        Span::current().record("serialize_ms", serialize_time.as_millis() as u64);
+        
+        let decision = route_proof(&self.routes, &self.topic, proof, &self.network);
+        println!("Routing {} to {}", proof.identifier, decision);
         
-        // Set certificate paths if provided
-        if let Some(ca_path) = &config.ca_cert_path {
-            if Path::new(ca_path).exists() {
-                client_config.set("ssl.ca.location", ca_path);
-            } else {
-                eprintln!("Warning: CA certificate not found at {}", ca_path);
+        if self.payload_format != PayloadFormat::Json {
+            if let Ok(json_size) = json_len(proof) {
+                println!(
+                    "Payload size: {} bytes ({}) vs {} bytes (json), {:.1}% of the JSON size",
+                    payload.len(),
+                    self.payload_format,
+                    json_size,
+                    payload.len() as f64 * 100.0 / json_size.max(1) as f64
+                );
             }
         }
         
-        if let Some(cert_path) = &config.client_cert_path {
-            if Path::new(cert_path).exists() {
-                client_config.set("ssl.certificate.location", cert_path);
-            } else {
-                eprintln!("Warning: Client certificate not found at {}", cert_path);
+        let mut headers = OwnedHeaders::new().insert(Header {
+            key: CONTENT_TYPE_HEADER,
+            value: Some(self.payload_format.content_type()),
+        });
+        for &(key, value) in extra_headers {
+            headers = headers.insert(Header { key, value: Some(value) });
+        }
+        let key = match &self.key_template {
+            Some(template) => render_key_template(template, proof),
+            None => self.partition_key.record_key(proof),
+        };
+        
+        // Older spooled messages go first; while they cannot be sent, new ones queue up behind them
+        let (result, attempts) = if !self.drain_spool().await? {
+            (Err(self.spool_message(&decision.topic, &key, &payload, timestamp_ms)), 0)
+        } else {
+            match self.send_counting_attempts(&decision.topic, &key, &payload, headers, Some(timestamp_ms)).await {
+                (Err(error), attempts) if error.is_retryable() && self.spool.is_some() => {
+                    (Err(self.spool_message(&decision.topic, &key, &payload, timestamp_ms)), attempts)
+                }
+                other => other,
+            }
+        };
+        
+        match &result {
+            Ok(_) => *self.routed.lock().unwrap().entry(decision.topic).or_default() += 1,
+            Err(error) => {
+                if self.dlq_topic.is_some() && !error.is_fenced() && !error.is_spooled() {
+                    self.dead_letter(&decision.topic, &key, &payload, error, attempts).await;
+                }
             }
         }
+        result
+    }
+    
+    /// Write a message to the spool, returning `Spooled` or the reason it could not be spooled
+    ///
+    /// A full spool is reported as a fatal error; older messages are never evicted.
+    fn spool_message(&self, topic: &str, key: &str, payload: &[u8], timestamp_ms: i64) -> KafkaPublishError {
+        let Some(spool) = &self.spool else {
+            return KafkaPublishError::Fatal("No spool directory configured".into());
+        };
+        let message = SpooledMessage::new(topic, key, self.payload_format.content_type(), payload, Some(timestamp_ms));
         
-        if let Some(key_path) = &config.client_key_path {
-            if Path::new(key_path).exists() {
-                client_config.set("ssl.key.location", key_path);
-            } else {
-                eprintln!("Warning: Client key not found at {}", key_path);
+        match spool.push(&message) {
+            Ok(path) => {
+                eprintln!("Warning: Kafka unavailable, {} spooled to {}", key, path.display());
+                KafkaPublishError::Spooled(path)
+            }
+            Err(e) => {
+                if let SpoolError::Full { .. } = e {
+                    eprintln!("Error: refusing to spool {}: {}", key, e);
+                }
+                KafkaPublishError::Fatal(Box::new(e))
             }
         }
+    }
+    
+    /// Publish spooled messages oldest first and return whether the spool is now empty
+    ///
+    /// Stops at the first retryable failure so the remaining messages keep
+    /// their order. A message failing with a non-retryable error is moved to
+    /// the dead letters so it cannot block the spool.
+    pub async fn drain_spool(&self) -> Result<bool, KafkaPublishError> {
+        let Some(spool) = &self.spool else { return Ok(true) };
+        let paths = spool.pending().map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+        if paths.is_empty() {
+            return Ok(true);
+        }
         
-        println!("Using TLS connection to {}", broker);
-    } else {
-        println!("Using plain connection to {}", broker);
+        println!("Draining {} spooled messages from {}", paths.len(), spool.dir().display());
+        for path in paths {
+            let message = match spool.read(&path) {
+                Ok(message) => message,
+                Err(e @ SpoolError::Corrupt { .. }) => {
+                    let kept = spool.set_aside(&path).map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+                    eprintln!("Error: {}, moved to {}", e, kept.display());
+                    continue;
+                }
+                Err(e) => return Err(KafkaPublishError::Fatal(Box::new(e))),
+            };
+            let payload = message
+                .payload_bytes()
+                .map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+            let headers = OwnedHeaders::new().insert(Header {
+                key: CONTENT_TYPE_HEADER,
+                value: Some(message.content_type.as_str()),
+            });
+            
+            let sent =
+                self.send_counting_attempts(&message.topic, &message.key, &payload, headers, message.timestamp_ms);
+            match sent.await {
+                (Ok(_), _) => {
+                    *self.routed.lock().unwrap().entry(message.topic.clone()).or_default() += 1;
+                }
+                (Err(e), _) if e.is_retryable() => {
+                    eprintln!("Warning: Kafka still unavailable, keeping spooled messages: {}", e);
+                    return Ok(false);
+                }
+                (Err(e), _) if e.is_fenced() => return Err(e),
+                (Err(e), attempts) => {
+                    eprintln!("Error: spooled message {} cannot be published: {}", message.key, e);
+                    self.dead_letter(&message.topic, &message.key, &payload, &e, attempts).await;
+                }
+            }
+            spool.remove(&path).map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+        }
+        
+        println!("Spool drained");
+        Ok(true)
+    }
+    
+    /// Number of proofs delivered to each topic so far
+    pub fn routing_summary(&self) -> BTreeMap<String, usize> {
+        self.routed.lock().unwrap().clone()
+    }
+    
+    /// Route a message that failed to publish to the DLQ topic, or to the spool directory
+    ///
+    /// In transactional mode the DLQ record would be discarded together with
+    /// the aborted transaction, so dead letters are spooled directly.
+    async fn dead_letter(&self, topic: &str, key: &str, payload: &[u8], error: &KafkaPublishError, attempts: u32) {
+        let letter = DeadLetter::new(
+            topic,
+            key,
+            self.payload_format.content_type(),
+            payload,
+            error.reason(),
+            &error.to_string(),
+            attempts,
+        );
+        
+        if let (Some(dlq_topic), false) = (&self.dlq_topic, self.transactional) {
+            let mut headers = OwnedHeaders::new()
+                .insert(Header { key: CONTENT_TYPE_HEADER, value: Some(letter.content_type.as_str()) });
+            for (key, value) in letter.headers() {
+                headers = headers.insert(Header { key, value: Some(value.as_str()) });
+            }
+            
+            match self.send_to(dlq_topic, key, payload, headers, None).await {
+                Ok((partition, offset)) => {
+                    eprintln!(
+                        "Dead letter for {} written to {} (partition {}, offset {})",
+                        key, dlq_topic, partition, offset
+                    );
+                    return;
+                }
+                Err(e) => eprintln!("Warning: failed to write dead letter to {}: {}", dlq_topic, e),
+            }
+        }
+        
+        match dlq::spool(&self.dlq_dir, &letter) {
+            Ok(path) => eprintln!("Dead letter for {} spooled to {}", key, path.display()),
+            Err(e) => eprintln!("Error: failed to spool dead letter for {}: {}", key, e),
+        }
+    }
+    
+    /// Publish a spooled dead letter to its original topic
+    pub async fn replay_dead_letter(&self, letter: &DeadLetter) -> Result<(i32, i64), KafkaPublishError> {
+        let payload = letter
+            .payload_bytes()
+            .map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+        let headers = OwnedHeaders::new().insert(Header {
+            key: CONTENT_TYPE_HEADER,
+            value: Some(letter.content_type.as_str()),
+        });
+        self.send_to(&letter.original_topic, &letter.key, &payload, headers, None).await
     }
     
-    client_config.create().map_err(|e| Box::new(e) as Box<dyn Error>)
+    async fn encode_proof(&self, proof: &ZkProof, metadata: Option<&Value>) -> Result<Vec<u8>, KafkaPublishError> {
+        if let (Some(_), false) = (metadata, self.payload_format == PayloadFormat::Json) {
+            eprintln!(
+                "Warning: {} payloads have no metadata section; publishing {} without its metadata",
+                self.payload_format, proof.identifier
+            );
+        }
+        match self.payload_format {
+            PayloadFormat::Json => match metadata {
+                Some(metadata) => serde_json::to_vec(&EnvelopeWithMetadata { proof, metadata })
+                    .map_err(|e| KafkaPublishError::Fatal(Box::new(e))),
+                None => serde_json::to_vec(proof).map_err(|e| KafkaPublishError::Fatal(Box::new(e))),
+            },
+            PayloadFormat::Bincode => {
+                bincode::serialize(proof).map_err(|e| KafkaPublishError::Fatal(e))
+            }
+            PayloadFormat::Avro => {
+                let registry = self
+                    .schema_registry
+                    .as_ref()
+                    .ok_or_else(|| KafkaPublishError::Fatal("Avro payload format requires a Schema Registry".into()))?;
+                let schema = proof_envelope_schema();
+                let schema_id = self
+                    .schema_id
+                    .get_or_try_init(|| registry.register(PROOF_ENVELOPE_SUBJECT, &schema))
+                    .await
+                    .map_err(|e| KafkaPublishError::Fatal(e.to_string().into()))?;
+                let body = encode_proof(&schema, proof)
+                    .map_err(|e| KafkaPublishError::Fatal(e.to_string().into()))?;
+                Ok(encode_wire_format(*schema_id, &body))
+            }
+        }
+    }
 }
 
 /// Publish a proof to Kafka (legacy function for compatibility)
 pub async fn publish_to_kafka(proof: ZkProof) -> Result<(), Box<dyn Error>> {
-    let config = KafkaConfig::default();
-    let producer = create_producer(&config)?;
-    
-    let payload = serde_json::to_string(&proof)?;
-    
-    let delivery_status = producer
-        .send(
-            FutureRecord::to(KAFKA_TOPIC)
-                .payload(&payload)
-                .key(&proof.identifier),
-            Duration::from_secs(5),
-        )
-        .await;
-    
-    match delivery_status {
-        Ok((partition, offset)) => {
-            println!("Message sent to partition {} at offset {}", partition, offset);
-            Ok(())
-        }
-        Err((e, _)) => Err(Box::new(e)),
-    }
+    let config = KafkaConfig::from_env()?;
+    config.validate()?;
+    let publisher = ProofPublisher::new(&config)?;
+    
+    publisher.publish_proof(&proof).await?;
+    Ok(())
+}
+
+/// Publish a typed proof to Kafka with configuration
+pub async fn publish_proof_to_kafka_with_config(
+    proof: &ZkProof,
+    config: &KafkaConfig,
+) -> Result<(), Box<dyn Error>> {
+    let publisher = ProofPublisher::new(config)?;
+    publisher.publish_proof(proof).await?;
+    Ok(())
 }
 
 /// Publish JSON value to Kafka with configuration
@@ -114,37 +1950,579 @@ pub async fn publish_json_to_kafka_with_config(
     json_value: Value, 
     config: &KafkaConfig
 ) -> Result<(), Box<dyn Error>> {
-    let producer = create_producer(config)?;
-    
-    let payload = json_value.to_string();
-    
-    // Extract identifier from JSON for the key
-    let key = json_value
-        .get("identifier")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown")
-        .to_string();
-    
-    let delivery_status = producer
-        .send(
-            FutureRecord::to(KAFKA_TOPIC)
-                .payload(&payload)
-                .key(&key),
-            Duration::from_secs(5),
-        )
-        .await;
-    
-    match delivery_status {
-        Ok((partition, offset)) => {
-            println!("Message sent to partition {} at offset {}", partition, offset);
-            Ok(())
-        }
-        Err((e, _)) => Err(Box::new(e)),
-    }
+    let publisher = ProofPublisher::new(config)?;
+    publisher.publish_json(&json_value).await?;
+    Ok(())
 }
 
-/// Publish JSON value to Kafka (uses default TLS configuration)
+/// Publish JSON value to Kafka (uses the environment/default configuration)
 pub async fn publish_json_to_kafka(json_value: Value) -> Result<(), Box<dyn Error>> {
-    let config = KafkaConfig::default();
+    let config = KafkaConfig::from_env()?;
+    config.validate()?;
     publish_json_to_kafka_with_config(json_value, &config).await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for the files of one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kafka-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a file of one PEM block labelled `label`, returning its path
+    fn pem_file(dir: &Path, name: &str, label: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("-----BEGIN {}-----\nc3R1Yg==\n-----END {}-----\n", label, label)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// A valid plaintext configuration, for tests to change what they check
+    fn plain_config() -> KafkaConfig {
+        KafkaConfig {
+            use_tls: false,
+            ca_cert_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+            broker: Some("localhost:9092".to_string()),
+            ..KafkaConfig::default()
+        }
+    }
+
+    /// Credentials a client can present
+    #[derive(Debug, Clone, Copy)]
+    enum Credential {
+        None,
+        ClientCertificate,
+        Sasl(SaslMechanism),
+    }
+
+    #[test]
+    fn every_protocol_and_credential_sets_its_client_settings() {
+        let dir = test_dir("security-matrix");
+        let ca = pem_file(&dir, "ca.crt", "CERTIFICATE");
+        let cert = pem_file(&dir, "user.crt", "CERTIFICATE");
+        let key = pem_file(&dir, "user.key", "PRIVATE KEY");
+        let credentials = [
+            Credential::None,
+            Credential::ClientCertificate,
+            Credential::Sasl(SaslMechanism::Plain),
+            Credential::Sasl(SaslMechanism::ScramSha256),
+            Credential::Sasl(SaslMechanism::ScramSha512),
+        ];
+        for use_tls in [false, true] {
+            for credential in credentials {
+                let mechanism = match credential {
+                    Credential::Sasl(mechanism) => Some(mechanism),
+                    _ => None,
+                };
+                let client_cert = matches!(credential, Credential::ClientCertificate);
+                let config = KafkaConfig {
+                    use_tls,
+                    ca_cert_path: use_tls.then(|| ca.clone()),
+                    client_cert_path: client_cert.then(|| cert.clone()),
+                    client_key_path: client_cert.then(|| key.clone()),
+                    sasl_mechanism: mechanism,
+                    sasl_username: mechanism.map(|_| "prover".to_string()),
+                    sasl_password: mechanism.map(|_| "secret".to_string()),
+                    ..plain_config()
+                };
+                if client_cert && !use_tls {
+                    // Client certificates authenticate over TLS only
+                    assert!(matches!(config.validate(), Err(KafkaConfigError::Conflict(_))));
+                    continue;
+                }
+                config.validate().unwrap_or_else(|e| panic!("{:?} with TLS {}: {}", credential, use_tls, e));
+                let protocol = SecurityProtocol::new(use_tls, mechanism.is_some());
+                assert_eq!(config.security_protocol(), protocol);
+                KafkaConfig { protocol: Some(protocol), ..config.clone() }.validate().unwrap();
+
+                let client_config = config.client_config();
+                let mechanism_name = mechanism.map(|mechanism| mechanism.to_string());
+                let expected = [
+                    ("security.protocol", Some(protocol.to_string())),
+                    ("ssl.ca.location", use_tls.then(|| ca.clone())),
+                    ("ssl.certificate.location", client_cert.then(|| cert.clone())),
+                    ("ssl.key.location", client_cert.then(|| key.clone())),
+                    ("sasl.mechanism", mechanism_name),
+                    ("sasl.username", mechanism.map(|_| "prover".to_string())),
+                    ("sasl.password", mechanism.map(|_| "secret".to_string())),
+                ];
+                for (property, value) in expected {
+                    assert_eq!(client_config.get(property), value.as_deref(), "{} of {:?}", property, credential);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_must_agree_with_the_credentials() {
+        let sasl = KafkaConfig {
+            sasl_mechanism: Some(SaslMechanism::ScramSha512),
+            sasl_username: Some("prover".to_string()),
+            sasl_password: Some("secret".to_string()),
+            ..plain_config()
+        };
+        let conflicts = [
+            // SASL requested without a mechanism
+            KafkaConfig { protocol: Some(SecurityProtocol::SaslPlaintext), ..plain_config() },
+            // A mechanism configured for a protocol without SASL
+            KafkaConfig { protocol: Some(SecurityProtocol::Plaintext), ..sasl.clone() },
+            // TLS requested but disabled
+            KafkaConfig { protocol: Some(SecurityProtocol::SaslSsl), ..sasl.clone() },
+            KafkaConfig { protocol: Some(SecurityProtocol::Ssl), ..plain_config() },
+            // MSK IAM signs its own tokens
+            KafkaConfig { auth: KafkaAuth::MskIam, use_tls: true, aws_region: Some("eu-west-1".to_string()), ..sasl },
+        ];
+        for config in conflicts {
+            assert!(matches!(config.validate(), Err(KafkaConfigError::Conflict(_))), "{:?}", config);
+        }
+    }
+
+    fn assert_conflict(config: KafkaConfig, reason: &str) {
+        match config.validate() {
+            Err(KafkaConfigError::Conflict(message)) => assert!(message.contains(reason), "{}", message),
+            other => panic!("expected a conflict about {}, got {:?}", reason, other),
+        }
+    }
+
+    #[test]
+    fn accepts_broker_lists() {
+        for broker in ["localhost:9092", "b-1.kafka:9094, b-2.kafka:9094", "[::1]:9092"] {
+            KafkaConfig { broker: Some(broker.to_string()), ..plain_config() }.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_brokers() {
+        for broker in ["", "localhost", ":9092", "localhost:", "localhost:kafka", "localhost:70000", "a:9092,,b:9092"] {
+            let config = KafkaConfig { broker: Some(broker.to_string()), ..plain_config() };
+            assert!(matches!(config.validate(), Err(KafkaConfigError::InvalidBroker(_))), "{:?}", broker);
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_options() {
+        assert_conflict(KafkaConfig { topic: String::new(), ..plain_config() }, "topic");
+        assert_conflict(KafkaConfig { client_id: Some(" ".to_string()), ..plain_config() }, "client ID");
+        assert_conflict(KafkaConfig { ca_cert_path: Some("ca.crt".to_string()), ..plain_config() }, "TLS is disabled");
+        let dir = test_dir("conflicts");
+        let cert = pem_file(&dir, "user.crt", "CERTIFICATE");
+        let tls = KafkaConfig { use_tls: true, ..plain_config() };
+        assert_conflict(KafkaConfig { client_cert_path: Some(cert), ..tls }, "configured together");
+        assert_conflict(KafkaConfig { payload_format: PayloadFormat::Avro, ..plain_config() }, "Schema Registry");
+        assert_conflict(KafkaConfig { key_template: Some("{slot}".to_string()), ..plain_config() }, "{slot}");
+        assert_conflict(
+            KafkaConfig { spool_dir: Some(dir), transactional_id: Some("prover-1".to_string()), ..plain_config() },
+            "transactional",
+        );
+    }
+
+    #[test]
+    fn sasl_needs_a_username_and_password() {
+        let sasl = KafkaConfig {
+            sasl_mechanism: Some(SaslMechanism::ScramSha256),
+            sasl_username: Some("prover".to_string()),
+            sasl_password: Some("secret".to_string()),
+            ..plain_config()
+        };
+        sasl.validate().unwrap();
+        assert_conflict(KafkaConfig { sasl_username: None, ..sasl.clone() }, "username and password");
+        assert_conflict(KafkaConfig { sasl_password: None, ..sasl }, "username and password");
+    }
+
+    #[test]
+    fn rejects_numbers_out_of_range() {
+        for max_message_bytes in [0, 999, 1_000_000_001] {
+            assert_conflict(KafkaConfig { max_message_bytes, ..plain_config() }, "max message bytes");
+        }
+        assert_conflict(KafkaConfig { message_timeout_ms: i32::MAX as u64 + 1, ..plain_config() }, "message timeout");
+        let retry_policy = RetryPolicy { max_attempts: 0, ..RetryPolicy::default() };
+        assert_conflict(KafkaConfig { retry_policy, ..plain_config() }, "retry attempts");
+        let retry_policy =
+            RetryPolicy { base_delay: Duration::from_secs(20), max_delay: Duration::from_secs(10), max_attempts: 3 };
+        assert_conflict(KafkaConfig { retry_policy, ..plain_config() }, "base delay");
+    }
+
+    /// A TLS configuration whose certificate files are valid PEM in `dir`
+    fn tls_config(dir: &Path) -> KafkaConfig {
+        KafkaConfig {
+            use_tls: true,
+            ca_cert_path: Some(pem_file(dir, "ca.crt", "CERTIFICATE")),
+            client_cert_path: Some(pem_file(dir, "user.crt", "CERTIFICATE")),
+            client_key_path: Some(pem_file(dir, "user.key", "PRIVATE KEY")),
+            ..plain_config()
+        }
+    }
+
+    #[test]
+    fn accepts_certificate_and_key_labels() {
+        let dir = test_dir("pem-labels");
+        tls_config(&dir).validate().unwrap();
+        KafkaConfig {
+            ca_cert_path: Some(pem_file(&dir, "trusted.crt", "TRUSTED CERTIFICATE")),
+            client_key_path: Some(pem_file(&dir, "ec.key", "EC PRIVATE KEY")),
+            ..tls_config(&dir)
+        }
+        .validate()
+        .unwrap();
+    }
+
+    #[test]
+    fn missing_certificate_files_fail_unless_allowed() {
+        let dir = test_dir("missing-certs");
+        let missing = dir.join("absent.crt").to_string_lossy().into_owned();
+        let config = KafkaConfig { ca_cert_path: Some(missing.clone()), ..tls_config(&dir) };
+        match config.validate() {
+            Err(KafkaConfigError::MissingFile { path, description }) => {
+                assert_eq!(path, missing);
+                assert_eq!(description, "CA certificate");
+            }
+            other => panic!("expected a missing file, got {:?}", other),
+        }
+        
+        let config = KafkaConfig { allow_missing_certs: true, ..config };
+        config.validate().unwrap();
+        let properties: Vec<_> = config.ssl_file_settings().into_iter().map(|(property, _)| property).collect();
+        assert_eq!(properties, ["ssl.certificate.location", "ssl.key.location"]);
+    }
+
+    #[test]
+    fn unreadable_certificate_files_are_reported() {
+        // A directory exists but cannot be read as a file, even by root
+        let dir = test_dir("unreadable-certs");
+        let unreadable = dir.join("ca.crt.d");
+        std::fs::create_dir(&unreadable).unwrap();
+        let config = KafkaConfig { ca_cert_path: Some(unreadable.to_string_lossy().into_owned()), ..tls_config(&dir) };
+        let err = config.validate().unwrap_err();
+        assert!(matches!(err, KafkaConfigError::UnreadableFile { description: "CA certificate", .. }), "{:?}", err);
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn swapped_certificate_and_key_are_reported() {
+        let dir = test_dir("swapped-certs");
+        let config = tls_config(&dir);
+        let config = KafkaConfig {
+            client_cert_path: config.client_key_path.clone(),
+            client_key_path: config.client_cert_path.clone(),
+            ..config
+        };
+        let err = config.validate().unwrap_err();
+        match &err {
+            KafkaConfigError::WrongPemType { description, expected, found, .. } => {
+                assert_eq!(*description, "Client certificate");
+                assert_eq!(*expected, "a CERTIFICATE");
+                assert_eq!(found, "PRIVATE KEY");
+            }
+            other => panic!("expected a wrong PEM type, got {:?}", other),
+        }
+        assert!(err.to_string().contains("swapped"));
+    }
+
+    #[test]
+    fn wrong_pem_labels_are_reported() {
+        let dir = test_dir("wrong-labels");
+        let config = KafkaConfig { ca_cert_path: Some(pem_file(&dir, "ca.pub", "PUBLIC KEY")), ..tls_config(&dir) };
+        assert!(matches!(
+            config.validate(),
+            Err(KafkaConfigError::WrongPemType { description: "CA certificate", expected: "a CERTIFICATE", .. })
+        ));
+        
+        let config = KafkaConfig {
+            client_key_path: Some(pem_file(&dir, "user.csr", "CERTIFICATE REQUEST")),
+            ..tls_config(&dir)
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(KafkaConfigError::WrongPemType { description: "Client key", expected: "a PRIVATE KEY", .. })
+        ));
+    }
+
+    #[test]
+    fn files_that_are_not_pem_are_reported() {
+        let dir = test_dir("invalid-pem");
+        for (contents, reason) in [
+            ("not a certificate\n", "no PEM blocks found"),
+            ("-----BEGIN CERTIFICATE-----\nc3R1Yg==\n", "missing '-----END CERTIFICATE-----'"),
+            ("-----BEGIN CERTIFICATE-----\n%%%\n-----END CERTIFICATE-----\n", "invalid base64"),
+        ] {
+            let path = dir.join("ca.crt");
+            let config = KafkaConfig { ca_cert_path: Some(path.to_string_lossy().into_owned()), ..tls_config(&dir) };
+            // Written after `tls_config`, which puts a valid certificate at the same path
+            std::fs::write(&path, contents).unwrap();
+            match config.validate() {
+                Err(KafkaConfigError::InvalidPem { reason: found, .. }) => assert!(found.contains(reason), "{}", found),
+                other => panic!("expected invalid PEM, got {:?}", other),
+            }
+        }
+    }
+
+    fn classify(code: RDKafkaErrorCode) -> KafkaPublishError {
+        KafkaPublishError::from_kafka_error(KafkaError::MessageProduction(code), 2048)
+    }
+
+    #[test]
+    fn transient_broker_errors_are_retryable() {
+        for code in [
+            RDKafkaErrorCode::QueueFull,
+            RDKafkaErrorCode::MessageTimedOut,
+            RDKafkaErrorCode::RequestTimedOut,
+            RDKafkaErrorCode::OperationTimedOut,
+            RDKafkaErrorCode::BrokerTransportFailure,
+            RDKafkaErrorCode::AllBrokersDown,
+            RDKafkaErrorCode::NetworkException,
+            RDKafkaErrorCode::LeaderNotAvailable,
+            RDKafkaErrorCode::NotLeaderForPartition,
+        ] {
+            let error = classify(code);
+            assert!(error.is_retryable(), "{:?} classified as {}", code, error.reason());
+        }
+    }
+
+    #[test]
+    fn fencing_errors_are_fenced() {
+        for code in
+            [RDKafkaErrorCode::Fenced, RDKafkaErrorCode::ProducerFenced, RDKafkaErrorCode::TransactionCoordinatorFenced]
+        {
+            let error = classify(code);
+            assert!(error.is_fenced() && !error.is_retryable(), "{:?} classified as {}", code, error.reason());
+        }
+    }
+
+    #[test]
+    fn a_message_too_large_for_the_broker_is_oversized() {
+        let error = classify(RDKafkaErrorCode::MessageSizeTooLarge);
+        assert!(matches!(error, KafkaPublishError::Oversized { size: 2048, limit: 0 }));
+        assert!(!error.is_retryable());
+        assert_eq!(error.to_string(), "Message of 2048 bytes rejected by the broker as too large");
+    }
+
+    #[test]
+    fn other_errors_are_fatal() {
+        for code in [
+            RDKafkaErrorCode::TopicAuthorizationFailed,
+            RDKafkaErrorCode::UnknownTopicOrPartition,
+            RDKafkaErrorCode::SaslAuthenticationFailed,
+            RDKafkaErrorCode::InvalidRecord,
+        ] {
+            let error = classify(code);
+            assert_eq!(error.reason(), "fatal", "{:?}", code);
+        }
+        // Errors without a code, e.g. from creating the client, are fatal too
+        let error = KafkaPublishError::from_kafka_error(KafkaError::ClientCreation("bad config".to_string()), 0);
+        assert_eq!(error.reason(), "fatal");
+    }
+
+    #[tokio::test]
+    async fn retries_give_up_on_a_black_holed_broker() {
+        // Accepts connections but never answers, so every attempt times out
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let retry_policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_millis(80),
+        };
+        let config = KafkaConfig {
+            broker: Some(listener.local_addr().unwrap().to_string()),
+            message_timeout_ms: 300,
+            retry_policy,
+            ..plain_config()
+        };
+        let publisher = ProofPublisher::new(&config).unwrap();
+        
+        let started = Instant::now();
+        let error = publisher.publish("key", b"payload").await.unwrap_err();
+        let elapsed = started.elapsed();
+        
+        match &error {
+            KafkaPublishError::Retryable(e) => {
+                assert_eq!(e.rdkafka_error_code(), Some(RDKafkaErrorCode::MessageTimedOut))
+            }
+            other => panic!("expected a retryable timeout, got {}", other),
+        }
+        // Three timed-out attempts with 50 ms and 80 ms between them, then no more
+        assert!(elapsed >= Duration::from_millis(3 * 300 + 50 + 80), "gave up after {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(10), "still retrying after {:?}", elapsed);
+    }
+
+    /// Output of a `fmt` subscriber, including span closes with the fields recorded on them
+    #[derive(Clone, Default)]
+    struct CapturedTraces(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedTraces {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedTraces {
+        /// Capture this thread's spans and events until the guard is dropped
+        fn install(&self) -> tracing::subscriber::DefaultGuard {
+            let writer = self.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .with_ansi(false)
+                .without_time()
+                .with_level(false)
+                .with_target(false)
+                .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+                .finish();
+            tracing::subscriber::set_default(subscriber)
+        }
+
+        /// Lines closing the span `name`, e.g. `outer{a=1}:name{b=2}: close`
+        fn closed(&self, name: &str) -> Vec<String> {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            let prefix = format!("{}{{", name);
+            output
+                .lines()
+                .filter(|line| {
+                    // The closing span is the innermost one of the line's span context
+                    line.strip_suffix(": close")
+                        .is_some_and(|spans| spans.rsplit("}:").next().unwrap().starts_with(&prefix))
+                })
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn publishing_records_spans_and_metrics() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = KafkaConfig {
+            broker: Some(listener.local_addr().unwrap().to_string()),
+            message_timeout_ms: 300,
+            retry_policy: RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(10),
+            },
+            ..plain_config()
+        };
+        let publisher = ProofPublisher::new(&config).unwrap();
+        let proof = ZkProof {
+            identifier: "traced-proof".to_string(),
+            proof_kind: twine_types::proofs::ProofKind::SolanaConsensusProof,
+            proof_data: ProofData::SP1(twine_types::proofs::SP1Proof {
+                version: 2,
+                proof: vec![1, 2, 3],
+                public_value: Vec::new(),
+                verification_key: [0; 32],
+            }),
+        };
+
+        let attempts = metrics::KAFKA_PUBLISH_ATTEMPTS.get();
+        let retries = metrics::KAFKA_PUBLISH_RETRIES.get();
+        let failures = metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&["retryable"]).get();
+        let serialized = metrics::KAFKA_SERIALIZE_SECONDS.get_sample_count();
+
+        let traces = CapturedTraces::default();
+        let guard = traces.install();
+        publisher.publish_proof_at(&proof, Some(1_700_000_000_000)).await.unwrap_err();
+        drop(guard);
+
+        let publish = traces.closed("kafka_publish_proof");
+        assert_eq!(publish.len(), 1, "{:?}", publish);
+        assert!(publish[0].contains("identifier=traced-proof"), "{}", publish[0]);
+        assert!(publish[0].contains("timestamp_ms=1700000000000"), "{}", publish[0]);
+        assert!(publish[0].contains("serialize_ms="), "{}", publish[0]);
+
+        // The send span is nested in the publish span and records each attempt, but no ack
+        let send = traces.closed("kafka_send");
+        assert_eq!(send.len(), 1, "{:?}", send);
+        assert!(send[0].contains("kafka_publish_proof{"), "{}", send[0]);
+        assert!(send[0].contains(&format!("topic={:?}", config.topic)), "{}", send[0]);
+        assert!(send[0].contains(r#"key="traced-proof""#), "{}", send[0]);
+        assert!(send[0].contains("attempts=2"), "{}", send[0]);
+        assert!(!send[0].contains("ack_ms="), "{}", send[0]);
+
+        // Other tests publish concurrently, so the counters only give lower bounds
+        assert!(metrics::KAFKA_PUBLISH_ATTEMPTS.get() >= attempts + 2);
+        assert!(metrics::KAFKA_PUBLISH_RETRIES.get() > retries);
+        assert!(metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&["retryable"]).get() > failures);
+        assert!(metrics::KAFKA_SERIALIZE_SECONDS.get_sample_count() > serialized);
+    }
+
+    #[tokio::test]
+    async fn oversized_messages_are_counted_without_an_attempt() {
+        let publisher = ProofPublisher::new(&KafkaConfig { max_message_bytes: 1000, ..plain_config() }).unwrap();
+        let oversized = metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&["oversized"]).get();
+
+        let traces = CapturedTraces::default();
+        let guard = traces.install();
+        let error = publisher.publish("big", &[0; 2000]).await.unwrap_err();
+        drop(guard);
+
+        assert_eq!(error.reason(), "oversized");
+        let send = traces.closed("kafka_send");
+        assert_eq!(send.len(), 1, "{:?}", send);
+        assert!(send[0].contains(r#"key="big""#) && send[0].contains("bytes=2000"), "{}", send[0]);
+        assert!(!send[0].contains("attempts="), "{}", send[0]);
+        assert!(metrics::KAFKA_PUBLISH_FAILURES.with_label_values(&["oversized"]).get() > oversized);
+    }
+
+    #[test]
+    fn debug_output_leaves_out_the_passwords() {
+        let config = KafkaConfig {
+            sasl_password: Some("hunter2".to_string()),
+            schema_registry: Some(SchemaRegistryConfig {
+                url: "http://localhost:8081".to_string(),
+                username: Some("registry".to_string()),
+                password: Some("hunter2".to_string()),
+            }),
+            ..KafkaConfig::default()
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(debug.contains(r#"sasl_password: Some("<redacted>")"#), "{}", debug);
+        assert!(debug.contains(r#"username: Some("registry")"#), "{}", debug);
+        let options = ConnectionOptions { sasl_password: Some("hunter2".to_string()), ..ConnectionOptions::default() };
+        assert!(!format!("{:?}", options).contains("hunter2"));
+        assert!(format!("{:?}", KafkaConfig::default()).contains("sasl_password: None"));
+    }
+
+    #[test]
+    fn record_size_counts_the_key_and_headers() {
+        assert_eq!(record_size("", 0, &OwnedHeaders::new()), RECORD_OVERHEAD_BYTES);
+        let headers = OwnedHeaders::new()
+            .insert(Header { key: CONTENT_TYPE_HEADER, value: Some("application/json") })
+            .insert(Header { key: AGGREGATED_COUNT_HEADER, value: None::<&str> });
+        let headers_len =
+            10 + CONTENT_TYPE_HEADER.len() + "application/json".len() + 10 + AGGREGATED_COUNT_HEADER.len();
+        assert_eq!(record_size("solana-stub-1-2", 1000, &headers), RECORD_OVERHEAD_BYTES + 15 + 1000 + headers_len);
+    }
+
+    #[test]
+    fn payload_under_the_limit_can_be_oversized_as_a_record() {
+        let limit = DEFAULT_MAX_MESSAGE_BYTES;
+        let headers = OwnedHeaders::new().insert(Header { key: CONTENT_TYPE_HEADER, value: Some("application/json") });
+        let payload_len = limit - RECORD_OVERHEAD_BYTES;
+        assert!(check_message_size(record_size("", payload_len, &OwnedHeaders::new()), limit).is_ok());
+        let size = record_size("key", payload_len, &headers);
+        match check_message_size(size, limit) {
+            Err(KafkaPublishError::Oversized { size: rejected, limit: reported }) => {
+                assert_eq!((rejected, reported), (size, limit))
+            }
+            other => panic!("expected an oversized error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_fetch_holds_the_largest_message() {
+        assert!(ConsumerTuning::default().validate().is_ok());
+        let tuning = ConsumerTuning { fetch_max_bytes: DEFAULT_MAX_MESSAGE_BYTES as u64, ..ConsumerTuning::default() };
+        assert!(tuning.validate().is_ok());
+        let tuning = ConsumerTuning { fetch_max_bytes: DEFAULT_MAX_MESSAGE_BYTES as u64 - 1, ..tuning };
+        assert!(matches!(tuning.validate(), Err(KafkaConfigError::Conflict(_))));
+    }
+}
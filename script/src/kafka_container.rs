@@ -0,0 +1,182 @@
+//! Kafka broker in a container, for integration tests
+//!
+//! [`run_with_cluster`] runs a test against a single-node Kafka container
+//! started through testcontainers. Starting the broker is the slow part, so
+//! the tests of a binary that run at the same time share one container, on a
+//! runtime shared with it; the last of them to finish removes it, whether the
+//! tests passed or panicked. Tests keep out of each other's way with their own
+//! [`unique_topic`]. Only stopping or dropping its cluster removes a
+//! container; there is no reaper, so a cluster kept in a static would outlive
+//! the process. Requires the `kafka-it` feature and Docker.
+
+use crate::kafka::{ConsumerTuning, KafkaConfig, ProofPublisher, TopicCreation};
+use futures::FutureExt;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::OwnedMessage;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, ImageExt};
+use testcontainers_modules::kafka::{Kafka, KAFKA_PORT};
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+
+/// How long topic creation may take on a freshly started broker
+const CREATE_TOPIC_TIMEOUT: Duration = Duration::from_secs(30);
+
+static TOPIC_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Runtime of every test of the process, so the shared container is only used from the runtime it was started on
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| Runtime::new().expect("Failed to start the test runtime"));
+
+/// The cluster of the tests running now
+static SHARED: LazyLock<Mutex<Option<SharedCluster>>> = LazyLock::new(|| Mutex::new(None));
+
+struct SharedCluster {
+    cluster: Arc<KafkaTestCluster>,
+    /// Tests using the cluster
+    users: usize,
+}
+
+/// A running broker and how to reach it
+pub struct KafkaTestCluster {
+    bootstrap_servers: String,
+    container: ContainerAsync<Kafka>,
+}
+
+impl KafkaTestCluster {
+    /// Start a new broker; [`stop`](Self::stop) removes it
+    ///
+    /// The transaction log is configured for the single broker, so transactional producers can start.
+    pub async fn start() -> Result<Self, String> {
+        let container = Kafka::default()
+            .with_env_var("KAFKA_TRANSACTION_STATE_LOG_REPLICATION_FACTOR", "1")
+            .with_env_var("KAFKA_TRANSACTION_STATE_LOG_MIN_ISR", "1")
+            .start()
+            .await
+            .map_err(|e| format!("Failed to start Kafka: {}", e))?;
+        let port = container
+            .get_host_port_ipv4(KAFKA_PORT)
+            .await
+            .map_err(|e| format!("Failed to get the Kafka port: {}", e))?;
+        Ok(Self { bootstrap_servers: format!("127.0.0.1:{}", port), container })
+    }
+
+    /// Stop the broker and remove its container
+    pub async fn stop(self) -> Result<(), String> {
+        self.container.rm().await.map_err(|e| format!("Failed to remove the Kafka container: {}", e))
+    }
+
+    pub fn bootstrap_servers(&self) -> &str {
+        &self.bootstrap_servers
+    }
+
+    /// Plaintext configuration publishing to `topic` on this broker
+    pub fn config(&self, topic: &str) -> KafkaConfig {
+        KafkaConfig {
+            use_tls: false,
+            ca_cert_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+            broker: Some(self.bootstrap_servers.clone()),
+            topic: topic.to_string(),
+            ..KafkaConfig::default()
+        }
+    }
+
+    /// Create `topic` with the admin code the prover's pre-flight check uses
+    pub async fn create_topic(&self, topic: &str, partitions: i32) -> Result<(), String> {
+        let publisher = ProofPublisher::new(&self.config(topic)).map_err(|e| e.to_string())?;
+        let creation = TopicCreation { partitions, replication_factor: 1 };
+        publisher.check_connectivity(CREATE_TOPIC_TIMEOUT, Some(creation)).await.map_err(|e| e.to_string())
+    }
+
+    /// Read `count` messages of `topic` from the beginning, fetching like the consumer by default
+    ///
+    /// Returns fewer messages if `timeout` passes first.
+    pub async fn read_messages(
+        &self,
+        topic: &str,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Vec<OwnedMessage>, String> {
+        let mut client_config = self.config(topic).client_config();
+        ConsumerTuning::default().apply(&mut client_config);
+        let consumer: StreamConsumer = client_config
+            .set("group.id", unique_topic("it-reader"))
+            .set("auto.offset.reset", "earliest")
+            .set("enable.auto.commit", "false")
+            .create()
+            .map_err(|e| format!("Failed to create the consumer: {}", e))?;
+        consumer.subscribe(&[topic]).map_err(|e| format!("Failed to subscribe to {}: {}", topic, e))?;
+
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::new();
+        while messages.len() < count {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match tokio::time::timeout(remaining, consumer.recv()).await {
+                Ok(Ok(message)) => messages.push(message.detach()),
+                Ok(Err(e)) => return Err(format!("Failed to read {}: {}", topic, e)),
+                Err(_) => break,
+            }
+        }
+        Ok(messages)
+    }
+}
+
+/// Run `test` against the broker of the tests running now, starting one if there is none
+///
+/// The last test to finish removes the broker. A panic of `test` is resumed
+/// once it has let go of the broker, so a failing test fails without leaving
+/// the broker running.
+pub fn run_with_cluster<F, Fut>(test: F)
+where
+    F: FnOnce(Arc<KafkaTestCluster>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    RUNTIME.block_on(async {
+        let cluster = acquire_cluster().await;
+        let outcome = AssertUnwindSafe(test(cluster)).catch_unwind().await;
+        release_cluster().await;
+        if let Err(panic) = outcome {
+            panic::resume_unwind(panic);
+        }
+    });
+}
+
+/// The shared cluster, started for the first test using it
+async fn acquire_cluster() -> Arc<KafkaTestCluster> {
+    let mut shared = SHARED.lock().await;
+    if let Some(shared) = shared.as_mut() {
+        shared.users += 1;
+        return Arc::clone(&shared.cluster);
+    }
+    let cluster = Arc::new(KafkaTestCluster::start().await.expect("Failed to start the Kafka container"));
+    *shared = Some(SharedCluster { cluster: Arc::clone(&cluster), users: 1 });
+    cluster
+}
+
+/// Let go of the shared cluster, removing it when no other test uses it
+async fn release_cluster() {
+    let mut shared = SHARED.lock().await;
+    let Some(SharedCluster { users, .. }) = shared.as_mut() else { return };
+    *users -= 1;
+    if *users > 0 {
+        return;
+    }
+    let Some(SharedCluster { cluster, .. }) = shared.take() else { return };
+    // Held elsewhere only by a task a test left running; dropping the last handle removes it as well
+    if let Ok(cluster) = Arc::try_unwrap(cluster) {
+        if let Err(e) = cluster.stop().await {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+/// Topic name no other test of the process uses
+pub fn unique_topic(prefix: &str) -> String {
+    format!("{}-{}-{}", prefix, std::process::id(), TOPIC_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
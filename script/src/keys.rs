@@ -0,0 +1,642 @@
+//! Signing keys shared by the binaries that sign
+//!
+//! A key is ed25519 (Solana) or secp256k1 (Ethereum) and is loaded from one of:
+//!
+//! - a key file: a JSON array of secret bytes as written by `solana-keygen`
+//!   (64 bytes of secret and public key, or a bare 32-byte secret), or an
+//!   encrypted [`Keystore`]
+//! - an environment variable holding the 32-byte secret as hex (ed25519 also
+//!   takes the 64-byte keypair)
+//!
+//! Keystores derive an AES-256-GCM key from a passphrase with scrypt. The
+//! passphrase is read from [`PASSPHRASE_ENV`], or prompted for on the terminal.
+//! Binaries take the key as `--key-file <PATH>` or `--key-env <VAR>`. Secrets
+//! are zeroized when dropped, including the intermediate buffers they are
+//! decoded from. Requires the `keys` feature.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use ed25519_dalek::{Signer as _, Verifier as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Environment variable holding the passphrase of encrypted keystores
+pub const PASSPHRASE_ENV: &str = "KEY_PASSPHRASE";
+
+/// Version of the keystore format written by [`Keystore::encrypt`]
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// scrypt cost of new keystores: 2^15 iterations, 32 MiB of memory
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyKind {
+    Ed25519,
+    Secp256k1,
+}
+
+impl FromStr for KeyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ed25519" => Ok(KeyKind::Ed25519),
+            "secp256k1" => Ok(KeyKind::Secp256k1),
+            _ => Err(format!("Unknown key kind '{}' (expected ed25519 or secp256k1)", s)),
+        }
+    }
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyKind::Ed25519 => write!(f, "ed25519"),
+            KeyKind::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
+/// Why a key could not be loaded or a signature not verified
+#[derive(Debug)]
+pub enum KeyError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The environment variable is not set
+    MissingEnv(String),
+    /// The key material is not in a supported format
+    Format(String),
+    /// The keystore holds a key of another kind
+    WrongKind {
+        expected: KeyKind,
+        actual: KeyKind,
+    },
+    /// No passphrase was given and none could be prompted for
+    NoPassphrase(String),
+    /// The keystore did not decrypt with the passphrase
+    WrongPassphrase,
+    InvalidSignature(String),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Io { path, source } => write!(f, "Failed to read {}: {}", path.display(), source),
+            KeyError::MissingEnv(var) => write!(f, "Environment variable {} is not set", var),
+            KeyError::Format(reason) => write!(f, "Invalid key: {}", reason),
+            KeyError::WrongKind { expected, actual } => {
+                write!(f, "The keystore holds a {} key, not {}", actual, expected)
+            }
+            KeyError::NoPassphrase(reason) => {
+                write!(f, "The keystore needs a passphrase ({} or a terminal): {}", PASSPHRASE_ENV, reason)
+            }
+            KeyError::WrongPassphrase => write!(f, "Wrong passphrase, or the keystore is corrupted"),
+            KeyError::InvalidSignature(reason) => write!(f, "Invalid signature: {}", reason),
+        }
+    }
+}
+
+impl Error for KeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KeyError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Where a key is loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// A key file or keystore (`--key-file`)
+    File(PathBuf),
+    /// An environment variable holding the secret as hex (`--key-env`)
+    Env(String),
+}
+
+impl KeySource {
+    /// Source given by `--key-file` or `--key-env`, preferring the file
+    pub fn from_args(key_file: Option<&Path>, key_env: Option<&str>) -> Option<Self> {
+        match (key_file, key_env) {
+            (Some(path), _) => Some(KeySource::File(path.to_path_buf())),
+            (None, Some(var)) => Some(KeySource::Env(var.to_string())),
+            (None, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::File(path) => write!(f, "{}", path.display()),
+            KeySource::Env(var) => write!(f, "${}", var),
+        }
+    }
+}
+
+/// A secret key; zeroized on drop
+pub enum Key {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key({}, 0x{})", self.kind(), hex::encode(self.pubkey()))
+    }
+}
+
+impl Key {
+    /// Generate a new random key
+    pub fn generate(kind: KeyKind) -> Self {
+        let mut secret = Zeroizing::new([0u8; 32]);
+        loop {
+            rand::rngs::OsRng.fill_bytes(&mut secret[..]);
+            // Only secp256k1 rejects secrets, with negligible probability
+            if let Ok(key) = Key::from_secret(kind, &secret[..]) {
+                return key;
+            }
+        }
+    }
+
+    /// Key of a 32-byte secret, or of a 64-byte ed25519 keypair whose public half must match
+    pub fn from_secret(kind: KeyKind, bytes: &[u8]) -> Result<Self, KeyError> {
+        match (kind, bytes.len()) {
+            (KeyKind::Ed25519, 32) => {
+                let mut secret = Zeroizing::new([0u8; 32]);
+                secret.copy_from_slice(bytes);
+                Ok(Key::Ed25519(ed25519_dalek::SigningKey::from_bytes(&secret)))
+            }
+            (KeyKind::Ed25519, 64) => {
+                let mut keypair = Zeroizing::new([0u8; 64]);
+                keypair.copy_from_slice(bytes);
+                ed25519_dalek::SigningKey::from_keypair_bytes(&keypair)
+                    .map(Key::Ed25519)
+                    .map_err(|e| KeyError::Format(format!("the public key does not match the secret: {}", e)))
+            }
+            (KeyKind::Secp256k1, 32) => k256::ecdsa::SigningKey::from_slice(bytes)
+                .map(Key::Secp256k1)
+                .map_err(|_| KeyError::Format("not a valid secp256k1 secret".to_string())),
+            (KeyKind::Ed25519, len) => {
+                Err(KeyError::Format(format!("{} bytes, expected a 32-byte secret or a 64-byte keypair", len)))
+            }
+            (KeyKind::Secp256k1, len) => Err(KeyError::Format(format!("{} bytes, expected a 32-byte secret", len))),
+        }
+    }
+
+    /// Key of a hex secret, with or without `0x`
+    pub fn from_hex(kind: KeyKind, hex_secret: &str) -> Result<Self, KeyError> {
+        let bytes = Zeroizing::new(
+            hex::decode(hex_secret.trim().trim_start_matches("0x"))
+                .map_err(|e| KeyError::Format(format!("not hex: {}", e)))?,
+        );
+        Key::from_secret(kind, &bytes)
+    }
+
+    /// Key of the hex secret in an environment variable
+    pub fn from_env(kind: KeyKind, var: &str) -> Result<Self, KeyError> {
+        let value = Zeroizing::new(std::env::var(var).map_err(|_| KeyError::MissingEnv(var.to_string()))?);
+        Key::from_hex(kind, &value).map_err(|e| match e {
+            KeyError::Format(reason) => KeyError::Format(format!("{}: {}", var, reason)),
+            e => e,
+        })
+    }
+
+    /// Key of a `solana-keygen` JSON file or an encrypted keystore
+    ///
+    /// `passphrase` is only called for keystores.
+    pub fn from_file(
+        kind: KeyKind,
+        path: &Path,
+        passphrase: impl FnOnce() -> Result<Zeroizing<String>, KeyError>,
+    ) -> Result<Self, KeyError> {
+        let contents =
+            Zeroizing::new(fs::read(path).map_err(|source| KeyError::Io { path: path.to_path_buf(), source })?);
+        let in_file = |e: KeyError| match e {
+            KeyError::Format(reason) => KeyError::Format(format!("{}: {}", path.display(), reason)),
+            e => e,
+        };
+        match contents.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'[') => {
+                let bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+                    serde_json::from_slice(&contents)
+                        .map_err(|e| in_file(KeyError::Format(format!("not a JSON array of bytes: {}", e))))?,
+                );
+                Key::from_secret(kind, &bytes).map_err(in_file)
+            }
+            Some(b'{') => {
+                let keystore: Keystore = serde_json::from_slice(&contents)
+                    .map_err(|e| in_file(KeyError::Format(format!("not a keystore: {}", e))))?;
+                keystore.decrypt(kind, &passphrase()?).map_err(in_file)
+            }
+            _ => Err(in_file(KeyError::Format("expected a JSON array of bytes or a keystore".to_string()))),
+        }
+    }
+
+    /// Load a key, prompting for the passphrase of a keystore unless [`PASSPHRASE_ENV`] is set
+    pub fn load(kind: KeyKind, source: &KeySource) -> Result<Self, KeyError> {
+        match source {
+            KeySource::File(path) => Key::from_file(kind, path, || read_passphrase(path)),
+            KeySource::Env(var) => Key::from_env(kind, var),
+        }
+    }
+
+    pub fn kind(&self) -> KeyKind {
+        match self {
+            Key::Ed25519(_) => KeyKind::Ed25519,
+            Key::Secp256k1(_) => KeyKind::Secp256k1,
+        }
+    }
+
+    /// Public key: 32 bytes for ed25519, the 33-byte compressed SEC1 point for secp256k1
+    pub fn pubkey(&self) -> Vec<u8> {
+        match self {
+            Key::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+            Key::Secp256k1(key) => key.verifying_key().to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    /// Sign `msg`: a 64-byte ed25519 signature, or a 64-byte `r || s` ECDSA signature of its SHA-256
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        match self {
+            Key::Ed25519(key) => key.sign(msg).to_bytes().to_vec(),
+            Key::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign(msg);
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+
+    /// The ed25519 key, for signers that need the concrete type
+    pub fn as_ed25519(&self) -> Option<&ed25519_dalek::SigningKey> {
+        match self {
+            Key::Ed25519(key) => Some(key),
+            Key::Secp256k1(_) => None,
+        }
+    }
+
+    /// The secp256k1 key, for signers that need the concrete type
+    pub fn as_secp256k1(&self) -> Option<&k256::ecdsa::SigningKey> {
+        match self {
+            Key::Secp256k1(key) => Some(key),
+            Key::Ed25519(_) => None,
+        }
+    }
+
+    fn secret(&self) -> Zeroizing<[u8; 32]> {
+        match self {
+            Key::Ed25519(key) => Zeroizing::new(key.to_bytes()),
+            Key::Secp256k1(key) => Zeroizing::new(key.to_bytes().into()),
+        }
+    }
+}
+
+/// Check a signature made by [`Key::sign`]
+pub fn verify(kind: KeyKind, pubkey: &[u8], msg: &[u8], signature: &[u8]) -> Result<(), KeyError> {
+    let invalid = |e: &dyn fmt::Display| KeyError::InvalidSignature(e.to_string());
+    match kind {
+        KeyKind::Ed25519 => {
+            let pubkey: [u8; 32] =
+                pubkey.try_into().map_err(|_| KeyError::Format(format!("{}-byte ed25519 public key", pubkey.len())))?;
+            let key = ed25519_dalek::VerifyingKey::from_bytes(&pubkey).map_err(|e| KeyError::Format(e.to_string()))?;
+            let signature = ed25519_dalek::Signature::from_slice(signature).map_err(|e| invalid(&e))?;
+            key.verify(msg, &signature).map_err(|e| invalid(&e))
+        }
+        KeyKind::Secp256k1 => {
+            let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey)
+                .map_err(|_| KeyError::Format("not a SEC1 secp256k1 public key".to_string()))?;
+            let signature = k256::ecdsa::Signature::from_slice(signature).map_err(|e| invalid(&e))?;
+            key.verify(msg, &signature).map_err(|e| invalid(&e))
+        }
+    }
+}
+
+/// Passphrase of the keystore at `path`: [`PASSPHRASE_ENV`], or a prompt on the terminal
+pub fn read_passphrase(path: &Path) -> Result<Zeroizing<String>, KeyError> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    rpassword::prompt_password(format!("🔐 Passphrase for {}: ", path.display()))
+        .map(Zeroizing::new)
+        .map_err(|e| KeyError::NoPassphrase(e.to_string()))
+}
+
+/// scrypt parameters and salt of a keystore
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    /// Hex of the salt
+    pub salt: String,
+}
+
+/// A key encrypted with AES-256-GCM under an scrypt-derived key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub kind: KeyKind,
+    /// Hex of the public key, readable without the passphrase
+    pub pubkey: String,
+    pub scrypt: ScryptParams,
+    /// Hex of the 12-byte nonce
+    pub nonce: String,
+    /// Hex of the encrypted 32-byte secret followed by the 16-byte tag
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypt a key with a passphrase
+    pub fn encrypt(key: &Key, passphrase: &str) -> Result<Self, KeyError> {
+        Self::encrypt_with_cost(key, passphrase, SCRYPT_LOG_N)
+    }
+
+    /// Encrypt with 2^`log_n` scrypt iterations
+    fn encrypt_with_cost(key: &Key, passphrase: &str, log_n: u8) -> Result<Self, KeyError> {
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let scrypt = ScryptParams { log_n, r: SCRYPT_R, p: SCRYPT_P, salt: hex::encode(salt) };
+        let cipher = scrypt.cipher(passphrase)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), &key.secret()[..])
+            .map_err(|_| KeyError::Format("encryption failed".to_string()))?;
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            kind: key.kind(),
+            pubkey: hex::encode(key.pubkey()),
+            scrypt,
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the key, which must be of kind `kind`
+    pub fn decrypt(&self, kind: KeyKind, passphrase: &str) -> Result<Key, KeyError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeyError::Format(format!("unsupported keystore version {}", self.version)));
+        }
+        if self.kind != kind {
+            return Err(KeyError::WrongKind { expected: kind, actual: self.kind });
+        }
+        let nonce = decode_hex("nonce", &self.nonce)?;
+        if nonce.len() != 12 {
+            return Err(KeyError::Format(format!("{}-byte nonce, expected 12", nonce.len())));
+        }
+        let ciphertext = decode_hex("ciphertext", &self.ciphertext)?;
+        let secret = Zeroizing::new(
+            self.scrypt
+                .cipher(passphrase)?
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+                .map_err(|_| KeyError::WrongPassphrase)?,
+        );
+        let key = Key::from_secret(kind, &secret)?;
+        if hex::encode(key.pubkey()) != self.pubkey.trim_start_matches("0x").to_ascii_lowercase() {
+            return Err(KeyError::Format("the decrypted key does not match the keystore's public key".to_string()));
+        }
+        Ok(key)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), KeyError> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| KeyError::Format(e.to_string()))?;
+        fs::write(path, json).map_err(|source| KeyError::Io { path: path.to_path_buf(), source })
+    }
+}
+
+impl ScryptParams {
+    /// AES-256-GCM cipher keyed by scrypt of the passphrase
+    fn cipher(&self, passphrase: &str) -> Result<Aes256Gcm, KeyError> {
+        let salt = decode_hex("salt", &self.salt)?;
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, 32)
+            .map_err(|e| KeyError::Format(format!("invalid scrypt parameters: {}", e)))?;
+        let mut derived = Zeroizing::new([0u8; 32]);
+        scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut derived[..])
+            .map_err(|e| KeyError::Format(format!("scrypt failed: {}", e)))?;
+        Aes256Gcm::new_from_slice(&derived[..]).map_err(|e| KeyError::Format(e.to_string()))
+    }
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, KeyError> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|e| KeyError::Format(format!("'{}' is not hex: {}", field, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 8032 test 1
+    const ED25519_SECRET: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const ED25519_PUBKEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const ED25519_EMPTY_SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+    /// Cheap scrypt cost, so keystore tests do not each spend 32 MiB and seconds on key derivation
+    const TEST_LOG_N: u8 = 4;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("keys-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn passphrase(passphrase: &str) -> impl FnOnce() -> Result<Zeroizing<String>, KeyError> + '_ {
+        move || Ok(Zeroizing::new(passphrase.to_string()))
+    }
+
+    fn no_passphrase() -> Result<Zeroizing<String>, KeyError> {
+        panic!("only keystores ask for a passphrase")
+    }
+
+    #[test]
+    fn signs_and_verifies_with_both_kinds() {
+        for kind in [KeyKind::Ed25519, KeyKind::Secp256k1] {
+            let key = Key::generate(kind);
+            assert_eq!(key.kind(), kind);
+            let signature = key.sign(b"public commitments");
+            assert_eq!(signature.len(), 64);
+            verify(kind, &key.pubkey(), b"public commitments", &signature).unwrap();
+
+            let other = Key::generate(kind);
+            assert!(matches!(
+                verify(kind, &other.pubkey(), b"public commitments", &signature),
+                Err(KeyError::InvalidSignature(_))
+            ));
+            assert!(matches!(
+                verify(kind, &key.pubkey(), b"other commitments", &signature),
+                Err(KeyError::InvalidSignature(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn matches_the_rfc_8032_vector() {
+        let key = Key::from_hex(KeyKind::Ed25519, &format!("0x{}", ED25519_SECRET)).unwrap();
+        assert_eq!(hex::encode(key.pubkey()), ED25519_PUBKEY);
+        assert_eq!(hex::encode(key.sign(b"")), ED25519_EMPTY_SIGNATURE);
+        assert_eq!(key.pubkey().len(), 32);
+        assert_eq!(Key::generate(KeyKind::Secp256k1).pubkey().len(), 33);
+    }
+
+    #[test]
+    fn rejects_tampered_signatures() {
+        for kind in [KeyKind::Ed25519, KeyKind::Secp256k1] {
+            let key = Key::generate(kind);
+            let signature = key.sign(b"proof");
+            for index in [0, 31, 32, 63] {
+                let mut tampered = signature.clone();
+                tampered[index] ^= 0x01;
+                let result = verify(kind, &key.pubkey(), b"proof", &tampered);
+                assert!(matches!(result, Err(KeyError::InvalidSignature(_))), "{} byte {}: {:?}", kind, index, result);
+            }
+            assert!(matches!(
+                verify(kind, &key.pubkey(), b"proof", &signature[..63]),
+                Err(KeyError::InvalidSignature(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_public_keys() {
+        let key = Key::generate(KeyKind::Ed25519);
+        let signature = key.sign(b"proof");
+        assert!(matches!(
+            verify(KeyKind::Ed25519, &key.pubkey()[..31], b"proof", &signature),
+            Err(KeyError::Format(_))
+        ));
+        assert!(matches!(verify(KeyKind::Secp256k1, &[5; 33], b"proof", &signature), Err(KeyError::Format(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_secrets() {
+        let error = |kind, bytes: &[u8]| Key::from_secret(kind, bytes).unwrap_err().to_string();
+        assert!(error(KeyKind::Ed25519, &[1; 31]).contains("31 bytes, expected a 32-byte secret or a 64-byte keypair"));
+        assert!(error(KeyKind::Secp256k1, &[1; 64]).contains("64 bytes, expected a 32-byte secret"));
+        // Zero is not a valid secp256k1 scalar
+        assert!(error(KeyKind::Secp256k1, &[0; 32]).contains("not a valid secp256k1 secret"));
+        assert!(Key::from_hex(KeyKind::Ed25519, "zz").unwrap_err().to_string().contains("not hex"));
+
+        // A keypair whose public half belongs to another secret
+        let mut keypair = hex::decode(ED25519_SECRET).unwrap();
+        keypair.extend(Key::generate(KeyKind::Ed25519).pubkey());
+        assert!(error(KeyKind::Ed25519, &keypair).contains("does not match"));
+    }
+
+    #[test]
+    fn loads_solana_keygen_files() {
+        let dir = temp_dir("keygen");
+        let mut keypair = hex::decode(ED25519_SECRET).unwrap();
+        keypair.extend(hex::decode(ED25519_PUBKEY).unwrap());
+        let path = dir.join("id.json");
+        fs::write(&path, serde_json::to_vec(&keypair).unwrap()).unwrap();
+
+        let key = Key::from_file(KeyKind::Ed25519, &path, no_passphrase).unwrap();
+        assert_eq!(hex::encode(key.pubkey()), ED25519_PUBKEY);
+
+        fs::write(&path, "not a key").unwrap();
+        let error = Key::from_file(KeyKind::Ed25519, &path, no_passphrase).unwrap_err().to_string();
+        assert!(
+            error.contains("id.json") && error.contains("expected a JSON array of bytes or a keystore"),
+            "{}",
+            error
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keystores_round_trip_through_a_file() {
+        let dir = temp_dir("keystore");
+        for kind in [KeyKind::Ed25519, KeyKind::Secp256k1] {
+            let key = Key::generate(kind);
+            let keystore = Keystore::encrypt_with_cost(&key, "correct horse", TEST_LOG_N).unwrap();
+            assert_eq!(keystore.pubkey, hex::encode(key.pubkey()));
+            let path = dir.join(format!("{}.json", kind));
+            keystore.save(&path).unwrap();
+
+            let loaded = Key::from_file(kind, &path, passphrase("correct horse")).unwrap();
+            assert_eq!(loaded.pubkey(), key.pubkey());
+            verify(kind, &key.pubkey(), b"proof", &loaded.sign(b"proof")).unwrap();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn new_keystores_use_the_default_cost() {
+        let keystore = Keystore::encrypt(&Key::generate(KeyKind::Ed25519), "pass").unwrap();
+        assert_eq!((keystore.version, keystore.scrypt.log_n, keystore.scrypt.r), (KEYSTORE_VERSION, 15, 8));
+        assert_ne!(keystore.ciphertext, hex::encode(Key::generate(KeyKind::Ed25519).secret()));
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        let dir = temp_dir("wrong-passphrase");
+        let path = dir.join("key.json");
+        let key = Key::generate(KeyKind::Secp256k1);
+        Keystore::encrypt_with_cost(&key, "correct horse", TEST_LOG_N).unwrap().save(&path).unwrap();
+
+        let result = Key::from_file(KeyKind::Secp256k1, &path, passphrase("battery staple"));
+        assert!(matches!(result, Err(KeyError::WrongPassphrase)), "{:?}", result);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_tampered_keystores() {
+        let key = Key::generate(KeyKind::Ed25519);
+        let keystore = Keystore::encrypt_with_cost(&key, "pass", TEST_LOG_N).unwrap();
+
+        let mut ciphertext = keystore.clone();
+        let mut bytes = hex::decode(&ciphertext.ciphertext).unwrap();
+        bytes[0] ^= 0x01;
+        ciphertext.ciphertext = hex::encode(bytes);
+        assert!(matches!(ciphertext.decrypt(KeyKind::Ed25519, "pass"), Err(KeyError::WrongPassphrase)));
+
+        let mut pubkey = keystore.clone();
+        pubkey.pubkey = hex::encode(Key::generate(KeyKind::Ed25519).pubkey());
+        assert!(pubkey.decrypt(KeyKind::Ed25519, "pass").unwrap_err().to_string().contains("does not match"));
+
+        let mut version = keystore.clone();
+        version.version = 2;
+        assert!(version.decrypt(KeyKind::Ed25519, "pass").unwrap_err().to_string().contains("version 2"));
+
+        assert!(matches!(
+            keystore.decrypt(KeyKind::Secp256k1, "pass"),
+            Err(KeyError::WrongKind { expected: KeyKind::Secp256k1, actual: KeyKind::Ed25519 })
+        ));
+    }
+
+    #[test]
+    fn loads_hex_secrets_from_the_environment() {
+        let var = format!("KEYS_TEST_SECRET_{}", std::process::id());
+        assert!(matches!(Key::from_env(KeyKind::Ed25519, &var), Err(KeyError::MissingEnv(_))));
+        std::env::set_var(&var, ED25519_SECRET);
+        let key = Key::load(KeyKind::Ed25519, &KeySource::Env(var.clone())).unwrap();
+        assert_eq!(hex::encode(key.pubkey()), ED25519_PUBKEY);
+
+        std::env::set_var(&var, "abcd");
+        let error = Key::from_env(KeyKind::Ed25519, &var).unwrap_err().to_string();
+        assert!(error.contains(&var) && error.contains("2 bytes"), "{}", error);
+        std::env::remove_var(&var);
+    }
+
+    #[test]
+    fn parses_kinds_and_sources() {
+        assert_eq!("Ed25519".parse::<KeyKind>().unwrap(), KeyKind::Ed25519);
+        assert_eq!("secp256k1".parse::<KeyKind>().unwrap().to_string(), "secp256k1");
+        assert!("rsa".parse::<KeyKind>().is_err());
+
+        let file = Path::new("key.json");
+        assert_eq!(KeySource::from_args(Some(file), Some("VAR")), Some(KeySource::File(file.to_path_buf())));
+        assert_eq!(KeySource::from_args(None, Some("VAR")).unwrap().to_string(), "$VAR");
+        assert_eq!(KeySource::from_args(None, None), None);
+    }
+}
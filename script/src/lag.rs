@@ -0,0 +1,73 @@
+//! Consumer lag from partition high watermarks
+//!
+//! Lag is the number of messages between a consumer's position (or a group's
+//! committed offset) and the partition's high watermark. A partition with no
+//! position yet counts every retained message as lag.
+
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::error::KafkaResult;
+use rdkafka::{Offset, TopicPartitionList};
+use std::time::Duration;
+
+/// Lag of one partition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+    /// Next offset to consume, or `None` if nothing was consumed or committed yet
+    pub position: Option<i64>,
+    pub low: i64,
+    pub high: i64,
+}
+
+impl PartitionLag {
+    /// Messages behind the high watermark
+    pub fn lag(&self) -> i64 {
+        // Offsets below the low watermark were deleted by retention and cannot be consumed
+        let from = self.position.map_or(self.low, |position| position.max(self.low));
+        (self.high - from).max(0)
+    }
+}
+
+/// Fetch the watermarks of every partition in `positions` and compute its lag
+///
+/// Entries whose offset is not a concrete offset (e.g. no committed offset)
+/// have no position. Fetching watermarks is a blocking broker round trip.
+pub fn fetch_lag<C, T>(consumer: &T, positions: &TopicPartitionList, timeout: Duration) -> KafkaResult<Vec<PartitionLag>>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    positions
+        .elements()
+        .iter()
+        .map(|element| {
+            let (low, high) = consumer.fetch_watermarks(element.topic(), element.partition(), timeout)?;
+            let position = match element.offset() {
+                Offset::Offset(offset) => Some(offset),
+                _ => None,
+            };
+            Ok(PartitionLag { topic: element.topic().to_string(), partition: element.partition(), position, low, high })
+        })
+        .collect()
+}
+
+/// Committed offsets of the consumer's group for every partition of `topics`
+///
+/// Only reads the group's offsets; the consumer does not join the group.
+pub fn group_positions<C, T>(consumer: &T, topics: &[String], timeout: Duration) -> KafkaResult<TopicPartitionList>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let mut partitions = TopicPartitionList::new();
+    for topic in topics {
+        let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+        for metadata_topic in metadata.topics().iter().filter(|t| t.name() == topic) {
+            for partition in metadata_topic.partitions() {
+                partitions.add_partition(topic, partition.id());
+            }
+        }
+    }
+    consumer.committed_offsets(partitions, timeout)
+}
@@ -0,0 +1,154 @@
+//! Finding the newest proof of an account in the tail of a topic
+//!
+//! The latest-proof query assigns every partition, reads a bounded window
+//! before each high watermark and keeps the matching proof with the highest
+//! end slot. On a compacted topic keyed by account the log retains the last
+//! record of every account, so the whole log is read and records keyed by
+//! other accounts are skipped without decoding them.
+
+use crate::msk_iam::KafkaClientContext;
+use crate::replay::ResolvedWindow;
+use rdkafka::admin::{AdminClient, AdminOptions, ResourceSpecifier};
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::error::KafkaResult;
+use rdkafka::{Offset, TopicPartitionList};
+use std::time::Duration;
+
+/// Default number of messages read before the high watermark of each partition
+pub const DEFAULT_LOOKBACK: u64 = 1000;
+
+/// How far back from the end of each partition to look
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookback {
+    /// The last N messages of each partition
+    Messages(u64),
+    /// Records at or after this time (Unix milliseconds)
+    Since(i64),
+    /// The whole retained log
+    Log,
+}
+
+/// Resolve the tail window of every partition of `topics`, up to the high watermark
+///
+/// Partitions with no record at or after a [`Lookback::Since`] timestamp get
+/// an empty window.
+pub fn tail_windows<C, T>(
+    consumer: &T,
+    topics: &[String],
+    lookback: Lookback,
+    timeout: Duration,
+) -> KafkaResult<Vec<ResolvedWindow>>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let mut windows = Vec::new();
+    for topic in topics {
+        let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+        let partitions: Vec<i32> = metadata
+            .topics()
+            .iter()
+            .filter(|t| t.name() == topic)
+            .flat_map(|t| t.partitions().iter().map(|partition| partition.id()))
+            .collect();
+
+        let starts = match lookback {
+            Lookback::Since(millis) => {
+                // offsets_for_times takes the timestamp in the offset field
+                let mut query = TopicPartitionList::new();
+                for &partition in &partitions {
+                    query.add_partition_offset(topic, partition, Offset::Offset(millis))?;
+                }
+                Some(consumer.offsets_for_times(query, timeout)?)
+            }
+            Lookback::Messages(_) | Lookback::Log => None,
+        };
+
+        for partition in partitions {
+            let (low, high) = consumer.fetch_watermarks(topic, partition, timeout)?;
+            let start = match lookback {
+                Lookback::Messages(count) => high.saturating_sub(i64::try_from(count).unwrap_or(i64::MAX)),
+                Lookback::Log => low,
+                Lookback::Since(_) => {
+                    let found = starts.as_ref().and_then(|starts| starts.find_partition(topic, partition));
+                    match found.map(|element| element.offset()) {
+                        Some(Offset::Offset(offset)) => offset,
+                        _ => high,
+                    }
+                }
+            };
+            windows.push(ResolvedWindow {
+                topic: topic.clone(),
+                partition,
+                requested: start.max(low)..high,
+                low,
+                high,
+            });
+        }
+    }
+    Ok(windows)
+}
+
+/// Whether the topic's `cleanup.policy` includes `compact`
+pub async fn is_compacted(
+    admin: &AdminClient<KafkaClientContext>,
+    topic: &str,
+    timeout: Duration,
+) -> KafkaResult<bool> {
+    let options = AdminOptions::new().request_timeout(Some(timeout));
+    let results = admin.describe_configs(&[ResourceSpecifier::Topic(topic)], &options).await?;
+    Ok(results.iter().flatten().any(|resource| {
+        resource
+            .get("cleanup.policy")
+            .and_then(|entry| entry.value.as_deref())
+            .is_some_and(|policy| policy.split(',').any(|policy| policy.trim() == "compact"))
+    }))
+}
+
+/// Position of a candidate proof, ordered by end slot, then record time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProofRank {
+    pub end_slot: u64,
+    /// Record timestamp in Unix milliseconds
+    pub timestamp: Option<i64>,
+}
+
+/// The newest of the candidates offered so far
+#[derive(Debug)]
+pub struct Newest<T> {
+    best: Option<(ProofRank, T)>,
+    candidates: u64,
+}
+
+impl<T> Default for Newest<T> {
+    fn default() -> Self {
+        Self { best: None, candidates: 0 }
+    }
+}
+
+impl<T> Newest<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a matching proof, returning whether it is the newest so far
+    ///
+    /// Ties keep the candidate offered first.
+    pub fn offer(&mut self, rank: ProofRank, candidate: T) -> bool {
+        self.candidates += 1;
+        if self.best.as_ref().is_some_and(|(best, _)| rank <= *best) {
+            return false;
+        }
+        self.best = Some((rank, candidate));
+        true
+    }
+
+    /// Number of matching proofs offered
+    pub fn candidates(&self) -> u64 {
+        self.candidates
+    }
+
+    pub fn into_best(self) -> Option<(ProofRank, T)> {
+        self.best
+    }
+}
@@ -1,4 +1,73 @@
+pub mod account_data;
+pub mod account_watch;
+pub mod acls;
+pub mod aggregation;
+pub mod admin_output;
+pub mod api_client;
+pub mod archive;
+pub mod artifacts;
+pub mod backpressure;
+pub mod chain_audit;
+pub mod checkpoint;
+pub mod commit;
+pub mod commitment_facts;
+pub mod consumer_groups;
+pub mod continuity;
+pub mod csv_export;
+pub mod dashboard;
+pub mod dedup;
+pub mod dlq;
+pub mod envelope;
+#[cfg(feature = "eth")]
+pub mod eth_submit;
+pub mod evm_export;
+pub mod fixtures;
+pub mod guest_profile;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
 pub mod kafka;
+#[cfg(feature = "kafka-it")]
+pub mod kafka_container;
+#[cfg(feature = "keys")]
+pub mod keys;
+pub mod lag;
+pub mod latest;
+pub mod metrics;
+#[cfg(feature = "test-util")]
+pub mod mock_rpc;
+pub mod msk_iam;
+pub mod native_admin;
+pub mod offset_reset;
+pub mod partition_workers;
+pub mod pipeline;
+pub mod presenter;
+pub mod proof_filter;
+pub mod proof_sink;
+#[cfg(feature = "sqlite")]
+pub mod proof_store;
+pub mod prove_jobs;
+pub mod public_values;
+pub mod publish_queue;
+pub mod purge;
+pub mod replay;
+pub mod routing;
+pub mod s3_archive;
+pub mod scheduler;
+pub mod schema_registry;
+pub mod shutdown;
 pub mod solana;
+#[cfg(feature = "solana-submit")]
+pub mod solana_submit;
+pub mod spool;
+pub mod sqlite_sink;
+pub mod stats;
+pub mod tail;
+pub mod throughput;
+pub mod topic_profile;
 pub mod types;  // For Solana RPC types
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod verify;
+pub mod vkey_registry;
+pub mod webhook;
+pub mod wrapped_proof;
\ No newline at end of file
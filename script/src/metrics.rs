@@ -0,0 +1,305 @@
+//! Prometheus metrics shared by the prover and consumer binaries
+
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::sync::{Arc, LazyLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Total number of publish attempts (including retries)
+pub static KAFKA_PUBLISH_ATTEMPTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "solana_prover_kafka_publish_attempts_total",
+        "Number of Kafka publish attempts, including retries"
+    )
+    .expect("failed to register kafka publish attempts counter")
+});
+
+/// Number of publish attempts that were retried after a retryable error
+pub static KAFKA_PUBLISH_RETRIES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "solana_prover_kafka_publish_retries_total",
+        "Number of Kafka publish retries after a retryable error"
+    )
+    .expect("failed to register kafka publish retries counter")
+});
+
+/// Number of messages successfully delivered to the broker
+pub static KAFKA_PUBLISHED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "solana_prover_kafka_published_total",
+        "Number of messages successfully delivered to Kafka"
+    )
+    .expect("failed to register kafka published counter")
+});
+
+/// Number of publishes that ultimately failed, by error class
+pub static KAFKA_PUBLISH_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_prover_kafka_publish_failures_total",
+        "Number of Kafka publishes that failed after all retries",
+        &["reason"]
+    )
+    .expect("failed to register kafka publish failures counter")
+});
+
+/// Number of proofs waiting in the background publish queue
+pub static KAFKA_PUBLISH_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "solana_prover_kafka_publish_queue_depth",
+        "Number of proofs waiting in the background publish queue"
+    )
+    .expect("failed to register kafka publish queue depth gauge")
+});
+
+/// Latency buckets in seconds, from sub-millisecond encoding up to slow broker acks
+const LATENCY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Lag buckets in seconds, up to an hour behind the producer
+const LAG_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0, 3600.0];
+
+/// Time spent encoding a proof into its payload format
+pub static KAFKA_SERIALIZE_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_prover_kafka_serialize_seconds",
+        "Time spent encoding a proof for Kafka",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .expect("failed to register kafka serialize histogram")
+});
+
+/// Time a proof waited in the background publish queue
+pub static KAFKA_QUEUE_WAIT_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_prover_kafka_queue_wait_seconds",
+        "Time a proof waited in the background publish queue",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .expect("failed to register kafka queue wait histogram")
+});
+
+/// Time from handing a message to the producer until the broker acknowledged it
+pub static KAFKA_ACK_LATENCY_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_prover_kafka_ack_latency_seconds",
+        "Time from send until the broker acknowledged the message",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .expect("failed to register kafka ack latency histogram")
+});
+
+/// Time the consumer spent decoding a message into a proof
+pub static KAFKA_DECODE_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_prover_kafka_decode_seconds",
+        "Time spent decoding a consumed Kafka message",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .expect("failed to register kafka decode histogram")
+});
+
+/// Time between the record timestamp and the consumer receiving it
+pub static KAFKA_END_TO_END_LAG_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_prover_kafka_end_to_end_lag_seconds",
+        "Time between the record timestamp and consumption",
+        LAG_BUCKETS.to_vec()
+    )
+    .expect("failed to register kafka end-to-end lag histogram")
+});
+
+/// Number of displayed messages waiting for or in processing by the partition workers
+pub static CONSUMER_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "solana_proofs_consumer_queue_depth",
+        "Number of consumed messages queued to the partition workers"
+    )
+    .expect("failed to register consumer queue depth gauge")
+});
+
+/// Whether consumption is paused because the sinks fell behind (1) or not (0)
+pub static CONSUMER_PAUSED: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "solana_proofs_consumer_paused",
+        "Whether the consumer paused its partitions because the sinks fell behind"
+    )
+    .expect("failed to register consumer paused gauge")
+});
+
+/// How long each backpressure pause lasted
+pub static CONSUMER_PAUSE_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "solana_proofs_consumer_pause_seconds",
+        "Duration of consumer pauses caused by slow sinks",
+        LAG_BUCKETS.to_vec()
+    )
+    .expect("failed to register consumer pause histogram")
+});
+
+/// Number of messages received by the consumer, by source topic
+pub static PROOFS_CONSUMED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_consumed_total",
+        "Number of messages received by the consumer",
+        &["topic"]
+    )
+    .expect("failed to register proofs consumed counter")
+});
+
+/// Number of decoded proofs received by the consumer, by source topic and proof kind
+pub static PROOFS_CONSUMED_BY_KIND: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_consumed_by_kind_total",
+        "Number of decoded proofs received by the consumer, by kind (execution or consensus)",
+        &["topic", "kind"]
+    )
+    .expect("failed to register proofs consumed by kind counter")
+});
+
+/// Number of consumed messages that could not be decoded into a proof, by source topic
+pub static PROOFS_DECODE_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_decode_failures_total",
+        "Number of consumed messages that could not be decoded into a proof",
+        &["topic"]
+    )
+    .expect("failed to register proofs decode failures counter")
+});
+
+/// Number of consumed proofs that failed verification, by source topic
+pub static PROOFS_VERIFICATION_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_verification_failures_total",
+        "Number of consumed proofs that failed verification",
+        &["topic"]
+    )
+    .expect("failed to register proofs verification failures counter")
+});
+
+/// Number of consumed proofs whose commitments report `validations_passed = false`, by source topic
+pub static PROOFS_VALIDATION_FAILED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_validation_failed_total",
+        "Number of consumed proofs whose public commitments report failed validations",
+        &["topic"]
+    )
+    .expect("failed to register proofs validation failed counter")
+});
+
+/// Highest end slot among consumed proofs, by source topic
+pub static PROOFS_LAST_END_SLOT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "solana_proofs_last_end_slot",
+        "Highest end slot among consumed proofs",
+        &["topic"]
+    )
+    .expect("failed to register proofs last end slot gauge")
+});
+
+/// Messages between the consumer's position and the high watermark, per partition
+pub static PROOFS_CONSUMER_LAG: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "solana_proofs_consumer_lag",
+        "Messages between the last consumed offset and the high watermark",
+        &["topic", "partition"]
+    )
+    .expect("failed to register proofs consumer lag gauge")
+});
+
+/// Repeated proofs seen within the dedup window, by source topic and verdict (duplicate or conflict)
+pub static PROOFS_DUPLICATES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_duplicates_total",
+        "Number of proofs repeating a recent identifier or commitments",
+        &["topic", "verdict"]
+    )
+    .expect("failed to register proofs duplicates counter")
+});
+
+/// Breaks in proof-chain continuity by kind (gap, mismatch)
+pub static PROOFS_CONTINUITY_BREAKS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_continuity_breaks_total",
+        "Number of proofs that do not continue the previous proof of their chain",
+        &["topic", "kind"]
+    )
+    .expect("failed to register proofs continuity breaks counter")
+});
+
+/// Webhook deliveries by result (delivered, failed, skipped while the circuit is open)
+pub static WEBHOOK_DELIVERIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "solana_proofs_webhook_deliveries_total",
+        "Number of proof summaries forwarded to webhooks, by result",
+        &["result"]
+    )
+    .expect("failed to register webhook deliveries counter")
+});
+
+/// Render all registered metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        eprintln!("Warning: failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Response to a plain HTTP request
+pub struct HttpResponse {
+    /// Status line, e.g. `200 OK`
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+/// Handler for paths served next to `/metrics`, returning `None` for unknown paths
+pub type Routes = Arc<dyn Fn(&str) -> Option<HttpResponse> + Send + Sync>;
+
+/// Serve `/metrics` over plain HTTP until the listener fails
+pub async fn serve(listener: TcpListener) {
+    serve_routes(listener, true, None).await
+}
+
+/// Serve `/metrics` if `metrics` is set, and the paths `routes` handles, until the listener fails
+pub async fn serve_routes(listener: TcpListener, metrics: bool, routes: Option<Routes>) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("Warning: HTTP listener failed: {}", e);
+                return;
+            }
+        };
+
+        let routes = routes.clone();
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let Ok(read) = stream.read(&mut request).await else { return };
+            let request = String::from_utf8_lossy(&request[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+            let response = if metrics && path == "/metrics" {
+                Some(HttpResponse { status: "200 OK", content_type: "text/plain; version=0.0.4", body: render() })
+            } else {
+                routes.and_then(|routes| routes(path))
+            };
+            let response = response.unwrap_or_else(|| HttpResponse {
+                status: "404 Not Found",
+                content_type: "text/plain",
+                body: "Not found\n".to_string(),
+            });
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.status,
+                response.content_type,
+                response.body.len(),
+                response.body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
@@ -0,0 +1,481 @@
+//! Local Solana JSON-RPC server for tests
+//!
+//! [`MockSolanaRpc`] serves the methods the prover uses (`getAccountInfo`,
+//! `getSignaturesForAddress`, `getSlot`, `getBlock`, `getBlockTime`,
+//! `getEpochInfo`, `getEpochSchedule`, `getVoteAccounts`)
+//! from a programmable chain state, so the fetch and input assembly can run
+//! without devnet. On top of the state, responses can be scripted per method,
+//! slowed down or replaced by RPC and HTTP errors. Point a
+//! [`SolanaRpcClient`](crate::solana::SolanaRpcClient) at [`MockSolanaRpc::url`].
+//! Requires the `test-util` feature.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use solana_stub_prover_lib::epoch::EpochSchedule;
+use solana_stub_prover_lib::{hash_with_domain, stub_bank_hash};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Block time of slot 0 of the mock chain
+const GENESIS_TIME: i64 = 1_700_000_000;
+
+/// Domain of the made-up transaction signatures of the mock chain
+const SIGNATURE_DOMAIN: &str = "stub:mock-signature";
+
+/// JSON-RPC error codes returned by Solana nodes
+pub const BLOCK_NOT_AVAILABLE: i64 = -32004;
+pub const SLOT_SKIPPED: i64 = -32007;
+pub const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Reply to one request
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockResponse {
+    /// `result` of a successful call
+    Result(Value),
+    /// JSON-RPC `error` object
+    Error { code: i64, message: String },
+    /// HTTP error status without a JSON-RPC body
+    HttpStatus(u16),
+}
+
+impl MockResponse {
+    pub fn error(code: i64, message: &str) -> Self {
+        MockResponse::Error { code, message: message.to_string() }
+    }
+}
+
+/// An account served by `getAccountInfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockAccount {
+    pub data: Vec<u8>,
+    pub lamports: u64,
+    /// Base58 owner program
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+/// A request received by the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Default)]
+struct ChainState {
+    slot: u64,
+    accounts: HashMap<String, MockAccount>,
+    /// Slots of the transactions touching each account
+    signatures: HashMap<String, Vec<u64>>,
+    skipped: HashSet<u64>,
+    epoch_schedule: EpochSchedule,
+    scripted: HashMap<String, VecDeque<MockResponse>>,
+    overrides: HashMap<String, MockResponse>,
+    latency: HashMap<String, Duration>,
+    requests: Vec<RecordedRequest>,
+}
+
+impl ChainState {
+    /// Reply derived from the chain state
+    fn respond(&self, method: &str, params: &Value) -> MockResponse {
+        match method {
+            "getSlot" => MockResponse::Result(json!(self.slot)),
+            "getAccountInfo" => {
+                if let Some(min_slot) = params[1]["minContextSlot"].as_u64() {
+                    if min_slot > self.slot {
+                        return MockResponse::error(
+                            MIN_CONTEXT_SLOT_NOT_REACHED,
+                            "Minimum context slot has not been reached",
+                        );
+                    }
+                }
+                let value = params[0].as_str().and_then(|pubkey| self.accounts.get(pubkey)).map(|account| {
+                    json!({
+                        "data": [general_purpose::STANDARD.encode(&account.data), "base64"],
+                        "executable": account.executable,
+                        "lamports": account.lamports,
+                        "owner": account.owner,
+                        "rentEpoch": account.rent_epoch,
+                        "space": account.data.len(),
+                    })
+                });
+                MockResponse::Result(json!({ "context": { "slot": self.slot }, "value": value }))
+            }
+            "getSignaturesForAddress" => {
+                let limit = params[1]["limit"].as_u64().unwrap_or(1000) as usize;
+                let mut slots: Vec<u64> = params[0]
+                    .as_str()
+                    .and_then(|pubkey| self.signatures.get(pubkey))
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|&slot| slot <= self.slot)
+                    .collect();
+                slots.sort_unstable_by(|a, b| b.cmp(a));
+                let signatures: Vec<Value> = slots
+                    .into_iter()
+                    .take(limit)
+                    .map(|slot| {
+                        json!({
+                            "signature": bs58::encode(signature(slot)).into_string(),
+                            "slot": slot,
+                            "err": null,
+                            "memo": null,
+                            "blockTime": block_time(slot),
+                            "confirmationStatus": "confirmed",
+                        })
+                    })
+                    .collect();
+                MockResponse::Result(json!(signatures))
+            }
+            "getBlockTime" => match self.block(params) {
+                Ok(slot) => MockResponse::Result(json!(block_time(slot))),
+                Err(e) => e,
+            },
+            "getBlock" => match self.block(params) {
+                Ok(slot) => {
+                    let parent_slot = (0..slot).rev().find(|parent| !self.skipped.contains(parent)).unwrap_or(0);
+                    MockResponse::Result(json!({
+                        "blockHeight": self.block_height(slot),
+                        "blockTime": block_time(slot),
+                        "blockhash": blockhash(slot),
+                        "parentSlot": parent_slot,
+                        "previousBlockhash": blockhash(parent_slot),
+                        "transactions": [],
+                    }))
+                }
+                Err(e) => e,
+            },
+            "getEpochInfo" => {
+                let (epoch, slot_index) = self.epoch_schedule.get_epoch_and_slot_index(self.slot);
+                MockResponse::Result(json!({
+                    "absoluteSlot": self.slot,
+                    "blockHeight": self.block_height(self.slot),
+                    "epoch": epoch,
+                    "slotIndex": slot_index,
+                    "slotsInEpoch": self.epoch_schedule.get_slots_in_epoch(epoch),
+                    "transactionCount": 0,
+                }))
+            }
+            "getEpochSchedule" => MockResponse::Result(json!({
+                "firstNormalEpoch": self.epoch_schedule.first_normal_epoch,
+                "firstNormalSlot": self.epoch_schedule.first_normal_slot,
+                "leaderScheduleSlotOffset": self.epoch_schedule.slots_per_epoch,
+                "slotsPerEpoch": self.epoch_schedule.slots_per_epoch,
+                "warmup": self.epoch_schedule.warmup,
+            })),
+            "getVoteAccounts" => MockResponse::Result(json!({ "current": [], "delinquent": [] })),
+            _ => MockResponse::error(METHOD_NOT_FOUND, "Method not found"),
+        }
+    }
+
+    /// Blocks produced up to and including `slot`
+    fn block_height(&self, slot: u64) -> u64 {
+        slot - self.skipped.iter().filter(|&&skipped| skipped <= slot).count() as u64
+    }
+
+    /// Slot of a block request, or the error a node returns for it
+    fn block(&self, params: &Value) -> Result<u64, MockResponse> {
+        let slot = params[0].as_u64().ok_or_else(|| MockResponse::error(-32602, "Invalid params: expected a slot"))?;
+        if slot > self.slot {
+            return Err(MockResponse::error(BLOCK_NOT_AVAILABLE, &format!("Block not available for slot {}", slot)));
+        }
+        if self.skipped.contains(&slot) {
+            return Err(MockResponse::error(
+                SLOT_SKIPPED,
+                &format!("Slot {} was skipped, or missing due to ledger jump to recent snapshot", slot),
+            ));
+        }
+        Ok(slot)
+    }
+}
+
+fn block_time(slot: u64) -> i64 {
+    // 400 ms slots
+    GENESIS_TIME + (slot * 2 / 5) as i64
+}
+
+fn blockhash(slot: u64) -> String {
+    bs58::encode(stub_bank_hash(slot)).into_string()
+}
+
+/// Signature of the one transaction in `slot`, 32 bytes as the mock does not sign anything
+fn signature(slot: u64) -> [u8; 32] {
+    hash_with_domain(SIGNATURE_DOMAIN, &[&slot.to_le_bytes()])
+}
+
+type SharedState = Arc<Mutex<ChainState>>;
+
+fn lock(state: &SharedState) -> MutexGuard<'_, ChainState> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn handle(State(state): State<SharedState>, Json(request): Json<Value>) -> Response {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let (response, delay) = {
+        let mut state = lock(&state);
+        state.requests.push(RecordedRequest { method: method.clone(), params: params.clone() });
+        let delay = state.latency.get(&method).copied().unwrap_or_default();
+        let response = match state.scripted.get_mut(&method).and_then(VecDeque::pop_front) {
+            Some(response) => response,
+            None => match state.overrides.get(&method) {
+                Some(response) => response.clone(),
+                None => state.respond(&method, &params),
+            },
+        };
+        (response, delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    match response {
+        MockResponse::Result(result) => Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })).into_response(),
+        MockResponse::Error { code, message } => Json(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }))
+        .into_response(),
+        MockResponse::HttpStatus(status) => {
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR).into_response()
+        }
+    }
+}
+
+/// Solana JSON-RPC server on a local port, stopped when dropped
+pub struct MockSolanaRpc {
+    url: String,
+    state: SharedState,
+    server: JoinHandle<()>,
+}
+
+impl MockSolanaRpc {
+    /// Start a server at `slot` with no accounts
+    pub async fn start(slot: u64) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state: SharedState = Arc::new(Mutex::new(ChainState { slot, ..ChainState::default() }));
+        let app = Router::new().route("/", post(handle)).with_state(state.clone());
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                eprintln!("Mock Solana RPC server failed: {}", e);
+            }
+        });
+        Ok(Self { url, state, server })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Move the chain to `slot`
+    pub fn set_slot(&self, slot: u64) {
+        lock(&self.state).slot = slot;
+    }
+
+    pub fn set_account(&self, pubkey: &str, account: MockAccount) {
+        lock(&self.state).accounts.insert(pubkey.to_string(), account);
+    }
+
+    pub fn remove_account(&self, pubkey: &str) {
+        lock(&self.state).accounts.remove(pubkey);
+    }
+
+    /// Record a transaction touching `pubkey` at `slot`
+    pub fn add_signature(&self, pubkey: &str, slot: u64) {
+        lock(&self.state).signatures.entry(pubkey.to_string()).or_default().push(slot);
+    }
+
+    /// Serve `schedule` instead of mainnet's, e.g. one with warmup epochs
+    pub fn set_epoch_schedule(&self, schedule: EpochSchedule) {
+        lock(&self.state).epoch_schedule = schedule;
+    }
+
+    /// Mark a slot as skipped: no block or block time is served for it
+    pub fn skip_slot(&self, slot: u64) {
+        lock(&self.state).skipped.insert(slot);
+    }
+
+    /// Reply to the next call of `method` with `response`
+    ///
+    /// Scripted replies are used once each, in the order they were added,
+    /// before falling back to [`respond_with`](Self::respond_with) or the chain state.
+    pub fn script(&self, method: &str, response: MockResponse) {
+        lock(&self.state).scripted.entry(method.to_string()).or_default().push_back(response);
+    }
+
+    /// Reply to every call of `method` with `response` once its scripted replies are used up
+    pub fn respond_with(&self, method: &str, response: MockResponse) {
+        lock(&self.state).overrides.insert(method.to_string(), response);
+    }
+
+    /// Serve `method` from the chain state again
+    pub fn clear_response(&self, method: &str) {
+        let mut state = lock(&self.state);
+        state.overrides.remove(method);
+        state.scripted.remove(method);
+    }
+
+    /// Delay every reply to `method`
+    pub fn set_latency(&self, method: &str, latency: Duration) {
+        lock(&self.state).latency.insert(method.to_string(), latency);
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        lock(&self.state).requests.clone()
+    }
+
+    /// Number of calls of `method` received so far
+    pub fn request_count(&self, method: &str) -> usize {
+        lock(&self.state).requests.iter().filter(|request| request.method == method).count()
+    }
+}
+
+impl Drop for MockSolanaRpc {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::SolanaRpcClient;
+    use crate::types::AccountData;
+    use std::time::Instant;
+
+    const ACCOUNT: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+    const OWNER: &str = "11111111111111111111111111111111";
+
+    async fn server(slot: u64) -> (MockSolanaRpc, SolanaRpcClient) {
+        let server = MockSolanaRpc::start(slot).await.unwrap();
+        let client = SolanaRpcClient::new(server.url());
+        (server, client)
+    }
+
+    fn account(data: &[u8]) -> MockAccount {
+        MockAccount {
+            data: data.to_vec(),
+            lamports: 1_141_440,
+            owner: OWNER.to_string(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn scripted_replies_come_first_then_the_override_then_the_chain() {
+        let (server, client) = server(100).await;
+        server.script("getSlot", MockResponse::Result(json!(1)));
+        server.script("getSlot", MockResponse::Result(json!(2)));
+        server.respond_with("getSlot", MockResponse::Result(json!(50)));
+        let mut slots = Vec::new();
+        for _ in 0..4 {
+            slots.push(client.get_current_slot().await.unwrap());
+        }
+        assert_eq!(slots, [1, 2, 50, 50]);
+
+        server.clear_response("getSlot");
+        assert_eq!(client.get_current_slot().await.unwrap(), 100);
+        assert_eq!(server.request_count("getSlot"), 5);
+    }
+
+    #[tokio::test]
+    async fn injected_errors_fail_the_call() {
+        let (server, client) = server(100).await;
+        server.script("getSlot", MockResponse::error(-32005, "Node is behind"));
+        server.script("getSlot", MockResponse::HttpStatus(503));
+        assert!(client.get_current_slot().await.is_err());
+        assert!(client.get_current_slot().await.is_err());
+        assert_eq!(client.get_current_slot().await.unwrap(), 100);
+
+        assert!(client.get_signature_slots(ACCOUNT, 10).await.unwrap().is_empty());
+        server.script("getSignaturesForAddress", MockResponse::error(METHOD_NOT_FOUND, "Method not found"));
+        let error = client.get_signature_slots(ACCOUNT, 10).await.unwrap_err();
+        assert!(error.to_string().contains("Method not found"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn latency_delays_only_its_method() {
+        let (server, client) = server(100).await;
+        server.set_latency("getSlot", Duration::from_millis(200));
+        let started = Instant::now();
+        client.get_current_slot().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        let started = Instant::now();
+        client.get_block_time(10).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn accounts_are_read_at_the_current_slot() {
+        let (server, client) = server(100).await;
+        server.set_account(ACCOUNT, account(b"stub"));
+        // The node answers from its own slot, after the requested one
+        let (info, slot) = client.get_account_info(ACCOUNT, Some(90)).await.unwrap();
+        let info = info.unwrap();
+        assert_eq!(slot, 100);
+        assert_eq!((info.lamports, info.owner.as_str(), info.space), (1_141_440, OWNER, 4));
+        assert_eq!(info.data, AccountData::Encoded("c3R1Yg==".to_string(), "base64".to_string()));
+        let request = server.requests().pop().unwrap();
+        assert_eq!((request.method.as_str(), &request.params[1]["minContextSlot"]), ("getAccountInfo", &json!(90)));
+
+        server.remove_account(ACCOUNT);
+        assert!(client.get_account_info(ACCOUNT, None).await.unwrap().0.is_none());
+    }
+
+    #[tokio::test]
+    async fn min_context_slot_must_be_reached() {
+        let (server, client) = server(100).await;
+        server.set_account(ACCOUNT, account(b""));
+        assert!(client.get_account_info(ACCOUNT, Some(101)).await.is_err());
+        server.set_slot(101);
+        assert_eq!(client.get_account_info(ACCOUNT, Some(101)).await.unwrap().1, 101);
+    }
+
+    #[tokio::test]
+    async fn skipped_and_future_slots_have_no_block() {
+        let (server, client) = server(100).await;
+        server.skip_slot(50);
+        assert_eq!(client.get_block_time(49).await.unwrap(), Some(block_time(49)));
+        assert_eq!(client.get_block_time(50).await.unwrap(), None);
+        assert_eq!(client.get_block_time(101).await.unwrap(), None);
+        let state = lock(&server.state);
+        assert_eq!(state.block_height(100), 99);
+        let MockResponse::Result(block) = state.respond("getBlock", &json!([51])) else { panic!("no block at 51") };
+        assert_eq!(block["parentSlot"], 49);
+    }
+
+    #[tokio::test]
+    async fn signatures_are_newest_first_up_to_the_current_slot() {
+        let (server, client) = server(100).await;
+        for slot in [10, 90, 40, 120] {
+            server.add_signature(ACCOUNT, slot);
+        }
+        assert_eq!(client.get_signature_slots(ACCOUNT, 1000).await.unwrap(), [90, 40, 10]);
+        assert_eq!(client.get_signature_slots(ACCOUNT, 2).await.unwrap(), [90, 40]);
+        server.set_slot(120);
+        assert_eq!(client.get_signature_slots(ACCOUNT, 1).await.unwrap(), [120]);
+    }
+
+    #[tokio::test]
+    async fn serves_the_epoch_schedule() {
+        let (server, client) = server(100).await;
+        assert_eq!(client.get_epoch_schedule().await.unwrap(), EpochSchedule::mainnet());
+        let warmup = EpochSchedule::new(8192, true);
+        server.set_epoch_schedule(warmup);
+        assert_eq!(client.get_epoch_schedule().await.unwrap(), warmup);
+    }
+}
@@ -0,0 +1,124 @@
+//! AWS MSK IAM authentication over SASL/OAUTHBEARER
+//!
+//! All Kafka clients are created with `KafkaClientContext`. When MSK IAM is
+//! configured, librdkafka asks the context for a token on startup and again
+//! at 80% of the previous token's lifetime, so tokens are refreshed well
+//! before they expire. Signing uses the AWS SDK default credentials chain,
+//! optionally assuming a role, and requires the `msk-iam` feature.
+//!
+//! Consumers using manual commits also register themselves with the context
+//! so that processed offsets are committed before partitions are revoked.
+
+use rdkafka::client::{ClientContext, OAuthToken};
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::ClientConfig;
+use std::error::Error;
+use std::sync::{Arc, OnceLock, Weak};
+
+/// MSK IAM signing settings
+#[derive(Debug, Clone)]
+pub struct MskIamConfig {
+    /// AWS region of the MSK cluster
+    pub region: String,
+    /// Role to assume before signing, instead of using the caller's credentials directly
+    pub role_arn: Option<String>,
+}
+
+/// Whether this build can sign MSK IAM tokens
+pub const MSK_IAM_SUPPORTED: bool = cfg!(feature = "msk-iam");
+
+/// Configure a client for MSK IAM: SASL over TLS with OAUTHBEARER tokens
+pub fn apply_msk_iam(config: &mut ClientConfig) {
+    config.set("security.protocol", "sasl_ssl");
+    config.set("sasl.mechanism", "OAUTHBEARER");
+}
+
+/// Client context shared by the producer, consumer and admin clients
+#[derive(Debug, Clone, Default)]
+pub struct KafkaClientContext {
+    msk_iam: Option<MskIamConfig>,
+    /// Consumer whose stored offsets are committed before its partitions are revoked
+    commit_on_revoke: OnceLock<Weak<StreamConsumer<KafkaClientContext>>>,
+}
+
+impl KafkaClientContext {
+    pub fn new(msk_iam: Option<MskIamConfig>) -> Self {
+        Self { msk_iam, commit_on_revoke: OnceLock::new() }
+    }
+
+    /// Commit the consumer's stored offsets synchronously whenever partitions are revoked
+    pub fn commit_before_revoke(&self, consumer: &Arc<StreamConsumer<KafkaClientContext>>) {
+        let _ = self.commit_on_revoke.set(Arc::downgrade(consumer));
+    }
+}
+
+impl ClientContext for KafkaClientContext {
+    // Refresh events only fire when `sasl.mechanism` is OAUTHBEARER
+    const ENABLE_REFRESH_OAUTH_TOKEN: bool = true;
+
+    fn generate_oauth_token(&self, _oauthbearer_config: Option<&str>) -> Result<OAuthToken, Box<dyn Error>> {
+        let config = self
+            .msk_iam
+            .as_ref()
+            .ok_or("OAUTHBEARER token requested but MSK IAM authentication is not configured")?;
+        generate_token(config)
+    }
+}
+
+impl ConsumerContext for KafkaClientContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance<'_>) {
+        let Rebalance::Revoke(partitions) = rebalance else { return };
+        let Some(consumer) = self.commit_on_revoke.get().and_then(Weak::upgrade) else { return };
+
+        match consumer.commit_consumer_state(CommitMode::Sync) {
+            Ok(()) => tracing::info!(
+                partitions = partitions.count(),
+                "Committed processed offsets before partitions were revoked"
+            ),
+            // Nothing was processed since the last commit
+            Err(KafkaError::ConsumerCommit(RDKafkaErrorCode::NoOffset)) => {}
+            Err(e) => tracing::warn!(error = %e, "Failed to commit offsets before partitions were revoked"),
+        }
+    }
+}
+
+#[cfg(feature = "msk-iam")]
+fn generate_token(config: &MskIamConfig) -> Result<OAuthToken, Box<dyn Error>> {
+    use aws_msk_iam_sasl_signer::{generate_auth_token, generate_auth_token_from_role_arn};
+    use aws_types::region::Region;
+
+    let config = config.clone();
+    // librdkafka may call this from a tokio worker (the stream consumer polls
+    // inside its task), so sign on a separate thread with its own runtime
+    let signed = std::thread::spawn(move || -> Result<(String, i64), String> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let region = Region::new(config.region);
+        runtime
+            .block_on(async {
+                match config.role_arn {
+                    Some(role_arn) => generate_auth_token_from_role_arn(region, role_arn).await,
+                    None => generate_auth_token(region).await,
+                }
+            })
+            .map_err(|e| format!("Failed to sign MSK IAM token: {}", e))
+    })
+    .join()
+    .map_err(|_| "MSK IAM token signer panicked")?;
+
+    let (token, expiration_ms) = signed?;
+    Ok(OAuthToken {
+        token,
+        // MSK derives the principal from the signed token
+        principal_name: String::new(),
+        lifetime_ms: expiration_ms,
+    })
+}
+
+#[cfg(not(feature = "msk-iam"))]
+fn generate_token(_config: &MskIamConfig) -> Result<OAuthToken, Box<dyn Error>> {
+    Err("MSK IAM authentication requires building with the msk-iam feature".into())
+}
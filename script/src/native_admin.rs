@@ -0,0 +1,102 @@
+//! Admin requests that rdkafka's safe API does not wrap
+//!
+//! The ACL and DeleteRecords requests call librdkafka directly on the admin
+//! client's handle. Each request gets a private result queue and blocks until
+//! its result arrives or the request times out.
+
+use rdkafka::admin::AdminClient;
+use rdkafka::client::ClientContext;
+use rdkafka::error::RDKafkaErrorCode;
+use rdkafka_sys::*;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::time::Duration;
+
+/// Message for a failed request; a cluster without an authorizer answers every ACL request with SECURITY_DISABLED
+fn error_message(code: rd_kafka_resp_err_t, detail: &str) -> String {
+    if code == rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_SECURITY_DISABLED {
+        return "the cluster has no authorizer configured, so ACLs are disabled (set authorizer.class.name on the brokers)"
+            .to_string();
+    }
+    let error = RDKafkaErrorCode::from(code);
+    if detail.is_empty() {
+        error.to_string()
+    } else {
+        format!("{}: {}", error, detail)
+    }
+}
+
+/// Fail with the error, if there is one
+///
+/// # Safety
+/// `error` must be null or point to an error owned by a live result event.
+pub(crate) unsafe fn check_error(error: *const rd_kafka_error_t) -> Result<(), String> {
+    if error.is_null() {
+        return Ok(());
+    }
+    Err(error_message(rd_kafka_error_code(error), &from_c(rd_kafka_error_string(error))))
+}
+
+/// Copy a string owned by librdkafka; null is empty
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+pub(crate) unsafe fn from_c(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+/// A result event, destroyed on drop
+pub(crate) struct Event(pub(crate) *mut rd_kafka_event_t);
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_event_destroy(self.0) }
+    }
+}
+
+/// Queue an admin request with `start` and wait for its result event
+///
+/// `start` receives the client handle, the request options and the queue the
+/// result is delivered to. Request-level errors are returned as `Err`.
+pub(crate) fn run_request<C, F>(
+    admin: &AdminClient<C>,
+    op: rd_kafka_admin_op_t,
+    timeout: Duration,
+    start: F,
+) -> Result<Event, String>
+where
+    C: ClientContext,
+    F: FnOnce(*mut rd_kafka_t, *const rd_kafka_AdminOptions_t, *mut rd_kafka_queue_t),
+{
+    let rk = admin.inner().native_ptr();
+    let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+    unsafe {
+        let options = rd_kafka_AdminOptions_new(rk, op);
+        let mut errstr = [0 as c_char; 512];
+        let set = rd_kafka_AdminOptions_set_request_timeout(options, timeout_ms, errstr.as_mut_ptr(), errstr.len());
+        if set != rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR {
+            rd_kafka_AdminOptions_destroy(options);
+            return Err(from_c(errstr.as_ptr()));
+        }
+        let queue = rd_kafka_queue_new(rk);
+        start(rk, options, queue);
+        rd_kafka_AdminOptions_destroy(options);
+
+        // librdkafka fails the request itself on timeout; the extra second lets that error arrive
+        let event = rd_kafka_queue_poll(queue, timeout_ms.saturating_add(1000));
+        rd_kafka_queue_destroy(queue);
+        if event.is_null() {
+            return Err(format!("request timed out after {:?}", timeout));
+        }
+        let event = Event(event);
+        let code = rd_kafka_event_error(event.0);
+        if code != rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR {
+            return Err(error_message(code, &from_c(rd_kafka_event_error_string(event.0))));
+        }
+        Ok(event)
+    }
+}
@@ -0,0 +1,152 @@
+//! Moving a consumer group's committed offsets
+//!
+//! Targets are resolved per partition from a strategy (earliest, latest, an
+//! offset or a point in time), with explicit offsets overriding it on
+//! individual partitions. Offsets are clamped to the retained log, so a group
+//! is never pointed below the log start or past the high watermark.
+
+use crate::lag::group_positions;
+use crate::replay::PartitionOffset;
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Where to move the group's offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStrategy {
+    Earliest,
+    Latest,
+    Offset(i64),
+    /// First record at or after this time (Unix milliseconds); the end if there is none
+    Timestamp(i64),
+}
+
+impl fmt::Display for ResetStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResetStrategy::Earliest => write!(f, "earliest"),
+            ResetStrategy::Latest => write!(f, "latest"),
+            ResetStrategy::Offset(offset) => write!(f, "offset {}", offset),
+            ResetStrategy::Timestamp(millis) => write!(f, "timestamp {}", millis),
+        }
+    }
+}
+
+/// New committed offset of one partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OffsetTarget {
+    pub topic: String,
+    pub partition: i32,
+    /// Committed offset before the reset, if any
+    pub current: Option<i64>,
+    pub target: i64,
+    /// Log start offset
+    pub low: i64,
+    /// High watermark
+    pub high: i64,
+    /// Whether the requested offset lay outside the log and was clamped
+    pub clamped: bool,
+}
+
+impl OffsetTarget {
+    /// Messages the reset skips (positive) or re-reads (negative), if there was a committed offset
+    pub fn delta(&self) -> Option<i64> {
+        self.current.map(|current| self.target - current)
+    }
+}
+
+/// Resolve the target offset of every partition of `topic`
+///
+/// Partitions without an override keep their offset when no strategy is
+/// given. Overrides naming a partition the topic does not have are an error.
+pub fn resolve_targets<C, T>(
+    consumer: &T,
+    topic: &str,
+    strategy: Option<ResetStrategy>,
+    overrides: &[PartitionOffset],
+    timeout: Duration,
+) -> Result<Vec<OffsetTarget>, String>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let committed = group_positions(consumer, &[topic.to_string()], timeout)
+        .map_err(|e| format!("Failed to fetch the committed offsets of {}: {}", topic, e))?;
+    if committed.count() == 0 {
+        return Err(format!("Topic {} has no partitions", topic));
+    }
+    let partitions: Vec<i32> = committed.elements().iter().map(|element| element.partition()).collect();
+    if let Some(unknown) = overrides.iter().find(|o| !partitions.contains(&o.partition)) {
+        return Err(format!("Topic {} has no partition {}", topic, unknown.partition));
+    }
+
+    let by_time: BTreeMap<i32, i64> = match strategy {
+        Some(ResetStrategy::Timestamp(millis)) => {
+            // offsets_for_times takes the timestamp in the offset field
+            let mut query = TopicPartitionList::new();
+            for &partition in &partitions {
+                query
+                    .add_partition_offset(topic, partition, Offset::Offset(millis))
+                    .map_err(|e| e.to_string())?;
+            }
+            let found = consumer
+                .offsets_for_times(query, timeout)
+                .map_err(|e| format!("Failed to look up offsets by time: {}", e))?;
+            found
+                .elements()
+                .iter()
+                .filter_map(|element| match element.offset() {
+                    Offset::Offset(offset) => Some((element.partition(), offset)),
+                    _ => None,
+                })
+                .collect()
+        }
+        _ => BTreeMap::new(),
+    };
+
+    let mut targets = Vec::new();
+    for element in committed.elements() {
+        let partition = element.partition();
+        let (low, high) = consumer
+            .fetch_watermarks(topic, partition, timeout)
+            .map_err(|e| format!("Failed to fetch watermarks of {} partition {}: {}", topic, partition, e))?;
+        let current = match element.offset() {
+            Offset::Offset(offset) => Some(offset),
+            _ => None,
+        };
+        let requested = match overrides.iter().rev().find(|o| o.partition == partition) {
+            Some(o) => o.offset,
+            None => match strategy {
+                Some(ResetStrategy::Earliest) => low,
+                Some(ResetStrategy::Latest) => high,
+                Some(ResetStrategy::Offset(offset)) => offset,
+                Some(ResetStrategy::Timestamp(_)) => by_time.get(&partition).copied().unwrap_or(high),
+                None => continue,
+            },
+        };
+        let target = requested.clamp(low, high);
+        targets.push(OffsetTarget {
+            topic: topic.to_string(),
+            partition,
+            current,
+            target,
+            low,
+            high,
+            clamped: target != requested,
+        });
+    }
+    Ok(targets)
+}
+
+/// Offsets to commit for the targets
+pub fn commit_list(targets: &[OffsetTarget]) -> Result<TopicPartitionList, String> {
+    let mut list = TopicPartitionList::new();
+    for target in targets {
+        list.add_partition_offset(&target.topic, target.partition, Offset::Offset(target.target))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(list)
+}
@@ -0,0 +1,215 @@
+//! Per-partition processing tasks
+//!
+//! Each partition gets its own task that handles its messages one at a time,
+//! so work on different partitions (e.g. proof verification) runs concurrently
+//! while every partition keeps its order. Queues are bounded: dispatching to a
+//! full queue waits, which stops the consumer from reading further ahead.
+//!
+//! Because partitions finish out of step with the order messages were read,
+//! offsets may only be stored up to the contiguous processed watermark that
+//! [`ProcessedWatermarks`] tracks.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Default number of messages queued per partition
+pub const DEFAULT_PARTITION_QUEUE: usize = 32;
+
+type Handler<J> = Arc<dyn Fn(J) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// One processing task per (topic, partition), created on first use
+pub struct PartitionWorkers<J> {
+    queue: usize,
+    handler: Handler<J>,
+    senders: HashMap<(String, i32), mpsc::Sender<J>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<J: Send + 'static> PartitionWorkers<J> {
+    /// Workers running `handler` on each job, with `queue` jobs buffered per partition
+    pub fn new<F, Fut>(queue: usize, handler: F) -> Self
+    where
+        F: Fn(J) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            queue: queue.max(1),
+            handler: Arc::new(move |job| Box::pin(handler(job)) as Pin<Box<dyn Future<Output = ()> + Send>>),
+            senders: HashMap::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Queue a job for its partition, waiting while the partition's queue is full
+    pub async fn dispatch(&mut self, topic: &str, partition: i32, job: J) {
+        let key = (topic.to_string(), partition);
+        if !self.senders.contains_key(&key) {
+            let (sender, mut receiver) = mpsc::channel::<J>(self.queue);
+            let handler = Arc::clone(&self.handler);
+            self.tasks.push(tokio::spawn(async move {
+                while let Some(job) = receiver.recv().await {
+                    handler(job).await;
+                }
+            }));
+            self.senders.insert(key.clone(), sender);
+        }
+
+        if let Some(sender) = self.senders.get(&key) {
+            // Sending fails only if the partition's task panicked, which tokio already reports
+            let _ = sender.send(job).await;
+        }
+    }
+
+    /// Number of partitions with a task
+    pub fn partitions(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Stop accepting jobs and wait for every queued job to be processed
+    pub async fn finish(mut self) {
+        self.senders.clear();
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Highest offset per partition below which every read message has been processed
+#[derive(Debug, Default)]
+pub struct ProcessedWatermarks {
+    /// Offsets read but not yet part of the watermark, and whether each is processed
+    pending: HashMap<(String, i32), BTreeMap<i64, bool>>,
+}
+
+impl ProcessedWatermarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a message as read, in the order messages are read
+    pub fn read(&mut self, topic: &str, partition: i32, offset: i64) {
+        self.pending.entry((topic.to_string(), partition)).or_default().insert(offset, false);
+    }
+
+    /// Mark a message as processed
+    ///
+    /// Returns the offset of the last message of the partition's contiguous
+    /// processed run if it advanced, i.e. the offset that may now be stored.
+    pub fn processed(&mut self, topic: &str, partition: i32, offset: i64) -> Option<i64> {
+        let pending = self.pending.get_mut(&(topic.to_string(), partition))?;
+        if let Some(done) = pending.get_mut(&offset) {
+            *done = true;
+        }
+
+        let mut watermark = None;
+        while let Some(entry) = pending.first_entry() {
+            if !*entry.get() {
+                break;
+            }
+            watermark = Some(entry.remove_entry().0);
+        }
+        watermark
+    }
+
+    /// Number of messages read but not yet covered by a watermark
+    pub fn in_flight(&self) -> usize {
+        self.pending.values().map(BTreeMap::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn watermark_advances_in_order() {
+        let mut watermarks = ProcessedWatermarks::new();
+        for offset in 10..13 {
+            watermarks.read("proofs", 0, offset);
+        }
+        assert_eq!(watermarks.processed("proofs", 0, 10), Some(10));
+        assert_eq!(watermarks.processed("proofs", 0, 11), Some(11));
+        assert_eq!(watermarks.in_flight(), 1);
+    }
+
+    #[test]
+    fn out_of_order_completion_does_not_skip_ahead() {
+        let mut watermarks = ProcessedWatermarks::new();
+        for offset in 10..14 {
+            watermarks.read("proofs", 0, offset);
+        }
+        assert_eq!(watermarks.processed("proofs", 0, 12), None);
+        assert_eq!(watermarks.processed("proofs", 0, 11), None);
+        // 10 closes the gap, so the watermark moves up to the last processed offset after it
+        assert_eq!(watermarks.processed("proofs", 0, 10), Some(12));
+        assert_eq!(watermarks.in_flight(), 1);
+        assert_eq!(watermarks.processed("proofs", 0, 13), Some(13));
+        assert_eq!(watermarks.in_flight(), 0);
+    }
+
+    #[test]
+    fn offset_gaps_between_read_messages_do_not_block() {
+        // Transaction markers and compaction leave holes in the offsets read
+        let mut watermarks = ProcessedWatermarks::new();
+        watermarks.read("proofs", 0, 4);
+        watermarks.read("proofs", 0, 9);
+        assert_eq!(watermarks.processed("proofs", 0, 9), None);
+        assert_eq!(watermarks.processed("proofs", 0, 4), Some(9));
+    }
+
+    #[test]
+    fn partitions_advance_independently() {
+        let mut watermarks = ProcessedWatermarks::new();
+        watermarks.read("proofs", 0, 1);
+        watermarks.read("proofs", 1, 1);
+        watermarks.read("other", 0, 1);
+        watermarks.read("proofs", 0, 2);
+
+        assert_eq!(watermarks.processed("proofs", 0, 2), None);
+        assert_eq!(watermarks.processed("proofs", 1, 1), Some(1));
+        assert_eq!(watermarks.processed("other", 0, 1), Some(1));
+        assert_eq!(watermarks.in_flight(), 2);
+    }
+
+    #[test]
+    fn ignores_offsets_that_were_never_read() {
+        let mut watermarks = ProcessedWatermarks::new();
+        assert_eq!(watermarks.processed("proofs", 0, 5), None);
+        watermarks.read("proofs", 0, 6);
+        assert_eq!(watermarks.processed("proofs", 0, 5), None);
+        assert_eq!(watermarks.in_flight(), 1);
+    }
+
+    #[tokio::test]
+    async fn workers_keep_partition_order_and_finish_queued_jobs() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&handled);
+        let mut workers = PartitionWorkers::new(2, move |job: (i32, i64)| {
+            let seen = Arc::clone(&seen);
+            async move {
+                // Later partitions finish sooner, so completion interleaves across partitions
+                tokio::time::sleep(std::time::Duration::from_millis(5 * (3 - job.0) as u64)).await;
+                seen.lock().unwrap().push(job);
+            }
+        });
+        for offset in 0..4 {
+            for partition in 0..3 {
+                workers.dispatch("proofs", partition, (partition, offset)).await;
+            }
+        }
+        assert_eq!(workers.partitions(), 3);
+        workers.finish().await;
+
+        let handled = handled.lock().unwrap();
+        assert_eq!(handled.len(), 12);
+        for partition in 0..3 {
+            let offsets: Vec<i64> = handled.iter().filter(|job| job.0 == partition).map(|job| job.1).collect();
+            assert_eq!(offsets, [0, 1, 2, 3]);
+        }
+    }
+}
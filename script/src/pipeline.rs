@@ -0,0 +1,949 @@
+//! The proving pipeline as a library
+//!
+//! [`assemble_input`] fetches the monitored accounts and builds the program
+//! input, [`execute`] runs the program without proving, [`prove`] generates and
+//! verifies a proof, and [`publish`] hands it to a [`ProofSink`].
+//! [`prove_aggregate`] proves the union range of existing compressed proofs
+//! with the program's aggregation mode. Unless asked to commit it, the raw
+//! account data is left out of the program input and published next to the
+//! proof (see [`crate::account_data`]). What the program commits is checked
+//! against the input it was given before anything is published (see
+//! [`check_consistency`]). The prover binary is a command line
+//! over these functions; other services can call them directly. Configuration
+//! is plain structs and failures are [`PipelineError`].
+
+use crate::account_data::{self, RawAccountData, RawData};
+use crate::guest_profile::GuestProfile;
+use crate::kafka::KafkaPublishError;
+use crate::proof_sink::{ProofSink, SinkReceipt};
+use crate::prove_jobs::ProofMode;
+use crate::public_values::{diff, FieldChange};
+use crate::solana::{SolanaRpcClient, DEVNET_RPC_URL};
+use crate::types::AccountData;
+use crate::utils::{base58_to_bytes32, sha256_hash, validate_pubkeys, InvalidPubkey};
+use base64::engine::general_purpose;
+use futures::stream::{self, StreamExt};
+use sha2::{Digest, Sha256};
+use solana_stub_prover_lib::epoch::EpochSchedule;
+use solana_stub_prover_lib::{
+    compute_accounts_hash, stub_bank_hash, vkey_digest_bytes, AccountStateCommitment, AggregationInput, ProgramInput,
+    ProverInput, PublicCommitments, MAX_ACCOUNT_DATA_LEN, NOT_AGGREGATED, SYSTEM_PROGRAM_ID,
+};
+use sp1_sdk::{
+    include_elf, EnvProver, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1ProvingKey, SP1Stdin,
+    SP1VerifyingKey,
+};
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use twine_types::proofs::{ProofData, ProofKind, SP1Proof, ZkProof};
+
+/// The ELF file for the Solana stub prover program
+pub const PROVER_ELF: &[u8] = include_elf!("solana-stub-prover-program");
+
+/// Accounts fetched at the same time by default
+///
+/// Each account costs two RPC calls; public endpoints rate-limit per IP, so
+/// keep this low unless the node is dedicated.
+pub const DEFAULT_RPC_CONCURRENCY: usize = 4;
+
+/// Signatures looked at per account when finding its last change
+const SIGNATURE_LIMIT: usize = 1000;
+
+/// Default limit on the data of all monitored accounts together
+pub const DEFAULT_MAX_TOTAL_INPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Limits on the size of the fetched account data
+///
+/// Checked while the accounts are fetched, before the data goes through
+/// base64 decoding, hashing and serialization into the program input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLimits {
+    /// Largest data of one account; the program's [`MAX_ACCOUNT_DATA_LEN`] applies above it
+    pub max_account_bytes: usize,
+    /// Largest data of all accounts together
+    pub max_total_bytes: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self { max_account_bytes: MAX_ACCOUNT_DATA_LEN, max_total_bytes: DEFAULT_MAX_TOTAL_INPUT_BYTES }
+    }
+}
+
+impl InputLimits {
+    /// Limit on one account, never above what the program accepts
+    fn account_limit(&self) -> usize {
+        self.max_account_bytes.min(MAX_ACCOUNT_DATA_LEN)
+    }
+}
+
+/// What to fetch for the program input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputConfig {
+    /// Solana JSON-RPC endpoint
+    pub rpc_url: String,
+    /// Base58 pubkeys of the accounts to monitor
+    pub accounts: Vec<String>,
+    pub start_slot: u64,
+    /// End slot, or `None` for the node's current slot
+    pub end_slot: Option<u64>,
+    /// Accounts fetched at the same time
+    pub concurrency: usize,
+    pub limits: InputLimits,
+    /// Commit accounts that do not exist at the end slot as closed instead of failing
+    pub allow_missing: bool,
+    /// Accept the all-zeros pubkey among `accounts`
+    pub allow_zero_pubkey: bool,
+    /// Epoch schedule of the cluster, or `None` to ask the node
+    pub epoch_schedule: Option<EpochSchedule>,
+    /// Keep each account's decoded data in the input
+    ///
+    /// Without it the data is hashed as it is decoded and only its hash and
+    /// length are kept, so the data can be neither committed nor published
+    /// next to the proof, but a large account never sits in memory.
+    pub keep_data: bool,
+}
+
+impl InputConfig {
+    /// Config fetching one account
+    pub fn new(rpc_url: &str, account: &str, start_slot: u64, end_slot: Option<u64>) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+            accounts: vec![account.to_string()],
+            start_slot,
+            end_slot,
+            concurrency: DEFAULT_RPC_CONCURRENCY,
+            limits: InputLimits::default(),
+            allow_missing: false,
+            allow_zero_pubkey: false,
+            epoch_schedule: None,
+            keep_data: true,
+        }
+    }
+
+    /// Config fetching one account from devnet
+    pub fn devnet(account: &str, start_slot: u64, end_slot: Option<u64>) -> Self {
+        Self::new(DEVNET_RPC_URL, account, start_slot, end_slot)
+    }
+}
+
+/// Why the pipeline failed
+#[derive(Debug)]
+pub enum PipelineError {
+    /// The end slot is not after the start slot
+    InvalidRange { start_slot: u64, end_slot: u64 },
+    /// The account pubkey, its owner or its data could not be decoded
+    InvalidAccount(String),
+    /// Some of the configured account pubkeys are invalid; all of them, in the order given
+    InvalidPubkeys(Vec<InvalidPubkey>),
+    /// A Solana RPC call failed
+    Rpc(String),
+    /// The account does not exist at the end slot and missing accounts are not allowed
+    AccountNotFound(String),
+    /// An account's data is over the per-account limit
+    AccountTooLarge { account: String, bytes: usize, limit: usize },
+    /// The data of the monitored accounts together is over the total limit
+    InputTooLarge { bytes: usize, limit: usize },
+    /// Some monitored accounts could not be fetched; one failure per account, by pubkey
+    Accounts { total: usize, failures: Vec<(String, PipelineError)> },
+    /// The program rejected the input
+    Execution(String),
+    /// Proof generation failed
+    Proving(String),
+    /// The generated proof did not verify
+    Verification(String),
+    /// The committed public values differ from the input; input value -> committed value
+    Inconsistent(Vec<FieldChange>),
+    /// The proof or its public values could not be encoded or decoded
+    Encoding(String),
+    /// The sink refused the proof
+    Publish(KafkaPublishError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::InvalidRange { start_slot, end_slot } => write!(
+                f,
+                "end_slot must be greater than start_slot (start {}, end {})",
+                start_slot, end_slot
+            ),
+            PipelineError::InvalidAccount(reason) => write!(f, "Invalid account: {}", reason),
+            PipelineError::InvalidPubkeys(invalid) => {
+                write!(f, "{} invalid account pubkey(s):", invalid.len())?;
+                for pubkey in invalid {
+                    write!(f, "\n  {}", pubkey)?;
+                }
+                Ok(())
+            }
+            PipelineError::Rpc(reason) => write!(f, "Solana RPC error: {}", reason),
+            PipelineError::AccountNotFound(account) => write!(f, "Account {} does not exist", account),
+            PipelineError::AccountTooLarge { account, bytes, limit } => {
+                write!(f, "Account {} has {} bytes of data, over the {} byte limit", account, bytes, limit)
+            }
+            PipelineError::InputTooLarge { bytes, limit } => {
+                write!(f, "The monitored accounts have {} bytes of data, over the {} byte limit", bytes, limit)
+            }
+            PipelineError::Accounts { total, failures } => {
+                write!(f, "Failed to fetch {} of {} monitored accounts:", failures.len(), total)?;
+                for (account, e) in failures {
+                    write!(f, "\n  {}: {}", account, e)?;
+                }
+                Ok(())
+            }
+            PipelineError::Execution(reason) => write!(f, "Program execution failed: {}", reason),
+            PipelineError::Proving(reason) => write!(f, "Proof generation failed: {}", reason),
+            PipelineError::Verification(reason) => write!(f, "Proof verification failed: {}", reason),
+            PipelineError::Inconsistent(changes) => {
+                write!(f, "The committed public values do not match the input in {} field(s):", changes.len())?;
+                for change in changes {
+                    write!(f, "\n  {}", change)?;
+                }
+                Ok(())
+            }
+            PipelineError::Encoding(reason) => write!(f, "Encoding error: {}", reason),
+            PipelineError::Publish(e) => write!(f, "Publishing failed: {}", e),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Publish(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<KafkaPublishError> for PipelineError {
+    fn from(e: KafkaPublishError) -> Self {
+        PipelineError::Publish(e)
+    }
+}
+
+/// Outcome of running the program without proving
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub commitments: PublicCommitments,
+    pub cycles: u64,
+    /// Cycles of each of the program's phases
+    pub profile: GuestProfile,
+    /// Size of the committed public values in bytes
+    pub public_values_len: usize,
+}
+
+/// Whether [`execute`] and [`prove`] check the committed public values against their input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consistency {
+    /// Fail with [`PipelineError::Inconsistent`] when they differ
+    Check,
+    /// Trust the program's output as is; for debugging the program only
+    UnsafeSkip,
+}
+
+impl Consistency {
+    fn check(self, input: &ProverInput, commitments: &PublicCommitments) -> Result<(), PipelineError> {
+        match self {
+            Consistency::Check => check_consistency(input, commitments),
+            Consistency::UnsafeSkip => Ok(()),
+        }
+    }
+}
+
+/// A generated proof and everything derived from it
+pub struct ProofArtifacts {
+    pub mode: ProofMode,
+    pub proof: SP1ProofWithPublicValues,
+    pub vk: SP1VerifyingKey,
+    pub commitments: PublicCommitments,
+    /// Message published to the sinks
+    pub zk_proof: ZkProof,
+    /// Raw data of the monitored accounts, published next to the message; empty when committed
+    pub account_data: Vec<RawAccountData>,
+    /// Cycles of each of the program's phases, when it was executed next to proving (Groth16)
+    pub profile: Option<GuestProfile>,
+}
+
+/// Build the program input from the monitored accounts as of the end slot
+///
+/// Up to `config.concurrency` accounts are fetched at the same time, each with
+/// its account info and its recent signatures, the newest of them at or before
+/// the end slot giving its last change slot. The accounts are sorted by pubkey
+/// whatever order they arrive in. The pubkeys are validated before any RPC
+/// call, every invalid one reported. An account that does not exist fails the
+/// run unless `config.allow_missing`, which commits it as closed. Every failed
+/// account is reported, not just the first. The RPC node may answer from a
+/// later slot than requested; the input then ends at that slot.
+pub async fn assemble_input(config: &InputConfig) -> Result<ProverInput, PipelineError> {
+    validate_pubkeys(&config.accounts, config.allow_zero_pubkey).map_err(PipelineError::InvalidPubkeys)?;
+    let rpc = SolanaRpcClient::new(&config.rpc_url);
+    let start_slot = config.start_slot;
+    let end_slot = match config.end_slot {
+        Some(end_slot) => end_slot,
+        None => {
+            let end_slot = rpc.get_current_slot().await.map_err(|e| PipelineError::Rpc(e.to_string()))?;
+            println!("Using current slot as end_slot: {}", end_slot);
+            end_slot
+        }
+    };
+
+    // Warmup epochs make the epoch of early slots depend on the cluster
+    let epoch_schedule = match config.epoch_schedule {
+        Some(schedule) => schedule,
+        None => rpc.get_epoch_schedule().await.map_err(|e| PipelineError::Rpc(e.to_string()))?,
+    };
+
+    let mut accounts = config.accounts.clone();
+    accounts.sort();
+    accounts.dedup();
+    let concurrency = config.concurrency.max(1);
+    println!("Fetching {} account(s), {} at a time", accounts.len(), concurrency);
+    println!("Start slot: {}, End slot: {}", start_slot, end_slot);
+
+    // Validate slots
+    if end_slot <= start_slot {
+        return Err(PipelineError::InvalidRange { start_slot, end_slot });
+    }
+
+    let (rpc, total_bytes) = (&rpc, &AtomicUsize::new(0));
+    let results: Vec<(String, Result<FetchedAccount, PipelineError>)> = stream::iter(accounts.iter())
+        .map(|account| async move {
+            let fetched = fetch_account(rpc, account, end_slot, config, total_bytes).await;
+            (account.clone(), fetched)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut fetched = Vec::with_capacity(results.len());
+    let mut failures = Vec::new();
+    for (account, result) in results {
+        match result {
+            Ok(account) => fetched.push(account),
+            Err(e) => failures.push((account, e)),
+        }
+    }
+    if !failures.is_empty() {
+        failures.sort_by(|(a, _), (b, _)| a.cmp(b));
+        return Err(PipelineError::Accounts { total: accounts.len(), failures });
+    }
+    let missing = fetched.iter().filter(|account| account.missing).count();
+    if missing > 0 {
+        println!("{} of {} account(s) do not exist, committed as closed", missing, accounts.len());
+    }
+
+    // Use the latest slot any account was read at if it is after the requested one
+    let actual_slot = fetched.iter().map(|account| account.actual_slot).max().unwrap_or(end_slot);
+    let effective_end_slot = if actual_slot > end_slot {
+        println!("Note: Using actual slot {} as end_slot (was {})", actual_slot, end_slot);
+        actual_slot
+    } else {
+        end_slot
+    };
+
+    let mut monitored_accounts_state: Vec<AccountStateCommitment> = fetched
+        .into_iter()
+        .map(|account| {
+            // Without a transaction in the lookup window, the account is taken as changed at the end slot
+            let last_change_slot = account
+                .signature_slots
+                .iter()
+                .copied()
+                .find(|&slot| slot <= effective_end_slot)
+                .unwrap_or(effective_end_slot);
+            AccountStateCommitment { last_change_slot, ..account.state }
+        })
+        .collect();
+    monitored_accounts_state.sort_by(|a, b| a.account_pubkey.cmp(&b.account_pubkey));
+
+    // Create dummy bank hashes
+    Ok(ProverInput {
+        start_slot,
+        end_slot: effective_end_slot,
+        epoch: epoch_schedule.get_epoch(effective_end_slot),
+        original_bank_hash: stub_bank_hash(start_slot),
+        last_bank_hash: stub_bank_hash(effective_end_slot),
+        monitored_accounts_state,
+    })
+}
+
+/// One account as fetched, before its last change slot is known
+struct FetchedAccount {
+    state: AccountStateCommitment,
+    /// Slot the node read the account at
+    actual_slot: u64,
+    /// Slots of its recent transactions, newest first
+    signature_slots: Vec<u64>,
+    /// The account does not exist and `state` commits to it as closed
+    missing: bool,
+}
+
+/// Fetch an account's info and recent signatures concurrently
+///
+/// The data is checked against the limits by its reported size before it is
+/// decoded, and `total_bytes` counts the data of every account fetched so far.
+/// An account that does not exist is an error unless `allow_missing`, in which
+/// case it is committed as closed.
+async fn fetch_account(
+    rpc: &SolanaRpcClient,
+    account: &str,
+    end_slot: u64,
+    config: &InputConfig,
+    total_bytes: &AtomicUsize,
+) -> Result<FetchedAccount, PipelineError> {
+    let limits = &config.limits;
+    let account_pubkey = base58_to_bytes32(account).map_err(PipelineError::InvalidAccount)?;
+    let (info, signatures) = futures::join!(
+        rpc.get_account_info(account, Some(end_slot)),
+        rpc.get_signature_slots(account, SIGNATURE_LIMIT)
+    );
+    let (account_info, actual_slot) = info.map_err(|e| PipelineError::Rpc(e.to_string()))?;
+    let signature_slots = signatures.map_err(|e| PipelineError::Rpc(e.to_string()))?;
+    let Some(account_info) = account_info else {
+        if !config.allow_missing {
+            return Err(PipelineError::AccountNotFound(account.to_string()));
+        }
+        println!("{} does not exist at slot {}, committing it as closed", account, actual_slot);
+        let state = closed_account(account_pubkey, actual_slot);
+        return Ok(FetchedAccount { state, actual_slot, signature_slots, missing: true });
+    };
+    println!("Fetched {} at slot {}", account, actual_slot);
+
+    let limit = limits.account_limit();
+    let too_large = |bytes| PipelineError::AccountTooLarge { account: account.to_string(), bytes, limit };
+    let space = account_info.space as usize;
+    if space > limit {
+        return Err(too_large(space));
+    }
+    let total = total_bytes.fetch_add(space, Ordering::SeqCst) + space;
+    if total > limits.max_total_bytes {
+        return Err(PipelineError::InputTooLarge { bytes: total, limit: limits.max_total_bytes });
+    }
+    let decoded = read_account_data(&account_info.data, limit, config.keep_data)?;
+    // The node's reported size is not trusted over the data itself
+    if decoded.len > limit {
+        return Err(too_large(decoded.len));
+    }
+    let owner = base58_to_bytes32(&account_info.owner).map_err(PipelineError::InvalidAccount)?;
+    let state = AccountStateCommitment {
+        account_pubkey,
+        last_change_slot: actual_slot,
+        account_data_hash: decoded.hash,
+        lamports: account_info.lamports,
+        owner,
+        executable: account_info.executable,
+        rent_epoch: account_info.rent_epoch,
+        data_len: decoded.len as u64,
+        data: decoded.data,
+    };
+    Ok(FetchedAccount { state, actual_slot, signature_slots, missing: false })
+}
+
+/// Commitment to an account that does not exist: no lamports, no data, owned by the system program
+fn closed_account(account_pubkey: [u8; 32], slot: u64) -> AccountStateCommitment {
+    AccountStateCommitment {
+        account_pubkey,
+        last_change_slot: slot,
+        account_data_hash: sha256_hash(&[]),
+        lamports: 0,
+        owner: SYSTEM_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+        data_len: 0,
+        data: Vec::new(),
+    }
+}
+
+/// Base58 pubkeys of the accounts the input commits to as closed, such as
+/// missing accounts assembled with [`InputConfig::allow_missing`]
+pub fn missing_accounts(input: &ProverInput) -> Vec<String> {
+    input
+        .monitored_accounts_state
+        .iter()
+        .filter(|account| account.is_closed())
+        .map(|account| bs58::encode(account.account_pubkey).into_string())
+        .collect()
+}
+
+/// Check an input built elsewhere, such as a fixture, against the limits
+///
+/// Also rejects accounts whose data is not `data_len` bytes long, which the
+/// program would refuse. Data left out of the input is empty whatever its
+/// `data_len`, which the program accepts, and so is this. The limits apply to
+/// `data_len`, the size of the account's data whether it is in the input or not.
+pub fn check_input_size(input: &ProverInput, limits: &InputLimits) -> Result<(), PipelineError> {
+    let mut total: usize = 0;
+    for account in &input.monitored_accounts_state {
+        if !account.data.is_empty() && account.data.len() as u64 != account.data_len {
+            return Err(PipelineError::InvalidAccount(format!(
+                "{} has {} bytes of data but a data_len of {}",
+                bs58::encode(account.account_pubkey).into_string(),
+                account.data.len(),
+                account.data_len
+            )));
+        }
+        let bytes = usize::try_from(account.data_len).unwrap_or(usize::MAX);
+        if bytes > limits.account_limit() {
+            return Err(PipelineError::AccountTooLarge {
+                account: bs58::encode(account.account_pubkey).into_string(),
+                bytes,
+                limit: limits.account_limit(),
+            });
+        }
+        total = total.saturating_add(bytes);
+    }
+    if total > limits.max_total_bytes {
+        return Err(PipelineError::InputTooLarge { bytes: total, limit: limits.max_total_bytes });
+    }
+    Ok(())
+}
+
+/// Account data as the RPC node returns it, a `[data, encoding]` pair
+///
+/// `base64` and `base64+zstd` are decoded; any other encoding, and the parsed
+/// object of `jsonParsed`, is an error rather than bytes that would hash to
+/// the wrong commitment. An empty `[]` is an account without data, like
+/// `["", "base64"]`. Decoding stops one byte past [`MAX_ACCOUNT_DATA_LEN`],
+/// leaving the size check to the caller.
+pub fn decode_account_data(data: &AccountData) -> Result<Vec<u8>, PipelineError> {
+    read_account_data(data, MAX_ACCOUNT_DATA_LEN, true).map(|decoded| decoded.data)
+}
+
+/// Account data read by [`read_account_data`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedAccountData {
+    /// SHA-256 of the data, the account's `account_data_hash`
+    pub hash: [u8; 32],
+    /// Length of the data
+    pub len: usize,
+    /// The data, empty unless it was kept
+    pub data: Vec<u8>,
+}
+
+/// Decode account data as [`decode_account_data`] does, hashing it as it streams out of the base64 text
+///
+/// The data is decoded and decompressed a chunk at a time into the hasher and
+/// only collected if `keep`, so an account whose data is not kept is never in
+/// memory as a whole. Decoding stops one byte past `limit`; the returned
+/// length then tells the caller the data is too large.
+pub fn read_account_data(data: &AccountData, limit: usize, keep: bool) -> Result<DecodedAccountData, PipelineError> {
+    // Some nodes return an account without data as `[]` instead of `["", "base64"]`
+    if matches!(data, AccountData::Other(serde_json::Value::Array(items)) if items.is_empty()) {
+        return Ok(DecodedAccountData { hash: sha256_hash(&[]), len: 0, data: Vec::new() });
+    }
+    let AccountData::Encoded(encoded, encoding) = data else {
+        let reason = "account data is not a [data, encoding] pair, e.g. jsonParsed";
+        return Err(PipelineError::InvalidAccount(reason.to_string()));
+    };
+    let encoding = encoding.as_str();
+    let invalid = |e: std::io::Error| PipelineError::InvalidAccount(format!("account data is not {}: {}", encoding, e));
+    let decoder = base64::read::DecoderReader::new(encoded.as_bytes(), &general_purpose::STANDARD);
+    let reader: Box<dyn Read + '_> = match encoding {
+        "base64" => Box::new(decoder),
+        "base64+zstd" => Box::new(zstd::stream::read::Decoder::new(decoder).map_err(invalid)?),
+        _ => {
+            return Err(PipelineError::InvalidAccount(format!("unsupported account data encoding '{}'", encoding)));
+        }
+    };
+    let mut reader = reader.take(limit as u64 + 1);
+    let mut hasher = Sha256::new();
+    let mut decoded = DecodedAccountData { hash: [0; 32], len: 0, data: Vec::new() };
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(invalid(e)),
+        };
+        hasher.update(&chunk[..read]);
+        if keep {
+            decoded.data.extend_from_slice(&chunk[..read]);
+        }
+        decoded.len += read;
+    }
+    decoded.hash = hasher.finalize().into();
+    Ok(decoded)
+}
+
+/// The program input for `input`, and the raw data left out of it
+fn program_input(input: &ProverInput, raw_data: RawData) -> (ProverInput, Vec<RawAccountData>) {
+    let mut input = input.clone();
+    let account_data = match raw_data {
+        RawData::Omit => account_data::detach(&mut input),
+        RawData::Commit => Vec::new(),
+    };
+    (input, account_data)
+}
+
+fn stdin_for(input: &ProverInput) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&ProgramInput::Range(input.clone()));
+    stdin
+}
+
+fn execute_with(client: &EnvProver, input: &ProverInput) -> Result<Execution, PipelineError> {
+    let (output, report) =
+        client.execute(PROVER_ELF, &stdin_for(input)).run().map_err(|e| PipelineError::Execution(e.to_string()))?;
+    let public_values = output.to_vec();
+    let commitments = bincode::deserialize(&public_values)
+        .map_err(|e| PipelineError::Encoding(format!("program output is not PublicCommitments: {}", e)))?;
+    let cycles = report.total_instruction_count();
+    let accounts: Vec<[u8; 32]> = input.monitored_accounts_state.iter().map(|account| account.account_pubkey).collect();
+    let profile = GuestProfile::from_tracker(&report.cycle_tracker, cycles, &accounts);
+    Ok(Execution { commitments, cycles, profile, public_values_len: public_values.len() })
+}
+
+/// Check that `commitments` commit to `input`, the input the program was given
+///
+/// The slots, epoch, bank hashes and monitored accounts must be the input's,
+/// and the accounts hash the host's hash of them. A range aggregates nothing,
+/// so its vkey digest must be [`NOT_AGGREGATED`]. The validator set values
+/// and the validation result are the stub's own and not compared. Every
+/// differing field is reported, as the input's value -> the committed one.
+pub fn check_consistency(input: &ProverInput, commitments: &PublicCommitments) -> Result<(), PipelineError> {
+    let expected = PublicCommitments {
+        start_slot: input.start_slot,
+        end_slot: input.end_slot,
+        epoch: input.epoch,
+        original_bank_hash: input.original_bank_hash,
+        last_bank_hash: input.last_bank_hash,
+        account_data_hash: compute_accounts_hash(&input.monitored_accounts_state),
+        hash_root_valset: commitments.hash_root_valset,
+        total_active_stake: commitments.total_active_stake,
+        validator_count: commitments.validator_count,
+        monitored_accounts_state: input.monitored_accounts_state.clone(),
+        validations_passed: commitments.validations_passed,
+        aggregated_vkey_digest: NOT_AGGREGATED,
+    };
+    let changes = diff(&expected, commitments);
+    if changes.is_empty() {
+        Ok(())
+    } else {
+        Err(PipelineError::Inconsistent(changes))
+    }
+}
+
+/// Run the program on `input` without generating a proof
+pub fn execute(input: &ProverInput, raw_data: RawData, consistency: Consistency) -> Result<Execution, PipelineError> {
+    let (input, _) = program_input(input, raw_data);
+    let execution = execute_with(&ProverClient::from_env(), &input)?;
+    consistency.check(&input, &execution.commitments)?;
+    Ok(execution)
+}
+
+/// The prover selected by `SP1_PROVER` with the embedded program's keys
+///
+/// Setting the keys up is slow, so callers that check the key before proving
+/// set them up once and prove with [`prove_with`].
+pub struct ProgramKeys {
+    client: EnvProver,
+    pk: SP1ProvingKey,
+    pub vk: SP1VerifyingKey,
+}
+
+impl ProgramKeys {
+    pub fn setup() -> Self {
+        let client = ProverClient::from_env();
+        println!("Setting up proving keys...");
+        let (pk, vk) = client.setup(PROVER_ELF);
+        Self { client, pk, vk }
+    }
+
+    /// Program vkey hash (`vk.bytes32()`)
+    pub fn vkey_hash(&self) -> [u8; 32] {
+        self.vk.bytes32_raw()
+    }
+}
+
+/// Program vkey hash (`vk.bytes32()`) of the embedded ELF, from a fresh key setup
+pub fn program_vkey_hash() -> [u8; 32] {
+    ProgramKeys::setup().vkey_hash()
+}
+
+/// Generate a proof of `input` and verify it
+///
+/// Uses the prover selected by `SP1_PROVER`. The public values are checked
+/// against the input before the proof is verified. CPU-bound and slow; call it
+/// from a blocking task in async code.
+pub fn prove(
+    input: &ProverInput,
+    mode: ProofMode,
+    raw_data: RawData,
+    consistency: Consistency,
+) -> Result<ProofArtifacts, PipelineError> {
+    prove_with(&ProgramKeys::setup(), input, mode, raw_data, consistency)
+}
+
+/// [`prove`] with keys that are already set up
+pub fn prove_with(
+    keys: &ProgramKeys,
+    input: &ProverInput,
+    mode: ProofMode,
+    raw_data: RawData,
+    consistency: Consistency,
+) -> Result<ProofArtifacts, PipelineError> {
+    let ProgramKeys { client, pk, vk } = keys;
+    let (input, account_data) = program_input(input, raw_data);
+    let stdin = stdin_for(&input);
+
+    let proof = generate(client, pk, &stdin, mode)?;
+    let (commitments, profile) = match mode {
+        ProofMode::Compressed => {
+            let commitments = bincode::deserialize(proof.public_values.as_slice())
+                .map_err(|e| PipelineError::Encoding(format!("public values are not PublicCommitments: {}", e)))?;
+            (commitments, None)
+        }
+        // The published public values come from the program's own output
+        ProofMode::Groth16 => {
+            let execution = execute_with(client, &input)?;
+            (execution.commitments, Some(execution.profile))
+        }
+    };
+    consistency.check(&input, &commitments)?;
+    let identifier = format!("solana-stub-{}-{}", input.start_slot, input.end_slot);
+    let mut artifacts = finish(client, mode, proof, vk.clone(), commitments, identifier)?;
+    artifacts.account_data = account_data;
+    artifacts.profile = profile;
+    Ok(artifacts)
+}
+
+/// Prove the union range of compressed proofs of contiguous ranges, given in slot order
+///
+/// The proofs must come from this program (a range or an earlier aggregate);
+/// the program verifies each of them recursively and rejects ranges that do
+/// not link up. Slow like [`prove`]; call it from a blocking task in async code.
+pub fn prove_aggregate(proofs: &[SP1ProofWithPublicValues], mode: ProofMode) -> Result<ProofArtifacts, PipelineError> {
+    let client = ProverClient::from_env();
+
+    println!("Setting up proving keys...");
+    let (pk, vk) = client.setup(PROVER_ELF);
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&ProgramInput::Aggregate(AggregationInput {
+        vkey_digest: vk.hash_u32(),
+        public_values: proofs.iter().map(|proof| proof.public_values.to_vec()).collect(),
+    }));
+    for proof in proofs {
+        let sp1_sdk::SP1Proof::Compressed(compressed) = &proof.proof else {
+            return Err(PipelineError::Proving("only compressed proofs can be aggregated".to_string()));
+        };
+        stdin.write_proof(compressed.as_ref().clone(), vk.vk.clone());
+    }
+
+    println!("Aggregating {} proofs...", proofs.len());
+    let proof = generate(&client, &pk, &stdin, mode)?;
+    let commitments: PublicCommitments = bincode::deserialize(proof.public_values.as_slice())
+        .map_err(|e| PipelineError::Encoding(format!("public values are not PublicCommitments: {}", e)))?;
+    if commitments.aggregated_vkey_digest != vkey_digest_bytes(&vk.hash_u32()) {
+        return Err(PipelineError::Verification(
+            "the aggregate does not commit the vkey digest its proofs were verified under".to_string(),
+        ));
+    }
+    let identifier = format!("solana-stub-aggregate-{}-{}", commitments.start_slot, commitments.end_slot);
+    finish(&client, mode, proof, vk, commitments, identifier)
+}
+
+/// Run the prover in the given mode
+fn generate(
+    client: &EnvProver,
+    pk: &SP1ProvingKey,
+    stdin: &SP1Stdin,
+    mode: ProofMode,
+) -> Result<SP1ProofWithPublicValues, PipelineError> {
+    let prover = client.prove(pk, stdin);
+    let prover = match mode {
+        ProofMode::Compressed => {
+            // Faster, but not verifiable on-chain
+            println!("Generating compressed proof...");
+            prover.compressed()
+        }
+        ProofMode::Groth16 => {
+            // Verifiable on-chain
+            println!("Generating Groth16 proof...");
+            prover.groth16()
+        }
+    };
+    prover.run().map_err(|e| PipelineError::Proving(e.to_string()))
+}
+
+/// Verify a generated proof and build the message published for it
+fn finish(
+    client: &EnvProver,
+    mode: ProofMode,
+    proof: SP1ProofWithPublicValues,
+    vk: SP1VerifyingKey,
+    commitments: PublicCommitments,
+    identifier: String,
+) -> Result<ProofArtifacts, PipelineError> {
+    let label = match mode {
+        ProofMode::Compressed => "compressed",
+        ProofMode::Groth16 => "Groth16",
+    };
+    println!("Successfully generated {} proof!", label);
+
+    client.verify(&proof, &vk).map_err(|e| PipelineError::Verification(e.to_string()))?;
+    println!("Successfully verified {} proof!", label);
+
+    // Create ZkProof structure for Kafka using weaver types
+    let proof_bytes = bincode::serialize(&proof).map_err(|e| PipelineError::Encoding(e.to_string()))?;
+    let public_value = match mode {
+        ProofMode::Compressed => proof.public_values.to_vec(),
+        ProofMode::Groth16 => bincode::serialize(&commitments).map_err(|e| PipelineError::Encoding(e.to_string()))?,
+    };
+    // The program vkey hash verifiers are configured with
+    let vk_hash = vk.bytes32_raw();
+    let zk_proof = envelope(identifier, mode, proof_bytes, public_value, vk_hash);
+    Ok(ProofArtifacts { mode, proof, vk, commitments, zk_proof, account_data: Vec::new(), profile: None })
+}
+
+/// The message published for a bincode-serialized proof
+pub fn envelope(
+    identifier: String,
+    mode: ProofMode,
+    proof: Vec<u8>,
+    public_value: Vec<u8>,
+    vk_hash: [u8; 32],
+) -> ZkProof {
+    let version = match mode {
+        ProofMode::Compressed => 1,
+        ProofMode::Groth16 => 2,
+    };
+    ZkProof {
+        identifier,
+        proof_kind: ProofKind::SolanaConsensusProof,
+        proof_data: ProofData::SP1(SP1Proof { version, proof, public_value, verification_key: vk_hash }),
+    }
+}
+
+/// Publish a proof's message to `sink`, with the raw account data it does not commit
+pub async fn publish(artifacts: &ProofArtifacts, sink: &dyn ProofSink) -> Result<SinkReceipt, PipelineError> {
+    if artifacts.account_data.is_empty() {
+        return Ok(sink.publish(&artifacts.zk_proof).await?);
+    }
+    let metadata = account_data::metadata(&artifacts.account_data);
+    Ok(sink.publish_with_metadata(&artifacts.zk_proof, &metadata).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{Preset, DEFAULT_SEED};
+
+    fn account(data_len: u64, data: Vec<u8>) -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: [1; 32],
+            last_change_slot: 10,
+            account_data_hash: sha256_hash(&data),
+            lamports: 1_000_000,
+            owner: [2; 32],
+            executable: false,
+            rent_epoch: 0,
+            data_len,
+            data,
+        }
+    }
+
+    fn input(monitored_accounts_state: Vec<AccountStateCommitment>) -> ProverInput {
+        ProverInput {
+            start_slot: 1,
+            end_slot: 2,
+            epoch: 0,
+            original_bank_hash: stub_bank_hash(1),
+            last_bank_hash: stub_bank_hash(2),
+            monitored_accounts_state,
+        }
+    }
+
+    #[test]
+    fn accepts_committed_and_left_out_data() {
+        let limits = InputLimits::default();
+        assert!(check_input_size(&input(vec![account(4, b"stub".to_vec())]), &limits).is_ok());
+        assert!(check_input_size(&input(vec![account(4, Vec::new())]), &limits).is_ok());
+    }
+
+    #[test]
+    fn rejects_data_of_another_length() {
+        let result = check_input_size(&input(vec![account(5, b"stub".to_vec())]), &InputLimits::default());
+        assert!(matches!(result, Err(PipelineError::InvalidAccount(_))));
+    }
+
+    #[test]
+    fn limits_apply_to_left_out_data() {
+        let limits = InputLimits { max_account_bytes: 16, max_total_bytes: 24 };
+        let result = check_input_size(&input(vec![account(17, Vec::new())]), &limits);
+        assert!(matches!(result, Err(PipelineError::AccountTooLarge { bytes: 17, limit: 16, .. })));
+        let result = check_input_size(&input(vec![account(16, Vec::new()), account(16, vec![0; 16])]), &limits);
+        assert!(matches!(result, Err(PipelineError::InputTooLarge { bytes: 32, limit: 24 })));
+    }
+
+    fn encoded(data: &[u8], encoding: &str) -> AccountData {
+        use base64::Engine as _;
+        let bytes = if encoding == "base64+zstd" { zstd::encode_all(data, 3).unwrap() } else { data.to_vec() };
+        AccountData::Encoded(general_purpose::STANDARD.encode(bytes), encoding.to_string())
+    }
+
+    #[test]
+    fn both_empty_shapes_hash_alike() {
+        for data in [encoded(&[], "base64"), AccountData::Other(serde_json::json!([]))] {
+            let decoded = read_account_data(&data, MAX_ACCOUNT_DATA_LEN, true).unwrap();
+            assert_eq!(decoded, DecodedAccountData { hash: sha256_hash(&[]), len: 0, data: Vec::new() });
+        }
+    }
+
+    #[test]
+    fn hashes_without_keeping_the_data() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        for encoding in ["base64", "base64+zstd"] {
+            let kept = read_account_data(&encoded(&data, encoding), MAX_ACCOUNT_DATA_LEN, true).unwrap();
+            assert_eq!((kept.hash, kept.len, &kept.data), (sha256_hash(&data), data.len(), &data));
+            let dropped = read_account_data(&encoded(&data, encoding), MAX_ACCOUNT_DATA_LEN, false).unwrap();
+            assert_eq!((dropped.hash, dropped.len, dropped.data.len()), (kept.hash, kept.len, 0));
+        }
+    }
+
+    #[test]
+    fn stops_one_byte_past_the_limit() {
+        let data = vec![7; 4096];
+        for encoding in ["base64", "base64+zstd"] {
+            let decoded = read_account_data(&encoded(&data, encoding), 1000, true).unwrap();
+            assert_eq!((decoded.len, decoded.data.len()), (1001, 1001));
+        }
+    }
+
+    #[test]
+    fn rejects_other_encodings() {
+        assert!(read_account_data(&encoded(b"data", "base58"), MAX_ACCOUNT_DATA_LEN, true).is_err());
+        assert!(read_account_data(&AccountData::Encoded("!!".to_string(), "base64".to_string()), 16, true).is_err());
+        let parsed = AccountData::Other(serde_json::json!({ "parsed": {}, "space": 0 }));
+        assert!(read_account_data(&parsed, MAX_ACCOUNT_DATA_LEN, true).is_err());
+    }
+
+    #[test]
+    fn program_hashes_sorted_accounts_as_the_host_does() {
+        let sorted = Preset::TokenAccounts.generate(DEFAULT_SEED);
+        let execution = execute(&sorted, RawData::Omit, Consistency::Check).unwrap();
+        assert_eq!(execution.commitments.account_data_hash, compute_accounts_hash(&sorted.monitored_accounts_state));
+    }
+
+    #[test]
+    fn program_refuses_unsorted_accounts() {
+        let sorted = Preset::TokenAccounts.generate(DEFAULT_SEED);
+        let mut reversed = sorted.clone();
+        reversed.monitored_accounts_state.reverse();
+        // The order is part of the hash, which is why the program allows only one
+        assert_ne!(
+            compute_accounts_hash(&reversed.monitored_accounts_state),
+            compute_accounts_hash(&sorted.monitored_accounts_state)
+        );
+        let mut duplicated = sorted.clone();
+        duplicated.monitored_accounts_state.insert(1, sorted.monitored_accounts_state[0].clone());
+        for input in [reversed, duplicated] {
+            let result = execute(&input, RawData::Omit, Consistency::Check);
+            assert!(matches!(result, Err(PipelineError::Execution(_))));
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//! Terminal output styles shared by the prover and consumer binaries
+//!
+//! The pretty style decorates lines with emoji and draws proofs in boxes. The
+//! plain style is meant for files, log collectors and terminals without UTF-8:
+//! it drops the leading emoji of every line and prints sections as aligned
+//! key-value lines. Colors are controlled separately and honor `NO_COLOR`.
+//!
+//! Binaries install a presenter once at startup and print through the
+//! [`outln!`](crate::outln) and [`errln!`](crate::errln) macros.
+
+use crate::utils::format_pubkey;
+use solana_stub_prover_lib::{AccountStateCommitment, PublicCommitments};
+use std::fmt;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Output decoration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Emoji and box drawing
+    Pretty,
+    /// ASCII key-value lines
+    Plain,
+}
+
+impl OutputStyle {
+    /// Pretty on a terminal, plain when stdout is redirected
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            OutputStyle::Pretty
+        } else {
+            OutputStyle::Plain
+        }
+    }
+}
+
+impl FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(OutputStyle::Pretty),
+            "plain" => Ok(OutputStyle::Plain),
+            _ => Err(format!("Invalid output style: {} (expected pretty or plain)", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutputStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputStyle::Pretty => write!(f, "pretty"),
+            OutputStyle::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// When to color output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("Invalid color choice: {} (expected auto, always or never)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorChoice::Auto => write!(f, "auto"),
+            ColorChoice::Always => write!(f, "always"),
+            ColorChoice::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Meaning of a highlighted word, mapped to a color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Good,
+    Bad,
+    Warning,
+    Emphasis,
+}
+
+impl Tone {
+    fn ansi(self) -> &'static str {
+        match self {
+            Tone::Good => "\x1b[32m",
+            Tone::Bad => "\x1b[31m",
+            Tone::Warning => "\x1b[33m",
+            Tone::Emphasis => "\x1b[1m",
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+const BOX_WIDTH: usize = 70;
+
+static PRESENTER: OnceLock<Presenter> = OnceLock::new();
+
+/// Output style and color settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Presenter {
+    style: OutputStyle,
+    color: bool,
+}
+
+impl Presenter {
+    /// Presenter for `style` (detected from stdout if `None`) and `color`
+    pub fn new(style: Option<OutputStyle>, color: ColorChoice) -> Self {
+        let color = match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        };
+        Self { style: style.unwrap_or_else(OutputStyle::detect), color }
+    }
+
+    /// Make this the presenter used by [`current`] and the print macros
+    ///
+    /// Only the first call has an effect.
+    pub fn install(self) -> &'static Presenter {
+        PRESENTER.get_or_init(|| self)
+    }
+
+    pub fn style(&self) -> OutputStyle {
+        self.style
+    }
+
+    pub fn is_pretty(&self) -> bool {
+        self.style == OutputStyle::Pretty
+    }
+
+    /// A line of output in this style: plain drops leading emoji and box-drawing lines
+    pub fn text(&self, line: &str) -> String {
+        match self.style {
+            OutputStyle::Pretty => line.to_string(),
+            OutputStyle::Plain => strip_decorations(line),
+        }
+    }
+
+    /// Highlight `text` if colors are enabled
+    pub fn paint(&self, text: impl fmt::Display, tone: Tone) -> String {
+        if self.color {
+            format!("{}{}{}", tone.ansi(), text, ANSI_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The installed presenter, or one detected from the terminal if none was installed
+pub fn current() -> &'static Presenter {
+    PRESENTER.get_or_init(|| Presenter::new(None, ColorChoice::Auto))
+}
+
+/// Print a line to stdout through the installed presenter
+#[macro_export]
+macro_rules! outln {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::presenter::current().text(&format!($($arg)*)))
+    };
+}
+
+/// Print a line to stderr through the installed presenter
+#[macro_export]
+macro_rules! errln {
+    () => {
+        eprintln!()
+    };
+    ($($arg:tt)*) => {
+        eprintln!("{}", $crate::presenter::current().text(&format!($($arg)*)))
+    };
+}
+
+/// Whether a character is an icon or box-drawing character rather than text
+fn is_decoration(c: char) -> bool {
+    // Arrows through box drawing, dingbats and misc symbols, then the emoji planes
+    matches!(c, '\u{2190}'..='\u{2BFF}' | '\u{1F000}'..='\u{1FAFF}' | '\u{FE0F}' | '\u{200D}')
+}
+
+/// Remove the leading icons of every line; lines of pure box drawing become empty
+fn strip_decorations(line: &str) -> String {
+    line.split('\n')
+        .map(|segment| {
+            let mut rest = segment.trim_start_matches(' ');
+            let indent = &segment[..segment.len() - rest.len()];
+            while let Some(c) = rest.chars().next().filter(|c| is_decoration(*c)) {
+                rest = rest[c.len_utf8()..].trim_start_matches(' ');
+            }
+            if rest.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indent, rest)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Hex of `bytes`, truncated to `max_len` bytes
+pub fn format_bytes(bytes: &[u8], max_len: usize) -> String {
+    if bytes.len() <= max_len {
+        hex::encode(bytes)
+    } else {
+        format!("{}... ({} bytes)", hex::encode(&bytes[..max_len]), bytes.len())
+    }
+}
+
+enum Row {
+    Field { depth: usize, key: String, value: String },
+    Heading { depth: usize, text: String },
+    Note(String),
+    Blank,
+}
+
+/// A titled block of key-value lines, drawn as a box in the pretty style
+pub struct Section {
+    title: String,
+    rows: Vec<Row>,
+}
+
+impl Section {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), rows: Vec::new() }
+    }
+
+    /// Add a `key: value` line, indented by `depth` levels
+    pub fn field(&mut self, depth: usize, key: impl Into<String>, value: impl fmt::Display) -> &mut Self {
+        self.rows.push(Row::Field { depth, key: key.into(), value: value.to_string() });
+        self
+    }
+
+    /// Add a heading for the lines that follow
+    pub fn heading(&mut self, depth: usize, text: impl Into<String>) -> &mut Self {
+        self.rows.push(Row::Heading { depth, text: text.into() });
+        self
+    }
+
+    /// Add a free-form line
+    pub fn note(&mut self, text: impl Into<String>) -> &mut Self {
+        self.rows.push(Row::Note(text.into()));
+        self
+    }
+
+    /// Add an empty line (pretty style only)
+    pub fn blank(&mut self) -> &mut Self {
+        self.rows.push(Row::Blank);
+        self
+    }
+
+    /// Render the section for `presenter`
+    pub fn render(&self, presenter: &Presenter) -> String {
+        match presenter.style {
+            OutputStyle::Pretty => self.render_pretty(presenter),
+            OutputStyle::Plain => self.render_plain(presenter),
+        }
+    }
+
+    fn render_pretty(&self, presenter: &Presenter) -> String {
+        let mut lines = vec![
+            format!("\n╔{}", "═".repeat(BOX_WIDTH)),
+            format!("║ {}", presenter.paint(&self.title, Tone::Emphasis)),
+            format!("╟{}", "─".repeat(BOX_WIDTH)),
+        ];
+        for row in &self.rows {
+            lines.push(match row {
+                Row::Field { depth, key, value } => format!("║ {}{}: {}", "  ".repeat(*depth), key, value),
+                Row::Heading { depth, text } => format!("║ {}{}", "  ".repeat(*depth), text),
+                Row::Note(text) => format!("║ {}", text),
+                Row::Blank => "║".to_string(),
+            });
+        }
+        lines.push(format!("╚{}", "═".repeat(BOX_WIDTH)));
+        lines.join("\n")
+    }
+
+    fn render_plain(&self, presenter: &Presenter) -> String {
+        let width = self
+            .rows
+            .iter()
+            .filter_map(|row| match row {
+                Row::Field { depth, key, .. } => Some(depth * 2 + key.len()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![presenter.paint(strip_decorations(&self.title), Tone::Emphasis)];
+        for row in &self.rows {
+            match row {
+                Row::Field { depth, key, value } => {
+                    let key = format!("{}{}", "  ".repeat(*depth), key);
+                    lines.push(format!("  {:<width$}  {}", key, value, width = width));
+                }
+                Row::Heading { depth, text } => {
+                    lines.push(format!("  {}{}", "  ".repeat(*depth), strip_decorations(text)))
+                }
+                Row::Note(text) => lines.push(format!("  {}", strip_decorations(text))),
+                Row::Blank => {}
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Rent epoch for display, `rent-exempt` instead of the `u64::MAX` sentinel
+pub fn rent_epoch(account: &AccountStateCommitment) -> String {
+    if account.is_rent_exempt() {
+        "rent-exempt".to_string()
+    } else {
+        account.rent_epoch.to_string()
+    }
+}
+
+/// Add the decoded public commitments and monitored accounts to a section
+pub fn commitment_rows(section: &mut Section, commitments: &PublicCommitments, depth: usize) {
+    section
+        .field(depth, "Start Slot", commitments.start_slot)
+        .field(depth, "End Slot", commitments.end_slot)
+        .field(depth, "Epoch", commitments.epoch)
+        .field(depth, "Original Bank Hash", format_bytes(&commitments.original_bank_hash, 8))
+        .field(depth, "Last Bank Hash", format_bytes(&commitments.last_bank_hash, 8))
+        .field(depth, "Account Data Hash", format_bytes(&commitments.account_data_hash, 8))
+        .field(depth, "Validator Set Hash", format_bytes(&commitments.hash_root_valset, 8))
+        .field(depth, "Total Active Stake", commitments.total_active_stake)
+        .field(depth, "Validator Count", commitments.validator_count)
+        .field(depth, "Monitored Accounts", commitments.monitored_accounts_state.len())
+        .field(depth, "Validations Passed", commitments.validations_passed);
+    if commitments.is_aggregate() {
+        section.field(depth, "Aggregated VKey Digest", format_bytes(&commitments.aggregated_vkey_digest, 8));
+    }
+
+    for (i, account) in commitments.monitored_accounts_state.iter().enumerate() {
+        section
+            .blank()
+            .field(depth, format!("Account #{}", i + 1), format_pubkey(&account.account_pubkey, false))
+            .field(depth + 1, "Last Change Slot", account.last_change_slot)
+            .field(depth + 1, "Lamports", account.lamports)
+            .field(depth + 1, "Executable", account.executable)
+            .field(depth + 1, "Rent Epoch", rent_epoch(account))
+            .field(depth + 1, "Data Size", format!("{} bytes", account.data_len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_stub_prover_lib::NOT_AGGREGATED;
+
+    const PRETTY: Presenter = Presenter { style: OutputStyle::Pretty, color: false };
+    const PRETTY_COLOR: Presenter = Presenter { style: OutputStyle::Pretty, color: true };
+    const PLAIN: Presenter = Presenter { style: OutputStyle::Plain, color: false };
+    const PLAIN_COLOR: Presenter = Presenter { style: OutputStyle::Plain, color: true };
+
+    fn section() -> Section {
+        let mut section = Section::new("🧾 Proof Details");
+        section
+            .field(0, "Identifier", "proof-1")
+            .field(0, "Kind", "SolanaConsensusProof")
+            .blank()
+            .heading(0, "📦 Accounts")
+            .field(1, "Lamports", 42)
+            .note("✅ Verified");
+        section
+    }
+
+    fn pretty_snapshot(title: &str) -> String {
+        [
+            format!("\n╔{}", "═".repeat(BOX_WIDTH)),
+            format!("║ {}", title),
+            format!("╟{}", "─".repeat(BOX_WIDTH)),
+            "║ Identifier: proof-1".to_string(),
+            "║ Kind: SolanaConsensusProof".to_string(),
+            "║".to_string(),
+            "║ 📦 Accounts".to_string(),
+            "║   Lamports: 42".to_string(),
+            "║ ✅ Verified".to_string(),
+            format!("╚{}", "═".repeat(BOX_WIDTH)),
+        ]
+        .join("\n")
+    }
+
+    const PLAIN_ROWS: &str = "  Identifier  proof-1
+  Kind        SolanaConsensusProof
+  Accounts
+    Lamports  42
+  Verified";
+
+    #[test]
+    fn pretty_sections_are_boxed() {
+        assert_eq!(section().render(&PRETTY), pretty_snapshot("🧾 Proof Details"));
+    }
+
+    #[test]
+    fn pretty_sections_color_only_the_title() {
+        assert_eq!(section().render(&PRETTY_COLOR), pretty_snapshot("\x1b[1m🧾 Proof Details\x1b[0m"));
+    }
+
+    #[test]
+    fn plain_sections_are_aligned_key_value_lines() {
+        assert_eq!(section().render(&PLAIN), format!("Proof Details\n{}", PLAIN_ROWS));
+    }
+
+    #[test]
+    fn plain_sections_color_only_the_title() {
+        assert_eq!(section().render(&PLAIN_COLOR), format!("\x1b[1mProof Details\x1b[0m\n{}", PLAIN_ROWS));
+    }
+
+    #[test]
+    fn plain_text_drops_leading_icons_and_box_lines() {
+        assert_eq!(PLAIN.text("✅ Proof verified"), "Proof verified");
+        assert_eq!(PLAIN.text("  ⚠️ Warning: lag"), "  Warning: lag");
+        assert_eq!(PLAIN.text("🔍 📦 nested\n═══════\nkept ✅"), "nested\n\nkept ✅");
+        assert_eq!(PRETTY.text("✅ Proof verified"), "✅ Proof verified");
+    }
+
+    #[test]
+    fn paint_colors_only_when_enabled() {
+        assert_eq!(PLAIN.paint("FAILED", Tone::Bad), "FAILED");
+        assert_eq!(PLAIN_COLOR.paint("FAILED", Tone::Bad), "\x1b[31mFAILED\x1b[0m");
+        assert_eq!(PRETTY_COLOR.paint(3, Tone::Good), "\x1b[32m3\x1b[0m");
+        assert_eq!(Presenter::new(Some(OutputStyle::Plain), ColorChoice::Never), PLAIN);
+        assert_eq!(Presenter::new(Some(OutputStyle::Pretty), ColorChoice::Always), PRETTY_COLOR);
+    }
+
+    #[test]
+    fn commitments_render_as_plain_rows() {
+        let commitments = PublicCommitments {
+            start_slot: 100,
+            end_slot: 200,
+            epoch: 3,
+            original_bank_hash: [0x11; 32],
+            last_bank_hash: [0x22; 32],
+            account_data_hash: [0x33; 32],
+            hash_root_valset: [0x44; 32],
+            total_active_stake: 5000,
+            validator_count: 7,
+            monitored_accounts_state: vec![AccountStateCommitment {
+                account_pubkey: [0; 32],
+                last_change_slot: 150,
+                account_data_hash: [0; 32],
+                lamports: 42,
+                owner: [0; 32],
+                executable: false,
+                rent_epoch: u64::MAX,
+                data_len: 4,
+                data: b"stub".to_vec(),
+            }],
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        };
+        let mut section = Section::new("Commitments");
+        commitment_rows(&mut section, &commitments, 0);
+
+        let expected = "Commitments
+  Start Slot          100
+  End Slot            200
+  Epoch               3
+  Original Bank Hash  1111111111111111... (32 bytes)
+  Last Bank Hash      2222222222222222... (32 bytes)
+  Account Data Hash   3333333333333333... (32 bytes)
+  Validator Set Hash  4444444444444444... (32 bytes)
+  Total Active Stake  5000
+  Validator Count     7
+  Monitored Accounts  1
+  Validations Passed  true
+  Account #1          11111111111111111111111111111111
+    Last Change Slot  150
+    Lamports          42
+    Executable        false
+    Rent Epoch        rent-exempt
+    Data Size         4 bytes";
+        assert_eq!(section.render(&PLAIN), expected);
+    }
+}
@@ -0,0 +1,311 @@
+//! Filtering of consumed proofs by kind, slot range, account and identifier
+//!
+//! Only consensus proofs carry Solana [`PublicCommitments`]; execution proofs
+//! are never decoded as such, and slot or account criteria skip them. The
+//! criteria only read slots and pubkeys, so filtering decodes the borrowed
+//! [`PublicCommitmentsRef`] and never copies the accounts' data.
+
+use solana_stub_prover_lib::{PublicCommitments, PublicCommitmentsRef};
+use std::fmt;
+use std::str::FromStr;
+use twine_types::proofs::{ProofData, ProofKind, ZkProof};
+
+/// Proof kinds selected by `--kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KindFilter {
+    /// `ExecutionProof` only
+    Execution,
+    /// `SolanaConsensusProof` only
+    Consensus,
+    #[default]
+    All,
+}
+
+impl KindFilter {
+    pub fn matches(&self, kind: &ProofKind) -> bool {
+        match self {
+            KindFilter::Execution => matches!(kind, ProofKind::ExecutionProof(_)),
+            KindFilter::Consensus => matches!(kind, ProofKind::SolanaConsensusProof),
+            KindFilter::All => true,
+        }
+    }
+}
+
+impl FromStr for KindFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "execution" => Ok(KindFilter::Execution),
+            "consensus" => Ok(KindFilter::Consensus),
+            "all" => Ok(KindFilter::All),
+            _ => Err(format!("Invalid proof kind: {} (expected execution, consensus or all)", s)),
+        }
+    }
+}
+
+impl fmt::Display for KindFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KindFilter::Execution => write!(f, "execution"),
+            KindFilter::Consensus => write!(f, "consensus"),
+            KindFilter::All => write!(f, "all"),
+        }
+    }
+}
+
+/// Index of an execution proof, or `None` for other kinds
+pub fn execution_index(proof: &ZkProof) -> Option<u64> {
+    if let ProofKind::ExecutionProof(index) = &proof.proof_kind {
+        Some(*index)
+    } else {
+        None
+    }
+}
+
+/// Metric label of a proof's kind: `execution` or `consensus`
+pub fn kind_label(proof: &ZkProof) -> &'static str {
+    if execution_index(proof).is_some() {
+        "execution"
+    } else {
+        "consensus"
+    }
+}
+
+/// Decode the Solana public commitments of a consensus proof
+///
+/// Returns `None` for execution proofs, whose public values are not commitments,
+/// and for consensus proofs whose public values do not decode.
+pub fn decode_commitments(proof: &ZkProof) -> Option<PublicCommitments> {
+    if execution_index(proof).is_some() {
+        return None;
+    }
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    bincode::deserialize(&sp1_proof.public_value).ok()
+}
+
+/// Like [`decode_commitments`], borrowing the accounts' data from the proof
+pub fn decode_commitments_ref(proof: &ZkProof) -> Option<PublicCommitmentsRef<'_>> {
+    if execution_index(proof).is_some() {
+        return None;
+    }
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    bincode::deserialize(&sp1_proof.public_value).ok()
+}
+
+/// Criteria a proof must meet to be displayed; unset criteria match everything
+#[derive(Debug, Clone, Default)]
+pub struct ProofFilter {
+    /// Only proofs whose range starts at or after this slot
+    pub start_slot: Option<u64>,
+    /// Only proofs whose range ends at or before this slot
+    pub end_slot: Option<u64>,
+    /// Only proofs monitoring this account (any entry of `monitored_accounts_state`)
+    pub account: Option<[u8; 32]>,
+    /// Only proofs whose identifier starts with this prefix
+    pub identifier_prefix: Option<String>,
+    /// Only proofs of these kinds
+    pub kind: KindFilter,
+}
+
+/// Result of applying a filter to a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOutcome {
+    Matched,
+    Skipped,
+    /// A slot or account criterion is set but the public values could not be decoded
+    Undecodable,
+}
+
+impl ProofFilter {
+    /// Whether any criterion needs the decoded public commitments
+    pub fn needs_commitments(&self) -> bool {
+        self.start_slot.is_some() || self.end_slot.is_some() || self.account.is_some()
+    }
+
+    /// Whether no criteria are set
+    pub fn is_empty(&self) -> bool {
+        !self.needs_commitments() && self.identifier_prefix.is_none() && self.kind == KindFilter::All
+    }
+
+    /// Apply the filter to a proof, decoding its public values if needed
+    pub fn apply(&self, proof: &ZkProof) -> FilterOutcome {
+        if !self.kind.matches(&proof.proof_kind) {
+            return FilterOutcome::Skipped;
+        }
+        if let Some(prefix) = &self.identifier_prefix {
+            if !proof.identifier.starts_with(prefix.as_str()) {
+                return FilterOutcome::Skipped;
+            }
+        }
+        if !self.needs_commitments() {
+            return FilterOutcome::Matched;
+        }
+
+        // Execution proofs have no slot range or monitored accounts to match
+        if execution_index(proof).is_some() {
+            return FilterOutcome::Skipped;
+        }
+        match decode_commitments_ref(proof) {
+            Some(commitments) => self.apply_commitments(&commitments),
+            None => FilterOutcome::Undecodable,
+        }
+    }
+
+    /// Apply the slot and account criteria to decoded public commitments
+    pub fn apply_commitments(&self, commitments: &PublicCommitmentsRef<'_>) -> FilterOutcome {
+        let in_range = self.start_slot.is_none_or(|start| commitments.start_slot >= start)
+            && self.end_slot.is_none_or(|end| commitments.end_slot <= end);
+        let has_account = self.account.is_none_or(|account| {
+            commitments
+                .monitored_accounts_state
+                .iter()
+                .any(|state| state.account_pubkey == account)
+        });
+
+        if in_range && has_account {
+            FilterOutcome::Matched
+        } else {
+            FilterOutcome::Skipped
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_stub_prover_lib::{AccountStateCommitment, NOT_AGGREGATED};
+    use twine_types::proofs::SP1Proof;
+
+    const WATCHED: [u8; 32] = [9; 32];
+
+    fn with_public_value(identifier: &str, proof_kind: ProofKind, public_value: Vec<u8>) -> ZkProof {
+        ZkProof {
+            identifier: identifier.to_string(),
+            proof_kind,
+            proof_data: ProofData::SP1(SP1Proof {
+                version: 2,
+                proof: vec![7; 8],
+                public_value,
+                verification_key: [4; 32],
+            }),
+        }
+    }
+
+    /// A consensus proof of `start_slot..=end_slot` monitoring `accounts`
+    fn consensus(identifier: &str, start_slot: u64, end_slot: u64, accounts: &[[u8; 32]]) -> ZkProof {
+        let commitments = PublicCommitments {
+            start_slot,
+            end_slot,
+            epoch: 0,
+            original_bank_hash: [1; 32],
+            last_bank_hash: [2; 32],
+            account_data_hash: [3; 32],
+            hash_root_valset: [0; 32],
+            total_active_stake: 0,
+            validator_count: 0,
+            monitored_accounts_state: accounts
+                .iter()
+                .map(|&account_pubkey| AccountStateCommitment {
+                    account_pubkey,
+                    last_change_slot: start_slot,
+                    account_data_hash: [5; 32],
+                    lamports: 1,
+                    owner: [0; 32],
+                    executable: false,
+                    rent_epoch: 0,
+                    data_len: 0,
+                    data: Vec::new(),
+                })
+                .collect(),
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        };
+        let public_value = bincode::serialize(&commitments).unwrap();
+        with_public_value(identifier, ProofKind::SolanaConsensusProof, public_value)
+    }
+
+    fn execution(identifier: &str, index: u64) -> ZkProof {
+        with_public_value(identifier, ProofKind::ExecutionProof(index), vec![1, 2, 3])
+    }
+
+    #[test]
+    fn an_empty_filter_matches_everything_without_decoding() {
+        let filter = ProofFilter::default();
+        assert!(filter.is_empty() && !filter.needs_commitments());
+        assert_eq!(filter.apply(&consensus("a", 10, 20, &[])), FilterOutcome::Matched);
+        assert_eq!(filter.apply(&execution("b", 3)), FilterOutcome::Matched);
+        let garbage = with_public_value("c", ProofKind::SolanaConsensusProof, vec![0xff]);
+        assert_eq!(filter.apply(&garbage), FilterOutcome::Matched);
+    }
+
+    #[test]
+    fn slot_bounds_are_inclusive() {
+        let filter = ProofFilter { start_slot: Some(100), end_slot: Some(200), ..ProofFilter::default() };
+        for (start, end, expected) in [
+            (100, 200, FilterOutcome::Matched),
+            (120, 180, FilterOutcome::Matched),
+            (99, 150, FilterOutcome::Skipped),
+            (150, 201, FilterOutcome::Skipped),
+            (50, 250, FilterOutcome::Skipped),
+        ] {
+            assert_eq!(filter.apply(&consensus("a", start, end, &[])), expected, "{}..={}", start, end);
+        }
+
+        let from = ProofFilter { start_slot: Some(100), ..ProofFilter::default() };
+        assert_eq!(from.apply(&consensus("a", 100, u64::MAX, &[])), FilterOutcome::Matched);
+        let until = ProofFilter { end_slot: Some(200), ..ProofFilter::default() };
+        assert_eq!(until.apply(&consensus("a", 0, 200, &[])), FilterOutcome::Matched);
+    }
+
+    #[test]
+    fn account_matches_any_monitored_account() {
+        let filter = ProofFilter { account: Some(WATCHED), ..ProofFilter::default() };
+        assert_eq!(filter.apply(&consensus("a", 1, 2, &[[1; 32], WATCHED])), FilterOutcome::Matched);
+        assert_eq!(filter.apply(&consensus("a", 1, 2, &[[1; 32]])), FilterOutcome::Skipped);
+        assert_eq!(filter.apply(&consensus("a", 1, 2, &[])), FilterOutcome::Skipped);
+    }
+
+    #[test]
+    fn identifier_prefix_is_case_sensitive() {
+        let filter = ProofFilter { identifier_prefix: Some("mainnet-".to_string()), ..ProofFilter::default() };
+        assert_eq!(filter.apply(&consensus("mainnet-100-200", 100, 200, &[])), FilterOutcome::Matched);
+        assert_eq!(filter.apply(&consensus("Mainnet-100-200", 100, 200, &[])), FilterOutcome::Skipped);
+        assert_eq!(filter.apply(&consensus("devnet-100-200", 100, 200, &[])), FilterOutcome::Skipped);
+    }
+
+    #[test]
+    fn kind_selects_execution_or_consensus_proofs() {
+        assert_eq!("CONSENSUS".parse::<KindFilter>().unwrap(), KindFilter::Consensus);
+        assert!("groth16".parse::<KindFilter>().is_err());
+
+        let (consensus, execution) = (consensus("a", 1, 2, &[]), execution("b", 7));
+        assert_eq!(execution_index(&execution), Some(7));
+        assert_eq!((kind_label(&consensus), kind_label(&execution)), ("consensus", "execution"));
+        for (kind, consensus_outcome, execution_outcome) in [
+            (KindFilter::All, FilterOutcome::Matched, FilterOutcome::Matched),
+            (KindFilter::Consensus, FilterOutcome::Matched, FilterOutcome::Skipped),
+            (KindFilter::Execution, FilterOutcome::Skipped, FilterOutcome::Matched),
+        ] {
+            let filter = ProofFilter { kind, ..ProofFilter::default() };
+            assert_eq!(filter.apply(&consensus), consensus_outcome, "{}", kind);
+            assert_eq!(filter.apply(&execution), execution_outcome, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn undecodable_public_values_are_reported_only_when_needed() {
+        let garbage = with_public_value("a", ProofKind::SolanaConsensusProof, vec![0xff; 3]);
+        assert!(decode_commitments(&garbage).is_none());
+        let by_slot = ProofFilter { start_slot: Some(1), ..ProofFilter::default() };
+        assert_eq!(by_slot.apply(&garbage), FilterOutcome::Undecodable);
+        let by_account = ProofFilter { account: Some(WATCHED), ..ProofFilter::default() };
+        assert_eq!(by_account.apply(&garbage), FilterOutcome::Undecodable);
+
+        // Cheaper criteria are checked first, and execution proofs are never decoded
+        let by_prefix = ProofFilter { identifier_prefix: Some("b".to_string()), ..by_slot.clone() };
+        assert_eq!(by_prefix.apply(&garbage), FilterOutcome::Skipped);
+        assert!(decode_commitments(&execution("b", 1)).is_none());
+        assert_eq!(by_slot.apply(&execution("b", 1)), FilterOutcome::Skipped);
+    }
+}
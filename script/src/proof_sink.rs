@@ -0,0 +1,402 @@
+//! Destinations for finished proofs
+//!
+//! [`ProofSink`] is what the prover hands a proof to. [`ProofPublisher`]
+//! publishes it to Kafka; [`DirSink`] writes it to a directory (`--sink file`),
+//! [`ArchiveSink`](crate::s3_archive::ArchiveSink) uploads it to S3 and
+//! [`MemorySink`] keeps it in memory, with injectable failures, for tests
+//! of code that publishes proofs. Kafka and the memory sink also carry
+//! envelope metadata, such as the raw account data left out of the public
+//! values. Sinks report failures as [`KafkaPublishError`] so callers handle
+//! every sink the same way.
+
+use crate::artifacts::{json_len, write_json};
+use crate::envelope::EnvelopeWithMetadata;
+use crate::kafka::{check_message_size, KafkaPublishError, ProofPublisher};
+use crate::proof_filter::decode_commitments;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
+use twine_types::proofs::ZkProof;
+
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = Result<SinkReceipt, KafkaPublishError>> + Send + 'a>>;
+
+/// Something proofs can be published to
+pub trait ProofSink: Send + Sync {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a>;
+
+    /// Publish a proof with envelope metadata, e.g. the raw account data it does not commit
+    ///
+    /// Sinks without a place for the metadata publish the proof alone.
+    fn publish_with_metadata<'a>(&'a self, proof: &'a ZkProof, _metadata: &'a Value) -> SinkFuture<'a> {
+        self.publish(proof)
+    }
+}
+
+/// Where a sink stored a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkReceipt {
+    Kafka { partition: i32, offset: i64 },
+    File(PathBuf),
+    /// URI of an object in object storage
+    Object(String),
+    /// Position in a [`MemorySink`]
+    Memory(usize),
+}
+
+impl fmt::Display for SinkReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkReceipt::Kafka { partition, offset } => write!(f, "partition {} offset {}", partition, offset),
+            SinkReceipt::File(path) => write!(f, "{}", path.display()),
+            SinkReceipt::Object(uri) => write!(f, "{}", uri),
+            SinkReceipt::Memory(index) => write!(f, "memory #{}", index),
+        }
+    }
+}
+
+/// Sink selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SinkKind {
+    #[default]
+    Kafka,
+    File,
+}
+
+impl FromStr for SinkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kafka" => Ok(SinkKind::Kafka),
+            "file" => Ok(SinkKind::File),
+            _ => Err(format!("Invalid sink: {} (expected kafka or file)", s)),
+        }
+    }
+}
+
+impl fmt::Display for SinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkKind::Kafka => write!(f, "kafka"),
+            SinkKind::File => write!(f, "file"),
+        }
+    }
+}
+
+impl ProofSink for ProofPublisher {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let (partition, offset) = self.publish_proof(proof).await?;
+            Ok(SinkReceipt::Kafka { partition, offset })
+        })
+    }
+
+    fn publish_with_metadata<'a>(&'a self, proof: &'a ZkProof, metadata: &'a Value) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let (partition, offset) = self.publish_proof_with_metadata(proof, None, metadata).await?;
+            Ok(SinkReceipt::Kafka { partition, offset })
+        })
+    }
+}
+
+/// Writes each proof as `<identifier>.json` in a directory
+///
+/// Files are written to a temporary name, fsynced and renamed, so a reader
+/// never sees a partial proof. A proof with an identifier that was already
+/// written replaces the earlier file.
+#[derive(Debug, Clone)]
+pub struct DirSink {
+    dir: PathBuf,
+}
+
+impl DirSink {
+    /// Sink writing to `dir`, which is created if missing
+    pub fn new(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    /// File a proof with this identifier is written to
+    pub fn path_for(&self, identifier: &str) -> PathBuf {
+        let name: String = identifier
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", name.trim_start_matches('.')))
+    }
+
+    fn write(&self, proof: &ZkProof) -> Result<PathBuf, KafkaPublishError> {
+        let path = self.path_for(&proof.identifier);
+        // Streamed, so a large proof is never held as a JSON string
+        let write = || -> std::io::Result<()> {
+            write_json(&path, proof, false)?;
+            File::open(&self.dir)?.sync_all()
+        };
+        write().map_err(|e| KafkaPublishError::Fatal(format!("Failed to write {}: {}", path.display(), e).into()))?;
+        Ok(path)
+    }
+}
+
+impl ProofSink for DirSink {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+        // Proofs are small enough that blocking briefly on the write is fine
+        Box::pin(async move { self.write(proof).map(SinkReceipt::File) })
+    }
+}
+
+#[derive(Default)]
+struct MemoryState {
+    proofs: Vec<ZkProof>,
+    /// Metadata each proof was published with, by index
+    metadata: Vec<Option<Value>>,
+    attempts: usize,
+    fail_next: usize,
+    max_message_bytes: Option<usize>,
+}
+
+/// Keeps published proofs in memory, for tests
+///
+/// Failures can be injected: [`fail_next`](Self::fail_next) makes the next
+/// publishes fail like an unreachable broker, and
+/// [`set_max_message_bytes`](Self::set_max_message_bytes) rejects proofs whose
+/// JSON envelope, metadata included, is larger, like the producer's size check.
+#[derive(Default)]
+pub struct MemorySink {
+    state: Mutex<MemoryState>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the next `count` publishes with a retryable transport error
+    pub fn fail_next(&self, count: usize) {
+        self.lock().fail_next = count;
+    }
+
+    /// Reject proofs whose JSON is larger than `limit` bytes as oversized
+    pub fn set_max_message_bytes(&self, limit: usize) {
+        self.lock().max_message_bytes = Some(limit);
+    }
+
+    /// Proofs published so far, in order
+    pub fn proofs(&self) -> Vec<ZkProof> {
+        self.lock().proofs.clone()
+    }
+
+    /// Metadata the proofs were published with, in order; `None` for a proof published alone
+    pub fn metadata(&self) -> Vec<Option<Value>> {
+        self.lock().metadata.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Publishes attempted, including failed ones
+    pub fn attempts(&self) -> usize {
+        self.lock().attempts
+    }
+
+    /// Assert that a proof committing to `start_slot..end_slot` was published
+    ///
+    /// # Panics
+    ///
+    /// If no published proof has exactly this slot range.
+    pub fn assert_published_range(&self, start_slot: u64, end_slot: u64) {
+        let ranges: Vec<(u64, u64)> = self
+            .proofs()
+            .iter()
+            .filter_map(decode_commitments)
+            .map(|commitments| (commitments.start_slot, commitments.end_slot))
+            .collect();
+        assert!(
+            ranges.contains(&(start_slot, end_slot)),
+            "no proof for slots {}..{} was published (published ranges: {:?})",
+            start_slot,
+            end_slot,
+            ranges
+        );
+    }
+
+    /// Assert that no identifier was published twice
+    ///
+    /// # Panics
+    ///
+    /// On the first identifier published more than once.
+    pub fn assert_identifier_unique(&self) {
+        let mut seen = HashSet::new();
+        for proof in self.proofs() {
+            assert!(
+                seen.insert(proof.identifier.clone()),
+                "identifier {} was published more than once",
+                proof.identifier
+            );
+        }
+    }
+
+    fn record(&self, proof: &ZkProof, metadata: Option<&Value>) -> Result<SinkReceipt, KafkaPublishError> {
+        let mut state = self.lock();
+        state.attempts += 1;
+        if state.fail_next > 0 {
+            state.fail_next -= 1;
+            return Err(KafkaPublishError::Retryable(KafkaError::MessageProduction(
+                RDKafkaErrorCode::BrokerTransportFailure,
+            )));
+        }
+        if let Some(limit) = state.max_message_bytes {
+            let size = match metadata {
+                Some(metadata) => json_len(&EnvelopeWithMetadata { proof, metadata }),
+                None => json_len(proof),
+            };
+            check_message_size(size.map_err(|e| KafkaPublishError::Fatal(Box::new(e)))? as usize, limit)?;
+        }
+        state.proofs.push(proof.clone());
+        state.metadata.push(metadata.cloned());
+        Ok(SinkReceipt::Memory(state.proofs.len() - 1))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ProofSink for MemorySink {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+        Box::pin(async move { self.record(proof, None) })
+    }
+
+    fn publish_with_metadata<'a>(&'a self, proof: &'a ZkProof, metadata: &'a Value) -> SinkFuture<'a> {
+        Box::pin(async move { self.record(proof, Some(metadata)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_data::{self, RawAccountData};
+    use crate::pipeline::envelope;
+    use crate::prove_jobs::ProofMode;
+    use solana_stub_prover_lib::{PublicCommitments, NOT_AGGREGATED};
+
+    fn proof(identifier: &str, start_slot: u64, end_slot: u64) -> ZkProof {
+        let commitments = PublicCommitments {
+            start_slot,
+            end_slot,
+            epoch: 0,
+            original_bank_hash: [1; 32],
+            last_bank_hash: [2; 32],
+            account_data_hash: [3; 32],
+            hash_root_valset: [0; 32],
+            total_active_stake: 0,
+            validator_count: 0,
+            monitored_accounts_state: Vec::new(),
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        };
+        let public_value = bincode::serialize(&commitments).unwrap();
+        envelope(identifier.to_string(), ProofMode::Groth16, vec![7; 256], public_value, [4; 32])
+    }
+
+    fn metadata() -> Value {
+        account_data::metadata(&[RawAccountData {
+            account_pubkey: "11111111111111111111111111111111".into(),
+            data: "c3R1Yg==".into(),
+        }])
+    }
+
+    #[tokio::test]
+    async fn fails_the_next_publishes_then_recovers() {
+        let sink = MemorySink::new();
+        sink.fail_next(2);
+        for _ in 0..2 {
+            let error = sink.publish(&proof("a", 1, 2)).await.unwrap_err();
+            assert!(error.is_retryable(), "{}", error);
+        }
+        assert!(sink.is_empty());
+        assert_eq!(sink.publish(&proof("a", 1, 2)).await.unwrap(), SinkReceipt::Memory(0));
+        assert_eq!(sink.publish(&proof("b", 2, 3)).await.unwrap(), SinkReceipt::Memory(1));
+        assert_eq!((sink.attempts(), sink.len()), (4, 2));
+    }
+
+    #[tokio::test]
+    async fn rejects_proofs_over_the_size_limit() {
+        let sink = MemorySink::new();
+        let small = proof("small", 1, 2);
+        let size = json_len(&small).unwrap() as usize;
+        sink.set_max_message_bytes(size);
+        sink.publish(&small).await.unwrap();
+
+        let large = ZkProof { identifier: "larger".into(), ..small.clone() };
+        match sink.publish(&large).await {
+            Err(KafkaPublishError::Oversized { size: rejected, limit }) => {
+                assert_eq!((rejected, limit), (size + 1, size))
+            }
+            other => panic!("expected an oversized error, got {:?}", other),
+        }
+        assert_eq!((sink.attempts(), sink.len()), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn metadata_counts_against_the_limit() {
+        let sink = MemorySink::new();
+        let proof = proof("a", 1, 2);
+        sink.set_max_message_bytes(json_len(&proof).unwrap() as usize);
+        sink.publish(&proof).await.unwrap();
+        let error = sink.publish_with_metadata(&proof, &metadata()).await.unwrap_err();
+        assert!(matches!(error, KafkaPublishError::Oversized { .. }), "{}", error);
+        assert_eq!(sink.metadata(), [None]);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_metadata_of_each_proof() {
+        let sink = MemorySink::new();
+        sink.publish(&proof("a", 1, 2)).await.unwrap();
+        sink.publish_with_metadata(&proof("b", 2, 3), &metadata()).await.unwrap();
+        assert_eq!(sink.metadata(), [None, Some(metadata())]);
+    }
+
+    #[tokio::test]
+    async fn asserts_ranges_and_identifiers() {
+        let sink = MemorySink::new();
+        sink.publish(&proof("a", 1, 2)).await.unwrap();
+        sink.publish(&proof("b", 2, 3)).await.unwrap();
+        sink.assert_published_range(2, 3);
+        sink.assert_identifier_unique();
+
+        let missing = std::panic::catch_unwind(|| sink.assert_published_range(1, 3));
+        assert!(missing.is_err());
+        sink.publish(&proof("a", 3, 4)).await.unwrap();
+        let duplicate = std::panic::catch_unwind(|| sink.assert_identifier_unique());
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn dir_sink_replaces_proofs_by_identifier() {
+        let dir = std::env::temp_dir().join(format!("dir-sink-{}", std::process::id()));
+        let sink = DirSink::new(&dir).unwrap();
+        let first = proof("../range 1-2", 1, 2);
+        let receipt = sink.publish(&first).await.unwrap();
+        let path = dir.join("_range_1-2.json");
+        assert_eq!(receipt, SinkReceipt::File(path.clone()));
+
+        let second = proof("../range 1-2", 1, 3);
+        sink.publish(&second).await.unwrap();
+        let written: ZkProof = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(written).unwrap(), serde_json::to_value(second).unwrap());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
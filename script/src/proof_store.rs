@@ -0,0 +1,178 @@
+//! Read access to the database written by the SQLite sink
+//!
+//! Queries open their own read-only connection, so a store can be shared
+//! between request handlers while the consumer keeps writing (the sink uses
+//! WAL mode). Requires the `sqlite` feature.
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Most proofs a range query returns
+pub const MAX_RANGE_RESULTS: usize = 1000;
+
+/// A stored proof with its account commitments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoredProof {
+    pub identifier: String,
+    pub topic: String,
+    pub partition: i64,
+    pub offset: i64,
+    pub proof_kind: String,
+    pub version: i64,
+    pub verification_key: String,
+    pub start_slot: Option<i64>,
+    pub end_slot: Option<i64>,
+    pub epoch: Option<i64>,
+    pub original_bank_hash: Option<String>,
+    pub last_bank_hash: Option<String>,
+    pub account_data_hash: Option<String>,
+    pub hash_root_valset: Option<String>,
+    pub total_active_stake: Option<i64>,
+    pub validator_count: Option<i64>,
+    pub validations_passed: Option<i64>,
+    /// When the consumer stored the proof (RFC 3339)
+    pub received_at: String,
+    pub accounts: Vec<StoredAccount>,
+}
+
+/// Commitment to one monitored account's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoredAccount {
+    pub account_pubkey: String,
+    pub last_change_slot: i64,
+    pub account_data_hash: String,
+    pub lamports: i64,
+    pub owner: String,
+    pub executable: bool,
+    /// `-1` for rent-exempt accounts
+    pub rent_epoch: i64,
+    pub data_len: i64,
+}
+
+const PROOF_COLUMNS: &str = "identifier, topic, kafka_partition, kafka_offset, proof_kind, version, verification_key,
+    start_slot, end_slot, epoch, original_bank_hash, last_bank_hash, account_data_hash, hash_root_valset,
+    total_active_stake, validator_count, validations_passed, received_at";
+
+/// Database written by `consumer --sqlite`
+#[derive(Debug, Clone)]
+pub struct ProofStore {
+    path: PathBuf,
+}
+
+impl ProofStore {
+    /// Store reading the database at `path`, which must exist
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let store = Self { path: path.to_path_buf() };
+        store.connect()?;
+        Ok(store)
+    }
+
+    /// The proof with the highest end slot, optionally only among proofs committing to `account`
+    pub fn latest(&self, account: Option<&str>) -> rusqlite::Result<Option<StoredProof>> {
+        let connection = self.connect()?;
+        let query = match account {
+            Some(_) => format!(
+                "SELECT {} FROM proofs WHERE identifier IN
+                    (SELECT identifier FROM accounts WHERE account_pubkey = ?1)
+                ORDER BY end_slot DESC, received_at DESC LIMIT 1",
+                PROOF_COLUMNS
+            ),
+            None => format!("SELECT {} FROM proofs ORDER BY end_slot DESC, received_at DESC LIMIT 1", PROOF_COLUMNS),
+        };
+        let mut statement = connection.prepare(&query)?;
+        let proof = match account {
+            Some(account) => statement.query_row(params![account], read_proof).optional()?,
+            None => statement.query_row([], read_proof).optional()?,
+        };
+        proof.map(|proof| with_accounts(&connection, proof)).transpose()
+    }
+
+    /// The proof with this identifier
+    pub fn proof(&self, identifier: &str) -> rusqlite::Result<Option<StoredProof>> {
+        let connection = self.connect()?;
+        let proof = connection
+            .query_row(
+                &format!("SELECT {} FROM proofs WHERE identifier = ?1", PROOF_COLUMNS),
+                params![identifier],
+                read_proof,
+            )
+            .optional()?;
+        proof.map(|proof| with_accounts(&connection, proof)).transpose()
+    }
+
+    /// Proofs whose slot range overlaps `start_slot..=end_slot`, by end slot, at most [`MAX_RANGE_RESULTS`]
+    pub fn in_range(&self, start_slot: u64, end_slot: u64) -> rusqlite::Result<Vec<StoredProof>> {
+        let connection = self.connect()?;
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM proofs WHERE end_slot >= ?1 AND start_slot <= ?2 ORDER BY end_slot, identifier LIMIT ?3",
+            PROOF_COLUMNS
+        ))?;
+        let proofs = statement
+            .query_map(
+                params![start_slot as i64, end_slot as i64, MAX_RANGE_RESULTS as i64],
+                read_proof,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        proofs.into_iter().map(|proof| with_accounts(&connection, proof)).collect()
+    }
+
+    /// Every stored proof, by start slot
+    pub fn all(&self) -> rusqlite::Result<Vec<StoredProof>> {
+        let connection = self.connect()?;
+        let mut statement = connection
+            .prepare(&format!("SELECT {} FROM proofs ORDER BY start_slot, end_slot, identifier", PROOF_COLUMNS))?;
+        let proofs = statement.query_map([], read_proof)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        proofs.into_iter().map(|proof| with_accounts(&connection, proof)).collect()
+    }
+
+    fn connect(&self) -> rusqlite::Result<Connection> {
+        Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+    }
+}
+
+fn read_proof(row: &Row<'_>) -> rusqlite::Result<StoredProof> {
+    Ok(StoredProof {
+        identifier: row.get(0)?,
+        topic: row.get(1)?,
+        partition: row.get(2)?,
+        offset: row.get(3)?,
+        proof_kind: row.get(4)?,
+        version: row.get(5)?,
+        verification_key: row.get(6)?,
+        start_slot: row.get(7)?,
+        end_slot: row.get(8)?,
+        epoch: row.get(9)?,
+        original_bank_hash: row.get(10)?,
+        last_bank_hash: row.get(11)?,
+        account_data_hash: row.get(12)?,
+        hash_root_valset: row.get(13)?,
+        total_active_stake: row.get(14)?,
+        validator_count: row.get(15)?,
+        validations_passed: row.get(16)?,
+        received_at: row.get(17)?,
+        accounts: Vec::new(),
+    })
+}
+
+fn with_accounts(connection: &Connection, mut proof: StoredProof) -> rusqlite::Result<StoredProof> {
+    let mut statement = connection.prepare_cached(
+        "SELECT account_pubkey, last_change_slot, account_data_hash, lamports, owner, executable, rent_epoch, data_len
+        FROM accounts WHERE identifier = ?1 ORDER BY account_pubkey",
+    )?;
+    proof.accounts = statement
+        .query_map(params![proof.identifier], |row| {
+            Ok(StoredAccount {
+                account_pubkey: row.get(0)?,
+                last_change_slot: row.get(1)?,
+                account_data_hash: row.get(2)?,
+                lamports: row.get(3)?,
+                owner: row.get(4)?,
+                executable: row.get(5)?,
+                rent_epoch: row.get(6)?,
+                data_len: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(proof)
+}
@@ -0,0 +1,298 @@
+//! Prove jobs submitted over HTTP
+//!
+//! Jobs run one at a time, in submission order, on a single task; proving
+//! uses every core, so running two at once would only slow both down. The
+//! queue is bounded and submitting to a full queue fails instead of waiting.
+//! Job status is kept in memory for as long as the server runs.
+//!
+//! [`ProverCommand`] runs each job with the prover binary, so jobs go
+//! through the same proving, checkpointing and publishing as a manual run.
+
+use crate::utils::base58_to_bytes32;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Default number of jobs waiting to run
+pub const DEFAULT_MAX_QUEUED_JOBS: usize = 16;
+
+/// Lines of the prover's output kept in a failed job's error
+const ERROR_TAIL_LINES: usize = 10;
+
+/// Kind of proof a job generates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMode {
+    /// Groth16, verifiable on-chain
+    #[default]
+    Groth16,
+    /// Compressed only, faster
+    Compressed,
+}
+
+impl FromStr for ProofMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "groth16" => Ok(ProofMode::Groth16),
+            "compressed" => Ok(ProofMode::Compressed),
+            other => Err(format!("Unknown proof mode '{}' (expected groth16 or compressed)", other)),
+        }
+    }
+}
+
+impl fmt::Display for ProofMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofMode::Groth16 => write!(f, "groth16"),
+            ProofMode::Compressed => write!(f, "compressed"),
+        }
+    }
+}
+
+/// What to prove
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProveRequest {
+    /// Base58 pubkey of the account to monitor
+    pub account: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    #[serde(default)]
+    pub mode: ProofMode,
+}
+
+impl ProveRequest {
+    /// Reject requests the prover would refuse
+    pub fn validate(&self) -> Result<(), String> {
+        base58_to_bytes32(&self.account).map_err(|e| format!("Invalid account '{}': {}", self.account, e))?;
+        if self.end_slot <= self.start_slot {
+            return Err("end_slot must be greater than start_slot".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "queued"),
+            JobState::Running => write!(f, "running"),
+            JobState::Succeeded => write!(f, "succeeded"),
+            JobState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A submitted job and its progress
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub request: ProveRequest,
+    pub state: JobState,
+    /// RFC 3339 times
+    pub submitted_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Why the job failed
+    pub error: Option<String>,
+}
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type Runner = Arc<dyn Fn(ProveRequest) -> JobFuture + Send + Sync>;
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+/// Bounded queue of prove jobs with a single worker
+pub struct JobQueue {
+    jobs: Arc<Mutex<Jobs>>,
+    sender: Mutex<Option<mpsc::Sender<u64>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl JobQueue {
+    /// Queue running `runner` on each job, with at most `capacity` jobs waiting
+    pub fn new<F, Fut>(capacity: usize, runner: F) -> Self
+    where
+        F: Fn(ProveRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let runner: Runner = Arc::new(move |request| Box::pin(runner(request)) as JobFuture);
+        let jobs = Arc::new(Mutex::new(Jobs::default()));
+        let (sender, mut receiver) = mpsc::channel::<u64>(capacity.max(1));
+        let worker_jobs = Arc::clone(&jobs);
+        let worker = tokio::spawn(async move {
+            while let Some(id) = receiver.recv().await {
+                let request = {
+                    let mut jobs = lock(&worker_jobs);
+                    match jobs.jobs.get_mut(&id) {
+                        // Jobs failed by a shutdown are still in the channel
+                        Some(job) if job.state == JobState::Queued => {
+                            job.state = JobState::Running;
+                            job.started_at = Some(Utc::now().to_rfc3339());
+                            job.request.clone()
+                        }
+                        _ => continue,
+                    }
+                };
+                let result = runner(request).await;
+                if let Some(job) = lock(&worker_jobs).jobs.get_mut(&id) {
+                    job.finished_at = Some(Utc::now().to_rfc3339());
+                    match result {
+                        Ok(()) => job.state = JobState::Succeeded,
+                        Err(e) => {
+                            job.state = JobState::Failed;
+                            job.error = Some(e);
+                        }
+                    }
+                }
+            }
+        });
+        Self { jobs, sender: Mutex::new(Some(sender)), worker: Mutex::new(Some(worker)) }
+    }
+
+    /// Validate and queue a request, failing if the queue is full or shut down
+    pub fn submit(&self, request: ProveRequest) -> Result<Job, SubmitError> {
+        request.validate().map_err(SubmitError::Invalid)?;
+        let sender = lock(&self.sender).clone().ok_or(SubmitError::ShuttingDown)?;
+        // Reserve the slot first, so a full queue does not leave a job behind that never runs
+        let permit = sender.try_reserve().map_err(|e| match e {
+            mpsc::error::TrySendError::Full(()) => SubmitError::QueueFull,
+            mpsc::error::TrySendError::Closed(()) => SubmitError::ShuttingDown,
+        })?;
+        let mut jobs = lock(&self.jobs);
+        jobs.next_id += 1;
+        let job = Job {
+            id: jobs.next_id,
+            request,
+            state: JobState::Queued,
+            submitted_at: Utc::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+        };
+        jobs.jobs.insert(job.id, job.clone());
+        permit.send(job.id);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        lock(&self.jobs).jobs.get(&id).cloned()
+    }
+
+    /// Every job, oldest first
+    pub fn list(&self) -> Vec<Job> {
+        lock(&self.jobs).jobs.values().cloned().collect()
+    }
+
+    /// Stop accepting jobs, fail the queued ones and wait for the running one to finish
+    pub async fn shutdown(&self) {
+        lock(&self.sender).take();
+        {
+            let mut jobs = lock(&self.jobs);
+            for job in jobs.jobs.values_mut().filter(|job| job.state == JobState::Queued) {
+                job.state = JobState::Failed;
+                job.error = Some("the server shut down before the job started".to_string());
+                job.finished_at = Some(Utc::now().to_rfc3339());
+            }
+        }
+        let worker = lock(&self.worker).take();
+        if let Some(worker) = worker {
+            let _ = worker.await;
+        }
+    }
+}
+
+/// Why a job was not queued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitError {
+    Invalid(String),
+    QueueFull,
+    ShuttingDown,
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Invalid(reason) => write!(f, "{}", reason),
+            SubmitError::QueueFull => write!(f, "the job queue is full, try again later"),
+            SubmitError::ShuttingDown => write!(f, "the server is shutting down"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+/// Runs jobs with the prover binary
+#[derive(Debug, Clone)]
+pub struct ProverCommand {
+    pub binary: PathBuf,
+    /// Passed before each job's own arguments, e.g. the Kafka connection flags
+    pub args: Vec<String>,
+}
+
+impl ProverCommand {
+    /// Prove and publish the request like `solana-stub-prover --prove` would
+    ///
+    /// The prover's standard output goes to the server's; its error output is
+    /// printed and kept in the error if it fails.
+    pub async fn run(&self, request: ProveRequest) -> Result<(), String> {
+        let mut command = Command::new(&self.binary);
+        command
+            .args(&self.args)
+            .arg("--prove")
+            .arg("--account")
+            .arg(&request.account)
+            .arg("--start-slot")
+            .arg(request.start_slot.to_string())
+            .arg("--end-slot")
+            .arg(request.end_slot.to_string())
+            .stderr(Stdio::piped());
+        if request.mode == ProofMode::Compressed {
+            command.arg("--compressed-only");
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to start the prover {}: {}", self.binary.display(), e))?;
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr).await;
+        }
+        let status = child.wait().await.map_err(|e| e.to_string())?;
+        if status.success() {
+            return Ok(());
+        }
+        eprint!("{}", stderr);
+        let lines: Vec<&str> = stderr.lines().collect();
+        let tail = lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n");
+        Err(format!("the prover exited with {}: {}", status, tail))
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
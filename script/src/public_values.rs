@@ -0,0 +1,455 @@
+//! Decoding of committed public values
+//!
+//! The zkVM program commits `PublicCommitments` with bincode. The same struct
+//! is also accepted ABI-encoded, as `abi.encode(commitments)` of:
+//!
+//! ```solidity
+//! struct AccountStateCommitment {
+//!     bytes32 accountPubkey; uint64 lastChangeSlot; bytes32 accountDataHash; uint64 lamports;
+//!     bytes32 owner; bool executable; uint64 rentEpoch; uint64 dataLen; bytes data;
+//! }
+//! struct PublicCommitments {
+//!     uint64 startSlot; uint64 endSlot; uint64 epoch; bytes32 originalBankHash; bytes32 lastBankHash;
+//!     bytes32 accountDataHash; bytes32 hashRootValset; uint64 totalActiveStake; uint32 validatorCount;
+//!     AccountStateCommitment[] monitoredAccountsState; bool validationsPassed; bytes32 aggregatedVkeyDigest;
+//! }
+//! ```
+//!
+//! Decoding failures report the byte offset they happened at. Decoded values
+//! can be flattened into named fields, which is also how two sets of public
+//! values are compared, and re-encoded in the ABI layout for EVM contracts.
+
+use crate::presenter::rent_epoch;
+use crate::utils::bytes32_to_base58;
+use bincode::Options;
+use solana_stub_prover_lib::{AccountStateCommitment, PublicCommitments};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+const WORD: usize = 32;
+
+/// How public values were encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicValuesEncoding {
+    Bincode,
+    Abi,
+}
+
+impl fmt::Display for PublicValuesEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublicValuesEncoding::Bincode => write!(f, "bincode"),
+            PublicValuesEncoding::Abi => write!(f, "ABI"),
+        }
+    }
+}
+
+impl FromStr for PublicValuesEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bincode" => Ok(PublicValuesEncoding::Bincode),
+            "abi" => Ok(PublicValuesEncoding::Abi),
+            other => Err(format!("Unknown public values encoding '{}' (expected bincode or abi)", other)),
+        }
+    }
+}
+
+/// Why public values did not decode, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// Byte offset of the value that failed to decode
+    pub offset: usize,
+    pub reason: String,
+}
+
+impl DecodeError {
+    fn new(offset: usize, reason: impl Into<String>) -> Self {
+        Self { offset, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.offset, self.reason)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode public values as bincode, or failing that as ABI
+pub fn decode(bytes: &[u8]) -> Result<(PublicCommitments, PublicValuesEncoding), String> {
+    let bincode_error = match decode_bincode(bytes) {
+        Ok(commitments) => return Ok((commitments, PublicValuesEncoding::Bincode)),
+        Err(e) => e,
+    };
+    match decode_abi(bytes) {
+        Ok(commitments) => Ok((commitments, PublicValuesEncoding::Abi)),
+        Err(abi_error) => Err(format!("not PublicCommitments in bincode ({}) or ABI ({})", bincode_error, abi_error)),
+    }
+}
+
+/// Decode public values in the given encoding only
+pub fn decode_as(bytes: &[u8], encoding: PublicValuesEncoding) -> Result<PublicCommitments, String> {
+    let result = match encoding {
+        PublicValuesEncoding::Bincode => decode_bincode(bytes),
+        PublicValuesEncoding::Abi => decode_abi(bytes),
+    };
+    result.map_err(|e| format!("not PublicCommitments in {} ({})", encoding, e))
+}
+
+/// Decode public values as committed by the zkVM program
+pub fn decode_bincode(bytes: &[u8]) -> Result<PublicCommitments, DecodeError> {
+    // Same encoding as bincode::deserialize; the limit stops bogus lengths from allocating
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64);
+    let mut rest = bytes;
+    let result = options.deserialize_from::<_, PublicCommitments>(&mut rest);
+    let offset = bytes.len() - rest.len();
+    match result {
+        Ok(_) if !rest.is_empty() => Err(DecodeError::new(offset, format!("{} trailing byte(s)", rest.len()))),
+        Ok(commitments) => Ok(commitments),
+        Err(e) => Err(DecodeError::new(offset, e.to_string())),
+    }
+}
+
+/// Decode ABI-encoded public values (see the module docs for the layout)
+pub fn decode_abi(bytes: &[u8]) -> Result<PublicCommitments, DecodeError> {
+    if bytes.len() % WORD != 0 {
+        return Err(DecodeError::new(
+            bytes.len() - bytes.len() % WORD,
+            format!("length {} is not a multiple of 32", bytes.len()),
+        ));
+    }
+    let abi = AbiReader { bytes };
+    // abi.encode of a struct with dynamic members starts with the struct's offset
+    let base = abi.pointer(0, 0, "struct offset")?;
+    let head = |index: usize| base + index * WORD;
+
+    let start_slot = abi.uint(head(0), 64, "start_slot")?;
+    let end_slot = abi.uint(head(1), 64, "end_slot")?;
+    let epoch = abi.uint(head(2), 64, "epoch")?;
+    let original_bank_hash = abi.bytes32(head(3))?;
+    let last_bank_hash = abi.bytes32(head(4))?;
+    let account_data_hash = abi.bytes32(head(5))?;
+    let hash_root_valset = abi.bytes32(head(6))?;
+    let total_active_stake = abi.uint(head(7), 64, "total_active_stake")?;
+    let validator_count = abi.uint(head(8), 32, "validator_count")? as u32;
+    let accounts_at = abi.pointer(base, head(9), "monitored_accounts_state offset")?;
+    let validations_passed = abi.bool(head(10), "validations_passed")?;
+    let aggregated_vkey_digest = abi.bytes32(head(11))?;
+
+    let count = abi.uint(accounts_at, 64, "monitored_accounts_state length")? as usize;
+    let heads = accounts_at + WORD;
+    if count > (bytes.len() - heads) / WORD {
+        return Err(DecodeError::new(
+            accounts_at,
+            format!("{} accounts do not fit in the remaining {} bytes", count, bytes.len() - heads),
+        ));
+    }
+    let mut monitored_accounts_state = Vec::with_capacity(count);
+    for i in 0..count {
+        let at = abi.pointer(heads, heads + i * WORD, "account offset")?;
+        monitored_accounts_state.push(abi.account(at)?);
+    }
+
+    Ok(PublicCommitments {
+        start_slot,
+        end_slot,
+        epoch,
+        original_bank_hash,
+        last_bank_hash,
+        account_data_hash,
+        hash_root_valset,
+        total_active_stake,
+        validator_count,
+        monitored_accounts_state,
+        validations_passed,
+        aggregated_vkey_digest,
+    })
+}
+
+/// `abi.encode(commitments)` in the layout of the module docs, the inverse of [`decode_abi`]
+pub fn encode_abi(commitments: &PublicCommitments) -> Vec<u8> {
+    let accounts: Vec<Vec<u8>> = commitments.monitored_accounts_state.iter().map(encode_account).collect();
+    let mut out = Vec::new();
+    // Offset of the struct, which is dynamic because of the accounts
+    out.extend(uint_word(WORD as u64));
+    out.extend(uint_word(commitments.start_slot));
+    out.extend(uint_word(commitments.end_slot));
+    out.extend(uint_word(commitments.epoch));
+    out.extend(commitments.original_bank_hash);
+    out.extend(commitments.last_bank_hash);
+    out.extend(commitments.account_data_hash);
+    out.extend(commitments.hash_root_valset);
+    out.extend(uint_word(commitments.total_active_stake));
+    out.extend(uint_word(u64::from(commitments.validator_count)));
+    // The accounts follow the 12 head words of the struct
+    out.extend(uint_word((12 * WORD) as u64));
+    out.extend(uint_word(u64::from(commitments.validations_passed)));
+    out.extend(commitments.aggregated_vkey_digest);
+
+    out.extend(uint_word(accounts.len() as u64));
+    let mut offset = accounts.len() * WORD;
+    for account in &accounts {
+        out.extend(uint_word(offset as u64));
+        offset += account.len();
+    }
+    for account in accounts {
+        out.extend(account);
+    }
+    out
+}
+
+fn encode_account(account: &AccountStateCommitment) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend(account.account_pubkey);
+    out.extend(uint_word(account.last_change_slot));
+    out.extend(account.account_data_hash);
+    out.extend(uint_word(account.lamports));
+    out.extend(account.owner);
+    out.extend(uint_word(u64::from(account.executable)));
+    out.extend(uint_word(account.rent_epoch));
+    out.extend(uint_word(account.data_len));
+    // The data follows the 9 head words of the account
+    out.extend(uint_word((9 * WORD) as u64));
+    out.extend(uint_word(account.data.len() as u64));
+    out.extend(&account.data);
+    out.resize(out.len().next_multiple_of(WORD), 0);
+    out
+}
+
+/// A uint as a big-endian ABI word
+pub fn uint_word(value: u64) -> [u8; WORD] {
+    let mut word = [0u8; WORD];
+    word[WORD - 8..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Bounds-checked reads of 32-byte ABI words
+struct AbiReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl AbiReader<'_> {
+    fn word(&self, offset: usize) -> Result<&[u8], DecodeError> {
+        offset
+            .checked_add(WORD)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| DecodeError::new(offset, format!("input ends at byte {}", self.bytes.len())))
+    }
+
+    fn uint(&self, offset: usize, bits: usize, field: &str) -> Result<u64, DecodeError> {
+        let word = self.word(offset)?;
+        let (high, low) = word.split_at(WORD - bits / 8);
+        if high.iter().any(|&b| b != 0) {
+            return Err(DecodeError::new(offset, format!("{} does not fit in uint{}", field, bits)));
+        }
+        Ok(low.iter().fold(0u64, |value, &b| (value << 8) | u64::from(b)))
+    }
+
+    fn bool(&self, offset: usize, field: &str) -> Result<bool, DecodeError> {
+        match self.uint(offset, 64, field)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(DecodeError::new(offset, format!("{} is {}, not a bool", field, value))),
+        }
+    }
+
+    fn bytes32(&self, offset: usize) -> Result<[u8; 32], DecodeError> {
+        let mut value = [0u8; 32];
+        value.copy_from_slice(self.word(offset)?);
+        Ok(value)
+    }
+
+    /// Offset stored at `offset`, relative to `base`
+    fn pointer(&self, base: usize, offset: usize, field: &str) -> Result<usize, DecodeError> {
+        let relative = self.uint(offset, 64, field)?;
+        usize::try_from(relative)
+            .ok()
+            .and_then(|relative| base.checked_add(relative))
+            .filter(|&target| target < self.bytes.len())
+            .ok_or_else(|| DecodeError::new(offset, format!("{} {} points past the end of the input", field, relative)))
+    }
+
+    fn account(&self, at: usize) -> Result<AccountStateCommitment, DecodeError> {
+        let head = |index: usize| at + index * WORD;
+        let account_pubkey = self.bytes32(head(0))?;
+        let last_change_slot = self.uint(head(1), 64, "last_change_slot")?;
+        let account_data_hash = self.bytes32(head(2))?;
+        let lamports = self.uint(head(3), 64, "lamports")?;
+        let owner = self.bytes32(head(4))?;
+        let executable = self.bool(head(5), "executable")?;
+        let rent_epoch = self.uint(head(6), 64, "rent_epoch")?;
+        let data_len = self.uint(head(7), 64, "data_len")?;
+        let data_at = self.pointer(at, head(8), "data offset")?;
+
+        let len = self.uint(data_at, 64, "data length")?;
+        let start = data_at + WORD;
+        let data = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(|| DecodeError::new(data_at, format!("{} data bytes run past the end of the input", len)))?;
+
+        Ok(AccountStateCommitment {
+            account_pubkey,
+            last_change_slot,
+            account_data_hash,
+            lamports,
+            owner,
+            executable,
+            rent_epoch,
+            data_len,
+            data: data.to_vec(),
+        })
+    }
+}
+
+/// Every value of `commitments` as `(path, value)`, with byte strings as `0x`-prefixed hex
+///
+/// Paths are the struct's field names; account fields are prefixed with
+/// `monitored_accounts_state[i].`.
+pub fn fields(commitments: &PublicCommitments) -> Vec<(String, String)> {
+    let hex = |bytes: &[u8]| format!("0x{}", hex::encode(bytes));
+    let key = |bytes: &[u8; 32]| format!("{} (0x{})", bytes32_to_base58(bytes), hex::encode(bytes));
+    let mut fields = vec![
+        ("start_slot".to_string(), commitments.start_slot.to_string()),
+        ("end_slot".to_string(), commitments.end_slot.to_string()),
+        ("epoch".to_string(), commitments.epoch.to_string()),
+        ("original_bank_hash".to_string(), hex(&commitments.original_bank_hash)),
+        ("last_bank_hash".to_string(), hex(&commitments.last_bank_hash)),
+        ("account_data_hash".to_string(), hex(&commitments.account_data_hash)),
+        ("hash_root_valset".to_string(), hex(&commitments.hash_root_valset)),
+        ("total_active_stake".to_string(), commitments.total_active_stake.to_string()),
+        ("validator_count".to_string(), commitments.validator_count.to_string()),
+        (
+            "monitored_accounts_state".to_string(),
+            format!("{} account(s)", commitments.monitored_accounts_state.len()),
+        ),
+        ("validations_passed".to_string(), commitments.validations_passed.to_string()),
+        ("aggregated_vkey_digest".to_string(), hex(&commitments.aggregated_vkey_digest)),
+    ];
+    for (i, account) in commitments.monitored_accounts_state.iter().enumerate() {
+        let path = |name: &str| format!("monitored_accounts_state[{}].{}", i, name);
+        fields.extend([
+            (path("account_pubkey"), key(&account.account_pubkey)),
+            (path("last_change_slot"), account.last_change_slot.to_string()),
+            (path("account_data_hash"), hex(&account.account_data_hash)),
+            (path("lamports"), account.lamports.to_string()),
+            (path("owner"), key(&account.owner)),
+            (path("executable"), account.executable.to_string()),
+            (path("rent_epoch"), rent_epoch(account)),
+            (path("data_len"), account.data_len.to_string()),
+            (path("data"), format!("{} ({} bytes)", hex(&account.data), account.data.len())),
+        ]);
+    }
+    fields
+}
+
+/// A field that differs between two sets of public values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldChange {
+    Changed { path: String, left: String, right: String },
+    /// Only on the left, e.g. an account the right side does not have
+    Removed { path: String, value: String },
+    Added { path: String, value: String },
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldChange::Changed { path, left, right } => write!(f, "{}: {} -> {}", path, left, right),
+            FieldChange::Removed { path, value } => write!(f, "{}: {} -> (missing)", path, value),
+            FieldChange::Added { path, value } => write!(f, "{}: (missing) -> {}", path, value),
+        }
+    }
+}
+
+/// Fields that differ from `left` to `right`, in field order
+pub fn diff(left: &PublicCommitments, right: &PublicCommitments) -> Vec<FieldChange> {
+    let left = fields(left);
+    let right = fields(right);
+    let right_values: HashMap<&str, &str> = right.iter().map(|(path, value)| (path.as_str(), value.as_str())).collect();
+    let left_paths: HashSet<&str> = left.iter().map(|(path, _)| path.as_str()).collect();
+
+    let mut changes = Vec::new();
+    for (path, value) in &left {
+        match right_values.get(path.as_str()) {
+            Some(other) if *other == value => {}
+            Some(other) => {
+                changes.push(FieldChange::Changed { path: path.clone(), left: value.clone(), right: other.to_string() })
+            }
+            None => changes.push(FieldChange::Removed { path: path.clone(), value: value.clone() }),
+        }
+    }
+    for (path, value) in &right {
+        if !left_paths.contains(path.as_str()) {
+            changes.push(FieldChange::Added { path: path.clone(), value: value.clone() });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The lib crate's encoding of its sample statement for the current version
+    const FIXTURE: &str = include_str!("../../lib/tests/fixtures/statement-v4-commitments.hex");
+
+    /// Bytes of a fixture, its hex with the `#` comments removed
+    fn fixture_bytes() -> Vec<u8> {
+        let hex: String = FIXTURE.lines().map(|line| line.split('#').next().unwrap_or_default().trim()).collect();
+        hex::decode(hex).unwrap()
+    }
+
+    #[test]
+    fn decodes_the_pinned_statement() {
+        let bytes = fixture_bytes();
+        let commitments = decode_bincode(&bytes).unwrap();
+        assert_eq!((commitments.start_slot, commitments.end_slot, commitments.epoch), (1_000_000, 1_000_100, 2));
+        assert_eq!(commitments.monitored_accounts_state.len(), 1);
+        assert_eq!(commitments.monitored_accounts_state[0].data, b"stub");
+        assert_eq!(commitments.aggregated_vkey_digest, [0x88; 32]);
+        assert_eq!(bincode::serialize(&commitments).unwrap(), bytes);
+        assert_eq!(decode(&bytes).unwrap(), (commitments, PublicValuesEncoding::Bincode));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = fixture_bytes();
+        let end = bytes.len();
+        bytes.push(0);
+        let error = decode_bincode(&bytes).unwrap_err();
+        assert_eq!(error.offset, end);
+    }
+
+    #[test]
+    fn abi_round_trips() {
+        let commitments = decode_bincode(&fixture_bytes()).unwrap();
+        let abi = encode_abi(&commitments);
+        assert_eq!(decode_abi(&abi).unwrap(), commitments);
+        assert_eq!(decode(&abi).unwrap(), (commitments, PublicValuesEncoding::Abi));
+    }
+
+    #[test]
+    fn diff_names_the_changed_fields() {
+        let left = decode_bincode(&fixture_bytes()).unwrap();
+        let mut right = left.clone();
+        right.end_slot += 1;
+        right.monitored_accounts_state[0].lamports = 0;
+        let paths: Vec<String> = diff(&left, &right)
+            .into_iter()
+            .map(|change| match change {
+                FieldChange::Changed { path, .. } => path,
+                other => panic!("unexpected change {}", other),
+            })
+            .collect();
+        assert_eq!(paths, ["end_slot", "monitored_accounts_state[0].lamports"]);
+    }
+}
@@ -0,0 +1,454 @@
+//! Bounded background queue in front of a proof sink
+//!
+//! Proofs are published in enqueue order by a single background task, so
+//! proving does not wait on broker latency while per-key ordering is kept.
+//! Each enqueued proof comes with a [`Delivery`] that resolves once the proof
+//! is published; callers that need the receipt await it, others drop it and
+//! find the proof's failure in the [`QueueReport`] of `close`. The queue is a
+//! [`ProofSink`] itself, publishing a proof by enqueueing it and awaiting its
+//! delivery, which is how the scheduler publishes to Kafka.
+
+use crate::kafka::KafkaPublishError;
+use crate::metrics;
+use crate::proof_sink::{ProofSink, SinkFuture, SinkReceipt};
+use serde_json::Value;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use twine_types::proofs::ZkProof;
+
+/// What `enqueue` does when the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueFullPolicy {
+    /// Wait until the background task frees a slot
+    #[default]
+    Block,
+    /// Return `EnqueueError::Full` immediately
+    Error,
+}
+
+/// Proof that could not be enqueued, handed back to the caller
+///
+/// The proof is boxed to keep `Result<_, EnqueueError>` small.
+#[derive(Debug)]
+pub enum EnqueueError {
+    /// The queue is full and the policy is `QueueFullPolicy::Error`
+    Full(Box<ZkProof>),
+    /// The queue was closed
+    Closed(Box<ZkProof>),
+}
+
+impl EnqueueError {
+    /// Take back the proof that was not enqueued
+    pub fn into_proof(self) -> ZkProof {
+        match self {
+            EnqueueError::Full(proof) | EnqueueError::Closed(proof) => *proof,
+        }
+    }
+}
+
+impl fmt::Display for EnqueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnqueueError::Full(proof) => write!(f, "Publish queue is full, rejected {}", proof.identifier),
+            EnqueueError::Closed(proof) => write!(f, "Publish queue is closed, rejected {}", proof.identifier),
+        }
+    }
+}
+
+impl std::error::Error for EnqueueError {}
+
+/// Outcome of all publishes handled by the queue
+#[derive(Debug, Default)]
+pub struct QueueReport {
+    pub published: usize,
+    /// Proofs kept in the local spool because the broker was unreachable
+    pub spooled: usize,
+    /// Proofs that failed after all retries
+    pub failed: usize,
+    /// The failed proofs whose `Delivery` was dropped, in enqueue order; the others went to their delivery
+    pub unclaimed: Vec<(ZkProof, KafkaPublishError)>,
+}
+
+/// Error returned by `PublishQueue::close`
+#[derive(Debug)]
+pub enum CloseError {
+    /// The deadline passed before all queued proofs were published
+    DeadlineExceeded { pending: usize },
+    /// The background task panicked
+    TaskFailed(String),
+    /// The queue was closed before
+    AlreadyClosed,
+}
+
+impl fmt::Display for CloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloseError::DeadlineExceeded { pending } => {
+                write!(f, "Publish queue deadline exceeded with {} proofs pending", pending)
+            }
+            CloseError::TaskFailed(e) => write!(f, "Publish queue task failed: {}", e),
+            CloseError::AlreadyClosed => write!(f, "Publish queue is already closed"),
+        }
+    }
+}
+
+impl std::error::Error for CloseError {}
+
+/// Result of publishing one queued proof
+pub type DeliveryResult = Result<SinkReceipt, KafkaPublishError>;
+
+/// Resolves once the background task has published or given up on a queued proof
+#[derive(Debug)]
+pub struct Delivery(oneshot::Receiver<DeliveryResult>);
+
+impl Delivery {
+    /// Wait for the proof to be published
+    pub async fn wait(self) -> DeliveryResult {
+        self.0.await.unwrap_or_else(|_| Err(KafkaPublishError::Fatal("publish queue stopped before the proof".into())))
+    }
+}
+
+/// A proof waiting in the queue
+struct Queued {
+    proof: ZkProof,
+    metadata: Option<Value>,
+    enqueued_at: Instant,
+    delivery: oneshot::Sender<DeliveryResult>,
+}
+
+/// Bounded queue drained by a background publishing task
+pub struct PublishQueue {
+    /// `None` once closed
+    sender: Mutex<Option<mpsc::Sender<Queued>>>,
+    task: Mutex<Option<JoinHandle<QueueReport>>>,
+    policy: QueueFullPolicy,
+    /// Queued plus in-flight proofs
+    pending: Arc<AtomicUsize>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl PublishQueue {
+    /// Spawn the background task publishing to `sink`
+    pub fn start(sink: Arc<dyn ProofSink>, capacity: usize, policy: QueueFullPolicy) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Queued>(capacity.max(1));
+        let pending = Arc::new(AtomicUsize::new(0));
+        let task_pending = pending.clone();
+
+        let task = tokio::spawn(async move {
+            let mut report = QueueReport::default();
+            while let Some(Queued { proof, metadata, enqueued_at, delivery }) = receiver.recv().await {
+                metrics::KAFKA_PUBLISH_QUEUE_DEPTH.dec();
+                let queue_wait = enqueued_at.elapsed();
+                metrics::KAFKA_QUEUE_WAIT_SECONDS.observe(queue_wait.as_secs_f64());
+                let span = tracing::info_span!(
+                    "publish_queue_item",
+                    identifier = %proof.identifier,
+                    queue_wait_ms = queue_wait.as_millis() as u64,
+                );
+                let result = match &metadata {
+                    Some(metadata) => sink.publish_with_metadata(&proof, metadata).instrument(span).await,
+                    None => sink.publish(&proof).instrument(span).await,
+                };
+                match &result {
+                    Ok(_) => report.published += 1,
+                    Err(e) if e.is_spooled() => report.spooled += 1,
+                    Err(e) => {
+                        eprintln!("Warning: failed to publish {}: {}", proof.identifier, e);
+                        report.failed += 1;
+                    }
+                }
+                task_pending.fetch_sub(1, Ordering::SeqCst);
+                // Nobody is waiting for this proof; keep its failure for the report
+                if let Err(Err(e)) = delivery.send(result) {
+                    if !e.is_spooled() {
+                        report.unclaimed.push((proof, e));
+                    }
+                }
+            }
+            report
+        });
+
+        Self { sender: Mutex::new(Some(sender)), task: Mutex::new(Some(task)), policy, pending }
+    }
+
+    /// Queue a proof for publishing, applying the queue-full policy
+    pub async fn enqueue(&self, proof: ZkProof) -> Result<Delivery, EnqueueError> {
+        self.enqueue_item(proof, None).await
+    }
+
+    /// Queue a proof to be published with envelope metadata
+    pub async fn enqueue_with_metadata(&self, proof: ZkProof, metadata: Value) -> Result<Delivery, EnqueueError> {
+        self.enqueue_item(proof, Some(metadata)).await
+    }
+
+    async fn enqueue_item(&self, proof: ZkProof, metadata: Option<Value>) -> Result<Delivery, EnqueueError> {
+        let Some(sender) = lock(&self.sender).clone() else {
+            return Err(EnqueueError::Closed(Box::new(proof)));
+        };
+        // Reserving first leaves nothing to undo if a blocked caller gives up
+        let permit = match self.policy {
+            QueueFullPolicy::Block => sender.reserve().await.map_err(|_| EnqueueError::Closed(Box::new(proof.clone()))),
+            QueueFullPolicy::Error => sender.try_reserve().map_err(|e| match e {
+                TrySendError::Full(()) => EnqueueError::Full(Box::new(proof.clone())),
+                TrySendError::Closed(()) => EnqueueError::Closed(Box::new(proof.clone())),
+            }),
+        }?;
+        // Count before sending so the background task can never decrement first
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        metrics::KAFKA_PUBLISH_QUEUE_DEPTH.inc();
+        let (delivery, receiver) = oneshot::channel();
+        permit.send(Queued { proof, metadata, enqueued_at: Instant::now(), delivery });
+        Ok(Delivery(receiver))
+    }
+
+    /// Number of proofs queued or currently being published
+    pub fn len(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Whether no proofs are waiting to be published
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stop accepting proofs and wait until everything queued, including
+    /// in-flight sends, has been published or has failed
+    pub async fn close(&self, deadline: Duration) -> Result<QueueReport, CloseError> {
+        lock(&self.sender).take();
+        let Some(mut task) = lock(&self.task).take() else {
+            return Err(CloseError::AlreadyClosed);
+        };
+
+        match tokio::time::timeout(deadline, &mut task).await {
+            Ok(Ok(report)) => Ok(report),
+            Ok(Err(e)) => Err(CloseError::TaskFailed(e.to_string())),
+            Err(_) => {
+                task.abort();
+                Err(CloseError::DeadlineExceeded { pending: self.len() })
+            }
+        }
+    }
+
+    async fn publish_queued(&self, proof: &ZkProof, metadata: Option<&Value>) -> DeliveryResult {
+        let delivery = self
+            .enqueue_item(proof.clone(), metadata.cloned())
+            .await
+            .map_err(|e| KafkaPublishError::Fatal(Box::new(e)))?;
+        delivery.wait().await
+    }
+}
+
+impl ProofSink for PublishQueue {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+        Box::pin(self.publish_queued(proof, None))
+    }
+
+    fn publish_with_metadata<'a>(&'a self, proof: &'a ZkProof, metadata: &'a Value) -> SinkFuture<'a> {
+        Box::pin(self.publish_queued(proof, Some(metadata)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::envelope;
+    use crate::proof_sink::MemorySink;
+    use crate::prove_jobs::ProofMode;
+    use tokio::sync::Semaphore;
+
+    fn proof(identifier: &str) -> ZkProof {
+        envelope(identifier.to_string(), ProofMode::Compressed, vec![7; 64], vec![1; 32], [4; 32])
+    }
+
+    fn identifiers(sink: &MemorySink) -> Vec<String> {
+        sink.proofs().into_iter().map(|proof| proof.identifier).collect()
+    }
+
+    /// A memory sink whose publishes each wait for a permit, like a slow broker
+    struct GatedSink {
+        inner: MemorySink,
+        gate: Semaphore,
+        started: AtomicUsize,
+    }
+
+    impl GatedSink {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { inner: MemorySink::new(), gate: Semaphore::new(0), started: AtomicUsize::new(0) })
+        }
+
+        fn open(&self, publishes: usize) {
+            self.gate.add_permits(publishes);
+        }
+
+        /// Wait until `count` publishes have started
+        async fn started(&self, count: usize) {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while self.started.load(Ordering::SeqCst) < count {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+            })
+            .await
+            .expect("publishes started");
+        }
+    }
+
+    impl ProofSink for GatedSink {
+        fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+            Box::pin(async move {
+                self.started.fetch_add(1, Ordering::SeqCst);
+                self.gate.acquire().await.unwrap().forget();
+                self.inner.publish(proof).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_in_enqueue_order() {
+        let sink = Arc::new(MemorySink::new());
+        let queue = PublishQueue::start(sink.clone(), 4, QueueFullPolicy::Block);
+        let expected: Vec<String> = (0..20).map(|i| format!("proof-{}", i)).collect();
+        for identifier in &expected {
+            drop(queue.enqueue(proof(identifier)).await.unwrap());
+        }
+        let report = queue.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!((report.published, report.failed), (20, 0));
+        assert_eq!(identifiers(&sink), expected);
+    }
+
+    #[tokio::test]
+    async fn delivery_resolves_to_the_receipt() {
+        let sink = Arc::new(MemorySink::new());
+        let queue = PublishQueue::start(sink.clone(), 1, QueueFullPolicy::Block);
+        let first = queue.enqueue(proof("a")).await.unwrap();
+        let second = queue.enqueue_with_metadata(proof("b"), serde_json::json!({ "note": "b" })).await.unwrap();
+        assert_eq!(first.wait().await.unwrap(), SinkReceipt::Memory(0));
+        assert_eq!(second.wait().await.unwrap(), SinkReceipt::Memory(1));
+        assert_eq!(sink.metadata(), [None, Some(serde_json::json!({ "note": "b" }))]);
+        // As a sink, the queue publishes and waits for the receipt
+        assert_eq!(queue.publish(&proof("c")).await.unwrap(), SinkReceipt::Memory(2));
+    }
+
+    #[tokio::test]
+    async fn full_queue_errors_under_the_error_policy() {
+        let sink = GatedSink::new();
+        let queue = PublishQueue::start(sink.clone(), 1, QueueFullPolicy::Error);
+        let _in_flight = queue.enqueue(proof("in-flight")).await.unwrap();
+        sink.started(1).await;
+        let _queued = queue.enqueue(proof("queued")).await.unwrap();
+        match queue.enqueue(proof("rejected")).await {
+            Err(EnqueueError::Full(rejected)) => assert_eq!(rejected.identifier, "rejected"),
+            other => panic!("expected a full queue, got {:?}", other),
+        }
+        assert_eq!(queue.len(), 2);
+
+        sink.open(2);
+        let report = queue.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report.published, 2);
+        assert_eq!(identifiers(&sink.inner), ["in-flight", "queued"]);
+    }
+
+    #[tokio::test]
+    async fn full_queue_blocks_under_the_block_policy() {
+        let sink = GatedSink::new();
+        let queue = Arc::new(PublishQueue::start(sink.clone(), 1, QueueFullPolicy::Block));
+        drop(queue.enqueue(proof("in-flight")).await.unwrap());
+        sink.started(1).await;
+        drop(queue.enqueue(proof("queued")).await.unwrap());
+
+        let blocked = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.enqueue(proof("blocked")).await.is_ok() }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!blocked.is_finished(), "enqueue returned while the queue was full");
+        assert_eq!(queue.len(), 2);
+
+        // Finishing the in-flight publish frees the slot
+        sink.open(1);
+        assert!(blocked.await.unwrap());
+        sink.open(2);
+        let report = queue.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report.published, 3);
+        assert_eq!(identifiers(&sink.inner), ["in-flight", "queued", "blocked"]);
+    }
+
+    #[tokio::test]
+    async fn a_blocked_caller_that_gives_up_leaves_nothing_queued() {
+        let sink = GatedSink::new();
+        let queue = PublishQueue::start(sink.clone(), 1, QueueFullPolicy::Block);
+        drop(queue.enqueue(proof("in-flight")).await.unwrap());
+        sink.started(1).await;
+        drop(queue.enqueue(proof("queued")).await.unwrap());
+        let gave_up = tokio::time::timeout(Duration::from_millis(20), queue.enqueue(proof("gave-up"))).await;
+        assert!(gave_up.is_err());
+        assert_eq!(queue.len(), 2);
+        sink.open(2);
+        queue.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(identifiers(&sink.inner), ["in-flight", "queued"]);
+    }
+
+    #[tokio::test]
+    async fn close_waits_for_queued_and_in_flight_proofs() {
+        let sink = GatedSink::new();
+        let queue = Arc::new(PublishQueue::start(sink.clone(), 4, QueueFullPolicy::Block));
+        for identifier in ["a", "b", "c"] {
+            drop(queue.enqueue(proof(identifier)).await.unwrap());
+        }
+        sink.started(1).await;
+
+        let closing = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.close(Duration::from_secs(5)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!closing.is_finished(), "close returned with proofs in flight");
+        assert!(matches!(queue.enqueue(proof("late")).await, Err(EnqueueError::Closed(_))));
+
+        sink.open(3);
+        let report = closing.await.unwrap().unwrap();
+        assert_eq!(report.published, 3);
+        assert!(queue.is_empty());
+        assert_eq!(identifiers(&sink.inner), ["a", "b", "c"]);
+        assert!(matches!(queue.close(Duration::from_secs(1)).await, Err(CloseError::AlreadyClosed)));
+    }
+
+    #[tokio::test]
+    async fn close_gives_up_at_the_deadline() {
+        let sink = GatedSink::new();
+        let queue = PublishQueue::start(sink.clone(), 4, QueueFullPolicy::Block);
+        for identifier in ["a", "b"] {
+            drop(queue.enqueue(proof(identifier)).await.unwrap());
+        }
+        sink.started(1).await;
+        match queue.close(Duration::from_millis(20)).await {
+            Err(CloseError::DeadlineExceeded { pending }) => assert_eq!(pending, 2),
+            other => panic!("expected the deadline to pass, got {:?}", other.map(|report| report.published)),
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_nobody_awaited_are_reported() {
+        let sink = Arc::new(MemorySink::new());
+        sink.fail_next(2);
+        let queue = PublishQueue::start(sink.clone(), 4, QueueFullPolicy::Block);
+        let awaited = queue.enqueue(proof("awaited")).await.unwrap();
+        assert!(awaited.wait().await.unwrap_err().is_retryable());
+        drop(queue.enqueue(proof("dropped")).await.unwrap());
+        drop(queue.enqueue(proof("published")).await.unwrap());
+
+        let report = queue.close(Duration::from_secs(5)).await.unwrap();
+        assert_eq!((report.published, report.failed), (1, 2));
+        let unclaimed: Vec<&str> = report.unclaimed.iter().map(|(proof, _)| proof.identifier.as_str()).collect();
+        assert_eq!(unclaimed, ["dropped"]);
+        assert_eq!(identifiers(&sink), ["published"]);
+    }
+}
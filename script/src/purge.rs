@@ -0,0 +1,195 @@
+//! Deleting the records before an offset (DeleteRecords)
+//!
+//! Kafka deletes records by moving a partition's log start offset forward;
+//! the records below it are removed with their segments. The cut-off is an
+//! offset, or per partition the first record at or after a point in time, and
+//! is clamped to the retained log. rdkafka's safe admin API has no
+//! DeleteRecords, so the request goes through [`crate::native_admin`].
+
+use crate::native_admin::{from_c, run_request};
+use rdkafka::admin::AdminClient;
+use rdkafka::client::ClientContext;
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::error::RDKafkaErrorCode;
+use rdkafka::{Offset, TopicPartitionList};
+use rdkafka_sys::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Where to cut a partition's log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurgePoint {
+    /// Delete the records before this offset
+    Offset(i64),
+    /// Delete the records before the first record at or after this time (Unix milliseconds); all if there is none
+    Timestamp(i64),
+}
+
+impl fmt::Display for PurgePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurgePoint::Offset(offset) => write!(f, "offset {}", offset),
+            PurgePoint::Timestamp(millis) => write!(f, "timestamp {}", millis),
+        }
+    }
+}
+
+/// New log start offset of one partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeTarget {
+    pub topic: String,
+    pub partition: i32,
+    /// Log start offset before the purge
+    pub low: i64,
+    /// High watermark
+    pub high: i64,
+    /// Records before this offset are deleted
+    pub before: i64,
+}
+
+impl PurgeTarget {
+    /// Number of records the purge deletes
+    pub fn records(&self) -> i64 {
+        self.before - self.low
+    }
+}
+
+/// Log start offset of one partition after a purge, or why it failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgedPartition {
+    pub topic: String,
+    pub partition: i32,
+    pub low: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Resolve the cut-off of `partition`, or of every partition of `topic`
+pub fn resolve_purge<C, T>(
+    consumer: &T,
+    topic: &str,
+    partition: Option<i32>,
+    point: PurgePoint,
+    timeout: Duration,
+) -> Result<Vec<PurgeTarget>, String>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let metadata = consumer
+        .fetch_metadata(Some(topic), timeout)
+        .map_err(|e| format!("Failed to fetch the metadata of {}: {}", topic, e))?;
+    let mut partitions: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .filter(|t| t.name() == topic && t.error().is_none())
+        .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+        .collect();
+    if partitions.is_empty() {
+        return Err(format!("Topic {} does not exist", topic));
+    }
+    if let Some(partition) = partition {
+        if !partitions.contains(&partition) {
+            return Err(format!("Topic {} has no partition {}", topic, partition));
+        }
+        partitions = vec![partition];
+    }
+    partitions.sort_unstable();
+
+    let by_time: BTreeMap<i32, i64> = match point {
+        PurgePoint::Timestamp(millis) => {
+            // offsets_for_times takes the timestamp in the offset field
+            let mut query = TopicPartitionList::new();
+            for &partition in &partitions {
+                query
+                    .add_partition_offset(topic, partition, Offset::Offset(millis))
+                    .map_err(|e| e.to_string())?;
+            }
+            let found = consumer
+                .offsets_for_times(query, timeout)
+                .map_err(|e| format!("Failed to look up offsets by time: {}", e))?;
+            found
+                .elements()
+                .iter()
+                .filter_map(|element| match element.offset() {
+                    Offset::Offset(offset) => Some((element.partition(), offset)),
+                    _ => None,
+                })
+                .collect()
+        }
+        PurgePoint::Offset(_) => BTreeMap::new(),
+    };
+
+    let mut targets = Vec::new();
+    for partition in partitions {
+        let (low, high) = consumer
+            .fetch_watermarks(topic, partition, timeout)
+            .map_err(|e| format!("Failed to fetch watermarks of {} partition {}: {}", topic, partition, e))?;
+        let before = match point {
+            PurgePoint::Offset(offset) => offset,
+            PurgePoint::Timestamp(_) => by_time.get(&partition).copied().unwrap_or(high),
+        };
+        targets.push(PurgeTarget { topic: topic.to_string(), partition, low, high, before: before.clamp(low, high) });
+    }
+    Ok(targets)
+}
+
+/// Delete the records before each target's cut-off, returning the new log start offsets
+///
+/// Targets that delete nothing are not sent.
+pub fn delete_records<C: ClientContext>(
+    admin: &AdminClient<C>,
+    targets: &[PurgeTarget],
+    timeout: Duration,
+) -> Result<Vec<PurgedPartition>, String> {
+    let mut offsets = TopicPartitionList::new();
+    for target in targets.iter().filter(|target| target.records() > 0) {
+        offsets
+            .add_partition_offset(&target.topic, target.partition, Offset::Offset(target.before))
+            .map_err(|e| e.to_string())?;
+    }
+    if offsets.count() == 0 {
+        return Ok(Vec::new());
+    }
+
+    // librdkafka copies the offsets, so the request object is freed once queued
+    let request = Request(unsafe { rd_kafka_DeleteRecords_new(offsets.ptr()) });
+    let mut pointers = [request.0];
+    let op = rd_kafka_admin_op_t::RD_KAFKA_ADMIN_OP_DELETERECORDS;
+    let event = run_request(admin, op, timeout, |rk, options, queue| unsafe {
+        rd_kafka_DeleteRecords(rk, pointers.as_mut_ptr(), pointers.len(), options, queue)
+    })?;
+    unsafe {
+        let result = rd_kafka_event_DeleteRecords_result(event.0);
+        if result.is_null() {
+            return Err("unexpected result for DeleteRecords".to_string());
+        }
+        let list = rd_kafka_DeleteRecords_result_offsets(result);
+        if list.is_null() {
+            return Ok(Vec::new());
+        }
+        let count = usize::try_from((*list).cnt).unwrap_or(0);
+        Ok((0..count)
+            .map(|i| {
+                let element = &*(*list).elems.add(i);
+                let failed = element.err != rd_kafka_resp_err_t::RD_KAFKA_RESP_ERR_NO_ERROR;
+                PurgedPartition {
+                    topic: from_c(element.topic),
+                    partition: element.partition,
+                    low: (!failed).then_some(element.offset),
+                    error: failed.then(|| RDKafkaErrorCode::from(element.err).to_string()),
+                }
+            })
+            .collect())
+    }
+}
+
+/// A DeleteRecords request object, destroyed on drop
+struct Request(*mut rd_kafka_DeleteRecords_t);
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_DeleteRecords_destroy(self.0) }
+    }
+}
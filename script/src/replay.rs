@@ -0,0 +1,473 @@
+//! Replaying a topic from explicit offsets, a point in time or offset windows
+//!
+//! Replays assign partitions directly instead of joining a consumer group, so
+//! they never move a group's committed offsets.
+
+use chrono::DateTime;
+use rdkafka::consumer::{Consumer, ConsumerContext};
+use rdkafka::error::KafkaResult;
+use rdkafka::{Offset, TopicPartitionList};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Starting offset for one partition, parsed from `<partition>:<offset>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionOffset {
+    pub partition: i32,
+    pub offset: i64,
+}
+
+impl FromStr for PartitionOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid partition offset '{}' (expected <partition>:<offset>)", s);
+
+        let (partition, offset) = s.split_once(':').ok_or_else(invalid)?;
+        let partition: i32 = partition.trim().parse().map_err(|_| invalid())?;
+        let offset: i64 = offset.trim().parse().map_err(|_| invalid())?;
+        if partition < 0 || offset < 0 {
+            return Err(invalid());
+        }
+        Ok(PartitionOffset { partition, offset })
+    }
+}
+
+impl fmt::Display for PartitionOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.partition, self.offset)
+    }
+}
+
+/// Offset window of one partition, parsed from `<partition>:<start>..<end>` (end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionWindow {
+    pub partition: i32,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl FromStr for PartitionWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid replay window '{}' (expected <partition>:<start>..<end>)", s);
+
+        let (partition, range) = s.split_once(':').ok_or_else(invalid)?;
+        let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+        let partition: i32 = partition.trim().parse().map_err(|_| invalid())?;
+        let start: i64 = start.trim().parse().map_err(|_| invalid())?;
+        let end: i64 = end.trim().parse().map_err(|_| invalid())?;
+        if partition < 0 || start < 0 {
+            return Err(invalid());
+        }
+        if end <= start {
+            return Err(format!("Invalid replay window '{}': end must be greater than start", s));
+        }
+        Ok(PartitionWindow { partition, start, end })
+    }
+}
+
+impl fmt::Display for PartitionWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}..{}", self.partition, self.start, self.end)
+    }
+}
+
+/// Parse a replay timestamp given as RFC 3339 or Unix milliseconds
+pub fn parse_timestamp(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    if let Ok(millis) = s.parse::<i64>() {
+        return Ok(millis);
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.timestamp_millis())
+        .map_err(|e| format!("Invalid timestamp '{}' (expected RFC 3339 or Unix milliseconds): {}", s, e))
+}
+
+/// Where a replay starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayStart {
+    /// Explicit offsets for the listed partitions
+    Offsets(Vec<PartitionOffset>),
+    /// First record at or after this time (Unix milliseconds) on every partition
+    Timestamp(i64),
+    /// Exactly the given offset windows of the listed partitions
+    Windows(Vec<PartitionWindow>),
+}
+
+/// Resolve the partition assignment for a replay of `topics`
+///
+/// Explicit offsets and windows apply to the listed partitions of every topic;
+/// windows start at their requested offset (see [`resolve_windows`] to clamp them).
+/// Timestamps are resolved with `offsets_for_times`; partitions with no
+/// record at or after the timestamp start at the end.
+pub fn resolve_assignment<C, T>(
+    consumer: &T,
+    topics: &[String],
+    start: &ReplayStart,
+    timeout: Duration,
+) -> KafkaResult<TopicPartitionList>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let mut assignment = TopicPartitionList::new();
+    match start {
+        ReplayStart::Offsets(offsets) => {
+            for topic in topics {
+                for start in offsets {
+                    assignment.add_partition_offset(topic, start.partition, Offset::Offset(start.offset))?;
+                }
+            }
+            Ok(assignment)
+        }
+        ReplayStart::Windows(windows) => {
+            for topic in topics {
+                for window in windows {
+                    assignment.add_partition_offset(topic, window.partition, Offset::Offset(window.start))?;
+                }
+            }
+            Ok(assignment)
+        }
+        ReplayStart::Timestamp(millis) => {
+            for topic in topics {
+                let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+                for metadata_topic in metadata.topics().iter().filter(|t| t.name() == topic) {
+                    for partition in metadata_topic.partitions() {
+                        // offsets_for_times takes the timestamp in the offset field
+                        assignment.add_partition_offset(topic, partition.id(), Offset::Offset(*millis))?;
+                    }
+                }
+            }
+            consumer.offsets_for_times(assignment, timeout)
+        }
+    }
+}
+
+/// A replay window checked against the partition's watermarks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedWindow {
+    pub topic: String,
+    pub partition: i32,
+    pub requested: Range<i64>,
+    /// Log start offset
+    pub low: i64,
+    /// High watermark
+    pub high: i64,
+}
+
+impl ResolvedWindow {
+    /// Offsets that can actually be read: the requested window within the retained log
+    pub fn range(&self) -> Range<i64> {
+        self.requested.start.max(self.low)..self.requested.end.min(self.high)
+    }
+
+    /// Whether the window starts below the log start offset (its first messages were deleted)
+    pub fn starts_before_log(&self) -> bool {
+        self.requested.start < self.low
+    }
+
+    /// Whether the window ends beyond the last message in the partition
+    pub fn ends_after_log(&self) -> bool {
+        self.requested.end > self.high
+    }
+}
+
+/// Check each window of each topic against the partition watermarks
+pub fn resolve_windows<C, T>(
+    consumer: &T,
+    topics: &[String],
+    windows: &[PartitionWindow],
+    timeout: Duration,
+) -> KafkaResult<Vec<ResolvedWindow>>
+where
+    C: ConsumerContext,
+    T: Consumer<C>,
+{
+    let mut resolved = Vec::new();
+    for topic in topics {
+        for window in windows {
+            let (low, high) = consumer.fetch_watermarks(topic, window.partition, timeout)?;
+            resolved.push(ResolvedWindow {
+                topic: topic.clone(),
+                partition: window.partition,
+                requested: window.start..window.end,
+                low,
+                high,
+            });
+        }
+    }
+    Ok(resolved)
+}
+
+/// Assignment starting every non-empty window at its first readable offset
+pub fn window_assignment(windows: &[ResolvedWindow]) -> KafkaResult<TopicPartitionList> {
+    let mut assignment = TopicPartitionList::new();
+    for window in windows.iter().filter(|window| !window.range().is_empty()) {
+        assignment.add_partition_offset(&window.topic, window.partition, Offset::Offset(window.range().start))?;
+    }
+    Ok(assignment)
+}
+
+/// Progress of a bounded replay through its windows
+#[derive(Debug, Default)]
+pub struct WindowTracker {
+    /// End offset of each window not yet exhausted
+    open: BTreeMap<(String, i32), i64>,
+}
+
+impl WindowTracker {
+    /// Track the non-empty windows
+    pub fn new(windows: &[ResolvedWindow]) -> Self {
+        let open = windows
+            .iter()
+            .filter(|window| !window.range().is_empty())
+            .map(|window| ((window.topic.clone(), window.partition), window.range().end))
+            .collect();
+        Self { open }
+    }
+
+    /// Record a consumed message, returning whether it lies inside its partition's window
+    ///
+    /// The window is closed once the message at its last offset, or any later
+    /// one, has been seen.
+    pub fn observe(&mut self, topic: &str, partition: i32, offset: i64) -> bool {
+        let key = (topic.to_string(), partition);
+        let Some(&end) = self.open.get(&key) else {
+            return false;
+        };
+        if offset + 1 >= end {
+            self.open.remove(&key);
+        }
+        offset < end
+    }
+
+    /// Close windows whose partition position has reached the end
+    ///
+    /// Covers windows ending in offsets without messages, such as transaction markers.
+    pub fn close_reached(&mut self, positions: &TopicPartitionList) {
+        for element in positions.elements() {
+            if let Offset::Offset(position) = element.offset() {
+                let key = (element.topic().to_string(), element.partition());
+                if self.open.get(&key).is_some_and(|&end| position >= end) {
+                    self.open.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Whether every window has been consumed
+    pub fn is_done(&self) -> bool {
+        self.open.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdkafka::consumer::BaseConsumer;
+    use rdkafka::ClientConfig;
+
+    /// A consumer that never reaches a broker, enough for assignments that need no lookups
+    fn unconnected_consumer() -> BaseConsumer {
+        ClientConfig::new()
+            .set("bootstrap.servers", "127.0.0.1:1")
+            .set("group.id", "replay-tests")
+            .set("log_level", "0")
+            .create()
+            .unwrap()
+    }
+
+    fn topics(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// (topic, partition, offset) of each element of an assignment
+    fn elements(assignment: &TopicPartitionList) -> Vec<(String, i32, Offset)> {
+        assignment
+            .elements()
+            .iter()
+            .map(|element| (element.topic().to_string(), element.partition(), element.offset()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_partition_offsets() {
+        assert_eq!("3:1500".parse::<PartitionOffset>().unwrap(), PartitionOffset { partition: 3, offset: 1500 });
+        assert_eq!(" 0 : 0 ".parse::<PartitionOffset>().unwrap(), PartitionOffset { partition: 0, offset: 0 });
+        assert_eq!(PartitionOffset { partition: 2, offset: 7 }.to_string(), "2:7");
+    }
+
+    #[test]
+    fn rejects_invalid_partition_offsets() {
+        for invalid in ["3", "3:", ":10", "a:10", "3:ten", "-1:10", "3:-5", "3:1:2"] {
+            let error = invalid.parse::<PartitionOffset>().unwrap_err();
+            assert!(error.contains("expected <partition>:<offset>"), "{:?}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn parses_windows() {
+        let window: PartitionWindow = "1:100..200".parse().unwrap();
+        assert_eq!(window, PartitionWindow { partition: 1, start: 100, end: 200 });
+        assert_eq!(window.to_string(), "1:100..200");
+        assert_eq!(
+            " 0 : 5 .. 6 ".parse::<PartitionWindow>().unwrap(),
+            PartitionWindow { partition: 0, start: 5, end: 6 }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_windows() {
+        for invalid in ["1", "1:100", "1:100-200", "1:..200", "x:1..2", "-1:1..2", "1:-1..2"] {
+            let error = invalid.parse::<PartitionWindow>().unwrap_err();
+            assert!(error.contains("expected <partition>:<start>..<end>"), "{:?}: {}", invalid, error);
+        }
+        for empty in ["1:100..100", "1:200..100"] {
+            let error = empty.parse::<PartitionWindow>().unwrap_err();
+            assert!(error.contains("end must be greater than start"), "{:?}: {}", empty, error);
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("1700000000000").unwrap(), 1_700_000_000_000);
+        assert_eq!(parse_timestamp(" 2023-11-14T22:13:20Z ").unwrap(), 1_700_000_000_000);
+        assert_eq!(parse_timestamp("2023-11-14T23:13:20.5+01:00").unwrap(), 1_700_000_000_500);
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for invalid in ["", "yesterday", "2023-11-14", "2023-11-14 22:13:20"] {
+            let error = parse_timestamp(invalid).unwrap_err();
+            assert!(error.contains("expected RFC 3339 or Unix milliseconds"), "{:?}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn assigns_explicit_offsets_on_every_topic() {
+        let start = ReplayStart::Offsets(vec![
+            PartitionOffset { partition: 0, offset: 10 },
+            PartitionOffset { partition: 2, offset: 0 },
+        ]);
+        let assignment =
+            resolve_assignment(&unconnected_consumer(), &topics(&["a", "b"]), &start, Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            elements(&assignment),
+            [
+                ("a".to_string(), 0, Offset::Offset(10)),
+                ("a".to_string(), 2, Offset::Offset(0)),
+                ("b".to_string(), 0, Offset::Offset(10)),
+                ("b".to_string(), 2, Offset::Offset(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn assigns_windows_at_their_requested_start() {
+        let start = ReplayStart::Windows(vec![PartitionWindow { partition: 1, start: 100, end: 200 }]);
+        let assignment =
+            resolve_assignment(&unconnected_consumer(), &topics(&["proofs"]), &start, Duration::from_secs(1)).unwrap();
+        assert_eq!(elements(&assignment), [("proofs".to_string(), 1, Offset::Offset(100))]);
+    }
+
+    #[test]
+    fn timestamps_need_the_broker() {
+        let start = ReplayStart::Timestamp(1_700_000_000_000);
+        let result =
+            resolve_assignment(&unconnected_consumer(), &topics(&["proofs"]), &start, Duration::from_millis(200));
+        assert!(result.is_err());
+    }
+
+    fn window(partition: i32, requested: Range<i64>, low: i64, high: i64) -> ResolvedWindow {
+        ResolvedWindow { topic: "proofs".to_string(), partition, requested, low, high }
+    }
+
+    fn positions(offsets: &[(i32, i64)]) -> TopicPartitionList {
+        let mut positions = TopicPartitionList::new();
+        for &(partition, offset) in offsets {
+            positions.add_partition_offset("proofs", partition, Offset::Offset(offset)).unwrap();
+        }
+        positions
+    }
+
+    #[test]
+    fn windows_inside_the_log_are_unchanged() {
+        let inside = window(0, 100..200, 50, 500);
+        assert_eq!(inside.range(), 100..200);
+        assert!(!inside.starts_before_log() && !inside.ends_after_log());
+    }
+
+    #[test]
+    fn clamps_windows_to_the_retained_log() {
+        let early = window(0, 10..200, 50, 500);
+        assert_eq!(early.range(), 50..200);
+        assert!(early.starts_before_log() && !early.ends_after_log());
+
+        let late = window(0, 100..900, 50, 500);
+        assert_eq!(late.range(), 100..500);
+        assert!(!late.starts_before_log() && late.ends_after_log());
+
+        let both = window(0, 0..900, 50, 500);
+        assert_eq!(both.range(), 50..500);
+        assert!(both.starts_before_log() && both.ends_after_log());
+    }
+
+    #[test]
+    fn windows_outside_the_log_are_empty() {
+        // Entirely deleted by retention, entirely beyond the high watermark, and an empty partition
+        for outside in [window(0, 0..40, 50, 500), window(0, 600..700, 50, 500), window(0, 0..10, 0, 0)] {
+            assert!(outside.range().is_empty(), "{:?}", outside);
+        }
+        let windows = [window(0, 0..40, 50, 500), window(1, 100..200, 50, 500)];
+        assert_eq!(elements(&window_assignment(&windows).unwrap()), [("proofs".to_string(), 1, Offset::Offset(100))]);
+        assert!(WindowTracker::new(&windows[..1]).is_done());
+    }
+
+    #[test]
+    fn observes_messages_until_each_window_closes() {
+        let mut tracker = WindowTracker::new(&[window(0, 100..103, 0, 500), window(1, 10..20, 0, 500)]);
+        assert!(tracker.observe("proofs", 0, 100));
+        assert!(tracker.observe("proofs", 0, 101));
+        assert!(!tracker.is_done());
+        // The last offset closes partition 0; anything after it is outside
+        assert!(tracker.observe("proofs", 0, 102));
+        assert!(!tracker.observe("proofs", 0, 103));
+        assert!(!tracker.observe("other", 1, 15));
+        assert!(!tracker.is_done());
+
+        // A later message (e.g. after a compacted gap) also closes the window, but is not replayed
+        assert!(!tracker.observe("proofs", 1, 25));
+        assert!(tracker.is_done());
+    }
+
+    #[test]
+    fn closes_windows_once_the_position_reaches_their_end() {
+        let mut tracker = WindowTracker::new(&[window(0, 100..103, 0, 500), window(1, 10..20, 0, 500)]);
+        // Positions short of the end, and offsets that are not positions, leave windows open
+        tracker.close_reached(&positions(&[(0, 102), (1, 19)]));
+        let mut invalid = TopicPartitionList::new();
+        invalid.add_partition_offset("proofs", 0, Offset::Invalid).unwrap();
+        tracker.close_reached(&invalid);
+        assert!(!tracker.is_done());
+
+        // The window ends in a transaction marker, so its last offset is never delivered
+        tracker.close_reached(&positions(&[(0, 103)]));
+        assert!(!tracker.observe("proofs", 0, 102));
+        tracker.close_reached(&positions(&[(1, 25)]));
+        assert!(tracker.is_done());
+    }
+
+    #[test]
+    fn a_clamped_window_closes_at_the_high_watermark() {
+        let mut tracker = WindowTracker::new(&[window(0, 100..900, 0, 500)]);
+        assert!(tracker.observe("proofs", 0, 498));
+        assert!(!tracker.is_done());
+        assert!(tracker.observe("proofs", 0, 499));
+        assert!(tracker.is_done());
+    }
+}
@@ -0,0 +1,202 @@
+//! Topic routing by proof kind and Solana network
+//!
+//! Routes are checked in the order they were given and the first match wins;
+//! proofs matching no route go to the default topic.
+
+use std::fmt;
+use std::str::FromStr;
+use twine_types::proofs::{ProofData, ZkProof};
+
+/// Solana cluster the RPC client fetches account data from
+pub const DEFAULT_NETWORK: &str = "devnet";
+
+/// Proof kinds a route can match on
+pub const PROOF_KINDS: [&str; 2] = ["groth16", "compressed"];
+
+/// Kind of SP1 proof carried by a `ZkProof`
+///
+/// The prover marks compressed proofs with version 1 and Groth16 proofs with version 2.
+pub fn proof_kind(proof: &ZkProof) -> &'static str {
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+    match sp1_proof.version {
+        1 => "compressed",
+        2 => "groth16",
+        _ => "unknown",
+    }
+}
+
+/// Condition a route matches on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteMatch {
+    /// Proof kind (`groth16` or `compressed`)
+    Kind(String),
+    /// Solana network the proof was generated against
+    Network(String),
+}
+
+/// A routing rule, parsed from `kind=<kind>:<topic>` or `network=<network>:<topic>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub matcher: RouteMatch,
+    pub topic: String,
+}
+
+impl Route {
+    fn matches(&self, kind: &str, network: &str) -> bool {
+        match &self.matcher {
+            RouteMatch::Kind(k) => k == kind,
+            RouteMatch::Network(n) => n == network,
+        }
+    }
+}
+
+impl FromStr for Route {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid route '{}' (expected kind=<kind>:<topic> or network=<network>:<topic>)", s);
+
+        let (field, rest) = s.split_once('=').ok_or_else(invalid)?;
+        let (value, topic) = rest.split_once(':').ok_or_else(invalid)?;
+        let (value, topic) = (value.trim(), topic.trim());
+        if value.is_empty() || topic.is_empty() {
+            return Err(invalid());
+        }
+
+        let matcher = match field.trim().to_ascii_lowercase().as_str() {
+            "kind" => {
+                let kind = value.to_ascii_lowercase();
+                if !PROOF_KINDS.contains(&kind.as_str()) {
+                    return Err(format!(
+                        "Unknown proof kind '{}' in route '{}' (expected {})",
+                        value,
+                        s,
+                        PROOF_KINDS.join(" or ")
+                    ));
+                }
+                RouteMatch::Kind(kind)
+            }
+            "network" => RouteMatch::Network(value.to_ascii_lowercase()),
+            _ => return Err(invalid()),
+        };
+
+        Ok(Route { matcher, topic: topic.to_string() })
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.matcher {
+            RouteMatch::Kind(kind) => write!(f, "kind={}:{}", kind, self.topic),
+            RouteMatch::Network(network) => write!(f, "network={}:{}", network, self.topic),
+        }
+    }
+}
+
+/// Topic chosen for a proof and the rule that chose it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDecision {
+    pub topic: String,
+    /// Matching rule, or `None` when the default topic was used
+    pub route: Option<Route>,
+}
+
+impl fmt::Display for RouteDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.route {
+            Some(route) => write!(f, "{} (route {})", self.topic, route),
+            None => write!(f, "{} (default topic)", self.topic),
+        }
+    }
+}
+
+/// Pick the topic for a proof: the first matching route, or the default topic
+pub fn route_proof(routes: &[Route], default_topic: &str, proof: &ZkProof, network: &str) -> RouteDecision {
+    let kind = proof_kind(proof);
+    let network = network.to_ascii_lowercase();
+
+    match routes.iter().find(|route| route.matches(kind, &network)) {
+        Some(route) => RouteDecision { topic: route.topic.clone(), route: Some(route.clone()) },
+        None => RouteDecision { topic: default_topic.to_string(), route: None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twine_types::proofs::{ProofKind, SP1Proof};
+
+    fn proof(version: u32) -> ZkProof {
+        ZkProof {
+            identifier: "proof-1".to_string(),
+            proof_kind: ProofKind::SolanaConsensusProof,
+            proof_data: ProofData::SP1(SP1Proof {
+                version,
+                proof: Vec::new(),
+                public_value: Vec::new(),
+                verification_key: [0; 32],
+            }),
+        }
+    }
+
+    fn routes(specs: &[&str]) -> Vec<Route> {
+        specs.iter().map(|spec| spec.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_kind_and_network_routes() {
+        assert_eq!(
+            " Kind = GROTH16 : proofs.groth16 ".parse::<Route>().unwrap(),
+            Route { matcher: RouteMatch::Kind("groth16".to_string()), topic: "proofs.groth16".to_string() }
+        );
+        let route: Route = "network=Mainnet-Beta:proofs.mainnet".parse().unwrap();
+        assert_eq!(route.matcher, RouteMatch::Network("mainnet-beta".to_string()));
+        assert_eq!(route.to_string(), "network=mainnet-beta:proofs.mainnet");
+        // Only the first ':' separates the topic
+        assert_eq!("kind=compressed:a:b".parse::<Route>().unwrap().topic, "a:b");
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        for spec in ["", "kind", "kind=groth16", "kind=:proofs", "kind=groth16:", "slot=1:proofs", "=groth16:proofs"] {
+            let err = spec.parse::<Route>().unwrap_err();
+            assert!(err.starts_with("Invalid route"), "{:?}: {}", spec, err);
+        }
+        let err = "kind=plonk:proofs".parse::<Route>().unwrap_err();
+        assert_eq!(err, "Unknown proof kind 'plonk' in route 'kind=plonk:proofs' (expected groth16 or compressed)");
+    }
+
+    #[test]
+    fn the_first_matching_route_wins() {
+        let routes = routes(&["network=mainnet-beta:proofs.mainnet", "kind=groth16:proofs.groth16"]);
+
+        let decision = route_proof(&routes, "proofs", &proof(2), "mainnet-beta");
+        assert_eq!(decision.topic, "proofs.mainnet");
+        assert_eq!(decision.route, Some(routes[0].clone()));
+
+        let reversed: Vec<Route> = routes.iter().rev().cloned().collect();
+        let decision = route_proof(&reversed, "proofs", &proof(2), "mainnet-beta");
+        assert_eq!(decision.topic, "proofs.groth16");
+        assert_eq!(decision.to_string(), "proofs.groth16 (route kind=groth16:proofs.groth16)");
+    }
+
+    #[test]
+    fn later_routes_apply_when_earlier_ones_do_not_match() {
+        let routes = routes(&["kind=compressed:proofs.compressed", "network=devnet:proofs.devnet"]);
+        assert_eq!(route_proof(&routes, "proofs", &proof(1), "devnet").topic, "proofs.compressed");
+        assert_eq!(route_proof(&routes, "proofs", &proof(2), "DevNet").topic, "proofs.devnet");
+    }
+
+    #[test]
+    fn unmatched_proofs_go_to_the_default_topic() {
+        let decision = route_proof(&[], "proofs", &proof(2), DEFAULT_NETWORK);
+        assert_eq!(decision, RouteDecision { topic: "proofs".to_string(), route: None });
+        assert_eq!(decision.to_string(), "proofs (default topic)");
+
+        let routes = routes(&["kind=compressed:proofs.compressed", "network=testnet:proofs.testnet"]);
+        assert_eq!(route_proof(&routes, "proofs", &proof(2), "devnet").route, None);
+        // A version the prover does not produce matches no kind route
+        assert_eq!(proof_kind(&proof(3)), "unknown");
+        assert_eq!(route_proof(&routes, "proofs", &proof(3), "devnet").topic, "proofs");
+    }
+}
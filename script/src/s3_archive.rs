@@ -0,0 +1,330 @@
+//! Archive of proofs in S3 or S3-compatible object storage
+//!
+//! [`ArchiveSink`] uploads a proof's Kafka envelope, the proof itself as JSON
+//! and optionally a metrics file under content-addressed keys,
+//! `{prefix}/{network}/{start}_{end}/{digest}.json`, `.proof.json` and
+//! `.metrics.json`, where the digest is the SHA-256 of the public values. An
+//! object already stored under its key is left alone, so archiving a proof
+//! again is a no-op. Throttled requests are retried by the SDK, which also
+//! slows down its request rate while the store keeps throttling. Requires the
+//! `s3` feature.
+
+use crate::dedup::commitment_digest;
+use crate::guest_profile::GuestProfile;
+use crate::kafka::KafkaPublishError;
+use crate::proof_filter::decode_commitments;
+use crate::proof_sink::{ProofSink, SinkFuture, SinkReceipt};
+use crate::prove_jobs::ProofMode;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use twine_types::proofs::ZkProof;
+
+/// Attempts per request, including the first
+#[cfg(feature = "s3")]
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Bucket and key prefix given as `bucket/prefix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Key prefix without leading or trailing slashes, possibly empty
+    pub prefix: String,
+}
+
+impl FromStr for S3Location {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = s.strip_prefix("s3://").unwrap_or(s);
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            return Err(format!("Invalid S3 location: {} (expected bucket/prefix)", s));
+        }
+        Ok(Self { bucket: bucket.to_string(), prefix: prefix.trim_matches('/').to_string() })
+    }
+}
+
+impl fmt::Display for S3Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// Server-side encryption of uploaded objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encryption {
+    /// The bucket's default encryption
+    #[default]
+    None,
+    /// SSE-S3 (AES-256 with S3 managed keys)
+    Aes256,
+    /// SSE-KMS, with the bucket's AWS managed key unless a key id is given
+    Kms,
+}
+
+impl FromStr for Encryption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Encryption::None),
+            "aes256" => Ok(Encryption::Aes256),
+            "kms" | "aws:kms" => Ok(Encryption::Kms),
+            _ => Err(format!("Invalid server-side encryption: {} (expected none, aes256 or kms)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encryption::None => write!(f, "none"),
+            Encryption::Aes256 => write!(f, "aes256"),
+            Encryption::Kms => write!(f, "kms"),
+        }
+    }
+}
+
+/// Where and how proofs are archived
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    pub location: S3Location,
+    /// Solana network, the first key component below the prefix
+    pub network: String,
+    /// Endpoint of an S3-compatible store; `None` for AWS
+    pub endpoint: Option<String>,
+    pub encryption: Encryption,
+    pub kms_key_id: Option<String>,
+}
+
+/// Measurements of a proving run, archived next to the proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofMetrics {
+    pub identifier: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub proof_mode: ProofMode,
+    /// Time spent fetching the monitored accounts; `None` for inputs loaded from a file
+    #[serde(default)]
+    pub assemble_seconds: Option<f64>,
+    /// Base58 pubkeys of the monitored accounts committed as closed because they do not exist
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_accounts: Vec<String>,
+    /// Time spent generating and verifying the proof
+    pub prove_seconds: f64,
+    /// Size of the proof as JSON
+    pub proof_bytes: usize,
+    /// Cycles of each of the program's phases, when it was executed for the proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guest_profile: Option<GuestProfile>,
+    /// RFC 3339 time the proof was generated
+    pub generated_at: String,
+}
+
+/// Object keys of an archived proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveKeys {
+    pub envelope: String,
+    pub proof: String,
+    pub metrics: String,
+}
+
+impl ArchiveKeys {
+    /// Keys of a proof of `start_slot..end_slot` with commitments digest `digest`
+    pub fn new(prefix: &str, network: &str, start_slot: u64, end_slot: u64, digest: &[u8; 32]) -> Self {
+        let mut base = format!("{}/{}_{}/{}", network, start_slot, end_slot, hex::encode(digest));
+        if !prefix.is_empty() {
+            base = format!("{}/{}", prefix, base);
+        }
+        Self {
+            envelope: format!("{}.json", base),
+            proof: format!("{}.proof.json", base),
+            metrics: format!("{}.metrics.json", base),
+        }
+    }
+}
+
+/// Outcome of archiving a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveReceipt {
+    pub bucket: String,
+    pub keys: ArchiveKeys,
+    /// Objects written by this call
+    pub uploaded: usize,
+    /// Objects that were already archived and left alone
+    pub existing: usize,
+}
+
+impl fmt::Display for ArchiveReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "s3://{}/{} ({} uploaded, {} already archived)",
+            self.bucket, self.keys.envelope, self.uploaded, self.existing
+        )
+    }
+}
+
+/// Uploads proofs to a bucket
+pub struct ArchiveSink {
+    #[cfg(feature = "s3")]
+    client: aws_sdk_s3::Client,
+    options: ArchiveOptions,
+}
+
+impl ArchiveSink {
+    /// Client for the bucket, with credentials and region from the AWS environment
+    #[cfg(feature = "s3")]
+    pub async fn connect(options: ArchiveOptions) -> Result<Self, Box<dyn Error>> {
+        use aws_config::retry::RetryConfig;
+        use aws_config::BehaviorVersion;
+
+        let mut loader = aws_config::defaults(BehaviorVersion::latest())
+            .retry_config(RetryConfig::adaptive().with_max_attempts(MAX_ATTEMPTS));
+        if let Some(endpoint) = &options.endpoint {
+            loader = loader.endpoint_url(endpoint);
+        }
+        let shared_config = loader.load().await;
+        // S3-compatible stores rarely support virtual-hosted bucket addressing
+        let config =
+            aws_sdk_s3::config::Builder::from(&shared_config).force_path_style(options.endpoint.is_some()).build();
+        Ok(Self { client: aws_sdk_s3::Client::from_conf(config), options })
+    }
+
+    #[cfg(not(feature = "s3"))]
+    pub async fn connect(_options: ArchiveOptions) -> Result<Self, Box<dyn Error>> {
+        Err("--archive-s3 requires building with the s3 feature".into())
+    }
+
+    pub fn options(&self) -> &ArchiveOptions {
+        &self.options
+    }
+
+    /// Keys `proof` is archived under
+    pub fn keys_for(&self, proof: &ZkProof) -> Result<ArchiveKeys, String> {
+        let commitments = decode_commitments(proof)
+            .ok_or_else(|| format!("Public values of {} are not PublicCommitments", proof.identifier))?;
+        Ok(ArchiveKeys::new(
+            &self.options.location.prefix,
+            &self.options.network,
+            commitments.start_slot,
+            commitments.end_slot,
+            &commitment_digest(proof),
+        ))
+    }
+
+    /// Upload the envelope, the proof JSON and, if given, the metrics of `proof`
+    #[cfg(feature = "s3")]
+    pub async fn archive(
+        &self,
+        proof: &ZkProof,
+        metrics: Option<&ProofMetrics>,
+    ) -> Result<ArchiveReceipt, Box<dyn Error + Send + Sync>> {
+        use twine_types::proofs::ProofData;
+
+        let keys = self.keys_for(proof)?;
+        let ProofData::SP1(sp1_proof) = &proof.proof_data;
+        let sp1_proof: sp1_sdk::SP1ProofWithPublicValues = bincode::deserialize(&sp1_proof.proof)
+            .map_err(|e| format!("Proof of {} is not an SP1 proof: {}", proof.identifier, e))?;
+
+        let mut objects = vec![
+            (&keys.envelope, serde_json::to_vec_pretty(proof)?),
+            (&keys.proof, serde_json::to_vec_pretty(&sp1_proof)?),
+        ];
+        if let Some(metrics) = metrics {
+            objects.push((&keys.metrics, serde_json::to_vec_pretty(metrics)?));
+        }
+
+        let (mut uploaded, mut existing) = (0, 0);
+        for (key, body) in objects {
+            if self.put_if_absent(key, body).await? {
+                uploaded += 1;
+            } else {
+                existing += 1;
+            }
+        }
+        Ok(ArchiveReceipt { bucket: self.options.location.bucket.clone(), keys, uploaded, existing })
+    }
+
+    #[cfg(not(feature = "s3"))]
+    pub async fn archive(
+        &self,
+        _proof: &ZkProof,
+        _metrics: Option<&ProofMetrics>,
+    ) -> Result<ArchiveReceipt, Box<dyn Error + Send + Sync>> {
+        Err("--archive-s3 requires building with the s3 feature".into())
+    }
+
+    /// Write `body` to `key` unless the key exists; returns whether it was written
+    #[cfg(feature = "s3")]
+    async fn put_if_absent(&self, key: &str, body: Vec<u8>) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        use aws_sdk_s3::error::DisplayErrorContext;
+        use aws_sdk_s3::primitives::ByteStream;
+        use aws_sdk_s3::types::ServerSideEncryption;
+
+        let bucket = &self.options.location.bucket;
+        match self.client.head_object().bucket(bucket).key(key).send().await {
+            Ok(_) => return Ok(false),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => {}
+            Err(e) => {
+                return Err(format!("Failed to check s3://{}/{}: {}", bucket, key, DisplayErrorContext(&e)).into())
+            }
+        }
+
+        let mut put = self
+            .client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .content_type("application/json")
+            .body(ByteStream::from(body));
+        match self.options.encryption {
+            Encryption::None => {}
+            Encryption::Aes256 => put = put.server_side_encryption(ServerSideEncryption::Aes256),
+            Encryption::Kms => {
+                put = put
+                    .server_side_encryption(ServerSideEncryption::AwsKms)
+                    .set_ssekms_key_id(self.options.kms_key_id.clone());
+            }
+        }
+        put.send()
+            .await
+            .map_err(|e| format!("Failed to upload s3://{}/{}: {}", bucket, key, DisplayErrorContext(&e)))?;
+        Ok(true)
+    }
+
+    /// Read back the envelope archived under `key`
+    #[cfg(feature = "s3")]
+    pub async fn fetch(&self, key: &str) -> Result<ZkProof, Box<dyn Error>> {
+        use aws_sdk_s3::error::DisplayErrorContext;
+
+        let bucket = &self.options.location.bucket;
+        let object = self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to read s3://{}/{}: {}", bucket, key, DisplayErrorContext(&e)))?;
+        let body = object.body.collect().await?.into_bytes();
+        serde_json::from_slice(&body)
+            .map_err(|e| format!("s3://{}/{} is not a proof envelope: {}", bucket, key, e).into())
+    }
+
+    #[cfg(not(feature = "s3"))]
+    pub async fn fetch(&self, _key: &str) -> Result<ZkProof, Box<dyn Error>> {
+        Err("--archive-s3 requires building with the s3 feature".into())
+    }
+}
+
+impl ProofSink for ArchiveSink {
+    fn publish<'a>(&'a self, proof: &'a ZkProof) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let receipt = self.archive(proof, None).await.map_err(KafkaPublishError::Fatal)?;
+            Ok(SinkReceipt::Object(format!("s3://{}/{}", receipt.bucket, receipt.keys.envelope)))
+        })
+    }
+}
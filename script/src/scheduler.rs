@@ -0,0 +1,393 @@
+//! Decision engine and state of the account watcher scheduler
+//!
+//! The scheduler tracks, per watched account, the slot its last proof ended at
+//! and the first change since. At a given slot an account is due for a proof
+//! when it changed and its last proof is at least `min_slots_between_proofs`
+//! old, or when its last proof is `max_slots_since_last_proof` old whether it
+//! changed or not. At most `max_concurrent_proofs` proofs run at once; due
+//! accounts waiting for a free place go oldest proof first. Time is counted in
+//! slots, so the engine behaves the same against a node and in simulation.
+//!
+//! [`SchedulerState`] is what survives restarts. It is written atomically after
+//! every proof; a proof that was running when the process died is scheduled
+//! again because its account never recorded it.
+
+use crate::prove_jobs::ProofMode;
+use crate::solana::DEVNET_RPC_URL;
+use crate::utils::base58_to_bytes32;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+fn default_rpc_url() -> String {
+    DEVNET_RPC_URL.to_string()
+}
+
+fn default_state_file() -> PathBuf {
+    PathBuf::from("scheduler-state.json")
+}
+
+fn default_max_concurrent_proofs() -> usize {
+    1
+}
+
+fn default_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_retry_after_slots() -> u64 {
+    // About a minute of 400 ms slots
+    150
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// When an account gets a new proof
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountPolicy {
+    /// Base58 pubkey of the account
+    pub pubkey: String,
+    /// Prove when the account changes
+    #[serde(default = "default_true")]
+    pub prove_on_change: bool,
+    /// Fewest slots between two proofs triggered by changes
+    #[serde(default)]
+    pub min_slots_between_proofs: u64,
+    /// Prove once the last proof is this many slots old, changed or not
+    #[serde(default)]
+    pub max_slots_since_last_proof: Option<u64>,
+}
+
+/// Scheduler configuration, read from TOML
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// Solana JSON-RPC endpoint
+    #[serde(default = "default_rpc_url")]
+    pub rpc_url: String,
+    /// Websocket endpoint for account subscriptions (default: `rpc_url` with a ws scheme)
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    /// File the scheduler state is kept in
+    #[serde(default = "default_state_file")]
+    pub state_file: PathBuf,
+    #[serde(default = "default_max_concurrent_proofs")]
+    pub max_concurrent_proofs: usize,
+    /// Seconds between slot polls, and between account polls without a websocket
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// Slots to wait before proving an account again after its proof failed
+    #[serde(default = "default_retry_after_slots")]
+    pub retry_after_slots: u64,
+    #[serde(default)]
+    pub proof_mode: ProofMode,
+    /// Write proofs to this directory instead of publishing them to Kafka
+    #[serde(default)]
+    pub sink_dir: Option<PathBuf>,
+    /// Serve /healthz, /status and /metrics on this address
+    #[serde(default)]
+    pub status_addr: Option<SocketAddr>,
+    pub accounts: Vec<AccountPolicy>,
+}
+
+impl SchedulerConfig {
+    /// Read and check a configuration file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let config: Self = toml::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.accounts.is_empty() {
+            return Err("No accounts configured".to_string());
+        }
+        if self.max_concurrent_proofs == 0 {
+            return Err("max_concurrent_proofs must be at least 1".to_string());
+        }
+        if self.poll_interval_secs == 0 {
+            return Err("poll_interval_secs must be at least 1".to_string());
+        }
+        let mut seen = HashSet::new();
+        for policy in &self.accounts {
+            base58_to_bytes32(&policy.pubkey).map_err(|e| format!("Invalid account {}: {}", policy.pubkey, e))?;
+            if !seen.insert(&policy.pubkey) {
+                return Err(format!("Account {} is configured twice", policy.pubkey));
+            }
+            if !policy.prove_on_change && policy.max_slots_since_last_proof.is_none() {
+                return Err(format!(
+                    "Account {} would never be proven: enable prove_on_change or set max_slots_since_last_proof",
+                    policy.pubkey
+                ));
+            }
+            if policy.max_slots_since_last_proof == Some(0) {
+                return Err(format!("max_slots_since_last_proof of {} must be at least 1", policy.pubkey));
+            }
+        }
+        Ok(())
+    }
+
+    /// Websocket endpoint: `ws_url`, or `rpc_url` with its scheme switched
+    ///
+    /// Local validators listen for websockets on the RPC port plus one and
+    /// need `ws_url` set.
+    pub fn ws_url(&self) -> String {
+        if let Some(ws_url) = &self.ws_url {
+            return ws_url.clone();
+        }
+        match self.rpc_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some(("http", rest)) => format!("ws://{}", rest),
+            _ => self.rpc_url.clone(),
+        }
+    }
+}
+
+/// What the scheduler knows about one account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountState {
+    /// Slot the account was first watched at, where its first proof starts
+    pub watched_since: u64,
+    pub last_proof_end_slot: Option<u64>,
+    pub last_identifier: Option<String>,
+    /// First change not covered by a proof yet
+    pub pending_change_slot: Option<u64>,
+    pub proofs: u64,
+    pub failures: u64,
+}
+
+impl AccountState {
+    /// Slot the next proof of the account starts at
+    pub fn next_start_slot(&self) -> u64 {
+        self.last_proof_end_slot.unwrap_or(self.watched_since)
+    }
+}
+
+/// State kept across restarts, by account pubkey
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulerState {
+    pub accounts: BTreeMap<String, AccountState>,
+}
+
+impl SchedulerState {
+    /// Load the state, starting empty if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read(path)?;
+        serde_json::from_slice(&contents).map_err(|e| format!("Invalid state file {}: {}", path.display(), e).into())
+    }
+
+    /// Write the state so that a crash leaves either the old or the new state
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("tmp");
+        let contents = serde_json::to_vec_pretty(self)?;
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&tmp_path, path)?;
+
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Why a proof was scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofTrigger {
+    /// The account changed
+    Change,
+    /// The last proof reached `max_slots_since_last_proof`
+    MaxSlots,
+}
+
+impl fmt::Display for ProofTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofTrigger::Change => write!(f, "change"),
+            ProofTrigger::MaxSlots => write!(f, "max slots since last proof"),
+        }
+    }
+}
+
+/// A proof the scheduler decided to run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledProof {
+    pub account: String,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub trigger: ProofTrigger,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    slot: u64,
+    running: Vec<&'a ScheduledProof>,
+    accounts: &'a BTreeMap<String, AccountState>,
+}
+
+/// Decides which accounts get proofs, and when
+pub struct Scheduler {
+    policies: Vec<AccountPolicy>,
+    state: SchedulerState,
+    max_concurrent: usize,
+    retry_after_slots: u64,
+    slot: u64,
+    running: BTreeMap<String, ScheduledProof>,
+    /// Slot before which a failed account is not retried
+    retry_at: BTreeMap<String, u64>,
+}
+
+impl Scheduler {
+    /// Scheduler for the accounts of `config` at `slot`, continuing from `state`
+    ///
+    /// Accounts missing from the state start being watched at `slot`; accounts
+    /// no longer configured are dropped from it.
+    pub fn new(config: &SchedulerConfig, mut state: SchedulerState, slot: u64) -> Self {
+        state.accounts.retain(|pubkey, _| config.accounts.iter().any(|policy| &policy.pubkey == pubkey));
+        for policy in &config.accounts {
+            state
+                .accounts
+                .entry(policy.pubkey.clone())
+                .or_insert_with(|| AccountState { watched_since: slot, ..AccountState::default() });
+        }
+        Self {
+            policies: config.accounts.clone(),
+            state,
+            max_concurrent: config.max_concurrent_proofs,
+            retry_after_slots: config.retry_after_slots,
+            slot,
+            running: BTreeMap::new(),
+            retry_at: BTreeMap::new(),
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    pub fn state(&self) -> &SchedulerState {
+        &self.state
+    }
+
+    /// Proofs started and not completed or failed yet
+    pub fn running(&self) -> impl Iterator<Item = &ScheduledProof> {
+        self.running.values()
+    }
+
+    /// Move the clock to `slot`; the clock never goes back
+    pub fn advance(&mut self, slot: u64) {
+        self.slot = self.slot.max(slot);
+    }
+
+    /// Record a change of `account` at `slot`
+    ///
+    /// Returns `false` for accounts that are not watched and for changes the
+    /// last proof already covers.
+    pub fn observe_change(&mut self, account: &str, slot: u64) -> bool {
+        self.advance(slot);
+        let Some(state) = self.state.accounts.get_mut(account) else {
+            return false;
+        };
+        if state.last_proof_end_slot.is_some_and(|end_slot| slot <= end_slot) {
+            return false;
+        }
+        state.pending_change_slot = Some(state.pending_change_slot.map_or(slot, |pending| pending.min(slot)));
+        true
+    }
+
+    /// Why `policy`'s account is due at the current slot, if it is
+    fn trigger(&self, policy: &AccountPolicy, state: &AccountState) -> Option<ProofTrigger> {
+        let age = self.slot.saturating_sub(state.next_start_slot());
+        // A proof needs an end slot after its start slot
+        if age == 0 {
+            return None;
+        }
+        if policy.max_slots_since_last_proof.is_some_and(|max| age >= max) {
+            return Some(ProofTrigger::MaxSlots);
+        }
+        if policy.prove_on_change && state.pending_change_slot.is_some() && age >= policy.min_slots_between_proofs {
+            return Some(ProofTrigger::Change);
+        }
+        None
+    }
+
+    /// Start the proofs that are due, as far as the concurrency limit allows
+    pub fn due(&mut self) -> Vec<ScheduledProof> {
+        let mut candidates: Vec<(u64, ScheduledProof)> = self
+            .policies
+            .iter()
+            .filter(|policy| !self.running.contains_key(&policy.pubkey))
+            .filter(|policy| self.retry_at.get(&policy.pubkey).is_none_or(|&retry_at| self.slot >= retry_at))
+            .filter_map(|policy| {
+                let state = self.state.accounts.get(&policy.pubkey)?;
+                let trigger = self.trigger(policy, state)?;
+                let start_slot = state.next_start_slot();
+                let proof = ScheduledProof { account: policy.pubkey.clone(), start_slot, end_slot: self.slot, trigger };
+                Some((start_slot, proof))
+            })
+            .collect();
+        // Oldest proof first, then by pubkey so the order is stable
+        candidates.sort_by(|(a_start, a), (b_start, b)| a_start.cmp(b_start).then_with(|| a.account.cmp(&b.account)));
+
+        let free = self.max_concurrent.saturating_sub(self.running.len());
+        let started: Vec<ScheduledProof> = candidates.into_iter().take(free).map(|(_, proof)| proof).collect();
+        for proof in &started {
+            self.retry_at.remove(&proof.account);
+            self.running.insert(proof.account.clone(), proof.clone());
+        }
+        started
+    }
+
+    /// Record that `account`'s running proof was published, ending at `end_slot`
+    ///
+    /// `end_slot` can be later than scheduled when the node answered from a later slot.
+    pub fn complete(&mut self, account: &str, end_slot: u64, identifier: &str) {
+        self.running.remove(account);
+        let Some(state) = self.state.accounts.get_mut(account) else {
+            return;
+        };
+        state.last_proof_end_slot = Some(state.last_proof_end_slot.map_or(end_slot, |last| last.max(end_slot)));
+        state.last_identifier = Some(identifier.to_string());
+        if state.pending_change_slot.is_some_and(|pending| pending <= end_slot) {
+            state.pending_change_slot = None;
+        }
+        state.proofs += 1;
+    }
+
+    /// Record that `account`'s running proof failed; it is retried after `retry_after_slots`
+    pub fn fail(&mut self, account: &str) {
+        self.running.remove(account);
+        if let Some(state) = self.state.accounts.get_mut(account) {
+            state.failures += 1;
+            self.retry_at.insert(account.to_string(), self.slot + self.retry_after_slots);
+        }
+    }
+
+    /// Current slot, running proofs and account states as JSON
+    pub fn status_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&Status {
+            slot: self.slot,
+            running: self.running.values().collect(),
+            accounts: &self.state.accounts,
+        })
+    }
+}
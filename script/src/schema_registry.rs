@@ -0,0 +1,415 @@
+//! Confluent Schema Registry client and Avro encoding of proof envelopes
+//!
+//! Avro messages use the Confluent wire format: a zero magic byte, the
+//! big-endian schema id, followed by the Avro-encoded body.
+
+use crate::kafka::redacted;
+use apache_avro::types::{Record, Value as AvroValue};
+use apache_avro::{from_avro_datum, to_avro_datum, Schema};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use twine_types::proofs::{ProofData, SP1Proof, ZkProof};
+
+/// Magic byte prefixing every Confluent wire-format message
+pub const WIRE_FORMAT_MAGIC: u8 = 0;
+
+/// Subject the proof envelope schema is registered under
+pub const PROOF_ENVELOPE_SUBJECT: &str = "twine.solana.proofs-value";
+
+/// Avro schema for the proof envelope
+pub const PROOF_ENVELOPE_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "ProofEnvelope",
+    "namespace": "xyz.twine.solana",
+    "fields": [
+        {"name": "identifier", "type": "string"},
+        {"name": "proof_kind", "type": "string"},
+        {"name": "proof_type", "type": "string"},
+        {"name": "version", "type": "long"},
+        {"name": "proof", "type": "bytes"},
+        {"name": "public_values", "type": "bytes"},
+        {"name": "verification_key", "type": "bytes"}
+    ]
+}"#;
+
+/// Schema Registry connection settings
+#[derive(Clone)]
+pub struct SchemaRegistryConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for SchemaRegistryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaRegistryConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisterResponse {
+    id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaResponse {
+    schema: String,
+}
+
+/// Minimal Schema Registry client with a schema-id cache
+pub struct SchemaRegistryClient {
+    config: SchemaRegistryConfig,
+    http: reqwest::Client,
+    schemas: Mutex<HashMap<u32, Schema>>,
+}
+
+impl SchemaRegistryClient {
+    pub fn new(config: SchemaRegistryConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            schemas: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.username {
+            Some(username) => request.basic_auth(username, self.config.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// Register a schema under a subject (or look up the existing id) and return its id
+    pub async fn register(&self, subject: &str, schema: &Schema) -> Result<u32, Box<dyn Error>> {
+        let url = format!("{}/subjects/{}/versions", self.config.url.trim_end_matches('/'), subject);
+        let request = self
+            .http
+            .post(&url)
+            .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+            .json(&json!({ "schema": schema.canonical_form() }));
+
+        let response = self.with_auth(request).send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Schema registration for '{}' failed ({}): {}", subject, status, body).into());
+        }
+
+        let registered: RegisterResponse = response.json().await?;
+        self.schemas.lock().unwrap().insert(registered.id, schema.clone());
+        Ok(registered.id)
+    }
+
+    /// Fetch a schema by id, using the local cache when possible
+    pub async fn schema_by_id(&self, id: u32) -> Result<Schema, Box<dyn Error>> {
+        if let Some(schema) = self.schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+
+        let url = format!("{}/schemas/ids/{}", self.config.url.trim_end_matches('/'), id);
+        let response = self.with_auth(self.http.get(&url)).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("Schema id {} lookup failed ({})", id, response.status()).into());
+        }
+
+        let fetched: SchemaResponse = response.json().await?;
+        let schema = Schema::parse_str(&fetched.schema)?;
+        self.schemas.lock().unwrap().insert(id, schema.clone());
+        Ok(schema)
+    }
+}
+
+/// Parsed proof envelope schema
+pub fn proof_envelope_schema() -> Schema {
+    Schema::parse_str(PROOF_ENVELOPE_SCHEMA).expect("proof envelope schema is valid Avro")
+}
+
+/// Frame an Avro body in the Confluent wire format
+pub fn encode_wire_format(schema_id: u32, body: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(body.len() + 5);
+    framed.push(WIRE_FORMAT_MAGIC);
+    framed.extend_from_slice(&schema_id.to_be_bytes());
+    framed.extend_from_slice(body);
+    framed
+}
+
+/// Split a Confluent wire-format message into schema id and Avro body
+pub fn decode_wire_format(payload: &[u8]) -> Result<(u32, &[u8]), String> {
+    if payload.len() < 5 {
+        return Err(format!("Payload too short for wire format: {} bytes", payload.len()));
+    }
+    if payload[0] != WIRE_FORMAT_MAGIC {
+        return Err(format!("Unexpected magic byte: {:#04x}", payload[0]));
+    }
+    let schema_id = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+    Ok((schema_id, &payload[5..]))
+}
+
+/// Whether a payload looks like a Confluent wire-format message
+pub fn is_wire_format(payload: &[u8]) -> bool {
+    payload.len() >= 5 && payload[0] == WIRE_FORMAT_MAGIC
+}
+
+/// Encode a proof as an Avro body using the proof envelope schema
+pub fn encode_proof(schema: &Schema, proof: &ZkProof) -> Result<Vec<u8>, Box<dyn Error>> {
+    let ProofData::SP1(sp1_proof) = &proof.proof_data;
+
+    let mut record = Record::new(schema).ok_or("Proof envelope schema is not a record")?;
+    record.put("identifier", proof.identifier.clone());
+    record.put("proof_kind", serde_json::to_string(&proof.proof_kind)?);
+    record.put("proof_type", "SP1");
+    record.put("version", sp1_proof.version as i64);
+    record.put("proof", AvroValue::Bytes(sp1_proof.proof.clone()));
+    record.put("public_values", AvroValue::Bytes(sp1_proof.public_value.clone()));
+    record.put("verification_key", AvroValue::Bytes(sp1_proof.verification_key.to_vec()));
+
+    Ok(to_avro_datum(schema, record)?)
+}
+
+/// Decode an Avro body written with the given schema back into a proof
+pub fn decode_proof(schema: &Schema, mut body: &[u8]) -> Result<ZkProof, Box<dyn Error>> {
+    let value = from_avro_datum(schema, &mut body, None)?;
+    let AvroValue::Record(fields) = value else {
+        return Err("Avro payload is not a record".into());
+    };
+    let fields: HashMap<String, AvroValue> = fields.into_iter().collect();
+
+    let string_field = |name: &str| -> Result<String, Box<dyn Error>> {
+        match fields.get(name) {
+            Some(AvroValue::String(s)) => Ok(s.clone()),
+            _ => Err(format!("Missing or invalid field '{}'", name).into()),
+        }
+    };
+    let bytes_field = |name: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        match fields.get(name) {
+            Some(AvroValue::Bytes(b)) => Ok(b.clone()),
+            _ => Err(format!("Missing or invalid field '{}'", name).into()),
+        }
+    };
+
+    let proof_type = string_field("proof_type")?;
+    if proof_type != "SP1" {
+        return Err(format!("Unsupported proof type '{}'", proof_type).into());
+    }
+    let version = match fields.get("version") {
+        Some(AvroValue::Long(v)) => *v,
+        _ => return Err("Missing or invalid field 'version'".into()),
+    };
+    let verification_key: [u8; 32] = bytes_field("verification_key")?
+        .try_into()
+        .map_err(|_| "verification_key must be 32 bytes")?;
+
+    Ok(ZkProof {
+        identifier: string_field("identifier")?,
+        proof_kind: serde_json::from_str(&string_field("proof_kind")?)?,
+        proof_data: ProofData::SP1(SP1Proof {
+            version: version.try_into()?,
+            proof: bytes_field("proof")?,
+            public_value: bytes_field("public_values")?,
+            verification_key,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::envelope;
+    use crate::prove_jobs::ProofMode;
+
+    fn proof() -> ZkProof {
+        envelope("proof-1".to_string(), ProofMode::Groth16, vec![1, 2, 3], vec![4, 5], [7; 32])
+    }
+
+    #[test]
+    fn framing_puts_the_magic_byte_and_big_endian_id_before_the_body() {
+        let framed = encode_wire_format(0x0102_0304, b"avro");
+        assert_eq!(framed, [0, 1, 2, 3, 4, b'a', b'v', b'r', b'o']);
+        assert!(is_wire_format(&framed));
+        assert_eq!(decode_wire_format(&framed).unwrap(), (0x0102_0304, &b"avro"[..]));
+    }
+
+    #[test]
+    fn framing_round_trips_an_empty_body_and_extreme_ids() {
+        for schema_id in [0, 1, u32::MAX] {
+            let framed = encode_wire_format(schema_id, b"");
+            assert_eq!(framed.len(), 5);
+            assert_eq!(decode_wire_format(&framed).unwrap(), (schema_id, &[][..]));
+        }
+    }
+
+    #[test]
+    fn decoding_rejects_short_payloads_and_other_magic_bytes() {
+        for short in [&[][..], &[0], &[0, 0, 0, 1]] {
+            assert!(!is_wire_format(short));
+            let err = decode_wire_format(short).unwrap_err();
+            assert!(err.contains("too short"), "{}", err);
+        }
+        // A JSON payload starts with '{'
+        let json = br#"{"identifier":"proof-1"}"#;
+        assert!(!is_wire_format(json));
+        assert_eq!(decode_wire_format(json).unwrap_err(), "Unexpected magic byte: 0x7b");
+    }
+
+    #[test]
+    fn proofs_round_trip_through_the_envelope_schema() {
+        let schema = proof_envelope_schema();
+        let body = encode_proof(&schema, &proof()).unwrap();
+        let framed = encode_wire_format(42, &body);
+
+        let (schema_id, body) = decode_wire_format(&framed).unwrap();
+        assert_eq!(schema_id, 42);
+        let decoded = decode_proof(&schema, body).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(proof()).unwrap());
+    }
+
+    #[test]
+    fn a_truncated_body_does_not_decode() {
+        let schema = proof_envelope_schema();
+        let body = encode_proof(&schema, &proof()).unwrap();
+        assert!(decode_proof(&schema, &body[..body.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn debug_output_leaves_out_the_password() {
+        let config = SchemaRegistryConfig {
+            url: "http://registry:8081".to_string(),
+            username: Some("prover".to_string()),
+            password: Some("hunter2".to_string()),
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(debug.contains("prover"));
+    }
+
+    /// Registration and lookups against a local registry; needs axum from `test-util`
+    #[cfg(feature = "test-util")]
+    mod registry {
+        use super::*;
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::routing::{get, post};
+        use axum::{Json, Router};
+        use base64::{engine::general_purpose, Engine as _};
+        use serde_json::Value;
+        use std::sync::Arc;
+        use tokio::net::TcpListener;
+
+        /// Requests seen by the mock registry
+        #[derive(Default)]
+        struct Seen {
+            /// Subject, schema and `Authorization` header of each registration
+            registrations: Vec<(String, String, Option<String>)>,
+            lookups: Vec<u32>,
+            /// Status returned for registrations instead of success
+            fail_with: Option<StatusCode>,
+        }
+
+        type Shared = Arc<Mutex<Seen>>;
+
+        async fn register(
+            State(seen): State<Shared>,
+            Path(subject): Path<String>,
+            headers: HeaderMap,
+            body: String,
+        ) -> Result<Json<Value>, (StatusCode, String)> {
+            let mut seen = seen.lock().unwrap();
+            if let Some(status) = seen.fail_with {
+                return Err((status, r#"{"error_code":409,"message":"incompatible schema"}"#.to_string()));
+            }
+            let request: Value = serde_json::from_str(&body).unwrap();
+            let authorization = headers.get("authorization").map(|v| v.to_str().unwrap().to_string());
+            seen.registrations.push((subject, request["schema"].as_str().unwrap().to_string(), authorization));
+            Ok(Json(json!({ "id": 7 })))
+        }
+
+        async fn lookup(State(seen): State<Shared>, Path(id): Path<u32>) -> Result<Json<Value>, StatusCode> {
+            seen.lock().unwrap().lookups.push(id);
+            match id {
+                9 => Ok(Json(json!({ "schema": PROOF_ENVELOPE_SCHEMA }))),
+                _ => Err(StatusCode::NOT_FOUND),
+            }
+        }
+
+        async fn start(username: Option<&str>) -> (SchemaRegistryClient, Shared) {
+            let seen = Shared::default();
+            let app = Router::new()
+                .route("/subjects/:subject/versions", post(register))
+                .route("/schemas/ids/:id", get(lookup))
+                .with_state(seen.clone());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            // A trailing slash must not produce `//subjects`
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+
+            let client = SchemaRegistryClient::new(SchemaRegistryConfig {
+                url,
+                username: username.map(str::to_string),
+                password: username.map(|_| "secret".to_string()),
+            });
+            (client, seen)
+        }
+
+        #[tokio::test]
+        async fn registers_the_canonical_schema_under_the_subject() {
+            let (client, seen) = start(None).await;
+            let schema = proof_envelope_schema();
+            assert_eq!(client.register(PROOF_ENVELOPE_SUBJECT, &schema).await.unwrap(), 7);
+
+            let seen = seen.lock().unwrap();
+            assert_eq!(seen.registrations, [(PROOF_ENVELOPE_SUBJECT.to_string(), schema.canonical_form(), None)]);
+        }
+
+        #[tokio::test]
+        async fn sends_basic_auth_when_configured() {
+            let (client, seen) = start(Some("prover")).await;
+            client.register(PROOF_ENVELOPE_SUBJECT, &proof_envelope_schema()).await.unwrap();
+
+            let expected = format!("Basic {}", general_purpose::STANDARD.encode("prover:secret"));
+            assert_eq!(seen.lock().unwrap().registrations[0].2, Some(expected));
+        }
+
+        #[tokio::test]
+        async fn registered_schemas_are_cached_by_id() {
+            let (client, seen) = start(None).await;
+            let schema = proof_envelope_schema();
+            let id = client.register(PROOF_ENVELOPE_SUBJECT, &schema).await.unwrap();
+
+            assert_eq!(client.schema_by_id(id).await.unwrap(), schema);
+            assert!(seen.lock().unwrap().lookups.is_empty());
+        }
+
+        #[tokio::test]
+        async fn unknown_ids_are_fetched_once() {
+            let (client, seen) = start(None).await;
+            for _ in 0..3 {
+                assert_eq!(client.schema_by_id(9).await.unwrap(), proof_envelope_schema());
+            }
+            assert_eq!(seen.lock().unwrap().lookups, [9]);
+
+            let err = client.schema_by_id(10).await.unwrap_err();
+            assert!(err.to_string().contains("404"), "{}", err);
+            // Failed lookups are not cached
+            assert!(client.schema_by_id(10).await.is_err());
+            assert_eq!(seen.lock().unwrap().lookups, [9, 10, 10]);
+        }
+
+        #[tokio::test]
+        async fn a_rejected_registration_reports_the_status_and_body() {
+            let (client, seen) = start(None).await;
+            seen.lock().unwrap().fail_with = Some(StatusCode::CONFLICT);
+
+            let err = client.register(PROOF_ENVELOPE_SUBJECT, &proof_envelope_schema()).await.unwrap_err().to_string();
+            assert!(err.contains("409") && err.contains("incompatible schema"), "{}", err);
+            assert!(client.schema_by_id(7).await.is_err(), "a rejected schema must not be cached");
+        }
+    }
+}
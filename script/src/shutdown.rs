@@ -0,0 +1,50 @@
+//! Graceful shutdown on SIGINT/SIGTERM
+//!
+//! The first signal flips a watch channel so the caller can finish its
+//! in-flight work and exit cleanly; a second signal exits immediately.
+
+use tokio::sync::watch;
+
+/// Exit code used when a second signal forces the process to exit
+pub const FORCED_EXIT_CODE: i32 = 130;
+
+/// Listen for shutdown signals in the background
+///
+/// The returned receiver changes to `true` on the first SIGINT or SIGTERM.
+pub fn spawn_signal_handler() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        let _ = sender.send(true);
+        crate::errln!("\n🛑 Shutting down after in-flight work (signal again to force exit)");
+
+        wait_for_signal().await;
+        crate::errln!("\n💥 Forced exit");
+        std::process::exit(FORCED_EXIT_CODE);
+    });
+
+    receiver
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -1,92 +1,258 @@
-use crate::types::{AccountInfo, AccountInfoResponse, SlotResponse};
+use crate::types::{
+    AccountInfo, AccountInfoResponse, BlockTimeResponse, EpochScheduleResponse, SignatureStatus, SimulationResult,
+    SlotResponse,
+};
+use base64::{engine::general_purpose, Engine as _};
 use reqwest;
-use serde_json::json;
+use serde_json::{json, Value};
+use solana_stub_prover_lib::epoch::EpochSchedule;
 use std::error::Error;
 
-const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
+/// Public devnet endpoint, used unless another RPC URL is given
+pub const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
 
-/// Fetch account information from Solana RPC
-/// Note: Solana RPC may return data from a more recent slot than requested
-pub async fn fetch_account_info(
-    account: &str,
-    slot: Option<u64>,
-) -> Result<(AccountInfo, u64), Box<dyn Error>> {
-    let client = reqwest::Client::new();
+/// JSON-RPC client of a Solana node
+#[derive(Debug, Clone)]
+pub struct SolanaRpcClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl SolanaRpcClient {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), client: reqwest::Client::new() }
+    }
     
-    // Build params based on whether we want a specific slot
-    let params = if let Some(target_slot) = slot {
-        // Request account info with minContextSlot to ensure we get data at or after the target slot
-        json!([
-            account,
-            {
-                "encoding": "base64",
-                "commitment": "confirmed",
-                "minContextSlot": target_slot
-            }
-        ])
-    } else {
-        json!([
-            account,
-            {
-                "encoding": "base64",
-                "commitment": "confirmed"
+    pub fn devnet() -> Self {
+        Self::new(DEVNET_RPC_URL)
+    }
+    
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    
+    /// Fetch account information from Solana RPC
+    /// Note: Solana RPC may return data from a more recent slot than requested
+    pub async fn fetch_account_info(
+        &self,
+        account: &str,
+        slot: Option<u64>,
+    ) -> Result<(AccountInfo, u64), Box<dyn Error>> {
+        match self.get_account_info(account, slot).await? {
+            (Some(account_info), actual_slot) => Ok((account_info, actual_slot)),
+            (None, _) => Err("Account not found".into()),
+        }
+    }
+    
+    /// Fetch account information, `None` if the account does not exist at the slot read
+    pub async fn get_account_info(
+        &self,
+        account: &str,
+        slot: Option<u64>,
+    ) -> Result<(Option<AccountInfo>, u64), Box<dyn Error>> {
+        // Build params based on whether we want a specific slot
+        let params = if let Some(target_slot) = slot {
+            // Request account info with minContextSlot to ensure we get data at or after the target slot
+            json!([
+                account,
+                {
+                    "encoding": "base64",
+                    "commitment": "confirmed",
+                    "minContextSlot": target_slot
+                }
+            ])
+        } else {
+            json!([
+                account,
+                {
+                    "encoding": "base64",
+                    "commitment": "confirmed"
+                }
+            ])
+        };
+        
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getAccountInfo",
+            "params": params
+        });
+        
+        let response = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?;
+        
+        let account_response: AccountInfoResponse = response.json().await?;
+        
+        let actual_slot = account_response.result.context.slot;
+        
+        // Warn if we got data from a different slot than requested
+        if let Some(target_slot) = slot {
+            if actual_slot != target_slot {
+                eprintln!(
+                    "Warning: Requested slot {} but got data from slot {} (difference: {})",
+                    target_slot,
+                    actual_slot,
+                    actual_slot as i64 - target_slot as i64
+                );
+                eprintln!(
+                    "Note: Solana RPC returns the latest available data, historical slot data may not be available"
+                );
             }
-        ])
-    };
+        }
+        
+        Ok((account_response.result.value, actual_slot))
+    }
     
-    let request = json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getAccountInfo",
-        "params": params
-    });
+    /// Get current slot from Solana RPC
+    pub async fn get_current_slot(&self) -> Result<u64, Box<dyn Error>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getSlot",
+            "params": [{"commitment": "confirmed"}]
+        });
+        
+        let response = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?;
+        
+        let slot_response: SlotResponse = response.json().await?;
+        Ok(slot_response.result)
+    }
     
-    let response = client
-        .post(DEVNET_RPC_URL)
-        .json(&request)
-        .send()
-        .await?;
+    /// Get the cluster's epoch schedule
+    pub async fn get_epoch_schedule(&self) -> Result<EpochSchedule, Box<dyn Error>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getEpochSchedule"
+        });
+        
+        let response = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?;
+        
+        let schedule = response.json::<EpochScheduleResponse>().await?.result;
+        Ok(EpochSchedule {
+            slots_per_epoch: schedule.slots_per_epoch,
+            warmup: schedule.warmup,
+            first_normal_epoch: schedule.first_normal_epoch,
+            first_normal_slot: schedule.first_normal_slot,
+        })
+    }
     
-    let account_response: AccountInfoResponse = response.json().await?;
+    /// Get the estimated production time of a slot as a Unix timestamp in seconds
+    ///
+    /// Returns `None` when the RPC node has no block time for the slot
+    /// (skipped slot or pruned history).
+    pub async fn get_block_time(&self, slot: u64) -> Result<Option<i64>, Box<dyn Error>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getBlockTime",
+            "params": [slot]
+        });
+        
+        let response = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?;
+        
+        let block_time_response: BlockTimeResponse = response.json().await?;
+        Ok(block_time_response.result)
+    }
     
-    let actual_slot = account_response.result.context.slot;
+    /// Slots of the most recent transactions touching `account`, newest first
+    ///
+    /// Returns up to `limit` (at most 1000) slots, at or before the node's
+    /// current slot, including failed transactions, which still charge the fee payer.
+    pub async fn get_signature_slots(&self, account: &str, limit: usize) -> Result<Vec<u64>, Box<dyn Error>> {
+        let params = json!([account, {"commitment": "confirmed", "limit": limit}]);
+        let result = self.call("getSignaturesForAddress", params).await?;
+        let signatures = result.as_array().ok_or("getSignaturesForAddress returned no signatures")?;
+        Ok(signatures.iter().filter_map(|signature| signature["slot"].as_u64()).collect())
+    }
     
-    // Warn if we got data from a different slot than requested
-    if let Some(target_slot) = slot {
-        if actual_slot != target_slot {
-            eprintln!(
-                "Warning: Requested slot {} but got data from slot {} (difference: {})",
-                target_slot,
-                actual_slot,
-                actual_slot as i64 - target_slot as i64
-            );
-            eprintln!("Note: Solana RPC returns the latest available data, historical slot data may not be available");
+    /// Call `method` and return its `result`, turning a JSON-RPC error into an error with the node's logs
+    async fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        });
+        
+        let response: Value = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        if let Some(error) = response.get("error") {
+            let mut message = format!("{} failed: {}", method, error["message"].as_str().unwrap_or("unknown error"));
+            // Failed preflight simulations carry the program logs
+            for log in error["data"]["logs"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                message.push_str("\n  ");
+                message.push_str(log);
+            }
+            return Err(message.into());
         }
+        Ok(response["result"].clone())
     }
     
-    match account_response.result.value {
-        Some(account_info) => Ok((account_info, actual_slot)),
-        None => Err("Account not found".into()),
+    /// Latest blockhash for signing transactions
+    pub async fn get_latest_blockhash(&self) -> Result<[u8; 32], Box<dyn Error>> {
+        let result = self.call("getLatestBlockhash", json!([{"commitment": "confirmed"}])).await?;
+        let blockhash = result["value"]["blockhash"].as_str().ok_or("getLatestBlockhash returned no blockhash")?;
+        let bytes = bs58::decode(blockhash).into_vec()?;
+        Ok(bytes.try_into().map_err(|_| format!("Invalid blockhash {}", blockhash))?)
+    }
+    
+    /// Lamports an account of `space` bytes needs to be rent-exempt
+    pub async fn get_minimum_balance_for_rent_exemption(&self, space: u64) -> Result<u64, Box<dyn Error>> {
+        let result = self.call("getMinimumBalanceForRentExemption", json!([space])).await?;
+        Ok(result.as_u64().ok_or("getMinimumBalanceForRentExemption returned no balance")?)
     }
-}
-
-/// Get current slot from Solana RPC
-pub async fn get_current_slot() -> Result<u64, Box<dyn Error>> {
-    let client = reqwest::Client::new();
     
-    let request = json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getSlot",
-        "params": [{"commitment": "confirmed"}]
-    });
+    /// Send a signed transaction after a preflight simulation, returning its signature
+    pub async fn send_transaction(&self, transaction: &[u8]) -> Result<String, Box<dyn Error>> {
+        let params = json!([
+            general_purpose::STANDARD.encode(transaction),
+            {"encoding": "base64", "preflightCommitment": "confirmed"}
+        ]);
+        let result = self.call("sendTransaction", params).await?;
+        Ok(result.as_str().ok_or("sendTransaction returned no signature")?.to_string())
+    }
     
-    let response = client
-        .post(DEVNET_RPC_URL)
-        .json(&request)
-        .send()
-        .await?;
+    /// Simulate a signed transaction without sending it
+    pub async fn simulate_transaction(&self, transaction: &[u8]) -> Result<SimulationResult, Box<dyn Error>> {
+        let params = json!([
+            general_purpose::STANDARD.encode(transaction),
+            {"encoding": "base64", "commitment": "confirmed", "sigVerify": true}
+        ]);
+        let result = self.call("simulateTransaction", params).await?;
+        Ok(serde_json::from_value(result["value"].clone())?)
+    }
     
-    let slot_response: SlotResponse = response.json().await?;
-    Ok(slot_response.result)
-}
\ No newline at end of file
+    /// Status of a transaction, `None` while the node has not seen it
+    pub async fn get_signature_status(&self, signature: &str) -> Result<Option<SignatureStatus>, Box<dyn Error>> {
+        let result = self.call("getSignatureStatuses", json!([[signature]])).await?;
+        Ok(serde_json::from_value(result["value"][0].clone())?)
+    }
+}
+
+impl Default for SolanaRpcClient {
+    fn default() -> Self {
+        Self::devnet()
+    }
+}
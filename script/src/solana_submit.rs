@@ -0,0 +1,391 @@
+//! Submitting Groth16 proofs to a verifier program on Solana
+//!
+//! The verifier program takes Anchor-style instructions, each starting with
+//! the first 8 bytes of `sha256("global:<name>")`. The proof payload is the
+//! program vkey hash (32 bytes) followed by the public values and the proof
+//! bytes (verifier selector and encoded proof), each prefixed with its length
+//! as a little-endian `u32`:
+//!
+//! - `verify(payload)` verifies a payload carried in the instruction itself
+//! - `init_buffer(len: u32)` prepares a program-owned buffer account for a
+//!   payload of `len` bytes, with the signer as its authority
+//! - `write_buffer(offset: u32, bytes)` writes part of the payload
+//! - `verify_buffer()` verifies the buffered payload and closes the buffer,
+//!   refunding its rent to the authority
+//!
+//! A transaction is at most [`MAX_TRANSACTION_SIZE`] bytes. Payloads that do
+//! not fit in one `verify` transaction go through a buffer account created in
+//! the same transaction as `init_buffer`. Transactions are built here as
+//! legacy messages and signed with ed25519 keys (see [`crate::keys`]); sending them is left to
+//! the [`SolanaRpcClient`](crate::solana::SolanaRpcClient). Requires the
+//! `solana-submit` feature.
+
+use crate::envelope::decode_json;
+use crate::verify::VerifyingKeySource;
+use crate::wrapped_proof::WrappedProof;
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use sp1_sdk::HashableKey;
+use std::fs;
+use std::path::Path;
+use twine_types::proofs::ProofData;
+
+/// Largest serialized transaction a node accepts
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// Address of the system program
+pub const SYSTEM_PROGRAM_ID: Pubkey = [0; 32];
+
+pub type Pubkey = [u8; 32];
+
+/// Instruction discriminator of `name`, the first 8 bytes of `sha256("global:<name>")`
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let digest = Sha256::digest(format!("global:{}", name));
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&digest[..8]);
+    discriminator
+}
+
+/// Base58 form of a pubkey
+pub fn encode_pubkey(pubkey: &Pubkey) -> String {
+    bs58::encode(pubkey).into_string()
+}
+
+pub fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    let bytes = bs58::decode(s.trim()).into_vec().map_err(|e| format!("Invalid pubkey '{}': {}", s, e))?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| format!("Invalid pubkey '{}': {} bytes instead of 32", s, bytes.len()))
+}
+
+/// What the verifier program needs to verify a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Groth16Proof {
+    /// Program vkey hash committed by the proof
+    pub vkey_hash: [u8; 32],
+    pub public_values: Vec<u8>,
+    /// Verifier selector followed by the encoded proof
+    pub proof_bytes: Vec<u8>,
+}
+
+impl Groth16Proof {
+    /// Load a Groth16 proof from `last_proof.json` or a Kafka message such as `last_kafka_message.json`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let wrapped = match decode_json(&bytes) {
+            Ok(decoded) => {
+                let ProofData::SP1(sp1_proof) = decoded.proof.proof_data;
+                WrappedProof::decode(&sp1_proof.proof).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            Err(_) => WrappedProof::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        };
+        Self::from_wrapped(&wrapped).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_wrapped(wrapped: &WrappedProof) -> Result<Self, String> {
+        if wrapped.kind() != "groth16" {
+            return Err(format!("a {} proof cannot be verified on-chain, generate a Groth16 proof", wrapped.kind()));
+        }
+        let vkey_hash = wrapped
+            .snark()
+            .and_then(|snark| snark.vkey_hash)
+            .and_then(|hash| hex::decode(hash.trim_start_matches("0x")).ok())
+            .and_then(|hash| hash.try_into().ok())
+            .ok_or("the proof does not commit to a program vkey hash")?;
+        Ok(Self { vkey_hash, public_values: wrapped.public_values().to_vec(), proof_bytes: wrapped.bundle.bytes() })
+    }
+
+    /// Fail unless the proof was generated for the program with this key
+    pub fn expect_program(&self, source: &VerifyingKeySource) -> Result<(), String> {
+        let expected = match source {
+            VerifyingKeySource::Key(vk) => vk.bytes32(),
+            VerifyingKeySource::Hash(hash) => hash.clone(),
+        };
+        let actual = format!("0x{}", hex::encode(self.vkey_hash));
+        if !expected.eq_ignore_ascii_case(&actual) {
+            return Err(format!("the proof is for program {}, not {}", actual, expected));
+        }
+        Ok(())
+    }
+
+    /// Vkey hash, public values and proof bytes as the verifier program reads them
+    pub fn payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(40 + self.public_values.len() + self.proof_bytes.len());
+        payload.extend_from_slice(&self.vkey_hash);
+        push_bytes(&mut payload, &self.public_values);
+        push_bytes(&mut payload, &self.proof_bytes);
+        payload
+    }
+}
+
+/// Append `bytes` with a little-endian `u32` length prefix
+fn push_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    data.extend_from_slice(bytes);
+}
+
+/// An account an instruction reads or writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl AccountMeta {
+    pub fn writable(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: true }
+    }
+
+    pub fn readonly(pubkey: Pubkey, is_signer: bool) -> Self {
+        Self { pubkey, is_signer, is_writable: false }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+/// `verify` with the whole payload in the instruction
+pub fn verify_instruction(program_id: &Pubkey, proof: &Groth16Proof) -> Instruction {
+    let mut data = discriminator("verify").to_vec();
+    data.extend(proof.payload());
+    Instruction { program_id: *program_id, accounts: Vec::new(), data }
+}
+
+/// System program `CreateAccount` of a new account owned by `owner`
+pub fn create_account_instruction(
+    payer: &Pubkey,
+    account: &Pubkey,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Instruction {
+    let mut data = 0u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data.extend_from_slice(&space.to_le_bytes());
+    data.extend_from_slice(owner);
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID,
+        accounts: vec![AccountMeta::writable(*payer, true), AccountMeta::writable(*account, true)],
+        data,
+    }
+}
+
+/// `init_buffer` for a payload of `len` bytes
+pub fn init_buffer_instruction(program_id: &Pubkey, buffer: &Pubkey, authority: &Pubkey, len: u32) -> Instruction {
+    let mut data = discriminator("init_buffer").to_vec();
+    data.extend_from_slice(&len.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::writable(*buffer, false), AccountMeta::readonly(*authority, true)],
+        data,
+    }
+}
+
+/// `write_buffer` of `bytes` at `offset` into the payload
+pub fn write_buffer_instruction(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    authority: &Pubkey,
+    offset: u32,
+    bytes: &[u8],
+) -> Instruction {
+    let mut data = discriminator("write_buffer").to_vec();
+    data.extend_from_slice(&offset.to_le_bytes());
+    push_bytes(&mut data, bytes);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::writable(*buffer, false), AccountMeta::readonly(*authority, true)],
+        data,
+    }
+}
+
+/// `verify_buffer`, which also closes the buffer into the authority
+pub fn verify_buffer_instruction(program_id: &Pubkey, buffer: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::writable(*buffer, false), AccountMeta::writable(*authority, true)],
+        data: discriminator("verify_buffer").to_vec(),
+    }
+}
+
+/// Append `n` as a compact-u16 (1 to 3 bytes, 7 bits each)
+fn push_compact_u16(data: &mut Vec<u8>, n: usize) {
+    let mut n = n as u16;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            data.push(byte);
+            return;
+        }
+        data.push(byte | 0x80);
+    }
+}
+
+/// A legacy transaction message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Required signatures, read-only signed and read-only unsigned accounts
+    pub header: [u8; 3],
+    /// Writable signers, read-only signers, writable and read-only non-signers; the fee payer first
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: [u8; 32],
+    instructions: Vec<(u8, Vec<u8>, Vec<u8>)>,
+}
+
+impl Message {
+    pub fn new(instructions: &[Instruction], payer: &Pubkey, recent_blockhash: [u8; 32]) -> Self {
+        // (pubkey, is_signer, is_writable), merged per key, fee payer first
+        let mut keys: Vec<(Pubkey, bool, bool)> = vec![(*payer, true, true)];
+        let metas = instructions.iter().flat_map(|instruction| {
+            let accounts =
+                instruction.accounts.iter().map(|account| (account.pubkey, account.is_signer, account.is_writable));
+            accounts.chain([(instruction.program_id, false, false)])
+        });
+        for (pubkey, is_signer, is_writable) in metas {
+            match keys.iter_mut().find(|(key, _, _)| *key == pubkey) {
+                Some(key) => {
+                    key.1 |= is_signer;
+                    key.2 |= is_writable;
+                }
+                None => keys.push((pubkey, is_signer, is_writable)),
+            }
+        }
+        // Stable, so the fee payer stays first
+        keys.sort_by_key(|&(_, is_signer, is_writable)| (!is_signer, !is_writable));
+
+        let count = |signer: bool, writable: bool| {
+            keys.iter().filter(|&&(_, is_signer, is_writable)| is_signer == signer && is_writable == writable).count()
+        };
+        let header = [
+            keys.iter().filter(|(_, is_signer, _)| *is_signer).count() as u8,
+            count(true, false) as u8,
+            count(false, false) as u8,
+        ];
+        let account_keys: Vec<Pubkey> = keys.into_iter().map(|(pubkey, _, _)| pubkey).collect();
+        let index = |pubkey: &Pubkey| account_keys.iter().position(|key| key == pubkey).unwrap_or_default() as u8;
+        let instructions = instructions
+            .iter()
+            .map(|instruction| {
+                let accounts = instruction.accounts.iter().map(|account| index(&account.pubkey)).collect();
+                (index(&instruction.program_id), accounts, instruction.data.clone())
+            })
+            .collect();
+        Self { header, account_keys, recent_blockhash, instructions }
+    }
+
+    /// Accounts that must sign, in signature order
+    pub fn signers(&self) -> &[Pubkey] {
+        &self.account_keys[..self.header[0] as usize]
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut data = self.header.to_vec();
+        push_compact_u16(&mut data, self.account_keys.len());
+        for key in &self.account_keys {
+            data.extend_from_slice(key);
+        }
+        data.extend_from_slice(&self.recent_blockhash);
+        push_compact_u16(&mut data, self.instructions.len());
+        for (program_index, accounts, instruction_data) in &self.instructions {
+            data.push(*program_index);
+            push_compact_u16(&mut data, accounts.len());
+            data.extend_from_slice(accounts);
+            push_compact_u16(&mut data, instruction_data.len());
+            data.extend_from_slice(instruction_data);
+        }
+        data
+    }
+
+    /// Size of the signed transaction
+    pub fn transaction_size(&self) -> usize {
+        let signatures = self.signers().len();
+        let mut prefix = Vec::new();
+        push_compact_u16(&mut prefix, signatures);
+        prefix.len() + 64 * signatures + self.serialize().len()
+    }
+
+    /// Sign with `keypairs`, which must include every signer
+    pub fn sign(&self, keypairs: &[&SigningKey]) -> Result<Vec<u8>, String> {
+        let message = self.serialize();
+        let mut transaction = Vec::new();
+        push_compact_u16(&mut transaction, self.signers().len());
+        for signer in self.signers() {
+            let keypair = keypairs
+                .iter()
+                .find(|keypair| keypair.verifying_key().as_bytes() == signer)
+                .ok_or_else(|| format!("No keypair for signer {}", encode_pubkey(signer)))?;
+            transaction.extend_from_slice(&keypair.sign(&message).to_bytes());
+        }
+        transaction.extend(message);
+        Ok(transaction)
+    }
+}
+
+/// Whether `instructions` fit in one transaction paid by `payer`
+pub fn fits(instructions: &[Instruction], payer: &Pubkey) -> bool {
+    Message::new(instructions, payer, [0; 32]).transaction_size() <= MAX_TRANSACTION_SIZE
+}
+
+/// `write_buffer` instructions of `payload`, each as large as a transaction allows
+pub fn write_buffer_instructions(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    authority: &Pubkey,
+    payload: &[u8],
+) -> Vec<Instruction> {
+    let empty = write_buffer_instruction(program_id, buffer, authority, 0, &[]);
+    let overhead = Message::new(&[empty], authority, [0; 32]).transaction_size();
+    // The data length prefix grows from 1 to 2 bytes once the data passes 127 bytes
+    let chunk_size = MAX_TRANSACTION_SIZE - overhead - 1;
+    payload
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(index, chunk)| {
+            write_buffer_instruction(program_id, buffer, authority, (index * chunk_size) as u32, chunk)
+        })
+        .collect()
+}
+
+/// Transactions that submit one proof, in the order they must land
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmissionPlan {
+    /// One `verify` transaction
+    Direct(Instruction),
+    /// Create and initialize the buffer, fill it, then `verify_buffer`
+    Buffered {
+        buffer: Pubkey,
+        /// Space of the buffer account: the authority followed by the payload
+        space: u64,
+        writes: Vec<Instruction>,
+        verify: Instruction,
+    },
+}
+
+impl SubmissionPlan {
+    /// `Direct` if the proof fits in one transaction, otherwise `Buffered` through `buffer`
+    pub fn new(program_id: &Pubkey, payer: &Pubkey, buffer: &Pubkey, proof: &Groth16Proof) -> Self {
+        let verify = verify_instruction(program_id, proof);
+        if fits(std::slice::from_ref(&verify), payer) {
+            return SubmissionPlan::Direct(verify);
+        }
+        let payload = proof.payload();
+        SubmissionPlan::Buffered {
+            buffer: *buffer,
+            space: 32 + payload.len() as u64,
+            writes: write_buffer_instructions(program_id, buffer, payer, &payload),
+            verify: verify_buffer_instruction(program_id, buffer, payer),
+        }
+    }
+
+    /// Number of transactions the plan sends, including the buffer setup
+    pub fn transactions(&self) -> usize {
+        match self {
+            SubmissionPlan::Direct(_) => 1,
+            SubmissionPlan::Buffered { writes, .. } => writes.len() + 2,
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Local store-and-forward spool for proofs the broker could not accept
+//!
+//! When publishing fails with a retryable error after all retries, the
+//! encoded message is written to the spool directory instead, one JSON file
+//! per message named by a monotonically increasing sequence number. The
+//! publisher drains the spool in that order before sending anything new, so
+//! messages reach the topic in the order they were produced.
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default upper bound for the total size of the spool directory
+pub const DEFAULT_MAX_SPOOL_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// A message waiting in the spool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledMessage {
+    pub topic: String,
+    pub key: String,
+    pub content_type: String,
+    pub spooled_at: String,
+    /// Record timestamp in milliseconds to publish the message with
+    #[serde(default)]
+    pub timestamp_ms: Option<i64>,
+    /// Base64-encoded message payload
+    pub payload: String,
+}
+
+impl SpooledMessage {
+    pub fn new(topic: &str, key: &str, content_type: &str, payload: &[u8], timestamp_ms: Option<i64>) -> Self {
+        Self {
+            topic: topic.to_string(),
+            key: key.to_string(),
+            content_type: content_type.to_string(),
+            spooled_at: Utc::now().to_rfc3339(),
+            timestamp_ms,
+            payload: general_purpose::STANDARD.encode(payload),
+        }
+    }
+
+    /// Decoded message payload
+    pub fn payload_bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        general_purpose::STANDARD.decode(&self.payload)
+    }
+}
+
+/// Error reading or writing the spool
+#[derive(Debug)]
+pub enum SpoolError {
+    /// Accepting the message would exceed the size limit; older messages are never evicted
+    Full { size: u64, limit: u64 },
+    /// A spool file that is not a complete message, e.g. after a crash or manual edit
+    Corrupt {
+        path: PathBuf,
+        reason: String,
+    },
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SpoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpoolError::Full { size, limit } => write!(
+                f,
+                "Spool is full: {} bytes would exceed the limit of {} bytes",
+                size, limit
+            ),
+            SpoolError::Corrupt { path, reason } => {
+                write!(f, "Spooled message {} is corrupt: {}", path.display(), reason)
+            }
+            SpoolError::Io(e) => write!(f, "Spool I/O error: {}", e),
+            SpoolError::Json(e) => write!(f, "Spooled message JSON error: {}", e),
+        }
+    }
+}
+
+impl Error for SpoolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpoolError::Full { .. } | SpoolError::Corrupt { .. } => None,
+            SpoolError::Io(e) => Some(e),
+            SpoolError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for SpoolError {
+    fn from(e: std::io::Error) -> Self {
+        SpoolError::Io(e)
+    }
+}
+
+/// Spool directory with a size limit
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl Spool {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Spooled message files, oldest first
+    pub fn pending(&self) -> Result<Vec<PathBuf>, SpoolError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| sequence_of(path).is_some())
+            .collect();
+        // Names are zero-padded sequence numbers, so lexical order is spool order
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Whether no messages are waiting
+    pub fn is_empty(&self) -> Result<bool, SpoolError> {
+        Ok(self.pending()?.is_empty())
+    }
+
+    /// Total size of the spooled message files in bytes
+    pub fn size(&self) -> Result<u64, SpoolError> {
+        let mut total = 0;
+        for path in self.pending()? {
+            total += fs::metadata(&path)?.len();
+        }
+        Ok(total)
+    }
+
+    /// Append a message and return its file path
+    ///
+    /// Fails with `SpoolError::Full` instead of evicting older messages when
+    /// the size limit would be exceeded.
+    pub fn push(&self, message: &SpooledMessage) -> Result<PathBuf, SpoolError> {
+        fs::create_dir_all(&self.dir)?;
+
+        let contents = serde_json::to_vec_pretty(message).map_err(SpoolError::Json)?;
+        let pending = self.pending()?;
+
+        let mut size = contents.len() as u64;
+        for path in &pending {
+            size += fs::metadata(path)?.len();
+        }
+        if size > self.max_bytes {
+            return Err(SpoolError::Full { size, limit: self.max_bytes });
+        }
+
+        let next = pending
+            .last()
+            .and_then(|path| sequence_of(path))
+            .map_or(0, |sequence| sequence + 1);
+        let path = self.dir.join(format!("{:020}.json", next));
+        let tmp_path = path.with_extension("tmp");
+
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(path)
+    }
+
+    /// Read a spooled message
+    ///
+    /// Fails with `SpoolError::Corrupt` when the file is not valid JSON or its
+    /// payload is not valid base64.
+    pub fn read(&self, path: &Path) -> Result<SpooledMessage, SpoolError> {
+        let corrupt = |reason: String| SpoolError::Corrupt { path: path.to_path_buf(), reason };
+        let message: SpooledMessage = serde_json::from_slice(&fs::read(path)?).map_err(|e| corrupt(e.to_string()))?;
+        message.payload_bytes().map_err(|e| corrupt(format!("invalid payload: {}", e)))?;
+        Ok(message)
+    }
+
+    /// Move a corrupt message out of the spool so it no longer blocks draining
+    ///
+    /// The file is kept next to the spooled messages with a `.corrupt` suffix
+    /// for inspection; returns its new path.
+    pub fn set_aside(&self, path: &Path) -> Result<PathBuf, SpoolError> {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".corrupt");
+        let kept = path.with_file_name(name);
+        fs::rename(path, &kept)?;
+        Ok(kept)
+    }
+
+    /// Remove a message once it has been delivered
+    pub fn remove(&self, path: &Path) -> Result<(), SpoolError> {
+        Ok(fs::remove_file(path)?)
+    }
+}
+
+/// Sequence number of a spool file, or `None` for unrelated files
+fn sequence_of(path: &Path) -> Option<u64> {
+    if path.extension()? != "json" {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A spool in an empty directory for one test
+    fn test_spool(name: &str, max_bytes: u64) -> Spool {
+        let dir = std::env::temp_dir().join(format!("spool-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Spool::new(dir, max_bytes)
+    }
+
+    /// A message whose file size depends only on the length of its key
+    fn message(key: &str) -> SpooledMessage {
+        SpooledMessage {
+            spooled_at: "2026-01-01T00:00:00+00:00".to_string(),
+            ..SpooledMessage::new("proofs", key, "application/json", key.as_bytes(), Some(1_700_000_000_000))
+        }
+    }
+
+    fn keys(spool: &Spool) -> Vec<String> {
+        spool.pending().unwrap().iter().map(|path| spool.read(path).unwrap().key).collect()
+    }
+
+    #[test]
+    fn a_missing_directory_is_an_empty_spool() {
+        let spool = test_spool("missing", DEFAULT_MAX_SPOOL_BYTES);
+        assert!(spool.is_empty().unwrap());
+        assert_eq!(spool.size().unwrap(), 0);
+    }
+
+    #[test]
+    fn messages_drain_in_the_order_they_were_spooled() {
+        let spool = test_spool("order", DEFAULT_MAX_SPOOL_BYTES);
+        // Enough messages that sequence numbers gain a digit
+        let spooled: Vec<String> = (0..12).map(|i| format!("proof-{}", i)).collect();
+        for key in &spooled {
+            spool.push(&message(key)).unwrap();
+        }
+        assert_eq!(keys(&spool), spooled);
+
+        let first = spool.read(&spool.pending().unwrap()[0]).unwrap();
+        assert_eq!(first.topic, "proofs");
+        assert_eq!(first.content_type, "application/json");
+        assert_eq!(first.timestamp_ms, Some(1_700_000_000_000));
+        assert_eq!(first.payload_bytes().unwrap(), b"proof-0");
+    }
+
+    #[test]
+    fn delivered_messages_are_removed_and_new_ones_queue_behind_the_rest() {
+        let spool = test_spool("remove", DEFAULT_MAX_SPOOL_BYTES);
+        for key in ["a", "b", "c"] {
+            spool.push(&message(key)).unwrap();
+        }
+
+        // Deliver the oldest, as the publisher does once the broker acknowledges it
+        let oldest = spool.pending().unwrap().remove(0);
+        spool.remove(&oldest).unwrap();
+        assert!(!oldest.exists());
+        spool.push(&message("d")).unwrap();
+        assert_eq!(keys(&spool), ["b", "c", "d"]);
+
+        for path in spool.pending().unwrap() {
+            spool.remove(&path).unwrap();
+        }
+        assert!(spool.is_empty().unwrap());
+    }
+
+    #[test]
+    fn corrupt_files_are_reported_and_can_be_set_aside() {
+        let spool = test_spool("corrupt", DEFAULT_MAX_SPOOL_BYTES);
+        spool.push(&message("a")).unwrap();
+        let truncated = spool.push(&message("b")).unwrap();
+        let bad_payload = spool.push(&message("c")).unwrap();
+        spool.push(&message("d")).unwrap();
+
+        // A message cut short, e.g. by a full disk
+        let contents = fs::read(&truncated).unwrap();
+        fs::write(&truncated, &contents[..contents.len() / 2]).unwrap();
+        let mut edited = spool.read(&bad_payload).unwrap();
+        edited.payload = "not base64!".to_string();
+        fs::write(&bad_payload, serde_json::to_vec(&edited).unwrap()).unwrap();
+
+        for path in [&truncated, &bad_payload] {
+            match spool.read(path) {
+                Err(SpoolError::Corrupt { path: reported, .. }) => assert_eq!(&reported, path),
+                other => panic!("expected a corrupt message, got {:?}", other),
+            }
+            let kept = spool.set_aside(path).unwrap();
+            assert!(kept.exists() && !path.exists());
+        }
+        assert_eq!(keys(&spool), ["a", "d"]);
+    }
+
+    #[test]
+    fn partial_writes_and_unrelated_files_are_ignored() {
+        let spool = test_spool("partial", DEFAULT_MAX_SPOOL_BYTES);
+        let first = spool.push(&message("a")).unwrap();
+        // A write interrupted before the rename leaves only the temporary file
+        fs::write(first.with_file_name(format!("{:020}.tmp", 1)), b"{\"topic\":").unwrap();
+        fs::write(spool.dir().join("notes.json"), b"{}").unwrap();
+
+        assert_eq!(spool.pending().unwrap(), [first]);
+        spool.push(&message("b")).unwrap();
+        assert_eq!(keys(&spool), ["a", "b"]);
+    }
+
+    #[test]
+    fn a_full_spool_refuses_new_messages() {
+        let probe = test_spool("probe", DEFAULT_MAX_SPOOL_BYTES);
+        let size = fs::metadata(probe.push(&message("a")).unwrap()).unwrap().len();
+
+        let spool = test_spool("full", 2 * size);
+        spool.push(&message("a")).unwrap();
+        spool.push(&message("b")).unwrap();
+        assert_eq!(spool.size().unwrap(), 2 * size);
+        match spool.push(&message("c")) {
+            Err(SpoolError::Full { size: wanted, limit }) => {
+                assert_eq!(wanted, 3 * size);
+                assert_eq!(limit, 2 * size);
+            }
+            other => panic!("expected a full spool, got {:?}", other),
+        }
+        assert_eq!(keys(&spool), ["a", "b"]);
+    }
+}
@@ -0,0 +1,337 @@
+//! SQLite sink for decoded public commitments
+//!
+//! Each decoded message becomes a row in `proofs`, keyed by identifier so
+//! redelivered or duplicated messages overwrite the earlier row, with one row
+//! per monitored account in `accounts`. Requires the `sqlite` feature.
+
+use solana_stub_prover_lib::PublicCommitments;
+use std::error::Error;
+use std::path::Path;
+use twine_types::proofs::ZkProof;
+
+/// Where a stored message was read from
+#[derive(Debug, Clone, Copy)]
+pub struct MessagePosition<'a> {
+    pub topic: &'a str,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS proofs (
+    identifier TEXT PRIMARY KEY,
+    topic TEXT NOT NULL,
+    kafka_partition INTEGER NOT NULL,
+    kafka_offset INTEGER NOT NULL,
+    proof_kind TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    verification_key TEXT NOT NULL,
+    start_slot INTEGER,
+    end_slot INTEGER,
+    epoch INTEGER,
+    original_bank_hash TEXT,
+    last_bank_hash TEXT,
+    account_data_hash TEXT,
+    hash_root_valset TEXT,
+    total_active_stake INTEGER,
+    validator_count INTEGER,
+    validations_passed INTEGER,
+    received_at TEXT NOT NULL,
+    raw_payload BLOB
+);
+CREATE INDEX IF NOT EXISTS proofs_position ON proofs (topic, kafka_partition, kafka_offset);
+CREATE INDEX IF NOT EXISTS proofs_end_slot ON proofs (end_slot);
+CREATE TABLE IF NOT EXISTS accounts (
+    identifier TEXT NOT NULL REFERENCES proofs (identifier) ON DELETE CASCADE,
+    account_pubkey TEXT NOT NULL,
+    last_change_slot INTEGER NOT NULL,
+    account_data_hash TEXT NOT NULL,
+    lamports INTEGER NOT NULL,
+    owner TEXT NOT NULL,
+    executable INTEGER NOT NULL,
+    rent_epoch INTEGER NOT NULL,
+    data_len INTEGER NOT NULL,
+    PRIMARY KEY (identifier, account_pubkey)
+);
+CREATE INDEX IF NOT EXISTS accounts_pubkey ON accounts (account_pubkey);
+";
+
+/// SQLite database that decoded proofs are written to
+pub struct SqliteSink {
+    #[cfg(feature = "sqlite")]
+    connection: rusqlite::Connection,
+    store_raw: bool,
+}
+
+impl SqliteSink {
+    /// Open (creating if needed) the database and its schema
+    ///
+    /// With `store_raw` unset the raw payload column is left empty.
+    #[cfg(feature = "sqlite")]
+    pub fn open(path: &Path, store_raw: bool) -> Result<Self, Box<dyn Error>> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection, store_raw })
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn open(_path: &Path, _store_raw: bool) -> Result<Self, Box<dyn Error>> {
+        Err("--sqlite requires building with the sqlite feature".into())
+    }
+
+    /// Insert or replace a proof and its account commitments
+    #[cfg(feature = "sqlite")]
+    pub fn upsert(
+        &mut self,
+        proof: &ZkProof,
+        kind: &str,
+        commitments: Option<&PublicCommitments>,
+        position: MessagePosition<'_>,
+        payload: &[u8],
+    ) -> Result<(), Box<dyn Error>> {
+        use rusqlite::params;
+        use twine_types::proofs::ProofData;
+
+        let ProofData::SP1(sp1_proof) = &proof.proof_data;
+        let raw_payload = self.store_raw.then_some(payload);
+        let hex32 = |bytes: &[u8; 32]| hex::encode(bytes);
+
+        let transaction = self.connection.transaction()?;
+        // Replacing the row cascades to the old account rows
+        transaction.execute("DELETE FROM proofs WHERE identifier = ?1", params![proof.identifier])?;
+        transaction.execute(
+            "INSERT INTO proofs (
+                identifier, topic, kafka_partition, kafka_offset, proof_kind, version, verification_key,
+                start_slot, end_slot, epoch, original_bank_hash, last_bank_hash, account_data_hash,
+                hash_root_valset, total_active_stake, validator_count, validations_passed,
+                received_at, raw_payload
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            params![
+                proof.identifier,
+                position.topic,
+                position.partition,
+                position.offset,
+                kind,
+                sp1_proof.version as i64,
+                hex32(&sp1_proof.verification_key),
+                commitments.map(|c| c.start_slot as i64),
+                commitments.map(|c| c.end_slot as i64),
+                commitments.map(|c| c.epoch as i64),
+                commitments.map(|c| hex32(&c.original_bank_hash)),
+                commitments.map(|c| hex32(&c.last_bank_hash)),
+                commitments.map(|c| hex32(&c.account_data_hash)),
+                commitments.map(|c| hex32(&c.hash_root_valset)),
+                commitments.map(|c| c.total_active_stake as i64),
+                commitments.map(|c| c.validator_count),
+                commitments.map(|c| c.validations_passed),
+                chrono::Utc::now().to_rfc3339(),
+                raw_payload,
+            ],
+        )?;
+
+        for account in commitments.map_or(&[][..], |c| c.monitored_accounts_state.as_slice()) {
+            transaction.execute(
+                "INSERT OR REPLACE INTO accounts (
+                    identifier, account_pubkey, last_change_slot, account_data_hash, lamports,
+                    owner, executable, rent_epoch, data_len
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    proof.identifier,
+                    bs58::encode(account.account_pubkey).into_string(),
+                    account.last_change_slot as i64,
+                    hex32(&account.account_data_hash),
+                    account.lamports as i64,
+                    bs58::encode(account.owner).into_string(),
+                    account.executable,
+                    // Rent-exempt accounts use u64::MAX, which is stored as -1
+                    account.rent_epoch as i64,
+                    account.data_len as i64,
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn upsert(
+        &mut self,
+        _proof: &ZkProof,
+        _kind: &str,
+        _commitments: Option<&PublicCommitments>,
+        _position: MessagePosition<'_>,
+        _payload: &[u8],
+    ) -> Result<(), Box<dyn Error>> {
+        let _ = self.store_raw;
+        Err("--sqlite requires building with the sqlite feature".into())
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use solana_stub_prover_lib::{AccountStateCommitment, NOT_AGGREGATED};
+    use std::path::PathBuf;
+    use twine_types::proofs::{ProofData, ProofKind, SP1Proof};
+
+    /// A fresh directory for the database, since WAL mode adds files beside it
+    fn db_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sqlite-sink-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn proof(identifier: &str) -> ZkProof {
+        ZkProof {
+            identifier: identifier.to_string(),
+            proof_kind: ProofKind::SolanaConsensusProof,
+            proof_data: ProofData::SP1(SP1Proof {
+                version: 2,
+                proof: Vec::new(),
+                public_value: Vec::new(),
+                verification_key: [7; 32],
+            }),
+        }
+    }
+
+    fn account(seed: u8) -> AccountStateCommitment {
+        AccountStateCommitment {
+            account_pubkey: [seed; 32],
+            last_change_slot: 150,
+            account_data_hash: [0; 32],
+            lamports: 1_000,
+            owner: [0; 32],
+            executable: false,
+            rent_epoch: u64::MAX,
+            data_len: 0,
+            data: Vec::new(),
+        }
+    }
+
+    fn commitments(accounts: Vec<AccountStateCommitment>) -> PublicCommitments {
+        PublicCommitments {
+            start_slot: 100,
+            end_slot: 200,
+            epoch: 3,
+            original_bank_hash: [1; 32],
+            last_bank_hash: [2; 32],
+            account_data_hash: [3; 32],
+            hash_root_valset: [4; 32],
+            total_active_stake: 500,
+            validator_count: 10,
+            monitored_accounts_state: accounts,
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        }
+    }
+
+    fn at(offset: i64) -> MessagePosition<'static> {
+        MessagePosition { topic: "proofs", partition: 0, offset }
+    }
+
+    fn count(sink: &SqliteSink, sql: &str) -> i64 {
+        sink.connection.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn storing_a_proof_twice_keeps_one_row() {
+        let dir = db_dir("idempotent");
+        let path = dir.join("proofs.db");
+        let mut sink = SqliteSink::open(&path, true).unwrap();
+        let commitments = commitments(vec![account(1), account(2)]);
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&commitments), at(5), b"first").unwrap();
+        // A redelivery at a later offset replaces the earlier row
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&commitments), at(9), b"second").unwrap();
+
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM proofs"), 1);
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM accounts"), 2);
+        assert_eq!(count(&sink, "SELECT kafka_offset FROM proofs"), 9);
+        let raw: Vec<u8> = sink.connection.query_row("SELECT raw_payload FROM proofs", [], |row| row.get(0)).unwrap();
+        assert_eq!(raw, b"second");
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replacing_a_proof_drops_its_old_accounts() {
+        let dir = db_dir("cascade");
+        let path = dir.join("proofs.db");
+        let mut sink = SqliteSink::open(&path, true).unwrap();
+        let before = commitments(vec![account(1), account(2)]);
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&before), at(1), b"").unwrap();
+        let after = commitments(vec![account(3)]);
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&after), at(2), b"").unwrap();
+
+        let pubkey: String =
+            sink.connection.query_row("SELECT account_pubkey FROM accounts", [], |row| row.get(0)).unwrap();
+        assert_eq!(pubkey, bs58::encode([3u8; 32]).into_string());
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM accounts"), 1);
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_raw_leaves_the_payload_empty() {
+        let dir = db_dir("no-raw");
+        let path = dir.join("proofs.db");
+        let mut sink = SqliteSink::open(&path, false).unwrap();
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&commitments(vec![])), at(1), b"payload").unwrap();
+
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM proofs WHERE raw_payload IS NULL"), 1);
+        // Everything else is still stored
+        assert_eq!(count(&sink, "SELECT end_slot FROM proofs"), 200);
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stores_commitment_columns_and_rent_exempt_epochs() {
+        let dir = db_dir("columns");
+        let path = dir.join("proofs.db");
+        let mut sink = SqliteSink::open(&path, true).unwrap();
+        let commitments = commitments(vec![account(1)]);
+        sink.upsert(&proof("proof-1"), "SolanaConsensusProof", Some(&commitments), at(1), b"").unwrap();
+
+        let (kind, version, key, epoch): (String, i64, String, i64) = sink
+            .connection
+            .query_row("SELECT proof_kind, version, verification_key, epoch FROM proofs", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap();
+        assert_eq!((kind.as_str(), version, key, epoch), ("SolanaConsensusProof", 2, hex::encode([7u8; 32]), 3));
+        assert_eq!(count(&sink, "SELECT rent_epoch FROM accounts"), -1);
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn proofs_without_commitments_store_null_slots() {
+        let dir = db_dir("no-commitments");
+        let path = dir.join("proofs.db");
+        let mut sink = SqliteSink::open(&path, true).unwrap();
+        sink.upsert(&proof("exec-1"), "ExecutionProof", None, at(1), b"").unwrap();
+
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM proofs WHERE start_slot IS NULL AND end_slot IS NULL"), 1);
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM accounts"), 0);
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reopening_keeps_existing_rows() {
+        let dir = db_dir("reopen");
+        let path = dir.join("proofs.db");
+        SqliteSink::open(&path, true)
+            .unwrap()
+            .upsert(&proof("proof-1"), "SolanaConsensusProof", None, at(1), b"")
+            .unwrap();
+        let sink = SqliteSink::open(&path, true).unwrap();
+        assert_eq!(count(&sink, "SELECT COUNT(*) FROM proofs"), 1);
+        drop(sink);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
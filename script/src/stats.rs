@@ -0,0 +1,304 @@
+//! Aggregate statistics of a consumer session
+//!
+//! Counts are kept for every message read, whether or not filters display it:
+//! messages and bytes per proof kind, decode and verification failures,
+//! duplicates, the range of end slots seen and the number of distinct
+//! monitored accounts. Snapshots serialize to JSON so dashboards can ingest
+//! them alongside the formatted summary.
+
+use crate::presenter::Section;
+use serde::{Deserialize, Serialize};
+use solana_stub_prover_lib::PublicCommitmentsRef;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Messages and bytes of one proof kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KindStats {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+/// Running totals of a consumer session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsumerStats {
+    /// Messages read, including filtered and undecodable ones
+    pub messages: u64,
+    /// Payload bytes read
+    pub bytes: u64,
+    /// Decoded messages per proof kind (`ExecutionProof`, `SolanaConsensusProof`, ...)
+    pub by_kind: BTreeMap<String, KindStats>,
+    /// Messages without a payload
+    pub empty: u64,
+    pub decode_failures: u64,
+    pub verify_failures: u64,
+    pub duplicates: u64,
+    pub min_end_slot: Option<u64>,
+    pub max_end_slot: Option<u64>,
+    /// Distinct monitored accounts across all decoded commitments
+    pub unique_accounts: usize,
+    #[serde(skip)]
+    accounts: HashSet<[u8; 32]>,
+}
+
+impl ConsumerStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a message without a payload
+    pub fn record_empty(&mut self) {
+        self.messages += 1;
+        self.empty += 1;
+    }
+
+    /// Record a payload that could not be decoded
+    pub fn record_decode_failure(&mut self, bytes: usize) {
+        self.messages += 1;
+        self.bytes += bytes as u64;
+        self.decode_failures += 1;
+    }
+
+    /// Record a decoded proof of `kind` and its commitments, if they could be decoded
+    pub fn record_decoded(&mut self, kind: &str, bytes: usize, commitments: Option<&PublicCommitmentsRef<'_>>) {
+        self.messages += 1;
+        self.bytes += bytes as u64;
+        let kind_stats = self.by_kind.entry(kind_name(kind).to_string()).or_default();
+        kind_stats.messages += 1;
+        kind_stats.bytes += bytes as u64;
+
+        if let Some(commitments) = commitments {
+            self.min_end_slot = Some(self.min_end_slot.map_or(commitments.end_slot, |min| min.min(commitments.end_slot)));
+            self.max_end_slot = Some(self.max_end_slot.map_or(commitments.end_slot, |max| max.max(commitments.end_slot)));
+            self.accounts.extend(commitments.monitored_accounts_state.iter().map(|account| account.account_pubkey));
+            self.unique_accounts = self.accounts.len();
+        }
+    }
+
+    pub fn record_duplicate(&mut self) {
+        self.duplicates += 1;
+    }
+
+    pub fn record_verify_failure(&mut self) {
+        self.verify_failures += 1;
+    }
+
+    /// The statistics as a formatted section
+    pub fn section(&self, title: impl Into<String>) -> Section {
+        let mut section = Section::new(title);
+        section
+            .field(0, "Messages", self.messages)
+            .field(0, "Bytes", self.bytes)
+            .field(0, "Decode Failures", self.decode_failures)
+            .field(0, "Verify Failures", self.verify_failures)
+            .field(0, "Duplicates", self.duplicates);
+        if self.empty > 0 {
+            section.field(0, "Empty Payloads", self.empty);
+        }
+        match (self.min_end_slot, self.max_end_slot) {
+            (Some(min), Some(max)) => section.field(0, "End Slots", format!("{} - {}", min, max)),
+            _ => section.field(0, "End Slots", "none"),
+        };
+        section.field(0, "Unique Accounts", self.unique_accounts);
+        if !self.by_kind.is_empty() {
+            section.heading(0, "By Proof Kind");
+            for (kind, kind_stats) in &self.by_kind {
+                section.field(1, kind, format!("{} message(s), {} bytes", kind_stats.messages, kind_stats.bytes));
+            }
+        }
+        section
+    }
+
+    /// One-line JSON object tagged `"type": "stats"`
+    pub fn to_json_line(&self) -> Result<String, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(fields) = &mut value {
+            fields.insert("type".to_string(), serde_json::Value::String("stats".to_string()));
+        }
+        serde_json::to_string(&value)
+    }
+}
+
+/// Proof kind without variant data: `ExecutionProof(5)` counts as `ExecutionProof`
+fn kind_name(kind: &str) -> &str {
+    kind.split_once('(').map_or(kind, |(name, _)| name).trim()
+}
+
+/// Reporting interval given as seconds (`60`, `60s`), minutes (`5m`) or hours (`1h`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsInterval(pub Duration);
+
+impl FromStr for StatsInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().to_lowercase();
+        let (number, unit) = match trimmed.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) => trimmed.split_at(index),
+            None => (trimmed.as_str(), "s"),
+        };
+        let multiplier = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            _ => return Err(format!("Invalid interval: {} (expected e.g. 30s, 5m or 1h)", s)),
+        };
+        match number.parse::<u64>() {
+            Ok(value) if value > 0 => Ok(StatsInterval(Duration::from_secs(value * multiplier))),
+            _ => Err(format!("Invalid interval: {} (expected e.g. 30s, 5m or 1h)", s)),
+        }
+    }
+}
+
+impl fmt::Display for StatsInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.0.as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presenter::{ColorChoice, OutputStyle, Presenter};
+    use solana_stub_prover_lib::{AccountStateCommitment, PublicCommitments, NOT_AGGREGATED};
+
+    /// Encoded commitments ending at `end_slot`, monitoring `accounts`
+    fn encoded(end_slot: u64, accounts: &[[u8; 32]]) -> Vec<u8> {
+        let commitments = PublicCommitments {
+            start_slot: end_slot - 10,
+            end_slot,
+            epoch: 0,
+            original_bank_hash: [0; 32],
+            last_bank_hash: [0; 32],
+            account_data_hash: [0; 32],
+            hash_root_valset: [0; 32],
+            total_active_stake: 0,
+            validator_count: 0,
+            monitored_accounts_state: accounts
+                .iter()
+                .map(|&account_pubkey| AccountStateCommitment {
+                    account_pubkey,
+                    last_change_slot: end_slot,
+                    account_data_hash: [0; 32],
+                    lamports: 0,
+                    owner: [0; 32],
+                    executable: false,
+                    rent_epoch: 0,
+                    data_len: 0,
+                    data: Vec::new(),
+                })
+                .collect(),
+            validations_passed: true,
+            aggregated_vkey_digest: NOT_AGGREGATED,
+        };
+        bincode::serialize(&commitments).unwrap()
+    }
+
+    fn record(stats: &mut ConsumerStats, kind: &str, bytes: usize, end_slot: u64, accounts: &[[u8; 32]]) {
+        let encoded = encoded(end_slot, accounts);
+        let commitments: PublicCommitmentsRef<'_> = bincode::deserialize(&encoded).unwrap();
+        stats.record_decoded(kind, bytes, Some(&commitments));
+    }
+
+    #[test]
+    fn counts_messages_and_bytes_per_kind() {
+        let mut stats = ConsumerStats::new();
+        record(&mut stats, "SolanaConsensusProof", 1000, 110, &[]);
+        record(&mut stats, "SolanaConsensusProof", 1500, 120, &[]);
+        stats.record_decoded("ExecutionProof(5)", 300, None);
+        stats.record_decoded("ExecutionProof(6)", 200, None);
+
+        assert_eq!((stats.messages, stats.bytes), (4, 3000));
+        assert_eq!(stats.by_kind["SolanaConsensusProof"], KindStats { messages: 2, bytes: 2500 });
+        assert_eq!(stats.by_kind["ExecutionProof"], KindStats { messages: 2, bytes: 500 });
+        assert_eq!(stats.by_kind.len(), 2);
+    }
+
+    #[test]
+    fn failures_and_empty_payloads_count_as_messages_but_not_kinds() {
+        let mut stats = ConsumerStats::new();
+        stats.record_decode_failure(64);
+        stats.record_decode_failure(36);
+        stats.record_empty();
+        stats.record_decoded("SolanaConsensusProof", 900, None);
+        stats.record_verify_failure();
+
+        assert_eq!((stats.messages, stats.bytes), (4, 1000));
+        assert_eq!((stats.decode_failures, stats.empty, stats.verify_failures), (2, 1, 1));
+        assert_eq!(stats.by_kind.values().map(|kind| kind.messages).sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn duplicates_are_counted_on_top_of_their_messages() {
+        let mut stats = ConsumerStats::new();
+        record(&mut stats, "SolanaConsensusProof", 100, 110, &[]);
+        record(&mut stats, "SolanaConsensusProof", 100, 110, &[]);
+        stats.record_duplicate();
+        assert_eq!((stats.messages, stats.duplicates), (2, 1));
+    }
+
+    #[test]
+    fn tracks_the_end_slot_range_and_distinct_accounts() {
+        let mut stats = ConsumerStats::new();
+        assert_eq!((stats.min_end_slot, stats.max_end_slot), (None, None));
+        record(&mut stats, "SolanaConsensusProof", 100, 250, &[[1; 32], [2; 32]]);
+        record(&mut stats, "SolanaConsensusProof", 100, 120, &[[2; 32]]);
+        record(&mut stats, "SolanaConsensusProof", 100, 400, &[[3; 32], [1; 32]]);
+        // Proofs without decodable commitments leave the range and accounts alone
+        stats.record_decoded("SolanaConsensusProof", 100, None);
+
+        assert_eq!((stats.min_end_slot, stats.max_end_slot), (Some(120), Some(400)));
+        assert_eq!(stats.unique_accounts, 3);
+    }
+
+    #[test]
+    fn json_lines_are_tagged_and_leave_out_the_account_set() {
+        let mut stats = ConsumerStats::new();
+        record(&mut stats, "SolanaConsensusProof", 100, 110, &[[1; 32]]);
+        stats.record_decode_failure(10);
+
+        let value: serde_json::Value = serde_json::from_str(&stats.to_json_line().unwrap()).unwrap();
+        assert_eq!(value["type"], "stats");
+        assert_eq!(value["messages"], 2);
+        assert_eq!(value["decode_failures"], 1);
+        assert_eq!(value["unique_accounts"], 1);
+        assert_eq!(value["by_kind"]["SolanaConsensusProof"]["bytes"], 100);
+        assert!(value.get("accounts").is_none());
+    }
+
+    #[test]
+    fn the_summary_section_lists_every_total() {
+        let plain = Presenter::new(Some(OutputStyle::Plain), ColorChoice::Never);
+        let lines = |stats: &ConsumerStats| -> Vec<String> {
+            stats
+                .section("Stats")
+                .render(&plain)
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect()
+        };
+        assert!(lines(&ConsumerStats::new()).contains(&"End Slots none".to_string()));
+
+        let mut stats = ConsumerStats::new();
+        record(&mut stats, "SolanaConsensusProof", 100, 110, &[]);
+        stats.record_empty();
+        let rendered = lines(&stats);
+        for expected in ["Messages 2", "Bytes 100", "Empty Payloads 1", "End Slots 110 - 110", "Unique Accounts 0"] {
+            assert!(rendered.contains(&expected.to_string()), "{:?} missing from {:#?}", expected, rendered);
+        }
+        assert_eq!(rendered.last().unwrap(), "SolanaConsensusProof 1 message(s), 100 bytes");
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!("90".parse::<StatsInterval>().unwrap(), StatsInterval(Duration::from_secs(90)));
+        assert_eq!("30s".parse::<StatsInterval>().unwrap().to_string(), "30s");
+        assert_eq!(" 5M ".parse::<StatsInterval>().unwrap(), StatsInterval(Duration::from_secs(300)));
+        assert_eq!("1h".parse::<StatsInterval>().unwrap(), StatsInterval(Duration::from_secs(3600)));
+        for invalid in ["0", "5d", "m", "-5s", ""] {
+            assert!(invalid.parse::<StatsInterval>().is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! One-line summaries of the last messages on a topic
+//!
+//! Records are not spread evenly over partitions, so reading `N / partitions`
+//! messages from each could miss some of the last N. The tail reads up to the
+//! last N messages of every partition instead and keeps the N newest by
+//! record time, then prints them oldest first.
+
+use crate::envelope::{decode_json, DecodedProof};
+use crate::kafka::PayloadFormat;
+use crate::proof_filter::decode_commitments;
+use crate::replay::ResolvedWindow;
+use chrono::{DateTime, Utc};
+use rdkafka::error::KafkaResult;
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use std::fmt;
+use twine_types::proofs::ZkProof;
+
+/// Summary of one consumed message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MessageSummary {
+    pub partition: i32,
+    pub offset: i64,
+    /// Record timestamp in Unix milliseconds
+    pub timestamp: Option<i64>,
+    pub key: Option<String>,
+    /// Proof identifier, if the payload decoded
+    pub identifier: Option<String>,
+    /// `(start, end)` slot range, if the proof carries consensus commitments
+    pub slots: Option<(u64, u64)>,
+    /// Payload size in bytes
+    pub size: usize,
+    /// Why the payload was not decoded
+    pub note: Option<String>,
+}
+
+impl MessageSummary {
+    /// Summarize a message, decoding JSON and bincode payloads
+    ///
+    /// Avro payloads need a Schema Registry and are summarized without decoding.
+    pub fn new(
+        partition: i32,
+        offset: i64,
+        timestamp: Option<i64>,
+        key: Option<&[u8]>,
+        payload: Option<&[u8]>,
+        format: PayloadFormat,
+    ) -> Self {
+        let mut summary = Self {
+            partition,
+            offset,
+            timestamp,
+            key: key.map(|key| String::from_utf8_lossy(key).into_owned()),
+            identifier: None,
+            slots: None,
+            size: payload.map_or(0, <[u8]>::len),
+            note: None,
+        };
+        let Some(payload) = payload else {
+            summary.note = Some("tombstone".to_string());
+            return summary;
+        };
+        let decoded = match format {
+            PayloadFormat::Json => decode_json(payload).map_err(|e| e.to_string()),
+            PayloadFormat::Bincode => bincode::deserialize::<ZkProof>(payload)
+                .map(DecodedProof::typed)
+                .map_err(|e| format!("undecodable bincode: {}", e)),
+            PayloadFormat::Avro => Err("avro, not decoded".to_string()),
+        };
+        match decoded {
+            Ok(decoded) => {
+                summary.slots = decode_commitments(&decoded.proof).map(|c| (c.start_slot, c.end_slot));
+                summary.identifier = Some(decoded.proof.identifier);
+            }
+            Err(note) => summary.note = Some(note),
+        }
+        summary
+    }
+}
+
+impl fmt::Display for MessageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self
+            .timestamp
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .map_or_else(|| "-".to_string(), |time| time.format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+        write!(f, "{}  p{} @{}  key={}", time, self.partition, self.offset, self.key.as_deref().unwrap_or("-"))?;
+        if let Some(identifier) = &self.identifier {
+            write!(f, "  id={}", identifier)?;
+        }
+        if let Some((start, end)) = self.slots {
+            write!(f, "  slots {}..{}", start, end)?;
+        }
+        write!(f, "  {} B", self.size)?;
+        if let Some(note) = &self.note {
+            write!(f, "  ({})", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `count` newest summaries by record time, oldest first
+///
+/// Messages without a timestamp sort first; ties are broken by partition and offset.
+pub fn newest(mut summaries: Vec<MessageSummary>, count: usize) -> Vec<MessageSummary> {
+    summaries.sort_by_key(|summary| (summary.timestamp, summary.partition, summary.offset));
+    let skip = summaries.len().saturating_sub(count);
+    summaries.split_off(skip)
+}
+
+/// Assignment starting every partition at the start of its window
+///
+/// When following, partitions with empty windows are assigned at their high
+/// watermark so new messages on them are streamed too.
+pub fn tail_assignment(windows: &[ResolvedWindow], follow: bool) -> KafkaResult<TopicPartitionList> {
+    let mut assignment = TopicPartitionList::new();
+    for window in windows.iter().filter(|window| follow || !window.range().is_empty()) {
+        assignment.add_partition_offset(&window.topic, window.partition, Offset::Offset(window.range().start))?;
+    }
+    Ok(assignment)
+}
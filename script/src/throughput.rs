@@ -0,0 +1,94 @@
+//! Produce rates of a topic from its high watermarks
+//!
+//! Every sample compares the high watermark of each partition with the
+//! previous sample, which gives messages per second without consuming. Bytes
+//! per second are an estimate: the average size of a sample of the records
+//! produced during the interval times the message rate.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Messages produced to one partition during an interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionRate {
+    pub partition: i32,
+    /// High watermark at the end of the interval
+    pub high: i64,
+    pub messages: i64,
+    pub messages_per_sec: f64,
+}
+
+/// Rates of a topic over one interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThroughputSample {
+    /// End of the interval in Unix milliseconds
+    pub timestamp: i64,
+    pub interval_secs: f64,
+    pub messages: i64,
+    pub messages_per_sec: f64,
+    /// Records whose sizes were averaged
+    pub sampled: usize,
+    /// Average key and payload size of the sampled records
+    pub average_bytes: Option<f64>,
+    /// `average_bytes` times the message rate
+    pub bytes_per_sec: Option<f64>,
+    pub partitions: Vec<PartitionRate>,
+}
+
+impl ThroughputSample {
+    /// Rates between two sets of high watermarks taken `elapsed` apart
+    ///
+    /// Partitions missing from `previous` (e.g. added during the interval)
+    /// count from zero. A watermark that went back counts as no messages.
+    pub fn new(
+        timestamp: i64,
+        previous: &BTreeMap<i32, i64>,
+        current: &BTreeMap<i32, i64>,
+        elapsed: Duration,
+        sizes: &[usize],
+    ) -> Self {
+        let secs = elapsed.as_secs_f64();
+        let rate = |messages: i64| if secs > 0.0 { messages as f64 / secs } else { 0.0 };
+        let partitions: Vec<PartitionRate> = current
+            .iter()
+            .map(|(&partition, &high)| {
+                let messages = (high - previous.get(&partition).copied().unwrap_or(0)).max(0);
+                PartitionRate { partition, high, messages, messages_per_sec: rate(messages) }
+            })
+            .collect();
+        let messages = partitions.iter().map(|p| p.messages).sum();
+        let average_bytes = (!sizes.is_empty()).then(|| sizes.iter().sum::<usize>() as f64 / sizes.len() as f64);
+        ThroughputSample {
+            timestamp,
+            interval_secs: secs,
+            messages,
+            messages_per_sec: rate(messages),
+            sampled: sizes.len(),
+            average_bytes,
+            bytes_per_sec: average_bytes.map(|average| average * rate(messages)),
+            partitions,
+        }
+    }
+}
+
+impl fmt::Display for ThroughputSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>8.1} msg/s", self.messages_per_sec)?;
+        match (self.bytes_per_sec, self.average_bytes) {
+            (Some(bytes), Some(average)) => write!(
+                f,
+                "  {:>10.1} KB/s  (avg {:.1} KB over {} record(s))",
+                bytes / 1024.0,
+                average / 1024.0,
+                self.sampled
+            )?,
+            _ => write!(f, "  {:>10} KB/s", "-")?,
+        }
+        for partition in &self.partitions {
+            write!(f, "  p{} {:.1}", partition.partition, partition.messages_per_sec)?;
+        }
+        Ok(())
+    }
+}
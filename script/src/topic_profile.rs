@@ -0,0 +1,142 @@
+//! Topic configuration profiles for topics created by the admin tool
+//!
+//! Proof payloads exceed the broker's default message size limit, so the
+//! proofs topic is created with a larger `max.message.bytes`, zstd compression
+//! and 30 days of retention. The summary topic republished by the consumer
+//! keeps the latest record per key and is compacted instead.
+
+use rdkafka::admin::ConfigResource;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// `max.message.bytes` of the proofs topic (8 MiB)
+pub const PROOFS_MAX_MESSAGE_BYTES: u64 = 8_388_608;
+
+/// `retention.ms` of the proofs topic (30 days)
+pub const PROOFS_RETENTION_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// Set of topic configs applied on creation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TopicProfile {
+    /// Broker defaults
+    #[default]
+    Default,
+    /// Large messages, zstd compression and 30 days of retention
+    Proofs,
+    /// Compacted, zstd compression
+    Summary,
+}
+
+impl TopicProfile {
+    /// Topic configs of the profile
+    pub fn configs(&self) -> BTreeMap<String, String> {
+        let configs = match self {
+            TopicProfile::Default => vec![],
+            TopicProfile::Proofs => vec![
+                ("max.message.bytes", PROOFS_MAX_MESSAGE_BYTES.to_string()),
+                ("retention.ms", PROOFS_RETENTION_MS.to_string()),
+                ("compression.type", "zstd".to_string()),
+            ],
+            TopicProfile::Summary => vec![
+                ("cleanup.policy", "compact".to_string()),
+                ("compression.type", "zstd".to_string()),
+            ],
+        };
+        configs.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+    }
+}
+
+impl FromStr for TopicProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "default" => Ok(TopicProfile::Default),
+            "proofs" => Ok(TopicProfile::Proofs),
+            "summary" => Ok(TopicProfile::Summary),
+            other => Err(format!("Unknown topic profile '{}' (expected proofs, summary or default)", other)),
+        }
+    }
+}
+
+impl fmt::Display for TopicProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopicProfile::Default => write!(f, "default"),
+            TopicProfile::Proofs => write!(f, "proofs"),
+            TopicProfile::Summary => write!(f, "summary"),
+        }
+    }
+}
+
+/// A topic config override, parsed from `<key>=<value>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for ConfigOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid topic config '{}' (expected <key>=<value>)", s))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("Invalid topic config '{}': the key is empty", s));
+        }
+        Ok(ConfigOverride { key: key.to_string(), value: value.trim().to_string() })
+    }
+}
+
+impl fmt::Display for ConfigOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// Configs of the profile with the overrides applied; later overrides win
+pub fn topic_configs(profile: TopicProfile, overrides: &[ConfigOverride]) -> BTreeMap<String, String> {
+    let mut configs = profile.configs();
+    for config in overrides {
+        configs.insert(config.key.clone(), config.value.clone());
+    }
+    configs
+}
+
+/// A requested config the created topic does not have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigMismatch {
+    pub key: String,
+    pub requested: String,
+    /// Value the broker reports, or `None` if it does not know the config
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for ConfigMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.actual {
+            Some(actual) => write!(f, "{}: requested {}, broker has {}", self.key, self.requested, actual),
+            None => write!(f, "{}: requested {}, unknown to the broker", self.key, self.requested),
+        }
+    }
+}
+
+/// Requested configs the described topic does not match
+pub fn config_mismatches(requested: &BTreeMap<String, String>, described: &ConfigResource) -> Vec<ConfigMismatch> {
+    requested
+        .iter()
+        .filter_map(|(key, value)| {
+            let actual = described.get(key).and_then(|entry| entry.value.clone());
+            (actual.as_deref() != Some(value.as_str())).then(|| ConfigMismatch {
+                key: key.clone(),
+                requested: value.clone(),
+                actual,
+            })
+        })
+        .collect()
+}
@@ -22,19 +22,77 @@ pub struct Context {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountInfo {
-    pub data: Vec<String>,
+    pub data: AccountData,
     pub executable: bool,
     pub lamports: u64,
     pub owner: String,
     pub rent_epoch: u64,
-    #[allow(dead_code)]
+    /// Size of the data in bytes
     pub space: u64,
 }
 
+/// Data of an account as returned by getAccountInfo
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum AccountData {
+    /// `[data, encoding]`, such as `["", "base64"]` for an account without data
+    Encoded(String, String),
+    /// Any other shape, such as `[]` for an account without data or the object returned for `jsonParsed`
+    Other(serde_json::Value),
+}
+
 /// Solana RPC response for getSlot
 #[derive(Debug, Deserialize)]
 pub struct SlotResponse {
     #[allow(dead_code)]
     pub jsonrpc: String,
     pub result: u64,
-}
\ No newline at end of file
+}
+
+/// Solana RPC response for getEpochSchedule
+#[derive(Debug, Deserialize)]
+pub struct EpochScheduleResponse {
+    #[allow(dead_code)]
+    pub jsonrpc: String,
+    pub result: EpochScheduleResult,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochScheduleResult {
+    pub slots_per_epoch: u64,
+    pub warmup: bool,
+    pub first_normal_epoch: u64,
+    pub first_normal_slot: u64,
+}
+
+/// Solana RPC response for getBlockTime
+///
+/// `result` is null when the block time is not available for the slot.
+#[derive(Debug, Deserialize)]
+pub struct BlockTimeResponse {
+    #[allow(dead_code)]
+    pub jsonrpc: String,
+    pub result: Option<i64>,
+}
+
+/// Outcome of simulateTransaction
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    /// Why the transaction failed, `None` if it succeeded
+    pub err: Option<serde_json::Value>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,
+}
+
+/// Status of a transaction signature from getSignatureStatuses
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    pub slot: u64,
+    /// Why the transaction failed, `None` if it succeeded
+    pub err: Option<serde_json::Value>,
+    /// `processed`, `confirmed` or `finalized`
+    pub confirmation_status: Option<String>,
+}
@@ -1,5 +1,7 @@
 use bs58;
 use sha2::{Sha256, Digest};
+use solana_stub_prover_lib::epoch::EpochSchedule;
+use std::fmt;
 
 /// Decode a base58 string to bytes
 pub fn base58_decode(input: &str) -> Result<Vec<u8>, bs58::decode::Error> {
@@ -17,6 +19,94 @@ pub fn base58_to_bytes32(pubkey: &str) -> Result<[u8; 32], String> {
     Ok(arr)
 }
 
+/// Base58 of a 32-byte pubkey, as explorers and the Solana CLI show it
+pub fn bytes32_to_base58(bytes: &[u8; 32]) -> String {
+    bs58::encode(bytes).into_string()
+}
+
+/// A pubkey for display: base58, or its first and last four characters if `short`
+pub fn format_pubkey(bytes: &[u8; 32], short: bool) -> String {
+    let base58 = bytes32_to_base58(bytes);
+    if short && base58.len() > 11 {
+        format!("{}..{}", &base58[..4], &base58[base58.len() - 4..])
+    } else {
+        base58
+    }
+}
+
+/// Parse a pubkey given as base58 or as 64 hex digits, with or without `0x`
+///
+/// The two cannot be confused: 32 bytes are at most 44 base58 characters.
+pub fn parse_pubkey_or_hex(pubkey: &str) -> Result<[u8; 32], String> {
+    let pubkey = pubkey.trim();
+    let digits = pubkey.strip_prefix("0x").unwrap_or(pubkey);
+    if pubkey.starts_with("0x") || digits.len() == 64 {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(digits, &mut bytes)
+            .map_err(|_| format!("'{}' is neither base58 nor 32 bytes of hex", pubkey))?;
+        return Ok(bytes);
+    }
+    base58_to_bytes32(pubkey)
+}
+
+/// What is wrong with a pubkey argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PubkeyProblem {
+    /// Not base58; the decoder's error
+    NotBase58(String),
+    /// Decodes to this many bytes instead of 32
+    WrongLength(usize),
+    /// All zeros, the system program and a common placeholder
+    Zero,
+}
+
+/// A pubkey argument that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidPubkey {
+    /// Index of the pubkey in the list it was given in
+    pub position: usize,
+    pub pubkey: String,
+    pub problem: PubkeyProblem,
+}
+
+impl fmt::Display for InvalidPubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} '{}': ", self.position + 1, self.pubkey)?;
+        match &self.problem {
+            PubkeyProblem::NotBase58(reason) => write!(f, "not base58, {}", reason),
+            PubkeyProblem::WrongLength(len) => write!(f, "decodes to {} bytes instead of 32", len),
+            PubkeyProblem::Zero => write!(f, "the all-zeros pubkey"),
+        }
+    }
+}
+
+/// Decode every pubkey in `pubkeys`, reporting all invalid ones at once
+///
+/// The all-zeros pubkey is rejected unless `allow_zero`.
+pub fn validate_pubkeys(pubkeys: &[String], allow_zero: bool) -> Result<Vec<[u8; 32]>, Vec<InvalidPubkey>> {
+    let mut decoded = Vec::with_capacity(pubkeys.len());
+    let mut invalid = Vec::new();
+    for (position, pubkey) in pubkeys.iter().enumerate() {
+        let problem = match base58_decode(pubkey) {
+            Err(e) => PubkeyProblem::NotBase58(e.to_string()),
+            Ok(bytes) => match <[u8; 32]>::try_from(bytes.as_slice()) {
+                Err(_) => PubkeyProblem::WrongLength(bytes.len()),
+                Ok(bytes) if bytes == [0; 32] && !allow_zero => PubkeyProblem::Zero,
+                Ok(bytes) => {
+                    decoded.push(bytes);
+                    continue;
+                }
+            },
+        };
+        invalid.push(InvalidPubkey { position, pubkey: pubkey.clone(), problem });
+    }
+    if invalid.is_empty() {
+        Ok(decoded)
+    } else {
+        Err(invalid)
+    }
+}
+
 /// Calculate SHA256 hash and return as 32-byte array
 pub fn sha256_hash(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
@@ -24,14 +114,10 @@ pub fn sha256_hash(data: &[u8]) -> [u8; 32] {
     hasher.finalize().into()
 }
 
-/// Calculate SHA256 hash from a u64 value
-pub fn sha256_from_u64(value: u64) -> [u8; 32] {
-    sha256_hash(&value.to_le_bytes())
-}
-
-/// Calculate epoch number from slot
+/// Calculate epoch number from slot with mainnet's schedule
+///
+/// Only right for clusters without warmup epochs; input assembly asks the
+/// node for its [`EpochSchedule`] instead.
 pub fn get_epoch_for_slot(slot: u64) -> u64 {
-    // Solana mainnet/devnet has 432000 slots per epoch
-    const SLOTS_PER_EPOCH: u64 = 432000;
-    slot / SLOTS_PER_EPOCH
+    EpochSchedule::mainnet().get_epoch(slot)
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+//! Verification of received SP1 proofs
+//!
+//! With the full verifying key (`vkey.json` written by the prover) both
+//! compressed and Groth16 proofs are checked with the CPU prover's verifier.
+//! With only the program vkey hash (`vk.bytes32()`), Groth16 and Plonk proofs
+//! are checked against SP1's circuit verifying keys; compressed proofs need the
+//! full key. The wrapped proof's own type picks the verifier, not the message version.
+//!
+//! An aggregate only shows that its proofs verified under the vkey digest it
+//! commits, so that digest must be the full key's; aggregates are refused
+//! with only the vkey hash, which the digest cannot be compared with.
+
+use crate::utils::sha256_hash;
+use crate::wrapped_proof::WrappedProof;
+use solana_stub_prover_lib::{vkey_digest_bytes, PublicCommitmentsRef};
+use sp1_sdk::{CpuProver, HashableKey, Prover, ProverClient, SP1VerifyingKey};
+use sp1_verifier::{Groth16Verifier, PlonkVerifier, GROTH16_VK_BYTES, PLONK_VK_BYTES};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use twine_types::proofs::{ProofData, ZkProof};
+
+/// Key material proofs are verified against
+pub enum VerifyingKeySource {
+    /// Full verifying key, as written to `vkey.json` by the prover
+    Key(Box<SP1VerifyingKey>),
+    /// Program vkey hash (`0x`-prefixed, as returned by `vk.bytes32()`)
+    Hash(String),
+}
+
+impl VerifyingKeySource {
+    /// Load a verifying key from a JSON file
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let json = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let vk: SP1VerifyingKey =
+            serde_json::from_slice(&json).map_err(|e| format!("Invalid verifying key in {}: {}", path.display(), e))?;
+        Ok(VerifyingKeySource::Key(Box::new(vk)))
+    }
+
+    /// Use a program vkey hash, normalized to lowercase with a `0x` prefix
+    pub fn from_hash(hash: &str) -> Result<Self, Box<dyn Error>> {
+        let digits = hash.trim().trim_start_matches("0x").to_ascii_lowercase();
+        if digits.len() != 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid vkey hash '{}' (expected 32 bytes of hex)", hash).into());
+        }
+        Ok(VerifyingKeySource::Hash(format!("0x{}", digits)))
+    }
+}
+
+/// Outcome of verifying one proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Verified,
+    Failed(String),
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyStatus::Verified => write!(f, "VERIFIED"),
+            VerifyStatus::Failed(reason) => write!(f, "FAILED ({})", reason),
+        }
+    }
+}
+
+/// Verification outcome and how long it took
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub status: VerifyStatus,
+    pub elapsed: Duration,
+}
+
+impl Verification {
+    pub fn is_verified(&self) -> bool {
+        self.status == VerifyStatus::Verified
+    }
+}
+
+/// Verifies received proofs against a fixed key
+///
+/// Verification is CPU-bound and can take seconds; call `verify` from a
+/// blocking task.
+pub struct ProofVerifier {
+    source: VerifyingKeySource,
+    client: Option<CpuProver>,
+}
+
+impl ProofVerifier {
+    pub fn new(source: VerifyingKeySource) -> Self {
+        // Only the full key needs a prover client; the vkey hash path uses the standalone verifier
+        let client = match &source {
+            VerifyingKeySource::Key(_) => Some(ProverClient::builder().cpu().build()),
+            VerifyingKeySource::Hash(_) => None,
+        };
+        Self { source, client }
+    }
+
+    /// Reconstruct the SP1 proof from a message and verify it
+    pub fn verify(&self, proof: &ZkProof) -> Verification {
+        let started = Instant::now();
+        let status = match self.check(proof) {
+            Ok(()) => VerifyStatus::Verified,
+            Err(reason) => VerifyStatus::Failed(reason),
+        };
+        Verification { status, elapsed: started.elapsed() }
+    }
+
+    fn check(&self, proof: &ZkProof) -> Result<(), String> {
+        let ProofData::SP1(sp1_proof) = &proof.proof_data;
+        let wrapped = WrappedProof::decode(&sp1_proof.proof)?;
+        let bundle = &wrapped.bundle;
+
+        // The displayed commitments come from `public_value`, so they must be what the proof commits to
+        if bundle.public_values.as_slice() != sp1_proof.public_value.as_slice() {
+            return Err("public values do not match the proof".to_string());
+        }
+        self.check_aggregated_key(bundle.public_values.as_slice())?;
+
+        match &self.source {
+            VerifyingKeySource::Key(vk) => {
+                // Older envelopes carry the SHA-256 of the bincode key instead of the program vkey hash
+                let legacy_hash = sha256_hash(&bincode::serialize(vk.as_ref()).unwrap_or_default());
+                if ![vk.bytes32_raw(), legacy_hash].contains(&sp1_proof.verification_key) {
+                    return Err("proof was generated with a different verifying key".to_string());
+                }
+                let client = self.client.as_ref().expect("prover client is built for a full key");
+                client.verify(bundle, vk).map_err(|e| e.to_string())
+            }
+            VerifyingKeySource::Hash(vkey_hash) => match wrapped.kind() {
+                "groth16" => Groth16Verifier::verify(
+                    &bundle.bytes(),
+                    bundle.public_values.as_slice(),
+                    vkey_hash,
+                    *GROTH16_VK_BYTES,
+                )
+                .map_err(|e| e.to_string()),
+                "plonk" => PlonkVerifier::verify(
+                    &bundle.bytes(),
+                    bundle.public_values.as_slice(),
+                    vkey_hash,
+                    *PLONK_VK_BYTES,
+                )
+                .map_err(|e| e.to_string()),
+                kind => Err(format!("{} proofs need the full verifying key (--vkey)", kind)),
+            },
+        }
+    }
+
+    /// Check that an aggregate verified its proofs under this key; other public values pass
+    fn check_aggregated_key(&self, public_values: &[u8]) -> Result<(), String> {
+        let Ok(commitments) = bincode::deserialize::<PublicCommitmentsRef>(public_values) else {
+            return Ok(());
+        };
+        if !commitments.is_aggregate() {
+            return Ok(());
+        }
+        match &self.source {
+            VerifyingKeySource::Key(vk) if commitments.aggregated_vkey_digest == vkey_digest_bytes(&vk.hash_u32()) => {
+                Ok(())
+            }
+            VerifyingKeySource::Key(_) => Err("aggregate of proofs verified under another program's key".to_string()),
+            VerifyingKeySource::Hash(_) => Err("aggregated proofs need the full verifying key (--vkey)".to_string()),
+        }
+    }
+}
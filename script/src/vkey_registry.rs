@@ -0,0 +1,198 @@
+//! Program vkey hashes expected per statement version
+//!
+//! A proof's envelope carries the program vkey hash it verifies under
+//! (`vk.bytes32()`). Rebuilding the program ELF changes that hash, so the
+//! prover checks it against the expected one before publishing and the
+//! consumer flags proofs under keys it does not know. The expected hashes come
+//! from a single `--expected-vkey-hash`, taken as the current
+//! [`STATEMENT_VERSION`]'s, or from a registry file:
+//!
+//! ```toml
+//! # statement version = program vkey hash
+//! 1 = "0x00a1..."
+//! ```
+//!
+//! The ELF is embedded in the prover, so its key is fixed when the prover is
+//! built. The key the deployed verifiers use is pinned in
+//! [`EXPECTED_VKEY_FILE`] and compiled in as [`EXPECTED_VKEY_HASH`], which the
+//! prover checks its key against at startup and the consumer expects when
+//! given no other key. Building with the `EXPECTED_VKEY_HASH` environment
+//! variable set overrides the file.
+
+use solana_stub_prover_lib::STATEMENT_VERSION;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+include!(concat!(env!("OUT_DIR"), "/expected_vkey.rs"));
+
+/// File the expected program vkey hash is pinned in, relative to the script crate; written by `vkey-hash --write`
+pub const EXPECTED_VKEY_FILE: &str = "../program/vkey-hash";
+
+/// [`EXPECTED_VKEY_HASH`] as bytes
+pub fn expected_vkey_hash() -> Option<[u8; 32]> {
+    EXPECTED_VKEY_HASH.map(|hash| parse_vkey_hash(hash).expect("the build checks the pinned hash"))
+}
+
+/// Parse a program vkey hash, with or without the `0x` prefix
+pub fn parse_vkey_hash(hash: &str) -> Result<[u8; 32], String> {
+    let digits = hash.trim().trim_start_matches("0x");
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(digits, &mut bytes)
+        .map_err(|_| format!("Invalid vkey hash '{}' (expected 32 bytes of hex)", hash))?;
+    Ok(bytes)
+}
+
+/// `hash` as `vk.bytes32()` prints it
+pub fn format_vkey_hash(hash: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+/// How a vkey hash compares with the expected one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VkeyCheck {
+    Match,
+    /// The registry expects another key for the version
+    Mismatch {
+        expected: [u8; 32],
+    },
+    /// The registry has no key for the version
+    UnknownVersion,
+}
+
+/// Expected program vkey hash of each statement version
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VkeyRegistry {
+    hashes: BTreeMap<u32, [u8; 32]>,
+}
+
+impl VkeyRegistry {
+    /// Registry expecting `hash` for the current statement version
+    pub fn current(hash: &str) -> Result<Self, String> {
+        Ok(Self { hashes: BTreeMap::from([(STATEMENT_VERSION, parse_vkey_hash(hash)?)]) })
+    }
+
+    /// Registry expecting [`EXPECTED_VKEY_HASH`] for the current statement version; empty if none was pinned
+    pub fn pinned() -> Self {
+        Self { hashes: expected_vkey_hash().map(|hash| (STATEMENT_VERSION, hash)).into_iter().collect() }
+    }
+
+    /// Read a registry file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid vkey registry {}: {}", path.display(), e))
+    }
+
+    /// Parse the TOML of a registry file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let entries: BTreeMap<String, String> = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut hashes = BTreeMap::new();
+        for (version, hash) in entries {
+            let version: u32 =
+                version.parse().map_err(|_| format!("'{}' is not a statement version number", version))?;
+            hashes.insert(version, parse_vkey_hash(&hash)?);
+        }
+        Ok(Self { hashes })
+    }
+
+    /// Number of registered statement versions
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Expected hash of `version`
+    pub fn get(&self, version: u32) -> Option<&[u8; 32]> {
+        self.hashes.get(&version)
+    }
+
+    /// Statement version `hash` is registered for, `None` for an unknown key
+    pub fn version_of(&self, hash: &[u8; 32]) -> Option<u32> {
+        self.hashes.iter().find(|(_, registered)| *registered == hash).map(|(version, _)| *version)
+    }
+
+    /// Compare `hash` with the key expected for `version`
+    pub fn check(&self, version: u32, hash: &[u8; 32]) -> VkeyCheck {
+        match self.get(version) {
+            Some(expected) if expected == hash => VkeyCheck::Match,
+            Some(expected) => VkeyCheck::Mismatch { expected: *expected },
+            None => VkeyCheck::UnknownVersion,
+        }
+    }
+}
+
+/// Check the program vkey hash against the pinned one before proving
+///
+/// `Ok(None)` when it matches. A rebuilt program, or a prover built without a
+/// pinned hash, is a warning (`Ok(Some(..))`), or the error when `strict`.
+pub fn check_startup_vkey(pinned: &VkeyRegistry, vk_hash: &[u8; 32], strict: bool) -> Result<Option<String>, String> {
+    let problem = match pinned.check(STATEMENT_VERSION, vk_hash) {
+        VkeyCheck::Match => return Ok(None),
+        VkeyCheck::Mismatch { expected } => format!(
+            "the program vkey hash is {}, but the prover was built to expect {}; was the program rebuilt?",
+            format_vkey_hash(vk_hash),
+            format_vkey_hash(&expected)
+        ),
+        VkeyCheck::UnknownVersion => format!(
+            "the prover was built without an expected program vkey hash (pin {} in {})",
+            format_vkey_hash(vk_hash),
+            EXPECTED_VKEY_FILE
+        ),
+    };
+    if strict {
+        Err(problem)
+    } else {
+        Ok(Some(problem))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: [u8; 32] = [0xab; 32];
+    const REBUILT: [u8; 32] = [0xcd; 32];
+
+    fn pinned() -> VkeyRegistry {
+        VkeyRegistry::current(&format_vkey_hash(&PROGRAM)).unwrap()
+    }
+
+    #[test]
+    fn pinned_key_passes() {
+        assert_eq!(check_startup_vkey(&pinned(), &PROGRAM, true), Ok(None));
+        assert_eq!(check_startup_vkey(&pinned(), &PROGRAM, false), Ok(None));
+    }
+
+    #[test]
+    fn rebuilt_program_warns() {
+        let warning = check_startup_vkey(&pinned(), &REBUILT, false).unwrap().unwrap();
+        assert!(warning.contains(&format_vkey_hash(&REBUILT)));
+        assert!(warning.contains(&format_vkey_hash(&PROGRAM)));
+    }
+
+    #[test]
+    fn rebuilt_program_is_refused_when_strict() {
+        let error = check_startup_vkey(&pinned(), &REBUILT, true).unwrap_err();
+        assert!(error.contains("was the program rebuilt?"));
+    }
+
+    #[test]
+    fn missing_pin_names_the_file() {
+        let warning = check_startup_vkey(&VkeyRegistry::default(), &PROGRAM, false).unwrap().unwrap();
+        assert!(warning.contains(EXPECTED_VKEY_FILE));
+        assert!(check_startup_vkey(&VkeyRegistry::default(), &PROGRAM, true).is_err());
+    }
+
+    #[test]
+    fn registry_file_pins_keys_per_version() {
+        let registry = VkeyRegistry::parse(&format!("1 = \"{}\"\n", format_vkey_hash(&PROGRAM))).unwrap();
+        assert_eq!(registry.check(1, &PROGRAM), VkeyCheck::Match);
+        assert_eq!(registry.check(1, &REBUILT), VkeyCheck::Mismatch { expected: PROGRAM });
+        assert_eq!(registry.check(2, &PROGRAM), VkeyCheck::UnknownVersion);
+        assert_eq!(registry.version_of(&PROGRAM), Some(1));
+        assert!(VkeyRegistry::parse("one = \"0x00\"").is_err());
+    }
+}
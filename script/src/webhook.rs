@@ -0,0 +1,352 @@
+//! Forwarding of decoded proofs to HTTP webhooks
+//!
+//! Each proof is POSTed as a compact JSON summary to every configured URL,
+//! with retries and exponential backoff. When a secret is configured the body
+//! is signed with HMAC-SHA256 in the `x-signature-sha256` header. After
+//! repeated failures a URL's circuit breaker opens and deliveries to it are
+//! skipped for a cooldown period, so a dead endpoint does not stall the consumer.
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::{Duration, Instant};
+
+/// Header carrying `sha256=<hex HMAC of the body>`
+pub const SIGNATURE_HEADER: &str = "x-signature-sha256";
+
+/// Default request timeout
+pub const DEFAULT_WEBHOOK_TIMEOUT_MS: u64 = 5000;
+/// Default number of retries after a failed delivery
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 3;
+/// Consecutive failed deliveries that open a URL's circuit breaker
+pub const DEFAULT_CIRCUIT_FAILURES: u32 = 5;
+/// How long an open circuit breaker skips deliveries
+pub const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 60;
+
+/// Base delay before the first retry; doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Webhook settings
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    pub secret: Option<String>,
+    pub timeout: Duration,
+    pub retries: u32,
+    pub circuit_failures: u32,
+    pub circuit_cooldown: Duration,
+}
+
+/// Compact summary of a proof sent to webhooks
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofSummary {
+    pub identifier: String,
+    pub kind: String,
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub start_slot: Option<u64>,
+    pub end_slot: Option<u64>,
+    pub epoch: Option<u64>,
+    pub validations_passed: Option<bool>,
+    /// The commitments report failed validations
+    pub alert: bool,
+    /// Base58 pubkeys of the monitored accounts
+    pub accounts: Vec<String>,
+    /// Verification result, when `--verify` is enabled
+    pub verified: Option<bool>,
+    pub received_at: String,
+}
+
+/// Result of delivering a summary to one URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered { attempts: u32 },
+    Failed { attempts: u32, error: String },
+    /// The URL's circuit breaker is open
+    Skipped,
+}
+
+impl DeliveryOutcome {
+    /// Metric label for the outcome
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeliveryOutcome::Delivered { .. } => "delivered",
+            DeliveryOutcome::Failed { .. } => "failed",
+            DeliveryOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// Per-URL circuit breaker
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Sends proof summaries to the configured webhooks
+pub struct WebhookForwarder {
+    client: reqwest::Client,
+    config: WebhookConfig,
+    breakers: Vec<CircuitBreaker>,
+}
+
+impl WebhookForwarder {
+    pub fn new(config: WebhookConfig) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(config.timeout).build()?;
+        let breakers = config.urls.iter().map(|_| CircuitBreaker::default()).collect();
+        Ok(Self { client, config, breakers })
+    }
+
+    /// Deliver a summary (or any other JSON event) to every URL, returning the outcome per URL
+    pub async fn forward(&mut self, summary: &impl Serialize) -> Vec<(String, DeliveryOutcome)> {
+        let body = match serde_json::to_vec(summary) {
+            Ok(body) => body,
+            Err(e) => {
+                let error = format!("Failed to serialize summary: {}", e);
+                return self
+                    .config
+                    .urls
+                    .iter()
+                    .map(|url| (url.clone(), DeliveryOutcome::Failed { attempts: 0, error: error.clone() }))
+                    .collect();
+            }
+        };
+        let signature = self.config.secret.as_deref().map(|secret| sign(secret, &body));
+
+        let mut outcomes = Vec::with_capacity(self.config.urls.len());
+        for index in 0..self.config.urls.len() {
+            let url = self.config.urls[index].clone();
+            if self.breakers[index].open_until.is_some_and(|until| Instant::now() < until) {
+                outcomes.push((url, DeliveryOutcome::Skipped));
+                continue;
+            }
+
+            let outcome = self.deliver(&url, &body, signature.as_deref()).await;
+            let breaker = &mut self.breakers[index];
+            match outcome {
+                DeliveryOutcome::Delivered { .. } => *breaker = CircuitBreaker::default(),
+                _ => {
+                    breaker.consecutive_failures += 1;
+                    if breaker.consecutive_failures >= self.config.circuit_failures {
+                        breaker.open_until = Some(Instant::now() + self.config.circuit_cooldown);
+                        breaker.consecutive_failures = 0;
+                        eprintln!(
+                            "Warning: webhook {} failed repeatedly, pausing deliveries for {:?}",
+                            url, self.config.circuit_cooldown
+                        );
+                    }
+                }
+            }
+            outcomes.push((url, outcome));
+        }
+        outcomes
+    }
+
+    async fn deliver(&self, url: &str, body: &[u8], signature: Option<&str>) -> DeliveryOutcome {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let mut request = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_vec());
+            if let Some(signature) = signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let (error, retryable) = match request.send().await {
+                Ok(response) if response.status().is_success() => return DeliveryOutcome::Delivered { attempts },
+                Ok(response) => {
+                    let status = response.status();
+                    // Client errors other than rate limiting will not succeed on retry
+                    (format!("HTTP {}", status), status.is_server_error() || status.as_u16() == 429)
+                }
+                Err(e) => (e.to_string(), true),
+            };
+
+            if !retryable || attempts > self.config.retries {
+                return DeliveryOutcome::Failed { attempts, error };
+            }
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempts - 1)).await;
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC signature of a body
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_ne!(sign("Jefe", b"{}"), sign("other", b"{}"));
+    }
+
+    #[test]
+    fn labels_outcomes() {
+        assert_eq!(DeliveryOutcome::Delivered { attempts: 1 }.label(), "delivered");
+        assert_eq!(DeliveryOutcome::Failed { attempts: 2, error: "HTTP 500".into() }.label(), "failed");
+        assert_eq!(DeliveryOutcome::Skipped.label(), "skipped");
+    }
+
+    #[cfg(feature = "test-util")]
+    mod server {
+        use super::*;
+        use axum::extract::State;
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::routing::post;
+        use axum::Router;
+        use serde_json::{json, Value};
+        use std::collections::VecDeque;
+        use std::sync::{Arc, Mutex};
+        use tokio::net::TcpListener;
+
+        /// Requests seen by the mock webhook
+        #[derive(Default)]
+        struct Seen {
+            /// Signature header and body of each request
+            requests: Vec<(Option<String>, Vec<u8>)>,
+            /// Statuses to answer with, in order, before falling back to 200
+            responses: VecDeque<StatusCode>,
+        }
+
+        type Shared = Arc<Mutex<Seen>>;
+
+        async fn receive(State(seen): State<Shared>, headers: HeaderMap, body: axum::body::Bytes) -> StatusCode {
+            let mut seen = seen.lock().unwrap();
+            let signature = headers.get(SIGNATURE_HEADER).map(|v| v.to_str().unwrap().to_string());
+            seen.requests.push((signature, body.to_vec()));
+            seen.responses.pop_front().unwrap_or(StatusCode::OK)
+        }
+
+        /// Start a webhook answering with `responses` first, returning its URL
+        async fn start(responses: &[StatusCode]) -> (String, Shared) {
+            let seen = Shared::default();
+            seen.lock().unwrap().responses.extend(responses);
+            let app = Router::new().route("/hook", post(receive)).with_state(seen.clone());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hook", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            (url, seen)
+        }
+
+        fn forwarder(urls: Vec<String>, retries: u32, circuit_failures: u32) -> WebhookForwarder {
+            WebhookForwarder::new(WebhookConfig {
+                urls,
+                secret: None,
+                timeout: Duration::from_secs(5),
+                retries,
+                circuit_failures,
+                circuit_cooldown: Duration::from_secs(60),
+            })
+            .unwrap()
+        }
+
+        fn outcomes(results: Vec<(String, DeliveryOutcome)>) -> Vec<DeliveryOutcome> {
+            results.into_iter().map(|(_, outcome)| outcome).collect()
+        }
+
+        #[tokio::test]
+        async fn signs_the_body_when_a_secret_is_set() {
+            let (url, seen) = start(&[]).await;
+            let mut signed = forwarder(vec![url.clone()], 0, 5);
+            signed.config.secret = Some("hook-secret".to_string());
+            let event = json!({ "identifier": "proof-1", "end_slot": 200 });
+            assert_eq!(outcomes(signed.forward(&event).await), [DeliveryOutcome::Delivered { attempts: 1 }]);
+            forwarder(vec![url], 0, 5).forward(&event).await;
+
+            let seen = seen.lock().unwrap();
+            let (signature, body) = &seen.requests[0];
+            assert_eq!(serde_json::from_slice::<Value>(body).unwrap(), event);
+            assert_eq!(signature.as_deref(), Some(sign("hook-secret", body).as_str()));
+            // Unsigned without a secret
+            assert_eq!(seen.requests[1].0, None);
+        }
+
+        #[tokio::test]
+        async fn retries_server_errors_and_rate_limits() {
+            let (url, seen) = start(&[StatusCode::SERVICE_UNAVAILABLE, StatusCode::TOO_MANY_REQUESTS]).await;
+            let mut forwarder = forwarder(vec![url], 3, 5);
+            assert_eq!(outcomes(forwarder.forward(&json!({})).await), [DeliveryOutcome::Delivered { attempts: 3 }]);
+            assert_eq!(seen.lock().unwrap().requests.len(), 3);
+        }
+
+        #[tokio::test]
+        async fn gives_up_after_the_configured_retries() {
+            let (url, seen) = start(&[StatusCode::INTERNAL_SERVER_ERROR; 3]).await;
+            let mut forwarder = forwarder(vec![url], 1, 5);
+            assert_eq!(
+                outcomes(forwarder.forward(&json!({})).await),
+                [DeliveryOutcome::Failed { attempts: 2, error: "HTTP 500 Internal Server Error".to_string() }]
+            );
+            assert_eq!(seen.lock().unwrap().requests.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn does_not_retry_client_errors() {
+            let (url, seen) = start(&[StatusCode::BAD_REQUEST]).await;
+            let mut forwarder = forwarder(vec![url], 3, 5);
+            assert_eq!(
+                outcomes(forwarder.forward(&json!({})).await),
+                [DeliveryOutcome::Failed { attempts: 1, error: "HTTP 400 Bad Request".to_string() }]
+            );
+            assert_eq!(seen.lock().unwrap().requests.len(), 1);
+        }
+
+        #[tokio::test]
+        async fn opens_the_circuit_after_consecutive_failures() {
+            let (failing, failing_seen) = start(&[StatusCode::BAD_REQUEST; 2]).await;
+            let (healthy, _) = start(&[]).await;
+            let mut forwarder = forwarder(vec![failing, healthy], 0, 2);
+
+            let first = outcomes(forwarder.forward(&json!({})).await);
+            assert_eq!(first[0].label(), "failed");
+            let second = outcomes(forwarder.forward(&json!({})).await);
+            assert_eq!(second[0].label(), "failed");
+            // Open now: the failing URL is skipped without a request, the healthy one still delivers
+            assert_eq!(
+                outcomes(forwarder.forward(&json!({})).await),
+                [DeliveryOutcome::Skipped, DeliveryOutcome::Delivered { attempts: 1 }]
+            );
+            assert_eq!(failing_seen.lock().unwrap().requests.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn a_delivery_resets_the_failure_count() {
+            let (url, _) = start(&[StatusCode::BAD_REQUEST, StatusCode::OK, StatusCode::BAD_REQUEST]).await;
+            let mut forwarder = forwarder(vec![url], 0, 2);
+            for _ in 0..3 {
+                forwarder.forward(&json!({})).await;
+            }
+            // Only one failure since the last delivery, so the circuit is still closed
+            assert_eq!(outcomes(forwarder.forward(&json!({})).await), [DeliveryOutcome::Delivered { attempts: 1 }]);
+        }
+
+        #[tokio::test]
+        async fn closes_the_circuit_after_the_cooldown() {
+            let (url, seen) = start(&[StatusCode::BAD_REQUEST]).await;
+            let mut forwarder = forwarder(vec![url], 0, 1);
+            forwarder.config.circuit_cooldown = Duration::from_millis(100);
+            forwarder.forward(&json!({})).await;
+            assert_eq!(outcomes(forwarder.forward(&json!({})).await), [DeliveryOutcome::Skipped]);
+
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            assert_eq!(outcomes(forwarder.forward(&json!({})).await), [DeliveryOutcome::Delivered { attempts: 1 }]);
+            assert_eq!(seen.lock().unwrap().requests.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Introspection of the SP1 proof wrapped in a message
+//!
+//! `SP1Proof::proof` holds an `SP1ProofWithPublicValues`. The prover encodes it
+//! with bincode; older producers embedded its JSON instead, either as bytes or
+//! as a nested object in the legacy envelope (which stores the object's JSON).
+//! Both are accepted. For Groth16 and Plonk proofs the SNARK's public inputs
+//! are the program vkey hash and a digest of the committed public values,
+//! which can be checked against the message without running the verifier.
+
+use sha2::{Digest, Sha256};
+use sp1_sdk::SP1ProofWithPublicValues;
+use std::fmt;
+
+/// How the wrapped proof was encoded in the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapperEncoding {
+    Bincode,
+    Json,
+}
+
+impl fmt::Display for WrapperEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WrapperEncoding::Bincode => write!(f, "bincode"),
+            WrapperEncoding::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Details of a Groth16 or Plonk proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnarkDetails {
+    /// `Groth16` or `Plonk`
+    pub system: &'static str,
+    /// Program vkey hash committed as the first public input, as `0x`-prefixed hex
+    pub vkey_hash: Option<String>,
+    /// Public values digest committed as the second public input, as `0x`-prefixed hex
+    pub public_values_digest: Option<String>,
+    /// Size of the raw proof in bytes
+    pub raw_proof_size: usize,
+    /// Size of the proof encoded for on-chain verification in bytes
+    pub encoded_proof_size: usize,
+    /// Hash of the SP1 circuit verifying key the proof was generated for
+    pub circuit_vkey_hash: [u8; 32],
+}
+
+/// The SP1 proof carried in a message
+pub struct WrappedProof {
+    pub bundle: SP1ProofWithPublicValues,
+    pub encoding: WrapperEncoding,
+}
+
+impl WrappedProof {
+    /// Decode the wrapped proof from bincode, or from JSON as older producers sent it
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        match bincode::deserialize(bytes) {
+            Ok(bundle) => Ok(Self { bundle, encoding: WrapperEncoding::Bincode }),
+            Err(bincode_error) => match serde_json::from_slice(bytes) {
+                Ok(bundle) => Ok(Self { bundle, encoding: WrapperEncoding::Json }),
+                Err(_) => Err(format!("undecodable proof: {}", bincode_error)),
+            },
+        }
+    }
+
+    /// Proof system: `core`, `compressed`, `groth16` or `plonk`
+    pub fn kind(&self) -> &'static str {
+        match &self.bundle.proof {
+            sp1_sdk::SP1Proof::Core(_) => "core",
+            sp1_sdk::SP1Proof::Compressed(_) => "compressed",
+            sp1_sdk::SP1Proof::Groth16(_) => "groth16",
+            sp1_sdk::SP1Proof::Plonk(_) => "plonk",
+        }
+    }
+
+    /// Public values the proof commits to
+    pub fn public_values(&self) -> &[u8] {
+        self.bundle.public_values.as_slice()
+    }
+
+    /// Groth16 or Plonk details, or `None` for STARK proofs
+    pub fn snark(&self) -> Option<SnarkDetails> {
+        let (system, public_inputs, raw_proof, encoded_proof, circuit_vkey_hash) = match &self.bundle.proof {
+            sp1_sdk::SP1Proof::Groth16(proof) => (
+                "Groth16",
+                &proof.public_inputs[..],
+                &proof.raw_proof,
+                &proof.encoded_proof,
+                proof.groth16_vkey_hash,
+            ),
+            sp1_sdk::SP1Proof::Plonk(proof) => {
+                ("Plonk", &proof.public_inputs[..], &proof.raw_proof, &proof.encoded_proof, proof.plonk_vkey_hash)
+            }
+            _ => return None,
+        };
+        Some(SnarkDetails {
+            system,
+            vkey_hash: public_inputs.first().and_then(|input| field_to_hex(input)),
+            public_values_digest: public_inputs.get(1).and_then(|input| field_to_hex(input)),
+            raw_proof_size: raw_proof.len() / 2,
+            encoded_proof_size: encoded_proof.len() / 2,
+            circuit_vkey_hash,
+        })
+    }
+}
+
+/// Digest of public values as committed by SP1 SNARKs: SHA-256 truncated to 253 bits, as `0x`-prefixed hex
+pub fn public_values_digest(public_values: &[u8]) -> String {
+    let mut digest: [u8; 32] = Sha256::digest(public_values).into();
+    digest[0] &= 0x1f;
+    format!("0x{}", hex::encode(digest))
+}
+
+/// A BN254 field element given in decimal, as 32 bytes of `0x`-prefixed hex
+fn field_to_hex(decimal: &str) -> Option<String> {
+    if decimal.is_empty() || !decimal.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for digit in decimal.bytes() {
+        // bytes = bytes * 10 + digit, big-endian
+        let mut carry = u32::from(digit - b'0');
+        for byte in bytes.iter_mut().rev() {
+            let value = u32::from(*byte) * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(format!("0x{}", hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::decode_json;
+    use solana_stub_prover_lib::PublicCommitments;
+    use twine_types::proofs::ProofData;
+
+    /// Groth16 bundle as JSON, as older producers sent it in `SP1Proof::proof`
+    const GROTH16_JSON: &str = include_str!("../tests/fixtures/wrapped-groth16.json");
+    /// Plonk bundle as JSON
+    const PLONK_JSON: &str = include_str!("../tests/fixtures/wrapped-plonk.json");
+    /// Legacy envelope carrying the Groth16 bundle bincode-encoded as hex
+    const LEGACY_HEX: &str = include_str!("../tests/fixtures/envelope-legacy-groth16-hex.json");
+    /// Legacy envelope carrying the Groth16 bundle as a nested JSON object
+    const LEGACY_NESTED: &str = include_str!("../tests/fixtures/envelope-legacy-groth16-nested.json");
+
+    const VKEY_HASH: &str = "0x000a1b3207587196d1c9e07a9e153f81aff8f4271fabf92f72f1e55d540e09f5";
+    const PUBLIC_VALUES_DIGEST: &str = "0x18084614c9b6d37e71116ec1a217cc28bdb4cbde159d19cd17f1c4026d75e4a8";
+
+    /// The wrapped proof bytes of a legacy envelope
+    fn proof_bytes(envelope: &str) -> Vec<u8> {
+        let ProofData::SP1(sp1_proof) = decode_json(envelope.as_bytes()).unwrap().proof.proof_data;
+        sp1_proof.proof
+    }
+
+    /// Check a decoded Groth16 fixture against the values it was generated with
+    fn assert_groth16(wrapped: &WrappedProof) {
+        assert_eq!(wrapped.kind(), "groth16");
+        assert_eq!(
+            wrapped.snark(),
+            Some(SnarkDetails {
+                system: "Groth16",
+                vkey_hash: Some(VKEY_HASH.to_string()),
+                public_values_digest: Some(PUBLIC_VALUES_DIGEST.to_string()),
+                raw_proof_size: 256,
+                encoded_proof_size: 260,
+                circuit_vkey_hash: [0xa1; 32],
+            })
+        );
+        assert_eq!(public_values_digest(wrapped.public_values()), PUBLIC_VALUES_DIGEST);
+
+        let commitments: PublicCommitments = bincode::deserialize(wrapped.public_values()).unwrap();
+        assert_eq!((commitments.start_slot, commitments.end_slot), (1_000_000, 1_000_100));
+        assert_eq!(commitments.monitored_accounts_state.len(), 1);
+    }
+
+    #[test]
+    fn decodes_a_json_groth16_proof() {
+        let wrapped = WrappedProof::decode(GROTH16_JSON.as_bytes()).unwrap();
+        assert_eq!(wrapped.encoding, WrapperEncoding::Json);
+        assert_groth16(&wrapped);
+    }
+
+    #[test]
+    fn decodes_a_json_plonk_proof() {
+        let wrapped = WrappedProof::decode(PLONK_JSON.as_bytes()).unwrap();
+        assert_eq!(wrapped.kind(), "plonk");
+        let snark = wrapped.snark().unwrap();
+        assert_eq!(snark.system, "Plonk");
+        assert_eq!(snark.vkey_hash.as_deref(), Some(VKEY_HASH));
+        assert_eq!(snark.public_values_digest.as_deref(), Some(PUBLIC_VALUES_DIGEST));
+        assert_eq!((snark.raw_proof_size, snark.encoded_proof_size), (868, 872));
+        assert_eq!(snark.circuit_vkey_hash, [0xb2; 32]);
+    }
+
+    #[test]
+    fn decodes_a_hex_proof_from_a_legacy_envelope() {
+        let wrapped = WrappedProof::decode(&proof_bytes(LEGACY_HEX)).unwrap();
+        assert_eq!(wrapped.encoding, WrapperEncoding::Bincode);
+        assert_groth16(&wrapped);
+    }
+
+    #[test]
+    fn decodes_a_nested_json_proof_from_a_legacy_envelope() {
+        let wrapped = WrappedProof::decode(&proof_bytes(LEGACY_NESTED)).unwrap();
+        assert_eq!(wrapped.encoding, WrapperEncoding::Json);
+        assert_groth16(&wrapped);
+    }
+
+    #[test]
+    fn bincode_round_trips_the_json_fixture() {
+        let wrapped = WrappedProof::decode(GROTH16_JSON.as_bytes()).unwrap();
+        assert_eq!(bincode::serialize(&wrapped.bundle).unwrap(), proof_bytes(LEGACY_HEX));
+    }
+
+    #[test]
+    fn rejects_undecodable_proofs() {
+        for bytes in [&b""[..], b"{}", b"not a proof", &[0xff; 16]] {
+            let error = WrappedProof::decode(bytes).err().unwrap();
+            assert!(error.starts_with("undecodable proof: "), "{}", error);
+        }
+    }
+
+    #[test]
+    fn converts_decimal_field_elements() {
+        assert_eq!(field_to_hex("0"), Some(format!("0x{}", "00".repeat(32))));
+        assert_eq!(field_to_hex("258"), Some(format!("0x{}0102", "00".repeat(30))));
+        // 2^256 - 1 still fits, 2^256 does not
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(field_to_hex(max), Some(format!("0x{}", "ff".repeat(32))));
+        assert_eq!(
+            field_to_hex("115792089237316195423570985008687907853269984665640564039457584007913129639936"),
+            None
+        );
+        for invalid in ["", "-1", "0x10", "1 2"] {
+            assert_eq!(field_to_hex(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn digests_fit_the_bn254_field() {
+        // SHA-256 of the empty string starts with 0xe3, of which the top three bits are cleared
+        assert_eq!(public_values_digest(b""), "0x03b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
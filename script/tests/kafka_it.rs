@@ -0,0 +1,566 @@
+//! Publishing to and consuming from a real broker
+//!
+//! Envelopes are published with the prover's `ProofPublisher` and read back
+//! through the consumer's decoding, for each proof mode and payload format,
+//! along with the publisher's transactions, dead letters, spool and pre-flight
+//! check and the consumer's manual commits and replays. Avro needs a Schema
+//! Registry and is left out. Tests running at the same time share one
+//! container through `run_with_cluster`. Run with
+//! `cargo test --features kafka-it --test kafka_it` (Docker required).
+
+use rdkafka::admin::{AdminOptions, NewTopic, TopicReplication};
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{Headers, Message, OwnedMessage};
+use rdkafka::Offset;
+use solana_stub_prover_lib::{compute_accounts_hash, PublicCommitments, NOT_AGGREGATED};
+use solana_stub_prover_script::account_data::{self, RawAccountData};
+use solana_stub_prover_script::commit::CommitBatcher;
+use solana_stub_prover_script::dlq::{
+    DLQ_ATTEMPTS_HEADER, DLQ_ERROR_HEADER, DLQ_FAILED_AT_HEADER, DLQ_ORIGINAL_TOPIC_HEADER, DLQ_REASON_HEADER,
+};
+use solana_stub_prover_script::envelope::{decode_payload, EnvelopeShape};
+use solana_stub_prover_script::fixtures::{self, Preset, DEFAULT_SEED};
+use solana_stub_prover_script::kafka::{
+    create_admin_client, record_size, ConsumerTuning, KafkaConfig, KafkaPublishError, PartitionKey, PayloadFormat,
+    PreflightError, ProofPublisher, RetryPolicy, TopicCreation, AGGREGATED_COUNT_HEADER, CONTENT_TYPE_HEADER,
+    DEFAULT_MAX_MESSAGE_BYTES,
+};
+use solana_stub_prover_script::kafka_container::{run_with_cluster, unique_topic, KafkaTestCluster};
+use solana_stub_prover_script::partition_workers::ProcessedWatermarks;
+use solana_stub_prover_script::pipeline::envelope;
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::replay::{
+    parse_timestamp, resolve_assignment, resolve_windows, window_assignment, PartitionWindow, ReplayStart,
+    ResolvedWindow, WindowTracker,
+};
+use solana_stub_prover_script::spool::{Spool, DEFAULT_MAX_SPOOL_BYTES};
+use std::time::Duration;
+use twine_types::proofs::ZkProof;
+
+const PARTITIONS: i32 = 3;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a topic must stay empty to count as not published to
+const QUIET_TIMEOUT: Duration = Duration::from_secs(5);
+/// Proof sizes from a typical Groth16 bundle to well past the broker's limit
+const LARGE_PROOF_LENS: [usize; 4] = [256 * 1024, 900 * 1024, 1536 * 1024, 6 * 1024 * 1024];
+const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Nothing listens here, so connecting fails at once
+const UNREACHABLE_BROKER: &str = "127.0.0.1:1";
+/// Messages published for the replays, one second apart
+const REPLAY_MESSAGES: i64 = 20;
+
+/// Commitments of the fixture input, with the raw account data taken out as the prover does
+fn statement(seed: u64) -> (PublicCommitments, Vec<RawAccountData>) {
+    let mut input = Preset::SmallAccount.generate(seed);
+    let raw_data = account_data::detach(&mut input);
+    let commitments = PublicCommitments {
+        start_slot: input.start_slot,
+        end_slot: input.end_slot,
+        epoch: input.epoch,
+        original_bank_hash: input.original_bank_hash,
+        last_bank_hash: input.last_bank_hash,
+        account_data_hash: compute_accounts_hash(&input.monitored_accounts_state),
+        hash_root_valset: [0u8; 32],
+        total_active_stake: 1_000_000_000,
+        validator_count: 100,
+        monitored_accounts_state: input.monitored_accounts_state,
+        validations_passed: true,
+        aggregated_vkey_digest: NOT_AGGREGATED,
+    };
+    (commitments, raw_data)
+}
+
+/// An envelope of `mode` with a stand-in proof of `proof_len` bytes
+fn proof(identifier: &str, mode: ProofMode, seed: u64, proof_len: usize) -> ZkProof {
+    let (commitments, _) = statement(seed);
+    let public_value = bincode::serialize(&commitments).unwrap();
+    envelope(identifier.to_string(), mode, fixtures::random_bytes(seed, proof_len), public_value, [0x42; 32])
+}
+
+/// A publisher to `topic` of `cluster`, configured by `configure`
+fn publisher(cluster: &KafkaTestCluster, topic: &str, configure: impl FnOnce(&mut KafkaConfig)) -> ProofPublisher {
+    let mut config = cluster.config(topic);
+    configure(&mut config);
+    ProofPublisher::new(&config).unwrap()
+}
+
+async fn topic(cluster: &KafkaTestCluster, prefix: &str) -> String {
+    topic_with(cluster, prefix, PARTITIONS).await
+}
+
+/// A new topic of `partitions` partitions, for tests relying on the order of a single partition
+async fn topic_with(cluster: &KafkaTestCluster, prefix: &str, partitions: i32) -> String {
+    let topic = unique_topic(prefix);
+    cluster.create_topic(&topic, partitions).await.unwrap();
+    topic
+}
+
+async fn read(cluster: &KafkaTestCluster, topic: &str, count: usize) -> Vec<OwnedMessage> {
+    let messages = cluster.read_messages(topic, count, READ_TIMEOUT).await.unwrap();
+    assert_eq!(messages.len(), count, "messages read from {}", topic);
+    messages
+}
+
+/// Headers of a message in order, with UTF-8 values
+fn headers(message: &OwnedMessage) -> Vec<(String, String)> {
+    let Some(headers) = message.headers() else { return Vec::new() };
+    headers
+        .iter()
+        .map(|header| {
+            let value = header.value.map(|value| String::from_utf8(value.to_vec()).unwrap()).unwrap_or_default();
+            (header.key.to_string(), value)
+        })
+        .collect()
+}
+
+fn key(message: &OwnedMessage) -> &str {
+    std::str::from_utf8(message.key().unwrap()).unwrap()
+}
+
+fn assert_same_proof(decoded: &ZkProof, published: &ZkProof) {
+    assert_eq!(serde_json::to_value(decoded).unwrap(), serde_json::to_value(published).unwrap());
+}
+
+/// A proof comes back whole, under its identifier, in one record saying how it is encoded
+async fn round_trip(cluster: &KafkaTestCluster, mode: ProofMode, format: PayloadFormat) {
+    let topic = topic(cluster, &format!("it-{}-{}", mode, format)).await;
+    let publisher = publisher(cluster, &topic, |config| config.payload_format = format);
+    let published = proof(&format!("{}-{}", mode, format), mode, DEFAULT_SEED, 64 * 1024);
+    let (partition, offset) = publisher.publish_proof(&published).await.unwrap();
+
+    let messages = read(cluster, &topic, 1).await;
+    let message = &messages[0];
+    assert_eq!((message.partition(), message.offset()), (partition, offset));
+    assert_eq!(key(message), published.identifier);
+    assert_eq!(headers(message), [(CONTENT_TYPE_HEADER.to_string(), format.content_type().to_string())]);
+
+    let payload = message.payload().unwrap();
+    assert_eq!(PayloadFormat::of_message(message, payload), format);
+    let decoded = decode_payload(payload, format, None).await.unwrap();
+    assert_eq!(decoded.shape, EnvelopeShape::Typed);
+    assert!(decoded.metadata.is_none());
+    assert_same_proof(&decoded.proof, &published);
+}
+
+#[test]
+fn proofs_round_trip_in_every_mode_and_format() {
+    run_with_cluster(|cluster| async move {
+        for mode in [ProofMode::Groth16, ProofMode::Compressed] {
+            for format in [PayloadFormat::Json, PayloadFormat::Bincode] {
+                round_trip(&cluster, mode, format).await;
+            }
+        }
+    });
+}
+
+/// Account data published as envelope metadata comes back and matches the commitments
+#[test]
+fn metadata_round_trips() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-metadata").await;
+        let publisher = publisher(&cluster, &topic, |_| {});
+        let (commitments, raw_data) = statement(DEFAULT_SEED);
+        assert!(!raw_data.is_empty());
+        let published = proof("with-metadata", ProofMode::Groth16, DEFAULT_SEED, 1024);
+        publisher.publish_proof_with_metadata(&published, None, &account_data::metadata(&raw_data)).await.unwrap();
+
+        let messages = read(&cluster, &topic, 1).await;
+        let decoded = decode_payload(messages[0].payload().unwrap(), PayloadFormat::Json, None).await.unwrap();
+        assert_same_proof(&decoded.proof, &published);
+        let accounts = account_data::from_metadata(decoded.metadata.as_ref().unwrap()).unwrap().unwrap();
+        assert_eq!(accounts, raw_data);
+        assert!(account_data::verify(&commitments, &accounts).unwrap() > 0);
+    });
+}
+
+/// Extra headers, like the aggregator's count, follow the content type
+#[test]
+fn extra_headers_follow_the_content_type() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-headers").await;
+        let publisher = publisher(&cluster, &topic, |_| {});
+        let published = proof("aggregate", ProofMode::Compressed, DEFAULT_SEED, 1024);
+        publisher.publish_proof_with_headers(&published, None, &[(AGGREGATED_COUNT_HEADER, "4")]).await.unwrap();
+
+        let messages = read(&cluster, &topic, 1).await;
+        assert_eq!(
+            headers(&messages[0]),
+            [
+                (CONTENT_TYPE_HEADER.to_string(), PayloadFormat::Json.content_type().to_string()),
+                (AGGREGATED_COUNT_HEADER.to_string(), "4".to_string()),
+            ]
+        );
+    });
+}
+
+/// Keyed by account, every proof of an account lands on one partition in publish order
+#[test]
+fn account_keys_keep_a_partition() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-keys").await;
+        let publisher = publisher(&cluster, &topic, |config| config.partition_key = PartitionKey::Account);
+        let proofs: Vec<ZkProof> =
+            (0..4).map(|i| proof(&format!("range-{}", i), ProofMode::Groth16, DEFAULT_SEED, 1024)).collect();
+        let account = PartitionKey::Account.record_key(&proofs[0]);
+        assert_ne!(account, proofs[0].identifier);
+        for proof in &proofs {
+            publisher.publish_proof(proof).await.unwrap();
+        }
+
+        let messages = read(&cluster, &topic, proofs.len()).await;
+        assert!(messages.iter().all(|message| key(message) == account));
+        assert!(messages.iter().all(|message| message.partition() == messages[0].partition()));
+        for (message, published) in messages.iter().zip(&proofs) {
+            let decoded = decode_payload(message.payload().unwrap(), PayloadFormat::Json, None).await.unwrap();
+            assert_same_proof(&decoded.proof, published);
+        }
+    });
+}
+
+/// Envelopes are never split: one over the size limit is refused and nothing reaches the topic
+#[test]
+fn oversized_envelopes_are_not_split() {
+    const LIMIT: usize = 64 * 1024;
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-oversized").await;
+        let publisher = publisher(&cluster, &topic, |config| config.max_message_bytes = LIMIT);
+        let oversized = proof("oversized", ProofMode::Groth16, DEFAULT_SEED, LIMIT);
+        match publisher.publish_proof(&oversized).await {
+            Err(KafkaPublishError::Oversized { size, limit }) => assert!(size > limit && limit == LIMIT),
+            other => panic!("expected an oversized error, got {:?}", other),
+        }
+        let messages = cluster.read_messages(&topic, 1, QUIET_TIMEOUT).await.unwrap();
+        assert!(messages.is_empty());
+
+        // Within the limit, the whole envelope is one record
+        let fitting = proof("fitting", ProofMode::Groth16, DEFAULT_SEED, LIMIT / 16);
+        publisher.publish_proof(&fitting).await.unwrap();
+        let messages = read(&cluster, &topic, 1).await;
+        assert_eq!(messages[0].payload().unwrap(), serde_json::to_vec(&fitting).unwrap());
+    });
+}
+
+/// Large envelopes up to the producer's limit arrive; larger ones are refused before they are sent,
+/// and with the producer's limit raised, by the broker's own (its default, about 1 MB)
+#[test]
+fn large_envelopes_meet_the_size_limits() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-large").await;
+        // Bincode keeps the payload the size of the proof
+        let bincode = publisher(&cluster, &topic, |config| config.payload_format = PayloadFormat::Bincode);
+        let mut delivered = Vec::new();
+        for len in LARGE_PROOF_LENS {
+            let large = proof(&format!("large-{}", len), ProofMode::Groth16, DEFAULT_SEED, len);
+            match bincode.publish_proof(&large).await {
+                Ok(_) => delivered.push(large),
+                Err(KafkaPublishError::Oversized { size, limit }) => {
+                    assert_eq!(limit, DEFAULT_MAX_MESSAGE_BYTES);
+                    assert!(size > len && size > limit, "{} byte proof counted as {} bytes", len, size);
+                }
+                Err(e) => panic!("{} byte proof failed: {}", len, e),
+            }
+        }
+        assert_eq!(delivered.len(), 2, "only the proofs under the limit are delivered");
+
+        let raised = publisher(&cluster, &topic, |config| {
+            config.payload_format = PayloadFormat::Bincode;
+            config.max_message_bytes = 8 * 1024 * 1024;
+        });
+        let large = proof("large-raised", ProofMode::Groth16, DEFAULT_SEED, LARGE_PROOF_LENS[2]);
+        match raised.publish_proof(&large).await {
+            Err(KafkaPublishError::Oversized { limit: 0, .. }) => {}
+            other => panic!("expected the broker to refuse the proof, got {:?}", other),
+        }
+
+        let messages = read(&cluster, &topic, delivered.len()).await;
+        for message in &messages {
+            let published = delivered.iter().find(|proof| proof.identifier == key(message)).unwrap();
+            let payload = message.payload().unwrap();
+            assert!(record_size(key(message), payload.len(), message.headers().unwrap()) <= DEFAULT_MAX_MESSAGE_BYTES);
+            let decoded = decode_payload(payload, PayloadFormat::Bincode, None).await.unwrap();
+            assert_same_proof(&decoded.proof, published);
+        }
+        let more = cluster.read_messages(&topic, delivered.len() + 1, QUIET_TIMEOUT).await.unwrap();
+        assert_eq!(more.len(), delivered.len());
+    });
+}
+
+/// A proof sent by a prover that restarts before committing never becomes visible, and the
+/// restarted prover fences the old one
+#[test]
+fn uncommitted_transactions_stay_invisible() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic(&cluster, "it-transactions").await;
+        let transactional_id = unique_topic("it-prover");
+        let transactional = |config: &mut KafkaConfig| {
+            config.transactional_id = Some(transactional_id.clone());
+            // Also bounds registering with the coordinator, whose log the first producer creates
+            config.message_timeout_ms = 30_000;
+        };
+
+        let crashed = publisher(&cluster, &topic, transactional);
+        crashed.begin_transaction().unwrap();
+        crashed.publish_proof(&proof("uncommitted", ProofMode::Groth16, DEFAULT_SEED, 1024)).await.unwrap();
+
+        // Registering the same id aborts the open transaction
+        let restarted = publisher(&cluster, &topic, transactional);
+        let fenced = crashed.commit_transaction().unwrap_err();
+        assert!(fenced.is_fenced(), "expected the old producer to be fenced, got {}", fenced);
+
+        restarted.begin_transaction().unwrap();
+        restarted.publish_proof(&proof("aborted", ProofMode::Groth16, DEFAULT_SEED, 1024)).await.unwrap();
+        restarted.abort_transaction().unwrap();
+        restarted.begin_transaction().unwrap();
+        let committed = proof("committed", ProofMode::Groth16, DEFAULT_SEED, 1024);
+        restarted.publish_proof(&committed).await.unwrap();
+        restarted.commit_transaction().unwrap();
+
+        // librdkafka consumers read committed records only by default
+        let messages = cluster.read_messages(&topic, 2, QUIET_TIMEOUT).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(key(&messages[0]), committed.identifier);
+    });
+}
+
+/// A proof the broker refuses goes to the DLQ topic with headers describing the failure
+#[test]
+fn refused_proofs_go_to_the_dlq_topic() {
+    run_with_cluster(|cluster| async move {
+        // The broker refuses records over the topic's limit, which the producer does not know about
+        let topic = unique_topic("it-dlq-source");
+        let small = NewTopic::new(&topic, 1, TopicReplication::Fixed(1)).set("max.message.bytes", "1024");
+        let admin = create_admin_client(&cluster.config(&topic)).unwrap();
+        for result in admin.create_topics(&[small], &AdminOptions::new()).await.unwrap() {
+            result.unwrap();
+        }
+        let dlq_topic = topic_with(&cluster, "it-dlq", 1).await;
+        let publisher = publisher(&cluster, &topic, |config| config.dlq_topic = Some(dlq_topic.clone()));
+
+        let refused = proof("refused", ProofMode::Groth16, DEFAULT_SEED, 8 * 1024);
+        let error = publisher.publish_proof(&refused).await.unwrap_err();
+        assert!(matches!(error, KafkaPublishError::Oversized { limit: 0, .. }), "{:?}", error);
+
+        let letters = read(&cluster, &dlq_topic, 1).await;
+        let letter = &letters[0];
+        assert_eq!(key(letter), refused.identifier);
+        assert_eq!(letter.payload().unwrap(), serde_json::to_vec(&refused).unwrap());
+        let headers = headers(letter);
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                CONTENT_TYPE_HEADER,
+                DLQ_REASON_HEADER,
+                DLQ_ERROR_HEADER,
+                DLQ_ORIGINAL_TOPIC_HEADER,
+                DLQ_FAILED_AT_HEADER,
+                DLQ_ATTEMPTS_HEADER
+            ]
+        );
+        assert_eq!(headers[0].1, PayloadFormat::Json.content_type());
+        assert_eq!(headers[1].1, "oversized");
+        assert_eq!(headers[2].1, error.to_string());
+        assert_eq!(headers[3].1, topic);
+        assert!(chrono::DateTime::parse_from_rfc3339(&headers[4].1).is_ok(), "{}", headers[4].1);
+        assert_eq!(headers[5].1, "1");
+
+        let messages = cluster.read_messages(&topic, 1, QUIET_TIMEOUT).await.unwrap();
+        assert!(messages.is_empty());
+    });
+}
+
+/// Proofs spooled while the broker is unreachable are published in order, before the next proof
+#[test]
+fn spooled_proofs_drain_in_order() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic_with(&cluster, "it-spool", 1).await;
+        let spool_dir = std::env::temp_dir().join(format!("kafka-it-spool-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&spool_dir);
+        let spooling = |config: &mut KafkaConfig| config.spool_dir = Some(spool_dir.clone());
+
+        let offline = publisher(&cluster, &topic, |config| {
+            spooling(config);
+            config.broker = Some(UNREACHABLE_BROKER.to_string());
+            config.message_timeout_ms = 1000;
+            config.retry_policy = RetryPolicy { max_attempts: 1, ..RetryPolicy::default() };
+        });
+        let proofs: Vec<ZkProof> =
+            (0..4).map(|i| proof(&format!("spooled-{}", i), ProofMode::Groth16, DEFAULT_SEED, 1024)).collect();
+        for proof in &proofs[..3] {
+            let error = offline.publish_proof(proof).await.unwrap_err();
+            assert!(error.is_spooled(), "expected {} to be spooled, got {}", proof.identifier, error);
+        }
+        let spool = Spool::new(spool_dir.clone(), DEFAULT_MAX_SPOOL_BYTES);
+        assert_eq!(spool.pending().unwrap().len(), 3);
+
+        let online = publisher(&cluster, &topic, spooling);
+        online.publish_proof(&proofs[3]).await.unwrap();
+        assert!(spool.is_empty().unwrap());
+
+        let messages = read(&cluster, &topic, proofs.len()).await;
+        let keys: Vec<&str> = messages.iter().map(key).collect();
+        let identifiers: Vec<&str> = proofs.iter().map(|proof| proof.identifier.as_str()).collect();
+        assert_eq!(keys, identifiers);
+        for (message, published) in messages.iter().zip(&proofs) {
+            let decoded = decode_payload(message.payload().unwrap(), PayloadFormat::Json, None).await.unwrap();
+            assert_same_proof(&decoded.proof, published);
+        }
+        std::fs::remove_dir_all(&spool_dir).unwrap();
+    });
+}
+
+/// The pre-flight check names missing topics, creates them when asked and reports an unreachable broker
+#[test]
+fn preflight_checks_the_broker_and_topics() {
+    run_with_cluster(|cluster| async move {
+        let topic = unique_topic("it-preflight");
+        let dlq_topic = unique_topic("it-preflight-dlq");
+        let checked = publisher(&cluster, &topic, |config| config.dlq_topic = Some(dlq_topic.clone()));
+
+        match checked.check_connectivity(PREFLIGHT_TIMEOUT, None).await {
+            Err(PreflightError::MissingTopics { broker, topics }) => {
+                assert_eq!(broker, cluster.bootstrap_servers());
+                let mut expected = vec![topic.clone(), dlq_topic.clone()];
+                expected.sort();
+                assert_eq!(topics, expected);
+            }
+            other => panic!("expected missing topics, got {:?}", other),
+        }
+
+        let creation = TopicCreation { partitions: PARTITIONS, replication_factor: 1 };
+        checked.check_connectivity(PREFLIGHT_TIMEOUT, Some(creation)).await.unwrap();
+        checked.check_connectivity(PREFLIGHT_TIMEOUT, None).await.unwrap();
+
+        let unreachable = publisher(&cluster, &topic, |config| config.broker = Some(UNREACHABLE_BROKER.to_string()));
+        let error = unreachable.check_connectivity(Duration::from_secs(2), None).await.unwrap_err();
+        assert!(matches!(&error, PreflightError::Unreachable { broker, .. } if broker == UNREACHABLE_BROKER));
+        assert!(error.to_string().contains(UNREACHABLE_BROKER), "{}", error);
+    });
+}
+
+/// A consumer group member configured like the consumer's `--commit manual`
+fn manual_commit_consumer(cluster: &KafkaTestCluster, topic: &str, group: &str) -> StreamConsumer {
+    let mut client_config = cluster.config(topic).client_config();
+    ConsumerTuning::default().apply(&mut client_config);
+    let consumer: StreamConsumer = client_config
+        .set("group.id", group)
+        .set("auto.offset.reset", "earliest")
+        .set("enable.auto.commit", "false")
+        .set("enable.auto.offset.store", "false")
+        .create()
+        .unwrap();
+    consumer.subscribe(&[topic]).unwrap();
+    consumer
+}
+
+/// A consumer assigning partitions itself, as replays do
+fn replay_consumer(cluster: &KafkaTestCluster, topic: &str) -> StreamConsumer {
+    let mut client_config = cluster.config(topic).client_config();
+    ConsumerTuning::default().apply(&mut client_config);
+    client_config.set("enable.auto.commit", "false").create().unwrap()
+}
+
+async fn receive(consumer: &StreamConsumer, count: usize) -> Vec<OwnedMessage> {
+    let mut messages = Vec::new();
+    while messages.len() < count {
+        let received = tokio::time::timeout(READ_TIMEOUT, consumer.recv()).await;
+        let message = received.expect("timed out waiting for a message").unwrap();
+        messages.push(message.detach());
+    }
+    messages
+}
+
+/// Only offsets of processed messages are committed: a message still in progress when the
+/// consumer stops is delivered again, along with everything after it
+#[test]
+fn manual_commits_redeliver_unprocessed_messages() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic_with(&cluster, "it-commit", 1).await;
+        let publisher = publisher(&cluster, &topic, |_| {});
+        for i in 0..4 {
+            let published = proof(&format!("commit-{}", i), ProofMode::Groth16, DEFAULT_SEED, 1024);
+            publisher.publish_proof(&published).await.unwrap();
+        }
+        let group = unique_topic("it-group");
+
+        let consumer = manual_commit_consumer(&cluster, &topic, &group);
+        let messages = receive(&consumer, 4).await;
+        let mut watermarks = ProcessedWatermarks::new();
+        let mut batcher = CommitBatcher::new(Duration::from_secs(3600), 3);
+        for message in &messages {
+            watermarks.read(&topic, message.partition(), message.offset());
+        }
+        // commit-2 is still being processed when the consumer stops
+        for message in messages.iter().filter(|message| key(message) != "commit-2") {
+            batcher.processed();
+            if let Some(watermark) = watermarks.processed(&topic, message.partition(), message.offset()) {
+                consumer.store_offset(&topic, message.partition(), watermark).unwrap();
+            }
+        }
+        assert!(batcher.is_due());
+        consumer.commit_consumer_state(CommitMode::Sync).unwrap();
+        batcher.committed();
+        let committed = consumer.committed(READ_TIMEOUT).unwrap();
+        assert_eq!(committed.find_partition(&topic, 0).unwrap().offset(), Offset::Offset(2));
+        drop(consumer);
+
+        let restarted = manual_commit_consumer(&cluster, &topic, &group);
+        let redelivered = receive(&restarted, 2).await;
+        assert_eq!(redelivered.iter().map(key).collect::<Vec<_>>(), ["commit-2", "commit-3"]);
+    });
+}
+
+/// Replay the messages of one offset window, returning the window as resolved and the offsets read
+async fn replay_window(cluster: &KafkaTestCluster, topic: &str, window: PartitionWindow) -> (ResolvedWindow, Vec<i64>) {
+    let consumer = replay_consumer(cluster, topic);
+    let mut windows = resolve_windows(&consumer, &[topic.to_string()], &[window], READ_TIMEOUT).unwrap();
+    consumer.assign(&window_assignment(&windows).unwrap()).unwrap();
+
+    let mut tracker = WindowTracker::new(&windows);
+    let mut offsets = Vec::new();
+    while !tracker.is_done() {
+        let message = receive(&consumer, 1).await.remove(0);
+        if tracker.observe(message.topic(), message.partition(), message.offset()) {
+            offsets.push(message.offset());
+        }
+    }
+    (windows.remove(0), offsets)
+}
+
+/// Replays start at the requested offset or the first record at a time, and read exactly a window
+#[test]
+fn replays_start_at_offsets_times_and_windows() {
+    run_with_cluster(|cluster| async move {
+        let topic = topic_with(&cluster, "it-replay", 1).await;
+        let publisher = publisher(&cluster, &topic, |_| {});
+        let first_ms = parse_timestamp("2024-06-01T14:00:00Z").unwrap();
+        for i in 0..REPLAY_MESSAGES {
+            let published = proof(&format!("replay-{}", i), ProofMode::Groth16, DEFAULT_SEED, 1024);
+            let (_, offset) = publisher.publish_proof_at(&published, Some(first_ms + i * 1000)).await.unwrap();
+            assert_eq!(offset, i);
+        }
+        let topics = [topic.clone()];
+
+        let consumer = replay_consumer(&cluster, &topic);
+        let at = ReplayStart::Timestamp(parse_timestamp("2024-06-01T14:00:06.500Z").unwrap());
+        let assignment = resolve_assignment(&consumer, &topics, &at, READ_TIMEOUT).unwrap();
+        assert_eq!(assignment.find_partition(&topic, 0).unwrap().offset(), Offset::Offset(7));
+        consumer.assign(&assignment).unwrap();
+        let message = receive(&consumer, 1).await.remove(0);
+        assert_eq!((key(&message), message.timestamp().to_millis()), ("replay-7", Some(first_ms + 7000)));
+
+        let consumer = replay_consumer(&cluster, &topic);
+        let from = ReplayStart::Offsets(vec!["0:12".parse().unwrap()]);
+        consumer.assign(&resolve_assignment(&consumer, &topics, &from, READ_TIMEOUT).unwrap()).unwrap();
+        assert_eq!(key(&receive(&consumer, 1).await[0]), "replay-12");
+
+        let (window, offsets) = replay_window(&cluster, &topic, "0:5..10".parse().unwrap()).await;
+        assert!(!window.starts_before_log() && !window.ends_after_log());
+        assert_eq!(offsets, (5..10).collect::<Vec<_>>());
+
+        let (window, offsets) = replay_window(&cluster, &topic, "0:15..30".parse().unwrap()).await;
+        assert!(window.ends_after_log());
+        assert_eq!(window.range(), 15..REPLAY_MESSAGES);
+        assert_eq!(offsets, (15..REPLAY_MESSAGES).collect::<Vec<_>>());
+    });
+}
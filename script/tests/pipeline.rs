@@ -0,0 +1,129 @@
+//! The pipeline library against the mock RPC and the memory sink
+//!
+//! Inputs are assembled from a `MockSolanaRpc`, executed, proven with SP1's
+//! mock prover and published to a `MemorySink`, through the functions other
+//! services call. Run with `cargo test --features test-util --test pipeline`.
+
+use solana_stub_prover_lib::epoch::EpochSchedule;
+use solana_stub_prover_lib::{compute_accounts_hash, stub_bank_hash};
+use solana_stub_prover_script::account_data::{self, RawData};
+use solana_stub_prover_script::mock_rpc::{MockAccount, MockSolanaRpc};
+use solana_stub_prover_script::pipeline::{
+    assemble_input, execute, missing_accounts, prove, publish, Consistency, InputConfig, PipelineError,
+};
+use solana_stub_prover_script::proof_sink::{MemorySink, SinkReceipt};
+use solana_stub_prover_script::prove_jobs::ProofMode;
+use solana_stub_prover_script::utils::sha256_hash;
+use std::sync::Once;
+
+const OWNER: &str = "11111111111111111111111111111111";
+
+/// Base58 pubkey of `[byte; 32]`
+fn pubkey(byte: u8) -> String {
+    bs58::encode([byte; 32]).into_string()
+}
+
+fn account(data: &[u8]) -> MockAccount {
+    MockAccount { data: data.to_vec(), lamports: 1_141_440, owner: OWNER.to_string(), executable: false, rent_epoch: 0 }
+}
+
+/// Prove with SP1's mock prover, which skips proof generation but runs everything around it
+fn use_mock_prover() {
+    static MOCK_PROVER: Once = Once::new();
+    MOCK_PROVER.call_once(|| std::env::set_var("SP1_PROVER", "mock"));
+}
+
+#[tokio::test]
+async fn assembles_sorted_accounts_at_the_node_slot() {
+    let rpc = MockSolanaRpc::start(105).await.unwrap();
+    for byte in 1..=3 {
+        rpc.set_account(&pubkey(byte), account(&[byte; 16]));
+        rpc.add_signature(&pubkey(byte), 60 + u64::from(byte));
+    }
+    // Newer than the node's slot, so not the last change
+    rpc.add_signature(&pubkey(1), 110);
+    let schedule = EpochSchedule::new(8192, true);
+    rpc.set_epoch_schedule(schedule);
+
+    let config = InputConfig {
+        accounts: [3, 1, 2, 1].map(pubkey).to_vec(),
+        ..InputConfig::new(rpc.url(), &pubkey(1), 10, Some(100))
+    };
+    let input = assemble_input(&config).await.unwrap();
+
+    // The node answered from a later slot than requested, which ends the range
+    assert_eq!((input.start_slot, input.end_slot), (10, 105));
+    assert_eq!(input.epoch, schedule.get_epoch(105));
+    assert_eq!((input.original_bank_hash, input.last_bank_hash), (stub_bank_hash(10), stub_bank_hash(105)));
+    let pubkeys: Vec<[u8; 32]> = input.monitored_accounts_state.iter().map(|account| account.account_pubkey).collect();
+    assert_eq!(pubkeys, [[1; 32], [2; 32], [3; 32]]);
+    for (account, byte) in input.monitored_accounts_state.iter().zip(1u8..) {
+        assert_eq!(account.last_change_slot, 60 + u64::from(byte));
+        assert_eq!((account.data_len, &account.data), (16, &vec![byte; 16]));
+        assert_eq!(account.account_data_hash, sha256_hash(&[byte; 16]));
+    }
+    assert_eq!(rpc.request_count("getEpochSchedule"), 1);
+}
+
+#[tokio::test]
+async fn lagging_node_fails_the_account() {
+    let rpc = MockSolanaRpc::start(100).await.unwrap();
+    rpc.set_account(&pubkey(1), account(b"stub"));
+    let config = InputConfig::new(rpc.url(), &pubkey(1), 10, Some(110));
+    match assemble_input(&config).await {
+        Err(PipelineError::Accounts { total: 1, failures }) => {
+            assert!(matches!(failures.as_slice(), [(account, PipelineError::Rpc(_))] if *account == pubkey(1)))
+        }
+        other => panic!("expected the account to fail, got {:?}", other.map(|input| input.end_slot)),
+    }
+}
+
+#[tokio::test]
+async fn missing_accounts_fail_unless_allowed() {
+    let rpc = MockSolanaRpc::start(100).await.unwrap();
+    rpc.set_account(&pubkey(1), account(b"stub"));
+    let config =
+        InputConfig { accounts: vec![pubkey(1), pubkey(2)], ..InputConfig::new(rpc.url(), &pubkey(1), 10, Some(100)) };
+    match assemble_input(&config).await {
+        Err(PipelineError::Accounts { total: 2, failures }) => {
+            assert!(
+                matches!(failures.as_slice(), [(account, PipelineError::AccountNotFound(_))] if *account == pubkey(2))
+            )
+        }
+        other => panic!("expected the missing account to fail, got {:?}", other.map(|input| input.end_slot)),
+    }
+
+    let input = assemble_input(&InputConfig { allow_missing: true, ..config }).await.unwrap();
+    assert_eq!(missing_accounts(&input), [pubkey(2)]);
+}
+
+#[tokio::test]
+async fn assembled_input_is_executed_proven_and_published() {
+    use_mock_prover();
+    let rpc = MockSolanaRpc::start(100).await.unwrap();
+    rpc.set_account(&pubkey(1), account(b"solana-stub-prover pipeline test"));
+    let input = assemble_input(&InputConfig::new(rpc.url(), &pubkey(1), 10, Some(100))).await.unwrap();
+
+    let execution = execute(&input, RawData::Omit, Consistency::Check).unwrap();
+    assert!(execution.commitments.validations_passed);
+    assert_eq!(execution.commitments.account_data_hash, compute_accounts_hash(&input.monitored_accounts_state));
+
+    let sink = MemorySink::new();
+    for (index, mode) in [ProofMode::Compressed, ProofMode::Groth16].into_iter().enumerate() {
+        let prove_input = input.clone();
+        let artifacts =
+            tokio::task::spawn_blocking(move || prove(&prove_input, mode, RawData::Omit, Consistency::Check))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(artifacts.commitments, execution.commitments);
+        assert_eq!(publish(&artifacts, &sink).await.unwrap(), SinkReceipt::Memory(index));
+    }
+    sink.assert_published_range(10, 100);
+
+    // The data left out of the program input travels in the metadata and matches the commitments
+    for metadata in sink.metadata() {
+        let accounts = account_data::from_metadata(&metadata.unwrap()).unwrap().unwrap();
+        assert_eq!(account_data::verify(&execution.commitments, &accounts).unwrap(), 32);
+    }
+}